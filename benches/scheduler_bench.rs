@@ -6,8 +6,47 @@
 //
 // Copyright (C) 2025 ghostkellz <ckelley@ghostkellz.sh>
 
-use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use std::alloc::{GlobalAlloc, Layout, System};
 use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Real detection code (the scheduler is a binary crate, so pull the module in directly)
+#[allow(dead_code, unused_imports)]
+#[path = "../src/gaming.rs"]
+mod gaming;
+
+#[allow(dead_code, unused_imports)]
+#[path = "../src/gamemode.rs"]
+mod gamemode;
+
+#[allow(dead_code, unused_imports)]
+#[path = "../src/procfs.rs"]
+mod procfs;
+
+/// System allocator that counts allocations, for the scan buffer benchmark
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
 
 /// Benchmark task classification decisions
 ///
@@ -222,6 +261,118 @@ fn bench_ccd_locality(c: &mut Criterion) {
     group.finish();
 }
 
+/// Benchmark the /proc gaming scan
+///
+/// The live variant walks the real /proc and depends on the machine; the
+/// synthetic variant feeds a fixed process mix through the same classifiers
+/// for a stable, comparable number.
+fn bench_proc_scan(c: &mut Criterion) {
+    let mut group = c.benchmark_group("proc_scan");
+
+    let nr_procs = std::fs::read_dir("/proc")
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|e| e.file_name().to_string_lossy().parse::<u32>().is_ok())
+                .count()
+        })
+        .unwrap_or(0);
+    group.throughput(Throughput::Elements(nr_procs as u64));
+    let overrides = gaming::ClassifyOverrides::default();
+    let browsers = gaming::BrowserFilter::default();
+    let compositors = gaming::CompositorFilter::default();
+    group.bench_function("scan_gaming_pids_live", |b| {
+        b.iter(|| {
+            black_box(gaming::scan_gaming_pids(
+                &overrides,
+                &browsers,
+                &compositors,
+            ))
+        })
+    });
+
+    // Typical desktop mix: mostly unclassified, a game, a build, an AI runtime
+    let mix: [(&str, &str); 8] = [
+        ("/usr/bin/bash", "HOME=/home/user\0TERM=xterm-256color\0"),
+        ("/usr/lib/firefox/firefox", "HOME=/home/user\0DISPLAY=:0\0"),
+        ("/usr/bin/kwin_wayland", "XDG_SESSION_TYPE=wayland\0"),
+        ("/usr/lib/systemd/systemd-journald", ""),
+        (
+            "/games/cyberpunk2077/bin/x64/cyberpunk2077.exe",
+            "WINEPREFIX=/pfx\0",
+        ),
+        ("/usr/bin/rustc", "CARGO=/usr/bin/cargo\0"),
+        (
+            "/usr/bin/python3.13",
+            "HOME=/home/user\0CUDA_VISIBLE_DEVICES=0\0",
+        ),
+        ("/usr/bin/pipewire", "HOME=/home/user\0"),
+    ];
+    let procs: Vec<(String, String)> = mix
+        .iter()
+        .cycle()
+        .take(512)
+        .map(|(exe, env)| (exe.to_lowercase(), env.to_string()))
+        .collect();
+
+    group.throughput(Throughput::Elements(procs.len() as u64));
+    group.bench_function("classify_synthetic", |b| {
+        b.iter(|| {
+            let classified = procs
+                .iter()
+                .filter_map(|(exe, env)| {
+                    gaming::classify_exe(exe).or_else(|| gaming::classify_environ(env))
+                })
+                .count();
+            black_box(classified)
+        });
+    });
+
+    group.finish();
+}
+
+/// Benchmark repeated detector scans with and without kept buffers
+///
+/// A fresh detector rebuilds its /proc list, scan results and PID sets every
+/// time; a long-lived one clears and refills them. The allocation counts per
+/// scan are printed before the timings.
+fn bench_repeated_scans(c: &mut Criterion) {
+    let mut group = c.benchmark_group("proc_scan_repeated");
+
+    let allocations = |f: &mut dyn FnMut()| {
+        let before = ALLOCATIONS.load(Ordering::Relaxed);
+        for _ in 0..10 {
+            f();
+        }
+        (ALLOCATIONS.load(Ordering::Relaxed) - before) / 10
+    };
+    let fresh = allocations(&mut || {
+        let mut detector = gaming::GamingDetector::new();
+        black_box(detector.scan_changes().ok());
+    });
+    let mut detector = gaming::GamingDetector::new();
+    let _ = detector.scan_changes();
+    let reused = allocations(&mut || {
+        black_box(detector.scan_changes().ok());
+    });
+    eprintln!(
+        "proc_scan_repeated: {} allocations per scan with a fresh detector, {} reused",
+        fresh, reused
+    );
+
+    group.bench_function("fresh_detector", |b| {
+        b.iter(|| {
+            let mut detector = gaming::GamingDetector::new();
+            black_box(detector.scan_changes().ok())
+        })
+    });
+    group.bench_function("reused_detector", |b| {
+        b.iter(|| black_box(detector.scan_changes().ok()))
+    });
+
+    group.finish();
+}
+
 criterion_group!(
     benches,
     bench_task_classification,
@@ -230,6 +381,8 @@ criterion_group!(
     bench_dsq_dispatch,
     bench_gaming_pid_lookup,
     bench_ccd_locality,
+    bench_proc_scan,
+    bench_repeated_scans,
 );
 
 criterion_main!(benches);
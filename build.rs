@@ -10,6 +10,18 @@ use std::path::PathBuf;
 
 const BPF_SRC: &str = "src/bpf/ghostbrew.bpf.c";
 
+/// __TARGET_ARCH the BPF program is compiled for
+const BPF_TARGET_ARCH: &str = "x86";
+
+/// Version of a package pinned in Cargo.lock
+fn locked_version(lock: &str, package: &str) -> Option<String> {
+    let name = format!("name = \"{}\"", package);
+    let mut lines = lock.lines();
+    lines.find(|line| *line == name)?;
+    let version = lines.next()?.strip_prefix("version = ")?;
+    Some(version.trim_matches('"').to_string())
+}
+
 fn main() {
     let out_dir = PathBuf::from(env::var("OUT_DIR").expect("OUT_DIR not set"));
 
@@ -20,18 +32,31 @@ fn main() {
     println!("cargo:rerun-if-changed=src/bpf/vmlinux.h");
     println!("cargo:rerun-if-changed=src/bpf/scx/"); // Track all sched-ext compat headers
     println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=Cargo.lock");
+
+    // Build details reported by --version-verbose
+    let libbpf_rs = std::fs::read_to_string("Cargo.lock")
+        .ok()
+        .and_then(|lock| locked_version(&lock, "libbpf-rs"))
+        .unwrap_or_else(|| "unknown".to_string());
+    println!(
+        "cargo:rustc-env=GHOSTBREW_BPF_TARGET_ARCH={}",
+        BPF_TARGET_ARCH
+    );
+    println!("cargo:rustc-env=GHOSTBREW_LIBBPF_RS_VERSION={}", libbpf_rs);
 
     // Build the BPF skeleton
+    let target_arch = format!("-D__TARGET_ARCH_{}", BPF_TARGET_ARCH);
     SkeletonBuilder::new()
         .source(BPF_SRC)
         .clang_args([
             "-I",
             "src/bpf", // For vmlinux.h
             "-I",
-            "src/bpf/scx",         // For scx headers (if needed directly)
-            "-D__TARGET_ARCH_x86", // Target architecture
-            "-g",                  // Debug info for BTF
-            "-O2",                 // Optimization
+            "src/bpf/scx",        // For scx headers (if needed directly)
+            target_arch.as_str(), // Target architecture
+            "-g",                 // Debug info for BTF
+            "-O2",                // Optimization
         ])
         .build_and_generate(&skel_path)
         .expect("Failed to build BPF skeleton");
@@ -0,0 +1,267 @@
+// SPDX-License-Identifier: GPL-2.0
+//
+// GhostBrew - Adaptive Latency Response (--adaptive-latency)
+//
+// BPF emits a HighLatency event whenever a gaming task waits more than 1ms
+// for a CPU. With --adaptive-latency, a sustained event rate above the
+// threshold triggers one corrective action: a shorter slice, a priority boost
+// for the game with the most recent events, or V-Cache cache mode. Actions
+// are spaced by a cooldown, and each one only moves one step (a quarter off
+// the slice, one boost level), so the response can't oscillate. Once the rate
+// has stayed below the threshold for a full cooldown, a shortened slice steps
+// back up toward the configured one, again one step per cooldown.
+//
+// Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
+
+use crate::profiles::PRIORITY_BOOST_MAX;
+use crate::vcache::VCacheMode;
+use std::time::{Duration, Instant};
+
+/// HighLatency events per second that trigger a correction
+pub const DEFAULT_RATE: f64 = 10.0;
+
+/// Seconds between corrections
+pub const DEFAULT_COOLDOWN_SECS: u64 = 30;
+
+/// Shortest slice the shorten-slice action goes down to
+pub const MIN_SLICE_NS: u64 = 500_000;
+
+/// Corrective action for --adaptive-latency
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum LatencyAction {
+    /// Cut slice_ns by a quarter, down to MIN_SLICE_NS
+    #[default]
+    ShortenSlice,
+    /// Raise the priority boost of the game with the most recent events
+    BoostGame,
+    /// Switch the V-Cache mode to cache
+    VcacheCache,
+}
+
+/// State the chosen action is applied against
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatencyContext {
+    pub slice_ns: u64,
+    /// TGID with the most recent HighLatency events and its current boost
+    pub top_game: Option<(u32, u32)>,
+    /// Current V-Cache mode, None without a switchable V-Cache
+    pub vcache_mode: Option<VCacheMode>,
+}
+
+/// One corrective step
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Correction {
+    Slice(u64),
+    Boost { pid: u32, boost: u32 },
+    VcacheCache,
+}
+
+/// Step `action` takes from `ctx`, None once it has nothing left to do
+pub fn correction(action: LatencyAction, ctx: &LatencyContext) -> Option<Correction> {
+    match action {
+        LatencyAction::ShortenSlice => {
+            let slice_ns = (ctx.slice_ns * 3 / 4).max(MIN_SLICE_NS);
+            (slice_ns < ctx.slice_ns).then_some(Correction::Slice(slice_ns))
+        }
+        LatencyAction::BoostGame => {
+            let (pid, boost) = ctx.top_game?;
+            (boost < PRIORITY_BOOST_MAX).then_some(Correction::Boost {
+                pid,
+                boost: boost + 1,
+            })
+        }
+        LatencyAction::VcacheCache => {
+            let mode = ctx.vcache_mode?;
+            (mode != VCacheMode::Cache).then_some(Correction::VcacheCache)
+        }
+    }
+}
+
+/// Step from `slice_ns` back toward `configured_slice_ns`, None once there
+///
+/// Only a shortened slice is undone; boosts and the V-Cache mode stay.
+pub fn recovery(
+    action: LatencyAction,
+    slice_ns: u64,
+    configured_slice_ns: u64,
+) -> Option<Correction> {
+    match action {
+        LatencyAction::ShortenSlice if slice_ns < configured_slice_ns => Some(Correction::Slice(
+            (slice_ns * 4 / 3).min(configured_slice_ns),
+        )),
+        _ => None,
+    }
+}
+
+/// HighLatency rate tracker with a cooldown between corrections
+#[derive(Debug)]
+pub struct AdaptiveLatency {
+    pub action: LatencyAction,
+    threshold: f64,
+    cooldown: Duration,
+    last: Option<(Instant, u64)>,
+    /// Last sample above the threshold
+    last_high: Option<Instant>,
+    last_correction: Option<Instant>,
+}
+
+impl AdaptiveLatency {
+    pub fn new(action: LatencyAction, threshold: f64, cooldown: Duration) -> Self {
+        Self {
+            action,
+            threshold,
+            cooldown,
+            last: None,
+            last_high: None,
+            last_correction: None,
+        }
+    }
+
+    /// Feed the cumulative HighLatency count; returns the event rate when it
+    /// is above the threshold and the cooldown has passed
+    pub fn observe(&mut self, total: u64, now: Instant) -> Option<f64> {
+        let (since, count) = self.last.replace((now, total))?;
+        let secs = now.duration_since(since).as_secs_f64();
+        if secs <= 0.0 {
+            return None;
+        }
+        let rate = total.saturating_sub(count) as f64 / secs;
+        if rate <= self.threshold {
+            return None;
+        }
+        self.last_high = Some(now);
+        if self
+            .last_correction
+            .is_some_and(|at| now.duration_since(at) < self.cooldown)
+        {
+            return None;
+        }
+        Some(rate)
+    }
+
+    /// Start the cooldown after a correction or recovery step was applied
+    pub fn corrected(&mut self, now: Instant) {
+        self.last_correction = Some(now);
+    }
+
+    /// Whether a recovery step is due: something was corrected, and neither
+    /// a step nor a sample above the threshold came within the last cooldown
+    pub fn recovery_due(&self, now: Instant) -> bool {
+        let quiet =
+            |at: Option<Instant>| at.is_none_or(|at| now.duration_since(at) >= self.cooldown);
+        self.last_correction.is_some() && quiet(self.last_correction) && quiet(self.last_high)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_threshold_crossing_and_action() {
+        let start = Instant::now();
+        let secs = |s: u64| start + Duration::from_secs(s);
+        let mut adaptive = AdaptiveLatency::new(
+            LatencyAction::ShortenSlice,
+            DEFAULT_RATE,
+            Duration::from_secs(DEFAULT_COOLDOWN_SECS),
+        );
+
+        // The first sample only sets the baseline
+        assert_eq!(adaptive.observe(1000, secs(0)), None);
+        // 50 events in 5s: 10/s is not above the threshold
+        assert_eq!(adaptive.observe(1050, secs(5)), None);
+        // 100 events in 5s: 20/s
+        assert_eq!(adaptive.observe(1150, secs(10)), Some(20.0));
+        adaptive.corrected(secs(10));
+        // Still high, but within the cooldown
+        assert_eq!(adaptive.observe(1250, secs(15)), None);
+        // Cooldown over: 600 events in the 25s since the last sample
+        assert_eq!(adaptive.observe(1850, secs(40)), Some(24.0));
+
+        let ctx = LatencyContext {
+            slice_ns: 3_000_000,
+            top_game: Some((4242, 1)),
+            vcache_mode: Some(VCacheMode::Frequency),
+        };
+        assert_eq!(
+            correction(LatencyAction::ShortenSlice, &ctx),
+            Some(Correction::Slice(2_250_000))
+        );
+        assert_eq!(
+            correction(LatencyAction::BoostGame, &ctx),
+            Some(Correction::Boost {
+                pid: 4242,
+                boost: 2
+            })
+        );
+        assert_eq!(
+            correction(LatencyAction::VcacheCache, &ctx),
+            Some(Correction::VcacheCache)
+        );
+
+        // At the limits: the slice stops at the floor, the boost at its
+        // maximum, and cache mode is already set
+        let done = LatencyContext {
+            slice_ns: 600_000,
+            top_game: Some((4242, PRIORITY_BOOST_MAX)),
+            vcache_mode: Some(VCacheMode::Cache),
+        };
+        assert_eq!(
+            correction(LatencyAction::ShortenSlice, &done),
+            Some(Correction::Slice(MIN_SLICE_NS))
+        );
+        let floor = LatencyContext {
+            slice_ns: MIN_SLICE_NS,
+            ..done
+        };
+        assert_eq!(correction(LatencyAction::ShortenSlice, &floor), None);
+        assert_eq!(correction(LatencyAction::BoostGame, &done), None);
+        assert_eq!(correction(LatencyAction::VcacheCache, &done), None);
+    }
+
+    #[test]
+    fn test_recovery_after_quiet_cooldown() {
+        let start = Instant::now();
+        let secs = |s: u64| start + Duration::from_secs(s);
+        let mut adaptive = AdaptiveLatency::new(
+            LatencyAction::ShortenSlice,
+            DEFAULT_RATE,
+            Duration::from_secs(DEFAULT_COOLDOWN_SECS),
+        );
+
+        // Nothing corrected yet: nothing to recover
+        assert_eq!(adaptive.observe(0, secs(0)), None);
+        assert!(!adaptive.recovery_due(secs(0)));
+
+        assert_eq!(adaptive.observe(100, secs(5)), Some(20.0));
+        adaptive.corrected(secs(5));
+        // Another spike 20s later restarts the quiet period
+        assert_eq!(adaptive.observe(400, secs(25)), None);
+        assert_eq!(adaptive.observe(410, secs(40)), None);
+        assert!(!adaptive.recovery_due(secs(40)));
+        assert_eq!(adaptive.observe(420, secs(55)), None);
+        assert!(adaptive.recovery_due(secs(55)));
+        adaptive.corrected(secs(55));
+        assert!(!adaptive.recovery_due(secs(70)));
+        assert!(adaptive.recovery_due(secs(85)));
+
+        // A third per step, capped at the configured slice
+        assert_eq!(
+            recovery(LatencyAction::ShortenSlice, 1_500_000, 3_000_000),
+            Some(Correction::Slice(2_000_000))
+        );
+        assert_eq!(
+            recovery(LatencyAction::ShortenSlice, 2_250_000, 2_500_000),
+            Some(Correction::Slice(2_500_000))
+        );
+        assert_eq!(
+            recovery(LatencyAction::ShortenSlice, 3_000_000, 3_000_000),
+            None
+        );
+        assert_eq!(
+            recovery(LatencyAction::BoostGame, 1_500_000, 3_000_000),
+            None
+        );
+    }
+}
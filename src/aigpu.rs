@@ -0,0 +1,122 @@
+// SPDX-License-Identifier: GPL-2.0
+//
+// GhostBrew - AI GPU Affinity ([ai_gpus])
+//
+// On multi-GPU AI hosts each inference server should run on the NUMA node of
+// the GPU it drives. [ai_gpus] maps process or container name substrings to a
+// GPU index; threads of matching AI workloads get that GPU's node in
+// numa_hints, in place of the automatic node choice.
+//
+// Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
+
+use crate::gpu::{GpuInfo, GpuVendor};
+use log::{info, warn};
+use std::collections::BTreeMap;
+
+/// Configured name patterns resolved to NUMA nodes
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct AiGpuMap {
+    /// Lowercase pattern and NUMA node, longest pattern first
+    entries: Vec<(String, u32)>,
+}
+
+impl AiGpuMap {
+    /// Resolve each configured GPU index to its NUMA node
+    ///
+    /// NVIDIA GPUs are indexed in PCI bus order, as nvidia-smi numbers them;
+    /// other vendors' GPUs don't take an index. Mappings to a missing GPU, or
+    /// to one without a NUMA node, are skipped.
+    pub fn resolve(map: &BTreeMap<String, u32>, gpus: &[GpuInfo]) -> Self {
+        let mut by_bus: Vec<&GpuInfo> = gpus
+            .iter()
+            .filter(|gpu| gpu.vendor == GpuVendor::Nvidia)
+            .collect();
+        by_bus.sort_by(|a, b| a.pci_address.cmp(&b.pci_address));
+
+        let mut entries = Vec::new();
+        for (pattern, &index) in map {
+            let pattern = pattern.trim().to_lowercase();
+            if pattern.is_empty() {
+                continue;
+            }
+            let Some(gpu) = by_bus.get(index as usize) else {
+                warn!(
+                    "[ai_gpus] '{}': no GPU {} ({} detected), ignored",
+                    pattern,
+                    index,
+                    by_bus.len()
+                );
+                continue;
+            };
+            let Ok(node) = u32::try_from(gpu.numa_node) else {
+                warn!(
+                    "[ai_gpus] '{}': GPU {} ({}) has no NUMA node, ignored",
+                    pattern, index, gpu.pci_address
+                );
+                continue;
+            };
+            info!(
+                "AI GPU affinity: '{}' -> GPU {} ({}), NUMA node {}",
+                pattern, index, gpu.pci_address, node
+            );
+            entries.push((pattern, node));
+        }
+        entries.sort_by_key(|(pattern, _)| std::cmp::Reverse(pattern.len()));
+        Self { entries }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// NUMA node for a workload known by `names` (comm, executable,
+    /// container name or image), if any pattern matches
+    pub fn node_for<'a>(&self, names: impl IntoIterator<Item = &'a str>) -> Option<u32> {
+        let names: Vec<String> = names.into_iter().map(str::to_lowercase).collect();
+        self.entries
+            .iter()
+            .find(|(pattern, _)| names.iter().any(|name| name.contains(pattern)))
+            .map(|&(_, node)| node)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gpu::synthetic_gpu;
+
+    fn gpu(pci_address: &str, numa_node: i32) -> GpuInfo {
+        let mut gpu = synthetic_gpu(GpuVendor::Nvidia, pci_address, 64 << 30, "x16");
+        gpu.numa_node = numa_node;
+        gpu
+    }
+
+    #[test]
+    fn test_ai_gpu_numa_nodes() {
+        // Detection order is not bus order: GPU 0 is 0000:41:00.0 on node 0
+        let gpus = [
+            gpu("0000:c1:00.0", 1),
+            gpu("0000:41:00.0", 0),
+            gpu("0000:e1:00.0", -1),
+        ];
+        let map = BTreeMap::from([
+            ("ollama".to_string(), 0),
+            ("ComfyUI".to_string(), 1),
+            ("vllm".to_string(), 2),
+            ("sdnext".to_string(), 7),
+        ]);
+
+        let resolved = AiGpuMap::resolve(&map, &gpus);
+        assert_eq!(resolved.node_for(["ollama_llama_se"]), Some(0));
+        assert_eq!(
+            resolved.node_for(["python3", "comfyui-worker", "ghcr.io/comfyui:latest"]),
+            Some(1)
+        );
+        // GPU 2 has no NUMA node and GPU 7 doesn't exist
+        assert_eq!(resolved.node_for(["vllm"]), None);
+        assert_eq!(resolved.node_for(["sdnext"]), None);
+        assert_eq!(resolved.node_for(["python3"]), None);
+
+        assert!(AiGpuMap::resolve(&map, &[]).is_empty());
+    }
+}
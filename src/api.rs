@@ -0,0 +1,252 @@
+// SPDX-License-Identifier: GPL-2.0
+//
+// GhostBrew - HTTP/JSON Query Interface (--api-addr)
+//
+// Read-only HTTP GET endpoints for observability tools:
+//   /state          stats snapshot plus the current modes
+//   /topology       detected CPU topology
+//   /pids           detected PIDs per class (same shape as --export-pids)
+//   /events/recent  last events from the ringbuf (?n=N to limit)
+//
+// Served from a background thread; the main loop publishes the state each
+// stats interval.
+//
+// Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
+
+use crate::events::EventHandler;
+use crate::shared::SharedPids;
+use crate::topology::CpuTopology;
+use anyhow::{Context, Result};
+use log::{debug, info};
+use serde::Serialize;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Address used when --api-addr is given without a value
+pub const DEFAULT_API_ADDR: &str = "127.0.0.1:9180";
+
+/// Time a client gets to send its request line
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Body of /state
+#[derive(Serialize)]
+pub struct StateDoc<'a, S: Serialize> {
+    pub version: &'static str,
+    pub gaming_mode: bool,
+    pub work_mode: bool,
+    /// Hardware V-Cache mode (None without the amd_x3d_vcache driver)
+    pub vcache_mode: Option<String>,
+    pub stats: &'a S,
+}
+
+/// Serialize a /state body
+pub fn state_json<S: Serialize>(doc: &StateDoc<'_, S>) -> Result<String> {
+    serde_json::to_string(doc).context("Failed to serialize state")
+}
+
+/// Resolve --api-addr; a bare port or ":port" binds localhost
+pub fn resolve_addr(addr: &str) -> Result<SocketAddr> {
+    let addr = addr.trim();
+    let addr = if addr.parse::<u16>().is_ok() {
+        format!("127.0.0.1:{}", addr)
+    } else if let Some(port) = addr.strip_prefix(':') {
+        format!("127.0.0.1:{}", port)
+    } else {
+        addr.to_string()
+    };
+    addr.to_socket_addrs()
+        .with_context(|| format!("Invalid API address {}", addr))?
+        .next()
+        .with_context(|| format!("No address for {}", addr))
+}
+
+/// Data the server answers from
+struct Shared {
+    state: Mutex<String>,
+    topology: String,
+    events: Arc<EventHandler>,
+    pids: Arc<SharedPids>,
+}
+
+/// HTTP status and JSON body for a GET of `target` (path plus query)
+fn route(shared: &Shared, target: &str) -> (u16, String) {
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    match path.trim_end_matches('/') {
+        "/state" => (
+            200,
+            shared
+                .state
+                .lock()
+                .map(|s| s.clone())
+                .unwrap_or_else(|_| "{}".to_string()),
+        ),
+        "/topology" => (200, shared.topology.clone()),
+        "/pids" => match shared.pids.snapshot().to_json() {
+            Ok(json) => (200, json),
+            Err(e) => (500, format!("{{\"error\":\"{}\"}}", e)),
+        },
+        "/events/recent" => {
+            let limit = query
+                .split('&')
+                .find_map(|kv| kv.strip_prefix("n="))
+                .and_then(|n| n.parse().ok());
+            let events = shared.events.recent_events(limit);
+            match serde_json::to_string(&events) {
+                Ok(json) => (200, json),
+                Err(e) => (500, format!("{{\"error\":\"{}\"}}", e)),
+            }
+        }
+        _ => (404, "{\"error\":\"not found\"}".to_string()),
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Internal Server Error",
+    }
+}
+
+fn handle_client(shared: &Shared, mut stream: TcpStream) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+
+    let mut parts = request_line.split_whitespace();
+    let (status, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some(target)) => route(shared, target),
+        _ => (405, "{\"error\":\"only GET is supported\"}".to_string()),
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason(status),
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+/// Background HTTP server for the query endpoints
+pub struct ApiServer {
+    shared: Arc<Shared>,
+}
+
+impl ApiServer {
+    /// Bind `addr` and start serving
+    pub fn start(
+        addr: &str,
+        topology: &CpuTopology,
+        events: Arc<EventHandler>,
+        pids: Arc<SharedPids>,
+    ) -> Result<Self> {
+        let addr = resolve_addr(addr)?;
+        let listener = TcpListener::bind(addr)
+            .with_context(|| format!("Failed to bind API address {}", addr))?;
+        let shared = Arc::new(Shared {
+            state: Mutex::new("{}".to_string()),
+            topology: serde_json::to_string(topology).context("Failed to serialize topology")?,
+            events,
+            pids,
+        });
+
+        let server = shared.clone();
+        thread::Builder::new()
+            .name("ghostbrew-api".to_string())
+            .spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    if let Err(e) = handle_client(&server, stream) {
+                        debug!("API client: {}", e);
+                    }
+                }
+            })
+            .context("Failed to start API thread")?;
+
+        info!("HTTP API listening on http://{}", addr);
+        Ok(Self { shared })
+    }
+
+    /// Replace the /state body
+    pub fn publish(&self, state: String) {
+        if let Ok(mut current) = self.shared.state.lock() {
+            *current = state;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize)]
+    struct Stats {
+        gaming_tasks: u64,
+        class_dispatches: BTreeMap<&'static str, u64>,
+    }
+
+    #[test]
+    fn test_state_json() {
+        let stats = Stats {
+            gaming_tasks: 3,
+            class_dispatches: BTreeMap::from([("gaming", 120)]),
+        };
+        let json = state_json(&StateDoc {
+            version: "0.0.0",
+            gaming_mode: true,
+            work_mode: false,
+            vcache_mode: Some("cache".to_string()),
+            stats: &stats,
+        })
+        .unwrap();
+
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        for key in [
+            "version",
+            "gaming_mode",
+            "work_mode",
+            "vcache_mode",
+            "stats",
+        ] {
+            assert!(value.get(key).is_some(), "missing {}", key);
+        }
+        assert_eq!(value["stats"]["gaming_tasks"], 3);
+        assert_eq!(value["stats"]["class_dispatches"]["gaming"], 120);
+
+        let shared = Shared {
+            state: Mutex::new(json.clone()),
+            topology: "{}".to_string(),
+            events: Arc::new(EventHandler::new(false, 16)),
+            pids: Arc::new(SharedPids::default()),
+        };
+        shared.pids.publish(crate::export::PidExport {
+            gaming: vec![4242],
+            ..Default::default()
+        });
+        assert_eq!(route(&shared, "/state"), (200, json));
+        assert_eq!(
+            route(&shared, "/events/recent?n=5"),
+            (200, "[]".to_string())
+        );
+        let (status, pids) = route(&shared, "/pids");
+        assert_eq!(status, 200);
+        assert!(pids.contains("\"gaming\":[4242]"));
+        assert_eq!(route(&shared, "/nope").0, 404);
+
+        assert_eq!(
+            resolve_addr("9180").unwrap(),
+            "127.0.0.1:9180".parse().unwrap()
+        );
+        assert_eq!(
+            resolve_addr(":9181").unwrap(),
+            "127.0.0.1:9181".parse().unwrap()
+        );
+    }
+}
@@ -0,0 +1,169 @@
+// SPDX-License-Identifier: GPL-2.0
+//
+// GhostBrew - Load/Attach Diagnostics
+//
+// Captures libbpf output (including the verifier log) and formats a report
+// with the kernel release and sched_ext state when loading or attaching the
+// scheduler fails, so bug reports carry the details that matter.
+//
+// Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
+
+use crate::doctor::{self, SchedExtState};
+use libbpf_rs::PrintLevel;
+use log::{debug, warn};
+use std::collections::VecDeque;
+use std::fs;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Default --attach-timeout in seconds
+pub const DEFAULT_ATTACH_TIMEOUT_SECS: u64 = 10;
+
+/// libbpf lines kept for the failure report (verifier logs run long)
+const LIBBPF_LOG_LINES: usize = 64;
+
+/// How often the sched_ext state is re-read while waiting
+const STATE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Most recent libbpf output lines
+static LIBBPF_LOG: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+/// Route libbpf output through the logger and keep the tail for reports
+fn libbpf_print(level: PrintLevel, msg: String) {
+    let msg = msg.trim_end();
+    match level {
+        PrintLevel::Warn => warn!("libbpf: {}", msg),
+        _ => debug!("libbpf: {}", msg),
+    }
+
+    if let Ok(mut log) = LIBBPF_LOG.lock() {
+        for line in msg.lines() {
+            if log.len() == LIBBPF_LOG_LINES {
+                log.pop_front();
+            }
+            log.push_back(line.to_string());
+        }
+    }
+}
+
+/// Install the libbpf print callback (call before opening the skeleton)
+pub fn capture_libbpf_log() {
+    libbpf_rs::set_print(Some((PrintLevel::Info, libbpf_print)));
+}
+
+/// libbpf lines captured so far
+pub fn libbpf_log() -> Vec<String> {
+    LIBBPF_LOG
+        .lock()
+        .map(|log| log.iter().cloned().collect())
+        .unwrap_or_default()
+}
+
+/// Kernel details included in a failure report
+#[derive(Debug, Clone, Default)]
+pub struct KernelInfo {
+    /// /proc/sys/kernel/osrelease
+    pub release: Option<String>,
+    /// Raw /sys/kernel/sched_ext/state
+    pub sched_ext_state: Option<String>,
+    /// /sys/kernel/sched_ext/root/ops
+    pub attached_ops: Option<String>,
+}
+
+impl KernelInfo {
+    /// Read the running kernel's details
+    pub fn capture() -> Self {
+        let read = |path: &str| {
+            fs::read_to_string(path)
+                .ok()
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+        };
+        Self {
+            release: read("/proc/sys/kernel/osrelease"),
+            sched_ext_state: read("/sys/kernel/sched_ext/state"),
+            attached_ops: doctor::attached_scheduler(),
+        }
+    }
+}
+
+/// Format a load/attach failure report
+///
+/// `stage` names the step that failed ("load", "attach", "enable"). The
+/// libbpf tail is included only when there is one.
+pub fn failure_report(stage: &str, error: &str, kernel: &KernelInfo, libbpf: &[String]) -> String {
+    let unknown = "unknown";
+    let mut report = format!("Failed to {} the GhostBrew scheduler: {}\n", stage, error);
+    report.push_str(&format!(
+        "  Kernel: {}\n",
+        kernel.release.as_deref().unwrap_or(unknown)
+    ));
+    report.push_str(&format!(
+        "  sched_ext state: {}\n",
+        kernel.sched_ext_state.as_deref().unwrap_or("missing")
+    ));
+    report.push_str(&format!(
+        "  Attached scheduler: {}\n",
+        kernel.attached_ops.as_deref().unwrap_or("none")
+    ));
+    if !libbpf.is_empty() {
+        report.push_str("  libbpf output (most recent last):\n");
+        for line in libbpf {
+            report.push_str(&format!("    {}\n", line));
+        }
+    }
+    report.push_str("Include this output in bug reports; see also --doctor");
+    report
+}
+
+/// Poll the sched_ext state until `done` accepts it or the timeout expires
+pub fn wait_for_state(timeout: Duration, done: impl Fn(&SchedExtState) -> bool) -> bool {
+    let start = Instant::now();
+    loop {
+        if done(&doctor::sched_ext_state()) {
+            return true;
+        }
+        if start.elapsed() >= timeout {
+            return false;
+        }
+        thread::sleep(STATE_POLL_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failure_report() {
+        let kernel = KernelInfo {
+            release: Some("6.12.5-cachyos".to_string()),
+            sched_ext_state: Some("disabled".to_string()),
+            attached_ops: None,
+        };
+        let libbpf = vec![
+            "prog 'ghostbrew_enqueue': BPF program load failed: Permission denied".to_string(),
+            "R1 invalid mem access 'scalar'".to_string(),
+        ];
+
+        let report = failure_report("load", "Permission denied", &kernel, &libbpf);
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(
+            lines[0],
+            "Failed to load the GhostBrew scheduler: Permission denied"
+        );
+        assert_eq!(lines[1], "  Kernel: 6.12.5-cachyos");
+        assert_eq!(lines[2], "  sched_ext state: disabled");
+        assert_eq!(lines[3], "  Attached scheduler: none");
+        assert_eq!(lines[4], "  libbpf output (most recent last):");
+        assert_eq!(lines[6], "    R1 invalid mem access 'scalar'");
+        assert!(lines[7].contains("--doctor"));
+
+        // No kernel details and no libbpf output
+        let report = failure_report("enable", "timed out", &KernelInfo::default(), &[]);
+        assert!(report.contains("  Kernel: unknown\n"));
+        assert!(report.contains("  sched_ext state: missing\n"));
+        assert!(!report.contains("libbpf output"));
+    }
+}
@@ -0,0 +1,373 @@
+// SPDX-License-Identifier: GPL-2.0
+//
+// GhostBrew - Benchmark Runs (--benchmark COMMAND)
+//
+// Launches a game or command with MangoHud logging enabled, samples the BPF
+// event counters while it runs, then pairs the resulting frame-time log with
+// the counter deltas over the window the frames were captured in.
+//
+// Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
+
+use crate::mangohud::{FrameTimeStats, MangoHudLogReader};
+use anyhow::{Context, Result, bail};
+use log::{debug, info, warn};
+use std::collections::BTreeMap;
+use std::fs;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Seconds after the first frame before MangoHud starts logging
+const LOG_START_DELAY_SECS: u64 = 1;
+
+/// Scheduler event counters by name (cumulative BSS values)
+pub type Counters = BTreeMap<&'static str, u64>;
+
+/// Counter snapshot taken while the benchmark runs
+#[derive(Debug, Clone)]
+pub struct CounterSample {
+    /// Milliseconds since the command was launched
+    pub offset_ms: u64,
+    pub counters: Counters,
+}
+
+/// A logged frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimedFrame {
+    /// Milliseconds since MangoHud started logging
+    pub elapsed_ms: f64,
+    pub frametime_ms: f64,
+}
+
+/// Frame statistics and scheduler activity over the same window
+#[derive(Debug, Clone, Default)]
+pub struct BenchmarkReport {
+    pub frames: FrameTimeStats,
+    /// Counter increase over the window
+    pub counters: Counters,
+    /// Window in milliseconds since launch
+    pub window_ms: (u64, u64),
+}
+
+/// Build MANGOHUD_CONFIG, keeping any options the user already set
+pub fn mangohud_config(existing: Option<&str>, output_dir: &Path, duration_secs: u64) -> String {
+    let ours = format!(
+        "autostart_log={},log_duration={},output_folder={}",
+        LOG_START_DELAY_SECS,
+        duration_secs,
+        output_dir.display()
+    );
+
+    match existing.map(str::trim) {
+        Some(existing) if !existing.is_empty() => format!("{},{}", existing, ours),
+        _ => ours,
+    }
+}
+
+/// Parse frames from a MangoHud CSV log
+///
+/// MangoHud writes a system info preamble before the frame header, so the
+/// header is the first line with a "frametime" column. The "elapsed" column
+/// is in nanoseconds; logs without it get cumulative frame times instead.
+pub fn parse_timed_frames(content: &str) -> Vec<TimedFrame> {
+    let mut lines = content.lines();
+    let Some(header) = lines.by_ref().find(|line| {
+        line.split(',')
+            .any(|col| col.trim().eq_ignore_ascii_case("frametime"))
+    }) else {
+        return Vec::new();
+    };
+
+    let column = |name: &str| {
+        header
+            .split(',')
+            .position(|col| col.trim().eq_ignore_ascii_case(name))
+    };
+    let Some(frametime_col) = column("frametime") else {
+        return Vec::new();
+    };
+    let elapsed_col = column("elapsed");
+
+    let mut frames = Vec::new();
+    let mut cumulative_ms = 0.0;
+    for line in lines {
+        let cols: Vec<&str> = line.split(',').collect();
+        let Some(frametime_ms) = cols
+            .get(frametime_col)
+            .and_then(|v| v.trim().parse::<f64>().ok())
+        else {
+            continue;
+        };
+
+        cumulative_ms += frametime_ms;
+        let elapsed_ms = elapsed_col
+            .and_then(|col| cols.get(col))
+            .and_then(|v| v.trim().parse::<f64>().ok())
+            .map(|ns| ns / 1_000_000.0)
+            .unwrap_or(cumulative_ms);
+
+        frames.push(TimedFrame {
+            elapsed_ms,
+            frametime_ms,
+        });
+    }
+
+    frames
+}
+
+/// Pair logged frames with counter samples taken over the same window
+///
+/// `log_start_ms` places the log on the launch timeline. The window is where
+/// frames and samples overlap; frames outside it are dropped and counters are
+/// diffed between the last sample at or before the window start and the
+/// first sample at or after its end.
+pub fn correlate(
+    frames: &[TimedFrame],
+    log_start_ms: u64,
+    samples: &[CounterSample],
+) -> BenchmarkReport {
+    let frame_at = |frame: &TimedFrame| log_start_ms as f64 + frame.elapsed_ms;
+
+    let (Some(first_frame), Some(last_frame)) = (frames.first(), frames.last()) else {
+        return BenchmarkReport::default();
+    };
+    let (Some(first_sample), Some(last_sample)) = (samples.first(), samples.last()) else {
+        return BenchmarkReport::default();
+    };
+
+    let start = (frame_at(first_frame) as u64).max(first_sample.offset_ms);
+    let end = (frame_at(last_frame).ceil() as u64).min(last_sample.offset_ms);
+    if start > end {
+        return BenchmarkReport::default();
+    }
+
+    let frame_times: Vec<f64> = frames
+        .iter()
+        .filter(|frame| (start as f64..=end as f64).contains(&frame_at(frame)))
+        .map(|frame| frame.frametime_ms)
+        .collect();
+
+    let before = samples
+        .iter()
+        .rev()
+        .find(|s| s.offset_ms <= start)
+        .unwrap_or(first_sample);
+    let after = samples
+        .iter()
+        .find(|s| s.offset_ms >= end)
+        .unwrap_or(last_sample);
+
+    let counters = after
+        .counters
+        .iter()
+        .map(|(&name, &value)| {
+            let base = before.counters.get(name).copied().unwrap_or(0);
+            (name, value.saturating_sub(base))
+        })
+        .collect();
+
+    BenchmarkReport {
+        frames: MangoHudLogReader::analyze_frame_times(&frame_times),
+        counters,
+        window_ms: (start, end),
+    }
+}
+
+/// User that invoked sudo, so the command doesn't run as root
+fn sudo_user() -> Option<(u32, u32)> {
+    let uid = std::env::var("SUDO_UID").ok()?.parse().ok()?;
+    let gid = std::env::var("SUDO_GID").ok()?.parse().ok()?;
+    Some((uid, gid))
+}
+
+/// A running benchmark command
+pub struct BenchmarkRun {
+    command: String,
+    child: Child,
+    started: Instant,
+    started_wall: SystemTime,
+    duration: Duration,
+    output_dir: PathBuf,
+    samples: Vec<CounterSample>,
+}
+
+impl BenchmarkRun {
+    /// Launch the command with MangoHud logging into a fresh directory
+    pub fn start(command: &str, duration: Duration) -> Result<Self> {
+        let output_dir =
+            std::env::temp_dir().join(format!("ghostbrew-benchmark-{}", std::process::id()));
+        fs::create_dir_all(&output_dir)
+            .with_context(|| format!("Failed to create {}", output_dir.display()))?;
+
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c")
+            .arg(command)
+            .env("MANGOHUD", "1")
+            .env(
+                "MANGOHUD_CONFIG",
+                mangohud_config(
+                    std::env::var("MANGOHUD_CONFIG").ok().as_deref(),
+                    &output_dir,
+                    duration.as_secs(),
+                ),
+            )
+            // Own process group so the game is stopped along with the shell
+            .process_group(0);
+
+        if let Some((uid, gid)) = sudo_user() {
+            std::os::unix::fs::chown(&output_dir, Some(uid), Some(gid))
+                .with_context(|| format!("Failed to chown {}", output_dir.display()))?;
+            cmd.uid(uid).gid(gid);
+            debug!("Benchmark command runs as uid {} gid {}", uid, gid);
+        }
+
+        let child = cmd
+            .spawn()
+            .with_context(|| format!("Failed to launch benchmark command: {}", command))?;
+        info!(
+            "Benchmark started: {} ({}s, pid {})",
+            command,
+            duration.as_secs(),
+            child.id()
+        );
+
+        Ok(Self {
+            command: command.to_string(),
+            child,
+            started: Instant::now(),
+            started_wall: SystemTime::now(),
+            duration,
+            output_dir,
+            samples: Vec::new(),
+        })
+    }
+
+    /// Record a counter snapshot
+    pub fn record(&mut self, counters: Counters) {
+        self.samples.push(CounterSample {
+            offset_ms: self.started.elapsed().as_millis() as u64,
+            counters,
+        });
+    }
+
+    /// Check whether the duration has elapsed or the command exited
+    pub fn is_finished(&mut self) -> bool {
+        self.started.elapsed() >= self.duration || !matches!(self.child.try_wait(), Ok(None))
+    }
+
+    /// Stop the command and correlate its MangoHud log with the samples
+    pub fn finish(mut self) -> Result<BenchmarkReport> {
+        if let Ok(None) = self.child.try_wait() {
+            let pgid = nix::unistd::Pid::from_raw(self.child.id() as i32);
+            if let Err(e) = nix::sys::signal::killpg(pgid, nix::sys::signal::Signal::SIGTERM) {
+                warn!("Failed to stop benchmark command: {}", e);
+            }
+        }
+        let _ = self.child.wait();
+
+        let reader = MangoHudLogReader::with_dir(self.output_dir.clone());
+        let Some(log_path) = reader.find_latest_log() else {
+            bail!(
+                "No MangoHud log written to {} (is MangoHud installed and does the command render with Vulkan/OpenGL?)",
+                self.output_dir.display()
+            );
+        };
+        let content = fs::read_to_string(&log_path)
+            .with_context(|| format!("Failed to read {}", log_path.display()))?;
+        let frames = parse_timed_frames(&content);
+        let Some(last) = frames.last() else {
+            bail!("No frames in MangoHud log {}", log_path.display());
+        };
+
+        // The log is written up to its last frame, so back the start out of its mtime
+        let written_ms = fs::metadata(&log_path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.duration_since(self.started_wall).ok())
+            .map(|d| d.as_millis() as f64)
+            .unwrap_or(self.started.elapsed().as_millis() as f64);
+        let log_start_ms = (written_ms - last.elapsed_ms).max(0.0) as u64;
+
+        info!(
+            "Benchmark finished: {} ({} frames in {})",
+            self.command,
+            frames.len(),
+            log_path.display()
+        );
+        Ok(correlate(&frames, log_start_ms, &self.samples))
+    }
+}
+
+/// Print a benchmark report
+pub fn print_report(report: &BenchmarkReport) {
+    let stats = &report.frames;
+    let (start, end) = report.window_ms;
+
+    println!();
+    println!("GhostBrew benchmark");
+    println!(
+        "  Window:           {:.1}s - {:.1}s ({} frames)",
+        start as f64 / 1000.0,
+        end as f64 / 1000.0,
+        stats.count
+    );
+    println!();
+    println!("  FPS:");
+    println!("    Average:        {:.1}", stats.fps_avg);
+    println!("    1% Low:         {:.1}", stats.fps_1_low);
+    println!("    0.1% Low:       {:.1}", stats.fps_01_low);
+    println!("  Frame time:       {:.2} ms avg", stats.avg_ms);
+    println!("    Jitter:         {:.2} ms (std dev)", stats.std_dev_ms);
+    if let Some(note) = stats.capped_note() {
+        println!("  Note:             {}", note);
+    }
+    println!();
+    println!("  Scheduler events:");
+    for (name, count) in &report.counters {
+        println!("    {:<18}{}", format!("{}:", name), count);
+    }
+    println!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_correlate_window() {
+        // System info preamble, then 60 frames at 10ms with elapsed in ns
+        let mut log = String::from("os,cpu,gpu\nArch,Ryzen 9 9950X3D,RTX 5090\n");
+        log.push_str("fps,frametime,cpu_load,elapsed\n");
+        for i in 0..60u64 {
+            let frametime = if i == 30 { 40.0 } else { 10.0 };
+            log.push_str(&format!("100,{},50,{}\n", frametime, i * 10_000_000));
+        }
+        let frames = parse_timed_frames(&log);
+        assert_eq!(frames.len(), 60);
+        assert_eq!(frames[1].elapsed_ms, 10.0);
+
+        // Log started 1s after launch; counters sampled every 100ms from 0.9s
+        let samples: Vec<CounterSample> = (9..=14u64)
+            .map(|tick| CounterSample {
+                offset_ms: tick * 100,
+                counters: Counters::from([
+                    ("preempt_kicks", tick * 10),
+                    ("vcache_migrations", tick.saturating_sub(11)),
+                ]),
+            })
+            .collect();
+
+        // Frames span 1000..1590ms, samples end at 1400ms
+        let report = correlate(&frames, 1000, &samples);
+        assert_eq!(report.window_ms, (1000, 1400));
+        assert_eq!(report.frames.count, 41);
+        assert!((report.frames.max_ms - 40.0).abs() < f64::EPSILON);
+        assert_eq!(report.counters["preempt_kicks"], 40);
+        assert_eq!(report.counters["vcache_migrations"], 3);
+
+        // No overlap between frames and samples
+        assert_eq!(correlate(&frames, 5000, &samples).frames.count, 0);
+        assert_eq!(correlate(&frames, 1000, &[]).frames.count, 0);
+    }
+}
@@ -3,9 +3,13 @@ use clap::{CommandFactory, Parser, Subcommand};
 use clap_complete::generate;
 use serde::Serialize;
 use std::ffi::OsString;
+use std::fmt;
 use std::fs;
+use std::io::{self, Read};
 use std::path::{Path, PathBuf};
-use std::process::{Command, Stdio};
+use std::process::{Command, Output, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
 use time::OffsetDateTime;
 use time::format_description::well_known::Rfc3339;
 
@@ -13,6 +17,10 @@ const APP_NAME: &str = "ghostbrew";
 const DEFAULT_SCHEDULER_NAME: &str = "scx_ghostbrew";
 const DEFAULT_BENCHMARK_REPORT_BASENAME: &str = "ghostbrew-benchmark";
 const DOCS_REPORT_DIR: &str = "docs/benchmarks";
+/// Upper bound for diagnostic commands (lscpu, uname, ...)
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(10);
+/// `sudo` may sit on a password prompt
+const SUDO_COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
 
 #[derive(Parser, Debug)]
 #[command(name = APP_NAME)]
@@ -80,6 +88,7 @@ struct SupportBundleJson {
     arch: String,
     version: String,
     sched_ext_state: String,
+    sched_ext_ops: String,
     control_file: String,
     cpu_model: String,
     lscpu_summary: String,
@@ -336,6 +345,7 @@ fn collect_support_bundle(timestamp: &str) -> Result<SupportBundleJson> {
         arch: read_command("uname", &["-m"]).unwrap_or_else(|_| "unavailable".to_string()),
         version: env!("CARGO_PKG_VERSION").to_string(),
         sched_ext_state: read_file_trim("/sys/kernel/sched_ext/state"),
+        sched_ext_ops: read_file_trim("/sys/kernel/sched_ext/root/ops"),
         control_file: read_command("ls", &["-l", "/run/ghostbrew/control"])
             .unwrap_or_else(|_| "missing".to_string()),
         cpu_model: first_cpu_model(),
@@ -405,6 +415,7 @@ fn render_support_bundle(bundle: &SupportBundleJson) -> String {
     out.push_str("[ghostbrew]\n");
     out.push_str(&format!("version={}\n", bundle.version));
     out.push_str(&format!("sched_ext_state={}\n", bundle.sched_ext_state));
+    out.push_str(&format!("sched_ext_ops={}\n", bundle.sched_ext_ops));
     out.push_str(&format!("control_file={}\n\n", bundle.control_file));
     out.push_str("[cpu]\n");
     out.push_str(&format!("model={}\n", bundle.cpu_model));
@@ -523,13 +534,91 @@ fn read_file_trim(path: &str) -> String {
 }
 
 fn read_command(command: &str, args: &[&str]) -> Result<String> {
-    let output = Command::new(command).args(args).output()?;
+    let output = run_command(Command::new(command).args(args), COMMAND_TIMEOUT)?;
     if !output.status.success() {
         bail!("command failed: {command}");
     }
     Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
 }
 
+/// Failure of a command run under a deadline
+#[derive(Debug)]
+enum CommandError {
+    Spawn { program: String, source: io::Error },
+    TimedOut { program: String, timeout: Duration },
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandError::Spawn { program, source } => {
+                write!(f, "failed to run {program}: {source}")
+            }
+            CommandError::TimedOut { program, timeout } => {
+                write!(f, "{program} timed out after {}s", timeout.as_secs())
+            }
+        }
+    }
+}
+
+impl std::error::Error for CommandError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CommandError::Spawn { source, .. } => Some(source),
+            CommandError::TimedOut { .. } => None,
+        }
+    }
+}
+
+/// Read a child pipe to EOF on a helper thread
+fn drain_pipe<R: Read + Send + 'static>(pipe: Option<R>) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buf);
+        }
+        buf
+    })
+}
+
+/// Run a command capturing its output, killing it once `timeout` expires
+fn run_command(cmd: &mut Command, timeout: Duration) -> Result<Output, CommandError> {
+    let program = cmd.get_program().to_string_lossy().to_string();
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|source| CommandError::Spawn {
+            program: program.clone(),
+            source,
+        })?;
+
+    // Drain both pipes so a chatty command cannot block on a full pipe
+    let stdout = drain_pipe(child.stdout.take());
+    let stderr = drain_pipe(child.stderr.take());
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(CommandError::TimedOut { program, timeout });
+            }
+            Ok(None) => thread::sleep(Duration::from_millis(20)),
+            Err(source) => return Err(CommandError::Spawn { program, source }),
+        }
+    };
+
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
 fn distribution_name() -> String {
     let content = fs::read_to_string("/etc/os-release").unwrap_or_default();
     content
@@ -649,7 +738,7 @@ fn find_report_files(root: &Path) -> Vec<PathBuf> {
 }
 
 fn collect_recent_dmesg() -> Vec<String> {
-    let Ok(output) = Command::new("sudo").arg("dmesg").output() else {
+    let Ok(output) = run_command(Command::new("sudo").arg("dmesg"), SUDO_COMMAND_TIMEOUT) else {
         return Vec::new();
     };
     if !output.status.success() {
@@ -697,3 +786,25 @@ fn stat_line(stats_block: &str, name: &str) -> Option<String> {
     }
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_command_timeout() {
+        let started = Instant::now();
+        let result = run_command(Command::new("sleep").arg("5"), Duration::from_millis(200));
+        assert!(matches!(result, Err(CommandError::TimedOut { .. })));
+        assert!(started.elapsed() < Duration::from_secs(2));
+
+        let output = run_command(Command::new("echo").arg("ok"), COMMAND_TIMEOUT).unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"ok\n");
+
+        assert!(matches!(
+            run_command(&mut Command::new("/nonexistent/cmd"), COMMAND_TIMEOUT),
+            Err(CommandError::Spawn { .. })
+        ));
+    }
+}
@@ -36,9 +36,10 @@ char _license[] SEC("license") = "GPL";
 #define NSEC_PER_SEC		1000000000ULL
 #define RINGBUF_SIZE		(256 * 1024)  /* 256KB ringbuf */
 
-/* DSQ IDs: 0 = fallback shared, 1-8 = per-CCD */
+/* DSQ IDs: 0 = fallback shared, 1-8 = per-CCD, 9 = AI CPU reservation */
 #define FALLBACK_DSQ		0
 #define CCD_DSQ_BASE		1
+#define AI_RESERVE_DSQ		(CCD_DSQ_BASE + MAX_CCDS)
 
 /* Priority classes for preemption */
 #define PRIO_GAMING		0
@@ -81,6 +82,8 @@ struct percpu_stats {
 	u64 latency_max_ns;    /* Max latency seen on this CPU */
 	u64 idle_time_ns;      /* Time spent idle */
 	u64 busy_time_ns;      /* Time spent busy */
+	u64 kick_window_start; /* Start of the current preempt-kick window */
+	u64 kick_window_count; /* Preempt kicks issued in that window */
 };
 
 /*
@@ -104,7 +107,8 @@ struct runtime_tunables {
 	u8  power_save_mode;       /* v0.3.0: 0=off, 1=balanced, 2=aggressive */
 	u8  tickless_enabled;      /* v0.3.0: Enable tickless mode */
 	u8  gpu_bound_mode;        /* v0.3.0: 0=balanced, 1=gpu_bound, 2=cpu_bound */
-	u8  _pad[3];               /* Padding for alignment */
+	u8  interactive_pct;       /* % of slice below which a task is interactive (0=use burst threshold) */
+	u16 max_preempt_rate;      /* Preempt kicks per CPU per second (0=unlimited) */
 };
 
 /* Default values for runtime tunables */
@@ -121,8 +125,23 @@ const volatile u32 zen_generation = 0;      /* 4 = Zen 4, 5 = Zen 5, 0 = not AMD
 const volatile u32 freq_ccd = 0;            /* Non-V-Cache CCD for freq-bound tasks */
 const volatile bool asymmetric_ccd_boost = false;  /* Zen 5 X3D: CCDs have different boost */
 const volatile u32 vcache_l3_mb = 0;        /* V-Cache L3 size in MB (64/96) */
+
+/* Multi-socket support */
+const volatile u32 nr_sockets = 1;
+const volatile u32 ccd_node[MAX_CCDS] = {};    /* NUMA node of each CCD */
+const volatile u32 ccd_socket[MAX_CCDS] = {};  /* Socket of each CCD */
+const volatile u32 ccd_hot_temp_c = 0;         /* Thermal steering threshold (0 = off) */
 /* Note: work_mode is now in runtime_tunables map for live updates */
 
+/* CPU isolation (isolcpus/nohz_full) */
+const volatile u32 nr_isolated_cpus = 0;
+
+/* Prefer the previous CCX before the rest of its CCD (--ccx-locality) */
+const volatile bool ccx_locality = false;
+
+/* Accumulate per-game stats into pid_stats (--per-pid-stats) */
+const volatile bool per_pid_stats = false;
+
 /*
  * User-exit info for error reporting
  */
@@ -139,9 +158,12 @@ u64 nr_interactive_tasks = 0;
 u64 nr_vcache_migrations = 0;
 u64 nr_ccd_local = 0;
 u64 nr_ccd_cross = 0;
+u64 nr_ccx_local = 0;                /* Idle picks inside the previous CCX */
 u64 nr_smt_idle_picks = 0;
 u64 nr_compaction_overflows = 0;
 u64 nr_preempt_kicks = 0;
+u64 nr_preempt_kicks_throttled = 0;  /* Kicks skipped by max_preempt_rate */
+u64 nr_events_dropped = 0;           /* Events lost to a full ringbuf */
 /* Phase 4a statistics */
 u64 nr_proton_tasks = 0;
 u64 nr_parent_chain_detects = 0;
@@ -153,6 +175,7 @@ u64 nr_gpu_feeder_tasks = 0;
 u64 nr_vm_vcpu_tasks = 0;
 u64 nr_gaming_vm_vcpus = 0;
 u64 nr_dev_vm_vcpus = 0;
+u64 nr_vm_iothread_tasks = 0;      /* QEMU IO threads (interactive class) */
 u64 nr_container_tasks = 0;
 u64 nr_ai_container_tasks = 0;
 /* Phase 4d statistics */
@@ -189,6 +212,12 @@ u64 system_util_pct = 50;          /* System utilization percentage */
 u64 gaming_latency_hist[HIST_BUCKETS] = {};  /* Latency histogram for gaming tasks */
 /* v0.3.0: GPU coordination */
 u64 nr_gpu_feeder_boosts = 0;      /* Times GPU feeder threads were boosted */
+u64 nr_shader_tasks = 0;           /* Shader compile threads classified */
+u64 nr_shader_placements = 0;      /* Shader threads placed off the V-Cache CCD */
+u64 nr_idle_hint_picks = 0;        /* Cross-CCD picks steered by ccd_idle_cpus */
+u64 nr_thermal_steers = 0;         /* Latency picks moved off a hot CCD */
+u64 nr_ai_reserved = 0;            /* AI/batch tasks placed inside their CPU reservation */
+u64 nr_isolated_dispatches = 0;    /* Pinned tasks queued straight to an isolated CPU */
 
 /*
  * v0.3.0: Task flags for special handling
@@ -237,11 +266,12 @@ struct cpu_ctx {
 	u32 ccd;           /* AMD: CCD, Intel: cluster */
 	u32 ccx;           /* AMD: CCX, Intel: module */
 	u32 node;          /* NUMA node */
+	u32 socket;        /* Physical package */
 	s32 smt_sibling;   /* SMT sibling CPU, -1 if none */
 	bool is_vcache;    /* AMD X3D: V-Cache CCD */
 	bool is_pcore;     /* Intel hybrid: Performance core */
 	bool is_turbo;     /* Best-performing core (prefcore or HWP) */
-	u8 _pad[1];
+	bool is_isolated;  /* isolcpus/nohz_full: outside the CCD DSQ pools */
 };
 
 struct {
@@ -251,6 +281,31 @@ struct {
 	__type(value, struct cpu_ctx);
 } cpu_ctxs SEC(".maps");
 
+/*
+ * Topology summary - populated once by userspace, never read by BPF.
+ * Pinned with cpu_ctxs (--export-topology) so external tools can reuse
+ * the CCD/V-Cache layout without re-deriving it.
+ */
+struct topology_summary {
+	u32 nr_cpus;
+	u32 nr_ccds;
+	u32 vcache_ccd;      /* V-Cache CCD, 0xFFFFFFFF if none */
+	u32 freq_ccd;        /* Zen 5 X3D frequency CCD, 0xFFFFFFFF if none */
+	u32 zen_generation;  /* 4 or 5, 0 if not AMD Zen */
+	u8 is_x3d;
+	u8 is_intel_hybrid;
+	u8 smt_enabled;
+	u8 asymmetric_ccd_boost;
+};
+
+struct {
+	__uint(type, BPF_MAP_TYPE_ARRAY);
+	__uint(max_entries, 1);
+	__uint(map_flags, BPF_F_RDONLY_PROG);
+	__type(key, u32);
+	__type(value, struct topology_summary);
+} topology_summary SEC(".maps");
+
 /*
  * Runtime tunables - updatable from userspace without restart
  * Single entry (key=0) containing all runtime-adjustable parameters
@@ -284,9 +339,22 @@ struct {
 	__type(value, u32);
 } prefcore_rankings SEC(".maps");
 
+/*
+ * CPU capacity - populated by userspace from cpuinfo_max_freq
+ * 1024 = highest capacity; the V-Cache die gets a cache weight on top of
+ * its lower clock. Used to compare CCD load by capacity, not task count.
+ */
+struct {
+	__uint(type, BPF_MAP_TYPE_ARRAY);
+	__uint(max_entries, MAX_CPUS);
+	__type(key, u32);
+	__type(value, u32);
+} cpu_capacities SEC(".maps");
+
 /*
  * VM vCPU PIDs - populated by userspace from QEMU/KVM detection
- * Key: PID, Value: workload class (WORKLOAD_VM_DEV, WORKLOAD_VM_GAMING, etc.)
+ * Key: PID, Value: workload class (WORKLOAD_VM_DEV, WORKLOAD_VM_GAMING, etc.;
+ * WORKLOAD_INTERACTIVE for QEMU IO threads)
  */
 struct {
 	__uint(type, BPF_MAP_TYPE_HASH);
@@ -295,6 +363,29 @@ struct {
 	__type(value, u32);
 } vm_vcpu_pids SEC(".maps");
 
+/*
+ * Shader compile threads - populated by userspace from gaming PID threads
+ * Key: TID, Value: workload class (WORKLOAD_SHADER)
+ */
+struct {
+	__uint(type, BPF_MAP_TYPE_HASH);
+	__uint(max_entries, 1024);
+	__type(key, u32);
+	__type(value, u32);
+} shader_threads SEC(".maps");
+
+/*
+ * Per-thread classes inside an interactive process - populated by userspace
+ * (OBS: capture threads interactive, encoder threads batch)
+ * Key: TID, Value: workload class
+ */
+struct {
+	__uint(type, BPF_MAP_TYPE_HASH);
+	__uint(max_entries, 1024);
+	__type(key, u32);
+	__type(value, u32);
+} thread_classes SEC(".maps");
+
 /*
  * Container PIDs - populated by userspace from container runtime detection
  * Key: PID, Value: workload class (WORKLOAD_CONTAINER, WORKLOAD_AI, etc.)
@@ -382,6 +473,40 @@ struct {
 	__type(value, struct ccd_load);
 } ccd_loads SEC(".maps");
 
+/*
+ * Per-CCD DSQ depth - tasks queued on each CCD's DSQ, refreshed by CPU 0's
+ * tick so userspace can see the imbalance behind EVENT_CCD_IMBALANCE
+ * Key: CCD index, Value: queued tasks
+ */
+struct {
+	__uint(type, BPF_MAP_TYPE_ARRAY);
+	__uint(max_entries, MAX_CCDS);
+	__type(key, u32);
+	__type(value, u64);
+} ccd_dsq_depths SEC(".maps");
+
+/*
+ * Approximate idle CPUs per CCD - populated from userspace each scan
+ * (idle jiffies delta from /proc/stat), used to pick the spill CCD
+ */
+struct {
+	__uint(type, BPF_MAP_TYPE_ARRAY);
+	__uint(max_entries, MAX_CCDS);
+	__type(key, u32);
+	__type(value, u32);
+} ccd_idle_cpus SEC(".maps");
+
+/*
+ * Per-CCD temperature in degrees C - populated from userspace each scan
+ * (k10temp Tccd sensors), 0 = unknown
+ */
+struct {
+	__uint(type, BPF_MAP_TYPE_ARRAY);
+	__uint(max_entries, MAX_CCDS);
+	__type(key, u32);
+	__type(value, u32);
+} ccd_temps SEC(".maps");
+
 /*
  * Per-CPU running state for kick preemption
  */
@@ -409,6 +534,142 @@ struct {
 #define WORKLOAD_VM_DEV		5	/* Development VM vCPU */
 #define WORKLOAD_VM_GAMING	6	/* Gaming VM vCPU */
 #define WORKLOAD_CONTAINER	7	/* Container process */
+#define WORKLOAD_SHADER		8	/* Shader/pipeline compile thread of a game */
+#define NR_WORKLOAD_CLASSES	9
+
+/*
+ * Per-class dispatch counts - tasks started running, indexed by workload
+ * class. Per-CPU so the running path never contends; summed by userspace.
+ */
+struct {
+	__uint(type, BPF_MAP_TYPE_PERCPU_ARRAY);
+	__uint(max_entries, NR_WORKLOAD_CLASSES);
+	__type(key, u32);
+	__type(value, u64);
+} class_dispatches SEC(".maps");
+
+static void count_class_dispatch(u32 class)
+{
+	u64 *count;
+
+	if (class >= NR_WORKLOAD_CLASSES)
+		class = WORKLOAD_UNKNOWN;
+	count = bpf_map_lookup_elem(&class_dispatches, &class);
+	if (count)
+		(*count)++;
+}
+
+/*
+ * Per-class CCD placement - populated by userspace from [placement]
+ * Key: workload class, Value: PLACEMENT_* (0 = built-in behaviour)
+ */
+#define PLACEMENT_AUTO		0
+#define PLACEMENT_VCACHE	1	/* Always prefer the V-Cache CCD */
+#define PLACEMENT_FREQUENCY	2	/* Prefer the frequency (non-V-Cache) CCD */
+#define PLACEMENT_ANY		3	/* No CCD preference */
+
+struct {
+	__uint(type, BPF_MAP_TYPE_ARRAY);
+	__uint(max_entries, NR_WORKLOAD_CLASSES);
+	__type(key, u32);
+	__type(value, u8);
+} class_placement SEC(".maps");
+
+static u8 get_class_placement(u32 class)
+{
+	u8 *placement;
+
+	if (class >= NR_WORKLOAD_CLASSES)
+		return PLACEMENT_AUTO;
+	placement = bpf_map_lookup_elem(&class_placement, &class);
+	return placement ? *placement : PLACEMENT_AUTO;
+}
+
+/*
+ * Per-game SMT preference - populated by userspace from profile smt_preference
+ * Key: TGID, Value: SMT_PREF_* (missing = built-in behaviour)
+ */
+#define SMT_PREF_AUTO		0
+#define SMT_PREF_PREFER_IDLE	1	/* Always look for a fully idle core first */
+#define SMT_PREF_ALLOW_SHARED	2	/* Skip the SMT-idle pass; siblings are fine */
+
+struct {
+	__uint(type, BPF_MAP_TYPE_HASH);
+	__uint(max_entries, 256);
+	__type(key, u32);
+	__type(value, u32);
+} smt_prefs SEC(".maps");
+
+static u32 get_smt_pref(struct task_struct *p)
+{
+	u32 tgid = BPF_CORE_READ(p, tgid);
+	u32 *pref = bpf_map_lookup_elem(&smt_prefs, &tgid);
+
+	return pref ? *pref : SMT_PREF_AUTO;
+}
+
+/*
+ * Per-game priority boost - populated by userspace from profile priority_boost
+ * Key: TGID, Value: 1-4, each step halves the game's vtime (missing = none)
+ */
+struct {
+	__uint(type, BPF_MAP_TYPE_HASH);
+	__uint(max_entries, 256);
+	__type(key, u32);
+	__type(value, u32);
+} priority_boosts SEC(".maps");
+
+static u32 get_priority_boost(struct task_struct *p)
+{
+	u32 tgid = BPF_CORE_READ(p, tgid);
+	u32 *boost = bpf_map_lookup_elem(&priority_boosts, &tgid);
+
+	return boost && *boost <= 4 ? *boost : 0;
+}
+
+/*
+ * Per-game scheduler stats - entries created by userspace for gaming PIDs
+ * (--per-pid-stats), summed over all threads of the game
+ * Key: TGID, Value: struct pid_stats
+ */
+struct pid_stats {
+	u64 runtime_ns;
+	u64 migrations;		/* Runs on a different CPU than the last one */
+	u64 preemptions;	/* Stopped while still runnable */
+};
+
+struct {
+	__uint(type, BPF_MAP_TYPE_HASH);
+	__uint(max_entries, 1024);
+	__type(key, u32);
+	__type(value, struct pid_stats);
+} pid_stats SEC(".maps");
+
+static struct pid_stats *get_pid_stats(struct task_struct *p)
+{
+	u32 tgid;
+
+	if (!per_pid_stats)
+		return NULL;
+	tgid = BPF_CORE_READ(p, tgid);
+	return bpf_map_lookup_elem(&pid_stats, &tgid);
+}
+
+/*
+ * Per-class CPU reservation - populated by userspace (--ai-cpu-reserve sets
+ * the AI class, --isolate-ai the AI and batch classes to the same mask)
+ * Key: workload class, Value: CPU bitmask (all zero = unrestricted)
+ */
+struct class_cpumask {
+	u64 bits[MAX_CPUS / 64];
+};
+
+struct {
+	__uint(type, BPF_MAP_TYPE_ARRAY);
+	__uint(max_entries, WORKLOAD_SHADER + 1);
+	__type(key, u32);
+	__type(value, struct class_cpumask);
+} class_cpumasks SEC(".maps");
 
 /*
  * Per-task context for burst tracking and classification
@@ -424,6 +685,7 @@ struct task_ctx {
 	u32 wakeup_freq_hz;		/* Calculated wakeup frequency (capped at 100Hz) */
 	u32 preferred_ccd;
 	u32 last_ccd;
+	s32 last_cpu;			/* CPU of the previous run (per-PID stats) */
 	u32 workload_class;		/* WORKLOAD_* type */
 	bool is_gaming;
 	bool is_interactive;
@@ -490,6 +752,23 @@ static inline bool get_work_mode(void)
 	return rt ? rt->work_mode : false;
 }
 
+/*
+ * Helper: Burst length below which a task counts as interactive.
+ * interactive_pct=0 keeps the legacy burst_threshold rule; otherwise the
+ * cutoff is that percentage of the current time slice.
+ */
+static inline u64 get_interactive_cutoff(void)
+{
+	u32 key = 0;
+	struct runtime_tunables *rt = bpf_map_lookup_elem(&runtime_tunables, &key);
+
+	if (!rt)
+		return default_burst_threshold_ns;
+	if (!rt->interactive_pct)
+		return rt->burst_threshold_ns;
+	return rt->slice_ns * rt->interactive_pct / 100;
+}
+
 /*
  * v0.3.0: Power save mode helper
  * Returns: 0=off, 1=balanced, 2=aggressive
@@ -531,6 +810,32 @@ static inline struct percpu_stats *get_percpu_stats(void)
 	return bpf_map_lookup_elem(&percpu_stats, &key);
 }
 
+/*
+ * Helper: Charge a preempt kick against this CPU's max_preempt_rate budget.
+ * Uses one-second windows; returns false once the window's budget is spent.
+ */
+static inline bool preempt_kick_allowed(struct percpu_stats *pstats)
+{
+	u32 key = 0;
+	struct runtime_tunables *rt = bpf_map_lookup_elem(&runtime_tunables, &key);
+	u64 now;
+
+	if (!rt || !rt->max_preempt_rate || !pstats)
+		return true;
+
+	now = bpf_ktime_get_ns();
+	if (now - pstats->kick_window_start >= NSEC_PER_SEC) {
+		pstats->kick_window_start = now;
+		pstats->kick_window_count = 0;
+	}
+	if (pstats->kick_window_count >= rt->max_preempt_rate) {
+		__sync_fetch_and_add(&nr_preempt_kicks_throttled, 1);
+		return false;
+	}
+	pstats->kick_window_count++;
+	return true;
+}
+
 /*
  * Helper: Get CPU performance state
  */
@@ -550,8 +855,11 @@ static inline int emit_event(u32 event_type, u32 pid, s32 cpu, u32 ccd,
 	struct sched_event *event;
 
 	event = bpf_ringbuf_reserve(&events, sizeof(*event), 0);
-	if (!event)
-		return -1;  /* Ringbuf full, drop event */
+	if (!event) {
+		/* Ringbuf full, drop event */
+		__sync_fetch_and_add(&nr_events_dropped, 1);
+		return -1;
+	}
 
 	event->timestamp_ns = bpf_ktime_get_ns();
 	event->event_type = event_type;
@@ -585,6 +893,87 @@ static u64 ccd_to_dsq(u32 ccd)
 	return CCD_DSQ_BASE + ccd;
 }
 
+/*
+ * Helper: Check if a CPU is set in a class CPU mask
+ */
+static bool cpu_in_class_mask(const struct class_cpumask *mask, u32 cpu)
+{
+	if (cpu >= MAX_CPUS)
+		return false;
+	return mask->bits[cpu / 64] & (1ULL << (cpu % 64));
+}
+
+/*
+ * Helper: Check if a CPU is part of the AI CPU reservation
+ */
+static bool cpu_reserved_for_ai(s32 cpu)
+{
+	u32 key = WORKLOAD_AI;
+	struct class_cpumask *mask = bpf_map_lookup_elem(&class_cpumasks, &key);
+
+	return mask && cpu >= 0 && cpu_in_class_mask(mask, cpu);
+}
+
+/*
+ * Helper: Get the CPU reservation confining an AI or batch task, NULL if none
+ *
+ * Only returned when the task's affinity allows at least one reserved CPU,
+ * so a narrower affinity never strands it on the reserve DSQ.
+ */
+static struct class_cpumask *ai_reservation(struct task_struct *p,
+					    struct task_ctx *tctx)
+{
+	struct class_cpumask *mask;
+	u32 key;
+
+	if (!tctx || (tctx->workload_class != WORKLOAD_AI &&
+		      tctx->workload_class != WORKLOAD_BATCH))
+		return NULL;
+	key = tctx->workload_class;
+
+	mask = bpf_map_lookup_elem(&class_cpumasks, &key);
+	if (!mask)
+		return NULL;
+
+	for (u32 c = 0; c < nr_cpus_possible && c < MAX_CPUS; c++) {
+		if (cpu_in_class_mask(mask, c) && bpf_cpumask_test_cpu(c, p->cpus_ptr))
+			return mask;
+	}
+	return NULL;
+}
+
+static bool cpu_isolated(s32 cpu)
+{
+	struct cpu_ctx *cctx = get_cpu_ctx(cpu);
+
+	return cctx && cctx->is_isolated;
+}
+
+/*
+ * Isolated CPU a task is confined to, or -1 if it may run on a
+ * non-isolated CPU. Only tasks pinned to isolated CPUs are placed there;
+ * they bypass the shared DSQs, which isolated CPUs never drain.
+ */
+static s32 isolated_target_cpu(struct task_struct *p, s32 cpu)
+{
+	s32 target = -1;
+
+	if (!nr_isolated_cpus)
+		return -1;
+	if (!cpu_isolated(cpu) && bpf_cpumask_test_cpu(cpu, p->cpus_ptr))
+		return -1;
+
+	for (u32 c = 0; c < nr_cpus_possible && c < MAX_CPUS; c++) {
+		if (!bpf_cpumask_test_cpu(c, p->cpus_ptr))
+			continue;
+		if (!cpu_isolated(c))
+			return -1;
+		if (target < 0 || c == cpu)
+			target = c;
+	}
+	return target;
+}
+
 /*
  * Helper: Check comm for gaming patterns
  * Returns: 0 = not gaming, 1 = gaming (generic), 2 = proton/wine specifically
@@ -625,6 +1014,25 @@ static int check_gaming_comm(const char *comm)
 	return 0;
 }
 
+/*
+ * Helper: Check if comm indicates a shader/pipeline compile thread
+ * dxvk-shader*, vkd3d-pipe*, fossilize* - CPU-heavy, not latency critical
+ */
+static bool check_shader_comm(const char *comm)
+{
+	if (comm[0] == 'd' && comm[1] == 'x' && comm[2] == 'v' && comm[3] == 'k' &&
+	    comm[4] == '-' && comm[5] == 's' && comm[6] == 'h')
+		return true;  /* dxvk-shader* */
+	if (comm[0] == 'v' && comm[1] == 'k' && comm[2] == 'd' && comm[3] == '3' &&
+	    comm[4] == 'd' && (comm[5] == '-' || comm[5] == '_') && comm[6] == 'p')
+		return true;  /* vkd3d-pipe*, vkd3d_pipe* */
+	if (comm[0] == 'f' && comm[1] == 'o' && comm[2] == 's' && comm[3] == 's' &&
+	    comm[4] == 'i' && comm[5] == 'l')
+		return true;  /* fossilize* */
+
+	return false;
+}
+
 /*
  * Helper: Check if comm indicates a GPU-feeding thread (Vulkan/OpenGL)
  * These threads feed work to the GPU and benefit from low latency
@@ -675,6 +1083,20 @@ static u32 check_userspace_gaming_hint(struct task_struct *p)
 	return 0;
 }
 
+/*
+ * Helper: Check if a thread is a shader compile thread
+ * Matches the comm directly, or the shader_threads map for threads renamed
+ * after they were first classified.
+ */
+static bool is_shader_thread(struct task_struct *p, const char *comm)
+{
+	u32 tid = BPF_CORE_READ(p, pid);
+
+	if (check_shader_comm(comm))
+		return true;
+	return bpf_map_lookup_elem(&shader_threads, &tid) != NULL;
+}
+
 /*
  * Helper: Check if PID is a VM vCPU thread
  * Returns workload class (WORKLOAD_VM_DEV, WORKLOAD_VM_GAMING, etc.) or 0
@@ -684,6 +1106,11 @@ static u32 check_vm_vcpu_hint(struct task_struct *p)
 	u32 pid = BPF_CORE_READ(p, pid);  /* Use thread ID for vCPUs */
 	u32 *class = bpf_map_lookup_elem(&vm_vcpu_pids, &pid);
 	if (class) {
+		/* QEMU IO thread: not a vCPU, just latency-sensitive */
+		if (*class == WORKLOAD_INTERACTIVE) {
+			__sync_fetch_and_add(&nr_vm_iothread_tasks, 1);
+			return *class;
+		}
 		__sync_fetch_and_add(&nr_vm_vcpu_tasks, 1);
 		if (*class == WORKLOAD_VM_GAMING)
 			__sync_fetch_and_add(&nr_gaming_vm_vcpus, 1);
@@ -786,20 +1213,53 @@ static bool is_gaming_task(struct task_struct *p)
 	struct task_ctx *tctx;
 	char comm[TASK_COMM_LEN];
 	int gaming_type;
-	u32 hint;
+	u32 hint, user_hint;
 	bool gpu_feeder = false;
 
 	/* Check cache first */
 	tctx = get_task_ctx(p);
-	if (tctx && tctx->classification_valid)
-		return tctx->is_gaming;
+	if (tctx && tctx->classification_valid) {
+		/* Shader threads inherit the game's comm and get renamed later */
+		u32 tid = BPF_CORE_READ(p, pid);
+
+		/* OBS threads are named after their first classification too */
+		if (!tctx->is_gaming && tctx->workload_class == WORKLOAD_INTERACTIVE) {
+			u32 *thread_class = bpf_map_lookup_elem(&thread_classes, &tid);
+
+			if (thread_class)
+				tctx->workload_class = *thread_class;
+			return false;
+		}
+
+		if (!tctx->is_gaming || !bpf_map_lookup_elem(&shader_threads, &tid))
+			return tctx->is_gaming;
+	}
 
 	/* Level 1: Direct comm check (fastest) */
 	BPF_CORE_READ_STR_INTO(&comm, p, comm);
+
+	/* Shader compile threads: batch work, kept apart from the render thread */
+	if (is_shader_thread(p, comm)) {
+		if (tctx) {
+			tctx->is_gaming = false;
+			tctx->is_proton = false;
+			tctx->is_gpu_feeder = false;
+			tctx->workload_class = WORKLOAD_SHADER;
+			tctx->classification_valid = true;
+			tctx->classification_time = bpf_ktime_get_ns();
+		}
+		__sync_fetch_and_add(&nr_shader_tasks, 1);
+		return false;
+	}
+
 	gaming_type = check_gaming_comm(comm);
+	user_hint = check_userspace_gaming_hint(p);
 
-	/* Check for GPU-feeding threads (Vulkan/OpenGL) */
-	if (check_gpu_feeder_comm(comm)) {
+	/*
+	 * Check for GPU-feeding threads (Vulkan/OpenGL). Browser GPU processes
+	 * run the same threads; userspace marks them interactive.
+	 */
+	if (check_gpu_feeder_comm(comm) && user_hint != WORKLOAD_INTERACTIVE) {
 		gpu_feeder = true;
 		__sync_fetch_and_add(&nr_gpu_feeder_tasks, 1);
 		/* GPU feeders are treated as gaming for scheduling purposes */
@@ -811,8 +1271,7 @@ static bool is_gaming_task(struct task_struct *p)
 		goto found_gaming;
 
 	/* Level 2: Userspace hint check (gaming_pids map) */
-	hint = check_userspace_gaming_hint(p);
-	if (hint == WORKLOAD_GAMING) {
+	if (user_hint == WORKLOAD_GAMING) {
 		__sync_fetch_and_add(&nr_userspace_hint_detects, 1);
 		gaming_type = 1;
 		goto found_gaming;
@@ -847,6 +1306,9 @@ static bool is_gaming_task(struct task_struct *p)
 		if (hint > 0 && hint != WORKLOAD_GAMING) {
 			/* VM vCPU (dev or AI) or cgroup classification */
 			tctx->workload_class = hint;
+		} else if (user_hint > 0) {
+			/* Userspace /proc scan (AI runtimes, batch jobs) */
+			tctx->workload_class = user_hint;
 		} else {
 			u32 container_class = check_container_hint(p);
 			if (container_class > 0) {
@@ -970,7 +1432,7 @@ static s32 pick_idle_cpu_in_ccd(struct task_struct *p, u32 target_ccd, bool pref
 				break;
 
 			cctx = get_cpu_ctx(cpu);
-			if (!cctx || cctx->ccd != target_ccd)
+			if (!cctx || cctx->ccd != target_ccd || cctx->is_isolated)
 				continue;
 
 			if (!bpf_cpumask_test_cpu(cpu, p->cpus_ptr))
@@ -1016,7 +1478,7 @@ static s32 pick_idle_cpu_in_ccd(struct task_struct *p, u32 target_ccd, bool pref
 			break;
 
 		cctx = get_cpu_ctx(cpu);
-		if (!cctx || cctx->ccd != target_ccd)
+		if (!cctx || cctx->ccd != target_ccd || cctx->is_isolated)
 			continue;
 
 		if (!bpf_cpumask_test_cpu(cpu, p->cpus_ptr))
@@ -1062,6 +1524,49 @@ static s32 pick_idle_cpu_in_ccd(struct task_struct *p, u32 target_ccd, bool pref
 	return -1;
 }
 
+/*
+ * Helper: Pick an idle CPU sharing the L3 of the given CCX
+ *
+ * On parts with two CCXs per CCD (Zen 2), staying inside the CCX keeps the
+ * task's L3 contents; the rest of the CCD is a different cache. Prefers
+ * uncontended SMT pairs, then the highest prefcore ranking.
+ */
+static s32 pick_idle_cpu_in_ccx(struct task_struct *p, u32 target_ccd, u32 target_ccx)
+{
+	struct cpu_ctx *cctx;
+	s32 cpu, best_cpu = -1;
+	u32 best_ranking = 0;
+	bool best_contended = true;
+
+	bpf_for(cpu, 0, nr_cpus_possible) {
+		if (cpu >= MAX_CPUS)
+			break;
+
+		cctx = get_cpu_ctx(cpu);
+		if (!cctx || cctx->ccd != target_ccd || cctx->ccx != target_ccx ||
+		    cctx->is_isolated)
+			continue;
+
+		if (!bpf_cpumask_test_cpu(cpu, p->cpus_ptr))
+			continue;
+
+		u32 ranking = get_prefcore_ranking(cpu);
+		bool contended = is_smt_contended(cpu);
+
+		if (best_cpu < 0 || (best_contended && !contended) ||
+		    (contended == best_contended && ranking > best_ranking)) {
+			best_cpu = cpu;
+			best_ranking = ranking;
+			best_contended = contended;
+		}
+	}
+
+	if (best_cpu >= 0 && scx_bpf_test_and_clear_cpu_idle(best_cpu))
+		return best_cpu;
+
+	return -1;
+}
+
 /*
  * Helper: Pick idle P-core for Intel hybrid systems
  *
@@ -1088,7 +1593,7 @@ static s32 pick_idle_pcore(struct task_struct *p, bool prefer_smt_idle)
 				break;
 
 			cctx = get_cpu_ctx(cpu);
-			if (!cctx || !cctx->is_pcore)
+			if (!cctx || !cctx->is_pcore || cctx->is_isolated)
 				continue;
 
 			if (!bpf_cpumask_test_cpu(cpu, p->cpus_ptr))
@@ -1127,7 +1632,7 @@ static s32 pick_idle_pcore(struct task_struct *p, bool prefer_smt_idle)
 			break;
 
 		cctx = get_cpu_ctx(cpu);
-		if (!cctx || !cctx->is_pcore)
+		if (!cctx || !cctx->is_pcore || cctx->is_isolated)
 			continue;
 
 		if (!bpf_cpumask_test_cpu(cpu, p->cpus_ptr))
@@ -1165,7 +1670,7 @@ static s32 pick_idle_ecore(struct task_struct *p)
 			break;
 
 		cctx = get_cpu_ctx(cpu);
-		if (!cctx || cctx->is_pcore)
+		if (!cctx || cctx->is_pcore || cctx->is_isolated)
 			continue;  /* Skip P-cores */
 
 		if (!bpf_cpumask_test_cpu(cpu, p->cpus_ptr))
@@ -1222,6 +1727,128 @@ static struct ccd_load *get_ccd_load(u32 ccd)
 	return bpf_map_lookup_elem(&ccd_loads, &ccd);
 }
 
+/*
+ * Summed CPU capacity per CCD, computed once from cpu_capacities on the
+ * first tick (the map is static after userspace populates it)
+ */
+u64 ccd_capacity[MAX_CCDS] = {};
+bool ccd_capacity_ready = false;
+
+static void init_ccd_capacity(void)
+{
+	struct cpu_ctx *cctx;
+	u32 *cap;
+	s32 cpu;
+
+	bpf_for(cpu, 0, nr_cpus_possible) {
+		if (cpu >= MAX_CPUS)
+			break;
+
+		u32 key = cpu;
+		cctx = get_cpu_ctx(cpu);
+		cap = bpf_map_lookup_elem(&cpu_capacities, &key);
+		if (!cctx || cctx->ccd >= MAX_CCDS)
+			continue;
+		ccd_capacity[cctx->ccd] += (cap && *cap) ? *cap : 1024;
+	}
+
+	ccd_capacity_ready = true;
+}
+
+/*
+ * Migration cost tier from CCD @from to CCD @to:
+ * 0 = same NUMA node, 1 = same socket other node, 2 = cross-socket
+ */
+static u32 ccd_distance(u32 from, u32 to)
+{
+	if (from >= MAX_CCDS || to >= MAX_CCDS)
+		return 0;
+	if (ccd_node[from] == ccd_node[to])
+		return 0;
+	if (ccd_socket[from] == ccd_socket[to])
+		return 1;
+	return 2;
+}
+
+/*
+ * Helper: Get a CCD's temperature in degrees C (0 if unknown)
+ */
+static u32 get_ccd_temp(u32 ccd)
+{
+	u32 key = ccd;
+	u32 *temp = bpf_map_lookup_elem(&ccd_temps, &key);
+	return temp ? *temp : 0;
+}
+
+/*
+ * Pick the CCD for latency-sensitive tasks. On X3D this is always the
+ * V-Cache CCD; when the dies are equivalent (no V-Cache), a CCD at or over
+ * ccd_hot_temp_c hands off to the coolest CCD so throttling doesn't stack
+ * stutter on top of load.
+ */
+static u32 latency_ccd(void)
+{
+	u32 best = vcache_ccd;
+	u32 best_temp;
+
+	if (vcache_l3_mb > 0 || !ccd_hot_temp_c || nr_ccds < 2)
+		return vcache_ccd;
+
+	best_temp = get_ccd_temp(vcache_ccd);
+	if (best_temp < ccd_hot_temp_c)
+		return vcache_ccd;
+
+	for (u32 i = 0; i < nr_ccds && i < MAX_CCDS; i++) {
+		u32 temp = get_ccd_temp(i);
+
+		if (temp && temp < best_temp) {
+			best_temp = temp;
+			best = i;
+		}
+	}
+
+	if (best != vcache_ccd)
+		__sync_fetch_and_add(&nr_thermal_steers, 1);
+	return best;
+}
+
+/*
+ * Find the CCD (other than @exclude) with the most idle CPUs according to
+ * the userspace ccd_idle_cpus hint, preferring CCDs closer to @exclude
+ * (same node, then same socket, then cross-socket). Returns MAX_CCDS if no
+ * CCD reports idle capacity.
+ */
+static u32 most_idle_ccd(u32 exclude)
+{
+	u32 best = MAX_CCDS;
+	u32 best_idle = 0;
+	u32 best_dist = 3;
+
+	for (u32 i = 0; i < nr_ccds && i < MAX_CCDS; i++) {
+		u32 key = i;
+		u32 *idle;
+		u32 dist;
+
+		if (i == exclude)
+			continue;
+		idle = bpf_map_lookup_elem(&ccd_idle_cpus, &key);
+		if (!idle || !*idle)
+			continue;
+
+		dist = ccd_distance(exclude, i);
+		/* Equal candidates: the cooler die wins */
+		if (dist < best_dist || (dist == best_dist && *idle > best_idle) ||
+		    (dist == best_dist && *idle == best_idle && best < MAX_CCDS &&
+		     get_ccd_temp(i) < get_ccd_temp(best))) {
+			best_dist = dist;
+			best_idle = *idle;
+			best = i;
+		}
+	}
+
+	return best;
+}
+
 /*
  * Helper: Find a CPU to kick in target CCD for preemption
  *
@@ -1240,7 +1867,7 @@ static s32 find_kick_victim_in_ccd(u32 target_ccd, u32 min_priority)
 			break;
 
 		cctx = get_cpu_ctx(cpu);
-		if (!cctx || cctx->ccd != target_ccd)
+		if (!cctx || cctx->ccd != target_ccd || cctx->is_isolated)
 			continue;
 
 		u32 key = cpu;
@@ -1282,6 +1909,7 @@ s32 BPF_STRUCT_OPS(ghostbrew_select_cpu, struct task_struct *p,
 	bool vcache_has_gaming = false;
 	s32 cpu = -1;
 	u32 target_ccd;
+	u8 placement;
 
 	tctx = get_task_ctx(p);
 	if (!tctx)
@@ -1294,8 +1922,43 @@ s32 BPF_STRUCT_OPS(ghostbrew_select_cpu, struct task_struct *p,
 	/* Update task classification */
 	bool gmode = get_gaming_mode();
 	tctx->is_gaming = gmode && is_gaming_task(p);
-	tctx->is_interactive = tctx->burst_time < get_burst_threshold();
-	tctx->wants_vcache = tctx->is_gaming || (tctx->is_interactive && gmode);
+	tctx->is_interactive = tctx->burst_time < get_interactive_cutoff() ||
+			       tctx->workload_class == WORKLOAD_INTERACTIVE;
+	tctx->wants_vcache = tctx->is_gaming ||
+			     (tctx->is_interactive && gmode &&
+			      tctx->workload_class != WORKLOAD_SHADER);
+
+	/* Admin placement overrides ([placement]); detected games count as gaming */
+	placement = get_class_placement(tctx->is_gaming ? WORKLOAD_GAMING :
+					tctx->workload_class);
+	if (placement == PLACEMENT_VCACHE)
+		tctx->wants_vcache = true;
+	else if (placement != PLACEMENT_AUTO)
+		tctx->wants_vcache = false;
+
+	/* Per-game SMT preference: AllowShared skips the SMT-idle pass */
+	u32 smt_pref = tctx->is_gaming ? get_smt_pref(p) : SMT_PREF_AUTO;
+	bool smt_idle = smt_pref != SMT_PREF_ALLOW_SHARED;
+
+	/*
+	 * AI CPU reservation (--ai-cpu-reserve): AI tasks only take idle CPUs
+	 * inside the reserved set, otherwise they wait on the reserve DSQ that
+	 * those CPUs drain first. Takes precedence over compaction and NUMA hints.
+	 */
+	struct class_cpumask *reserve = ai_reservation(p, tctx);
+	if (reserve) {
+		for (u32 c = 0; c < nr_cpus_possible && c < MAX_CPUS; c++) {
+			if (!cpu_in_class_mask(reserve, c) ||
+			    !bpf_cpumask_test_cpu(c, p->cpus_ptr))
+				continue;
+			if (scx_bpf_test_and_clear_cpu_idle(c)) {
+				__sync_fetch_and_add(&nr_ai_reserved, 1);
+				cpu = c;
+				goto dispatch;
+			}
+		}
+		return prev_cpu;
+	}
 
 	/*
 	 * v0.3.0: Core Compaction / Power Mode
@@ -1332,6 +1995,8 @@ s32 BPF_STRUCT_OPS(ghostbrew_select_cpu, struct task_struct *p,
 			for (u32 c = 0; c < active_cpus && c < MAX_CPUS; c++) {
 				if (!bpf_cpumask_test_cpu(c, p->cpus_ptr))
 					continue;
+				if (cpu_isolated(c))
+					continue;
 				if (scx_bpf_test_and_clear_cpu_idle(c)) {
 					__sync_fetch_and_add(&nr_power_compactions, 1);
 					scx_bpf_dsq_insert(p, SCX_DSQ_LOCAL, get_slice_ns(), 0);
@@ -1360,7 +2025,7 @@ s32 BPF_STRUCT_OPS(ghostbrew_select_cpu, struct task_struct *p,
 				continue;
 
 			struct cpu_ctx *cctx = get_cpu_ctx(c);
-			if (!cctx || cctx->node != target_node)
+			if (!cctx || cctx->node != target_node || cctx->is_isolated)
 				continue;
 
 			if (scx_bpf_test_and_clear_cpu_idle(c)) {
@@ -1378,7 +2043,7 @@ s32 BPF_STRUCT_OPS(ghostbrew_select_cpu, struct task_struct *p,
 	if (is_intel_hybrid) {
 		/* Gaming/interactive -> prefer P-cores */
 		if (tctx->wants_vcache) {
-			cpu = pick_idle_pcore(p, true);  /* SMT-idle P-core */
+			cpu = pick_idle_pcore(p, smt_idle);  /* SMT-idle P-core */
 			if (cpu >= 0)
 				goto dispatch;
 			cpu = pick_idle_pcore(p, false); /* Any P-core */
@@ -1417,7 +2082,7 @@ s32 BPF_STRUCT_OPS(ghostbrew_select_cpu, struct task_struct *p,
 	bool wmode = get_work_mode();
 	if (wmode && asymmetric_ccd_boost) {
 		/* First try: SMT-idle core in freq CCD */
-		cpu = pick_idle_cpu_in_ccd(p, freq_ccd, true);
+		cpu = pick_idle_cpu_in_ccd(p, freq_ccd, smt_idle);
 		if (cpu >= 0) {
 			__sync_fetch_and_add(&nr_freq_ccd_placements, 1);
 			goto dispatch;
@@ -1431,18 +2096,45 @@ s32 BPF_STRUCT_OPS(ghostbrew_select_cpu, struct task_struct *p,
 		/* Fallback to V-Cache CCD if freq CCD is full */
 	}
 
+	/* Classes placed on the frequency die: same target as shader threads */
+	if (placement == PLACEMENT_FREQUENCY && vcache_l3_mb > 0 && nr_ccds > 1) {
+		target_ccd = asymmetric_ccd_boost ? freq_ccd : (vcache_ccd == 0 ? 1 : 0);
+		cpu = pick_idle_cpu_in_ccd(p, target_ccd, false);
+		if (cpu >= 0) {
+			__sync_fetch_and_add(&nr_freq_ccd_placements, 1);
+			goto dispatch;
+		}
+	}
+
+	/*
+	 * Shader compile threads: keep them off the V-Cache CCD so pipeline
+	 * compilation stalls don't evict the render thread's cache. Prefer the
+	 * freq CCD on Zen 5 X3D, otherwise any non-V-Cache CCD.
+	 */
+	if (tctx->workload_class == WORKLOAD_SHADER && vcache_l3_mb > 0 && nr_ccds > 1) {
+		target_ccd = asymmetric_ccd_boost ? freq_ccd : (vcache_ccd == 0 ? 1 : 0);
+		cpu = pick_idle_cpu_in_ccd(p, target_ccd, false);
+		if (cpu >= 0) {
+			__sync_fetch_and_add(&nr_shader_placements, 1);
+			goto dispatch;
+		}
+	}
+
 	/*
 	 * Gaming/interactive tasks: prefer V-Cache CCD with SMT-idle cores
+	 * (without V-Cache, the cooler CCD once the preferred one runs hot)
 	 */
 	if (tctx->wants_vcache && !wmode) {
+		u32 lat_ccd = latency_ccd();
+
 		/* First try: SMT-idle core in V-Cache CCD */
-		cpu = pick_idle_cpu_in_ccd(p, vcache_ccd, true);
+		cpu = pick_idle_cpu_in_ccd(p, lat_ccd, smt_idle);
 		if (cpu >= 0) {
-			if (prev_cctx->ccd != vcache_ccd) {
+			if (prev_cctx->ccd != lat_ccd) {
 				__sync_fetch_and_add(&nr_vcache_migrations, 1);
 				/* Emit V-Cache migration event */
 				emit_event(EVENT_VCACHE_MIGRATION,
-					   p->pid, cpu, vcache_ccd,
+					   p->pid, cpu, lat_ccd,
 					   prev_cctx->ccd,  /* from CCD */
 					   tctx->is_gaming ? 1 : 0,
 					   NULL);
@@ -1450,13 +2142,13 @@ s32 BPF_STRUCT_OPS(ghostbrew_select_cpu, struct task_struct *p,
 			goto dispatch;
 		}
 		/* Second try: any idle CPU in V-Cache CCD */
-		cpu = pick_idle_cpu_in_ccd(p, vcache_ccd, false);
+		cpu = pick_idle_cpu_in_ccd(p, lat_ccd, false);
 		if (cpu >= 0) {
-			if (prev_cctx->ccd != vcache_ccd) {
+			if (prev_cctx->ccd != lat_ccd) {
 				__sync_fetch_and_add(&nr_vcache_migrations, 1);
 				/* Emit V-Cache migration event */
 				emit_event(EVENT_VCACHE_MIGRATION,
-					   p->pid, cpu, vcache_ccd,
+					   p->pid, cpu, lat_ccd,
 					   prev_cctx->ccd,  /* from CCD */
 					   tctx->is_gaming ? 1 : 0,
 					   NULL);
@@ -1497,17 +2189,36 @@ s32 BPF_STRUCT_OPS(ghostbrew_select_cpu, struct task_struct *p,
 		}
 	}
 
-	/* Try to stay on current CCD */
+	/* Try to stay on current CCX, then current CCD */
 	target_ccd = prev_cctx->ccd;
-	cpu = pick_idle_cpu_in_ccd(p, target_ccd, tctx->wants_vcache);
+	if (ccx_locality) {
+		cpu = pick_idle_cpu_in_ccx(p, target_ccd, prev_cctx->ccx);
+		if (cpu >= 0) {
+			__sync_fetch_and_add(&nr_ccx_local, 1);
+			__sync_fetch_and_add(&nr_ccd_local, 1);
+			goto dispatch;
+		}
+	}
+	cpu = pick_idle_cpu_in_ccd(p, target_ccd,
+				   (tctx->wants_vcache && smt_idle) ||
+				   smt_pref == SMT_PREF_PREFER_IDLE);
 	if (cpu >= 0) {
 		__sync_fetch_and_add(&nr_ccd_local, 1);
 		goto dispatch;
 	}
 
-	/* Try other CCDs */
+	/* Try other CCDs, starting with the one reporting the most idle CPUs */
+	u32 idle_ccd = most_idle_ccd(target_ccd);
+	if (idle_ccd < MAX_CCDS) {
+		cpu = pick_idle_cpu_in_ccd(p, idle_ccd, false);
+		if (cpu >= 0) {
+			__sync_fetch_and_add(&nr_ccd_cross, 1);
+			__sync_fetch_and_add(&nr_idle_hint_picks, 1);
+			goto dispatch;
+		}
+	}
 	for (u32 i = 0; i < nr_ccds && i < MAX_CCDS; i++) {
-		if (i == target_ccd)
+		if (i == target_ccd || i == idle_ccd)
 			continue;
 		cpu = pick_idle_cpu_in_ccd(p, i, false);
 		if (cpu >= 0) {
@@ -1524,6 +2235,10 @@ s32 BPF_STRUCT_OPS(ghostbrew_select_cpu, struct task_struct *p,
 	return cpu;
 
 dispatch:
+	/* Never hand an idle isolated CPU to a task that can run elsewhere */
+	if (!reserve && cpu_isolated(cpu) && isolated_target_cpu(p, cpu) < 0)
+		return prev_cpu;
+
 	/* Direct dispatch to the selected idle CPU */
 	scx_bpf_dsq_insert(p, SCX_DSQ_LOCAL, get_slice_ns(), 0);
 	__sync_fetch_and_add(&nr_direct_dispatched, 1);
@@ -1541,6 +2256,7 @@ void BPF_STRUCT_OPS(ghostbrew_enqueue, struct task_struct *p, u64 enq_flags)
 	struct task_ctx *tctx;
 	struct cpu_ctx *cctx;
 	struct percpu_stats *pstats;
+	struct class_cpumask *reserve;
 	u64 vtime = 0;
 	u64 dsq_id = FALLBACK_DSQ;
 	s32 cpu, kick_cpu;
@@ -1588,12 +2304,23 @@ void BPF_STRUCT_OPS(ghostbrew_enqueue, struct task_struct *p, u64 enq_flags)
 		}
 	}
 
+	/* Tasks pinned to isolated CPUs go straight to that CPU's local DSQ */
+	cpu = isolated_target_cpu(p, scx_bpf_task_cpu(p));
+	if (cpu >= 0) {
+		scx_bpf_dsq_insert(p, SCX_DSQ_LOCAL_ON | cpu, get_slice_ns(), enq_flags);
+		__sync_fetch_and_add(&nr_isolated_dispatches, 1);
+		return;
+	}
+
 	/* Determine target CCD DSQ */
+	reserve = ai_reservation(p, tctx);
 	cpu = scx_bpf_task_cpu(p);
 	cctx = get_cpu_ctx(cpu);
 	if (cctx) {
-		/* Use CCD-specific DSQ */
-		if (tctx && tctx->wants_vcache) {
+		/* Use CCD-specific DSQ (reserved AI tasks: only reserved CPUs drain it) */
+		if (reserve) {
+			dsq_id = AI_RESERVE_DSQ;
+		} else if (tctx && tctx->wants_vcache) {
 			dsq_id = ccd_to_dsq(vcache_ccd);
 		} else {
 			dsq_id = ccd_to_dsq(cctx->ccd);
@@ -1616,7 +2343,7 @@ void BPF_STRUCT_OPS(ghostbrew_enqueue, struct task_struct *p, u64 enq_flags)
 			 */
 			if (tctx->wants_vcache) {
 				kick_cpu = find_kick_victim_in_ccd(vcache_ccd, PRIO_GAMING);
-				if (kick_cpu >= 0) {
+				if (kick_cpu >= 0 && preempt_kick_allowed(pstats)) {
 					scx_bpf_kick_cpu(kick_cpu, SCX_KICK_PREEMPT);
 					__sync_fetch_and_add(&nr_preempt_kicks, 1);
 
@@ -1657,9 +2384,29 @@ void BPF_STRUCT_OPS(ghostbrew_enqueue, struct task_struct *p, u64 enq_flags)
 		if ((tctx->flags & TASK_FLAG_FUTEX_HOLDER) && vtime > 0) {
 			vtime = vtime >> 1;  /* 2x priority boost */
 		}
+
+		/*
+		 * Profile priority_boost: keeps a game's threads ahead when they
+		 * are outside the gaming tier (work mode, helper threads)
+		 */
+		if (vtime > 0)
+			vtime >>= get_priority_boost(p);
 	}
 
 	scx_bpf_dsq_insert_vtime(p, dsq_id, get_slice_ns(), vtime, enq_flags);
+
+	/* Wake an idle reserved CPU to drain the reserve DSQ */
+	if (reserve) {
+		for (u32 c = 0; c < nr_cpus_possible && c < MAX_CPUS; c++) {
+			if (!cpu_in_class_mask(reserve, c) ||
+			    !bpf_cpumask_test_cpu(c, p->cpus_ptr))
+				continue;
+			if (scx_bpf_test_and_clear_cpu_idle(c)) {
+				scx_bpf_kick_cpu(c, SCX_KICK_IDLE);
+				break;
+			}
+		}
+	}
 }
 
 /*
@@ -1684,6 +2431,20 @@ void BPF_STRUCT_OPS(ghostbrew_dispatch, s32 cpu, struct task_struct *prev)
 		return;
 	}
 
+	/* Reserved CPUs serve AI tasks first so the reserve DSQ never stalls */
+	if (cpu_reserved_for_ai(cpu) && scx_bpf_dsq_move_to_local(AI_RESERVE_DSQ)) {
+		__sync_fetch_and_add(&nr_ai_reserved, 1);
+		__sync_fetch_and_add(&nr_dispatched, 1);
+		return;
+	}
+
+	/*
+	 * Isolated CPUs only run what enqueue placed on their local DSQ (or an
+	 * AI reservation that deliberately includes them)
+	 */
+	if (cctx->is_isolated)
+		return;
+
 	/* First try local CCD's DSQ */
 	local_dsq = ccd_to_dsq(cctx->ccd);
 	if (scx_bpf_dsq_move_to_local(local_dsq)) {
@@ -1759,6 +2520,13 @@ void BPF_STRUCT_OPS(ghostbrew_running, struct task_struct *p)
 	pstats = get_percpu_stats();
 
 	if (tctx) {
+		struct pid_stats *ps;
+
+		cpu = scx_bpf_task_cpu(p);
+		if (tctx->last_run_at > 0 && tctx->last_cpu != cpu &&
+		    (ps = get_pid_stats(p)))
+			__sync_fetch_and_add(&ps->migrations, 1);
+		tctx->last_cpu = cpu;
 		tctx->last_run_at = now;
 
 		/* Calculate scheduling latency */
@@ -1815,6 +2583,8 @@ void BPF_STRUCT_OPS(ghostbrew_running, struct task_struct *p)
 			/* Reset enqueue_at to avoid double counting */
 			tctx->enqueue_at = 0;
 		}
+
+		count_class_dispatch(tctx->workload_class);
 	}
 
 	cpu = scx_bpf_task_cpu(p);
@@ -1886,7 +2656,14 @@ void BPF_STRUCT_OPS(ghostbrew_stopping, struct task_struct *p, bool runnable)
 
 	/* Update burst tracking */
 	if (tctx && tctx->last_run_at > 0) {
+		struct pid_stats *ps = get_pid_stats(p);
+
 		delta = now - tctx->last_run_at;
+		if (ps) {
+			__sync_fetch_and_add(&ps->runtime_ns, delta);
+			if (runnable)
+				__sync_fetch_and_add(&ps->preemptions, 1);
+		}
 
 		if (runnable) {
 			/* Still runnable - accumulate burst time */
@@ -1992,28 +2769,54 @@ void BPF_STRUCT_OPS(ghostbrew_tick, struct task_struct *p)
 	}
 	perf_state->last_update_ns = now;
 
+	/* Publish per-CCD DSQ depths (CPU 0 only) */
+	if (cpu == 0) {
+		for (u32 i = 0; i < nr_ccds && i < MAX_CCDS; i++) {
+			u32 key = i;
+			u64 *depth = bpf_map_lookup_elem(&ccd_dsq_depths, &key);
+
+			if (depth)
+				*depth = scx_bpf_dsq_nr_queued(ccd_to_dsq(i));
+		}
+	}
+
 	/* Check for CCD load imbalance (only on CPU 0 to avoid duplication) */
 	if (cpu == 0 && nr_ccds >= 2) {
+		u32 other_ccd = vcache_ccd == 0 ? 1 : 0;
+
+		if (!ccd_capacity_ready)
+			init_ccd_capacity();
+
 		vcache_load = get_ccd_load(vcache_ccd);
-		other_load = get_ccd_load(vcache_ccd == 0 ? 1 : 0);
+		other_load = get_ccd_load(other_ccd);
 
-		if (vcache_load && other_load) {
+		if (vcache_load && other_load && vcache_ccd < MAX_CCDS) {
 			u64 vcache_tasks = vcache_load->nr_tasks;
 			u64 other_tasks = other_load->nr_tasks;
+			u64 vcache_cap = ccd_capacity[vcache_ccd];
+			u64 other_cap = ccd_capacity[other_ccd];
+
+			if (!vcache_cap)
+				vcache_cap = 1;
+			if (!other_cap)
+				other_cap = 1;
 
 			/*
-			 * Emit imbalance event if one CCD has 4x or more tasks
-			 * than the other (significant imbalance)
+			 * Emit imbalance event if one CCD carries 4x or more
+			 * load per unit of capacity than the other
+			 * (cross-multiplied: tasks_a / cap_a > 4 * tasks_b / cap_b)
 			 */
-			if (other_tasks > 0 && vcache_tasks > other_tasks * 4) {
+			if (other_tasks > 0 &&
+			    vcache_tasks * other_cap > other_tasks * vcache_cap * 4) {
 				emit_event(EVENT_CCD_IMBALANCE,
 					   0, 0, vcache_ccd,
 					   vcache_tasks,  /* V-Cache CCD load */
 					   other_tasks,   /* Other CCD load */
 					   NULL);
-			} else if (vcache_tasks > 0 && other_tasks > vcache_tasks * 4) {
+			} else if (vcache_tasks > 0 &&
+				   other_tasks * vcache_cap > vcache_tasks * other_cap * 4) {
 				emit_event(EVENT_CCD_IMBALANCE,
-					   0, 0, vcache_ccd == 0 ? 1 : 0,
+					   0, 0, other_ccd,
 					   other_tasks,   /* Other CCD load */
 					   vcache_tasks,  /* V-Cache CCD load */
 					   NULL);
@@ -2051,8 +2854,8 @@ void BPF_STRUCT_OPS(ghostbrew_dump, struct scx_dump_ctx *dctx)
 		     nr_gaming_tasks, nr_interactive_tasks, nr_proton_tasks);
 	scx_bpf_dump("  vcache_migrations: %llu, preempt_kicks: %llu\n",
 		     nr_vcache_migrations, nr_preempt_kicks);
-	scx_bpf_dump("  ccd_local: %llu, ccd_cross: %llu\n",
-		     nr_ccd_local, nr_ccd_cross);
+	scx_bpf_dump("  ccd_local: %llu (ccx_local: %llu), ccd_cross: %llu\n",
+		     nr_ccd_local, nr_ccx_local, nr_ccd_cross);
 
 	/* Dump latency stats */
 	if (latency_count > 0) {
@@ -2091,6 +2894,11 @@ s32 BPF_STRUCT_OPS_SLEEPABLE(ghostbrew_init)
 			return ret;
 	}
 
+	/* AI CPU reservation DSQ (stays empty unless --ai-cpu-reserve is set) */
+	ret = scx_bpf_create_dsq(AI_RESERVE_DSQ, -1);
+	if (ret)
+		return ret;
+
 	return 0;
 }
 
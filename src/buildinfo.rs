@@ -0,0 +1,62 @@
+// SPDX-License-Identifier: GPL-2.0
+//
+// GhostBrew - Build Information (--version-verbose)
+//
+// What this binary was built against: the BPF target architecture and
+// libbpf-rs version come from build.rs, the kernel requirements from the
+// sched_ext feature checks, the optional features from Cargo.
+//
+// Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
+
+use crate::kernel::{DSQ_INSERT_KERNEL, MIN_KERNEL};
+
+/// __TARGET_ARCH the BPF object was compiled for
+pub const BPF_TARGET_ARCH: &str = env!("GHOSTBREW_BPF_TARGET_ARCH");
+
+/// libbpf-rs version from Cargo.lock
+pub const LIBBPF_RS_VERSION: &str = env!("GHOSTBREW_LIBBPF_RS_VERSION");
+
+/// Optional Cargo features and whether this build has them
+fn features() -> [(&'static str, bool); 1] {
+    [("tui", cfg!(feature = "tui"))]
+}
+
+/// Multi-line build report for --version-verbose
+pub fn verbose() -> String {
+    let features: Vec<String> = features()
+        .iter()
+        .map(|&(name, enabled)| format!("{}{}", if enabled { '+' } else { '-' }, name))
+        .collect();
+
+    format!(
+        "scx_ghostbrew {}\n\
+         BPF target arch: {}\n\
+         libbpf-rs: {}\n\
+         Minimum kernel: {}.{} (sched_ext), {}.{}+ for scx_bpf_dsq_insert*\n\
+         GPU backend: sysfs (no NVML)\n\
+         Features: {}",
+        env!("CARGO_PKG_VERSION"),
+        BPF_TARGET_ARCH,
+        LIBBPF_RS_VERSION,
+        MIN_KERNEL.0,
+        MIN_KERNEL.1,
+        DSQ_INSERT_KERNEL.0,
+        DSQ_INSERT_KERNEL.1,
+        features.join(", ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verbose_version_contents() {
+        let report = verbose();
+        assert!(report.starts_with(&format!("scx_ghostbrew {}\n", env!("CARGO_PKG_VERSION"))));
+        assert!(report.contains(&format!("BPF target arch: {}\n", BPF_TARGET_ARCH)));
+        assert!(!BPF_TARGET_ARCH.is_empty());
+        assert!(report.contains(&format!("libbpf-rs: {}", LIBBPF_RS_VERSION)));
+        assert!(report.contains("tui"));
+    }
+}
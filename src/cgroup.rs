@@ -6,10 +6,13 @@
 //
 // Classifies workloads by cgroup path patterns:
 // - gaming.slice, steam, proton -> GAMING
+// - app-flatpak-<game launcher app ID>-*.scope -> GAMING
 // - docker, libpod, containerd -> CONTAINER
 // - machine-qemu -> VM
 // - system.slice -> BATCH
 
+use crate::gaming::FLATPAK_GAMING_IDS;
+use crate::procfs;
 use anyhow::Result;
 use log::{debug, info};
 use std::collections::HashMap;
@@ -84,7 +87,15 @@ fn get_cgroup_id(path: &Path) -> Option<u64> {
 fn classify_cgroup_path(path: &str) -> u32 {
     let path_lower = path.to_lowercase();
 
-    // Gaming patterns (highest priority for latency)
+    // Gaming patterns (highest priority for latency), at any depth: Flatpak
+    // and systemd-run put the game's scope under app.slice
+    let flatpak_game = path_lower
+        .split('/')
+        .filter_map(flatpak_scope_app_id)
+        .any(|app_id| FLATPAK_GAMING_IDS.iter().any(|id| app_id.starts_with(id)));
+    if flatpak_game {
+        return WORKLOAD_GAMING;
+    }
     for pattern in GAMING_PATTERNS {
         if path_lower.contains(pattern) {
             return WORKLOAD_GAMING;
@@ -123,17 +134,27 @@ fn classify_cgroup_path(path: &str) -> u32 {
     0
 }
 
+/// App ID of a Flatpak app scope ("app-flatpak-<app id>-<instance>.scope")
+fn flatpak_scope_app_id(name: &str) -> Option<&str> {
+    let scope = name.strip_prefix("app-flatpak-")?.strip_suffix(".scope")?;
+    let (app_id, instance) = scope.rsplit_once('-')?;
+    instance
+        .bytes()
+        .all(|b| b.is_ascii_digit())
+        .then_some(app_id)
+}
+
 /// Scan cgroup hierarchy and classify cgroups
 pub fn scan_cgroups() -> Result<Vec<CgroupInfo>> {
     let mut cgroups = Vec::new();
-    let cgroup_root = Path::new("/sys/fs/cgroup");
+    let cgroup_root = procfs::rooted("/sys/fs/cgroup");
 
     if !cgroup_root.exists() {
         debug!("Cgroup filesystem not mounted at /sys/fs/cgroup");
         return Ok(cgroups);
     }
 
-    scan_cgroup_dir(cgroup_root, "", &mut cgroups)?;
+    scan_cgroup_dir(&cgroup_root, "", &mut cgroups)?;
 
     Ok(cgroups)
 }
@@ -351,9 +372,66 @@ mod tests {
         assert_eq!(classify_cgroup_path("user.slice/user-1000.slice"), 0);
     }
 
+    #[test]
+    fn test_nested_flatpak_scope_is_gaming() {
+        let app_slice = "user.slice/user-1000.slice/user@1000.service/app.slice";
+        assert_eq!(
+            classify_cgroup_path(&format!(
+                "{}/app-flatpak-com.valvesoftware.Steam-48213.scope",
+                app_slice
+            )),
+            WORKLOAD_GAMING
+        );
+        // No launcher name in the path, only the app ID
+        assert_eq!(
+            classify_cgroup_path(&format!(
+                "{}/app-flatpak-com.usebottles.bottles-9120.scope",
+                app_slice
+            )),
+            WORKLOAD_GAMING
+        );
+        assert_eq!(
+            flatpak_scope_app_id("app-flatpak-org.libretro.retroarch-77.scope"),
+            Some("org.libretro.retroarch")
+        );
+        assert_eq!(
+            classify_cgroup_path(&format!(
+                "{}/app-flatpak-org.mozilla.firefox-3301.scope",
+                app_slice
+            )),
+            0
+        );
+        assert_eq!(flatpak_scope_app_id("app-flatpak-org.foo.Bar.scope"), None);
+    }
+
     #[test]
     fn test_scan_cgroups() {
         let result = scan_cgroups();
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_scan_cgroups_from_fixture() {
+        let fixture = procfs::Fixture::new("cgroup");
+        fixture
+            .file("/sys/fs/cgroup/cgroup.id", "1\n")
+            .file("/sys/fs/cgroup/gaming.slice/cgroup.id", "4321\n")
+            .file("/sys/fs/cgroup/system.slice/cgroup.id", "77\n")
+            .file("/sys/fs/cgroup/user.slice/cgroup.id", "88\n");
+
+        let mut cgroups: Vec<(String, u64, u32)> = fixture
+            .run(scan_cgroups)
+            .unwrap()
+            .into_iter()
+            .map(|c| (c.path, c.id, c.workload_class))
+            .collect();
+        cgroups.sort();
+        assert_eq!(
+            cgroups,
+            vec![
+                ("gaming.slice".to_string(), 4321, WORKLOAD_GAMING),
+                ("system.slice".to_string(), 77, WORKLOAD_BATCH),
+            ]
+        );
+    }
 }
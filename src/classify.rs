@@ -0,0 +1,171 @@
+// SPDX-License-Identifier: GPL-2.0
+//
+// GhostBrew - Burst-Based Task Classification
+//
+// Userspace mirror of the BPF burst classification. The BPF side decides
+// interactivity in ghostbrew_select_cpu(); this module predicts the same
+// decision so it can be logged and unit-tested without loading the scheduler.
+//
+// Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
+
+/// Burst-based workload class
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorkloadClass {
+    /// Short bursts - woken often, runs briefly (input, audio, compositor)
+    Interactive,
+    /// Between the interactive cutoff and the full threshold
+    Normal,
+    /// Long bursts - consumes the whole threshold or more (compiles, encodes)
+    Batch,
+}
+
+impl std::fmt::Display for WorkloadClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WorkloadClass::Interactive => write!(f, "interactive"),
+            WorkloadClass::Normal => write!(f, "normal"),
+            WorkloadClass::Batch => write!(f, "batch"),
+        }
+    }
+}
+
+/// Number of BPF workload classes (WORKLOAD_UNKNOWN through WORKLOAD_SHADER)
+pub const NR_WORKLOAD_CLASSES: usize = 9;
+
+/// Stats labels of the BPF WORKLOAD_* classes, indexed by class
+const WORKLOAD_CLASS_NAMES: [&str; NR_WORKLOAD_CLASSES] = [
+    "unknown",
+    "gaming",
+    "interactive",
+    "batch",
+    "ai",
+    "vm-dev",
+    "vm-gaming",
+    "container",
+    "shader",
+];
+
+/// Stats label of a BPF workload class
+pub fn workload_class_name(class: u32) -> &'static str {
+    WORKLOAD_CLASS_NAMES
+        .get(class as usize)
+        .copied()
+        .unwrap_or("unknown")
+}
+
+/// BPF workload class for a stats label (e.g. "vm-gaming")
+pub fn workload_class_id(name: &str) -> Option<u32> {
+    WORKLOAD_CLASS_NAMES
+        .iter()
+        .position(|&label| label == name)
+        .map(|class| class as u32)
+}
+
+/// Convert an interactive ratio (0.0-1.0) to the percentage stored in runtime_tunables
+pub fn ratio_to_pct(ratio: f64) -> u8 {
+    (ratio.clamp(0.0, 1.0) * 100.0).round() as u8
+}
+
+/// Burst length below which a task is considered interactive
+///
+/// Uses the same integer math as the BPF side (`threshold * pct / 100`) so
+/// predictions match the scheduler exactly.
+pub fn interactive_cutoff(threshold: u64, ratio: f64) -> u64 {
+    threshold.saturating_mul(ratio_to_pct(ratio) as u64) / 100
+}
+
+/// Classify a task by its average burst length
+///
+/// `threshold` is the reference length the ratio applies to: the time slice
+/// when `--interactive-ratio` is set, otherwise the burst threshold with a
+/// ratio of 1.0 (the legacy `burst < burst_threshold` rule).
+pub fn classify_burst(burst_ns: u64, threshold: u64, ratio: f64) -> WorkloadClass {
+    if burst_ns < interactive_cutoff(threshold, ratio) {
+        WorkloadClass::Interactive
+    } else if burst_ns < threshold {
+        WorkloadClass::Normal
+    } else {
+        WorkloadClass::Batch
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cgroup, gaming};
+
+    #[test]
+    fn test_workload_class_names() {
+        // Every WORKLOAD_* constant defined on the Rust side has its label
+        let classes = [
+            (gaming::WORKLOAD_GAMING, "gaming"),
+            (gaming::WORKLOAD_INTERACTIVE, "interactive"),
+            (gaming::WORKLOAD_BATCH, "batch"),
+            (gaming::WORKLOAD_AI, "ai"),
+            (crate::WORKLOAD_VM_DEV, "vm-dev"),
+            (crate::WORKLOAD_VM_GAMING, "vm-gaming"),
+            (cgroup::WORKLOAD_CONTAINER, "container"),
+            (crate::WORKLOAD_SHADER, "shader"),
+        ];
+        for (class, name) in classes {
+            assert_eq!(workload_class_name(class), name);
+        }
+        assert_eq!(cgroup::WORKLOAD_INTERACTIVE, gaming::WORKLOAD_INTERACTIVE);
+        assert_eq!(crate::WORKLOAD_AI, gaming::WORKLOAD_AI);
+
+        // Table ends at the highest class, anything past it is unknown
+        assert_eq!(crate::WORKLOAD_SHADER as usize, NR_WORKLOAD_CLASSES - 1);
+        assert_eq!(workload_class_name(0), "unknown");
+        assert_eq!(workload_class_name(42), "unknown");
+    }
+
+    // Burst categories from benches/scheduler_bench.rs
+    const HIGHLY_INTERACTIVE: u64 = 100_000;
+    const INTERACTIVE: u64 = 500_000;
+    const MEDIUM: u64 = 2_500_000;
+    const BATCH: u64 = 10_000_000;
+    const HEAVY_BATCH: u64 = 50_000_000;
+
+    #[test]
+    fn test_classify_burst_default_ratio() {
+        let threshold = 2_000_000;
+        assert_eq!(
+            classify_burst(HIGHLY_INTERACTIVE, threshold, 1.0),
+            WorkloadClass::Interactive
+        );
+        assert_eq!(
+            classify_burst(INTERACTIVE, threshold, 1.0),
+            WorkloadClass::Interactive
+        );
+        assert_eq!(classify_burst(MEDIUM, threshold, 1.0), WorkloadClass::Batch);
+        assert_eq!(classify_burst(BATCH, threshold, 1.0), WorkloadClass::Batch);
+        assert_eq!(
+            classify_burst(HEAVY_BATCH, threshold, 1.0),
+            WorkloadClass::Batch
+        );
+    }
+
+    #[test]
+    fn test_classify_burst_slice_ratio() {
+        // 3ms slice, interactive below 25% of it (750us)
+        let slice = 3_000_000;
+        assert_eq!(
+            classify_burst(HIGHLY_INTERACTIVE, slice, 0.25),
+            WorkloadClass::Interactive
+        );
+        assert_eq!(
+            classify_burst(INTERACTIVE, slice, 0.25),
+            WorkloadClass::Interactive
+        );
+        assert_eq!(classify_burst(MEDIUM, slice, 0.25), WorkloadClass::Normal);
+        assert_eq!(classify_burst(BATCH, slice, 0.25), WorkloadClass::Batch);
+    }
+
+    #[test]
+    fn test_interactive_cutoff_clamps_ratio() {
+        assert_eq!(interactive_cutoff(3_000_000, 0.5), 1_500_000);
+        assert_eq!(interactive_cutoff(3_000_000, 2.0), 3_000_000);
+        assert_eq!(interactive_cutoff(3_000_000, -1.0), 0);
+        assert_eq!(ratio_to_pct(0.333), 33);
+    }
+}
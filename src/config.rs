@@ -6,9 +6,12 @@
 //
 // Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
 
-use anyhow::{Context, Result};
-use log::{debug, info};
+use crate::classify::{self, NR_WORKLOAD_CLASSES};
+use crate::container::ContainerWorkloadType;
+use anyhow::{Context, Result, bail};
+use log::{debug, info, warn};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::PathBuf;
 
@@ -27,9 +30,61 @@ pub struct GhostBrewConfig {
     #[serde(default)]
     pub intel: IntelConfig,
 
+    /// Gaming detection settings
+    #[serde(default)]
+    pub gaming: GamingConfig,
+
     /// Path to game profiles directory
     #[serde(default)]
     pub profiles_dir: Option<PathBuf>,
+
+    /// CCD preference per workload class ("gaming" = "vcache", ...);
+    /// classes left out keep the built-in placement
+    #[serde(default)]
+    pub placement: BTreeMap<String, CcdPlacement>,
+
+    /// Workload type per container name/image substring
+    /// ("myinference" = "ai"); wins over the process heuristics
+    #[serde(default)]
+    pub containers: BTreeMap<String, ContainerWorkloadType>,
+
+    /// GPU index per AI process/container name substring ("ollama" = 0);
+    /// matching threads are kept on that GPU's NUMA node
+    #[serde(default)]
+    pub ai_gpus: BTreeMap<String, u32>,
+}
+
+/// CCD preference of a workload class
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CcdPlacement {
+    /// Prefer the V-Cache CCD
+    Vcache,
+    /// Prefer the frequency (non-V-Cache) CCD
+    Frequency,
+    /// No CCD preference
+    Any,
+}
+
+impl std::fmt::Display for CcdPlacement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CcdPlacement::Vcache => write!(f, "vcache"),
+            CcdPlacement::Frequency => write!(f, "frequency"),
+            CcdPlacement::Any => write!(f, "any"),
+        }
+    }
+}
+
+impl CcdPlacement {
+    /// PLACEMENT_* value in the BPF class_placement map (0 = built-in)
+    pub fn to_bpf(self) -> u8 {
+        match self {
+            CcdPlacement::Vcache => 1,
+            CcdPlacement::Frequency => 2,
+            CcdPlacement::Any => 3,
+        }
+    }
 }
 
 /// Default scheduling parameters
@@ -130,6 +185,49 @@ impl Default for IntelConfig {
     }
 }
 
+/// Gaming detection configuration
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GamingConfig {
+    /// Comms/exe names always classified as gaming
+    #[serde(default)]
+    pub allow: Vec<String>,
+
+    /// Comms/exe names never classified as gaming
+    #[serde(default)]
+    pub deny: Vec<String>,
+
+    /// Extra browser comms/exe names (added to chrome, chromium, firefox,
+    /// brave, electron); their GPU threads are interactive, not gaming
+    #[serde(default)]
+    pub browsers: Vec<String>,
+
+    /// Extra compositor comms/exe names (added to kwin_wayland, gnome-shell,
+    /// hyprland, sway, picom, xorg); classified as interactive
+    #[serde(default)]
+    pub compositors: Vec<String>,
+
+    /// Age in milliseconds a process needs before it is classified as
+    /// gaming, so short-lived launcher helpers don't churn the gaming map
+    #[serde(default = "default_gaming_min_age_ms")]
+    pub min_age_ms: u64,
+}
+
+impl Default for GamingConfig {
+    fn default() -> Self {
+        Self {
+            allow: Vec::new(),
+            deny: Vec::new(),
+            browsers: Vec::new(),
+            compositors: Vec::new(),
+            min_age_ms: default_gaming_min_age_ms(),
+        }
+    }
+}
+
+fn default_gaming_min_age_ms() -> u64 {
+    2000
+}
+
 fn default_true() -> bool {
     true
 }
@@ -138,6 +236,66 @@ fn default_ecore_offload() -> String {
     "conservative".to_string()
 }
 
+/// Keys each config section accepts; [placement], [containers] and [ai_gpus]
+/// are keyed by class and workload name instead
+const KNOWN_KEYS: &[(&str, &[&str])] = &[
+    (
+        "defaults",
+        &[
+            "burst_threshold_ns",
+            "slice_ns",
+            "gaming_mode",
+            "stats_interval",
+        ],
+    ),
+    (
+        "amd",
+        &["prefer_vcache", "prefcore_enabled", "vcache_switching"],
+    ),
+    ("intel", &["prefer_pcores", "ecore_offload"]),
+    (
+        "gaming",
+        &["allow", "deny", "browsers", "compositors", "min_age_ms"],
+    ),
+    ("profiles_dir", &[]),
+    ("placement", &[]),
+    ("containers", &[]),
+    ("ai_gpus", &[]),
+];
+
+/// Keys and class names in a config file that would be silently ignored
+///
+/// serde skips unknown keys, so a typo like `[gamming]` parses fine and does
+/// nothing. Unparsable TOML is left to the real parse to report.
+pub fn unknown_keys(content: &str) -> Vec<String> {
+    let Ok(table) = content.parse::<toml::Table>() else {
+        return Vec::new();
+    };
+
+    let mut problems = Vec::new();
+    for (section, value) in &table {
+        let Some((_, keys)) = KNOWN_KEYS.iter().find(|(name, _)| name == section) else {
+            problems.push(format!("unknown key '{}'", section));
+            continue;
+        };
+        let Some(entries) = value.as_table() else {
+            continue;
+        };
+        for key in entries.keys() {
+            if section == "placement" {
+                if classify::workload_class_id(key).is_none() {
+                    problems.push(format!("unknown workload class 'placement.{}'", key));
+                }
+            } else if !matches!(section.as_str(), "containers" | "ai_gpus")
+                && !keys.contains(&key.as_str())
+            {
+                problems.push(format!("unknown key '{}.{}'", section, key));
+            }
+        }
+    }
+    problems
+}
+
 /// Standard config file locations (in priority order)
 const CONFIG_PATHS: &[&str] = &[
     "/etc/ghostbrew/config.toml",
@@ -147,17 +305,21 @@ const CONFIG_PATHS: &[&str] = &[
 impl GhostBrewConfig {
     /// Load configuration from standard paths
     pub fn load() -> Result<Self> {
-        for path in CONFIG_PATHS {
-            let expanded = shellexpand::tilde(path);
-            let path = PathBuf::from(expanded.as_ref());
-
-            if path.exists() {
-                return Self::load_from_path(&path);
+        match Self::find_path() {
+            Some(path) => Self::load_from_path(&path),
+            None => {
+                debug!("No config file found, using defaults");
+                Ok(Self::default())
             }
         }
+    }
 
-        debug!("No config file found, using defaults");
-        Ok(Self::default())
+    /// First existing config file in the standard locations
+    pub fn find_path() -> Option<PathBuf> {
+        CONFIG_PATHS
+            .iter()
+            .map(|path| PathBuf::from(shellexpand::tilde(path).as_ref()))
+            .find(|path| path.exists())
     }
 
     /// Load configuration from a specific path
@@ -168,20 +330,45 @@ impl GhostBrewConfig {
         let config: GhostBrewConfig = toml::from_str(&content)
             .with_context(|| format!("Failed to parse config file: {:?}", path))?;
 
+        for problem in unknown_keys(&content) {
+            warn!("Config {:?}: {}", path, problem);
+        }
         info!("Loaded config from {:?}", path);
         debug!("Config: {:?}", config);
 
         Ok(config)
     }
 
-    /// Check if V-Cache auto-switching is enabled
-    pub fn is_vcache_auto_switching(&self) -> bool {
-        self.amd.vcache_switching.to_lowercase() == "automatic"
+    /// Parse a config file and list its unknown keys (--check-config)
+    ///
+    /// Fails when the file can't be read or parsed.
+    pub fn check_path(path: &PathBuf) -> Result<Vec<String>> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file: {:?}", path))?;
+        toml::from_str::<GhostBrewConfig>(&content)
+            .with_context(|| format!("Failed to parse config file: {:?}", path))?;
+        Ok(unknown_keys(&content))
     }
 
-    /// Check if we should follow ghost-vcache mode changes
-    pub fn should_follow_ghost_vcache(&self) -> bool {
-        self.amd.vcache_switching.to_lowercase() == "follow_ghost_vcache"
+    /// class_placement map values indexed by workload class
+    ///
+    /// Fails on a class name the scheduler doesn't know, listing the valid ones.
+    pub fn class_placements(&self) -> Result<[u8; NR_WORKLOAD_CLASSES]> {
+        let mut values = [0u8; NR_WORKLOAD_CLASSES];
+        for (name, placement) in &self.placement {
+            let Some(class) = classify::workload_class_id(name) else {
+                let known: Vec<&str> = (0..NR_WORKLOAD_CLASSES as u32)
+                    .map(classify::workload_class_name)
+                    .collect();
+                bail!(
+                    "unknown workload class '{}' (known: {})",
+                    name,
+                    known.join(", ")
+                );
+            };
+            values[class as usize] = placement.to_bpf();
+        }
+        Ok(values)
     }
 }
 
@@ -208,10 +395,98 @@ gaming_mode = false
 
 [intel]
 ecore_offload = "aggressive"
+
+[gaming]
+deny = ["winword.exe"]
 "#;
         let config: GhostBrewConfig = toml::from_str(toml_str).unwrap();
         assert_eq!(config.defaults.burst_threshold_ns, 1500000);
         assert!(!config.defaults.gaming_mode);
         assert_eq!(config.intel.ecore_offload, "aggressive");
+        assert_eq!(config.gaming.deny, vec!["winword.exe"]);
+        assert!(config.gaming.allow.is_empty());
+    }
+
+    #[test]
+    fn test_class_placements() {
+        // Nothing configured: every class keeps the built-in placement
+        assert_eq!(
+            GhostBrewConfig::default().class_placements().unwrap(),
+            [0; NR_WORKLOAD_CLASSES]
+        );
+
+        let config: GhostBrewConfig = toml::from_str(
+            r#"
+[placement]
+gaming = "vcache"
+interactive = "vcache"
+ai = "frequency"
+container = "any"
+"#,
+        )
+        .unwrap();
+        // unknown, gaming, interactive, batch, ai, vm-dev, vm-gaming, container, shader
+        assert_eq!(
+            config.class_placements().unwrap(),
+            [0, 1, 1, 0, 2, 0, 0, 3, 0]
+        );
+
+        let config: GhostBrewConfig = toml::from_str(
+            "[placement]
+render = \"vcache\"",
+        )
+        .unwrap();
+        let err = config.class_placements().unwrap_err().to_string();
+        assert!(err.contains("unknown workload class 'render'"));
+        assert!(err.contains("vm-gaming"));
+    }
+
+    #[test]
+    fn test_unknown_keys() {
+        let valid = r#"
+profiles_dir = "/etc/ghostbrew/profiles"
+
+[defaults]
+slice_ns = 2000000
+
+[gaming]
+allow = ["factorio"]
+
+[placement]
+vm-gaming = "vcache"
+
+[containers]
+myinference = "ai"
+
+[ai_gpus]
+ollama = 0
+"#;
+        assert!(unknown_keys(valid).is_empty());
+
+        let typos = r#"
+[gamming]
+allow = ["factorio"]
+
+[amd]
+prefer_vcach = true
+
+[placement]
+gaming = "vcache"
+render = "frequency"
+"#;
+        // Typos still parse; they just do nothing
+        assert!(toml::from_str::<GhostBrewConfig>(typos).is_ok());
+        assert_eq!(
+            unknown_keys(typos),
+            vec![
+                "unknown key 'amd.prefer_vcach'",
+                "unknown key 'gamming'",
+                "unknown workload class 'placement.render'",
+            ]
+        );
+
+        // Every key the config itself writes out is known
+        let defaults = toml::to_string(&GhostBrewConfig::default()).unwrap();
+        assert!(unknown_keys(&defaults).is_empty());
     }
 }
@@ -10,18 +10,23 @@
 // - NVIDIA Container Runtime/Toolkit
 // - AI workloads (Ollama, PyTorch, TensorFlow)
 
+use crate::procfs;
 use anyhow::Result;
 use log::{debug, info};
-use std::collections::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
 use std::path::Path;
 
 /// Container workload classification
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum ContainerWorkloadType {
-    Ai,      // AI/ML workload (Ollama, PyTorch, etc.) - batch priority, GPU affinity
-    Gaming,  // Gaming container (rare but possible) - gaming priority
+    Ai,     // AI/ML workload (Ollama, PyTorch, etc.) - batch priority, GPU affinity
+    Gaming, // Gaming container (rare but possible) - gaming priority
+    #[serde(alias = "batch")]
     Compute, // GPU compute workload - batch priority
+    #[serde(alias = "container")]
     General, // General container - batch priority
 }
 
@@ -36,6 +41,18 @@ impl std::fmt::Display for ContainerWorkloadType {
     }
 }
 
+impl ContainerWorkloadType {
+    /// Convert to BPF workload class
+    pub fn as_bpf_class(self) -> u32 {
+        match self {
+            ContainerWorkloadType::Ai => 4,      // WORKLOAD_AI
+            ContainerWorkloadType::Gaming => 1,  // WORKLOAD_GAMING
+            ContainerWorkloadType::Compute => 3, // WORKLOAD_BATCH
+            ContainerWorkloadType::General => 7, // WORKLOAD_CONTAINER
+        }
+    }
+}
+
 /// Information about a detected container
 #[derive(Debug)]
 #[allow(dead_code)]
@@ -44,18 +61,67 @@ pub struct ContainerInfo {
     pub id: String,
     /// Container name (if available)
     pub name: String,
+    /// Image reference (Docker only, empty otherwise)
+    pub image: String,
     /// Runtime (docker, podman, containerd)
     pub runtime: String,
     /// Workload type
     pub workload_type: ContainerWorkloadType,
     /// Main process PIDs in the container
     pub pids: Vec<u32>,
-    /// Whether container has GPU access
-    pub has_gpu: bool,
+    /// Distinct GPU devices (or MIG slices) visible to the container
+    pub gpu_count: u32,
     /// Cgroup path
     pub cgroup_path: String,
 }
 
+/// Length of a short container ID, as `docker ps` and `podman ps` print it
+const SHORT_ID_LEN: usize = 12;
+
+/// User mapping of container names/images to workload types (`[containers]`)
+///
+/// Keys are matched case-insensitively as substrings of the container name
+/// or image, or as the container ID itself (in full or as a prefix of at
+/// least SHORT_ID_LEN characters, so a short key can't hit hex IDs at
+/// random); the longest matching key wins. Consulted before the process
+/// heuristics so a renamed binary can't be misclassified.
+#[derive(Debug, Clone, Default)]
+pub struct ContainerClassMap {
+    entries: Vec<(String, ContainerWorkloadType)>,
+}
+
+impl ContainerClassMap {
+    pub fn new(map: &BTreeMap<String, ContainerWorkloadType>) -> Self {
+        let mut entries: Vec<_> = map
+            .iter()
+            .map(|(key, class)| (key.trim().to_lowercase(), *class))
+            .filter(|(key, _)| !key.is_empty())
+            .collect();
+        entries.sort_by_key(|(key, _)| std::cmp::Reverse(key.len()));
+        Self { entries }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Configured type for a container, if any key matches
+    ///
+    /// `name` and `image` are empty when the runtime's metadata wasn't found;
+    /// `id` is the full container ID.
+    pub fn lookup(&self, name: &str, image: &str, id: &str) -> Option<ContainerWorkloadType> {
+        let (name, image, id) = (name.to_lowercase(), image.to_lowercase(), id.to_lowercase());
+        self.entries
+            .iter()
+            .find(|(key, _)| {
+                (!name.is_empty() && name.contains(key))
+                    || (!image.is_empty() && image.contains(key))
+                    || (key.len() >= SHORT_ID_LEN && id.starts_with(key.as_str()))
+            })
+            .map(|(_, class)| *class)
+    }
+}
+
 /// AI/ML process patterns
 const AI_PATTERNS: &[&str] = &[
     "ollama",
@@ -95,20 +161,24 @@ pub fn nvidia_runtime_available() -> bool {
 }
 
 /// Scan for containers via cgroups (runtime-agnostic)
-pub fn scan_containers() -> Result<Vec<ContainerInfo>> {
+pub fn scan_containers(classes: &ContainerClassMap) -> Result<Vec<ContainerInfo>> {
     let mut containers = Vec::new();
 
     // Scan cgroup v2 hierarchy
-    let cgroup_base = Path::new("/sys/fs/cgroup");
+    let cgroup_base = procfs::rooted("/sys/fs/cgroup");
     if cgroup_base.exists() {
-        scan_cgroup_dir(cgroup_base, &mut containers)?;
+        scan_cgroup_dir(&cgroup_base, classes, &mut containers)?;
     }
 
     Ok(containers)
 }
 
 /// Recursively scan cgroup directory for containers
-fn scan_cgroup_dir(dir: &Path, containers: &mut Vec<ContainerInfo>) -> Result<()> {
+fn scan_cgroup_dir(
+    dir: &Path,
+    classes: &ContainerClassMap,
+    containers: &mut Vec<ContainerInfo>,
+) -> Result<()> {
     if !dir.is_dir() {
         return Ok(());
     }
@@ -120,7 +190,7 @@ fn scan_cgroup_dir(dir: &Path, containers: &mut Vec<ContainerInfo>) -> Result<()
 
     // Check if this looks like a container cgroup
     if is_container_cgroup(&dir_name) {
-        if let Some(container) = parse_container_cgroup(dir)? {
+        if let Some(container) = parse_container_cgroup(dir, classes)? {
             containers.push(container);
         }
         return Ok(()); // Don't recurse into container cgroups
@@ -136,7 +206,7 @@ fn scan_cgroup_dir(dir: &Path, containers: &mut Vec<ContainerInfo>) -> Result<()
                 if name == "system.slice" || name == "user.slice" || name == "init.scope" {
                     continue;
                 }
-                scan_cgroup_dir(&path, containers)?;
+                scan_cgroup_dir(&path, classes, containers)?;
             }
         }
     }
@@ -170,7 +240,10 @@ fn is_container_cgroup(name: &str) -> bool {
 }
 
 /// Parse a container cgroup and extract info
-fn parse_container_cgroup(cgroup_path: &Path) -> Result<Option<ContainerInfo>> {
+fn parse_container_cgroup(
+    cgroup_path: &Path,
+    classes: &ContainerClassMap,
+) -> Result<Option<ContainerInfo>> {
     let cgroup_name = cgroup_path
         .file_name()
         .map(|n| n.to_string_lossy().to_string())
@@ -178,6 +251,7 @@ fn parse_container_cgroup(cgroup_path: &Path) -> Result<Option<ContainerInfo>> {
 
     // Extract container ID
     let id = extract_container_id(&cgroup_name);
+    let full_id = full_container_id(&cgroup_name);
 
     // Get PIDs in this cgroup
     let procs_path = cgroup_path.join("cgroup.procs");
@@ -207,31 +281,56 @@ fn parse_container_cgroup(cgroup_path: &Path) -> Result<Option<ContainerInfo>> {
     }
     .to_string();
 
-    // Classify workload and check for GPU
-    let (workload_type, has_gpu) = classify_container_workload(&pids, cgroup_path);
+    // Try to get container name and image (Docker and Podman)
+    let (name, image) = container_metadata(full_id, &runtime).unwrap_or_default();
 
-    // Try to get container name (from Docker/Podman)
-    let name = get_container_name(&id, &runtime);
+    // Classify workload and count visible GPUs; a configured mapping wins
+    // over the process heuristics
+    let (heuristic, gpu_count) = classify_container_workload(&pids, cgroup_path);
+    let workload_type = match classes.lookup(&name, &image, full_id) {
+        Some(configured) => {
+            if configured != heuristic {
+                debug!(
+                    "Container {} ({}): {} via [containers] (heuristic: {})",
+                    id, name, configured, heuristic
+                );
+            }
+            configured
+        }
+        None => heuristic,
+    };
+    // Without a resolved name, the short ID stands in for it
+    let name = if name.is_empty() { id.clone() } else { name };
 
     debug!(
-        "Container {}: {} PIDs, type: {}, GPU: {}",
+        "Container {}: {} PIDs, type: {}, GPUs: {}",
         id,
         pids.len(),
         workload_type,
-        has_gpu
+        gpu_count
     );
 
     Ok(Some(ContainerInfo {
         id,
         name,
+        image,
         runtime,
         workload_type,
         pids,
-        has_gpu,
+        gpu_count,
         cgroup_path: cgroup_path.to_string_lossy().to_string(),
     }))
 }
 
+/// Full container ID from a cgroup name (docker-<id>.scope, libpod-<id>.scope
+/// or the bare ID)
+fn full_container_id(name: &str) -> &str {
+    ["docker-", "libpod-"]
+        .iter()
+        .find_map(|prefix| name.strip_prefix(prefix)?.strip_suffix(".scope"))
+        .unwrap_or(name)
+}
+
 /// Extract container ID from cgroup name
 fn extract_container_id(name: &str) -> String {
     // docker-<id>.scope -> <id>
@@ -256,17 +355,114 @@ fn extract_container_id(name: &str) -> String {
     name.to_string()
 }
 
+/// NVIDIA character device major (/dev/nvidia0..N, nvidiactl, nvidia-modeset)
+const NVIDIA_DEVICE_MAJOR: &str = "195";
+/// Minors at and above this are control devices (nvidia-modeset 254, nvidiactl 255)
+const NVIDIA_CONTROL_MINOR: u32 = 254;
+
+/// Count GPUs in an NVIDIA_VISIBLE_DEVICES value
+///
+/// Entries are indices, GPU UUIDs or MIG identifiers; each distinct entry is
+/// one device. Returns None for "all", where the count is the host's GPUs.
+pub fn parse_visible_devices(value: &str) -> Option<u32> {
+    let value = value.trim();
+    match value {
+        "all" => None,
+        "" | "none" | "void" => Some(0),
+        _ => {
+            let devices: HashSet<&str> = value
+                .split(',')
+                .map(str::trim)
+                .filter(|d| !d.is_empty())
+                .collect();
+            Some(devices.len() as u32)
+        }
+    }
+}
+
+/// Count GPUs granted in a cgroup v1 devices.list
+///
+/// Counts distinct `c 195:N` entries below the control minors. Returns None
+/// when the list allows every device (`a *:* rwm` or `c 195:*`).
+pub fn count_devices_list_gpus(devices_list: &str) -> Option<u32> {
+    let mut minors = HashSet::new();
+
+    for line in devices_list.lines() {
+        let mut fields = line.split_whitespace();
+        let (Some(kind), Some(dev)) = (fields.next(), fields.next()) else {
+            continue;
+        };
+        let Some((major, minor)) = dev.split_once(':') else {
+            continue;
+        };
+
+        if kind == "a" || (major == NVIDIA_DEVICE_MAJOR && minor == "*") {
+            return None;
+        }
+        if kind == "c"
+            && major == NVIDIA_DEVICE_MAJOR
+            && let Ok(minor) = minor.parse::<u32>()
+            && minor < NVIDIA_CONTROL_MINOR
+        {
+            minors.insert(minor);
+        }
+    }
+
+    Some(minors.len() as u32)
+}
+
+/// Number of NVIDIA GPU device nodes on the host (/dev/nvidiaN)
+fn host_gpu_count() -> u32 {
+    fs::read_dir("/dev")
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|e| {
+                    e.file_name()
+                        .to_str()
+                        .and_then(|name| name.strip_prefix("nvidia"))
+                        .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+                })
+                .count() as u32
+        })
+        .unwrap_or(0)
+}
+
+/// NUMA node for a GPU-using AI container's threads
+///
+/// `gpu_nodes` lists host GPU NUMA nodes, primary first. A single-GPU
+/// container follows the primary GPU; a multi-GPU one spans the nodes of the
+/// host GPUs. numa_hints holds one node, so a span across several nodes gets
+/// no hint rather than being pinned to one of them.
+pub fn container_gpu_numa(gpu_count: u32, gpu_nodes: &[i32]) -> Option<u32> {
+    let nodes: HashSet<u32> = match gpu_count {
+        0 => return None,
+        1 => gpu_nodes.first().copied().into_iter().collect::<Vec<_>>(),
+        _ => gpu_nodes.to_vec(),
+    }
+    .into_iter()
+    .filter_map(|node| u32::try_from(node).ok())
+    .collect();
+
+    match nodes.len() {
+        1 => nodes.into_iter().next(),
+        _ => None,
+    }
+}
+
 /// Classify container workload based on processes and environment
-fn classify_container_workload(pids: &[u32], cgroup_path: &Path) -> (ContainerWorkloadType, bool) {
+///
+/// Returns the workload type and the number of GPUs the container can see.
+fn classify_container_workload(pids: &[u32], cgroup_path: &Path) -> (ContainerWorkloadType, u32) {
     let mut has_gpu = false;
+    let mut gpu_count: Option<u32> = Some(0);
     let mut workload_type = ContainerWorkloadType::General;
 
     // Check each process in the container
     for &pid in pids {
-        // Check process comm
-        let comm_path = format!("/proc/{}/comm", pid);
-        if let Ok(comm) = fs::read_to_string(&comm_path) {
-            let comm_lower = comm.trim().to_lowercase();
+        // Check process comm (untruncated, so long patterns can match)
+        if let Some(comm) = procfs::read_full_comm(pid) {
+            let comm_lower = comm.to_lowercase();
 
             // AI patterns
             for pattern in AI_PATTERNS {
@@ -296,11 +492,19 @@ fn classify_container_workload(pids: &[u32], cgroup_path: &Path) -> (ContainerWo
         }
 
         // Check environment for NVIDIA/CUDA
-        let environ_path = format!("/proc/{}/environ", pid);
-        if let Ok(environ) = fs::read_to_string(&environ_path) {
+        if let Some(environ) = procfs::read_pid_file(pid, "environ") {
             if environ.contains("NVIDIA") || environ.contains("CUDA") {
                 has_gpu = true;
             }
+            if let Some(visible) = environ
+                .split('\0')
+                .find_map(|var| var.strip_prefix("NVIDIA_VISIBLE_DEVICES="))
+            {
+                gpu_count = match (gpu_count, parse_visible_devices(visible)) {
+                    (Some(a), Some(b)) => Some(a.max(b)),
+                    _ => None,
+                };
+            }
             if environ.contains("OLLAMA") {
                 workload_type = ContainerWorkloadType::Ai;
             }
@@ -315,22 +519,76 @@ fn classify_container_workload(pids: &[u32], cgroup_path: &Path) -> (ContainerWo
         // NVIDIA devices are typically c 195:* (nvidia) or c 235:* (nvidia-uvm)
         if devices.contains("195:") || devices.contains("235:") {
             has_gpu = true;
+            gpu_count = match (gpu_count, count_devices_list_gpus(&devices)) {
+                (Some(a), Some(b)) => Some(a.max(b)),
+                _ => None,
+            };
         }
     }
 
-    (workload_type, has_gpu)
+    // "all" or wildcard access sees every host GPU; NVIDIA/CUDA hints with no
+    // device detail still mean at least one
+    let gpu_count = gpu_count.unwrap_or_else(host_gpu_count);
+    let gpu_count = if has_gpu { gpu_count.max(1) } else { gpu_count };
+
+    (workload_type, gpu_count)
 }
 
-/// Try to get container name from runtime
-fn get_container_name(id: &str, runtime: &str) -> String {
+/// Docker's per-container state (holds the name and image)
+const DOCKER_CONTAINERS_DIR: &str = "/var/lib/docker/containers";
+
+/// Name and image from a Docker config.v2.json
+fn parse_docker_config(json: &str) -> Option<(String, String)> {
+    let config: serde_json::Value = serde_json::from_str(json).ok()?;
+    let name = config["Name"].as_str()?.trim_start_matches('/').to_string();
+    let image = config["Config"]["Image"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+    Some((name, image))
+}
+
+/// Podman's (root) container store
+const PODMAN_CONTAINERS_JSON: &str =
+    "/var/lib/containers/storage/overlay-containers/containers.json";
+
+/// Name and image of one container in Podman's containers.json
+///
+/// Podman keeps its own metadata (including the image reference) as a JSON
+/// string inside each entry.
+fn parse_podman_containers(json: &str, full_id: &str) -> Option<(String, String)> {
+    let containers: serde_json::Value = serde_json::from_str(json).ok()?;
+    let container = containers
+        .as_array()?
+        .iter()
+        .find(|c| c["id"].as_str() == Some(full_id))?;
+    let name = container["names"][0].as_str()?.to_string();
+    let image = container["metadata"]
+        .as_str()
+        .and_then(|metadata| serde_json::from_str::<serde_json::Value>(metadata).ok())
+        .and_then(|metadata| metadata["image-name"].as_str().map(String::from))
+        .unwrap_or_default();
+    Some((name, image))
+}
+
+/// Try to get container name and image from the runtime
+///
+/// Docker keeps both in its state directory under the full ID, Podman in
+/// its container store; containerd containers have neither.
+fn container_metadata(full_id: &str, runtime: &str) -> Option<(String, String)> {
     match runtime {
-        "docker" => {
-            // Try docker inspect (if docker CLI available)
-            // For now, just use ID
-            id.to_string()
+        "docker" => parse_docker_config(
+            &fs::read_to_string(
+                Path::new(DOCKER_CONTAINERS_DIR)
+                    .join(full_id)
+                    .join("config.v2.json"),
+            )
+            .ok()?,
+        ),
+        "podman" => {
+            parse_podman_containers(&procfs::read_rooted(PODMAN_CONTAINERS_JSON).ok()?, full_id)
         }
-        "podman" => id.to_string(),
-        _ => id.to_string(),
+        _ => None,
     }
 }
 
@@ -338,7 +596,7 @@ fn get_container_name(id: &str, runtime: &str) -> String {
 pub fn scan_ollama() -> Vec<(u32, String)> {
     let mut ollama_pids = Vec::new();
 
-    if let Ok(proc_dir) = fs::read_dir("/proc") {
+    if let Ok(proc_dir) = fs::read_dir(procfs::rooted("/proc")) {
         for entry in proc_dir.flatten() {
             let file_name = entry.file_name();
             let name = file_name.to_string_lossy();
@@ -349,9 +607,8 @@ pub fn scan_ollama() -> Vec<(u32, String)> {
             };
 
             // Check comm
-            let comm_path = format!("/proc/{}/comm", pid);
-            if let Ok(comm) = fs::read_to_string(&comm_path) {
-                let comm = comm.trim().to_lowercase();
+            if let Some(comm) = procfs::read_full_comm(pid) {
+                let comm = comm.to_lowercase();
                 if comm.contains("ollama") {
                     ollama_pids.push((pid, comm.to_string()));
                 }
@@ -362,34 +619,58 @@ pub fn scan_ollama() -> Vec<(u32, String)> {
     ollama_pids
 }
 
+/// Scheduling treatment for Ollama inference
+///
+/// Inference runs at AI (batch) priority so it never preempts gaming, and its
+/// threads are kept on the GPU's NUMA node when the GPU reports one.
+pub fn ollama_workload(gpu_numa_node: i32) -> (ContainerWorkloadType, Option<u32>) {
+    (ContainerWorkloadType::Ai, u32::try_from(gpu_numa_node).ok())
+}
+
+/// Thread IDs of a process (numa_hints is keyed per thread)
+pub fn thread_ids(pid: u32) -> Vec<u32> {
+    fs::read_dir(procfs::rooted(format!("/proc/{}/task", pid)))
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|e| e.file_name().to_string_lossy().parse().ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
 /// Container Monitor for tracking containers and their state
 #[allow(dead_code)]
 pub struct ContainerMonitor {
     containers: Vec<ContainerInfo>,
     nvidia_available: bool,
     ollama_pids: Vec<(u32, String)>,
+    /// User name/image -> workload type mapping
+    classes: ContainerClassMap,
+    /// --containers-batch-only: push every container PID as batch
+    batch_only: bool,
 }
 
 impl ContainerMonitor {
-    pub fn new() -> Result<Self> {
+    pub fn new(classes: ContainerClassMap) -> Result<Self> {
         let nvidia_available = nvidia_runtime_available();
         if nvidia_available {
             info!("NVIDIA Container Runtime: available");
         }
 
-        let containers = scan_containers()?;
+        let containers = scan_containers(&classes)?;
         let ollama_pids = scan_ollama();
 
         if !containers.is_empty() {
             info!("Containers: {} detected", containers.len());
             for c in &containers {
                 debug!(
-                    "  {} ({}): {} PIDs, type: {}, GPU: {}",
+                    "  {} ({}): {} PIDs, type: {}, GPUs: {}",
                     c.id,
                     c.runtime,
                     c.pids.len(),
                     c.workload_type,
-                    c.has_gpu
+                    c.gpu_count
                 );
             }
         }
@@ -402,12 +683,36 @@ impl ContainerMonitor {
             containers,
             nvidia_available,
             ollama_pids,
+            classes,
+            batch_only: false,
+        })
+    }
+
+    /// Monitor with the `[containers]` mapping, empty if the scan fails
+    pub fn with_classes(classes: ContainerClassMap) -> Self {
+        Self::new(classes.clone()).unwrap_or(Self {
+            containers: Vec::new(),
+            nvidia_available: false,
+            ollama_pids: Vec::new(),
+            classes,
+            batch_only: false,
         })
     }
 
+    /// Replace the `[containers]` mapping (applies from the next rescan)
+    pub fn set_classes(&mut self, classes: ContainerClassMap) {
+        self.classes = classes;
+    }
+
+    /// Push every container PID as batch, whatever its classification
+    /// (classification still drives the counts)
+    pub fn set_batch_only(&mut self, batch_only: bool) {
+        self.batch_only = batch_only;
+    }
+
     /// Rescan for containers (call periodically)
     pub fn rescan(&mut self) -> Result<(Vec<ContainerInfo>, Vec<String>)> {
-        let current = scan_containers()?;
+        let current = scan_containers(&self.classes)?;
         self.ollama_pids = scan_ollama();
 
         let current_ids: HashSet<String> = current.iter().map(|c| c.id.clone()).collect();
@@ -423,7 +728,7 @@ impl ContainerMonitor {
         let removed_ids: Vec<String> = old_ids.difference(&current_ids).cloned().collect();
 
         // Update container list
-        self.containers = scan_containers()?;
+        self.containers = scan_containers(&self.classes)?;
 
         Ok((new_containers, removed_ids))
     }
@@ -452,7 +757,30 @@ impl ContainerMonitor {
 
     /// Get GPU container count
     pub fn gpu_container_count(&self) -> usize {
-        self.containers.iter().filter(|c| c.has_gpu).count()
+        self.containers.iter().filter(|c| c.gpu_count > 0).count()
+    }
+
+    /// GPU-using AI containers as (PIDs, GPU count)
+    pub fn gpu_ai_containers(&self) -> impl Iterator<Item = (&[u32], u32)> {
+        self.containers
+            .iter()
+            .filter(|c| c.workload_type == ContainerWorkloadType::Ai && c.gpu_count > 0)
+            .map(|c| (c.pids.as_slice(), c.gpu_count))
+    }
+
+    /// All AI containers, with or without GPUs
+    pub fn ai_containers(&self) -> impl Iterator<Item = &ContainerInfo> {
+        self.containers
+            .iter()
+            .filter(|c| c.workload_type == ContainerWorkloadType::Ai)
+    }
+
+    /// PIDs of AI containers without GPUs
+    pub fn cpu_ai_containers(&self) -> impl Iterator<Item = &[u32]> {
+        self.containers
+            .iter()
+            .filter(|c| c.workload_type == ContainerWorkloadType::Ai && c.gpu_count == 0)
+            .map(|c| c.pids.as_slice())
     }
 
     /// Get total container count
@@ -471,12 +799,22 @@ impl ContainerMonitor {
         self.ollama_pids.len()
     }
 
+    /// Get Ollama server/runner PIDs
+    pub fn ollama_pids(&self) -> Vec<u32> {
+        self.ollama_pids.iter().map(|(pid, _)| *pid).collect()
+    }
+
     /// Get all container PIDs (for BPF map)
     pub fn all_pids(&self) -> Vec<(u32, ContainerWorkloadType)> {
         let mut pids = Vec::new();
         for container in &self.containers {
+            let workload_type = if self.batch_only {
+                ContainerWorkloadType::Compute
+            } else {
+                container.workload_type
+            };
             for &pid in &container.pids {
-                pids.push((pid, container.workload_type));
+                pids.push((pid, workload_type));
             }
         }
         pids
@@ -485,11 +823,7 @@ impl ContainerMonitor {
 
 impl Default for ContainerMonitor {
     fn default() -> Self {
-        Self::new().unwrap_or(Self {
-            containers: Vec::new(),
-            nvidia_available: false,
-            ollama_pids: Vec::new(),
-        })
+        Self::with_classes(ContainerClassMap::default())
     }
 }
 
@@ -502,9 +836,42 @@ mod tests {
         let _available = nvidia_runtime_available();
     }
 
+    #[test]
+    fn test_truncated_comm_uses_cmdline() {
+        let pattern = "ollama_llama_server";
+        assert!(pattern.len() > procfs::COMM_MAX_LEN);
+        let comm = "ollama_llama_se\n";
+        let cmdline = "/usr/lib/ollama/runners/cuda_v12/ollama_llama_server\0runner\0--model\0";
+
+        // The kernel's comm alone can never match the full pattern
+        assert!(!comm.contains(pattern));
+        let name = procfs::full_comm(comm, Some(cmdline));
+        assert_eq!(name, pattern);
+        assert!(AI_PATTERNS.iter().any(|p| name == *p));
+
+        // Short comms and renamed processes keep their comm
+        assert_eq!(procfs::full_comm("ollama\n", Some(cmdline)), "ollama");
+        assert_eq!(
+            procfs::full_comm("inference-worke", Some("/usr/bin/python3\0serve.py\0")),
+            "inference-worke"
+        );
+        // Wine passes a Windows path as argv[0]
+        assert_eq!(
+            procfs::full_comm(
+                "EldenRingLaunch",
+                Some("Z:\\Games\\EldenRingLauncher.exe\0")
+            ),
+            "EldenRingLauncher.exe"
+        );
+        assert_eq!(
+            procfs::full_comm("ollama_llama_se", None),
+            "ollama_llama_se"
+        );
+    }
+
     #[test]
     fn test_scan_containers() {
-        let result = scan_containers();
+        let result = scan_containers(&ContainerClassMap::default());
         assert!(result.is_ok());
     }
 
@@ -512,4 +879,176 @@ mod tests {
     fn test_scan_ollama() {
         let _pids = scan_ollama();
     }
+
+    #[test]
+    fn test_ollama_workload() {
+        assert_eq!(ollama_workload(1), (ContainerWorkloadType::Ai, Some(1)));
+        // GPU without NUMA affinity (single-node systems report -1)
+        assert_eq!(ollama_workload(-1), (ContainerWorkloadType::Ai, None));
+        assert!(thread_ids(std::process::id()).contains(&std::process::id()));
+    }
+
+    #[test]
+    fn test_configured_class_wins() {
+        let config: BTreeMap<String, ContainerWorkloadType> = toml::from_str(
+            r#"
+"myinference" = "ai"
+"ollama/ollama" = "batch"
+"ollama/ollama:rocm" = "gaming"
+"#,
+        )
+        .unwrap();
+        let classes = ContainerClassMap::new(&config);
+
+        // The heuristic would call a renamed binary general
+        assert_eq!(
+            classes.lookup("MyInference-worker", "", "0123456789ab"),
+            Some(ContainerWorkloadType::Ai)
+        );
+        // Image match; the longest key wins
+        assert_eq!(
+            classes.lookup("llm", "docker.io/ollama/ollama:latest", ""),
+            Some(ContainerWorkloadType::Compute)
+        );
+        assert_eq!(
+            classes.lookup("llm", "ollama/ollama:rocm", ""),
+            Some(ContainerWorkloadType::Gaming)
+        );
+        assert_eq!(classes.lookup("web", "nginx:1.27", "0123456789ab"), None);
+        assert!(ContainerClassMap::default().is_empty());
+
+        // IDs match in full or by a prefix of at least 12 characters, never
+        // as a substring: "cafe" must not hit a random hex ID
+        let config: BTreeMap<String, ContainerWorkloadType> = toml::from_str(
+            r#"
+"cafe" = "ai"
+"3f4e9a1c7b2d" = "batch"
+"#,
+        )
+        .unwrap();
+        let classes = ContainerClassMap::new(&config);
+        let id = "3f4e9a1c7b2dcafe".repeat(4);
+        assert_eq!(
+            classes.lookup("", "", &id),
+            Some(ContainerWorkloadType::Compute)
+        );
+        assert_eq!(classes.lookup("", "", &"0cafe".repeat(13)), None);
+        assert_eq!(
+            classes.lookup("cafe-llm", "", &"0".repeat(64)),
+            Some(ContainerWorkloadType::Ai)
+        );
+
+        let docker = r#"{"ID":"abc","Name":"/myinference","Config":{"Image":"vllm/vllm-openai"}}"#;
+        assert_eq!(
+            parse_docker_config(docker),
+            Some(("myinference".to_string(), "vllm/vllm-openai".to_string()))
+        );
+
+        let podman = r#"[{"id":"abc","names":["myinference"],
+            "metadata":"{\"image-name\":\"docker.io/vllm/vllm-openai:latest\",\"name\":\"myinference\"}"}]"#;
+        assert_eq!(
+            parse_podman_containers(podman, "abc"),
+            Some((
+                "myinference".to_string(),
+                "docker.io/vllm/vllm-openai:latest".to_string()
+            ))
+        );
+        assert_eq!(parse_podman_containers(podman, "def"), None);
+    }
+
+    #[test]
+    fn test_gpu_count() {
+        assert_eq!(parse_visible_devices("0,1"), Some(2));
+        assert_eq!(parse_visible_devices("0, 1,1"), Some(2));
+        assert_eq!(
+            parse_visible_devices("MIG-4f2e1c3a-0000-1111-2222-333344445555"),
+            Some(1)
+        );
+        assert_eq!(parse_visible_devices("void"), Some(0));
+        assert_eq!(parse_visible_devices("all"), None);
+
+        let devices = "c 195:0 rwm\nc 195:1 rwm\nc 195:255 rwm\nc 235:0 rwm\n";
+        assert_eq!(count_devices_list_gpus(devices), Some(2));
+        assert_eq!(count_devices_list_gpus("a *:* rwm\n"), None);
+
+        // Single GPU follows the primary, multi-GPU spans (one node or none)
+        assert_eq!(container_gpu_numa(1, &[1, 0]), Some(1));
+        assert_eq!(container_gpu_numa(2, &[1, 1]), Some(1));
+        assert_eq!(container_gpu_numa(2, &[0, 1]), None);
+        assert_eq!(container_gpu_numa(0, &[0]), None);
+        assert_eq!(container_gpu_numa(2, &[-1, -1]), None);
+    }
+
+    #[test]
+    fn test_scan_containers_from_fixture() {
+        let fixture = procfs::Fixture::new("container");
+        let id = "3f4e9a1c7b2d".repeat(5) + "a1b2";
+        fixture
+            .file(
+                &format!(
+                    "/sys/fs/cgroup/machine.slice/libpod-{}.scope/cgroup.procs",
+                    id
+                ),
+                "4242\n",
+            )
+            .file("/proc/4242/comm", "ollama\n")
+            .file("/proc/4242/environ", "NVIDIA_VISIBLE_DEVICES=0,1\0");
+
+        let containers = fixture
+            .run(|| scan_containers(&ContainerClassMap::default()))
+            .unwrap();
+        assert_eq!(containers.len(), 1);
+        let container = &containers[0];
+        assert_eq!(container.id, "3f4e9a1c7b2d");
+        // No Podman store: the short ID stands in for the name
+        assert_eq!(container.name, "3f4e9a1c7b2d");
+        assert_eq!(container.runtime, "podman");
+        assert_eq!(container.workload_type, ContainerWorkloadType::Ai);
+        assert_eq!(container.pids, vec![4242]);
+        assert_eq!(container.gpu_count, 2);
+
+        // With the store, the Podman name resolves and a mapping applies
+        fixture.file(
+            PODMAN_CONTAINERS_JSON,
+            &format!(r#"[{{"id":"{}","names":["llm-sidecar"]}}]"#, id),
+        );
+        let config: BTreeMap<String, ContainerWorkloadType> =
+            toml::from_str(r#""llm-sidecar" = "general""#).unwrap();
+        let containers = fixture
+            .run(|| scan_containers(&ContainerClassMap::new(&config)))
+            .unwrap();
+        assert_eq!(containers[0].name, "llm-sidecar");
+        assert_eq!(containers[0].workload_type, ContainerWorkloadType::General);
+    }
+
+    #[test]
+    fn test_batch_only_pushes_batch() {
+        let mut monitor = ContainerMonitor {
+            containers: vec![ContainerInfo {
+                id: "3f4e9a1c7b2d".to_string(),
+                name: "ollama".to_string(),
+                image: "ollama/ollama".to_string(),
+                runtime: "docker".to_string(),
+                workload_type: ContainerWorkloadType::Ai,
+                pids: vec![4242, 4243],
+                gpu_count: 1,
+                cgroup_path: String::new(),
+            }],
+            nvidia_available: false,
+            ollama_pids: Vec::new(),
+            classes: ContainerClassMap::default(),
+            batch_only: false,
+        };
+        assert_eq!(monitor.all_pids()[0].1.as_bpf_class(), 4); // WORKLOAD_AI
+
+        monitor.set_batch_only(true);
+        let classes: Vec<(u32, u32)> = monitor
+            .all_pids()
+            .into_iter()
+            .map(|(pid, workload)| (pid, workload.as_bpf_class()))
+            .collect();
+        assert_eq!(classes, vec![(4242, 3), (4243, 3)]); // WORKLOAD_BATCH
+        // Still counted as what it is
+        assert_eq!(monitor.ai_container_count(), 1);
+    }
 }
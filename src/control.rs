@@ -5,12 +5,22 @@
 // Provides a simple file-based interface for runtime tuning.
 // Users can write commands to /run/ghostbrew/control to update tunables.
 //
+// Each command runs once. Appended lines (`>>`, the pacman hook) are read
+// from where the last poll stopped; a file that was rewritten (`>`) is read
+// from the start.
+//
 // Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
 
 use anyhow::{Context, Result};
 use log::{debug, info, warn};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+/// Runtime control file
+pub const CONTROL_FILE: &str = "/run/ghostbrew/control";
+
+/// Bare command asking for a profile reload and process rescan
+pub const RESCAN_COMMAND: &str = "rescan";
 
 /// Control file commands
 #[derive(Debug, Clone)]
@@ -23,25 +33,42 @@ pub enum ControlCommand {
     GamingMode(bool),
     /// Enable work mode
     WorkMode(bool),
+    /// Reload profiles and rescan processes (pacman hook)
+    Rescan,
 }
 
+/// Bytes of the consumed content compared to tell an append from a rewrite
+const HEAD_BYTES: usize = 64;
+
 /// Control interface manager
 pub struct ControlInterface {
     control_dir: PathBuf,
     control_file: PathBuf,
-    last_modified: Option<std::time::SystemTime>,
+    /// Modification time and length at the last poll
+    last_seen: Option<(Option<std::time::SystemTime>, u64)>,
+    /// Bytes of the file already parsed
+    consumed: usize,
+    /// Start of the parsed content
+    head: Vec<u8>,
 }
 
 impl ControlInterface {
     /// Create a new control interface
     pub fn new() -> Self {
-        let control_dir = PathBuf::from("/run/ghostbrew");
-        let control_file = control_dir.join("control");
+        let control_file = PathBuf::from(CONTROL_FILE);
+        Self::with_file(&control_file)
+    }
 
+    /// Control interface on another file (tests)
+    fn with_file(control_file: &Path) -> Self {
         Self {
-            control_dir,
-            control_file,
-            last_modified: None,
+            control_dir: control_file
+                .parent()
+                .map_or_else(PathBuf::new, Path::to_path_buf),
+            control_file: control_file.to_path_buf(),
+            last_seen: None,
+            consumed: 0,
+            head: Vec::new(),
         }
     }
 
@@ -61,12 +88,13 @@ impl ControlInterface {
 #   slice_ns=<value>            - Set time slice (nanoseconds)
 #   gaming_mode=<true|false>    - Enable/disable gaming mode
 #   work_mode=<true|false>      - Enable/disable work mode
+#   rescan                      - Reload profiles and rescan processes
 #
 # Example:
 #   echo "burst_threshold_ns=1500000" > /run/ghostbrew/control
 #   echo "gaming_mode=true" >> /run/ghostbrew/control
 #
-# Multiple commands can be on separate lines.
+# Multiple commands can be on separate lines. Each command runs once.
 "#;
         fs::write(&self.control_file, usage).context("Failed to create control file")?;
 
@@ -95,19 +123,22 @@ impl ControlInterface {
             Err(_) => return commands,
         };
 
-        let modified = metadata.modified().ok();
-        if modified == self.last_modified {
+        let seen = (metadata.modified().ok(), metadata.len());
+        if Some(seen) == self.last_seen {
             return commands; // No changes
         }
-        self.last_modified = modified;
+        self.last_seen = Some(seen);
 
-        // Read and parse commands
-        let content = match fs::read_to_string(&self.control_file) {
+        // Read and parse the commands not seen yet
+        let content = match fs::read(&self.control_file) {
             Ok(c) => c,
             Err(_) => return commands,
         };
+        let start = self.unconsumed_start(&content);
+        self.consumed = content.len();
+        self.head = content[..content.len().min(HEAD_BYTES)].to_vec();
 
-        for line in content.lines() {
+        for line in String::from_utf8_lossy(&content[start..]).lines() {
             let line = line.trim();
 
             // Skip comments and empty lines
@@ -124,8 +155,22 @@ impl ControlInterface {
         commands
     }
 
+    /// Offset of the first unparsed byte: the end of the last poll when
+    /// lines were appended, 0 when the file was rewritten
+    fn unconsumed_start(&self, content: &[u8]) -> usize {
+        if content.len() > self.consumed && content.starts_with(&self.head) {
+            self.consumed
+        } else {
+            0
+        }
+    }
+
     /// Parse a single command line
     fn parse_command(line: &str) -> Option<ControlCommand> {
+        if line.eq_ignore_ascii_case(RESCAN_COMMAND) {
+            return Some(ControlCommand::Rescan);
+        }
+
         let parts: Vec<&str> = line.splitn(2, '=').collect();
         if parts.len() != 2 {
             return None;
@@ -192,7 +237,49 @@ mod tests {
             Some(ControlCommand::WorkMode(false))
         ));
 
+        assert!(matches!(
+            ControlInterface::parse_command("rescan"),
+            Some(ControlCommand::Rescan)
+        ));
+
         assert!(ControlInterface::parse_command("# comment").is_none());
         assert!(ControlInterface::parse_command("invalid").is_none());
     }
+
+    #[test]
+    fn test_commands_run_once() {
+        use std::io::Write;
+
+        let dir = std::env::temp_dir().join(format!("ghostbrew-control-{}", std::process::id()));
+        let file = dir.join("control");
+        let mut control = ControlInterface::with_file(&file);
+        control.init().unwrap();
+        let append = |line: &str| {
+            let mut f = fs::OpenOptions::new().append(true).open(&file).unwrap();
+            writeln!(f, "{}", line).unwrap();
+        };
+
+        // The usage header holds no commands
+        assert!(control.poll_commands().is_empty());
+
+        append("slice_ns=2500000");
+        let commands = control.poll_commands();
+        assert!(matches!(
+            commands[..],
+            [ControlCommand::SetSlice(2_500_000)]
+        ));
+        assert!(control.poll_commands().is_empty());
+
+        // A second append (the pacman hook) runs only the new line
+        append("rescan");
+        let commands = control.poll_commands();
+        assert!(matches!(commands[..], [ControlCommand::Rescan]));
+
+        // A rewrite is read from the start
+        fs::write(&file, "gaming_mode=true\n").unwrap();
+        let commands = control.poll_commands();
+        assert!(matches!(commands[..], [ControlCommand::GamingMode(true)]));
+
+        fs::remove_dir_all(&dir).ok();
+    }
 }
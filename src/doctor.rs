@@ -0,0 +1,546 @@
+// SPDX-License-Identifier: GPL-2.0
+//
+// GhostBrew - System Prerequisite Checks (--doctor)
+//
+// Runs the same environment probes as the integration tests and reports
+// each as PASS/WARN/FAIL with a remediation hint. Only hard requirements
+// (sched-ext, BTF) turn the overall result into a failure.
+//
+// Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
+
+use crate::gpu;
+use crate::loader;
+use crate::pbo;
+use anyhow::{Result, bail};
+use log::{info, warn};
+use std::fs;
+use std::path::Path;
+
+/// Outcome of a single check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl std::fmt::Display for CheckStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CheckStatus::Pass => write!(f, "PASS"),
+            CheckStatus::Warn => write!(f, "WARN"),
+            CheckStatus::Fail => write!(f, "FAIL"),
+        }
+    }
+}
+
+/// Result of a single prerequisite check
+#[derive(Debug, Clone)]
+pub struct CheckResult {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+    /// How to fix it (shown for WARN/FAIL)
+    pub hint: Option<&'static str>,
+}
+
+impl CheckResult {
+    fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Pass,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    fn warn(name: &'static str, detail: impl Into<String>, hint: &'static str) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Warn,
+            detail: detail.into(),
+            hint: Some(hint),
+        }
+    }
+
+    fn fail(name: &'static str, detail: impl Into<String>, hint: &'static str) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Fail,
+            detail: detail.into(),
+            hint: Some(hint),
+        }
+    }
+}
+
+/// Check whether any hard requirement failed
+pub fn has_failures(results: &[CheckResult]) -> bool {
+    results.iter().any(|r| r.status == CheckStatus::Fail)
+}
+
+fn read_trim(path: &str) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+/// Interpreted /sys/kernel/sched_ext state
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchedExtState {
+    /// Kernel built without sched_ext (no /sys/kernel/sched_ext)
+    Unsupported,
+    /// sysfs directory present but no state reported: subsystem disabled
+    Disabled,
+    /// No BPF scheduler attached, ready to load ("disabled" in sysfs)
+    Idle,
+    /// Another BPF scheduler is attached
+    Attached,
+    /// A scheduler is being enabled or torn down
+    Transitioning(String),
+    /// State string this version doesn't know
+    Unknown(String),
+}
+
+/// Interpret the sched_ext sysfs directory and state file contents
+///
+/// The kernel reports "disabled" when no BPF scheduler is attached, which is
+/// the normal idle state; a missing or empty state file is what indicates
+/// the subsystem itself is unusable.
+pub fn interpret_sched_ext_state(dir_exists: bool, state: Option<&str>) -> SchedExtState {
+    if !dir_exists {
+        return SchedExtState::Unsupported;
+    }
+
+    match state.map(str::trim) {
+        None | Some("") => SchedExtState::Disabled,
+        Some("disabled") => SchedExtState::Idle,
+        Some("enabled") => SchedExtState::Attached,
+        Some(s @ ("enabling" | "disabling")) => SchedExtState::Transitioning(s.to_string()),
+        Some(s) => SchedExtState::Unknown(s.to_string()),
+    }
+}
+
+/// Read the current sched_ext state from sysfs
+pub fn sched_ext_state() -> SchedExtState {
+    interpret_sched_ext_state(
+        Path::new("/sys/kernel/sched_ext").exists(),
+        read_trim("/sys/kernel/sched_ext/state").as_deref(),
+    )
+}
+
+/// Parse /sys/kernel/sched_ext/root/ops (empty when nothing is attached)
+pub fn parse_ops_name(content: &str) -> Option<String> {
+    let name = content.trim();
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// Name of the attached BPF scheduler, if any
+pub fn attached_scheduler() -> Option<String> {
+    fs::read_to_string("/sys/kernel/sched_ext/root/ops")
+        .ok()
+        .and_then(|content| parse_ops_name(&content))
+}
+
+/// Check whether an attached ops name is GhostBrew's struct_ops
+pub fn is_ghostbrew_ops(name: &str) -> bool {
+    name == crate::SCHEDULER_NAME
+}
+
+/// Standard bpffs mount point (pins and libbpf's default paths live here)
+pub const BPF_FS_PATH: &str = "/sys/fs/bpf";
+
+/// bpffs mount state from /proc/mounts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BpfFsState {
+    /// Mounted at BPF_FS_PATH
+    Mounted,
+    /// Mounted, but only at another path
+    MountedElsewhere(String),
+    NotMounted,
+}
+
+/// Interpret /proc/mounts content ("source target fstype options ...")
+pub fn interpret_bpf_fs_mounts(mounts: &str) -> BpfFsState {
+    let targets: Vec<&str> = mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let target = fields.nth(1)?;
+            (fields.next()? == "bpf").then_some(target)
+        })
+        .collect();
+
+    if targets.contains(&BPF_FS_PATH) {
+        BpfFsState::Mounted
+    } else if let Some(target) = targets.first() {
+        BpfFsState::MountedElsewhere(target.to_string())
+    } else {
+        BpfFsState::NotMounted
+    }
+}
+
+/// Read the current bpffs mount state
+pub fn bpf_fs_state() -> BpfFsState {
+    fs::read_to_string("/proc/mounts")
+        .map(|mounts| interpret_bpf_fs_mounts(&mounts))
+        .unwrap_or(BpfFsState::NotMounted)
+}
+
+/// Make sure bpffs is mounted at BPF_FS_PATH before the BPF load
+///
+/// Mounts it when missing (the scheduler runs as root) and re-reads the
+/// mount table; fails with the manual command if that didn't work.
+pub fn ensure_bpf_fs() -> Result<()> {
+    let state = bpf_fs_state();
+    if state == BpfFsState::Mounted {
+        return Ok(());
+    }
+    if let BpfFsState::MountedElsewhere(target) = &state {
+        warn!("bpffs is mounted at {}, not {}", target, BPF_FS_PATH);
+    }
+
+    info!("Mounting bpffs at {}", BPF_FS_PATH);
+    // SAFETY: all pointers are NUL-terminated literals; no mount data
+    let ret = unsafe {
+        libc::mount(
+            c"bpf".as_ptr(),
+            c"/sys/fs/bpf".as_ptr(),
+            c"bpf".as_ptr(),
+            0,
+            std::ptr::null(),
+        )
+    };
+    if ret != 0 {
+        let err = std::io::Error::last_os_error();
+        bail!(
+            "BPF filesystem is not mounted at {} and mounting it failed ({}); \
+             run: mount -t bpf bpf {}",
+            BPF_FS_PATH,
+            err,
+            BPF_FS_PATH
+        );
+    }
+    if bpf_fs_state() != BpfFsState::Mounted {
+        bail!(
+            "BPF filesystem still not mounted at {}; run: mount -t bpf bpf {}",
+            BPF_FS_PATH,
+            BPF_FS_PATH
+        );
+    }
+    Ok(())
+}
+
+fn check_sched_ext() -> CheckResult {
+    match sched_ext_state() {
+        SchedExtState::Unsupported => CheckResult::fail(
+            "sched-ext",
+            "/sys/kernel/sched_ext not found",
+            "use a kernel built with CONFIG_SCHED_CLASS_EXT=y (6.12+, e.g. CachyOS)",
+        ),
+        SchedExtState::Disabled => CheckResult::fail(
+            "sched-ext",
+            "present but disabled (no state reported)",
+            "a BPF scheduler failed to load its struct_ops or was ejected; check `dmesg | grep sched_ext`",
+        ),
+        SchedExtState::Attached => match attached_scheduler() {
+            Some(ops) if is_ghostbrew_ops(&ops) => {
+                CheckResult::pass("sched-ext", format!("GhostBrew is attached ({})", ops))
+            }
+            ops => CheckResult::warn(
+                "sched-ext",
+                format!(
+                    "another scheduler is attached ({})",
+                    ops.unwrap_or_else(|| "unknown".to_string())
+                ),
+                "stop it first (e.g. systemctl stop scx_loader) before starting GhostBrew",
+            ),
+        },
+        SchedExtState::Transitioning(state) => CheckResult::warn(
+            "sched-ext",
+            format!("state: {}", state),
+            "a scheduler is loading or unloading; retry in a few seconds",
+        ),
+        SchedExtState::Idle => CheckResult::pass("sched-ext", "available (no scheduler attached)"),
+        SchedExtState::Unknown(state) => {
+            CheckResult::pass("sched-ext", format!("available (state: {})", state))
+        }
+    }
+}
+
+fn check_btf() -> CheckResult {
+    match fs::metadata("/sys/kernel/btf/vmlinux") {
+        Ok(meta) => CheckResult::pass(
+            "BTF vmlinux",
+            format!("{:.1} MB", meta.len() as f64 / 1024.0 / 1024.0),
+        ),
+        Err(_) => CheckResult::fail(
+            "BTF vmlinux",
+            "/sys/kernel/btf/vmlinux not found",
+            "rebuild the kernel with CONFIG_DEBUG_INFO_BTF=y",
+        ),
+    }
+}
+
+fn check_bpf_fs() -> CheckResult {
+    match bpf_fs_state() {
+        BpfFsState::Mounted => CheckResult::pass("BPF filesystem", "mounted"),
+        BpfFsState::MountedElsewhere(target) => CheckResult::warn(
+            "BPF filesystem",
+            format!("mounted at {}, not {}", target, BPF_FS_PATH),
+            "mount -t bpf bpf /sys/fs/bpf",
+        ),
+        BpfFsState::NotMounted => CheckResult::warn(
+            "BPF filesystem",
+            "bpffs not mounted",
+            "mount -t bpf bpf /sys/fs/bpf (the scheduler mounts it when run as root)",
+        ),
+    }
+}
+
+fn check_cgroup_v2() -> CheckResult {
+    if Path::new("/sys/fs/cgroup/cgroup.controllers").exists() {
+        CheckResult::pass("cgroup v2", "unified hierarchy")
+    } else {
+        CheckResult::warn(
+            "cgroup v2",
+            "cgroup v1 or hybrid mode",
+            "boot with systemd.unified_cgroup_hierarchy=1 for cgroup-based classification",
+        )
+    }
+}
+
+/// PCIe link of each discrete GPU
+fn check_gpu_links() -> Vec<CheckResult> {
+    let gpus = gpu::detect_gpus().unwrap_or_default();
+    gpus.iter()
+        .filter(|g| g.kind == gpu::GpuKind::Discrete)
+        .map(|g| {
+            if g.link_degraded {
+                CheckResult::warn(
+                    "GPU PCIe link",
+                    format!("{}: {}", g.pci_address, g.link_summary()),
+                    "check under load (idle GPUs lower the link speed); a narrow link \
+                     points at the slot, a riser or BIOS lane bifurcation",
+                )
+            } else {
+                CheckResult::pass(
+                    "GPU PCIe link",
+                    format!("{}: {} {}", g.pci_address, g.pcie_speed, g.pcie_width),
+                )
+            }
+        })
+        .collect()
+}
+
+fn is_amd_cpu() -> bool {
+    fs::read_to_string("/proc/cpuinfo").is_ok_and(|info| info.contains("AuthenticAMD"))
+}
+
+fn check_amd_pstate() -> CheckResult {
+    match pbo::detect_pstate_mode() {
+        pbo::PstateMode::Active => {}
+        mode => {
+            return CheckResult::warn(
+                "amd_pstate",
+                format!("mode: {:?}", mode),
+                "boot with amd_pstate=active for EPP hints",
+            );
+        }
+    }
+
+    match read_trim("/sys/devices/system/cpu/amd_pstate/prefcore").as_deref() {
+        Some("enabled") => CheckResult::pass("amd_pstate", "active, prefcore enabled"),
+        _ => CheckResult::warn(
+            "amd_pstate",
+            "active, prefcore disabled",
+            "enable CPPC in BIOS and remove amd_prefcore=disable from the kernel cmdline",
+        ),
+    }
+}
+
+fn check_cpufreq() -> CheckResult {
+    let Some(cpufreq) = pbo::detect_cpufreq() else {
+        return CheckResult::warn(
+            "cpufreq",
+            "no cpufreq driver",
+            "EPP hints need amd-pstate-epp or intel_pstate",
+        );
+    };
+
+    let detail = format!("{} (governor {})", cpufreq.driver, cpufreq.governor);
+    match cpufreq.epp_unavailable_reason() {
+        None => CheckResult::pass("cpufreq", detail),
+        Some(reason) => CheckResult::warn(
+            "cpufreq",
+            format!("{}: {}", detail, reason),
+            "boot with amd_pstate=active or intel_pstate=active and use the powersave governor for EPP hints",
+        ),
+    }
+}
+
+fn check_x3d() -> Option<CheckResult> {
+    let model = fs::read_to_string("/proc/cpuinfo")
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("model name"))
+        .and_then(|line| line.split_once(':'))
+        .map(|(_, name)| name.trim().to_string())?;
+
+    let driver = Path::new("/sys/bus/platform/drivers/amd_x3d_vcache");
+    if driver.exists() {
+        return Some(CheckResult::pass(
+            "X3D V-Cache",
+            format!("{} (driver loaded)", model),
+        ));
+    }
+
+    model.contains("X3D").then(|| {
+        CheckResult::warn(
+            "X3D V-Cache",
+            format!("{} without amd_x3d_vcache driver", model),
+            "modprobe amd_3d_vcache (kernel 6.13+) to enable V-Cache mode switching",
+        )
+    })
+}
+
+fn check_root() -> CheckResult {
+    if nix::unistd::Uid::effective().is_root() {
+        CheckResult::pass("root", "running as root")
+    } else {
+        CheckResult::warn(
+            "root",
+            "not running as root",
+            "the scheduler itself must be started as root",
+        )
+    }
+}
+
+fn check_scx_loader() -> CheckResult {
+    match loader::active_loader() {
+        None => CheckResult::pass("scx loader", "no scx_loader/scx.service active"),
+        Some(unit) => CheckResult::warn(
+            "scx loader",
+            format!("{} is active and may load its own scheduler", unit),
+            "disable it (systemctl disable --now scx_loader) or start GhostBrew with --take-over",
+        ),
+    }
+}
+
+/// Run all prerequisite checks
+pub fn run_checks() -> Vec<CheckResult> {
+    let mut results = vec![
+        check_sched_ext(),
+        check_btf(),
+        check_bpf_fs(),
+        check_cgroup_v2(),
+    ];
+
+    results.push(check_cpufreq());
+    results.extend(check_gpu_links());
+    if is_amd_cpu() {
+        results.push(check_amd_pstate());
+        results.extend(check_x3d());
+    }
+
+    results.push(check_scx_loader());
+    results.push(check_root());
+    results
+}
+
+/// Print checks as a PASS/WARN/FAIL report
+pub fn print_report(results: &[CheckResult]) {
+    println!("GhostBrew doctor");
+    println!();
+    for result in results {
+        println!(
+            "  [{}] {:<16} {}",
+            result.status, result.name, result.detail
+        );
+        if result.status != CheckStatus::Pass
+            && let Some(hint) = result.hint
+        {
+            println!("         {:<16} -> {}", "", hint);
+        }
+    }
+    println!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_has_failures() {
+        let mut results = vec![
+            CheckResult::pass("sched-ext", "available"),
+            CheckResult::warn("cgroup v2", "v1", "hint"),
+        ];
+        assert!(!has_failures(&results));
+
+        results.push(CheckResult::fail("BTF vmlinux", "missing", "hint"));
+        assert!(has_failures(&results));
+    }
+
+    #[test]
+    fn test_interpret_bpf_fs_mounts() {
+        let mounts = "\
+sysfs /sys sysfs rw,nosuid,nodev,noexec,relatime 0 0
+proc /proc proc rw,nosuid,nodev,noexec,relatime 0 0
+bpf /sys/fs/bpf bpf rw,nosuid,nodev,noexec,relatime,mode=700 0 0
+cgroup2 /sys/fs/cgroup cgroup2 rw,nosuid,nodev,noexec,relatime 0 0
+";
+        assert_eq!(interpret_bpf_fs_mounts(mounts), BpfFsState::Mounted);
+
+        let elsewhere = "none /run/bpf bpf rw,relatime 0 0\n";
+        assert_eq!(
+            interpret_bpf_fs_mounts(elsewhere),
+            BpfFsState::MountedElsewhere("/run/bpf".to_string())
+        );
+
+        // A bpf-named source on another filesystem type doesn't count
+        let missing = "bpf /mnt/bpf tmpfs rw 0 0\nsysfs /sys sysfs rw 0 0\n";
+        assert_eq!(interpret_bpf_fs_mounts(missing), BpfFsState::NotMounted);
+        assert_eq!(interpret_bpf_fs_mounts(""), BpfFsState::NotMounted);
+    }
+
+    #[test]
+    fn test_parse_ops_name() {
+        assert_eq!(parse_ops_name("ghostbrew\n"), Some("ghostbrew".to_string()));
+        assert!(is_ghostbrew_ops(&parse_ops_name("ghostbrew\n").unwrap()));
+        assert_eq!(parse_ops_name("lavd\n"), Some("lavd".to_string()));
+        assert!(!is_ghostbrew_ops("lavd"));
+        assert_eq!(parse_ops_name("\n"), None);
+        assert_eq!(parse_ops_name(""), None);
+    }
+
+    #[test]
+    fn test_interpret_sched_ext_state() {
+        assert_eq!(
+            interpret_sched_ext_state(false, None),
+            SchedExtState::Unsupported
+        );
+        assert_eq!(
+            interpret_sched_ext_state(true, None),
+            SchedExtState::Disabled
+        );
+        assert_eq!(
+            interpret_sched_ext_state(true, Some("\n")),
+            SchedExtState::Disabled
+        );
+        // "disabled" is the kernel's word for "no scheduler attached"
+        assert_eq!(
+            interpret_sched_ext_state(true, Some("disabled\n")),
+            SchedExtState::Idle
+        );
+        assert_eq!(
+            interpret_sched_ext_state(true, Some("enabled")),
+            SchedExtState::Attached
+        );
+        assert_eq!(
+            interpret_sched_ext_state(true, Some("disabling")),
+            SchedExtState::Transitioning("disabling".to_string())
+        );
+        assert_eq!(
+            interpret_sched_ext_state(true, Some("bypassing")),
+            SchedExtState::Unknown("bypassing".to_string())
+        );
+    }
+}
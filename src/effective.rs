@@ -0,0 +1,332 @@
+// SPDX-License-Identifier: GPL-2.0
+//
+// GhostBrew - Effective Configuration (--print-config)
+//
+// Resolves the settings precedence chain (built-in defaults, then the config
+// file, then CLI flags) and renders the result for support requests.
+//
+// Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
+
+use crate::Args;
+use crate::classify;
+use crate::config::{DefaultConfig, GhostBrewConfig};
+use crate::reserve;
+use crate::topology::CpuTopology;
+use crate::vcache::SwitchingStrategy;
+use anyhow::{Context, Result};
+use clap::ArgMatches;
+use clap::parser::ValueSource;
+use log::warn;
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// Output format for --print-config
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum ConfigFormat {
+    #[default]
+    Toml,
+    Json,
+}
+
+/// Settings the scheduler actually starts with
+#[derive(Debug, Serialize)]
+pub struct EffectiveConfig {
+    /// Config file that was loaded (None: built-in defaults)
+    pub config_file: Option<PathBuf>,
+    /// "gaming", "work" or "auto"
+    pub mode: &'static str,
+    pub gaming_mode: bool,
+    pub work_mode: bool,
+    pub burst_threshold_ns: u64,
+    pub slice_ns: u64,
+    /// Interactive/batch boundary derived from --interactive-ratio
+    pub interactive_cutoff_ns: u64,
+    /// Preempt kicks per CPU per second (0 = unlimited)
+    pub max_preempt_rate: u16,
+    pub ecore_offload: String,
+    pub stats_interval: u64,
+    pub gaming_scan_secs: u64,
+    pub vm_scan_secs: u64,
+    pub container_scan_secs: u64,
+    pub cgroup_scan_secs: u64,
+    pub gpu_debounce: u32,
+    pub ccd_temp_threshold: u32,
+    /// CPUs reserved for AI workloads (--ai-cpu-reserve)
+    pub ai_cpu_reserve: Option<Vec<u32>>,
+    /// Efficiency CPUs AI and batch work is confined to (--isolate-ai)
+    pub isolate_ai: Option<Vec<u32>>,
+    pub override_pinned: bool,
+    pub export_topology: bool,
+    pub vcache_switching: String,
+    pub profiles_dir: Option<PathBuf>,
+    pub gaming_allow: Vec<String>,
+    pub gaming_deny: Vec<String>,
+    /// Extra browser names from the config (on top of the built-ins)
+    pub gaming_browsers: Vec<String>,
+    /// Extra compositor names from the config (on top of the built-ins)
+    pub gaming_compositors: Vec<String>,
+}
+
+/// Config-backed settings given on the command line
+///
+/// The config file never overrides these, neither at startup nor on reload.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CliSettings {
+    pub burst_threshold: bool,
+    pub slice_ns: bool,
+    pub stats_interval: bool,
+    pub ecore_offload: bool,
+    pub vcache_strategy: bool,
+}
+
+impl CliSettings {
+    pub fn from_matches(matches: &ArgMatches) -> Self {
+        // Whether a flag was given on the command line (not a clap default)
+        let from_cli = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+        Self {
+            burst_threshold: from_cli("burst_threshold"),
+            slice_ns: from_cli("slice_ns"),
+            stats_interval: from_cli("stats_interval"),
+            ecore_offload: from_cli("ecore_offload"),
+            vcache_strategy: from_cli("vcache_strategy"),
+        }
+    }
+}
+
+/// Fill settings the CLI left at their defaults from the config file
+pub fn apply_config(args: &mut Args, cli: CliSettings, config: &GhostBrewConfig) {
+    if !cli.burst_threshold {
+        args.burst_threshold = config.defaults.burst_threshold_ns;
+    }
+    if !cli.slice_ns {
+        args.slice_ns = config.defaults.slice_ns;
+    }
+    if !cli.stats_interval {
+        args.stats_interval = config.defaults.stats_interval.max(1);
+    }
+    if !cli.ecore_offload {
+        args.ecore_offload = config.intel.ecore_offload.clone();
+    }
+    if !cli.vcache_strategy {
+        if SwitchingStrategy::is_known(&config.amd.vcache_switching) {
+            args.vcache_strategy = config.amd.vcache_switching.clone();
+        } else {
+            warn!(
+                "Config: unknown [amd] vcache_switching '{}', using {}",
+                config.amd.vcache_switching, args.vcache_strategy
+            );
+        }
+    }
+}
+
+/// Apply reloaded [defaults] that changed, leaving CLI-set values alone
+///
+/// Returns whether the burst threshold or slice changed.
+pub fn apply_reloaded_defaults(
+    args: &mut Args,
+    cli: CliSettings,
+    old: &DefaultConfig,
+    new: &DefaultConfig,
+) -> bool {
+    let mut tunables_changed = false;
+    if !cli.burst_threshold && old.burst_threshold_ns != new.burst_threshold_ns {
+        args.burst_threshold = new.burst_threshold_ns;
+        tunables_changed = true;
+    }
+    if !cli.slice_ns && old.slice_ns != new.slice_ns {
+        args.slice_ns = new.slice_ns;
+        tunables_changed = true;
+    }
+    if !cli.stats_interval && old.stats_interval != new.stats_interval {
+        args.stats_interval = new.stats_interval.max(1);
+    }
+    tunables_changed
+}
+
+/// Gaming and work mode for the given flags, config and topology
+///
+/// Auto mode enables gaming mode on X3D and Intel hybrid parts unless the
+/// config file turns it off.
+pub fn scheduler_modes(
+    args: &Args,
+    config: &GhostBrewConfig,
+    topology: &CpuTopology,
+) -> (bool, bool) {
+    if args.gaming {
+        (true, false)
+    } else if args.work {
+        (false, true)
+    } else {
+        (
+            config.defaults.gaming_mode && (topology.is_x3d || topology.is_intel_hybrid),
+            false,
+        )
+    }
+}
+
+/// Resolve the effective settings (args must already have the config applied)
+///
+/// Topology-derived values stay unset until `with_topology`.
+pub fn resolve(
+    args: &Args,
+    config: &GhostBrewConfig,
+    config_file: Option<PathBuf>,
+) -> EffectiveConfig {
+    let (reference, ratio) = args.interactive_reference();
+
+    EffectiveConfig {
+        config_file,
+        mode: if args.gaming {
+            "gaming"
+        } else if args.work {
+            "work"
+        } else {
+            "auto"
+        },
+        gaming_mode: args.gaming,
+        work_mode: args.work,
+        burst_threshold_ns: args.burst_threshold,
+        slice_ns: args.slice_ns,
+        interactive_cutoff_ns: classify::interactive_cutoff(reference, ratio),
+        max_preempt_rate: args.max_preempt_rate,
+        ecore_offload: args.ecore_offload.clone(),
+        stats_interval: args.stats_interval,
+        gaming_scan_secs: args.gaming_scan_secs,
+        vm_scan_secs: args.vm_scan_secs,
+        container_scan_secs: args.container_scan_secs,
+        cgroup_scan_secs: args.cgroup_scan_secs,
+        gpu_debounce: args.gpu_debounce,
+        ccd_temp_threshold: args.ccd_temp_threshold,
+        ai_cpu_reserve: None,
+        isolate_ai: None,
+        override_pinned: args.override_pinned,
+        export_topology: args.export_topology,
+        vcache_switching: args.vcache_strategy.clone(),
+        profiles_dir: config.profiles_dir.clone(),
+        gaming_allow: config.gaming.allow.clone(),
+        gaming_deny: config.gaming.deny.clone(),
+        gaming_browsers: config.gaming.browsers.clone(),
+        gaming_compositors: config.gaming.compositors.clone(),
+    }
+}
+
+impl EffectiveConfig {
+    /// Fill in the auto-detected modes and the resolved AI CPU reservation
+    pub fn with_topology(
+        mut self,
+        args: &Args,
+        config: &GhostBrewConfig,
+        topology: &CpuTopology,
+    ) -> Result<Self> {
+        (self.gaming_mode, self.work_mode) = scheduler_modes(args, config, topology);
+
+        self.ai_cpu_reserve = args
+            .ai_cpu_reserve
+            .as_ref()
+            .map(|list| reserve::resolve(list.as_deref(), topology))
+            .transpose()
+            .context("Invalid --ai-cpu-reserve")?
+            .map(|mask| mask.cpus());
+
+        if args.isolate_ai {
+            let mask = reserve::resolve_efficiency(topology).context("Invalid --isolate-ai")?;
+            self.isolate_ai = Some(mask.cpus());
+        }
+
+        Ok(self)
+    }
+}
+
+/// Render the effective settings as TOML or pretty JSON
+pub fn render(effective: &EffectiveConfig, format: ConfigFormat) -> Result<String> {
+    Ok(match format {
+        ConfigFormat::Toml => toml::to_string_pretty(effective)?,
+        ConfigFormat::Json => serde_json::to_string_pretty(effective)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::{CommandFactory, FromArgMatches};
+
+    #[test]
+    fn test_cli_overrides_config_file() {
+        let config: GhostBrewConfig = toml::from_str(
+            r#"
+[defaults]
+burst_threshold_ns = 1500000
+slice_ns = 4000000
+
+[intel]
+ecore_offload = "aggressive"
+
+[amd]
+vcache_switching = "automatc"
+"#,
+        )
+        .unwrap();
+
+        let matches = Args::command()
+            .try_get_matches_from(["scx_ghostbrew", "--slice-ns", "1000000"])
+            .unwrap();
+        let mut args = Args::from_arg_matches(&matches).unwrap();
+        apply_config(&mut args, CliSettings::from_matches(&matches), &config);
+
+        // CLI wins where given, the file fills the rest
+        assert_eq!(args.slice_ns, 1_000_000);
+        assert_eq!(args.burst_threshold, 1_500_000);
+        assert_eq!(args.ecore_offload, "aggressive");
+        assert_eq!(args.stats_interval, 2);
+        // A misspelled strategy keeps the built-in one instead of failing
+        assert_eq!(args.vcache_strategy, "follow_ghost_vcache");
+
+        let printed = render(&resolve(&args, &config, None), ConfigFormat::Toml).unwrap();
+        assert!(printed.contains("slice_ns = 1000000"));
+        assert!(printed.contains("burst_threshold_ns = 1500000"));
+        assert!(printed.contains("ecore_offload = \"aggressive\""));
+    }
+
+    #[test]
+    fn test_reload_keeps_cli_settings() {
+        let old: GhostBrewConfig = toml::from_str("").unwrap();
+        let new: GhostBrewConfig = toml::from_str(
+            r#"
+[defaults]
+burst_threshold_ns = 1500000
+slice_ns = 4000000
+stats_interval = 5
+"#,
+        )
+        .unwrap();
+
+        let matches = Args::command()
+            .try_get_matches_from(["scx_ghostbrew", "--slice-ns", "1000000"])
+            .unwrap();
+        let cli = CliSettings::from_matches(&matches);
+        let mut args = Args::from_arg_matches(&matches).unwrap();
+        apply_config(&mut args, cli, &old);
+
+        // The reloaded file changes what the CLI left alone, never --slice-ns
+        assert!(apply_reloaded_defaults(
+            &mut args,
+            cli,
+            &old.defaults,
+            &new.defaults
+        ));
+        assert_eq!(args.slice_ns, 1_000_000);
+        assert_eq!(args.burst_threshold, 1_500_000);
+        assert_eq!(args.stats_interval, 5);
+
+        // Only the CLI-set value changed: nothing to apply
+        let mut slice_only = old.clone();
+        slice_only.defaults.slice_ns = 4_000_000;
+        assert!(!apply_reloaded_defaults(
+            &mut args,
+            cli,
+            &old.defaults,
+            &slice_only.defaults
+        ));
+        assert_eq!(args.slice_ns, 1_000_000);
+    }
+}
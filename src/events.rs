@@ -4,13 +4,20 @@
 //
 // Consumes events from the BPF ringbuf for real-time visibility
 // into scheduler decisions: gaming detection, migrations, latency spikes, etc.
+// Events can be written to a JSON Lines log (--dump-events) and analyzed
+// offline (--analyze-events).
 //
 // Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
 
+use crate::mangohud::{self, HIST_BUCKETS};
+use anyhow::{Context, Result};
 use libbpf_rs::{RingBuffer, RingBufferBuilder};
 use log::{debug, info, warn};
-use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::io::{BufRead, Write};
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 /// Event types matching BPF side
@@ -21,6 +28,12 @@ pub const EVENT_HIGH_LATENCY: u32 = 4;
 pub const EVENT_CCD_IMBALANCE: u32 = 5;
 pub const EVENT_PROFILE_MATCH: u32 = 6;
 
+/// Dropped events per stats interval above which the stream is called lossy
+pub const DROP_WARN_THRESHOLD: u64 = 100;
+
+/// Default number of recent events kept for the HTTP API and TUI
+pub const DEFAULT_RECENT_EVENTS: usize = 1024;
+
 /// Event structure matching BPF sched_event
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
@@ -122,7 +135,7 @@ pub struct EventCounters {
     pub high_latency: AtomicU64,
     pub ccd_imbalance: AtomicU64,
     pub profile_matches: AtomicU64,
-    #[allow(dead_code)]
+    /// Events the BPF side could not reserve ringbuf space for
     pub dropped: AtomicU64,
 }
 
@@ -143,14 +156,20 @@ impl EventCounters {
         };
     }
 
+    /// Add events lost to a full ringbuf
+    pub fn record_dropped(&self, count: u64) {
+        self.dropped.fetch_add(count, Ordering::Relaxed);
+    }
+
     pub fn summary(&self) -> String {
         format!(
-            "Events: gaming={}, migrations={}, kicks={}, latency={}, imbalance={}",
+            "Events: gaming={}, migrations={}, kicks={}, latency={}, imbalance={}, dropped={}",
             self.gaming_detected.load(Ordering::Relaxed),
             self.vcache_migrations.load(Ordering::Relaxed),
             self.preempt_kicks.load(Ordering::Relaxed),
             self.high_latency.load(Ordering::Relaxed),
             self.ccd_imbalance.load(Ordering::Relaxed),
+            self.dropped.load(Ordering::Relaxed),
         )
     }
 }
@@ -159,16 +178,30 @@ impl EventCounters {
 pub struct EventHandler {
     pub counters: Arc<EventCounters>,
     pub verbose: bool,
+    /// Last `recent_capacity` events, oldest first
+    recent: Mutex<VecDeque<SchedEvent>>,
+    recent_capacity: usize,
+    /// JSON Lines event log (--dump-events)
+    dump: Mutex<Option<Box<dyn Write + Send>>>,
 }
 
 impl EventHandler {
-    pub fn new(verbose: bool) -> Self {
+    /// Handler keeping the last `capacity` events for recent()/recent_events()
+    pub fn new(verbose: bool, capacity: usize) -> Self {
         Self {
             counters: Arc::new(EventCounters::new()),
             verbose,
+            recent: Mutex::new(VecDeque::with_capacity(capacity)),
+            recent_capacity: capacity,
+            dump: Mutex::new(None),
         }
     }
 
+    /// Append every event to `writer`, one EventRecord JSON object per line
+    pub fn set_dump(&mut self, writer: impl Write + Send + 'static) {
+        self.dump = Mutex::new(Some(Box::new(writer)));
+    }
+
     /// Process a single event
     pub fn handle_event(&self, data: &[u8]) -> i32 {
         if data.len() < std::mem::size_of::<SchedEvent>() {
@@ -181,6 +214,8 @@ impl EventHandler {
 
         // Record in counters
         self.counters.record(event);
+        self.push_recent(event);
+        self.write_dump(event);
 
         // Log if verbose
         if self.verbose {
@@ -191,6 +226,227 @@ impl EventHandler {
 
         0 // Continue processing
     }
+
+    /// Keep an event in the recent ring, evicting the oldest when full
+    fn push_recent(&self, event: &SchedEvent) {
+        if self.recent_capacity == 0 {
+            return;
+        }
+        if let Ok(mut recent) = self.recent.lock() {
+            if recent.len() == self.recent_capacity {
+                recent.pop_front();
+            }
+            recent.push_back(*event);
+        }
+    }
+
+    /// Write an event to the dump; a failed write stops the dump
+    fn write_dump(&self, event: &SchedEvent) {
+        let Ok(mut dump) = self.dump.lock() else {
+            return;
+        };
+        let Some(writer) = dump.as_mut() else {
+            return;
+        };
+        let result = serde_json::to_writer(&mut *writer, &EventRecord::from(event))
+            .map_err(std::io::Error::from)
+            .and_then(|()| writeln!(writer));
+        if let Err(e) = result {
+            warn!("Failed to write event dump, no longer dumping: {}", e);
+            *dump = None;
+        }
+    }
+
+    /// The last `n` events formatted for display, newest first
+    #[cfg_attr(not(feature = "tui"), allow(dead_code))]
+    pub fn recent(&self, n: usize) -> Vec<String> {
+        let Ok(recent) = self.recent.lock() else {
+            return Vec::new();
+        };
+        recent
+            .iter()
+            .rev()
+            .take(n)
+            .map(SchedEvent::format)
+            .collect()
+    }
+
+    /// Task with the most HighLatency events among the recent ones
+    pub fn top_high_latency_pid(&self) -> Option<u32> {
+        let Ok(recent) = self.recent.lock() else {
+            return None;
+        };
+        let mut counts: HashMap<u32, usize> = HashMap::new();
+        for event in recent.iter().filter(|e| e.event_type == EVENT_HIGH_LATENCY) {
+            *counts.entry(event.pid).or_default() += 1;
+        }
+        counts
+            .into_iter()
+            .max_by_key(|&(pid, count)| (count, std::cmp::Reverse(pid)))
+            .map(|(pid, _)| pid)
+    }
+
+    /// The most recent events (at most `limit`), oldest first
+    pub fn recent_events(&self, limit: Option<usize>) -> Vec<EventRecord> {
+        let Ok(recent) = self.recent.lock() else {
+            return Vec::new();
+        };
+        let skip = limit.map_or(0, |limit| recent.len().saturating_sub(limit));
+        recent.iter().skip(skip).map(EventRecord::from).collect()
+    }
+}
+
+/// One line of a JSON Lines event log (fields of SchedEvent, comm as a string)
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EventRecord {
+    #[serde(default)]
+    timestamp_ns: u64,
+    event_type: u32,
+    #[serde(default)]
+    pid: u32,
+    #[serde(default)]
+    cpu: u32,
+    #[serde(default)]
+    ccd: u32,
+    #[serde(default)]
+    value1: u64,
+    #[serde(default)]
+    value2: u64,
+    #[serde(default)]
+    comm: String,
+}
+
+impl From<&SchedEvent> for EventRecord {
+    fn from(event: &SchedEvent) -> Self {
+        Self {
+            timestamp_ns: event.timestamp_ns,
+            event_type: event.event_type,
+            pid: event.pid,
+            cpu: event.cpu,
+            ccd: event.ccd,
+            value1: event.value1,
+            value2: event.value2,
+            comm: event.comm_str(),
+        }
+    }
+}
+
+impl From<EventRecord> for SchedEvent {
+    fn from(record: EventRecord) -> Self {
+        // Keep the trailing NUL like the kernel's TASK_COMM_LEN buffer
+        let mut comm = [0u8; 16];
+        let bytes = record.comm.as_bytes();
+        let len = bytes.len().min(15);
+        comm[..len].copy_from_slice(&bytes[..len]);
+
+        Self {
+            timestamp_ns: record.timestamp_ns,
+            event_type: record.event_type,
+            pid: record.pid,
+            cpu: record.cpu,
+            ccd: record.ccd,
+            value1: record.value1,
+            value2: record.value2,
+            comm,
+        }
+    }
+}
+
+/// Aggregate statistics over a captured event log
+#[derive(Default)]
+pub struct EventLogAnalysis {
+    pub counters: EventCounters,
+    /// Events with an unknown type
+    pub unknown: u64,
+    /// Lines that failed to parse
+    pub malformed: u64,
+    /// HighLatency events bucketed like the BPF gaming latency histogram
+    pub latency_hist: [u64; HIST_BUCKETS],
+    /// comm -> (HighLatency count, max latency in us)
+    pub latency_comms: HashMap<String, (u64, u64)>,
+    /// (from CCD, to CCD) -> V-Cache migration count
+    pub ccd_migrations: BTreeMap<(u64, u32), u64>,
+}
+
+impl EventLogAnalysis {
+    pub fn record(&mut self, event: &SchedEvent) {
+        self.counters.record(event);
+
+        match event.event_type {
+            EVENT_HIGH_LATENCY => {
+                let latency_us = event.value1;
+                self.latency_hist[mangohud::latency_to_bucket(latency_us)] += 1;
+                let entry = self.latency_comms.entry(event.comm_str()).or_default();
+                entry.0 += 1;
+                entry.1 = entry.1.max(latency_us);
+            }
+            EVENT_VCACHE_MIGRATION => {
+                *self
+                    .ccd_migrations
+                    .entry((event.value1, event.ccd))
+                    .or_default() += 1;
+            }
+            EVENT_GAMING_DETECTED
+            | EVENT_PREEMPT_KICK
+            | EVENT_CCD_IMBALANCE
+            | EVENT_PROFILE_MATCH => {}
+            _ => self.unknown += 1,
+        }
+    }
+
+    /// Total number of parsed events
+    pub fn total(&self) -> u64 {
+        let c = &self.counters;
+        [
+            &c.gaming_detected,
+            &c.vcache_migrations,
+            &c.preempt_kicks,
+            &c.high_latency,
+            &c.ccd_imbalance,
+            &c.profile_matches,
+        ]
+        .iter()
+        .map(|n| n.load(Ordering::Relaxed))
+        .sum::<u64>()
+            + self.unknown
+    }
+
+    /// comms with the most HighLatency events: (comm, count, max latency us)
+    pub fn top_latency_comms(&self, limit: usize) -> Vec<(&str, u64, u64)> {
+        let mut comms: Vec<(&str, u64, u64)> = self
+            .latency_comms
+            .iter()
+            .map(|(comm, &(count, max_us))| (comm.as_str(), count, max_us))
+            .collect();
+        comms.sort_by(|a, b| b.1.cmp(&a.1).then(b.2.cmp(&a.2)).then(a.0.cmp(b.0)));
+        comms.truncate(limit);
+        comms
+    }
+}
+
+/// Analyze a JSON Lines event log, one SchedEvent object per line
+///
+/// Blank lines are ignored; lines that don't parse are counted as malformed.
+pub fn analyze_event_log(reader: impl BufRead) -> Result<EventLogAnalysis> {
+    let mut analysis = EventLogAnalysis::default();
+
+    for (lineno, line) in reader.lines().enumerate() {
+        let line = line.with_context(|| format!("Failed to read event log line {}", lineno + 1))?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        match serde_json::from_str::<EventRecord>(line) {
+            Ok(record) => analysis.record(&record.into()),
+            Err(e) => {
+                debug!("Skipping malformed event on line {}: {}", lineno + 1, e);
+                analysis.malformed += 1;
+            }
+        }
+    }
+
+    Ok(analysis)
 }
 
 /// Build a ringbuf consumer for the events map
@@ -234,6 +490,29 @@ mod tests {
         assert_eq!(event.comm_str(), "game.exe");
     }
 
+    #[test]
+    fn test_dropped_counter() {
+        let counters = EventCounters::new();
+        let event = SchedEvent {
+            timestamp_ns: 0,
+            event_type: EVENT_PREEMPT_KICK,
+            pid: 1,
+            cpu: 0,
+            ccd: 0,
+            value1: 0,
+            value2: 0,
+            comm: [0; 16],
+        };
+
+        counters.record(&event);
+        assert_eq!(counters.preempt_kicks.load(Ordering::Relaxed), 1);
+        assert_eq!(counters.dropped.load(Ordering::Relaxed), 0);
+
+        counters.record_dropped(3);
+        assert_eq!(counters.dropped.load(Ordering::Relaxed), 3);
+        assert!(counters.summary().ends_with("dropped=3"));
+    }
+
     #[test]
     fn test_event_format() {
         let event = SchedEvent {
@@ -251,4 +530,112 @@ mod tests {
         assert!(formatted.contains("2500us"));
         assert!(formatted.contains("1000us"));
     }
+
+    #[test]
+    fn test_analyze_event_log() {
+        let log = r#"{"timestamp_ns":1,"event_type":1,"pid":100,"cpu":0,"ccd":0,"value1":2,"value2":1,"comm":"game.exe"}
+{"timestamp_ns":2,"event_type":2,"pid":100,"cpu":8,"ccd":0,"value1":1,"value2":0,"comm":"game.exe"}
+{"timestamp_ns":3,"event_type":2,"pid":101,"cpu":9,"ccd":0,"value1":1,"value2":0,"comm":"game.exe"}
+{"timestamp_ns":4,"event_type":4,"pid":200,"cpu":3,"ccd":0,"value1":2500,"value2":1000,"comm":"kwin_wayland"}
+{"timestamp_ns":5,"event_type":4,"pid":100,"cpu":1,"ccd":0,"value1":1200,"value2":1000,"comm":"game.exe"}
+{"timestamp_ns":6,"event_type":4,"pid":100,"cpu":1,"ccd":0,"value1":4000,"value2":1000,"comm":"game.exe"}
+
+{"timestamp_ns":7,"event_type":3,"pid":100,"cpu":2,"ccd":0}
+{"timestamp_ns":8,"event_type":99}
+not json
+"#;
+
+        let analysis = analyze_event_log(log.as_bytes()).unwrap();
+        let c = &analysis.counters;
+        assert_eq!(c.gaming_detected.load(Ordering::Relaxed), 1);
+        assert_eq!(c.vcache_migrations.load(Ordering::Relaxed), 2);
+        assert_eq!(c.high_latency.load(Ordering::Relaxed), 3);
+        assert_eq!(c.preempt_kicks.load(Ordering::Relaxed), 1);
+        assert_eq!(c.ccd_imbalance.load(Ordering::Relaxed), 0);
+        assert_eq!(analysis.unknown, 1);
+        assert_eq!(analysis.malformed, 1);
+        assert_eq!(analysis.total(), 8);
+
+        assert_eq!(analysis.ccd_migrations.get(&(1, 0)), Some(&2));
+        assert_eq!(analysis.top_latency_comms(1), vec![("game.exe", 2, 4000)]);
+        // 1.2ms and 2.5ms in 0.8-1.6ms / 1.6-3.2ms, 4ms in 3.2-6.4ms
+        assert_eq!(analysis.latency_hist[4..7], [1, 1, 1]);
+    }
+
+    #[test]
+    fn test_recent_ring_evicts_oldest() {
+        let handler = EventHandler::new(false, 3);
+        for pid in 1..=5u32 {
+            let event = SchedEvent {
+                timestamp_ns: pid as u64,
+                event_type: EVENT_PREEMPT_KICK,
+                pid,
+                cpu: 0,
+                ccd: 0,
+                value1: 0,
+                value2: 0,
+                comm: [0; 16],
+            };
+            let bytes = unsafe {
+                std::slice::from_raw_parts(
+                    &event as *const SchedEvent as *const u8,
+                    std::mem::size_of::<SchedEvent>(),
+                )
+            };
+            handler.handle_event(bytes);
+        }
+
+        // PIDs 1 and 2 were evicted; newest comes first
+        let recent = handler.recent(10);
+        assert_eq!(recent.len(), 3);
+        for (line, pid) in recent.iter().zip([5, 4, 3]) {
+            assert!(
+                line.starts_with(&format!("Preempt kick: PID {} ", pid)),
+                "{}",
+                line
+            );
+        }
+        assert_eq!(handler.recent(1).len(), 1);
+
+        let pids: Vec<u32> = handler.recent_events(None).iter().map(|e| e.pid).collect();
+        assert_eq!(pids, [3, 4, 5]);
+    }
+
+    #[test]
+    fn test_dump_round_trips_through_analysis() {
+        let fixture = crate::procfs::Fixture::new("event-dump");
+        let path = fixture.paths.join("/events.jsonl");
+        let mut handler = EventHandler::new(false, 0);
+        handler.set_dump(std::fs::File::create(&path).unwrap());
+
+        let mut comm = [0u8; 16];
+        comm[..8].copy_from_slice(b"game.exe");
+        for (event_type, value1) in [(EVENT_HIGH_LATENCY, 2500), (EVENT_VCACHE_MIGRATION, 1)] {
+            let event = SchedEvent {
+                timestamp_ns: 1,
+                event_type,
+                pid: 100,
+                cpu: 2,
+                ccd: 0,
+                value1,
+                value2: 1000,
+                comm,
+            };
+            let bytes = unsafe {
+                std::slice::from_raw_parts(
+                    &event as *const SchedEvent as *const u8,
+                    std::mem::size_of::<SchedEvent>(),
+                )
+            };
+            handler.handle_event(bytes);
+        }
+        drop(handler);
+
+        let log = std::fs::read(&path).unwrap();
+        let analysis = analyze_event_log(log.as_slice()).unwrap();
+        assert_eq!(analysis.total(), 2);
+        assert_eq!(analysis.malformed, 0);
+        assert_eq!(analysis.top_latency_comms(1), vec![("game.exe", 1, 2500)]);
+        assert_eq!(analysis.ccd_migrations.get(&(1, 0)), Some(&1));
+    }
 }
@@ -0,0 +1,141 @@
+// SPDX-License-Identifier: GPL-2.0
+//
+// GhostBrew - Detected PID Export (--export-pids)
+//
+// Writes the PIDs GhostBrew has classified to a JSON file after each scan,
+// so GameMode hooks and scripts can follow the classification without
+// reading BPF maps. The file is replaced atomically.
+//
+// Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
+
+use anyhow::{Context, Result};
+use log::{debug, info, warn};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Detected PIDs per class, each sorted ascending
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct PidExport {
+    pub gaming: Vec<u32>,
+    pub batch: Vec<u32>,
+    pub ai: Vec<u32>,
+    pub vm_vcpu: Vec<u32>,
+    pub container: Vec<u32>,
+}
+
+impl PidExport {
+    /// Sort and dedup every list so unchanged state serializes identically
+    pub fn normalized(mut self) -> Self {
+        for pids in [
+            &mut self.gaming,
+            &mut self.batch,
+            &mut self.ai,
+            &mut self.vm_vcpu,
+            &mut self.container,
+        ] {
+            pids.sort_unstable();
+            pids.dedup();
+        }
+        self
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).context("Failed to serialize PID export")
+    }
+}
+
+/// Replace `path` with `contents` via a temp file in the same directory
+pub fn write_atomic(path: &Path, contents: &str) -> Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+
+    fs::write(&tmp, contents).with_context(|| format!("Failed to write {}", tmp.display()))?;
+    fs::rename(&tmp, path).with_context(|| format!("Failed to replace {}", path.display()))
+}
+
+/// Writes the export file whenever the detected PIDs change
+pub struct PidExporter {
+    path: PathBuf,
+    /// Last JSON written (None: nothing written yet)
+    last: Option<String>,
+    /// A write failed and was logged; quiet until one succeeds again
+    failing: bool,
+}
+
+impl PidExporter {
+    pub fn new(path: PathBuf) -> Self {
+        info!("Exporting detected PIDs to {}", path.display());
+        Self {
+            path,
+            last: None,
+            failing: false,
+        }
+    }
+
+    /// Write the current PIDs if they differ from the last write
+    pub fn write(&mut self, pids: PidExport) {
+        let json = match pids.normalized().to_json() {
+            Ok(json) => json,
+            Err(e) => {
+                debug!("PID export: {:#}", e);
+                return;
+            }
+        };
+        if self.last.as_ref() == Some(&json) {
+            return;
+        }
+
+        match write_atomic(&self.path, &json) {
+            Ok(()) => {
+                self.last = Some(json);
+                self.failing = false;
+            }
+            Err(e) => {
+                if !self.failing {
+                    warn!("PID export failed: {:#}", e);
+                    self.failing = true;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pid_export_json() {
+        let pids = PidExport {
+            gaming: vec![4242, 4100, 4242],
+            batch: vec![],
+            ai: vec![9001],
+            vm_vcpu: vec![3001, 3000],
+            container: vec![7000],
+        };
+
+        let json = pids.normalized().to_json().unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["gaming"], serde_json::json!([4100, 4242]));
+        assert_eq!(value["batch"], serde_json::json!([]));
+        assert_eq!(value["ai"], serde_json::json!([9001]));
+        assert_eq!(value["vm_vcpu"], serde_json::json!([3000, 3001]));
+        assert_eq!(value["container"], serde_json::json!([7000]));
+
+        let dir = std::env::temp_dir().join(format!("ghostbrew-export-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("pids.json");
+        let mut exporter = PidExporter::new(path.clone());
+        exporter.write(PidExport {
+            ai: vec![9001],
+            ..Default::default()
+        });
+        let written: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(written["ai"], serde_json::json!([9001]));
+        assert!(!dir.join("pids.json.tmp").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
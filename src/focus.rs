@@ -0,0 +1,333 @@
+// SPDX-License-Identifier: GPL-2.0
+//
+// GhostBrew - Focus-Aware Gaming Tier (--focus-aware)
+//
+// Finds the process that owns the focused window and keeps only that game
+// (with its parent and child processes) in the gaming tier; other detected
+// gaming processes drop to interactive until they get focus.
+//
+// The display session comes from a game's own environment. Hyprland and Sway
+// are asked over their IPC (hyprctl, swaymsg); anything with DISPLAY (X11, or
+// Xwayland under other compositors) through xprop's _NET_ACTIVE_WINDOW.
+// Each query is killed after FOCUS_QUERY_TIMEOUT, so a hung compositor can't
+// stall the main loop.
+//
+// Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
+
+use crate::gaming::{WORKLOAD_GAMING, WORKLOAD_INTERACTIVE};
+use crate::procfs;
+use log::debug;
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Longest a focus helper (hyprctl, swaymsg, xprop) may take
+pub const FOCUS_QUERY_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// Environment variables that locate the user's display session
+const SESSION_VARS: &[&str] = &[
+    "DISPLAY",
+    "XAUTHORITY",
+    "WAYLAND_DISPLAY",
+    "XDG_RUNTIME_DIR",
+    "HYPRLAND_INSTANCE_SIGNATURE",
+    "SWAYSOCK",
+];
+
+/// Session variables from a raw (NUL-separated) environ block
+pub fn session_env(environ: &str) -> Vec<(String, String)> {
+    environ
+        .split('\0')
+        .filter_map(|var| var.split_once('='))
+        .filter(|(name, value)| SESSION_VARS.contains(name) && !value.is_empty())
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+}
+
+/// Where the focused window is asked for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FocusSource {
+    Hyprland,
+    Sway,
+    X11,
+}
+
+/// Focus source for a session environment
+pub fn focus_source(env: &[(String, String)]) -> Option<FocusSource> {
+    let has = |name: &str| env.iter().any(|(n, _)| n == name);
+    if has("HYPRLAND_INSTANCE_SIGNATURE") {
+        Some(FocusSource::Hyprland)
+    } else if has("SWAYSOCK") {
+        Some(FocusSource::Sway)
+    } else if has("DISPLAY") {
+        Some(FocusSource::X11)
+    } else {
+        None
+    }
+}
+
+/// PID from `hyprctl activewindow -j`
+pub fn parse_hyprctl_pid(json: &str) -> Option<u32> {
+    let window: serde_json::Value = serde_json::from_str(json).ok()?;
+    window["pid"].as_u64()?.try_into().ok()
+}
+
+/// PID of the focused node in `swaymsg -t get_tree`
+pub fn parse_sway_focused_pid(json: &str) -> Option<u32> {
+    fn find(node: &serde_json::Value) -> Option<u32> {
+        if node["focused"].as_bool() == Some(true) {
+            return node["pid"].as_u64()?.try_into().ok();
+        }
+        ["nodes", "floating_nodes"]
+            .iter()
+            .filter_map(|key| node[key].as_array())
+            .flatten()
+            .find_map(find)
+    }
+    find(&serde_json::from_str(json).ok()?)
+}
+
+/// Window ID from `xprop -root _NET_ACTIVE_WINDOW` (None when nothing is focused)
+pub fn parse_xprop_window(output: &str) -> Option<String> {
+    let id = output.rsplit_once('#')?.1.trim();
+    let id = id.split(',').next()?.trim();
+    (id.starts_with("0x") && id != "0x0").then(|| id.to_string())
+}
+
+/// PID from `xprop -id <window> _NET_WM_PID`
+pub fn parse_xprop_pid(output: &str) -> Option<u32> {
+    output.rsplit_once('=')?.1.trim().parse().ok()
+}
+
+/// Run a helper inside the session environment, None on any failure or
+/// once it has run longer than FOCUS_QUERY_TIMEOUT
+fn run(program: &str, args: &[&str], env: &[(String, String)]) -> Option<String> {
+    let mut child = Command::new(program)
+        .args(args)
+        .envs(env.iter().map(|(k, v)| (k.as_str(), v.as_str())))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .inspect_err(|e| debug!("Focus: {} failed: {}", program, e))
+        .ok()?;
+
+    // Drain stdout on the side so a large sway tree can't fill the pipe
+    let mut stdout = child.stdout.take()?;
+    let reader = thread::spawn(move || {
+        let mut out = Vec::new();
+        stdout.read_to_end(&mut out).ok();
+        out
+    });
+
+    let deadline = Instant::now() + FOCUS_QUERY_TIMEOUT;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() >= deadline => {
+                debug!("Focus: {} timed out", program);
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+            Ok(None) => thread::sleep(Duration::from_millis(5)),
+            Err(_) => return None,
+        }
+    };
+    let out = reader.join().ok()?;
+    status
+        .success()
+        .then(|| String::from_utf8_lossy(&out).into_owned())
+}
+
+/// PID owning the focused window in the session described by `env`
+pub fn focused_pid(env: &[(String, String)]) -> Option<u32> {
+    match focus_source(env)? {
+        FocusSource::Hyprland => parse_hyprctl_pid(&run("hyprctl", &["activewindow", "-j"], env)?),
+        FocusSource::Sway => {
+            parse_sway_focused_pid(&run("swaymsg", &["-t", "get_tree", "-r"], env)?)
+        }
+        FocusSource::X11 => {
+            let window = parse_xprop_window(&run("xprop", &["-root", "_NET_ACTIVE_WINDOW"], env)?)?;
+            parse_xprop_pid(&run("xprop", &["-id", &window, "_NET_WM_PID"], env)?)
+        }
+    }
+}
+
+/// Parent PID of a live process
+pub fn parent_pid(pid: u32) -> Option<u32> {
+    procfs::parse_stat_ppid(&procfs::read_pid_file(pid, "stat")?)
+}
+
+/// Class each gaming PID should have given the focused PID
+///
+/// The focused game is the focused process plus the gaming processes above
+/// and below it in the process tree. Unknown focus keeps every game in the
+/// gaming tier.
+pub fn focus_tiers(
+    focused: Option<u32>,
+    gaming: &[u32],
+    parent: impl Fn(u32) -> Option<u32>,
+) -> HashMap<u32, u32> {
+    let Some(focused) = focused else {
+        return gaming.iter().map(|&pid| (pid, WORKLOAD_GAMING)).collect();
+    };
+
+    // Ancestor chain of a PID (bounded in case of a cycle from PID reuse)
+    let ancestors = |pid: u32| -> Vec<u32> {
+        std::iter::successors(parent(pid), |&p| parent(p))
+            .take_while(|&p| p > 1)
+            .take(64)
+            .collect()
+    };
+    let above: HashSet<u32> = ancestors(focused).into_iter().collect();
+
+    gaming
+        .iter()
+        .map(|&pid| {
+            let in_focus =
+                pid == focused || above.contains(&pid) || ancestors(pid).contains(&focused);
+            (
+                pid,
+                if in_focus {
+                    WORKLOAD_GAMING
+                } else {
+                    WORKLOAD_INTERACTIVE
+                },
+            )
+        })
+        .collect()
+}
+
+/// Tracks which gaming PIDs are demoted for being out of focus
+#[derive(Default)]
+pub struct FocusTracker {
+    demoted: HashSet<u32>,
+    /// Focused PID that last put a game in focus
+    last_game: Option<u32>,
+}
+
+impl FocusTracker {
+    /// Classes to write for PIDs whose tier changed
+    ///
+    /// Focus on a window that isn't a game (chat, browser, the desktop)
+    /// leaves the last focused game in the gaming tier.
+    pub fn update(
+        &mut self,
+        focused: Option<u32>,
+        gaming: &[u32],
+        parent: impl Fn(u32) -> Option<u32>,
+    ) -> Vec<(u32, u32)> {
+        self.demoted.retain(|pid| gaming.contains(pid));
+        let has_game = |tiers: &HashMap<u32, u32>| tiers.values().any(|&c| c == WORKLOAD_GAMING);
+        let mut tiers = focus_tiers(focused, gaming, &parent);
+        if has_game(&tiers) {
+            self.last_game = focused;
+        } else {
+            tiers = focus_tiers(self.last_game, gaming, &parent);
+            if !has_game(&tiers) {
+                // The last focused game is gone too
+                self.last_game = None;
+                tiers = focus_tiers(None, gaming, &parent);
+            }
+        }
+
+        let mut changes: Vec<(u32, u32)> = tiers
+            .into_iter()
+            .filter(|&(pid, class)| {
+                if class == WORKLOAD_INTERACTIVE {
+                    self.demoted.insert(pid)
+                } else {
+                    self.demoted.remove(&pid)
+                }
+            })
+            .collect();
+        changes.sort();
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_focus_tiers() {
+        // steam (100) -> reaper (200) -> game.exe (300) -> crash handler (301);
+        // a second game (400) runs elsewhere
+        let parents = HashMap::from([(200, 100), (300, 200), (301, 300), (400, 1)]);
+        let parent = |pid: u32| parents.get(&pid).copied();
+        let gaming = [100, 300, 301, 400];
+
+        let tiers = focus_tiers(Some(300), &gaming, parent);
+        assert_eq!(tiers[&100], WORKLOAD_GAMING);
+        assert_eq!(tiers[&300], WORKLOAD_GAMING);
+        assert_eq!(tiers[&301], WORKLOAD_GAMING);
+        assert_eq!(tiers[&400], WORKLOAD_INTERACTIVE);
+
+        // Unknown focus demotes nothing
+        assert!(
+            focus_tiers(None, &gaming, parent)
+                .values()
+                .all(|&class| class == WORKLOAD_GAMING)
+        );
+
+        // Only tier changes are written
+        let mut tracker = FocusTracker::default();
+        assert_eq!(
+            tracker.update(Some(300), &gaming, parent),
+            vec![(400, WORKLOAD_INTERACTIVE)]
+        );
+        assert!(tracker.update(Some(300), &gaming, parent).is_empty());
+        assert_eq!(
+            tracker.update(Some(400), &gaming, parent),
+            vec![
+                (100, WORKLOAD_INTERACTIVE),
+                (300, WORKLOAD_INTERACTIVE),
+                (301, WORKLOAD_INTERACTIVE),
+                (400, WORKLOAD_GAMING),
+            ]
+        );
+
+        // Alt-tab to a chat window (999, not a game): game 400 keeps the tier
+        assert!(tracker.update(Some(999), &gaming, parent).is_empty());
+        // Once that game exits too, nothing stays demoted
+        assert_eq!(
+            tracker.update(Some(999), &[100, 300, 301], parent),
+            vec![
+                (100, WORKLOAD_GAMING),
+                (300, WORKLOAD_GAMING),
+                (301, WORKLOAD_GAMING),
+            ]
+        );
+
+        assert_eq!(
+            parse_xprop_window("_NET_ACTIVE_WINDOW(WINDOW): window id # 0x3e00007\n"),
+            Some("0x3e00007".to_string())
+        );
+        assert_eq!(
+            parse_xprop_window("_NET_ACTIVE_WINDOW(WINDOW): window id # 0x0\n"),
+            None
+        );
+        assert_eq!(
+            parse_xprop_pid("_NET_WM_PID(CARDINAL) = 4242\n"),
+            Some(4242)
+        );
+        assert_eq!(
+            parse_sway_focused_pid(
+                r#"{"focused":false,"nodes":[{"focused":false,"nodes":[],"floating_nodes":[{"focused":true,"pid":77}]}]}"#
+            ),
+            Some(77)
+        );
+    }
+
+    #[test]
+    fn test_hung_helper_times_out() {
+        let start = Instant::now();
+        assert_eq!(run("sleep", &["5"], &[]), None);
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert_eq!(run("echo", &["4242"], &[]).as_deref(), Some("4242\n"));
+    }
+}
@@ -0,0 +1,130 @@
+// SPDX-License-Identifier: GPL-2.0
+//
+// GhostBrew - Feral GameMode Coordination
+//
+// gamemoded switches the governor and EPP itself while a game has GameMode
+// active. Then GhostBrew hands EPP back to it instead of fighting over the
+// same sysfs files; --epp-over-gamemode keeps GhostBrew's hints anyway. A
+// running daemon alone changes nothing: GameMode is active while a client
+// (a game started with gamemoderun, or one linking libgamemode) is alive.
+//
+// Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
+
+use crate::procfs;
+use log::info;
+use std::collections::HashMap;
+
+/// comm of Feral's GameMode daemon
+pub const GAMEMODED_COMM: &str = "gamemoded";
+
+/// Whether a /proc/[pid]/maps dump has the GameMode client library mapped
+/// (libgamemodeauto from gamemoderun, or libgamemode loaded by the game)
+pub fn maps_gamemode_client(maps: &str) -> bool {
+    maps.lines().any(|line| {
+        line.rsplit('/')
+            .next()
+            .is_some_and(|file| file.starts_with("libgamemode"))
+    })
+}
+
+/// Tracks GameMode and decides whether GhostBrew may write EPP
+pub struct GameModeCoordinator {
+    /// PID of gamemoded while GameMode is active
+    pid: Option<u32>,
+    /// Game PIDs already checked for the client library
+    clients: HashMap<u32, bool>,
+    /// --epp-over-gamemode: keep writing EPP while it runs
+    take_precedence: bool,
+}
+
+impl GameModeCoordinator {
+    pub fn new(take_precedence: bool) -> Self {
+        Self {
+            pid: None,
+            clients: HashMap::new(),
+            take_precedence,
+        }
+    }
+
+    /// Record the gamemoded PID while GameMode is active, logging changes
+    pub fn observe(&mut self, pid: Option<u32>) {
+        match (self.pid, pid) {
+            (None, Some(pid)) if self.take_precedence => info!(
+                "GameMode active (gamemoded PID {}); keeping EPP hints (--epp-over-gamemode)",
+                pid
+            ),
+            (None, Some(pid)) => info!(
+                "GameMode active (gamemoded PID {}); leaving EPP to GameMode",
+                pid
+            ),
+            (Some(_), None) if !self.take_precedence => {
+                info!("GameMode inactive; resuming EPP hints")
+            }
+            _ => {}
+        }
+        self.pid = pid;
+    }
+
+    /// Re-check GameMode from the gaming scan: the running gamemoded and
+    /// the detected games, of which at least one must be a client
+    pub fn refresh(&mut self, gamemoded: Option<u32>, games: impl Iterator<Item = u32>) {
+        let games: Vec<u32> = games.collect();
+        self.clients.retain(|pid, _| games.contains(pid));
+        let active = gamemoded.is_some()
+            && games.iter().any(|&pid| {
+                *self.clients.entry(pid).or_insert_with(|| {
+                    procfs::read_pid_file(pid, "maps")
+                        .is_some_and(|maps| maps_gamemode_client(&maps))
+                })
+            });
+        self.observe(gamemoded.filter(|_| active));
+    }
+
+    /// Whether GhostBrew's EPP hints should apply
+    pub fn epp_allowed(&self) -> bool {
+        self.pid.is_none() || self.take_precedence
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gamemode_active_only_with_a_client() {
+        let fixture = procfs::Fixture::new("gamemode");
+        fixture
+            .file(
+                "/proc/2210/maps",
+                "7f1c2a000000-7f1c2a001000 r--p 00000000 103:02 1234 /usr/lib/libgamemodeauto.so.0\n",
+            )
+            .file(
+                "/proc/3300/maps",
+                "7f1c2b000000-7f1c2b001000 r--p 00000000 103:02 99 /usr/lib/libc.so.6\n",
+            );
+
+        fixture.run(|| {
+            let mut coordinator = GameModeCoordinator::new(false);
+            // Daemon running, but the only game is no client
+            coordinator.refresh(Some(1450), [3300].into_iter());
+            assert!(coordinator.epp_allowed());
+            // A gamemoderun game starts
+            coordinator.refresh(Some(1450), [3300, 2210].into_iter());
+            assert!(!coordinator.epp_allowed());
+            // The client exits
+            coordinator.refresh(Some(1450), [3300].into_iter());
+            assert!(coordinator.epp_allowed());
+            // No daemon: the library alone does nothing
+            coordinator.refresh(None, [2210].into_iter());
+            assert!(coordinator.epp_allowed());
+
+            let mut coordinator = GameModeCoordinator::new(true);
+            coordinator.refresh(Some(1450), [2210].into_iter());
+            assert!(coordinator.epp_allowed());
+        });
+
+        assert!(!maps_gamemode_client(
+            "00400000-00452000 r-xp 00000000 08:02 173521 /usr/bin/gamemoderun-helper\n"
+        ));
+    }
+}
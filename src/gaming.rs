@@ -4,16 +4,27 @@
 //
 // Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
 
+use crate::gamemode::GAMEMODED_COMM;
+use crate::procfs;
 use anyhow::Result;
 use log::{debug, info};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fs;
+use std::path::Path;
+use std::time::Duration;
 
 /// Workload classification types (matches BPF side)
 pub const WORKLOAD_GAMING: u32 = 1;
+pub const WORKLOAD_INTERACTIVE: u32 = 2;
 pub const WORKLOAD_BATCH: u32 = 3;
 pub const WORKLOAD_AI: u32 = 4;
 
+/// Default age a process needs before it is classified as gaming
+pub const DEFAULT_MIN_GAMING_AGE: Duration = Duration::from_secs(2);
+
+/// Default --max-scan-procs: /proc entries examined per detector scan
+pub const DEFAULT_MAX_SCAN_PROCS: usize = 8192;
+
 /// Gaming process patterns in executable paths
 const GAMING_EXE_PATTERNS: &[&str] = &[
     "wine",
@@ -47,50 +58,332 @@ const DEV_EXE_PATTERNS: &[&str] = &[
     "cmake", "make", "gcc", "g++", "zig",
 ];
 
+/// Media encoders; heavy batch work kept on the frequency die during gaming.
+/// Matched as a prefix of the executable basename (HandBrakeCLI, x264-10bit),
+/// except OBS (see `is_obs`).
+const ENCODING_PATTERNS: &[&str] = &["ffmpeg", "handbrake", "x264", "x265", "obs", "aomenc"];
+
+/// OBS thread names (comm prefixes) that capture and composite frames
+const OBS_CAPTURE_THREADS: &[&str] = &["libobs: graphic", "video-io", "audio-io", "obs-pipewire"];
+
+/// OBS thread names (substrings) that run software or hardware encoders
+const OBS_ENCODER_THREADS: &[&str] = &["x264", "x265", "encod", "nvenc", "ffmpeg", "svt", "aom"];
+
+/// Flatpak app IDs of game launchers (prefix match covers extensions like
+/// com.valvesoftware.Steam.CompatibilityTool.Proton)
+pub const FLATPAK_GAMING_IDS: &[&str] = &[
+    "com.valvesoftware.steam",
+    "net.lutris.lutris",
+    "com.heroicgameslauncher.hgl",
+    "com.usebottles.bottles",
+    "io.itch.itch",
+    "org.prismlauncher.prismlauncher",
+    "org.libretro.retroarch",
+];
+
+/// Browsers (and Electron) whose GPU processes run Vulkan/GL threads that
+/// look like a game's render thread
+const BROWSER_NAMES: &[&str] = &["chrome", "chromium", "firefox", "brave", "electron"];
+
+/// Wayland/X compositors and display servers; a stalled compositor frame
+/// stutters as visibly as a game frame
+const COMPOSITOR_NAMES: &[&str] = &[
+    "kwin_wayland",
+    "kwin_x11",
+    "gnome-shell",
+    "hyprland",
+    "sway",
+    "picom",
+    "xorg",
+];
+
 /// AI-related environment variables
 const AI_ENV_VARS: &[&str] = &["OLLAMA_", "CUDA_VISIBLE_DEVICES", "PYTORCH_", "TF_"];
 
-/// Scan /proc for gaming, batch/dev, and AI processes
-/// Returns a map of PID -> workload class
-pub fn scan_gaming_pids() -> Result<Vec<(u32, u32)>> {
-    let mut gaming_pids = Vec::new();
+/// pacman holds this lock for the duration of a transaction (-Syu, -S, -R)
+pub const PACMAN_DB_LOCK: &str = "/var/lib/pacman/db.lck";
 
-    // Read /proc directory
-    let proc_dir = match fs::read_dir("/proc") {
-        Ok(dir) => dir,
-        Err(e) => {
-            debug!("Failed to read /proc: {}", e);
-            return Ok(gaming_pids);
+/// Check whether a package manager transaction is in progress
+pub fn package_transaction_active(lock_path: &Path) -> bool {
+    lock_path.exists()
+}
+
+/// User allow/deny lists from the `[gaming]` config section
+///
+/// Entries match a process's comm or executable basename, case-insensitively.
+/// Denylisted processes are never gaming; allowlisted ones always are.
+#[derive(Debug, Clone, Default)]
+pub struct ClassifyOverrides {
+    allow: Vec<String>,
+    deny: Vec<String>,
+}
+
+impl ClassifyOverrides {
+    pub fn new(allow: &[String], deny: &[String]) -> Self {
+        let normalize = |list: &[String]| list.iter().map(|e| e.trim().to_lowercase()).collect();
+        Self {
+            allow: normalize(allow),
+            deny: normalize(deny),
         }
-    };
+    }
 
-    for entry in proc_dir.flatten() {
-        let file_name = entry.file_name();
-        let name = file_name.to_string_lossy();
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
 
-        // Skip non-numeric entries
-        let pid: u32 = match name.parse() {
-            Ok(p) => p,
-            Err(_) => continue,
-        };
+    fn matches(list: &[String], comm: &str, exe: &str) -> bool {
+        let basename = exe.rsplit('/').next().unwrap_or(exe);
+        list.iter().any(|entry| {
+            // comm is truncated to 15 bytes (TASK_COMM_LEN - 1)
+            entry == basename || entry == comm || (comm.len() == 15 && entry.starts_with(comm))
+        })
+    }
+
+    /// Apply the lists to a classification (`comm` and `exe` lowercased)
+    pub fn apply(&self, comm: &str, exe: &str, class: Option<u32>) -> Option<u32> {
+        if Self::matches(&self.deny, comm, exe) {
+            return class.filter(|&c| c != WORKLOAD_GAMING);
+        }
+        if Self::matches(&self.allow, comm, exe) {
+            return Some(WORKLOAD_GAMING);
+        }
+        class
+    }
+}
+
+/// Browser comm/exe names: the built-ins plus `[gaming] browsers`
+///
+/// Browser processes are pushed to BPF as interactive, which keeps their
+/// GPU-feeding threads from being promoted to gaming.
+#[derive(Debug, Clone)]
+pub struct BrowserFilter {
+    names: Vec<String>,
+}
+
+impl BrowserFilter {
+    pub fn new(extra: &[String]) -> Self {
+        let names = BROWSER_NAMES
+            .iter()
+            .map(|name| name.to_string())
+            .chain(extra.iter().map(|e| e.trim().to_lowercase()))
+            .collect();
+        Self { names }
+    }
+
+    /// Whether a process is a browser (`comm` and `exe` lowercased)
+    pub fn is_browser(&self, comm: &str, exe: &str) -> bool {
+        ClassifyOverrides::matches(&self.names, comm, exe)
+    }
+}
+
+impl Default for BrowserFilter {
+    fn default() -> Self {
+        Self::new(&[])
+    }
+}
+
+/// Compositor comm/exe names: the built-ins plus `[gaming] compositors`
+///
+/// Compositors are pushed to BPF as interactive so they stay on a fast core
+/// next to the game instead of queuing behind background work.
+#[derive(Debug, Clone)]
+pub struct CompositorFilter {
+    names: Vec<String>,
+}
 
-        // Check if this is a gaming or AI process
-        if let Some(workload_class) = classify_process(pid) {
-            gaming_pids.push((pid, workload_class));
+impl CompositorFilter {
+    pub fn new(extra: &[String]) -> Self {
+        let names = COMPOSITOR_NAMES
+            .iter()
+            .map(|name| name.to_string())
+            .chain(extra.iter().map(|e| e.trim().to_lowercase()))
+            .collect();
+        Self { names }
+    }
+
+    /// Whether a process is a compositor (`comm` and `exe` lowercased)
+    pub fn is_compositor(&self, comm: &str, exe: &str) -> bool {
+        ClassifyOverrides::matches(&self.names, comm, exe)
+    }
+}
+
+impl Default for CompositorFilter {
+    fn default() -> Self {
+        Self::new(&[])
+    }
+}
+
+/// Result of a /proc scan
+#[derive(Debug, Default)]
+pub struct PidScan {
+    /// PID -> workload class
+    pub classified: Vec<(u32, u32)>,
+    /// Processes that matched a class but run under SCHED_FIFO/SCHED_RR:
+    /// PID -> policy
+    pub realtime: Vec<(u32, u32)>,
+    /// Start times of the classified PIDs, to catch PID reuse
+    pub start_times: HashMap<u32, u64>,
+    /// System uptime at the scan, None if /proc/uptime is unreadable
+    pub uptime: Option<Duration>,
+    /// PID of Feral's gamemoded, if it was seen
+    pub gamemoded: Option<u32>,
+}
+
+impl PidScan {
+    /// Empty the scan, keeping its allocations for the next one
+    fn clear(&mut self) {
+        self.classified.clear();
+        self.realtime.clear();
+        self.start_times.clear();
+        self.uptime = None;
+        self.gamemoded = None;
+    }
+}
+
+/// List the numeric entries of /proc into `pids`, reusing its buffer
+fn list_pids(pids: &mut Vec<u32>) {
+    pids.clear();
+    match fs::read_dir(procfs::rooted("/proc")) {
+        Ok(dir) => pids.extend(
+            dir.flatten()
+                .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok()),
+        ),
+        Err(e) => debug!("Failed to read /proc: {}", e),
+    }
+}
+
+/// Classify one /proc entry into `scan`
+fn scan_pid(
+    pid: u32,
+    overrides: &ClassifyOverrides,
+    browsers: &BrowserFilter,
+    compositors: &CompositorFilter,
+    scan: &mut PidScan,
+) {
+    let comm = procfs::read_full_comm(pid)
+        .map(|c| c.to_lowercase())
+        .unwrap_or_default();
+    if comm == GAMEMODED_COMM {
+        scan.gamemoded = Some(pid);
+        return;
+    }
+    let Some(workload_class) = classify_process(pid, &comm, overrides, browsers, compositors)
+    else {
+        return;
+    };
+    let stat = procfs::read_pid_file(pid, "stat").unwrap_or_default();
+    match procfs::parse_stat_policy(&stat) {
+        Some(policy) if procfs::is_rt_policy(policy) => {
+            scan.realtime.push((pid, policy));
         }
+        _ => {
+            scan.classified.push((pid, workload_class));
+            if let Some(start) = procfs::parse_stat_starttime(&stat) {
+                scan.start_times.insert(pid, start);
+            }
+        }
+    }
+}
+
+/// Scan /proc for gaming, batch/dev, AI, browser and compositor processes
+///
+/// Real-time processes (JACK/PipeWire, some engine threads) belong to the
+/// kernel's RT class rather than sched_ext, so they are reported separately
+/// instead of being classified.
+pub fn scan_gaming_pids(
+    overrides: &ClassifyOverrides,
+    browsers: &BrowserFilter,
+    compositors: &CompositorFilter,
+) -> Result<PidScan> {
+    let mut scan = PidScan {
+        uptime: procfs::read_uptime(),
+        ..Default::default()
+    };
+
+    let mut pids = Vec::new();
+    list_pids(&mut pids);
+    for pid in pids {
+        scan_pid(pid, overrides, browsers, compositors, &mut scan);
     }
 
-    debug!("Found {} gaming/AI processes", gaming_pids.len());
-    Ok(gaming_pids)
+    debug!(
+        "Found {} gaming/AI processes ({} real-time, skipped)",
+        scan.classified.len(),
+        scan.realtime.len()
+    );
+    Ok(scan)
 }
 
-/// Classify a single process by PID
-fn classify_process(pid: u32) -> Option<u32> {
+/// Classify a single process by PID and lowercased comm, then apply the
+/// user allow/deny lists
+fn classify_process(
+    pid: u32,
+    comm: &str,
+    overrides: &ClassifyOverrides,
+    browsers: &BrowserFilter,
+    compositors: &CompositorFilter,
+) -> Option<u32> {
+    let exe = procfs::read_pid_exe(pid)
+        .map(|p| p.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    let class = classify_with_compositors(compositors, comm, &exe, || {
+        classify_with_browsers(browsers, comm, &exe, || classify_process_builtin(pid))
+    });
+    if overrides.is_empty() {
+        return class;
+    }
+
+    let result = overrides.apply(comm, &exe, class);
+    if result != class {
+        debug!(
+            "PID {} ({}) class {:?} -> {:?} via config allow/deny list",
+            pid, comm, class, result
+        );
+    }
+    result
+}
+
+/// Browsers are interactive regardless of what the built-in patterns say
+/// (Steam-launched or Wine-hosted browsers would otherwise be gaming)
+fn classify_with_browsers(
+    browsers: &BrowserFilter,
+    comm: &str,
+    exe: &str,
+    builtin: impl FnOnce() -> Option<u32>,
+) -> Option<u32> {
+    if browsers.is_browser(comm, exe) {
+        return Some(WORKLOAD_INTERACTIVE);
+    }
+    builtin()
+}
+
+/// Compositors are interactive rather than left unclassified with the
+/// background tasks
+fn classify_with_compositors(
+    compositors: &CompositorFilter,
+    comm: &str,
+    exe: &str,
+    other: impl FnOnce() -> Option<u32>,
+) -> Option<u32> {
+    if compositors.is_compositor(comm, exe) {
+        return Some(WORKLOAD_INTERACTIVE);
+    }
+    other()
+}
+
+/// Classify a single process by PID using the built-in patterns
+fn classify_process_builtin(pid: u32) -> Option<u32> {
     // Check executable path first (most reliable)
     if let Some(class) = check_exe_path(pid) {
         return Some(class);
     }
 
+    // Flatpak games have exe paths under /app that don't match the patterns
+    if let Some(class) = check_flatpak(pid, &procfs::rooted(format!("/proc/{}", pid))) {
+        return Some(class);
+    }
+
     // Check environment variables
     if let Some(class) = check_environ(pid) {
         return Some(class);
@@ -101,35 +394,49 @@ fn classify_process(pid: u32) -> Option<u32> {
 
 /// Check /proc/[pid]/exe for gaming patterns
 fn check_exe_path(pid: u32) -> Option<u32> {
-    let exe_path = format!("/proc/{}/exe", pid);
-
-    let exe = match fs::read_link(&exe_path) {
-        Ok(p) => p,
-        Err(_) => return None,
-    };
+    let exe = procfs::read_pid_exe(pid)?;
 
     let exe_str = exe.to_string_lossy().to_lowercase();
+    let class = classify_exe(&exe_str)?;
+    debug!(
+        "PID {} detected as class {} via exe: {}",
+        pid, class, exe_str
+    );
+    Some(class)
+}
 
+/// Classify a lowercased executable path
+pub fn classify_exe(exe_str: &str) -> Option<u32> {
     // Check for gaming patterns
-    for pattern in GAMING_EXE_PATTERNS {
-        if exe_str.contains(pattern) {
-            debug!("PID {} detected as gaming via exe: {}", pid, exe_str);
-            return Some(WORKLOAD_GAMING);
-        }
+    if GAMING_EXE_PATTERNS
+        .iter()
+        .any(|pattern| exe_str.contains(pattern))
+    {
+        return Some(WORKLOAD_GAMING);
     }
 
     // Check for .exe suffix (Wine/Proton)
     if exe_str.ends_with(".exe") {
-        debug!("PID {} detected as gaming via .exe: {}", pid, exe_str);
         return Some(WORKLOAD_GAMING);
     }
 
+    // Encoders are batch, except OBS itself: it is interactive and its
+    // encoder threads are split out per thread
+    let basename = exe_str.rsplit('/').next().unwrap_or(exe_str);
+    if is_encoder(basename) {
+        return Some(if is_obs(basename) {
+            WORKLOAD_INTERACTIVE
+        } else {
+            WORKLOAD_BATCH
+        });
+    }
+
     // Check for dev/build patterns before broad AI handling.
-    for pattern in DEV_EXE_PATTERNS {
-        if exe_str.contains(pattern) {
-            debug!("PID {} detected as dev/batch via exe: {}", pid, exe_str);
-            return Some(WORKLOAD_BATCH);
-        }
+    if DEV_EXE_PATTERNS
+        .iter()
+        .any(|pattern| exe_str.contains(pattern))
+    {
+        return Some(WORKLOAD_BATCH);
     }
 
     // Check for AI patterns
@@ -139,7 +446,6 @@ fn check_exe_path(pid: u32) -> Option<u32> {
             if *pattern == "python" {
                 return None; // Let environ check handle python
             }
-            debug!("PID {} detected as AI via exe: {}", pid, exe_str);
             return Some(WORKLOAD_AI);
         }
     }
@@ -147,39 +453,233 @@ fn check_exe_path(pid: u32) -> Option<u32> {
     None
 }
 
+/// Whether a lowercased executable basename is OBS Studio
+///
+/// Exact match: as a prefix "obs" would also catch obsidian and friends.
+pub fn is_obs(basename: &str) -> bool {
+    basename == "obs" || basename == "obs-studio"
+}
+
+/// Whether a lowercased executable basename is a media encoder
+fn is_encoder(basename: &str) -> bool {
+    ENCODING_PATTERNS.iter().any(|&pattern| {
+        if pattern == "obs" {
+            is_obs(basename)
+        } else {
+            basename.starts_with(pattern)
+        }
+    })
+}
+
+/// Class of an OBS thread by name: capture threads are interactive, encoder
+/// threads batch, anything else keeps the process class
+pub fn classify_obs_thread(name: &str) -> Option<u32> {
+    let lower = name.to_lowercase();
+    if OBS_CAPTURE_THREADS.iter().any(|p| lower.starts_with(p)) {
+        return Some(WORKLOAD_INTERACTIVE);
+    }
+    if OBS_ENCODER_THREADS.iter().any(|p| lower.contains(p)) {
+        return Some(WORKLOAD_BATCH);
+    }
+    None
+}
+
+/// Get the Flatpak app ID of a sandboxed process
+///
+/// `proc_dir` is the process's /proc entry. Only processes whose root has a
+/// `.flatpak-info` marker are considered; the ID comes from its
+/// `[Application] name=` key, falling back to `FLATPAK_ID` in the environ.
+fn flatpak_app_id(proc_dir: &Path) -> Option<String> {
+    let info = fs::read_to_string(proc_dir.join("root/.flatpak-info")).ok()?;
+
+    let mut in_application = false;
+    for line in info.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_application = line == "[Application]";
+        } else if in_application && let Some(name) = line.strip_prefix("name=") {
+            return Some(name.trim().to_string());
+        }
+    }
+
+    let environ = fs::read_to_string(proc_dir.join("environ")).ok()?;
+    environ
+        .split('\0')
+        .find_map(|var| var.strip_prefix("FLATPAK_ID="))
+        .map(|id| id.to_string())
+}
+
+/// Check whether a process runs inside a game launcher's Flatpak sandbox
+fn check_flatpak(pid: u32, proc_dir: &Path) -> Option<u32> {
+    let app_id = flatpak_app_id(proc_dir)?;
+    let app_id_lower = app_id.to_lowercase();
+
+    if FLATPAK_GAMING_IDS
+        .iter()
+        .any(|id| app_id_lower.starts_with(id))
+    {
+        debug!("PID {} detected as gaming via Flatpak: {}", pid, app_id);
+        return Some(WORKLOAD_GAMING);
+    }
+
+    None
+}
+
 /// Check /proc/[pid]/environ for gaming environment variables
 fn check_environ(pid: u32) -> Option<u32> {
-    let environ_path = format!("/proc/{}/environ", pid);
+    let environ = procfs::read_pid_file(pid, "environ")?;
 
-    let environ = match fs::read_to_string(&environ_path) {
-        Ok(e) => e,
-        Err(_) => return None,
-    };
+    let class = classify_environ(&environ)?;
+    debug!("PID {} detected as class {} via env", pid, class);
+    Some(class)
+}
 
+/// Classify a raw (NUL-separated) environ block
+pub fn classify_environ(environ: &str) -> Option<u32> {
     // Check for gaming environment variables
-    for var in GAMING_ENV_VARS {
-        if environ.contains(var) {
-            debug!("PID {} detected as gaming via env: {}", pid, var);
-            return Some(WORKLOAD_GAMING);
-        }
+    if GAMING_ENV_VARS.iter().any(|var| environ.contains(var)) {
+        return Some(WORKLOAD_GAMING);
     }
 
     // Check for AI environment variables
-    for var in AI_ENV_VARS {
-        if environ.contains(var) {
-            debug!("PID {} detected as AI via env: {}", pid, var);
-            return Some(WORKLOAD_AI);
-        }
+    if AI_ENV_VARS.iter().any(|var| environ.contains(var)) {
+        return Some(WORKLOAD_AI);
     }
 
     None
 }
 
+/// Wine prefix architecture of a gaming process
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WineArch {
+    Win32,
+    Win64,
+}
+
+impl WineArch {
+    fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "win32" => Some(WineArch::Win32),
+            "win64" => Some(WineArch::Win64),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for WineArch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WineArch::Win32 => write!(f, "Win32"),
+            WineArch::Win64 => write!(f, "Win64"),
+        }
+    }
+}
+
+/// Get a variable from a raw (NUL-separated) environ block
+fn environ_var<'a>(environ: &'a str, name: &str) -> Option<&'a str> {
+    environ
+        .split('\0')
+        .find_map(|var| var.strip_prefix(name)?.strip_prefix('='))
+}
+
+/// Read the `#arch=` marker Wine writes near the top of a prefix's system.reg
+fn wine_arch_from_prefix(prefix: &Path) -> Option<WineArch> {
+    let content = fs::read_to_string(prefix.join("system.reg")).ok()?;
+    content
+        .lines()
+        .take(16)
+        .find_map(|line| line.strip_prefix("#arch="))
+        .and_then(WineArch::parse)
+}
+
+/// Determine the Wine arch from an environ block
+///
+/// Prefers an explicit `WINEARCH`, then the prefix marker from `WINEPREFIX`
+/// or Proton's `STEAM_COMPAT_DATA_PATH/pfx`.
+pub fn wine_arch_from_environ(environ: &str) -> Option<WineArch> {
+    if let Some(arch) = environ_var(environ, "WINEARCH").and_then(WineArch::parse) {
+        return Some(arch);
+    }
+
+    if let Some(prefix) = environ_var(environ, "WINEPREFIX") {
+        return wine_arch_from_prefix(Path::new(prefix));
+    }
+
+    environ_var(environ, "STEAM_COMPAT_DATA_PATH")
+        .and_then(|data| wine_arch_from_prefix(&Path::new(data).join("pfx")))
+}
+
+/// Detect the Wine arch of a running process
+fn detect_wine_arch(pid: u32) -> Option<WineArch> {
+    let environ = procfs::read_pid_file(pid, "environ")?;
+    wine_arch_from_environ(&environ)
+}
+
+/// Open eventfds above which a Wine process is taken to use esync
+const ESYNC_EVENTFD_MIN: usize = 128;
+
+/// Wine synchronization primitive backend
+///
+/// esync turns every Windows sync object into an eventfd and fsync into a
+/// futex, so both games wake threads far more often than plain wineserver
+/// sync, in shorter bursts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WineSync {
+    Esync,
+    Fsync,
+}
+
+impl std::fmt::Display for WineSync {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WineSync::Esync => write!(f, "esync"),
+            WineSync::Fsync => write!(f, "fsync"),
+        }
+    }
+}
+
+/// Sync backend enabled in an environ block (fsync wins when both are set,
+/// as in Wine and Proton)
+pub fn wine_sync_from_environ(environ: &str) -> Option<WineSync> {
+    let enabled = |name| environ_var(environ, name).is_some_and(|v| v.trim() == "1");
+    if enabled("WINEFSYNC") {
+        Some(WineSync::Fsync)
+    } else if enabled("WINEESYNC") {
+        Some(WineSync::Esync)
+    } else {
+        None
+    }
+}
+
+/// Number of eventfds a process holds open
+fn count_eventfds(pid: u32) -> usize {
+    let Ok(fds) = fs::read_dir(procfs::rooted(format!("/proc/{}/fd", pid))) else {
+        return 0;
+    };
+    fds.flatten()
+        .filter(|fd| {
+            fs::read_link(fd.path())
+                .is_ok_and(|target| target.as_os_str() == "anon_inode:[eventfd]")
+        })
+        .count()
+}
+
+/// Detect the sync backend of a running Wine process, falling back to its
+/// eventfd count for esync enabled without the environment variable
+fn detect_wine_sync(pid: u32) -> Option<WineSync> {
+    if let Some(sync) =
+        procfs::read_pid_file(pid, "environ").and_then(|env| wine_sync_from_environ(&env))
+    {
+        return Some(sync);
+    }
+    (count_eventfds(pid) >= ESYNC_EVENTFD_MIN).then_some(WineSync::Esync)
+}
+
 /// Get all child PIDs of a process (for marking entire process trees)
 #[allow(dead_code)]
 pub fn get_child_pids(pid: u32) -> Vec<u32> {
     let mut children = Vec::new();
-    let children_path = format!("/proc/{}/task/{}/children", pid, pid);
+    let children_path = procfs::rooted(format!("/proc/{}/task/{}/children", pid, pid));
 
     if let Ok(content) = fs::read_to_string(&children_path) {
         for child_str in content.split_whitespace() {
@@ -194,11 +694,70 @@ pub fn get_child_pids(pid: u32) -> Vec<u32> {
     children
 }
 
+/// A /proc pass spread over several scans, with buffers kept between them
+///
+/// On hosts with tens of thousands of processes a full pass would stall the
+/// scheduler loop, so each scan examines at most `max_procs` entries and the
+/// next one resumes at `cursor`. Changes are applied once the pass is
+/// complete: a game starting on such a host is picked up a few intervals
+/// later, in exchange for a bounded cost per interval.
+#[derive(Debug)]
+struct ScanState {
+    /// /proc entries listed when the current pass started
+    pids: Vec<u32>,
+    /// Next index into `pids`
+    cursor: usize,
+    /// Results of the pass so far
+    pending: PidScan,
+    /// Entries examined per scan (0 = the whole pass at once)
+    max_procs: usize,
+    /// Known PID sets replaced by the last pass, reused as working sets
+    spare: [HashSet<u32>; 4],
+}
+
+impl Default for ScanState {
+    fn default() -> Self {
+        Self {
+            pids: Vec::new(),
+            cursor: 0,
+            pending: PidScan::default(),
+            max_procs: DEFAULT_MAX_SCAN_PROCS,
+            spare: Default::default(),
+        }
+    }
+}
+
 /// Gaming detector state for incremental updates
 pub struct GamingDetector {
     known_gaming_pids: HashSet<u32>,
     known_batch_pids: HashSet<u32>,
     known_ai_pids: HashSet<u32>,
+    /// Browser and compositor processes, pushed as interactive
+    known_browser_pids: HashSet<u32>,
+    /// OBS processes (interactive, with per-thread encoder classes)
+    known_obs_pids: HashSet<u32>,
+    /// Matching processes left alone because they run real-time
+    known_rt_pids: HashSet<u32>,
+    /// Start time of each known PID when it was classified
+    start_times: HashMap<u32, u64>,
+    /// Wine prefix arch of gaming PIDs (Wine/Proton only)
+    wine_arch: HashMap<u32, WineArch>,
+    /// esync/fsync backend of gaming PIDs
+    wine_sync: HashMap<u32, WineSync>,
+    /// comm of gaming PIDs when they were classified
+    comms: HashMap<u32, String>,
+    /// PID of gamemoded in the last completed scan
+    gamemoded: Option<u32>,
+    /// Skip new batch/AI classifications (package transaction in progress)
+    freeze_transient: bool,
+    /// Age a process needs before it is classified as gaming
+    min_age: Duration,
+    /// /proc pass in progress
+    scan_state: ScanState,
+    /// User allow/deny lists
+    overrides: ClassifyOverrides,
+    browsers: BrowserFilter,
+    compositors: CompositorFilter,
 }
 
 impl GamingDetector {
@@ -207,19 +766,142 @@ impl GamingDetector {
             known_gaming_pids: HashSet::new(),
             known_batch_pids: HashSet::new(),
             known_ai_pids: HashSet::new(),
+            known_browser_pids: HashSet::new(),
+            known_obs_pids: HashSet::new(),
+            known_rt_pids: HashSet::new(),
+            start_times: HashMap::new(),
+            wine_arch: HashMap::new(),
+            wine_sync: HashMap::new(),
+            comms: HashMap::new(),
+            gamemoded: None,
+            freeze_transient: false,
+            min_age: DEFAULT_MIN_GAMING_AGE,
+            scan_state: ScanState::default(),
+            overrides: ClassifyOverrides::default(),
+            browsers: BrowserFilter::default(),
+            compositors: CompositorFilter::default(),
         }
     }
 
+    /// Set the user allow/deny lists applied to every scan
+    pub fn set_overrides(&mut self, overrides: ClassifyOverrides) {
+        self.overrides = overrides;
+    }
+
+    /// Set the browser list applied to every scan
+    pub fn set_browsers(&mut self, browsers: BrowserFilter) {
+        self.browsers = browsers;
+    }
+
+    /// Set the compositor list applied to every scan
+    pub fn set_compositors(&mut self, compositors: CompositorFilter) {
+        self.compositors = compositors;
+    }
+
+    /// Set the age a process needs before it is classified as gaming
+    pub fn set_min_age(&mut self, min_age: Duration) {
+        self.min_age = min_age;
+    }
+
+    /// Cap the /proc entries examined per scan (0 = no cap)
+    pub fn set_max_scan_procs(&mut self, max_procs: usize) {
+        self.scan_state.max_procs = max_procs;
+    }
+
+    /// Stop classifying new batch/AI processes (e.g. during a pacman upgrade,
+    /// where short-lived build and hook processes would churn the BPF maps)
+    pub fn set_freeze_transient(&mut self, freeze: bool) {
+        self.freeze_transient = freeze;
+    }
+
     /// Scan and return only changed PIDs (new or removed)
+    ///
+    /// A pass over more than --max-scan-procs entries is spread across calls;
+    /// calls before the last one report no changes.
     #[allow(clippy::type_complexity)]
     pub fn scan_changes(&mut self) -> Result<(Vec<(u32, u32)>, Vec<u32>)> {
-        let current_scan = scan_gaming_pids()?;
+        let state = &mut self.scan_state;
+        if state.cursor == 0 {
+            list_pids(&mut state.pids);
+        }
+
+        let end = match state.max_procs {
+            0 => state.pids.len(),
+            max => (state.cursor + max).min(state.pids.len()),
+        };
+        for &pid in &state.pids[state.cursor..end] {
+            scan_pid(
+                pid,
+                &self.overrides,
+                &self.browsers,
+                &self.compositors,
+                &mut state.pending,
+            );
+        }
+        if end < state.pids.len() {
+            debug!(
+                "Process scan: {}/{} entries, continuing next interval",
+                end,
+                state.pids.len()
+            );
+            state.cursor = end;
+            return Ok((Vec::new(), Vec::new()));
+        }
+        state.cursor = 0;
+        state.pending.uptime = procfs::read_uptime();
+
+        let mut scan = std::mem::take(&mut self.scan_state.pending);
+        let changes = self.apply_scan(&mut scan);
+        self.scan_state.pending = scan;
+        Ok(changes)
+    }
+
+    /// Diff a scan against the known PIDs, leaving `scan` cleared
+    ///
+    /// A known PID whose start time changed was reused by a new process; it
+    /// is reported as removed and, if the new process matches too, added.
+    #[allow(clippy::type_complexity)]
+    fn apply_scan(&mut self, scan: &mut PidScan) -> (Vec<(u32, u32)>, Vec<u32>) {
+        self.note_realtime(&scan.realtime);
+        self.gamemoded = scan.gamemoded;
 
-        let mut current_gaming: HashSet<u32> = HashSet::new();
-        let mut current_batch: HashSet<u32> = HashSet::new();
-        let mut current_ai: HashSet<u32> = HashSet::new();
+        let mut removed_pids: Vec<u32> = Vec::new();
+        for (pid, start) in &scan.start_times {
+            if self
+                .start_times
+                .get(pid)
+                .is_some_and(|known| known != start)
+            {
+                debug!("PID {} was reused, reclassifying", pid);
+                self.known_gaming_pids.remove(pid);
+                self.known_batch_pids.remove(pid);
+                self.known_ai_pids.remove(pid);
+                self.known_browser_pids.remove(pid);
+                self.known_obs_pids.remove(pid);
+                self.wine_arch.remove(pid);
+                self.wine_sync.remove(pid);
+                self.comms.remove(pid);
+                removed_pids.push(*pid);
+            }
+        }
+        std::mem::swap(&mut self.start_times, &mut scan.start_times);
+
+        let [
+            mut current_gaming,
+            mut current_batch,
+            mut current_ai,
+            mut current_browser,
+        ] = std::mem::take(&mut self.scan_state.spare);
+        for set in [
+            &mut current_gaming,
+            &mut current_batch,
+            &mut current_ai,
+            &mut current_browser,
+        ] {
+            set.clear();
+        }
 
-        for (pid, class) in &current_scan {
+        for (pid, class) in &scan.classified {
             match *class {
                 WORKLOAD_GAMING => {
                     current_gaming.insert(*pid);
@@ -230,14 +912,58 @@ impl GamingDetector {
                 WORKLOAD_AI => {
                     current_ai.insert(*pid);
                 }
+                WORKLOAD_INTERACTIVE => {
+                    current_browser.insert(*pid);
+                }
                 _ => {}
             }
         }
 
+        // Launchers and helpers (Steam's reaper, pressure-vessel setup) match
+        // gaming patterns for a moment and exit; wait until a new PID is old
+        // enough before classifying it
+        if let Some(uptime) = scan.uptime
+            && !self.min_age.is_zero()
+        {
+            let ticks = procfs::clock_ticks();
+            current_gaming.retain(|pid| {
+                if self.known_gaming_pids.contains(pid) {
+                    return true;
+                }
+                let Some(&start) = self.start_times.get(pid) else {
+                    return true;
+                };
+                let age = procfs::process_age(start, uptime, ticks);
+                if age < self.min_age {
+                    debug!(
+                        "PID {} matches gaming but is only {:?} old, waiting",
+                        pid, age
+                    );
+                }
+                age >= self.min_age
+            });
+        }
+
+        // Only keep already-known batch/AI PIDs while frozen
+        if self.freeze_transient {
+            current_batch.retain(|pid| self.known_batch_pids.contains(pid));
+            current_ai.retain(|pid| self.known_ai_pids.contains(pid));
+        }
+
         // Find new PIDs
         let mut new_pids: Vec<(u32, u32)> = Vec::new();
         for pid in current_gaming.difference(&self.known_gaming_pids) {
             new_pids.push((*pid, WORKLOAD_GAMING));
+            if let Some(arch) = detect_wine_arch(*pid) {
+                debug!("PID {} Wine arch: {}", pid, arch);
+                self.wine_arch.insert(*pid, arch);
+            }
+            if let Some(sync) = detect_wine_sync(*pid) {
+                self.wine_sync.insert(*pid, sync);
+            }
+            if let Some(comm) = procfs::read_pid_file(*pid, "comm") {
+                self.comms.insert(*pid, comm.trim().to_string());
+            }
         }
         for pid in current_batch.difference(&self.known_batch_pids) {
             new_pids.push((*pid, WORKLOAD_BATCH));
@@ -245,9 +971,11 @@ impl GamingDetector {
         for pid in current_ai.difference(&self.known_ai_pids) {
             new_pids.push((*pid, WORKLOAD_AI));
         }
+        for pid in current_browser.difference(&self.known_browser_pids) {
+            new_pids.push((*pid, WORKLOAD_INTERACTIVE));
+        }
 
         // Find removed PIDs
-        let mut removed_pids: Vec<u32> = Vec::new();
         for pid in self.known_gaming_pids.difference(&current_gaming) {
             removed_pids.push(*pid);
         }
@@ -257,11 +985,29 @@ impl GamingDetector {
         for pid in self.known_ai_pids.difference(&current_ai) {
             removed_pids.push(*pid);
         }
+        for pid in self.known_browser_pids.difference(&current_browser) {
+            removed_pids.push(*pid);
+        }
 
         // Update state
-        self.known_gaming_pids = current_gaming;
-        self.known_batch_pids = current_batch;
-        self.known_ai_pids = current_ai;
+        self.wine_arch.retain(|pid, _| current_gaming.contains(pid));
+        self.wine_sync.retain(|pid, _| current_gaming.contains(pid));
+        self.comms.retain(|pid, _| current_gaming.contains(pid));
+        self.known_obs_pids = current_browser
+            .iter()
+            .copied()
+            .filter(|&pid| {
+                self.known_obs_pids.contains(&pid)
+                    || get_exe_name(pid).is_some_and(|name| is_obs(&name.to_lowercase()))
+            })
+            .collect();
+        self.scan_state.spare = [
+            std::mem::replace(&mut self.known_gaming_pids, current_gaming),
+            std::mem::replace(&mut self.known_batch_pids, current_batch),
+            std::mem::replace(&mut self.known_ai_pids, current_ai),
+            std::mem::replace(&mut self.known_browser_pids, current_browser),
+        ];
+        scan.clear();
 
         if !new_pids.is_empty() || !removed_pids.is_empty() {
             info!(
@@ -271,7 +1017,62 @@ impl GamingDetector {
             );
         }
 
-        Ok((new_pids, removed_pids))
+        (new_pids, removed_pids)
+    }
+
+    /// Log real-time processes once, when they first show up
+    fn note_realtime(&mut self, realtime: &[(u32, u32)]) {
+        for &(pid, policy) in realtime {
+            if !self.known_rt_pids.contains(&pid) {
+                info!(
+                    "PID {} ({}) runs {}; left to the kernel RT scheduler, not classified",
+                    pid,
+                    get_exe_name(pid).unwrap_or_default(),
+                    procfs::rt_policy_name(policy)
+                );
+            }
+        }
+        self.known_rt_pids = realtime.iter().map(|&(pid, _)| pid).collect();
+    }
+
+    /// PID of gamemoded, if the last scan saw it running
+    pub fn gamemoded(&self) -> Option<u32> {
+        self.gamemoded
+    }
+
+    /// Currently detected gaming PIDs
+    pub fn gaming_pids(&self) -> impl Iterator<Item = u32> + '_ {
+        self.known_gaming_pids.iter().copied()
+    }
+
+    /// Currently detected batch/dev PIDs
+    pub fn batch_pids(&self) -> impl Iterator<Item = u32> + '_ {
+        self.known_batch_pids.iter().copied()
+    }
+
+    /// Currently detected AI PIDs
+    pub fn ai_pids(&self) -> impl Iterator<Item = u32> + '_ {
+        self.known_ai_pids.iter().copied()
+    }
+
+    /// Currently detected OBS PIDs
+    pub fn obs_pids(&self) -> impl Iterator<Item = u32> + '_ {
+        self.known_obs_pids.iter().copied()
+    }
+
+    /// Wine prefix arch of a detected gaming PID
+    pub fn wine_arch(&self, pid: u32) -> Option<WineArch> {
+        self.wine_arch.get(&pid).copied()
+    }
+
+    /// esync/fsync backend of a detected gaming PID
+    pub fn wine_sync(&self, pid: u32) -> Option<WineSync> {
+        self.wine_sync.get(&pid).copied()
+    }
+
+    /// comm of a detected gaming PID
+    pub fn comm(&self, pid: u32) -> Option<&str> {
+        self.comms.get(&pid).map(String::as_str)
     }
 
     /// Get counts for logging
@@ -292,20 +1093,17 @@ impl Default for GamingDetector {
 
 /// Get the executable name for a PID (for profile matching)
 pub fn get_exe_name(pid: u32) -> Option<String> {
-    let exe_path = format!("/proc/{}/exe", pid);
-    if let Ok(exe) = fs::read_link(&exe_path)
+    if let Some(exe) = procfs::read_pid_exe(pid)
         && let Some(name) = exe.file_name()
     {
         return Some(name.to_string_lossy().to_string());
     }
 
     // Fallback to comm
-    let comm_path = format!("/proc/{}/comm", pid);
-    if let Ok(comm) = fs::read_to_string(&comm_path) {
-        let comm = comm.trim();
-        if !comm.is_empty() {
-            return Some(comm.to_string());
-        }
+    if let Some(comm) = procfs::read_full_comm(pid)
+        && !comm.is_empty()
+    {
+        return Some(comm);
     }
 
     None
@@ -318,10 +1116,251 @@ mod tests {
     #[test]
     fn test_scan_gaming_pids() {
         // This test just verifies the function runs without panicking
-        let result = scan_gaming_pids();
+        let result = scan_gaming_pids(
+            &ClassifyOverrides::default(),
+            &BrowserFilter::default(),
+            &CompositorFilter::default(),
+        );
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_scan_gaming_pids_from_snapshot() {
+        let fixture = procfs::Fixture::new("gaming");
+        let process = |pid: u32, comm: &str, exe: &str, environ: &str| {
+            let stat = format!(
+                "{} ({}) S 1 1 1 0 -1 4194560 0 0 0 0 0 0 0 0 20 0 1 0 {} 0",
+                pid, comm, pid
+            );
+            fixture
+                .file(&format!("/proc/{}/comm", pid), &format!("{}\n", comm))
+                .file(&format!("/proc/{}/environ", pid), environ)
+                .file(&format!("/proc/{}/stat", pid), &stat)
+                .symlink(&format!("/proc/{}/exe", pid), exe);
+        };
+        process(
+            101,
+            "Game.exe",
+            "/games/steamapps/common/Game/Game.exe",
+            "HOME=\0",
+        );
+        process(102, "server", "/opt/llm/server", "OLLAMA_HOST=\0");
+        process(103, "bash", "/usr/bin/bash", "HOME=\0");
+
+        let scan = fixture.run(|| {
+            scan_gaming_pids(
+                &ClassifyOverrides::default(),
+                &BrowserFilter::default(),
+                &CompositorFilter::default(),
+            )
+        });
+
+        let mut classified = scan.unwrap().classified;
+        classified.sort();
+        assert_eq!(classified, vec![(101, WORKLOAD_GAMING), (102, WORKLOAD_AI)]);
+    }
+
+    #[test]
+    fn test_scan_carries_over_between_calls() {
+        let fixture = procfs::Fixture::new("gaming-cursor");
+        for (pid, comm, exe, environ) in [
+            (
+                101,
+                "Game.exe",
+                "/games/steamapps/common/Game/Game.exe",
+                "HOME=\0",
+            ),
+            (102, "server", "/opt/llm/server", "OLLAMA_HOST=\0"),
+            (103, "bash", "/usr/bin/bash", "HOME=\0"),
+        ] {
+            fixture
+                .file(&format!("/proc/{}/comm", pid), &format!("{}\n", comm))
+                .file(&format!("/proc/{}/environ", pid), environ)
+                .file(
+                    &format!("/proc/{}/stat", pid),
+                    &format!(
+                        "{} ({}) S 1 1 1 0 -1 4194560 0 0 0 0 0 0 0 0 20 0 1 0 {} 0",
+                        pid, comm, pid
+                    ),
+                )
+                .symlink(&format!("/proc/{}/exe", pid), exe);
+        }
+        let mut detector = GamingDetector::new();
+        detector.set_max_scan_procs(2);
+
+        fixture.run(|| {
+            // First call covers 2 of 3 entries and reports nothing yet
+            assert_eq!(detector.scan_changes().unwrap(), (vec![], vec![]));
+            assert_eq!(detector.gaming_pids().count(), 0);
+
+            let (mut new_pids, removed) = detector.scan_changes().unwrap();
+            new_pids.sort();
+            assert_eq!(new_pids, [(101, WORKLOAD_GAMING), (102, WORKLOAD_AI)]);
+            assert!(removed.is_empty());
+
+            // The next pass starts over and finds nothing new
+            assert_eq!(detector.scan_changes().unwrap(), (vec![], vec![]));
+            assert_eq!(detector.scan_changes().unwrap(), (vec![], vec![]));
+            assert_eq!(detector.gaming_pids().collect::<Vec<_>>(), [101]);
+        });
+    }
+
+    #[test]
+    fn test_reused_pid_is_removed_and_added() {
+        // PIDs above pid_max never exist, so no /proc lookups match
+        let pid = 4_000_000_000;
+        let scan = |start: u64| PidScan {
+            classified: vec![(pid, WORKLOAD_GAMING)],
+            realtime: Vec::new(),
+            start_times: HashMap::from([(pid, start)]),
+            uptime: None,
+            gamemoded: None,
+        };
+        let mut detector = GamingDetector::new();
+
+        assert_eq!(
+            detector.apply_scan(&mut scan(1000)),
+            (vec![(pid, WORKLOAD_GAMING)], vec![])
+        );
+        // Same process: nothing changed
+        assert_eq!(detector.apply_scan(&mut scan(1000)), (vec![], vec![]));
+        // Same PID, new start time: the old entry goes, the new process is added
+        assert_eq!(
+            detector.apply_scan(&mut scan(2500)),
+            (vec![(pid, WORKLOAD_GAMING)], vec![pid])
+        );
+        assert_eq!(detector.gaming_pids().collect::<Vec<_>>(), [pid]);
+        assert_eq!(detector.apply_scan(&mut scan(2500)), (vec![], vec![]));
+    }
+
+    #[test]
+    fn test_young_gaming_pid_is_withheld() {
+        // PIDs above pid_max never exist, so no /proc lookups match
+        let (young, old) = (4_000_000_001, 4_000_000_002);
+        let ticks = procfs::clock_ticks();
+        let scan = |uptime_secs: u64| PidScan {
+            classified: vec![(young, WORKLOAD_GAMING), (old, WORKLOAD_GAMING)],
+            realtime: Vec::new(),
+            // Started 10s and 5s after boot
+            start_times: HashMap::from([(young, 10 * ticks), (old, 5 * ticks)]),
+            uptime: Some(Duration::from_secs(uptime_secs)),
+            gamemoded: None,
+        };
+        let mut detector = GamingDetector::new();
+
+        // At 11s the young PID is 1s old, under the 2s default
+        assert_eq!(
+            detector.apply_scan(&mut scan(11)),
+            (vec![(old, WORKLOAD_GAMING)], vec![])
+        );
+        assert_eq!(detector.gaming_pids().collect::<Vec<_>>(), [old]);
+        // Once it has lived long enough it is classified
+        assert_eq!(
+            detector.apply_scan(&mut scan(13)),
+            (vec![(young, WORKLOAD_GAMING)], vec![])
+        );
+
+        // A zero minimum age classifies immediately
+        let mut detector = GamingDetector::new();
+        detector.set_min_age(Duration::ZERO);
+        assert_eq!(detector.apply_scan(&mut scan(11)).0.len(), 2);
+    }
+
+    #[test]
+    fn test_package_transaction_lock() {
+        let dir = std::env::temp_dir().join(format!("ghostbrew-pacman-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let lock = dir.join("db.lck");
+
+        assert!(!package_transaction_active(&lock));
+        fs::write(&lock, "").unwrap();
+        assert!(package_transaction_active(&lock));
+        fs::remove_file(&lock).unwrap();
+        assert!(!package_transaction_active(&lock));
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_flatpak_steam_is_gaming() {
+        let base = std::env::temp_dir().join(format!("ghostbrew-flatpak-{}", std::process::id()));
+        let steam = base.join("100");
+        let browser = base.join("200");
+        fs::create_dir_all(steam.join("root")).unwrap();
+        fs::create_dir_all(browser.join("root")).unwrap();
+
+        fs::write(
+            steam.join("root/.flatpak-info"),
+            "[Application]\nname=com.valvesoftware.Steam\nruntime=runtime/org.freedesktop.Platform\n",
+        )
+        .unwrap();
+        assert_eq!(check_flatpak(100, &steam), Some(WORKLOAD_GAMING));
+
+        // No name in the marker: fall back to FLATPAK_ID
+        fs::write(browser.join("root/.flatpak-info"), "[Instance]\n").unwrap();
+        fs::write(
+            browser.join("environ"),
+            "HOME=/home/user\0FLATPAK_ID=org.mozilla.firefox\0",
+        )
+        .unwrap();
+        assert_eq!(
+            flatpak_app_id(&browser).as_deref(),
+            Some("org.mozilla.firefox")
+        );
+        assert_eq!(check_flatpak(200, &browser), None);
+
+        // Not sandboxed
+        assert_eq!(check_flatpak(300, &base.join("300")), None);
+
+        fs::remove_dir_all(&base).ok();
+    }
+
+    #[test]
+    fn test_wine_arch_from_environ() {
+        let environ = "HOME=/home/user\0WINEARCH=win64\0WINEPREFIX=/nonexistent\0";
+        assert_eq!(wine_arch_from_environ(environ), Some(WineArch::Win64));
+        assert_eq!(
+            wine_arch_from_environ("WINEARCH=win32\0"),
+            Some(WineArch::Win32)
+        );
+        assert_eq!(wine_arch_from_environ("MYWINEARCH=win64\0"), None);
+
+        // Prefix marker when only WINEPREFIX is set
+        let prefix = std::env::temp_dir().join(format!("ghostbrew-wine-{}", std::process::id()));
+        fs::create_dir_all(&prefix).unwrap();
+        fs::write(
+            prefix.join("system.reg"),
+            "WINE REGISTRY Version 2\n;; All keys relative to \\\\Machine\n\n#arch=win32\n",
+        )
+        .unwrap();
+        let environ = format!("WINEPREFIX={}\0", prefix.display());
+        assert_eq!(wine_arch_from_environ(&environ), Some(WineArch::Win32));
+        fs::remove_dir_all(&prefix).ok();
+    }
+
+    #[test]
+    fn test_wine_sync_from_environ() {
+        let environ = "HOME=/home/user\0WINEESYNC=1\0WINEFSYNC=1\0";
+        assert_eq!(wine_sync_from_environ(environ), Some(WineSync::Fsync));
+        assert_eq!(
+            wine_sync_from_environ("WINEESYNC=1\0WINEFSYNC=0\0"),
+            Some(WineSync::Esync)
+        );
+        assert_eq!(wine_sync_from_environ("PROTON_NO_FSYNC=1\0"), None);
+
+        // A game with neither variable but thousands of eventfds
+        let fixture = procfs::Fixture::new("wine-sync");
+        fixture.file("/proc/4242/environ", "WINEPREFIX=/pfx\0");
+        fixture.file("/proc/4343/environ", "WINEFSYNC=1\0");
+        for fd in 0..ESYNC_EVENTFD_MIN {
+            fixture.symlink(&format!("/proc/4242/fd/{}", fd), "anon_inode:[eventfd]");
+        }
+        fixture.run(|| {
+            assert_eq!(detect_wine_sync(4242), Some(WineSync::Esync));
+            assert_eq!(detect_wine_sync(4343), Some(WineSync::Fsync));
+        });
+    }
+
     #[test]
     fn test_dev_build_patterns_are_classified_as_batch() {
         for exe in [
@@ -334,6 +1373,138 @@ mod tests {
         ] {
             let exe = exe.to_lowercase();
             assert!(DEV_EXE_PATTERNS.iter().any(|pattern| exe.contains(pattern)));
+            assert_eq!(classify_exe(&exe), Some(WORKLOAD_BATCH));
         }
     }
+
+    #[test]
+    fn test_encoders_are_batch() {
+        assert_eq!(classify_exe("/usr/bin/ffmpeg"), Some(WORKLOAD_BATCH));
+        assert_eq!(classify_exe("/usr/bin/handbrakecli"), Some(WORKLOAD_BATCH));
+        assert_eq!(classify_exe("/usr/bin/x265"), Some(WORKLOAD_BATCH));
+
+        // OBS is interactive as a whole; only "obs" exactly
+        assert_eq!(classify_exe("/usr/bin/obs"), Some(WORKLOAD_INTERACTIVE));
+        assert_eq!(classify_exe("/opt/obsidian/obsidian"), None);
+
+        // Capture threads stay interactive, encoder threads are batch
+        assert_eq!(
+            classify_obs_thread("libobs: graphic"),
+            Some(WORKLOAD_INTERACTIVE)
+        );
+        assert_eq!(
+            classify_obs_thread("video-io: video"),
+            Some(WORKLOAD_INTERACTIVE)
+        );
+        assert_eq!(classify_obs_thread("obs-x264"), Some(WORKLOAD_BATCH));
+        assert_eq!(classify_obs_thread("nvenc-encoder"), Some(WORKLOAD_BATCH));
+        assert_eq!(classify_obs_thread("QXcbEventQueue"), None);
+    }
+
+    #[test]
+    fn test_classify_overrides() {
+        let overrides = ClassifyOverrides::new(
+            &["SpecificGame".to_string()],
+            &["winword.exe".to_string(), "visualstudiocode".to_string()],
+        );
+
+        // Denylisted .exe is excluded even though the pattern says gaming
+        let exe = "/home/user/.wine/drive_c/program files/microsoft office/winword.exe";
+        assert_eq!(classify_exe(exe), Some(WORKLOAD_GAMING));
+        assert_eq!(overrides.apply("winword.exe", exe, classify_exe(exe)), None);
+
+        // Under Wine the exe is the preloader, only comm carries the name
+        let preloader = "/usr/bin/wine64-preloader";
+        assert_eq!(
+            overrides.apply("winword.exe", preloader, Some(WORKLOAD_GAMING)),
+            None
+        );
+
+        // Truncated comm still matches a long entry
+        assert_eq!(
+            overrides.apply("visualstudioco", preloader, Some(WORKLOAD_GAMING)),
+            Some(WORKLOAD_GAMING)
+        );
+        assert_eq!(
+            overrides.apply("visualstudiocod", preloader, Some(WORKLOAD_GAMING)),
+            None
+        );
+
+        // Allowlisted process is forced to gaming
+        let exe = "/opt/specificgame/bin/specificgame";
+        assert_eq!(classify_exe(exe), None);
+        assert_eq!(
+            overrides.apply("specificgame", exe, classify_exe(exe)),
+            Some(WORKLOAD_GAMING)
+        );
+
+        // Denying a batch process leaves it batch
+        assert_eq!(
+            overrides.apply("winword.exe", "", Some(WORKLOAD_BATCH)),
+            Some(WORKLOAD_BATCH)
+        );
+        assert!(ClassifyOverrides::default().is_empty());
+    }
+
+    #[test]
+    fn test_browser_gpu_process_not_gaming() {
+        let browsers = BrowserFilter::new(&["Vivaldi-Bin".to_string()]);
+        let gaming = || Some(WORKLOAD_GAMING);
+
+        // Chromium GPU process started from Steam still comes out interactive
+        assert_eq!(
+            classify_with_browsers(&browsers, "chrome", "/opt/google/chrome/chrome", gaming),
+            Some(WORKLOAD_INTERACTIVE)
+        );
+        assert_eq!(
+            classify_with_browsers(&browsers, "firefox", "/usr/lib/firefox/firefox", || None),
+            Some(WORKLOAD_INTERACTIVE)
+        );
+
+        // Config extends the list; other processes keep their class
+        assert_eq!(
+            classify_with_browsers(&browsers, "vivaldi-bin", "/opt/vivaldi/vivaldi-bin", gaming),
+            Some(WORKLOAD_INTERACTIVE)
+        );
+        assert_eq!(
+            classify_with_browsers(&browsers, "game.exe", "/games/game.exe", gaming),
+            Some(WORKLOAD_GAMING)
+        );
+
+        // An explicit allowlist entry still wins
+        let overrides = ClassifyOverrides::new(&["firefox".to_string()], &[]);
+        assert_eq!(
+            overrides.apply("firefox", "", Some(WORKLOAD_INTERACTIVE)),
+            Some(WORKLOAD_GAMING)
+        );
+    }
+
+    #[test]
+    fn test_compositor_interactive() {
+        let compositors = CompositorFilter::new(&["Wayfire".to_string()]);
+
+        assert_eq!(
+            classify_with_compositors(
+                &compositors,
+                "kwin_wayland",
+                "/usr/bin/kwin_wayland",
+                || { None }
+            ),
+            Some(WORKLOAD_INTERACTIVE)
+        );
+        // Xorg's comm keeps its capital letter; callers lowercase it
+        assert!(compositors.is_compositor("xorg", "/usr/lib/xorg"));
+        assert!(compositors.is_compositor("wayfire", "/usr/bin/wayfire"));
+
+        // A random daemon stays unclassified
+        assert_eq!(
+            classify_with_compositors(
+                &compositors,
+                "systemd-journal",
+                "/usr/lib/systemd/systemd-journald",
+                || None
+            ),
+            None
+        );
+    }
 }
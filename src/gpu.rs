@@ -1,13 +1,19 @@
 // SPDX-License-Identifier: GPL-2.0
 //
-// GhostBrew - NVIDIA GPU Integration
+// GhostBrew - GPU Integration
+//
+// NVIDIA GPUs are read from the driver's /proc/driver/nvidia; every other
+// display controller with a host driver bound comes from sysfs, so hybrid
+// laptops see their iGPU next to the dGPU.
 //
 // Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
 
+use crate::pci;
+use crate::procfs;
+use crate::vm;
 use anyhow::{Context, Result};
-use log::{debug, info};
+use log::{debug, info, warn};
 use std::fs;
-use std::path::Path;
 
 /// GPU power state
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -33,19 +39,69 @@ impl std::fmt::Display for GpuPowerState {
     }
 }
 
-/// NVIDIA GPU information
+/// PCI vendor of a GPU
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GpuVendor {
+    Nvidia,
+    Amd,
+    Intel,
+    Other(u16),
+}
+
+impl GpuVendor {
+    /// Vendor from a sysfs `vendor` attribute ("0x10de")
+    pub fn from_pci_id(id: &str) -> Option<Self> {
+        let id = u16::from_str_radix(id.trim().trim_start_matches("0x"), 16).ok()?;
+        Some(match id {
+            0x10de => GpuVendor::Nvidia,
+            0x1002 => GpuVendor::Amd,
+            0x8086 => GpuVendor::Intel,
+            other => GpuVendor::Other(other),
+        })
+    }
+}
+
+impl std::fmt::Display for GpuVendor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GpuVendor::Nvidia => write!(f, "NVIDIA"),
+            GpuVendor::Amd => write!(f, "AMD"),
+            GpuVendor::Intel => write!(f, "Intel"),
+            GpuVendor::Other(id) => write!(f, "{:04x}", id),
+        }
+    }
+}
+
+/// Whether a GPU sits on the CPU package or on its own card
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GpuKind {
+    Discrete,
+    Integrated,
+}
+
+impl std::fmt::Display for GpuKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GpuKind::Discrete => write!(f, "discrete"),
+            GpuKind::Integrated => write!(f, "integrated"),
+        }
+    }
+}
+
+/// GPU information
 #[derive(Debug)]
 #[allow(dead_code)]
-pub struct NvidiaGpuInfo {
+pub struct GpuInfo {
     /// PCI device address (e.g., "0000:01:00.0")
     pub pci_address: String,
+    pub vendor: GpuVendor,
     /// GPU model name
     pub model: String,
     /// GPU UUID
     pub uuid: Option<String>,
     /// Whether Resizable BAR is enabled
     pub rebar_enabled: bool,
-    /// BAR1 size in bytes (VRAM mapping)
+    /// VRAM aperture in bytes (BAR1 on NVIDIA, the largest BAR otherwise)
     pub bar1_size: u64,
     /// Current power state
     pub power_state: GpuPowerState,
@@ -55,14 +111,207 @@ pub struct NvidiaGpuInfo {
     pub pcie_speed: String,
     /// PCIe link width (e.g., "x16")
     pub pcie_width: String,
+    /// Fastest link speed the device and the ports above it support
+    pub pcie_max_speed: String,
+    /// Widest link the device and the ports above it support
+    pub pcie_max_width: String,
+    /// Current link slower or narrower than the maximum, as last read while
+    /// the GPU was in D0 (idle GPUs retrain to a slower link)
+    pub link_degraded: bool,
+    /// Discrete card or integrated into the CPU package
+    pub kind: GpuKind,
+}
+
+impl GpuInfo {
+    /// Negotiated PCIe lanes (0 if unknown)
+    pub fn link_lanes(&self) -> u32 {
+        self.pcie_width.trim_start_matches('x').parse().unwrap_or(0)
+    }
+
+    /// "8.0 GT/s x4 of 32.0 GT/s x16"
+    pub fn link_summary(&self) -> String {
+        format!(
+            "{} {} of {} {}",
+            self.pcie_speed, self.pcie_width, self.pcie_max_speed, self.pcie_max_width
+        )
+    }
+
+    /// Re-read the current link (it retrains with ASPM and power state)
+    fn refresh_link(&mut self) {
+        let base = format!("/sys/bus/pci/devices/{}", self.pci_address);
+        (self.pcie_speed, self.pcie_width) = read_link(&base, "current");
+        self.link_degraded = link_degraded(
+            &self.pcie_speed,
+            &self.pcie_width,
+            &self.pcie_max_speed,
+            &self.pcie_max_width,
+        );
+    }
+}
+
+/// Transfer rate of a sysfs link speed ("16.0 GT/s PCIe") in GT/s
+fn parse_link_speed(speed: &str) -> Option<f64> {
+    speed.split_whitespace().next()?.parse().ok()
+}
+
+/// Maximum link of a device, capped by every port above it
+///
+/// A Gen5 card in a Gen4 slot can't go past Gen4, so the slot's maximum is
+/// the one to compare against. The sysfs device path nests each device
+/// under its upstream port; the walk stops at the host bridge, which has no
+/// link attributes.
+fn read_max_link(pci_address: &str) -> (String, String) {
+    let base = format!("/sys/bus/pci/devices/{}", pci_address);
+    let (mut speed, mut width) = read_link(&base, "max");
+    let Ok(mut dir) = fs::canonicalize(procfs::rooted(&base)) else {
+        return (speed, width);
+    };
+    let lanes = |w: &str| w.trim_start_matches('x').parse::<u32>().ok();
+
+    while let Some(port) = dir.parent().map(std::path::Path::to_path_buf) {
+        let read = |file: &str| fs::read_to_string(port.join(file)).ok();
+        let (Some(port_speed), Some(port_width)) = (read("max_link_speed"), read("max_link_width"))
+        else {
+            break;
+        };
+        let port_speed = port_speed.trim().to_string();
+        let port_width = format!("x{}", port_width.trim());
+        if let Some(port_rate) = parse_link_speed(&port_speed)
+            && parse_link_speed(&speed).is_none_or(|rate| port_rate < rate)
+        {
+            speed = port_speed;
+        }
+        if let Some(port_lanes) = lanes(&port_width)
+            && lanes(&width).is_none_or(|n| port_lanes < n)
+        {
+            width = port_width;
+        }
+        dir = port;
+    }
+    (speed, width)
+}
+
+/// Whether the current link runs below the maximum in speed or width
+///
+/// Unknown values (no sysfs attribute, integrated GPUs) never count.
+pub fn link_degraded(speed: &str, width: &str, max_speed: &str, max_width: &str) -> bool {
+    let lanes = |w: &str| w.trim_start_matches('x').parse::<u32>().ok();
+    let slower = matches!(
+        (parse_link_speed(speed), parse_link_speed(max_speed)),
+        (Some(cur), Some(max)) if cur < max
+    );
+    let narrower = matches!((lanes(width), lanes(max_width)), (Some(cur), Some(max)) if cur < max);
+    slower || narrower
+}
+
+/// Classify a GPU from its vendor and PCI location
+///
+/// NVIDIA has no PCI iGPUs, so its GPUs are always discrete, even on bus 00
+/// of a VM. Other integrated GPUs are root-complex endpoints without a PCIe
+/// link (Intel at 00:02.0), or, for AMD APUs that sit behind an internal
+/// bridge, have no dedicated VRAM (amdgpu only exposes
+/// `mem_info_vram_vendor` for VRAM chips of its own).
+fn gpu_kind(vendor: GpuVendor, pci_address: &str, pcie_width: &str, vram: bool) -> GpuKind {
+    let bus = pci_address.split(':').nth(1).unwrap_or("");
+    match vendor {
+        GpuVendor::Nvidia => GpuKind::Discrete,
+        _ if bus == "00" || pcie_width == "unknown" => GpuKind::Integrated,
+        GpuVendor::Amd if !vram => GpuKind::Integrated,
+        _ => GpuKind::Discrete,
+    }
+}
+
+/// Order GPUs best first: discrete before integrated, then larger BAR1
+/// (dedicated VRAM), then wider PCIe link. Ties keep detection order.
+fn rank_gpus(gpus: &mut [GpuInfo]) {
+    gpus.sort_by_key(|gpu| {
+        std::cmp::Reverse((
+            gpu.kind == GpuKind::Discrete,
+            gpu.bar1_size,
+            gpu.link_lanes(),
+        ))
+    });
+}
+
+/// Detect host GPUs: NVIDIA ones through the driver, then every other
+/// display controller bound to a host driver
+pub fn detect_gpus() -> Result<Vec<GpuInfo>> {
+    let mut gpus = detect_nvidia_gpus()?;
+    let known: Vec<String> = gpus.iter().map(|g| g.pci_address.clone()).collect();
+    for gpu in detect_pci_gpus(&known) {
+        info!(
+            "Detected {} GPU: {} at {}",
+            gpu.kind, gpu.model, gpu.pci_address
+        );
+        gpus.push(gpu);
+    }
+    Ok(gpus)
+}
+
+/// Display controllers in sysfs with a host driver, other than `known`
+///
+/// Unbound and vfio-pci devices belong to nobody or to a VM and are skipped.
+fn detect_pci_gpus(known: &[String]) -> Vec<GpuInfo> {
+    let Ok(entries) = fs::read_dir(procfs::rooted("/sys/bus/pci/devices")) else {
+        return Vec::new();
+    };
+    let mut addrs: Vec<String> = entries
+        .flatten()
+        .map(|entry| pci::normalize(&entry.file_name().to_string_lossy()))
+        .filter(|addr| !known.contains(addr))
+        .collect();
+    addrs.sort();
+
+    addrs.iter().filter_map(|addr| read_pci_gpu(addr)).collect()
+}
+
+/// GpuInfo for a non-NVIDIA-driver display controller, None if it isn't one
+/// or has no host driver
+fn read_pci_gpu(pci_address: &str) -> Option<GpuInfo> {
+    let base = format!("/sys/bus/pci/devices/{}", pci_address);
+    let read = |file: &str| procfs::read_rooted(format!("{}/{}", base, file)).ok();
+
+    if !read("class")?.trim().starts_with("0x03") {
+        return None;
+    }
+    fs::read_link(procfs::rooted(format!("{}/driver", base))).ok()?;
+    if vm::is_vfio_bound(pci_address) {
+        return None;
+    }
+    let vendor = GpuVendor::from_pci_id(&read("vendor")?)?;
+    let device = read("device").unwrap_or_default();
+
+    let power_state = read_gpu_power_state(pci_address);
+    let (numa_node, pcie_speed, pcie_width, _) = read_pci_info(pci_address);
+    let (pcie_max_speed, pcie_max_width) = read_max_link(pci_address);
+    let link_degraded = power_state == GpuPowerState::D0
+        && link_degraded(&pcie_speed, &pcie_width, &pcie_max_speed, &pcie_max_width);
+    let vram = read("mem_info_vram_vendor").is_some();
+
+    Some(GpuInfo {
+        pci_address: pci_address.to_string(),
+        vendor,
+        model: format!("{} GPU [{}]", vendor, device.trim()),
+        uuid: None,
+        rebar_enabled: false,
+        bar1_size: read_largest_bar_size(&base),
+        power_state,
+        numa_node,
+        kind: gpu_kind(vendor, pci_address, &pcie_width, vram),
+        pcie_speed,
+        pcie_width,
+        pcie_max_speed,
+        pcie_max_width,
+        link_degraded,
+    })
 }
 
-/// Detect NVIDIA GPUs on the system
-pub fn detect_nvidia_gpus() -> Result<Vec<NvidiaGpuInfo>> {
+/// Detect NVIDIA GPUs through the driver
+fn detect_nvidia_gpus() -> Result<Vec<GpuInfo>> {
     let mut gpus = Vec::new();
 
     // Check if NVIDIA driver is loaded
-    let nvidia_params = Path::new("/proc/driver/nvidia/params");
+    let nvidia_params = procfs::rooted("/proc/driver/nvidia/params");
     if !nvidia_params.exists() {
         debug!("NVIDIA driver not loaded");
         return Ok(gpus);
@@ -75,14 +324,15 @@ pub fn detect_nvidia_gpus() -> Result<Vec<NvidiaGpuInfo>> {
     }
 
     // Find NVIDIA GPUs in /proc/driver/nvidia/gpus/
-    let gpus_dir = Path::new("/proc/driver/nvidia/gpus");
-    if !gpus_dir.exists() {
+    let gpus_dir = procfs::rooted("/proc/driver/nvidia/gpus");
+    let Ok(entries) = fs::read_dir(&gpus_dir) else {
+        log_no_host_gpus();
         return Ok(gpus);
-    }
+    };
 
-    for entry in fs::read_dir(gpus_dir)? {
+    for entry in entries {
         let entry = entry?;
-        let pci_address = entry.file_name().to_string_lossy().to_string();
+        let pci_address = pci::normalize(&entry.file_name().to_string_lossy());
 
         if let Ok(gpu_info) = read_gpu_info(&pci_address, rebar_enabled) {
             info!(
@@ -93,14 +343,71 @@ pub fn detect_nvidia_gpus() -> Result<Vec<NvidiaGpuInfo>> {
         }
     }
 
+    if gpus.is_empty() {
+        log_no_host_gpus();
+    }
     Ok(gpus)
 }
 
+/// Why the NVIDIA driver is loaded but enumerates no GPUs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NoHostGpus {
+    /// Every NVIDIA GPU is bound to vfio-pci (passed through to VMs)
+    PassedThrough(Vec<String>),
+    /// No NVIDIA GPU is visible at all
+    NoneFound,
+}
+
+/// NVIDIA display controllers in sysfs bound to vfio-pci
+pub fn vfio_bound_nvidia_gpus() -> Vec<String> {
+    let Ok(entries) = fs::read_dir(procfs::rooted("/sys/bus/pci/devices")) else {
+        return Vec::new();
+    };
+    let read = |addr: &str, file: &str| {
+        procfs::read_rooted(format!("/sys/bus/pci/devices/{}/{}", addr, file)).unwrap_or_default()
+    };
+
+    let mut devices: Vec<String> = entries
+        .flatten()
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|addr| {
+            read(addr, "vendor").trim() == "0x10de"
+                && read(addr, "class").trim().starts_with("0x03")
+                && vm::is_vfio_bound(addr)
+        })
+        .collect();
+    devices.sort();
+    devices
+}
+
+/// Interpret a loaded driver with no GPUs under /proc/driver/nvidia/gpus
+pub fn no_host_gpus_reason() -> NoHostGpus {
+    let passed_through = vfio_bound_nvidia_gpus();
+    if passed_through.is_empty() {
+        NoHostGpus::NoneFound
+    } else {
+        NoHostGpus::PassedThrough(passed_through)
+    }
+}
+
+fn log_no_host_gpus() {
+    match no_host_gpus_reason() {
+        NoHostGpus::PassedThrough(devices) => info!(
+            "NVIDIA driver loaded, but all NVIDIA GPUs are passed through (vfio-pci: {}); \
+             no host GPUs to monitor",
+            devices.join(", ")
+        ),
+        NoHostGpus::NoneFound => {
+            warn!("NVIDIA driver loaded, but it enumerates no GPUs")
+        }
+    }
+}
+
 /// Check if Resizable BAR is enabled in NVIDIA driver
 fn check_rebar_enabled() -> bool {
     let params_path = "/proc/driver/nvidia/params";
 
-    if let Ok(content) = fs::read_to_string(params_path) {
+    if let Ok(content) = procfs::read_rooted(params_path) {
         for line in content.lines() {
             if line.starts_with("EnableResizableBar:") {
                 return line.contains("1");
@@ -112,12 +419,12 @@ fn check_rebar_enabled() -> bool {
 }
 
 /// Read detailed info for a specific GPU
-fn read_gpu_info(pci_address: &str, rebar_enabled: bool) -> Result<NvidiaGpuInfo> {
+fn read_gpu_info(pci_address: &str, rebar_enabled: bool) -> Result<GpuInfo> {
     let base_path = format!("/proc/driver/nvidia/gpus/{}", pci_address);
 
     // Read GPU information
     let info_path = format!("{}/information", base_path);
-    let info_content = fs::read_to_string(&info_path)
+    let info_content = procfs::read_rooted(&info_path)
         .with_context(|| format!("Failed to read GPU info at {}", info_path))?;
 
     let model = parse_gpu_field(&info_content, "Model:")
@@ -129,9 +436,14 @@ fn read_gpu_info(pci_address: &str, rebar_enabled: bool) -> Result<NvidiaGpuInfo
 
     // Read PCI info
     let (numa_node, pcie_speed, pcie_width, bar1_size) = read_pci_info(pci_address);
+    let kind = gpu_kind(GpuVendor::Nvidia, pci_address, &pcie_width, true);
+    let (pcie_max_speed, pcie_max_width) = read_max_link(pci_address);
+    let link_degraded = power_state == GpuPowerState::D0
+        && link_degraded(&pcie_speed, &pcie_width, &pcie_max_speed, &pcie_max_width);
 
-    Ok(NvidiaGpuInfo {
+    Ok(GpuInfo {
         pci_address: pci_address.to_string(),
+        vendor: GpuVendor::Nvidia,
         model,
         uuid,
         rebar_enabled,
@@ -140,6 +452,10 @@ fn read_gpu_info(pci_address: &str, rebar_enabled: bool) -> Result<NvidiaGpuInfo
         numa_node,
         pcie_speed,
         pcie_width,
+        pcie_max_speed,
+        pcie_max_width,
+        link_degraded,
+        kind,
     })
 }
 
@@ -160,7 +476,7 @@ fn parse_gpu_field(content: &str, field: &str) -> Option<String> {
 fn read_gpu_power_state(pci_address: &str) -> GpuPowerState {
     // Try NVIDIA driver power info
     let power_path = format!("/proc/driver/nvidia/gpus/{}/power", pci_address);
-    if let Ok(content) = fs::read_to_string(&power_path)
+    if let Ok(content) = procfs::read_rooted(&power_path)
         && content.contains("Runtime D3 status:")
         && (content.contains("Disabled") || content.contains("Not supported"))
     {
@@ -169,7 +485,7 @@ fn read_gpu_power_state(pci_address: &str) -> GpuPowerState {
 
     // Check PCI power state
     let pci_power_path = format!("/sys/bus/pci/devices/{}/power_state", pci_address);
-    if let Ok(state) = fs::read_to_string(&pci_power_path) {
+    if let Ok(state) = procfs::read_rooted(&pci_power_path) {
         match state.trim() {
             "D0" => return GpuPowerState::D0,
             "D1" => return GpuPowerState::D1,
@@ -188,69 +504,149 @@ fn read_pci_info(pci_address: &str) -> (i32, String, String, u64) {
     let base = format!("/sys/bus/pci/devices/{}", pci_address);
 
     // NUMA node
-    let numa_node = fs::read_to_string(format!("{}/numa_node", base))
+    let numa_node = procfs::read_rooted(format!("{}/numa_node", base))
         .ok()
         .and_then(|s| s.trim().parse().ok())
         .unwrap_or(-1);
 
-    // PCIe speed
-    let pcie_speed = fs::read_to_string(format!("{}/current_link_speed", base))
+    // PCIe speed and width
+    let (pcie_speed, pcie_width) = read_link(&base, "current");
+
+    // BAR1 size from resource file
+    let bar1_size = read_bar1_size(&base);
+
+    (numa_node, pcie_speed, pcie_width, bar1_size)
+}
+
+/// Read a PCIe link's speed and width; `which` is "current" or "max"
+fn read_link(pci_base: &str, which: &str) -> (String, String) {
+    let speed = procfs::read_rooted(format!("{}/{}_link_speed", pci_base, which))
         .map(|s| s.trim().to_string())
         .unwrap_or_else(|_| "unknown".to_string());
-
-    // PCIe width
-    let pcie_width = fs::read_to_string(format!("{}/current_link_width", base))
+    let width = procfs::read_rooted(format!("{}/{}_link_width", pci_base, which))
         .map(|s| format!("x{}", s.trim()))
         .unwrap_or_else(|_| "unknown".to_string());
+    (speed, width)
+}
 
-    // BAR1 size from resource file
-    let bar1_size = read_bar1_size(&base);
+/// Sizes of the BARs in a PCI resource file (0 for unused ones)
+fn read_bar_sizes(pci_base: &str) -> Vec<u64> {
+    let Ok(content) = procfs::read_rooted(format!("{}/resource", pci_base)) else {
+        return Vec::new();
+    };
+    // resource file format: start end flags (one line per BAR, then ROM)
+    content
+        .lines()
+        .take(6)
+        .map(|line| {
+            let mut parts = line
+                .split_whitespace()
+                .map(|p| u64::from_str_radix(p.trim_start_matches("0x"), 16).ok());
+            match (parts.next().flatten(), parts.next().flatten()) {
+                (Some(start), Some(end)) if end > start => end - start + 1,
+                _ => 0,
+            }
+        })
+        .collect()
+}
 
-    (numa_node, pcie_speed, pcie_width, bar1_size)
+/// Largest BAR, the VRAM aperture on AMD (BAR0) and Intel (BAR2) GPUs
+fn read_largest_bar_size(pci_base: &str) -> u64 {
+    read_bar_sizes(pci_base).into_iter().max().unwrap_or(0)
 }
 
 /// Read BAR1 (VRAM) size from PCI resource file
 fn read_bar1_size(pci_base: &str) -> u64 {
-    let resource_path = format!("{}/resource", pci_base);
-
-    if let Ok(content) = fs::read_to_string(&resource_path) {
-        // resource file format: start end flags (one line per BAR)
-        // BAR1 is typically the second line (index 1)
-        for (idx, line) in content.lines().enumerate() {
-            if idx == 1 {
-                // BAR1
-                let parts: Vec<&str> = line.split_whitespace().collect();
-                if parts.len() >= 2
-                    && let (Ok(start), Ok(end)) = (
-                        u64::from_str_radix(parts[0].trim_start_matches("0x"), 16),
-                        u64::from_str_radix(parts[1].trim_start_matches("0x"), 16),
-                    )
-                    && end > start
-                {
-                    return end - start + 1;
-                }
+    read_bar_sizes(pci_base).get(1).copied().unwrap_or(0)
+}
+
+/// Default number of consecutive polls before the GPU activity state flips
+pub const DEFAULT_DEBOUNCE_POLLS: u32 = 3;
+
+/// N-sample debouncer for a boolean signal
+///
+/// The stable output only changes after `window` consecutive samples
+/// disagree with it, so short power-gating blips are ignored.
+#[derive(Debug, Clone)]
+pub struct Debouncer {
+    window: u32,
+    stable: bool,
+    streak: u32,
+}
+
+impl Debouncer {
+    pub fn new(window: u32, initial: bool) -> Self {
+        Self {
+            window: window.max(1),
+            stable: initial,
+            streak: 0,
+        }
+    }
+
+    /// Feed a sample and return the debounced state
+    pub fn update(&mut self, sample: bool) -> bool {
+        if sample == self.stable {
+            self.streak = 0;
+        } else {
+            self.streak += 1;
+            if self.streak >= self.window {
+                self.stable = sample;
+                self.streak = 0;
             }
         }
+        self.stable
     }
 
-    0
+    pub fn stable(&self) -> bool {
+        self.stable
+    }
 }
 
 /// GPU state tracker for monitoring changes
 pub struct GpuMonitor {
-    gpus: Vec<NvidiaGpuInfo>,
+    gpus: Vec<GpuInfo>,
     last_power_states: Vec<GpuPowerState>,
+    /// Debounced "primary GPU in D0"
+    activity: Debouncer,
+    /// Degraded link already warned about, per GPU
+    link_warned: Vec<bool>,
 }
 
 impl GpuMonitor {
     pub fn new() -> Result<Self> {
-        let gpus = detect_nvidia_gpus()?;
+        Ok(Self::from_gpus(detect_gpus()?))
+    }
+
+    /// Build a monitor over already-detected GPUs, ranked best first
+    ///
+    /// Links are only judged by check_links once the GPU has been active, so
+    /// one read at startup from an idle GPU isn't reported.
+    fn from_gpus(mut gpus: Vec<GpuInfo>) -> Self {
+        rank_gpus(&mut gpus);
+        for gpu in &mut gpus {
+            gpu.link_degraded = false;
+        }
         let last_power_states = gpus.iter().map(|g| g.power_state).collect();
+        let link_warned = vec![false; gpus.len()];
 
-        Ok(Self {
+        let mut monitor = Self {
             gpus,
             last_power_states,
-        })
+            activity: Debouncer::new(DEFAULT_DEBOUNCE_POLLS, false),
+            link_warned,
+        };
+        monitor.activity = Debouncer::new(DEFAULT_DEBOUNCE_POLLS, monitor.primary_active());
+        monitor
+    }
+
+    /// Set how many consecutive polls are needed to flip active/idle
+    pub fn set_debounce_polls(&mut self, polls: u32) {
+        self.activity = Debouncer::new(polls, self.activity.stable());
+    }
+
+    /// Whether a GPU has been active (D0) for the whole debounce window
+    pub fn is_active_debounced(&self) -> bool {
+        self.activity.stable()
     }
 
     /// Check if any GPU is active (D0 state)
@@ -258,6 +654,15 @@ impl GpuMonitor {
         self.gpus.iter().any(|g| g.power_state == GpuPowerState::D0)
     }
 
+    /// Whether the GPU that drives activity decisions is in D0
+    ///
+    /// An integrated GPU scanning out the desktop is always awake, so only
+    /// the primary (discrete when present) GPU counts.
+    fn primary_active(&self) -> bool {
+        self.primary_gpu()
+            .is_some_and(|g| g.power_state == GpuPowerState::D0)
+    }
+
     /// Check if ReBAR is enabled on any GPU
     pub fn rebar_enabled(&self) -> bool {
         self.gpus.iter().any(|g| g.rebar_enabled)
@@ -287,13 +692,42 @@ impl GpuMonitor {
             gpu.power_state = new_state;
         }
 
+        let active = self.primary_active();
+        self.activity.update(active);
+        if self.activity.stable() {
+            self.check_links();
+        }
+
         changed
     }
 
+    /// Warn once per GPU when an active GPU's link runs below its maximum
+    fn check_links(&mut self) {
+        for (gpu, warned) in self.gpus.iter_mut().zip(self.link_warned.iter_mut()) {
+            if gpu.kind != GpuKind::Discrete || gpu.power_state != GpuPowerState::D0 {
+                continue;
+            }
+            gpu.refresh_link();
+            if gpu.link_degraded && !*warned {
+                warn!(
+                    "GPU {} PCIe link degraded while active: {} (check ASPM, riser and slot)",
+                    gpu.pci_address,
+                    gpu.link_summary()
+                );
+            }
+            *warned = gpu.link_degraded;
+        }
+    }
+
+    /// Discrete GPUs whose link runs below its maximum
+    pub fn degraded_links(&self) -> impl Iterator<Item = &GpuInfo> {
+        self.gpus.iter().filter(|g| g.link_degraded)
+    }
+
     /// Get summary for logging
     pub fn summary(&self) -> String {
         if self.gpus.is_empty() {
-            return "No NVIDIA GPUs detected".to_string();
+            return "No GPUs detected".to_string();
         }
 
         let rebar = if self.rebar_enabled() {
@@ -303,12 +737,20 @@ impl GpuMonitor {
         };
         let bar1_gb = self.total_bar1_size() as f64 / (1024.0 * 1024.0 * 1024.0);
 
-        format!(
+        let mut summary = format!(
             "{} GPU(s), {}, {:.0}GB BAR1",
             self.gpus.len(),
             rebar,
             bar1_gb
-        )
+        );
+        for gpu in self.degraded_links() {
+            summary.push_str(&format!(
+                ", PCIe link degraded on {} ({})",
+                gpu.pci_address,
+                gpu.link_summary()
+            ));
+        }
+        summary
     }
 
     /// Get GPU count
@@ -316,11 +758,31 @@ impl GpuMonitor {
         self.gpus.len()
     }
 
-    /// Get first GPU info (primary)
-    pub fn primary_gpu(&self) -> Option<&NvidiaGpuInfo> {
+    /// NUMA node of each GPU, primary first (-1 if not assigned)
+    pub fn numa_nodes(&self) -> Vec<i32> {
+        self.gpus.iter().map(|gpu| gpu.numa_node).collect()
+    }
+
+    /// Detected GPUs, ranked best first
+    pub fn gpus(&self) -> &[GpuInfo] {
+        &self.gpus
+    }
+
+    /// Best-ranked GPU: the discrete one on hybrid laptops
+    pub fn primary_gpu(&self) -> Option<&GpuInfo> {
         self.gpus.first()
     }
 
+    /// Highest-ranked discrete GPU
+    pub fn discrete_gpu(&self) -> Option<&GpuInfo> {
+        self.gpus.iter().find(|g| g.kind == GpuKind::Discrete)
+    }
+
+    /// Integrated GPU, if one was detected
+    pub fn integrated_gpu(&self) -> Option<&GpuInfo> {
+        self.gpus.iter().find(|g| g.kind == GpuKind::Integrated)
+    }
+
     /// Read GPU utilization percentage (0-100) for the primary GPU
     ///
     /// Tries multiple sources in order:
@@ -348,7 +810,7 @@ impl GpuMonitor {
         // AMD GPUs expose utilization via drm sysfs
         for card_num in 0..4 {
             let path = format!("/sys/class/drm/card{}/device/gpu_busy_percent", card_num);
-            if let Ok(content) = fs::read_to_string(&path)
+            if let Ok(content) = procfs::read_rooted(&path)
                 && let Ok(util) = content.trim().parse::<u32>()
             {
                 return Ok(util.min(100));
@@ -375,7 +837,7 @@ impl GpuMonitor {
 
         // Fallback: try reading from /proc
         let utilization_path = format!("/proc/driver/nvidia/gpus/{}/utilization", pci_address);
-        if let Ok(content) = fs::read_to_string(&utilization_path) {
+        if let Ok(content) = procfs::read_rooted(&utilization_path) {
             for line in content.lines() {
                 if line.contains("Graphics:")
                     && let Some(pct) = line.split(':').nth(1)
@@ -417,6 +879,8 @@ impl Default for GpuMonitor {
         Self::new().unwrap_or(Self {
             gpus: Vec::new(),
             last_power_states: Vec::new(),
+            activity: Debouncer::new(DEFAULT_DEBOUNCE_POLLS, false),
+            link_warned: Vec::new(),
         })
     }
 }
@@ -437,6 +901,13 @@ pub const GPU_THREAD_PATTERNS: &[&str] = &[
     "vkd3d", // VKD3D (D3D12 translation)
 ];
 
+/// Shader/pipeline compilation threads (DXVK, VKD3D-Proton, Fossilize replay)
+///
+/// These spike CPU while the game compiles pipelines and do better on the
+/// frequency die than on the V-Cache cores the render thread needs.
+pub const SHADER_THREAD_PATTERNS: &[&str] =
+    &["dxvk-shader", "vkd3d-pipe", "vkd3d_pipe", "fossilize"];
+
 /// GPU bottleneck state for scheduler coordination
 #[allow(dead_code)] // Scaffolding for future GPU coordination integration
 #[derive(Debug, Clone, Copy, PartialEq, Default)]
@@ -475,16 +946,209 @@ impl std::fmt::Display for GpuBottleneck {
 /// Check if a process name looks like a GPU-feeding thread
 #[allow(dead_code)]
 pub fn is_gpu_thread_name(name: &str) -> bool {
+    if is_shader_thread_name(name) {
+        return false;
+    }
+
     let lower = name.to_lowercase();
     GPU_THREAD_PATTERNS
         .iter()
         .any(|p| lower.contains(&p.to_lowercase()))
 }
 
+/// Check if a thread name is a shader/pipeline compilation worker
+pub fn is_shader_thread_name(name: &str) -> bool {
+    let lower = name.to_lowercase();
+    SHADER_THREAD_PATTERNS.iter().any(|p| lower.starts_with(p))
+}
+
+/// GpuInfo built from its arguments without sysfs, for tests
+#[cfg(test)]
+pub fn synthetic_gpu(
+    vendor: GpuVendor,
+    pci_address: &str,
+    bar1_size: u64,
+    pcie_width: &str,
+) -> GpuInfo {
+    GpuInfo {
+        pci_address: pci_address.to_string(),
+        vendor,
+        model: pci_address.to_string(),
+        uuid: None,
+        rebar_enabled: false,
+        bar1_size,
+        power_state: GpuPowerState::D0,
+        numa_node: -1,
+        pcie_speed: "unknown".to_string(),
+        pcie_width: pcie_width.to_string(),
+        pcie_max_speed: "unknown".to_string(),
+        pcie_max_width: pcie_width.to_string(),
+        link_degraded: false,
+        kind: gpu_kind(vendor, pci_address, pcie_width, vendor != GpuVendor::Intel),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_debouncer_ignores_blips() {
+        let mut debouncer = Debouncer::new(3, false);
+
+        // Single D0 blips during light load stay idle
+        let samples = [true, false, true, true, false, false];
+        for sample in samples {
+            assert!(!debouncer.update(sample));
+        }
+
+        // Three consecutive active polls flip to active, then a blip is ignored
+        assert!(!debouncer.update(true));
+        assert!(!debouncer.update(true));
+        assert!(debouncer.update(true));
+        assert!(debouncer.update(false));
+        assert!(debouncer.update(true));
+
+        // Three idle polls flip back
+        assert!(debouncer.update(false));
+        assert!(debouncer.update(false));
+        assert!(!debouncer.update(false));
+
+        // Window of 0 behaves like 1 (no debounce)
+        assert!(Debouncer::new(0, false).update(true));
+    }
+
+    #[test]
+    fn test_gpu_kind() {
+        // Intel iGPU: root-complex endpoint without a link
+        assert_eq!(
+            gpu_kind(GpuVendor::Intel, "0000:00:02.0", "unknown", false),
+            GpuKind::Integrated
+        );
+        // Intel Arc behind a root port
+        assert_eq!(
+            gpu_kind(GpuVendor::Intel, "0000:03:00.0", "x16", false),
+            GpuKind::Discrete
+        );
+        // AMD APU behind its internal bridge, with and without VRAM of its own
+        assert_eq!(
+            gpu_kind(GpuVendor::Amd, "0000:c4:00.0", "x16", false),
+            GpuKind::Integrated
+        );
+        assert_eq!(
+            gpu_kind(GpuVendor::Amd, "0000:03:00.0", "x16", true),
+            GpuKind::Discrete
+        );
+        // NVIDIA passed into a VM lands on bus 00 without a link
+        assert_eq!(
+            gpu_kind(GpuVendor::Nvidia, "0000:00:05.0", "unknown", true),
+            GpuKind::Discrete
+        );
+    }
+
+    #[test]
+    fn test_link_degraded() {
+        // Full Gen4 x16 link
+        assert!(!link_degraded(
+            "16.0 GT/s PCIe",
+            "x16",
+            "16.0 GT/s PCIe",
+            "x16"
+        ));
+        // Dropped to PCIe 1.1 speed, or to x4 lanes
+        assert!(link_degraded(
+            "2.5 GT/s PCIe",
+            "x16",
+            "16.0 GT/s PCIe",
+            "x16"
+        ));
+        assert!(link_degraded(
+            "16.0 GT/s PCIe",
+            "x4",
+            "16.0 GT/s PCIe",
+            "x16"
+        ));
+        // Unknown values (integrated GPUs) never count
+        assert!(!link_degraded(
+            "unknown",
+            "unknown",
+            "16.0 GT/s PCIe",
+            "x16"
+        ));
+        assert!(!link_degraded("8.0 GT/s PCIe", "x8", "unknown", "unknown"));
+    }
+
+    #[test]
+    fn test_link_capped_by_upstream_port() {
+        // Gen5 x16 card in a Gen4 x16 slot, nested under its root port
+        let fixture = procfs::Fixture::new("gpu-link-upstream");
+        let port = "/sys/devices/pci0000:00/0000:00:01.1";
+        let dev = format!("{}/0000:01:00.0", port);
+        fixture
+            .file(&format!("{}/max_link_speed", port), "16.0 GT/s PCIe\n")
+            .file(&format!("{}/max_link_width", port), "16\n")
+            .file(&format!("{}/power_state", dev), "D0\n")
+            .file(&format!("{}/current_link_speed", dev), "16.0 GT/s PCIe\n")
+            .file(&format!("{}/current_link_width", dev), "16\n")
+            .file(&format!("{}/max_link_speed", dev), "32.0 GT/s PCIe\n")
+            .file(&format!("{}/max_link_width", dev), "16\n")
+            .symlink(
+                "/sys/bus/pci/devices/0000:01:00.0",
+                "../../../devices/pci0000:00/0000:00:01.1/0000:01:00.0",
+            );
+
+        fixture.run(|| {
+            let mut gpu = synthetic_gpu(GpuVendor::Nvidia, "0000:01:00.0", 16 << 30, "x16");
+            (gpu.pcie_max_speed, gpu.pcie_max_width) = read_max_link(&gpu.pci_address);
+            assert_eq!(gpu.pcie_max_speed, "16.0 GT/s PCIe");
+            let mut monitor = GpuMonitor::from_gpus(vec![gpu]);
+            monitor.update_power_states();
+            assert_eq!(monitor.degraded_links().count(), 0);
+        });
+
+        // The same card trained down to x4 while active is reported...
+        fixture.file(&format!("{}/current_link_width", dev), "4\n");
+        fixture.run(|| {
+            let mut gpu = synthetic_gpu(GpuVendor::Nvidia, "0000:01:00.0", 16 << 30, "x4");
+            (gpu.pcie_max_speed, gpu.pcie_max_width) = read_max_link(&gpu.pci_address);
+            gpu.link_degraded = true;
+            let mut monitor = GpuMonitor::from_gpus(vec![gpu]);
+            // ...but not before the monitor has seen it active
+            assert_eq!(monitor.degraded_links().count(), 0);
+            monitor.update_power_states();
+            assert!(monitor.summary().contains(
+                "PCIe link degraded on 0000:01:00.0 (16.0 GT/s PCIe x4 of 16.0 GT/s PCIe x16)"
+            ));
+        });
+    }
+
+    #[test]
+    fn test_discrete_gpu_ranked_primary() {
+        // iGPU enumerated first, dGPU suspended behind a root port
+        let igpu = synthetic_gpu(GpuVendor::Intel, "0000:00:02.0", 256 << 20, "unknown");
+        let mut dgpu = synthetic_gpu(GpuVendor::Nvidia, "0000:01:00.0", 16 << 30, "x16");
+        dgpu.power_state = GpuPowerState::D3Cold;
+        let monitor = GpuMonitor::from_gpus(vec![igpu, dgpu]);
+
+        assert_eq!(monitor.primary_gpu().unwrap().pci_address, "0000:01:00.0");
+        assert_eq!(monitor.discrete_gpu().unwrap().kind, GpuKind::Discrete);
+        assert_eq!(
+            monitor.integrated_gpu().unwrap().pci_address,
+            "0000:00:02.0"
+        );
+
+        // The always-on iGPU does not make the system look GPU-active
+        assert!(monitor.any_gpu_active());
+        assert!(!monitor.is_active_debounced());
+
+        // Among discrete cards, the bigger BAR1 and wider link wins
+        let small = synthetic_gpu(GpuVendor::Nvidia, "0000:02:00.0", 256 << 20, "x4");
+        let big = synthetic_gpu(GpuVendor::Nvidia, "0000:01:00.0", 16 << 30, "x16");
+        let monitor = GpuMonitor::from_gpus(vec![small, big]);
+        assert_eq!(monitor.primary_gpu().unwrap().link_lanes(), 16);
+        assert!(monitor.integrated_gpu().is_none());
+    }
+
     #[test]
     fn test_detect_nvidia_gpus() {
         // Just verify it doesn't panic
@@ -498,4 +1162,139 @@ mod tests {
         assert!(is_gpu_thread_name("nvidia-modeset"));
         assert!(!is_gpu_thread_name("bash"));
     }
+
+    #[test]
+    fn test_shader_thread_vs_game_thread() {
+        // DXVK compile workers are split out from the render/submit threads
+        assert!(is_shader_thread_name("dxvk-shader-0"));
+        assert!(!is_gpu_thread_name("dxvk-shader-0"));
+        assert!(is_gpu_thread_name("dxvk-submit"));
+        assert!(!is_shader_thread_name("dxvk-submit"));
+
+        assert!(is_shader_thread_name("vkd3d-pipe"));
+        assert!(is_shader_thread_name("fossilize-repla"));
+
+        // The main game thread is neither
+        assert!(!is_shader_thread_name("Cyberpunk2077.e"));
+        assert!(!is_shader_thread_name("GameThread"));
+    }
+
+    #[test]
+    fn test_detect_nvidia_gpus_from_fixture() {
+        let fixture = procfs::Fixture::new("gpu");
+        let pci = "/sys/bus/pci/devices/0000:01:00.0";
+        fixture
+            .file("/proc/driver/nvidia/params", "EnableResizableBar: 1\n")
+            .file(
+                "/proc/driver/nvidia/gpus/0000:01:00.0/information",
+                "Model: \t\t NVIDIA GeForce RTX 4090\nGPU UUID: \t GPU-0c7e\n",
+            )
+            .file(&format!("{}/numa_node", pci), "0\n")
+            .file(&format!("{}/power_state", pci), "D0\n")
+            .file(&format!("{}/current_link_speed", pci), "16.0 GT/s PCIe\n")
+            .file(&format!("{}/current_link_width", pci), "8\n")
+            .file(&format!("{}/max_link_speed", pci), "16.0 GT/s PCIe\n")
+            .file(&format!("{}/max_link_width", pci), "16\n")
+            .file(
+                &format!("{}/resource", pci),
+                "0x00000000f2000000 0x00000000f2ffffff 0x0000000000040200\n\
+                 0x0000006000000000 0x00000067ffffffff 0x000000000014220c\n",
+            );
+
+        let gpus = fixture.run(detect_nvidia_gpus).unwrap();
+        assert_eq!(gpus.len(), 1);
+        let gpu = &gpus[0];
+        assert_eq!(gpu.model, "NVIDIA GeForce RTX 4090");
+        assert_eq!(gpu.uuid.as_deref(), Some("GPU-0c7e"));
+        assert!(gpu.rebar_enabled);
+        assert_eq!(gpu.bar1_size, 32 << 30);
+        assert_eq!(gpu.power_state, GpuPowerState::D0);
+        assert_eq!(gpu.numa_node, 0);
+        assert_eq!(gpu.kind, GpuKind::Discrete);
+        assert_eq!(
+            gpu.link_summary(),
+            "16.0 GT/s PCIe x8 of 16.0 GT/s PCIe x16"
+        );
+        assert!(gpu.link_degraded);
+    }
+
+    #[test]
+    fn test_driver_loaded_gpus_passed_through() {
+        let fixture = procfs::Fixture::new("gpu-vfio");
+        fixture.file("/proc/driver/nvidia/params", "EnableResizableBar: 0\n");
+        for (addr, vendor, class, driver) in [
+            ("0000:01:00.0", "0x10de", "0x030000", "vfio-pci"),
+            // The GPU's HDMI audio function goes along but isn't a GPU
+            ("0000:01:00.1", "0x10de", "0x040300", "vfio-pci"),
+            ("0000:0c:00.0", "0x1002", "0x030000", "amdgpu"),
+        ] {
+            let pci = format!("/sys/bus/pci/devices/{}", addr);
+            fixture
+                .file(&format!("{}/vendor", pci), &format!("{}\n", vendor))
+                .file(&format!("{}/class", pci), &format!("{}\n", class))
+                .symlink(
+                    &format!("{}/driver", pci),
+                    &format!("../../../bus/pci/drivers/{}", driver),
+                );
+        }
+
+        fixture.run(|| {
+            assert!(detect_nvidia_gpus().unwrap().is_empty());
+            assert_eq!(
+                no_host_gpus_reason(),
+                NoHostGpus::PassedThrough(vec!["0000:01:00.0".to_string()])
+            );
+        });
+
+        // Same driver state without a vfio-bound NVIDIA GPU
+        let empty = procfs::Fixture::new("gpu-none");
+        empty
+            .file("/proc/driver/nvidia/params", "EnableResizableBar: 0\n")
+            .file("/sys/bus/pci/devices/0000:0c:00.0/vendor", "0x1002\n");
+        assert_eq!(empty.run(no_host_gpus_reason), NoHostGpus::NoneFound);
+    }
+
+    #[test]
+    fn test_detect_gpus_all_vendors() {
+        let fixture = procfs::Fixture::new("gpu-all-vendors");
+        fixture
+            .file("/proc/driver/nvidia/params", "EnableResizableBar: 0\n")
+            .file(
+                "/proc/driver/nvidia/gpus/0000:01:00.0/information",
+                "Model: \t\t NVIDIA GeForce RTX 4070 Laptop GPU\n",
+            );
+        for (addr, vendor, class, driver) in [
+            ("0000:01:00.0", "0x10de", "0x030000", Some("nvidia")),
+            ("0000:01:00.1", "0x10de", "0x040300", Some("snd_hda_intel")),
+            ("0000:c4:00.0", "0x1002", "0x030000", Some("amdgpu")),
+            ("0000:0c:00.0", "0x1002", "0x030000", Some("vfio-pci")),
+            ("0000:0d:00.0", "0x1a03", "0x030000", None),
+        ] {
+            let pci = format!("/sys/bus/pci/devices/{}", addr);
+            fixture
+                .file(&format!("{}/vendor", pci), &format!("{}\n", vendor))
+                .file(&format!("{}/device", pci), "0x15bf\n")
+                .file(&format!("{}/class", pci), &format!("{}\n", class))
+                .file(&format!("{}/current_link_width", pci), "16\n");
+            if let Some(driver) = driver {
+                fixture.symlink(
+                    &format!("{}/driver", pci),
+                    &format!("../../../bus/pci/drivers/{}", driver),
+                );
+            }
+        }
+
+        let monitor = fixture.run(|| GpuMonitor::from_gpus(detect_gpus().unwrap()));
+        // The NVIDIA GPU is listed once; audio, vfio-bound and unbound
+        // devices are not GPUs of the host
+        assert_eq!(monitor.gpu_count(), 2);
+        let dgpu = monitor.primary_gpu().unwrap();
+        assert_eq!(dgpu.pci_address, "0000:01:00.0");
+        assert_eq!(dgpu.kind, GpuKind::Discrete);
+        // The APU's iGPU sits behind a bridge but has no VRAM of its own
+        let igpu = monitor.integrated_gpu().unwrap();
+        assert_eq!(igpu.pci_address, "0000:c4:00.0");
+        assert_eq!(igpu.vendor, GpuVendor::Amd);
+        assert_eq!(igpu.model, "AMD GPU [0x15bf]");
+    }
 }
@@ -0,0 +1,100 @@
+// SPDX-License-Identifier: GPL-2.0
+//
+// GhostBrew - pacman Hook
+//
+// Installs a pacman hook that asks the running scheduler to rescan after
+// package installs and upgrades, so newly installed games and profile
+// packages are picked up without waiting for a SIGHUP.
+//
+// Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
+
+use crate::control;
+use anyhow::{Context, Result, bail};
+use std::fs;
+use std::path::Path;
+
+/// Where pacman looks for system hooks
+pub const HOOK_PATH: &str = "/etc/pacman.d/hooks/ghostbrew.hook";
+
+/// First line of every hook we write, checked before removal
+const HOOK_MARKER: &str = "# Installed by scx_ghostbrew --install-pacman-hook";
+
+/// Hook file contents; the action is a no-op when the scheduler is not running
+pub fn hook_contents(control_file: &str) -> String {
+    format!(
+        "{marker}
+# Remove with: scx_ghostbrew --remove-pacman-hook
+
+[Trigger]
+Operation = Install
+Operation = Upgrade
+Type = Package
+Target = *
+
+[Action]
+Description = Notifying GhostBrew of package changes...
+When = PostTransaction
+Exec = /bin/sh -c 'test -w {file} && echo {command} >> {file} || true'
+",
+        marker = HOOK_MARKER,
+        file = control_file,
+        command = control::RESCAN_COMMAND,
+    )
+}
+
+/// Write the hook, replacing an older copy
+pub fn install(path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).with_context(|| format!("Failed to create {:?}", parent))?;
+    }
+    fs::write(path, hook_contents(control::CONTROL_FILE))
+        .with_context(|| format!("Failed to write {:?}", path))
+}
+
+/// Delete the hook; Ok(false) if it was not installed
+pub fn remove(path: &Path) -> Result<bool> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {:?}", path)),
+    };
+    if !content.starts_with(HOOK_MARKER) {
+        bail!(
+            "{:?} was not installed by GhostBrew, leaving it alone",
+            path
+        );
+    }
+    fs::remove_file(path).with_context(|| format!("Failed to remove {:?}", path))?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hook_sections() {
+        let hook = hook_contents("/run/ghostbrew/control");
+
+        // Every [Trigger] key pacman requires, then an [Action] with When/Exec
+        let trigger = hook.find("[Trigger]").unwrap();
+        let action = hook.find("[Action]").unwrap();
+        assert!(trigger < action);
+        let (trigger, action) = (&hook[trigger..action], &hook[action..]);
+        for key in ["Operation = Install", "Type = Package", "Target = *"] {
+            assert!(trigger.contains(key), "missing {}", key);
+        }
+        assert!(action.contains("When = PostTransaction"));
+        assert!(action.contains("echo rescan >> /run/ghostbrew/control"));
+
+        // Install/remove round trip, refusing foreign hooks
+        let dir = std::env::temp_dir().join(format!("ghostbrew-hook-{}", std::process::id()));
+        let path = dir.join("hooks/ghostbrew.hook");
+        install(&path).unwrap();
+        assert!(remove(&path).unwrap());
+        assert!(!remove(&path).unwrap());
+        fs::write(&path, "[Trigger]\n").unwrap();
+        assert!(remove(&path).is_err());
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -7,9 +7,9 @@
 //
 // Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
 
+use crate::procfs;
 use anyhow::{Context, Result};
 use log::debug;
-use std::fs;
 
 /// Intel hybrid processor information
 #[derive(Debug, Clone, Default)]
@@ -132,7 +132,7 @@ pub fn detect_intel_hybrid(nr_cpus: u32, model_name: &str) -> Result<IntelHybrid
 /// Read CPU capacity from sysfs
 fn read_cpu_capacity(cpu: u32) -> Result<u32> {
     let path = format!("/sys/devices/system/cpu/cpu{}/cpu_capacity", cpu);
-    let content = fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path))?;
+    let content = procfs::read_rooted(&path).with_context(|| format!("Failed to read {}", path))?;
     content
         .trim()
         .parse()
@@ -179,7 +179,7 @@ fn detect_by_frequency(
 fn read_base_frequency(cpu: u32) -> Result<u32> {
     // Try base_frequency first (preferred)
     let base_path = format!("/sys/devices/system/cpu/cpu{}/cpufreq/base_frequency", cpu);
-    if let Some(freq) = fs::read_to_string(&base_path)
+    if let Some(freq) = procfs::read_rooted(&base_path)
         .ok()
         .and_then(|c| c.trim().parse::<u32>().ok())
     {
@@ -192,7 +192,7 @@ fn read_base_frequency(cpu: u32) -> Result<u32> {
         cpu
     );
     let content =
-        fs::read_to_string(&max_path).with_context(|| format!("Failed to read {}", max_path))?;
+        procfs::read_rooted(&max_path).with_context(|| format!("Failed to read {}", max_path))?;
     content
         .trim()
         .parse()
@@ -0,0 +1,123 @@
+// SPDX-License-Identifier: GPL-2.0
+//
+// GhostBrew - sched_ext Kernel Feature Detection
+//
+// sched_ext kernels differ in what they offer: the scx_bpf_dsq_insert*
+// kfuncs replaced scx_bpf_dispatch* in 6.13, and cgroup classification
+// needs the unified (v2) cgroup hierarchy. This probes the running kernel
+// so unsupported features are switched off with a log line instead of
+// failing at load or silently doing nothing.
+//
+// Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
+
+use crate::mapcaps::MapFeature;
+use crate::procfs;
+use crate::topology::{self, KernelVersion};
+use std::fs;
+use std::path::Path;
+
+/// First mainline kernel with sched_ext
+pub const MIN_KERNEL: (u32, u32) = (6, 12);
+
+/// First kernel with the scx_bpf_dsq_insert* kfuncs the BPF side calls
+pub const DSQ_INSERT_KERNEL: (u32, u32) = (6, 13);
+
+/// sched_ext capabilities of the running kernel
+#[derive(Debug, Clone, Default)]
+pub struct SchedExtFeatures {
+    pub kernel: Option<KernelVersion>,
+    /// /sys/kernel/sched_ext exists
+    pub sched_ext: bool,
+    /// scx_bpf_dsq_insert_vtime is available
+    pub dsq_insert: bool,
+    /// Unified cgroup hierarchy mounted (tasks' default cgroup is meaningful)
+    pub cgroup_v2: bool,
+}
+
+impl SchedExtFeatures {
+    /// Map-fed features the kernel can't support, with the reason
+    pub fn unsupported(&self) -> Vec<(MapFeature, &'static str)> {
+        let mut unsupported = Vec::new();
+        if !self.cgroup_v2 {
+            unsupported.push((
+                MapFeature::CgroupClasses,
+                "no unified (v2) cgroup hierarchy mounted",
+            ));
+        }
+        unsupported
+    }
+}
+
+/// Kernel version from a /proc/version line
+/// ("Linux version 6.12.5-arch1-1 (linux@archlinux) ...")
+pub fn parse_proc_version(text: &str) -> Option<KernelVersion> {
+    let release = text
+        .strip_prefix("Linux version ")?
+        .split_whitespace()
+        .next()?;
+    KernelVersion::parse(release)
+}
+
+/// Running kernel version (uname release, else /proc/version)
+pub fn running_kernel() -> Option<KernelVersion> {
+    topology::get_kernel_version()
+        .or_else(|| parse_proc_version(&procfs::read_rooted("/proc/version").ok()?))
+}
+
+/// Whether a /proc/kallsyms dump contains a symbol
+fn kallsyms_has(kallsyms: &str, symbol: &str) -> bool {
+    kallsyms
+        .lines()
+        .any(|line| line.split_whitespace().nth(2) == Some(symbol))
+}
+
+/// Probe the running kernel
+///
+/// kfuncs are looked up in /proc/kallsyms; when it can't be read, the
+/// kernel version decides.
+pub fn sched_ext_features() -> SchedExtFeatures {
+    let kernel = running_kernel();
+    let dsq_insert = match fs::read_to_string("/proc/kallsyms") {
+        Ok(kallsyms) if !kallsyms.is_empty() => kallsyms_has(&kallsyms, "scx_bpf_dsq_insert_vtime"),
+        _ => kernel.is_some_and(|v| v.at_least(DSQ_INSERT_KERNEL.0, DSQ_INSERT_KERNEL.1)),
+    };
+
+    SchedExtFeatures {
+        kernel,
+        sched_ext: Path::new("/sys/kernel/sched_ext").exists(),
+        dsq_insert,
+        cgroup_v2: Path::new("/sys/fs/cgroup/cgroup.controllers").exists(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_proc_version() {
+        let arch = parse_proc_version(
+            "Linux version 6.12.5-arch1-1 (linux@archlinux) (gcc (GCC) 14.2.1) #1 SMP PREEMPT_DYNAMIC\n",
+        )
+        .unwrap();
+        let cachy = parse_proc_version("Linux version 6.13.2-2-cachyos (linux-cachyos@cachyos) #1")
+            .unwrap();
+        assert_eq!(arch.to_string(), "6.12.5");
+        assert!(arch < cachy);
+        assert!(!arch.at_least(DSQ_INSERT_KERNEL.0, DSQ_INSERT_KERNEL.1));
+        assert!(cachy.at_least(DSQ_INSERT_KERNEL.0, DSQ_INSERT_KERNEL.1));
+        assert!(KernelVersion::parse("7.0").unwrap() > cachy);
+        assert!(parse_proc_version("6.12.5-arch1-1").is_none());
+
+        let kallsyms = "ffffffff8110a2c0 T scx_bpf_dsq_insert_vtime\n\
+                        ffffffff8110a3f0 T scx_bpf_dsq_insert\n";
+        assert!(kallsyms_has(kallsyms, "scx_bpf_dsq_insert_vtime"));
+        assert!(!kallsyms_has(kallsyms, "scx_bpf_dispatch_vtime"));
+
+        let features = SchedExtFeatures::default();
+        assert_eq!(
+            features.unsupported().first().map(|(f, _)| *f),
+            Some(MapFeature::CgroupClasses)
+        );
+    }
+}
@@ -0,0 +1,98 @@
+// SPDX-License-Identifier: GPL-2.0
+//
+// GhostBrew - scx_loader Coordination (--take-over)
+//
+// Distros ship scx_loader (and the older scx.service) to start a sched_ext
+// scheduler at boot. While one of them is active it can load its scheduler at
+// any moment, racing a GhostBrew started by hand. The units are checked with
+// `systemctl is-active`; --take-over asks scx_loader over D-Bus to stop its
+// scheduler (scx.service is stopped) so GhostBrew can attach.
+//
+// Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
+
+use anyhow::{Context, Result, bail};
+use log::{debug, info};
+use std::process::Command;
+
+/// systemd units that load sched_ext schedulers
+pub const LOADER_UNITS: &[&str] = &["scx_loader.service", "scx.service"];
+
+/// D-Bus name, object and interface of scx_loader
+const LOADER_BUS: [&str; 3] = ["org.scx.Loader", "/org/scx/Loader", "org.scx.Loader"];
+
+/// Whether `systemctl is-active` output means the unit is running or about
+/// to run
+///
+/// activating and reloading count: the unit will load its scheduler shortly.
+pub fn unit_is_active(state: &str) -> bool {
+    matches!(state.trim(), "active" | "activating" | "reloading")
+}
+
+/// `systemctl is-active` state of a unit, None if systemctl can't be run
+fn unit_state(unit: &str) -> Option<String> {
+    let output = Command::new("systemctl")
+        .args(["is-active", unit])
+        .output()
+        .inspect_err(|e| debug!("systemctl is-active {}: {}", unit, e))
+        .ok()?;
+    // is-active exits non-zero for inactive units but still prints the state
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// First scheduler loader unit that is active
+pub fn active_loader() -> Option<&'static str> {
+    LOADER_UNITS
+        .iter()
+        .copied()
+        .find(|unit| unit_state(unit).is_some_and(|state| unit_is_active(&state)))
+}
+
+/// Stop the scheduler `unit` manages so GhostBrew can attach
+pub fn take_over(unit: &str) -> Result<()> {
+    let mut command = if unit == "scx_loader.service" {
+        // scx_loader only knows its own scheduler list; stop it over D-Bus
+        let mut command = Command::new("busctl");
+        command
+            .args(["call", "--system"])
+            .args(LOADER_BUS)
+            .arg("StopScheduler");
+        command
+    } else {
+        let mut command = Command::new("systemctl");
+        command.args(["stop", unit]);
+        command
+    };
+
+    let output = command
+        .output()
+        .with_context(|| format!("Failed to ask {} to stop its scheduler", unit))?;
+    if !output.status.success() {
+        bail!(
+            "{} refused to stop its scheduler: {}",
+            unit,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    info!("Took over from {}: its scheduler was stopped", unit);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unit_is_active() {
+        assert!(unit_is_active("active\n"));
+        assert!(unit_is_active("activating"));
+        assert!(unit_is_active("reloading"));
+
+        assert!(!unit_is_active("inactive\n"));
+        assert!(!unit_is_active("failed"));
+        assert!(!unit_is_active("deactivating"));
+        // Units that don't exist print "inactive" on current systemd,
+        // "unknown" on older releases
+        assert!(!unit_is_active("unknown"));
+        assert!(!unit_is_active(""));
+    }
+}
@@ -0,0 +1,87 @@
+// SPDX-License-Identifier: GPL-2.0
+//
+// GhostBrew - Logger Setup
+//
+// env_logger with either the default text format or JSON Lines (one object
+// per record with timestamp, level, target and message) for journald/Loki
+// style ingestion.
+//
+// Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
+
+use serde::Serialize;
+use std::io::Write;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+/// Log output format (--log-format)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum LogFormat {
+    /// env_logger's default human-readable format
+    #[default]
+    Text,
+    /// One JSON object per line
+    Json,
+}
+
+/// A single log record as emitted in JSON mode
+#[derive(Serialize)]
+struct JsonRecord<'a> {
+    timestamp: &'a str,
+    level: &'a str,
+    target: &'a str,
+    message: String,
+}
+
+/// Format a log record as a JSON line (without the trailing newline)
+pub fn json_line(timestamp: &str, record: &log::Record) -> String {
+    let json = JsonRecord {
+        timestamp,
+        level: record.level().as_str(),
+        target: record.target(),
+        message: record.args().to_string(),
+    };
+    serde_json::to_string(&json).unwrap_or_default()
+}
+
+/// Initialize the global logger
+///
+/// `default_level` applies when RUST_LOG is unset, as before.
+pub fn init(default_level: &str, format: LogFormat) {
+    let mut builder =
+        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(default_level));
+
+    if format == LogFormat::Json {
+        builder.format(|buf, record| {
+            let timestamp = OffsetDateTime::now_utc()
+                .format(&Rfc3339)
+                .unwrap_or_default();
+            writeln!(buf, "{}", json_line(&timestamp, record))
+        });
+    }
+
+    builder.init();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_line_is_valid_json() {
+        let line = json_line(
+            "2026-01-01T12:00:00Z",
+            &log::Record::builder()
+                .level(log::Level::Warn)
+                .target("scx_ghostbrew::gpu")
+                .args(format_args!("GPU \"{}\" busy\n{}%", "card0", 97))
+                .build(),
+        );
+
+        assert!(!line.contains('\n'));
+        let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["timestamp"], "2026-01-01T12:00:00Z");
+        assert_eq!(value["level"], "WARN");
+        assert_eq!(value["target"], "scx_ghostbrew::gpu");
+        assert_eq!(value["message"], "GPU \"card0\" busy\n97%");
+    }
+}
@@ -4,39 +4,105 @@
 //
 // Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
 
+mod adaptive;
+mod aigpu;
+mod api;
 mod arena;
+mod attach;
+mod benchmark;
 mod bpf_skel;
+mod buildinfo;
 mod cgroup;
+mod classify;
 mod config;
 mod container;
 mod control;
+mod doctor;
+mod effective;
 mod events;
+mod export;
+mod focus;
+mod gamemode;
 mod gaming;
 mod gpu;
+mod hook;
 mod intel;
+mod kernel;
+mod loader;
+mod logging;
 mod mangohud;
+mod mapcaps;
+mod marker;
 mod pbo;
+mod pci;
+mod pidstats;
+mod procfs;
 mod profiles;
+mod registry;
+mod reload;
+mod report;
+mod reserve;
+mod scan;
+mod selftest;
+mod shared;
+mod snapshot;
+mod state;
+mod statsd;
 mod topology;
+mod trace;
+#[cfg(feature = "tui")]
+mod tui;
+mod tunables;
 mod vcache;
 mod vm;
 
 use anyhow::{Context, Result, bail};
-use clap::{CommandFactory, Parser};
+use clap::{CommandFactory, FromArgMatches, Parser};
 use clap_complete::generate;
 use libbpf_rs::MapCore;
 use libbpf_rs::skel::{OpenSkel, SkelBuilder};
 use log::{debug, info, warn};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
 use std::mem::MaybeUninit;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::{Duration, Instant};
 
 use bpf_skel::*;
+use mapcaps::MapFeature;
 use topology::CpuTopology;
 
 const SCHEDULER_NAME: &str = "ghostbrew";
 
+/// Set by the SIGHUP handler, consumed by the run loop
+static RELOAD_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn handle_sighup(_: libc::c_int) {
+    RELOAD_REQUESTED.store(true, Ordering::Relaxed);
+}
+
+/// BPF WORKLOAD_INTERACTIVE class (QEMU IO threads)
+const WORKLOAD_INTERACTIVE: u32 = 2;
+
+/// BPF WORKLOAD_AI class (AI runtimes, AI containers and VMs)
+const WORKLOAD_AI: u32 = 4;
+
+/// BPF WORKLOAD_VM_DEV class (development VM vCPU)
+const WORKLOAD_VM_DEV: u32 = 5;
+
+/// BPF WORKLOAD_VM_GAMING class (gaming VM vCPU)
+const WORKLOAD_VM_GAMING: u32 = 6;
+
+/// BPF WORKLOAD_SHADER class (shader compile thread of a game)
+const WORKLOAD_SHADER: u32 = 8;
+
+/// bpffs directory for --export-topology pins
+const TOPOLOGY_PIN_DIR: &str = "/sys/fs/bpf/ghostbrew";
+
+/// Scan intervals are stretched by this factor during a pacman transaction
+const PACKAGE_TRANSACTION_SCAN_FACTOR: u32 = 4;
+
 /// GhostBrew - AMD Zen4/Zen5 X3D and Intel Hybrid optimized sched-ext scheduler
 #[derive(Parser, Debug)]
 #[command(name = "scx_ghostbrew")]
@@ -51,7 +117,7 @@ struct Args {
     gaming: bool,
 
     /// Work mode - prefer frequency CCD (AMD) for higher boost on non-gaming tasks
-    #[arg(short = 'w', long)]
+    #[arg(short = 'w', long, visible_alias = "productivity")]
     work: bool,
 
     /// Auto-detect workload and adjust (default)
@@ -62,14 +128,133 @@ struct Args {
     #[arg(long, default_value_t = 2_000_000)]
     burst_threshold: u64,
 
+    /// Burst threshold in nanoseconds while a Wine fsync game runs (fsync
+    /// wakes threads in shorter bursts); game profiles still take precedence
+    #[arg(long, value_name = "NS")]
+    fsync_burst_threshold: Option<u64>,
+
     /// Time slice in nanoseconds
     #[arg(long, default_value_t = 3_000_000)]
     slice_ns: u64,
 
+    /// Interactive cutoff as a fraction of the time slice (0.0-1.0).
+    /// Unset keeps the burst threshold as the interactive/batch boundary.
+    #[arg(long, value_name = "RATIO")]
+    interactive_ratio: Option<f64>,
+
+    /// Seconds to wait for sched_ext to become free and for the scheduler
+    /// to come up after attaching
+    #[arg(long, value_name = "SECS", default_value_t = attach::DEFAULT_ATTACH_TIMEOUT_SECS)]
+    attach_timeout: u64,
+
+    /// Stop the scheduler of an active scx_loader/scx.service before
+    /// attaching instead of racing it
+    #[arg(long)]
+    take_over: bool,
+
+    /// Preempt kicks allowed per CPU per second (0 = unlimited)
+    #[arg(long, value_name = "KICKS", default_value_t = tunables::DEFAULT_MAX_PREEMPT_RATE)]
+    max_preempt_rate: u16,
+
+    /// Correct sustained gaming latency spikes (HighLatency events above
+    /// --adaptive-latency-rate) with ACTION, one step per cooldown
+    #[arg(long, value_enum, value_name = "ACTION", num_args = 0..=1, default_missing_value = "shorten-slice")]
+    adaptive_latency: Option<adaptive::LatencyAction>,
+
+    /// HighLatency events per second that trigger --adaptive-latency
+    #[arg(long, value_name = "EVENTS", default_value_t = adaptive::DEFAULT_RATE)]
+    adaptive_latency_rate: f64,
+
+    /// Seconds between --adaptive-latency corrections
+    #[arg(long, value_name = "SECS", default_value_t = adaptive::DEFAULT_COOLDOWN_SECS)]
+    adaptive_latency_cooldown: u64,
+
+    /// Consecutive GPU power-state polls required before EPP boosts follow
+    /// an active/idle change
+    #[arg(long, value_name = "POLLS", default_value_t = gpu::DEFAULT_DEBOUNCE_POLLS)]
+    gpu_debounce: u32,
+
+    /// CCD temperature (C) at which latency-sensitive tasks move to a cooler
+    /// CCD on dies without V-Cache asymmetry (0 disables)
+    #[arg(long, value_name = "CELSIUS", default_value_t = 85)]
+    ccd_temp_threshold: u32,
+
+    /// Confine AI workloads (inference runtimes, AI containers and VMs) to
+    /// these CPUs; without a list, the frequency (non-V-Cache) CCD
+    #[arg(long, value_name = "CPULIST")]
+    ai_cpu_reserve: Option<Option<String>>,
+
+    /// Confine AI and batch workloads to the efficiency CPUs: E-cores on
+    /// Intel hybrid, the frequency CCD on AMD
+    #[arg(long, conflicts_with = "ai_cpu_reserve")]
+    isolate_ai: bool,
+
+    /// Schedule every container as batch, ignoring AI/gaming/compute
+    /// classification (containers are still counted by type)
+    #[arg(long)]
+    containers_batch_only: bool,
+
+    /// /proc entries examined per process scan; larger process tables are
+    /// scanned over several intervals (0 = whole table every scan)
+    #[arg(long, value_name = "N", default_value_t = gaming::DEFAULT_MAX_SCAN_PROCS)]
+    max_scan_procs: usize,
+
+    /// Classify vCPUs of VMs with explicit CPU pinning instead of leaving
+    /// them to their affinity mask
+    #[arg(long)]
+    override_pinned: bool,
+
+    /// Prefer an idle CPU in the task's previous CCX (shared L3) before the
+    /// rest of its CCD; only matters on parts with several CCXs per CCD
+    #[arg(long)]
+    ccx_locality: bool,
+
+    /// Keep applying EPP hints while Feral's GameMode is active (by
+    /// default GhostBrew leaves EPP to GameMode)
+    #[arg(long)]
+    epp_over_gamemode: bool,
+
+    /// Log the EPP changes GhostBrew would make (`CPU N: old -> new`)
+    /// without writing them
+    #[arg(long)]
+    epp_dry_run: bool,
+
+    /// Track runtime, migrations and preemptions of each detected game and
+    /// list them in the stats output (implies --stats)
+    #[arg(long)]
+    per_pid_stats: bool,
+
+    /// Keep only the game owning the focused window in the gaming tier and
+    /// drop other detected games to interactive (asks the display server via
+    /// hyprctl, swaymsg or xprop)
+    #[arg(long)]
+    focus_aware: bool,
+
+    /// Pin cpu_ctxs and topology_summary under /sys/fs/bpf/ghostbrew/ for external tools
+    #[arg(long)]
+    export_topology: bool,
+
     /// E-core offload mode for Intel hybrid CPUs: disabled, conservative, aggressive
     #[arg(long, default_value = "conservative")]
     ecore_offload: String,
 
+    /// V-Cache switching strategy: manual, automatic, follow_ghost_vcache
+    #[arg(long, value_name = "STRATEGY", default_value = "follow_ghost_vcache")]
+    vcache_strategy: String,
+
+    /// Gaming tasks that make the automatic strategy select cache mode
+    #[arg(long, value_name = "TASKS", default_value_t = vcache::DEFAULT_GAMING_THRESHOLD)]
+    vcache_gaming_threshold: u32,
+
+    /// Batch tasks (with no gaming tasks) that make the automatic strategy
+    /// select frequency mode
+    #[arg(long, value_name = "TASKS", default_value_t = vcache::DEFAULT_BATCH_THRESHOLD)]
+    vcache_batch_threshold: u32,
+
+    /// Seconds the automatic strategy's target must hold before switching
+    #[arg(long, value_name = "SECS", default_value_t = vcache::DEFAULT_HYSTERESIS_SECS)]
+    vcache_hysteresis_secs: u64,
+
     /// Print scheduler statistics periodically
     #[arg(short, long)]
     stats: bool,
@@ -78,9 +263,116 @@ struct Args {
     #[arg(long, default_value_t = 2)]
     stats_interval: u64,
 
-    /// Benchmark mode - export stats to MangoHud-compatible CSV
-    #[arg(short = 'b', long)]
-    benchmark: bool,
+    /// Seconds between gaming process scans
+    #[arg(
+        long,
+        value_name = "SECS",
+        default_value_t = scan::DEFAULT_GAMING_SCAN_SECS,
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    gaming_scan_secs: u64,
+
+    /// Seconds between VM scans
+    #[arg(
+        long,
+        value_name = "SECS",
+        default_value_t = scan::DEFAULT_SLOW_SCAN_SECS,
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    vm_scan_secs: u64,
+
+    /// Seconds between container scans
+    #[arg(
+        long,
+        value_name = "SECS",
+        default_value_t = scan::DEFAULT_SLOW_SCAN_SECS,
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    container_scan_secs: u64,
+
+    /// Seconds between cgroup scans
+    #[arg(
+        long,
+        value_name = "SECS",
+        default_value_t = scan::DEFAULT_SLOW_SCAN_SECS,
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    cgroup_scan_secs: u64,
+
+    /// Print a single stats snapshot after one interval, then detach and exit
+    #[arg(long)]
+    stats_once: bool,
+
+    /// Print stats as JSON instead of human-readable text
+    #[arg(long)]
+    stats_json: bool,
+
+    /// Send scheduler counters as StatsD gauges to HOST:PORT (UDP) each
+    /// stats interval
+    #[arg(long, value_name = "HOST:PORT")]
+    statsd_addr: Option<String>,
+
+    /// Write the detected gaming, batch, AI, VM vCPU and container PIDs to
+    /// PATH as JSON after each scan
+    #[arg(long, value_name = "PATH")]
+    export_pids: Option<std::path::PathBuf>,
+
+    /// Write the runtime tunables, V-Cache mode, EPP boost and detector
+    /// settings to PATH as JSON at shutdown
+    #[arg(long, value_name = "PATH")]
+    save_state: Option<std::path::PathBuf>,
+
+    /// Apply a --save-state snapshot at startup
+    #[arg(long, value_name = "PATH")]
+    load_state: Option<std::path::PathBuf>,
+
+    /// Serve /state, /topology and /events/recent as JSON over HTTP on
+    /// HOST:PORT (a bare port binds localhost)
+    #[arg(
+        long,
+        value_name = "HOST:PORT",
+        num_args = 0..=1,
+        default_missing_value = api::DEFAULT_API_ADDR
+    )]
+    api_addr: Option<String>,
+
+    /// Number of recent scheduler events kept for /events/recent and the TUI
+    #[arg(long, value_name = "EVENTS", default_value_t = events::DEFAULT_RECENT_EVENTS)]
+    recent_events: usize,
+
+    /// Append every scheduler event to FILE as JSON Lines, for --analyze-events
+    #[arg(long, value_name = "FILE")]
+    dump_events: Option<std::path::PathBuf>,
+
+    /// Print a task's measured burst length and wait time each stats
+    /// interval, next to the class GhostBrew assigned it
+    #[arg(long, value_name = "PID")]
+    trace_pid: Option<u32>,
+
+    /// Write classifications, mode switches and EPP changes to the tracefs
+    /// trace_marker, to line them up with kernel traces (trace-cmd, perfetto)
+    #[arg(long)]
+    trace_markers: bool,
+
+    /// Live terminal dashboard (q quits, g/w toggle gaming/work mode)
+    #[cfg(feature = "tui")]
+    #[arg(long)]
+    tui: bool,
+
+    /// Benchmark mode - export stats to MangoHud-compatible CSV; with a
+    /// COMMAND, launch it under MangoHud and report frame times against
+    /// scheduler events when it finishes
+    #[arg(short = 'b', long, value_name = "COMMAND")]
+    benchmark: Option<Option<String>>,
+
+    /// Benchmark run length in seconds (with --benchmark COMMAND)
+    #[arg(
+        long,
+        value_name = "SECS",
+        default_value_t = 60,
+        requires = "benchmark"
+    )]
+    benchmark_duration: u64,
 
     /// Verbose logging
     #[arg(short, long)]
@@ -90,6 +382,15 @@ struct Args {
     #[arg(short, long)]
     debug: bool,
 
+    /// Log output format: text, or json for one JSON object per line
+    #[arg(long, value_enum, default_value_t = logging::LogFormat::Text)]
+    log_format: logging::LogFormat,
+
+    /// Print the version with the BPF target arch, libbpf-rs version, kernel
+    /// requirements and optional features this binary was built with
+    #[arg(long)]
+    version_verbose: bool,
+
     /// Generate shell completions (bash, zsh, fish, powershell)
     #[arg(long, value_name = "SHELL")]
     completions: Option<clap_complete::Shell>,
@@ -97,6 +398,126 @@ struct Args {
     /// Analyze MangoHud frame time log (show stats without running scheduler)
     #[arg(long)]
     analyze_frametime: Option<Option<std::path::PathBuf>>,
+
+    /// Compare two MangoHud frame time logs (A/B scheduler runs) and show
+    /// whether B is better or worse on each metric
+    #[arg(long, num_args = 2, value_names = ["A", "B"])]
+    compare_logs: Option<Vec<std::path::PathBuf>>,
+
+    /// Print the --compare-logs table as JSON
+    #[arg(long, requires = "compare_logs")]
+    json: bool,
+
+    /// Analyze a captured JSON Lines event log (show stats without running scheduler)
+    #[arg(long, value_name = "FILE")]
+    analyze_events: Option<std::path::PathBuf>,
+
+    /// Check system prerequisites (sched-ext, BTF, cgroup v2, amd_pstate) and exit
+    #[arg(long)]
+    doctor: bool,
+
+    /// Load the BPF program without attaching it, check that the userspace-fed
+    /// maps read back what was written, and exit (requires root)
+    #[arg(long)]
+    self_test: bool,
+
+    /// Validate the config file (the standard location, or FILE) and exit;
+    /// fails on parse errors and unknown keys
+    #[arg(long, value_name = "FILE", num_args = 0..=1)]
+    check_config: Option<Option<std::path::PathBuf>>,
+
+    /// Validate game profile TOML files in a directory and exit
+    #[arg(long, value_name = "DIR")]
+    validate_profiles: Option<std::path::PathBuf>,
+
+    /// Capture the /proc and /sys files detection reads into a tarball and
+    /// exit (environment values are redacted)
+    #[arg(long, value_name = "FILE")]
+    capture_snapshot: Option<std::path::PathBuf>,
+
+    /// Replay topology and process detection against a captured snapshot
+    /// (tarball or directory) and exit
+    #[arg(long, value_name = "SNAPSHOT", conflicts_with = "capture_snapshot")]
+    simulate: Option<std::path::PathBuf>,
+
+    /// Write default profiles for installed Steam games and exit (to
+    /// --profiles-dir, else profiles_dir from the config file, else
+    /// ~/.config/ghostbrew/profiles)
+    #[arg(long)]
+    import_steam_profiles: bool,
+
+    /// Directory --import-steam-profiles writes to
+    #[arg(long, value_name = "DIR", requires = "import_steam_profiles")]
+    profiles_dir: Option<std::path::PathBuf>,
+
+    /// Install a pacman hook that triggers a rescan after package installs and exit
+    #[arg(long, conflicts_with = "remove_pacman_hook")]
+    install_pacman_hook: bool,
+
+    /// Remove the pacman hook installed by --install-pacman-hook and exit
+    #[arg(long)]
+    remove_pacman_hook: bool,
+
+    /// Print detected hardware and kernel sched_ext support as anonymized
+    /// JSON for bug reports and exit (no root needed)
+    #[arg(long)]
+    hw_report: bool,
+
+    /// Print the effective configuration (defaults, config file, then CLI
+    /// flags) as TOML or JSON and exit
+    #[arg(long, value_enum, value_name = "FORMAT", num_args = 0..=1, default_missing_value = "toml")]
+    print_config: Option<effective::ConfigFormat>,
+}
+
+impl Args {
+    /// Reference length and ratio for the interactive/batch boundary,
+    /// matching the BPF get_interactive_cutoff() rule
+    fn interactive_reference(&self) -> (u64, f64) {
+        match self.interactive_ratio {
+            Some(ratio) => (self.slice_ns, ratio),
+            None => (self.burst_threshold, 1.0),
+        }
+    }
+}
+
+/// Machine-readable stats snapshot (for --stats-json)
+#[derive(Serialize)]
+struct StatsSnapshot {
+    enqueued: u64,
+    dispatched: u64,
+    direct_dispatched: u64,
+    gaming_tasks: u64,
+    interactive_tasks: u64,
+    vcache_migrations: u64,
+    /// Share of tracked gaming threads last seen on the V-Cache CCD
+    vcache_placement_pct: Option<f64>,
+    /// Median on-CPU time per switch of sampled batch/AI tasks
+    observed_slice_us: Option<u64>,
+    /// observed_slice_us over the configured slice_ns
+    slice_ratio: Option<f64>,
+    ccd_local: u64,
+    ccx_local: u64,
+    ccd_cross: u64,
+    preempt_kicks: u64,
+    preempt_kicks_throttled: u64,
+    events_dropped: u64,
+    avg_latency_us: Option<u64>,
+    min_latency_us: Option<u64>,
+    max_latency_us: Option<u64>,
+    gaming_avg_latency_us: Option<u64>,
+    gaming_late_frames: u64,
+    /// Tasks started running, per workload class
+    class_dispatches: BTreeMap<&'static str, u64>,
+    gaming_vcpus: usize,
+    containers: usize,
+    /// Per-node memory (multi-node systems)
+    numa_memory: Vec<topology::NodeMemory>,
+    /// Per-CCD max boost range and outlier cores
+    boost_spread: pbo::BoostSpread,
+    /// Tasks queued on each CCD's DSQ
+    ccd_dsq_depths: Vec<u64>,
+    split_lock_detect: String,
+    attached_scheduler: Option<String>,
 }
 
 /// CPU context structure matching BPF side
@@ -106,11 +527,12 @@ struct CpuCtx {
     ccd: u32,
     ccx: u32,
     node: u32,
+    socket: u32,
     smt_sibling: i32, // SMT sibling CPU (-1 if none)
     is_vcache: u8,    // bool in BPF is 1 byte (AMD X3D)
     is_pcore: u8,     // Intel hybrid P-core flag
     is_turbo: u8,     // Highest-performing core (prefcore or HWP)
-    _pad: [u8; 1],    // padding for alignment
+    is_isolated: u8,  // isolcpus/nohz_full: outside the CCD DSQ pools
 }
 
 /// Scheduler state
@@ -118,11 +540,19 @@ struct Scheduler<'a> {
     skel: GhostbrewSkel<'a>,
     struct_ops: Option<libbpf_rs::Link>,
     args: Args,
+    /// Settings the CLI set, which config reloads leave alone
+    cli_settings: effective::CliSettings,
     topology: CpuTopology,
     gaming_detector: gaming::GamingDetector,
     prefcore: pbo::PrefcoreInfo,
+    /// Per-core max boost spread within each CCD
+    boost_spread: pbo::BoostSpread,
     gpu_monitor: gpu::GpuMonitor,
     epp_manager: pbo::EppManager,
+    /// EPP hints enabled (bare metal, EPP-capable driver and governor)
+    epp_hints: bool,
+    /// Backs EPP hints off while GameMode is active
+    gamemode: gamemode::GameModeCoordinator,
     vm_monitor: vm::VmMonitor,
     container_monitor: container::ContainerMonitor,
     cgroup_monitor: cgroup::CgroupMonitor,
@@ -132,16 +562,83 @@ struct Scheduler<'a> {
     vcache_controller: vcache::VCacheController,
     /// Active game profiles (PID -> profile name)
     active_profiles: std::collections::HashMap<u32, String>,
+    /// Running fsync games while --fsync-burst-threshold is set
+    fsync_games: HashSet<u32>,
     /// MangoHud stats exporter
     mangohud_exporter: Option<mangohud::MangoHudExporter>,
+    /// StatsD exporter (--statsd-addr)
+    statsd_exporter: Option<statsd::StatsdExporter>,
+    pid_exporter: Option<export::PidExporter>,
+    /// HTTP query interface (--api-addr)
+    api_server: Option<api::ApiServer>,
+    /// Detected PIDs published for other threads (API /pids)
+    shared_pids: Arc<shared::SharedPids>,
+    /// Burst sampler for --trace-pid
+    pid_tracer: Option<trace::PidTracer>,
+    /// Effective slice check over tracked batch/AI tasks
+    slice_sampler: trace::SliceSampler,
+    slice_observation: Option<trace::SliceObservation>,
+    /// HighLatency rate tracker for --adaptive-latency
+    adaptive_latency: Option<adaptive::AdaptiveLatency>,
+    /// Decision markers for --trace-markers
+    trace_marker: marker::TraceMarker,
+    /// Focused-game tiering for --focus-aware
+    focus_tracker: Option<focus::FocusTracker>,
     /// Runtime control interface
     control_interface: control::ControlInterface,
     /// Event handler for ringbuf events
     event_handler: Arc<events::EventHandler>,
+    /// Optional BPF maps present in the loaded object
+    map_caps: mapcaps::MapCaps,
+    /// Last /proc/stat sample for per-CCD idle tracking
+    idle_jiffies: Vec<topology::CpuJiffies>,
+    /// CCDs currently over --ccd-temp-threshold (for crossing logs)
+    hot_ccds: HashSet<u32>,
+    /// QEMU PIDs promoted to gaming hints for active Looking Glass sessions
+    boosted_vm_pids: HashSet<u32>,
+    /// Per-detector PID classes, resolved into gaming_pids/container_pids
+    workload_registry: registry::WorkloadRegistry,
+    /// vCPU thread PIDs in vm_vcpu_pids (paused VMs are left out)
+    vm_vcpu_tids: HashSet<u32>,
+    /// QEMU IO thread PIDs in vm_vcpu_pids (interactive class)
+    vm_iothread_pids: HashSet<u32>,
+    /// pacman transaction in progress (process scans slowed down)
+    package_transaction: bool,
+    /// Run the process scan on the next interval (control "rescan")
+    rescan_requested: bool,
+    /// Ollama PIDs in container_pids and their threads in numa_hints
+    ollama_pids: HashSet<u32>,
+    ollama_threads: HashSet<u32>,
+    /// sched_ext ops name last seen in sysfs (None if nothing attached)
+    attached_ops: Option<String>,
+    /// Threads of GPU AI containers in numa_hints, with their node
+    container_gpu_threads: std::collections::HashMap<u32, u32>,
+    /// Threads of CPU-only AI containers and AI VM vCPUs in numa_hints
+    ai_memory_threads: HashSet<u32>,
+    /// Least memory-pressured node those threads are hinted to
+    ai_memory_node: Option<u32>,
+    /// [ai_gpus] name patterns resolved to their GPU's NUMA node
+    ai_gpu_map: aigpu::AiGpuMap,
+    /// Threads of [ai_gpus] matches in numa_hints, with their node
+    ai_gpu_threads: std::collections::HashMap<u32, u32>,
+    /// Shader compile threads of gaming PIDs in shader_threads
+    shader_threads: HashSet<u32>,
+    /// OBS capture/encoder threads in thread_classes, with their class
+    obs_threads: std::collections::HashMap<u32, u32>,
+    /// Topology maps pinned under TOPOLOGY_PIN_DIR (removed on shutdown)
+    topology_pinned: bool,
+    /// nr_preempt_kicks at the previous stats print (achieved kick rate)
+    last_preempt_kicks: u64,
 }
 
 impl<'a> Scheduler<'a> {
-    fn init(args: Args, open_object: &'a mut MaybeUninit<libbpf_rs::OpenObject>) -> Result<Self> {
+    fn init(
+        args: Args,
+        cli_settings: effective::CliSettings,
+        config: config::GhostBrewConfig,
+        saved_state: Option<&state::RuntimeState>,
+        open_object: &'a mut MaybeUninit<libbpf_rs::OpenObject>,
+    ) -> Result<Self> {
         // Set rlimit for BPF
         let rlim = libc::rlimit {
             rlim_cur: libc::RLIM_INFINITY,
@@ -153,6 +650,9 @@ impl<'a> Scheduler<'a> {
             }
         }
 
+        // struct_ops attach and map pinning need bpffs
+        doctor::ensure_bpf_fs()?;
+
         // Detect CPU topology
         let topology = topology::detect_topology()?;
         info!(
@@ -160,6 +660,54 @@ impl<'a> Scheduler<'a> {
             topology.nr_cpus, topology.nr_ccds
         );
 
+        if topology.is_virtualized {
+            warn!("Running inside a virtual machine - V-Cache, prefcore and EPP features disabled");
+        }
+        if let Some(handheld) = topology.handheld_apu {
+            info!(
+                "Handheld detected: {} - V-Cache logic off, gaming EPP capped at {}",
+                handheld,
+                pbo::HANDHELD_BOOST_EPP
+            );
+        }
+        if topology.topology_confidence == topology::TopologyConfidence::Low {
+            if topology.nr_ccds > 1 {
+                warn!(
+                    "CPU topology unreadable from sysfs - {} CCDs guessed from core IDs",
+                    topology.nr_ccds
+                );
+                warn!(
+                    "CCD-aware placement may be wrong; check /sys/devices/system/cpu/cpu*/topology"
+                );
+            } else {
+                warn!("CPU topology unreadable from sysfs - scheduling all CPUs as a single CCD");
+                warn!(
+                    "CCD-aware placement is disabled; check /sys/devices/system/cpu/cpu*/topology"
+                );
+            }
+        }
+
+        let isolated_cpus = topology.isolated_cpus();
+        if !isolated_cpus.is_empty() {
+            info!(
+                "Isolated CPUs (isolcpus/nohz_full): {:?} - only pinned tasks run there",
+                isolated_cpus
+            );
+        }
+
+        // Validate the AI CPU reservation before loading anything
+        let ai_reserve = args
+            .ai_cpu_reserve
+            .as_ref()
+            .map(|list| reserve::resolve(list.as_deref(), &topology))
+            .transpose()
+            .context("Invalid --ai-cpu-reserve")?;
+        let efficiency_mask = args
+            .isolate_ai
+            .then(|| reserve::resolve_efficiency(&topology))
+            .transpose()
+            .context("Invalid --isolate-ai")?;
+
         // Log architecture-specific info
         match &topology.arch {
             topology::CpuArch::AmdZen { is_x3d, generation } => {
@@ -172,6 +720,23 @@ impl<'a> Scheduler<'a> {
                 } else {
                     info!("AMD Zen {} processor detected", generation);
                 }
+                // BPF hybrid placement is Intel-only; compact cores only get
+                // work of their own through a reservation
+                let compact_reserved = efficiency_mask.is_some()
+                    || (args.ai_cpu_reserve == Some(None)
+                        && reserve::default_ccd(&topology)
+                            .is_some_and(|ccd| topology.compact_ccds.contains(&ccd)));
+                if compact_reserved {
+                    info!(
+                        "Compact (Zen {}c) cores on CCD {:?} - used like E-cores for batch/AI",
+                        generation, topology.compact_ccds
+                    );
+                } else if !topology.compact_ccds.is_empty() {
+                    info!(
+                        "Compact (Zen {}c) cores on CCD {:?}",
+                        generation, topology.compact_ccds
+                    );
+                }
             }
             topology::CpuArch::IntelHybrid { generation } => {
                 info!(
@@ -188,6 +753,20 @@ impl<'a> Scheduler<'a> {
             }
         }
 
+        // sched_ext kfuncs and cgroup support of the running kernel
+        let kernel_features = kernel::sched_ext_features();
+        debug!("sched_ext kernel features: {:?}", kernel_features);
+        if kernel_features.sched_ext && !kernel_features.dsq_insert {
+            warn!(
+                "Kernel {} lacks scx_bpf_dsq_insert_vtime (added in {}.{}); the BPF scheduler will likely fail to load",
+                kernel_features
+                    .kernel
+                    .map_or("unknown".to_string(), |v| v.to_string()),
+                kernel::DSQ_INSERT_KERNEL.0,
+                kernel::DSQ_INSERT_KERNEL.1
+            );
+        }
+
         // Detect DL server support (kernel 7.0+ feature)
         let dl_server = topology::detect_dl_server_support();
         if dl_server.supported {
@@ -232,6 +811,16 @@ impl<'a> Scheduler<'a> {
             }
         };
 
+        if let Some(ratio) = args.interactive_ratio
+            && !(0.0..=1.0).contains(&ratio)
+        {
+            warn!(
+                "interactive_ratio {} out of range, clamping to {:.2}",
+                ratio,
+                ratio.clamp(0.0, 1.0)
+            );
+        }
+
         // Determine gaming mode and work mode
         let (mut gaming_mode, mut work_mode) = if args.gaming {
             if topology.is_intel_hybrid {
@@ -249,14 +838,29 @@ impl<'a> Scheduler<'a> {
             (false, true)
         } else {
             info!("Mode: Auto-detect");
-            (topology.is_x3d || topology.is_intel_hybrid, false)
+            effective::scheduler_modes(&args, &config, &topology)
         };
 
-        // Load configuration
-        let config = config::GhostBrewConfig::load().unwrap_or_else(|e| {
-            warn!("Failed to load config: {}, using defaults", e);
-            config::GhostBrewConfig::default()
-        });
+        // Gaming detector with user allow/deny lists
+        let mut gaming_detector = gaming::GamingDetector::new();
+        let overrides = gaming::ClassifyOverrides::new(&config.gaming.allow, &config.gaming.deny);
+        if !overrides.is_empty() {
+            info!(
+                "Gaming allowlist: {:?}, denylist: {:?}",
+                config.gaming.allow, config.gaming.deny
+            );
+            gaming_detector.set_overrides(overrides);
+        }
+        if !config.gaming.browsers.is_empty() {
+            info!("Extra browsers: {:?}", config.gaming.browsers);
+        }
+        gaming_detector.set_browsers(gaming::BrowserFilter::new(&config.gaming.browsers));
+        if !config.gaming.compositors.is_empty() {
+            info!("Extra compositors: {:?}", config.gaming.compositors);
+        }
+        gaming_detector.set_compositors(gaming::CompositorFilter::new(&config.gaming.compositors));
+        gaming_detector.set_min_age(Duration::from_millis(config.gaming.min_age_ms));
+        gaming_detector.set_max_scan_procs(args.max_scan_procs);
 
         // Load game profiles
         let mut profile_manager = profiles::ProfileManager::new();
@@ -272,7 +876,11 @@ impl<'a> Scheduler<'a> {
         }
 
         // Initialize V-Cache controller (ghost-vcache integration)
-        let mut vcache_controller = vcache::VCacheController::default();
+        let mut vcache_controller = if topology.is_virtualized {
+            vcache::VCacheController::unavailable()
+        } else {
+            vcache::VCacheController::default()
+        };
         if vcache_controller.is_available() {
             if !args.gaming && !args.work && topology.asymmetric_ccd_boost {
                 let (vcache_gaming_mode, vcache_work_mode) =
@@ -287,24 +895,64 @@ impl<'a> Scheduler<'a> {
                 );
             }
 
-            // Set strategy from config
-            if config.is_vcache_auto_switching() {
-                vcache_controller.set_strategy(vcache::SwitchingStrategy::Automatic {
-                    gaming_threshold: 1,
-                    batch_threshold: 4,
-                });
-                info!("V-Cache: automatic switching enabled");
-            } else if config.should_follow_ghost_vcache() {
-                vcache_controller.set_strategy(vcache::SwitchingStrategy::FollowGhostVcache);
-                info!("V-Cache: following ghost-vcache mode changes");
-            } else {
-                vcache_controller.set_strategy(vcache::SwitchingStrategy::Manual);
-                info!("V-Cache: manual control");
+            // Set strategy from --vcache-strategy / [amd] vcache_switching
+            let mut strategy = vcache::SwitchingStrategy::from_settings(
+                &args.vcache_strategy,
+                args.vcache_gaming_threshold,
+                args.vcache_batch_threshold,
+            )?;
+            if matches!(strategy, vcache::SwitchingStrategy::Automatic { .. })
+                && !vcache_controller.supports_switching()
+            {
+                warn!("V-Cache: automatic switching needs a writable amd_x3d_mode; disabled");
+                strategy = vcache::SwitchingStrategy::FollowGhostVcache;
+            }
+            match strategy {
+                vcache::SwitchingStrategy::Automatic {
+                    gaming_threshold,
+                    batch_threshold,
+                } => info!(
+                    "V-Cache: automatic switching enabled (cache at {} gaming tasks, \
+                     frequency at {} batch tasks, {}s hysteresis)",
+                    gaming_threshold, batch_threshold, args.vcache_hysteresis_secs
+                ),
+                vcache::SwitchingStrategy::FollowGhostVcache => {
+                    info!("V-Cache: following ghost-vcache mode changes")
+                }
+                vcache::SwitchingStrategy::Manual => info!("V-Cache: manual control"),
+            }
+            vcache_controller.set_strategy(strategy);
+            vcache_controller
+                .set_hysteresis_duration(Duration::from_secs(args.vcache_hysteresis_secs));
+
+            // --gaming / --work also switch the hardware mode (restored on exit)
+            if let Some(mode) = vcache::forced_mode(args.gaming, args.work)
+                && mode != vcache_controller.current_mode()
+            {
+                if !vcache_controller.supports_switching() {
+                    info!(
+                        "V-Cache: mode is read-only, leaving hardware mode at {}",
+                        vcache_controller.current_mode()
+                    );
+                } else if vcache_controller.allows_mode_changes() {
+                    if let Err(e) = vcache_controller.request_mode(mode) {
+                        warn!("Failed to switch V-Cache mode: {:#}", e);
+                    }
+                } else {
+                    info!(
+                        "V-Cache: manual control, leaving hardware mode at {}",
+                        vcache_controller.current_mode()
+                    );
+                }
             }
         }
 
-        // Detect AMD prefcore rankings
-        let prefcore = pbo::detect_prefcore(topology.nr_cpus)?;
+        // Detect AMD prefcore rankings (guest rankings don't reflect host cores)
+        let mut prefcore = if topology.is_virtualized {
+            pbo::PrefcoreInfo::new(topology.nr_cpus)
+        } else {
+            pbo::detect_prefcore(topology.nr_cpus)?
+        };
         if prefcore.enabled {
             info!(
                 "AMD Prefcore: enabled (max ranking: {})",
@@ -312,24 +960,103 @@ impl<'a> Scheduler<'a> {
             );
         }
 
-        // Detect NVIDIA GPUs
-        let gpu_monitor = gpu::GpuMonitor::default();
+        // Keep latency-critical work off cores that boost well below their
+        // CCD siblings (guest frequencies don't reflect host cores)
+        let boost_spread = if topology.is_virtualized {
+            pbo::BoostSpread::default()
+        } else {
+            pbo::per_core_boost_spread(
+                &topology::cpu_max_freqs(topology.nr_cpus),
+                &topology.cpu_to_ccd,
+            )
+        };
+        if !boost_spread.outliers.is_empty() {
+            if prefcore.enabled {
+                info!(
+                    "Boost spread: CPUs {:?} boost well below their CCD siblings, ranked last",
+                    boost_spread.outliers
+                );
+                prefcore.avoid(&boost_spread.outliers);
+            } else {
+                info!(
+                    "Boost spread: CPUs {:?} boost well below their CCD siblings (prefcore disabled, not avoided)",
+                    boost_spread.outliers
+                );
+            }
+        }
+
+        // Detect GPUs
+        let mut gpu_monitor = gpu::GpuMonitor::default();
+        gpu_monitor.set_debounce_polls(args.gpu_debounce);
         if gpu_monitor.gpu_count() > 0 {
             info!("GPU: {}", gpu_monitor.summary());
             if let Some(primary) = gpu_monitor.primary_gpu() {
                 info!(
-                    "  Primary: {} ({} {})",
-                    primary.model, primary.pcie_speed, primary.pcie_width
+                    "  Primary: {} ({}, {} {})",
+                    primary.model, primary.kind, primary.pcie_speed, primary.pcie_width
                 );
             }
+            if gpu_monitor.discrete_gpu().is_some()
+                && let Some(igpu) = gpu_monitor.integrated_gpu()
+            {
+                info!("  Integrated: {} (ignored for GPU activity)", igpu.model);
+            }
+        }
+        let ai_gpu_map = aigpu::AiGpuMap::resolve(&config.ai_gpus, gpu_monitor.gpus());
+
+        // EPP hints need an EPP-capable pstate driver (guest cpufreq is emulated)
+        let epp_hints = !topology.is_virtualized
+            && match pbo::detect_cpufreq() {
+                Some(cpufreq) => match cpufreq.epp_unavailable_reason() {
+                    None => {
+                        info!(
+                            "cpufreq: {} (governor {})",
+                            cpufreq.driver, cpufreq.governor
+                        );
+                        true
+                    }
+                    Some(reason) => {
+                        warn!("cpufreq: {} - EPP hints disabled", reason);
+                        false
+                    }
+                },
+                None => {
+                    warn!("No cpufreq driver - EPP hints disabled");
+                    false
+                }
+            };
+        // A --load-state snapshot can switch EPP hints off, never on where
+        // they're unusable
+        let epp_hints = epp_hints && saved_state.is_none_or(|saved| saved.epp.hints);
+
+        // Undo EPP left at "performance" by an unclean exit, whether or not
+        // EPP hints are enabled this time
+        let state_path = std::path::Path::new(pbo::EPP_STATE_PATH);
+        match pbo::restore_epp_state(state_path) {
+            Ok(0) => {}
+            Ok(n) => warn!("Restored EPP on {} CPUs left by a previous unclean exit", n),
+            Err(e) => warn!("Failed to restore stale EPP state: {}", e),
         }
 
         // Initialize EPP manager for frequency hints
         let mut epp_manager = pbo::EppManager::new(topology.nr_cpus);
-        epp_manager.save_original(topology.nr_cpus);
+        if topology.handheld_apu.is_some() {
+            epp_manager.set_boost_epp(pbo::HANDHELD_BOOST_EPP);
+        }
+        if epp_hints && args.epp_dry_run {
+            info!("EPP dry run: changes are logged, not written");
+            epp_manager.set_dry_run(true);
+            epp_manager.save_original(topology.nr_cpus);
+        } else if epp_hints {
+            epp_manager.save_original(topology.nr_cpus);
+            if let Err(e) = epp_manager.persist_state(state_path) {
+                warn!("Failed to persist EPP state: {}", e);
+            }
+        }
 
         // Detect VMs
-        let vm_monitor = vm::VmMonitor::default();
+        let mut vm_monitor = vm::VmMonitor::default();
+        vm_monitor.set_respect_pinning(!args.override_pinned);
         if vm_monitor.vm_count() > 0 {
             info!(
                 "VMs: {} detected ({} gaming vCPUs, {} dev vCPUs)",
@@ -341,9 +1068,20 @@ impl<'a> Scheduler<'a> {
         if vm_monitor.has_iommu() {
             info!("IOMMU: {}", vm_monitor.iommu_summary());
         }
+        if vm_monitor.split_lock_mode() != vm::SplitLockMode::Unknown {
+            info!("Split-lock detection: {}", vm_monitor.split_lock_mode());
+        }
 
         // Detect containers
-        let container_monitor = container::ContainerMonitor::default();
+        let container_classes = container::ContainerClassMap::new(&config.containers);
+        if !container_classes.is_empty() {
+            info!("Container class mapping: {:?}", config.containers);
+        }
+        let mut container_monitor = container::ContainerMonitor::with_classes(container_classes);
+        container_monitor.set_batch_only(args.containers_batch_only);
+        if args.containers_batch_only {
+            info!("Containers: all scheduled as batch (--containers-batch-only)");
+        }
         if container_monitor.container_count() > 0 {
             info!(
                 "Containers: {} detected ({} AI, {} GPU)",
@@ -397,6 +1135,24 @@ impl<'a> Scheduler<'a> {
             rodata.freq_ccd = topology.freq_ccd.unwrap_or(0);
             rodata.asymmetric_ccd_boost = topology.asymmetric_ccd_boost;
             rodata.vcache_l3_mb = topology.vcache_l3_mb.unwrap_or(0);
+            // Multi-socket support
+            rodata.nr_sockets = topology.nr_sockets;
+            for (ccd, (node, socket)) in topology
+                .ccd_domains()
+                .into_iter()
+                .enumerate()
+                .take(rodata.ccd_node.len())
+            {
+                rodata.ccd_node[ccd] = node;
+                rodata.ccd_socket[ccd] = socket;
+            }
+            rodata.nr_isolated_cpus = topology.isolated_cpus().len() as u32;
+            rodata.ccx_locality = args.ccx_locality && topology.has_split_ccx();
+            rodata.per_pid_stats = args.per_pid_stats;
+            // Thermal steering (guest sensors don't reflect host dies)
+            if !topology.is_virtualized {
+                rodata.ccd_hot_temp_c = args.ccd_temp_threshold;
+            }
             // Default tunables (will be overwritten by runtime_tunables map after load)
             rodata.default_burst_threshold_ns = args.burst_threshold;
             rodata.default_slice_ns = args.slice_ns;
@@ -404,35 +1160,166 @@ impl<'a> Scheduler<'a> {
 
         // Load BPF program
         debug!("Loading BPF program...");
-        let mut skel = open_skel.load().context("Failed to load BPF program")?;
+        let mut skel = match open_skel.load() {
+            Ok(skel) => skel,
+            Err(e) => {
+                let report = attach::failure_report(
+                    "load",
+                    &e.to_string(),
+                    &attach::KernelInfo::capture(),
+                    &attach::libbpf_log(),
+                );
+                return Err(anyhow::Error::new(e).context(report));
+            }
+        };
+
+        // Initialize event handler for ringbuf
+        let mut event_handler =
+            events::EventHandler::new(args.verbose || args.debug, args.recent_events);
+        if let Some(ref path) = args.dump_events {
+            let file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("Failed to open event dump {}", path.display()))?;
+            event_handler.set_dump(std::io::LineWriter::new(file));
+        }
+        let event_handler = Arc::new(event_handler);
+
+        // Switch off map-fed features the running kernel can't support
+        let mut map_caps = mapcaps::MapCaps::default();
+        if let Err(e) = events::build_ringbuf(&skel.maps.events, event_handler.clone()) {
+            map_caps.disable(
+                MapFeature::Events,
+                &format!("ring buffer setup failed: {}", e),
+            );
+        }
+        for (feature, reason) in kernel_features.unsupported() {
+            map_caps.disable(feature, reason);
+        }
 
         // Populate cpu_ctxs map with topology info
         debug!("Populating CPU context map...");
         Self::init_cpu_contexts(&mut skel, &topology)?;
+        Self::init_topology_summary(&mut skel, &topology)?;
+        Self::init_cpu_capacities(&mut skel, &topology)?;
+
+        // Export topology maps for external tools
+        let topology_pinned = args.export_topology
+            && match Self::pin_topology_maps(&mut skel) {
+                Ok(()) => {
+                    info!("Topology maps pinned under {}", TOPOLOGY_PIN_DIR);
+                    true
+                }
+                Err(e) => {
+                    warn!("Failed to pin topology maps: {:#}", e);
+                    false
+                }
+            };
 
         // Populate prefcore rankings map
-        if prefcore.enabled {
+        if prefcore.enabled && map_caps.has(MapFeature::PrefcoreRankings) {
             debug!("Populating prefcore rankings map...");
             Self::init_prefcore_rankings(&mut skel, &prefcore)?;
         }
 
+        // Confine AI workloads to the reserved CPUs
+        if let Some(mask) = ai_reserve
+            && map_caps.has(MapFeature::ClassCpumasks)
+        {
+            let key = WORKLOAD_AI.to_ne_bytes();
+            skel.maps
+                .class_cpumasks
+                .update(&key, mask.as_bytes(), libbpf_rs::MapFlags::ANY)
+                .context("Failed to set AI CPU reservation")?;
+            info!("AI workloads reserved to {} CPUs", mask.weight());
+        }
+
+        // Confine AI and batch workloads to the efficiency CPUs
+        if let Some(mask) = efficiency_mask
+            && map_caps.has(MapFeature::ClassCpumasks)
+        {
+            for class in [WORKLOAD_AI, gaming::WORKLOAD_BATCH] {
+                skel.maps
+                    .class_cpumasks
+                    .update(
+                        &class.to_ne_bytes(),
+                        mask.as_bytes(),
+                        libbpf_rs::MapFlags::ANY,
+                    )
+                    .context("Failed to set AI/batch isolation")?;
+            }
+            info!(
+                "AI and batch workloads isolated to efficiency CPUs {:?}",
+                mask.cpus()
+            );
+        }
+
+        // Per-class CCD placement from the [placement] config section
+        let placements = config
+            .class_placements()
+            .context("Invalid [placement] config")?;
+        for (class, placement) in placements.iter().enumerate() {
+            if *placement == 0 {
+                continue;
+            }
+            skel.maps
+                .class_placement
+                .update(
+                    &(class as u32).to_ne_bytes(),
+                    &[*placement],
+                    libbpf_rs::MapFlags::ANY,
+                )
+                .context("Failed to set class placement")?;
+        }
+        for (class, placement) in &config.placement {
+            info!("Placement: {} -> {}", class, placement);
+        }
+
         // Initialize runtime tunables map
         debug!("Initializing runtime tunables...");
         Self::init_runtime_tunables(&mut skel, &args, gaming_mode, work_mode)?;
 
         // Attach struct_ops scheduler
         debug!("Attaching scheduler...");
-        let struct_ops = skel
-            .maps
-            .ghostbrew_ops
-            .attach_struct_ops()
-            .context("Failed to attach struct_ops scheduler")?;
+        let struct_ops = match skel.maps.ghostbrew_ops.attach_struct_ops() {
+            Ok(link) => link,
+            Err(e) => {
+                let report = attach::failure_report(
+                    "attach",
+                    &e.to_string(),
+                    &attach::KernelInfo::capture(),
+                    &attach::libbpf_log(),
+                );
+                return Err(anyhow::Error::new(e).context(report));
+            }
+        };
+
+        // ops.init errors disable the scheduler again right after attach
+        let attach_timeout = Duration::from_secs(args.attach_timeout);
+        if !attach::wait_for_state(attach_timeout, |state| {
+            *state == doctor::SchedExtState::Attached
+                && doctor::attached_scheduler().is_none_or(|ops| doctor::is_ghostbrew_ops(&ops))
+        }) {
+            bail!(attach::failure_report(
+                "enable",
+                &format!("scheduler not active {}s after attach", args.attach_timeout),
+                &attach::KernelInfo::capture(),
+                &attach::libbpf_log(),
+            ));
+        }
 
         info!("GhostBrew scheduler attached successfully");
         info!(
             "  Per-CCD/Cluster DSQs: {} (IDs 1-{})",
             topology.nr_ccds, topology.nr_ccds
         );
+        if topology.nr_sockets > 1 {
+            info!(
+                "  Sockets: {} (spill prefers same node, then same socket)",
+                topology.nr_sockets
+            );
+        }
         if topology.is_intel_hybrid {
             info!(
                 "  Intel Hybrid: {} P-cores, {} E-cores",
@@ -466,9 +1353,20 @@ impl<'a> Scheduler<'a> {
                 prefcore.preferred_cpus.len()
             );
         }
+        let (reference, ratio) = args.interactive_reference();
+        info!(
+            "  Interactive cutoff: {}us",
+            classify::interactive_cutoff(reference, ratio) / 1000
+        );
+        debug!(
+            "  Predicted burst classes: 0.5ms={} 2.5ms={} 10ms={}",
+            classify::classify_burst(500_000, reference, ratio),
+            classify::classify_burst(2_500_000, reference, ratio),
+            classify::classify_burst(10_000_000, reference, ratio)
+        );
 
         // Initialize MangoHud exporter if MangoHud is detected or benchmark mode
-        let mangohud_exporter = if mangohud::is_mangohud_running() || args.benchmark {
+        let mangohud_exporter = if mangohud::is_mangohud_running() || args.benchmark.is_some() {
             let mut exporter = mangohud::MangoHudExporter::new();
             if let Err(e) = exporter.init() {
                 warn!("Failed to initialize MangoHud exporter: {}", e);
@@ -481,24 +1379,72 @@ impl<'a> Scheduler<'a> {
             None
         };
 
+        if let Some(pid) = args.trace_pid
+            && !procfs::pid_alive(pid)
+        {
+            bail!("--trace-pid {}: no such process", pid);
+        }
+        let pid_tracer = args.trace_pid.map(trace::PidTracer::new);
+
+        if args.dump_events.is_some() && !map_caps.has(MapFeature::Events) {
+            warn!("--dump-events needs the events ringbuf, which is unavailable");
+        }
+
+        let adaptive_latency = args.adaptive_latency.map(|action| {
+            if !map_caps.has(MapFeature::Events) {
+                warn!("--adaptive-latency needs the events ringbuf, which is unavailable");
+            }
+            info!(
+                "Adaptive latency: {:?} above {} HighLatency events/s, every {}s at most",
+                action, args.adaptive_latency_rate, args.adaptive_latency_cooldown
+            );
+            adaptive::AdaptiveLatency::new(
+                action,
+                args.adaptive_latency_rate,
+                Duration::from_secs(args.adaptive_latency_cooldown),
+            )
+        });
+        let trace_marker = marker::TraceMarker::new(args.trace_markers);
+        let focus_tracker = args.focus_aware.then(focus::FocusTracker::default);
+
+        let statsd_exporter = args
+            .statsd_addr
+            .as_deref()
+            .map(statsd::StatsdExporter::new)
+            .transpose()?;
+
+        let pid_exporter = args.export_pids.clone().map(export::PidExporter::new);
+
+        let shared_pids = Arc::new(shared::SharedPids::default());
+        let api_server = args
+            .api_addr
+            .as_deref()
+            .map(|addr| {
+                api::ApiServer::start(addr, &topology, event_handler.clone(), shared_pids.clone())
+            })
+            .transpose()?;
+
+        let gamemode = gamemode::GameModeCoordinator::new(args.epp_over_gamemode);
+
         // Initialize runtime control interface
         let mut control_interface = control::ControlInterface::new();
         if let Err(e) = control_interface.init() {
             warn!("Failed to initialize control interface: {}", e);
         }
 
-        // Initialize event handler for ringbuf
-        let event_handler = Arc::new(events::EventHandler::new(args.verbose || args.debug));
-
         Ok(Self {
             skel,
             struct_ops: Some(struct_ops),
             args,
+            cli_settings,
             topology,
-            gaming_detector: gaming::GamingDetector::new(),
+            gaming_detector,
             prefcore,
+            boost_spread,
             gpu_monitor,
             epp_manager,
+            epp_hints,
+            gamemode,
             vm_monitor,
             container_monitor,
             cgroup_monitor,
@@ -506,9 +1452,41 @@ impl<'a> Scheduler<'a> {
             profile_manager,
             vcache_controller,
             active_profiles: std::collections::HashMap::new(),
+            fsync_games: HashSet::new(),
             mangohud_exporter,
+            statsd_exporter,
+            pid_exporter,
+            api_server,
+            shared_pids,
+            pid_tracer,
+            slice_sampler: trace::SliceSampler::default(),
+            slice_observation: None,
+            adaptive_latency,
+            trace_marker,
+            focus_tracker,
             control_interface,
             event_handler,
+            map_caps,
+            idle_jiffies: Vec::new(),
+            hot_ccds: HashSet::new(),
+            boosted_vm_pids: HashSet::new(),
+            workload_registry: registry::WorkloadRegistry::default(),
+            vm_vcpu_tids: HashSet::new(),
+            vm_iothread_pids: HashSet::new(),
+            package_transaction: false,
+            rescan_requested: false,
+            ollama_pids: HashSet::new(),
+            ollama_threads: HashSet::new(),
+            container_gpu_threads: std::collections::HashMap::new(),
+            ai_memory_threads: HashSet::new(),
+            ai_memory_node: None,
+            ai_gpu_map,
+            ai_gpu_threads: std::collections::HashMap::new(),
+            attached_ops: Some(SCHEDULER_NAME.to_string()),
+            shader_threads: HashSet::new(),
+            obs_threads: std::collections::HashMap::new(),
+            topology_pinned,
+            last_preempt_kicks: 0,
         })
     }
 
@@ -525,6 +1503,7 @@ impl<'a> Scheduler<'a> {
             let ccd = topology.cpu_to_ccd.get(cpu_idx).copied().unwrap_or(0);
             let ccx = topology.cpu_to_ccx.get(cpu_idx).copied().unwrap_or(0);
             let node = topology.cpu_to_node.get(cpu_idx).copied().unwrap_or(0);
+            let socket = topology.cpu_to_socket.get(cpu_idx).copied().unwrap_or(0);
             let smt_sibling = topology.cpu_to_sibling.get(cpu_idx).copied().unwrap_or(-1);
 
             // Determine if this is a P-core (Intel hybrid)
@@ -538,15 +1517,19 @@ impl<'a> Scheduler<'a> {
             let turbo_ranking = topology.turbo_rankings.get(cpu_idx).copied().unwrap_or(0);
             let is_turbo = turbo_ranking >= turbo_threshold && turbo_threshold > 0;
 
+            // isolcpus/nohz_full CPUs only run tasks pinned to them
+            let is_isolated = topology.cpu_isolated.get(cpu_idx).copied().unwrap_or(false);
+
             let ctx = CpuCtx {
                 ccd,
                 ccx,
                 node,
+                socket,
                 smt_sibling,
                 is_vcache: if ccd == vcache_ccd { 1 } else { 0 },
                 is_pcore: if is_pcore { 1 } else { 0 },
                 is_turbo: if is_turbo { 1 } else { 0 },
-                _pad: [0; 1],
+                is_isolated: if is_isolated { 1 } else { 0 },
             };
 
             let key = cpu.to_ne_bytes();
@@ -563,14 +1546,108 @@ impl<'a> Scheduler<'a> {
                 .with_context(|| format!("Failed to update cpu_ctxs for CPU {}", cpu))?;
 
             debug!(
-                "CPU {}: CCD={}, CCX={}, node={}, vcache={}, pcore={}, turbo={}, smt_sibling={}",
-                cpu, ccd, ccx, node, ctx.is_vcache, ctx.is_pcore, ctx.is_turbo, smt_sibling
+                "CPU {}: CCD={}, CCX={}, node={}, socket={}, vcache={}, pcore={}, turbo={}, isolated={}, smt_sibling={}",
+                cpu,
+                ccd,
+                ccx,
+                node,
+                socket,
+                ctx.is_vcache,
+                ctx.is_pcore,
+                ctx.is_turbo,
+                ctx.is_isolated,
+                smt_sibling
             );
         }
 
         Ok(())
     }
 
+    /// Initialize the read-only topology summary map
+    fn init_topology_summary(skel: &mut GhostbrewSkel, topology: &CpuTopology) -> Result<()> {
+        // Struct layout must match BPF topology_summary:
+        // u32 nr_cpus, u32 nr_ccds, u32 vcache_ccd, u32 freq_ccd,
+        // u32 zen_generation, u8 is_x3d, u8 is_intel_hybrid,
+        // u8 smt_enabled, u8 asymmetric_ccd_boost
+        let mut value = [0u8; 24];
+        value[0..4].copy_from_slice(&topology.nr_cpus.to_ne_bytes());
+        value[4..8].copy_from_slice(&topology.nr_ccds.to_ne_bytes());
+        value[8..12].copy_from_slice(&topology.vcache_ccd.unwrap_or(u32::MAX).to_ne_bytes());
+        value[12..16].copy_from_slice(&topology.freq_ccd.unwrap_or(u32::MAX).to_ne_bytes());
+        value[16..20].copy_from_slice(&topology.zen_generation.unwrap_or(0).to_ne_bytes());
+        value[20] = topology.is_x3d as u8;
+        value[21] = topology.is_intel_hybrid as u8;
+        value[22] = topology.smt_enabled as u8;
+        value[23] = topology.asymmetric_ccd_boost as u8;
+
+        let key = 0u32.to_ne_bytes();
+        skel.maps
+            .topology_summary
+            .update(&key, &value, libbpf_rs::MapFlags::ANY)
+            .context("Failed to initialize topology_summary map")?;
+
+        Ok(())
+    }
+
+    /// Pin the topology maps under TOPOLOGY_PIN_DIR
+    fn pin_topology_maps(skel: &mut GhostbrewSkel) -> Result<()> {
+        std::fs::create_dir_all(TOPOLOGY_PIN_DIR)
+            .with_context(|| format!("Failed to create {}", TOPOLOGY_PIN_DIR))?;
+
+        let maps = &mut skel.maps;
+        for (name, map) in [
+            ("cpu_ctxs", &mut maps.cpu_ctxs),
+            ("topology_summary", &mut maps.topology_summary),
+        ] {
+            let path = std::path::Path::new(TOPOLOGY_PIN_DIR).join(name);
+            // Stale pin from an instance that did not shut down cleanly
+            if path.exists() {
+                let _ = std::fs::remove_file(&path);
+            }
+            map.pin(&path)
+                .with_context(|| format!("Failed to pin {} to {:?}", name, path))?;
+        }
+
+        Ok(())
+    }
+
+    /// Remove the topology pins created by pin_topology_maps()
+    fn unpin_topology_maps(&mut self) {
+        let maps = &mut self.skel.maps;
+        for (name, map) in [
+            ("cpu_ctxs", &mut maps.cpu_ctxs),
+            ("topology_summary", &mut maps.topology_summary),
+        ] {
+            let path = std::path::Path::new(TOPOLOGY_PIN_DIR).join(name);
+            if let Err(e) = map.unpin(&path) {
+                warn!("Failed to unpin {:?}: {}", path, e);
+            }
+        }
+
+        // Only succeeds once the directory is empty
+        let _ = std::fs::remove_dir(TOPOLOGY_PIN_DIR);
+        self.topology_pinned = false;
+    }
+
+    /// Initialize per-CPU capacity in BPF map
+    fn init_cpu_capacities(skel: &mut GhostbrewSkel, topology: &CpuTopology) -> Result<()> {
+        for (cpu, &capacity) in topology.cpu_capacity.iter().enumerate() {
+            let key = (cpu as u32).to_ne_bytes();
+            let value = capacity.to_ne_bytes();
+
+            skel.maps
+                .cpu_capacities
+                .update(&key, &value, libbpf_rs::MapFlags::ANY)
+                .with_context(|| format!("Failed to update cpu_capacities for CPU {}", cpu))?;
+        }
+
+        debug!(
+            "Populated CPU capacities for {} CPUs",
+            topology.cpu_capacity.len()
+        );
+        Ok(())
+    }
+
     /// Initialize prefcore rankings in BPF map
     fn init_prefcore_rankings(
         skel: &mut GhostbrewSkel,
@@ -600,24 +1677,31 @@ impl<'a> Scheduler<'a> {
         gaming_mode: bool,
         work_mode: bool,
     ) -> Result<()> {
-        // Struct layout must match BPF runtime_tunables:
-        // u64 burst_threshold_ns, u64 slice_ns, u8 gaming_mode, u8 work_mode, u8[6] pad
-        let mut value = [0u8; 24];
-        value[0..8].copy_from_slice(&args.burst_threshold.to_ne_bytes());
-        value[8..16].copy_from_slice(&args.slice_ns.to_ne_bytes());
-        value[16] = if gaming_mode { 1 } else { 0 };
-        value[17] = if work_mode { 1 } else { 0 };
-        // Padding bytes 18-23 are already 0
+        let interactive_pct = args.interactive_ratio.map_or(0, classify::ratio_to_pct);
+        let value = tunables::RuntimeTunables {
+            burst_threshold_ns: args.burst_threshold,
+            slice_ns: args.slice_ns,
+            gaming_mode,
+            work_mode,
+            interactive_pct,
+            max_preempt_rate: args.max_preempt_rate,
+            ..Default::default()
+        };
 
         let key = 0u32.to_ne_bytes();
         skel.maps
             .runtime_tunables
-            .update(&key, &value, libbpf_rs::MapFlags::ANY)
+            .update(&key, &value.to_bytes(), libbpf_rs::MapFlags::ANY)
             .context("Failed to initialize runtime_tunables map")?;
 
         debug!(
-            "Runtime tunables: burst={}ns, slice={}ns, gaming={}, work={}",
-            args.burst_threshold, args.slice_ns, gaming_mode, work_mode
+            "Runtime tunables: burst={}ns, slice={}ns, gaming={}, work={}, interactive_pct={}, max_preempt_rate={}",
+            args.burst_threshold,
+            args.slice_ns,
+            gaming_mode,
+            work_mode,
+            interactive_pct,
+            args.max_preempt_rate
         );
         Ok(())
     }
@@ -639,42 +1723,37 @@ impl<'a> Scheduler<'a> {
             .lookup(&key, libbpf_rs::MapFlags::ANY)?
             .ok_or_else(|| anyhow::anyhow!("runtime_tunables map empty"))?;
 
-        // Parse current values
-        let mut burst = u64::from_ne_bytes(current[0..8].try_into().unwrap_or([0; 8]));
-        let mut slice = u64::from_ne_bytes(current[8..16].try_into().unwrap_or([0; 8]));
-        let mut gaming = current[16] != 0;
-        let mut work = current[17] != 0;
-
-        // Apply updates
+        // Apply updates, preserving fields this function doesn't manage
+        let mut value = tunables::RuntimeTunables::from_bytes(&current);
+        let modes = (value.gaming_mode, value.work_mode);
         if let Some(v) = burst_threshold_ns {
-            burst = v;
+            value.burst_threshold_ns = v;
         }
         if let Some(v) = slice_ns {
-            slice = v;
+            value.slice_ns = v;
         }
         if let Some(v) = gaming_mode {
-            gaming = v;
+            value.gaming_mode = v;
         }
         if let Some(v) = work_mode {
-            work = v;
+            value.work_mode = v;
         }
 
-        // Write back
-        let mut value = [0u8; 24];
-        value[0..8].copy_from_slice(&burst.to_ne_bytes());
-        value[8..16].copy_from_slice(&slice.to_ne_bytes());
-        value[16] = if gaming { 1 } else { 0 };
-        value[17] = if work { 1 } else { 0 };
-
         self.skel
             .maps
             .runtime_tunables
-            .update(&key, &value, libbpf_rs::MapFlags::ANY)
+            .update(&key, &value.to_bytes(), libbpf_rs::MapFlags::ANY)
             .context("Failed to update runtime_tunables map")?;
 
+        if (value.gaming_mode, value.work_mode) != modes {
+            self.trace_marker.mark(&marker::Decision::Mode {
+                gaming: value.gaming_mode,
+                work: value.work_mode,
+            });
+        }
         debug!(
             "Updated runtime tunables: burst={}ns, slice={}ns, gaming={}, work={}",
-            burst, slice, gaming, work
+            value.burst_threshold_ns, value.slice_ns, value.gaming_mode, value.work_mode
         );
         Ok(())
     }
@@ -716,12 +1795,93 @@ impl<'a> Scheduler<'a> {
         }
     }
 
+    /// Reload config and game profiles, applying what changed
+    fn reload_config(&mut self, source: &impl reload::ConfigSource) {
+        let outcome = match reload::reload(&self.config, &self.profile_manager, source) {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                warn!("Config reload failed, keeping current config: {:#}", e);
+                return;
+            }
+        };
+
+        if outcome.changes.is_empty() {
+            info!("Config reloaded, no changes");
+        }
+        for change in &outcome.changes {
+            info!("Config reload: {}", change);
+        }
+
+        if outcome.overrides_changed(&self.config) {
+            self.gaming_detector
+                .set_overrides(gaming::ClassifyOverrides::new(
+                    &outcome.config.gaming.allow,
+                    &outcome.config.gaming.deny,
+                ));
+            self.gaming_detector
+                .set_browsers(gaming::BrowserFilter::new(&outcome.config.gaming.browsers));
+            self.gaming_detector
+                .set_compositors(gaming::CompositorFilter::new(
+                    &outcome.config.gaming.compositors,
+                ));
+        }
+        if outcome.config.gaming.min_age_ms != self.config.gaming.min_age_ms {
+            self.gaming_detector
+                .set_min_age(Duration::from_millis(outcome.config.gaming.min_age_ms));
+        }
+
+        if outcome.config.containers != self.config.containers {
+            self.container_monitor
+                .set_classes(container::ContainerClassMap::new(
+                    &outcome.config.containers,
+                ));
+        }
+
+        if outcome.config.ai_gpus != self.config.ai_gpus {
+            self.ai_gpu_map =
+                aigpu::AiGpuMap::resolve(&outcome.config.ai_gpus, self.gpu_monitor.gpus());
+        }
+
+        // Changed [defaults] replace the defaults profiles revert to, unless
+        // the CLI set them
+        let tunables_changed = effective::apply_reloaded_defaults(
+            &mut self.args,
+            self.cli_settings,
+            &self.config.defaults,
+            &outcome.config.defaults,
+        );
+        if tunables_changed
+            && self.active_profiles.is_empty()
+            && let Err(e) = self.update_runtime_tunables(
+                Some(self.default_burst_threshold()),
+                Some(self.args.slice_ns),
+                None,
+                None,
+            )
+        {
+            warn!("Failed to apply reloaded tunables: {}", e);
+        }
+
+        if let Some(profiles) = outcome.profiles {
+            self.profile_manager = profiles;
+        }
+        self.config = outcome.config;
+    }
+
+    /// Burst threshold without a profile: the fsync one while an fsync game runs
+    fn default_burst_threshold(&self) -> u64 {
+        match self.args.fsync_burst_threshold {
+            Some(ns) if !self.fsync_games.is_empty() => ns,
+            _ => self.args.burst_threshold,
+        }
+    }
+
     /// Revert to default tunables when no profiled games are active
     fn revert_to_default_tunables(&mut self) {
         info!("Reverting to default tunables (no active profiles)");
 
         if let Err(e) = self.update_runtime_tunables(
-            Some(self.args.burst_threshold),
+            Some(self.default_burst_threshold()),
             Some(self.args.slice_ns),
             None, // Keep current gaming_mode
             None, // Keep current work_mode
@@ -730,66 +1890,191 @@ impl<'a> Scheduler<'a> {
         }
     }
 
+    /// Run until shutdown, then restore EPP and V-Cache state and detach,
+    /// also when the main loop fails
     fn run(&mut self, shutdown: Arc<AtomicBool>) -> Result<()> {
+        let result = self.run_loop(shutdown);
+
+        info!("GhostBrew shutting down...");
+        self.save_state();
+
+        // Restore original EPP values and V-Cache mode
+        self.epp_manager.restore_original();
+        self.vcache_controller.restore_original();
+
+        // Detach scheduler
+        self.struct_ops.take();
+
+        if self.topology_pinned {
+            self.unpin_topology_maps();
+        }
+
+        result
+    }
+
+    fn run_loop(&mut self, shutdown: Arc<AtomicBool>) -> Result<()> {
         info!("GhostBrew v{} running...", env!("CARGO_PKG_VERSION"));
         info!("Burst threshold: {} ns", self.args.burst_threshold);
         info!("Time slice: {} ns", self.args.slice_ns);
-        info!(
-            "SMT: {}",
-            if self.topology.smt_enabled {
-                "enabled"
-            } else {
-                "disabled"
-            }
-        );
+        let (smt_cores, single_cores) = self.topology.smt_core_counts();
+        if self.topology.smt_enabled && single_cores > 0 {
+            info!(
+                "SMT: mixed ({} cores with SMT, {} without)",
+                smt_cores, single_cores
+            );
+        } else {
+            info!(
+                "SMT: {}",
+                if self.topology.smt_enabled {
+                    "enabled"
+                } else {
+                    "disabled"
+                }
+            );
+        }
+        info!("CCX layout: {:?} CCX per CCD", self.topology.ccx_per_ccd());
+        if self.args.ccx_locality && !self.topology.has_split_ccx() {
+            info!("--ccx-locality has no effect: every CCD is a single CCX");
+        }
 
-        info!("Event streaming enabled (ringbuf)");
+        if self.map_caps.has(MapFeature::Events) {
+            info!("Event streaming enabled (ringbuf)");
+        }
 
         // Initial gaming PID scan
         self.update_gaming_pids();
+        self.update_shader_threads();
+        self.update_obs_threads();
 
         // Initial cgroup classification population
         self.update_cgroup_classes();
 
+        // Prime the idle jiffies sample for per-CCD idle tracking
+        self.update_ccd_idle();
+
+        // Launch the benchmark command, if any, once the scheduler is ready
+        let mut benchmark = match self.args.benchmark.clone().flatten() {
+            Some(command) => Some(benchmark::BenchmarkRun::start(
+                &command,
+                Duration::from_secs(self.args.benchmark_duration),
+            )?),
+            None => None,
+        };
+
+        #[cfg(feature = "tui")]
+        let mut dashboard = self.args.tui.then(tui::Dashboard::start);
+        #[cfg(feature = "tui")]
+        let mut last_snapshot: Option<tui::DashboardSnapshot> = None;
+
         // Main loop
-        let stats_interval = Duration::from_secs(self.args.stats_interval);
         let poll_interval = Duration::from_millis(100);
         let mut last_stats = Instant::now();
+        let mut stats_ticks: u64 = 0;
+        let mut cadence = scan::ScanCadence::new(
+            [
+                self.args.gaming_scan_secs,
+                self.args.vm_scan_secs,
+                self.args.container_scan_secs,
+                self.args.cgroup_scan_secs,
+            ]
+            .map(Duration::from_secs),
+            last_stats,
+        );
 
         while !shutdown.load(Ordering::Relaxed) {
             // Poll ringbuf for events (100ms timeout, non-blocking)
             // Build ringbuf in each iteration to avoid lifetime issues
-            {
+            if self.map_caps.has(MapFeature::Events) {
                 if let Ok(ringbuf) =
                     events::build_ringbuf(&self.skel.maps.events, self.event_handler.clone())
                     && let Err(e) = events::poll_events(&ringbuf, poll_interval)
                 {
                     debug!("Ringbuf poll error: {}", e);
                 }
+            } else {
+                std::thread::sleep(poll_interval);
+            }
+
+            // Config reload requested via SIGHUP
+            if RELOAD_REQUESTED.swap(false, Ordering::Relaxed) {
+                info!("Received SIGHUP, reloading configuration");
+                self.reload_config(&reload::SystemSource);
+            }
+
+            // Sample counters every poll so the benchmark window lines up with frames
+            if let Some(run) = benchmark.as_mut() {
+                run.record(self.benchmark_counters());
+                if run.is_finished() {
+                    break;
+                }
+            }
+
+            #[cfg(feature = "tui")]
+            if let Some(action) = dashboard.as_ref().and_then(tui::Dashboard::poll_action)
+                && self.handle_dashboard_action(action)
+            {
+                break;
+            }
+
+            // Each monitor rescans on its own interval, backing off while
+            // pacman is upgrading the system
+            let factor = if self.package_transaction {
+                PACKAGE_TRANSACTION_SCAN_FACTOR
+            } else {
+                1
+            };
+            let force = std::mem::take(&mut self.rescan_requested);
+            let due = cadence.take_due(Instant::now(), factor, force);
+            for &monitor in &due {
+                match monitor {
+                    scan::Monitor::Gaming => {
+                        self.update_gaming_pids();
+                        self.update_focus_tiers();
+                        self.update_shader_threads();
+                        self.update_obs_threads();
+                        if self.epp_hints {
+                            self.gamemode.refresh(
+                                self.gaming_detector.gamemoded(),
+                                self.gaming_detector.gaming_pids(),
+                            );
+                        }
+                    }
+                    scan::Monitor::Vm => self.update_vm_pids(),
+                    scan::Monitor::Container => self.update_container_pids(),
+                    scan::Monitor::Cgroup => self.update_cgroup_classes(),
+                }
+            }
+            if !due.is_empty() {
+                self.export_pids();
             }
 
             // Check if it's time for periodic tasks
+            let stats_interval = Duration::from_secs(self.args.stats_interval);
             if last_stats.elapsed() < stats_interval {
                 continue;
             }
             last_stats = Instant::now();
+            stats_ticks += 1;
+
+            // Confirm GhostBrew is still the attached scheduler
+            self.check_attached_scheduler();
+
+            // Drop PID map entries whose process is gone
+            if stats_ticks.is_multiple_of(scan::RECONCILE_INTERVALS) {
+                self.prune_stale_pid_entries();
+            }
 
-            // Scan for gaming PIDs periodically
-            self.update_gaming_pids();
+            // Back off process scanning while pacman is upgrading the system
+            self.check_package_transaction();
 
             // Update GPU power states
             if self.gpu_monitor.update_power_states() {
                 debug!("GPU power state changed");
             }
 
-            // Scan for VMs and update BPF map
-            self.update_vm_pids();
-
-            // Scan for containers and update BPF map
-            self.update_container_pids();
-
-            // Update cgroup classifications
-            self.update_cgroup_classes();
+            // Push per-CCD idle capacity and temperatures to BPF
+            self.update_ccd_idle();
+            self.update_ccd_temps();
 
             // Apply EPP hints based on workload
             self.update_epp_hints();
@@ -800,79 +2085,210 @@ impl<'a> Scheduler<'a> {
             // Poll control interface for runtime tuning commands
             self.poll_control_interface();
 
+            self.sync_event_drops();
+            self.adapt_to_latency();
+            self.sample_slices();
+
+            if self.statsd_exporter.is_some()
+                && let Some(snapshot) = self.stats_snapshot()
+                && let Some(exporter) = self.statsd_exporter.as_mut()
+            {
+                exporter.send(&snapshot);
+            }
+            self.publish_api_state();
+
+            self.print_pid_trace();
+
             // Print stats if requested
-            if self.args.stats {
+            if self.args.stats || self.args.stats_once || self.args.per_pid_stats {
                 self.print_stats();
             }
 
+            #[cfg(feature = "tui")]
+            if let Some(dash) = dashboard.as_mut() {
+                let snapshot = self.dashboard_snapshot();
+                let data = tui::dashboard_data(last_snapshot.as_ref(), &snapshot, stats_interval);
+                if let Err(e) = dash.draw(&data) {
+                    debug!("Dashboard draw failed: {}", e);
+                }
+                last_snapshot = Some(snapshot);
+            }
+
             // Export to MangoHud CSV if enabled
             self.export_mangohud_stats();
+
+            // Single snapshot requested, fall through to normal shutdown
+            if self.args.stats_once {
+                break;
+            }
         }
 
-        info!("GhostBrew shutting down...");
+        // Leave the alternate screen before printing anything
+        #[cfg(feature = "tui")]
+        drop(dashboard);
 
-        // Restore original EPP values
-        self.epp_manager.restore_original();
-
-        // Detach scheduler
-        self.struct_ops.take();
+        if let Some(run) = benchmark {
+            match run.finish() {
+                Ok(report) => benchmark::print_report(&report),
+                Err(e) => warn!("Benchmark produced no results: {:#}", e),
+            }
+        }
 
         Ok(())
     }
 
+    /// Delete gaming_pids, vm_vcpu_pids and container_pids keys whose
+    /// process no longer exists
+    fn prune_stale_pid_entries(&mut self) {
+        let maps = [
+            (MapFeature::GamingPids, &self.skel.maps.gaming_pids),
+            (MapFeature::VmVcpuPids, &self.skel.maps.vm_vcpu_pids),
+            (MapFeature::ContainerPids, &self.skel.maps.container_pids),
+        ];
+        for (feature, map) in maps {
+            if !self.map_caps.has(feature) {
+                continue;
+            }
+            let stale = scan::stale_keys(map.keys(), procfs::pid_alive);
+            for pid in &stale {
+                let _ = map.delete(&pid.to_ne_bytes());
+                self.workload_registry.forget(*pid);
+            }
+            if !stale.is_empty() {
+                info!(
+                    "Pruned {} stale entries from {}",
+                    stale.len(),
+                    feature.map_name()
+                );
+            }
+        }
+    }
+
     /// Update the gaming_pids BPF map with detected gaming processes
     fn update_gaming_pids(&mut self) {
+        if !self.map_caps.has(MapFeature::GamingPids) {
+            return;
+        }
+
         match self.gaming_detector.scan_changes() {
             Ok((new_pids, removed_pids)) => {
-                // Add new gaming PIDs
+                // Remove old PIDs first: a reused PID is both removed and added
+                for pid in &removed_pids {
+                    let key = pid.to_ne_bytes();
+                    self.workload_registry
+                        .release(registry::Source::Process, *pid);
+                    let _ = self.skel.maps.smt_prefs.delete(&key);
+                    let _ = self.skel.maps.priority_boosts.delete(&key);
+                    let _ = self.skel.maps.pid_stats.delete(&key);
+                    // Clean up active profiles
+                    let last_fsync = self.fsync_games.remove(pid) && self.fsync_games.is_empty();
+                    if let Some(profile_name) = self.active_profiles.remove(pid) {
+                        debug!("Removed profile '{}' for PID {}", profile_name, pid);
+
+                        // If no more profiled games, revert to default tunables
+                        if self.active_profiles.is_empty() {
+                            self.revert_to_default_tunables();
+                        }
+                    } else if last_fsync && self.active_profiles.is_empty() {
+                        self.revert_to_default_tunables();
+                    }
+                }
+
+                // Add new gaming PIDs (skipping any that exited since the scan)
                 for (pid, class) in &new_pids {
+                    if !procfs::pid_alive(*pid) {
+                        continue;
+                    }
                     let key = pid.to_ne_bytes();
-                    let value = class.to_ne_bytes();
-                    if let Err(e) =
-                        self.skel
-                            .maps
-                            .gaming_pids
-                            .update(&key, &value, libbpf_rs::MapFlags::ANY)
+                    self.workload_registry
+                        .claim(registry::Source::Process, *pid, *class);
+                    if self.trace_marker.is_enabled() {
+                        let comm = procfs::read_pid_file(*pid, "comm").unwrap_or_default();
+                        self.trace_marker.mark(&marker::Decision::Classify {
+                            pid: *pid,
+                            comm: &comm,
+                            class: classify::workload_class_name(*class),
+                        });
+                    }
+
+                    // Start the game's per-PID stats from zero
+                    if *class == gaming::WORKLOAD_GAMING
+                        && self.args.per_pid_stats
+                        && self.map_caps.has(MapFeature::PidStats)
                     {
-                        debug!("Failed to add gaming PID {}: {}", pid, e);
+                        let _ = self.skel.maps.pid_stats.update(
+                            &key,
+                            &[0u8; pidstats::PID_STATS_SIZE],
+                            libbpf_rs::MapFlags::ANY,
+                        );
+                    }
+
+                    if *class == gaming::WORKLOAD_GAMING
+                        && let Some(sync) = self.gaming_detector.wine_sync(*pid)
+                    {
+                        info!("Gaming PID {} uses Wine {}", pid, sync);
+                        if sync == gaming::WineSync::Fsync {
+                            self.apply_fsync_burst_threshold(*pid);
+                        }
                     }
 
                     // Check for profile match
                     if let Some(exe_name) = gaming::get_exe_name(*pid) {
                         // Clone profile data to avoid borrow conflict
-                        let profile_data = self
-                            .profile_manager
-                            .match_process(&exe_name, None)
-                            .map(|p| (p.name.clone(), p.tunables.clone()));
-
-                        if let Some((profile_name, tunables)) = profile_data {
+                        let profile_data =
+                            self.profile_manager
+                                .match_process(&exe_name, None)
+                                .map(|p| {
+                                    (
+                                        p.name.clone(),
+                                        p.tunables.clone(),
+                                        p.smt_preference.bpf_value(),
+                                    )
+                                });
+
+                        if let Some((profile_name, tunables, smt_pref)) = profile_data {
+                            let arch = self
+                                .gaming_detector
+                                .wine_arch(*pid)
+                                .map(|arch| format!(", {}", arch))
+                                .unwrap_or_default();
                             info!(
-                                "Matched profile '{}' for {} (PID {})",
-                                profile_name, exe_name, pid
+                                "Matched profile '{}' for {} (PID {}{})",
+                                profile_name, exe_name, pid, arch
                             );
                             self.active_profiles.insert(*pid, profile_name.clone());
 
                             // Apply profile tunables to BPF
                             self.apply_profile_tunables_direct(&profile_name, &tunables);
-                        }
-                    }
-                }
-
-                // Remove old PIDs
-                for pid in &removed_pids {
-                    let key = pid.to_ne_bytes();
-                    let _ = self.skel.maps.gaming_pids.delete(&key);
-                    // Clean up active profiles
-                    if let Some(profile_name) = self.active_profiles.remove(pid) {
-                        debug!("Removed profile '{}' for PID {}", profile_name, pid);
 
-                        // If no more profiled games, revert to default tunables
-                        if self.active_profiles.is_empty() {
-                            self.revert_to_default_tunables();
+                            // Per-game SMT sibling preference
+                            if smt_pref != 0 && self.map_caps.has(MapFeature::SmtPrefs) {
+                                let _ = self.skel.maps.smt_prefs.update(
+                                    &key,
+                                    &smt_pref.to_ne_bytes(),
+                                    libbpf_rs::MapFlags::ANY,
+                                );
+                            }
+
+                            // Per-game priority boost
+                            let boost = tunables.bpf_priority_boost();
+                            if boost != 0 && self.map_caps.has(MapFeature::PriorityBoosts) {
+                                info!(
+                                    "Profile '{}': priority boost {} for PID {}",
+                                    profile_name, boost, pid
+                                );
+                                let _ = self.skel.maps.priority_boosts.update(
+                                    &key,
+                                    &boost.to_ne_bytes(),
+                                    libbpf_rs::MapFlags::ANY,
+                                );
+                            }
                         }
                     }
                 }
 
+                self.sync_workload_maps();
+
                 let (gaming, batch, ai) = self.gaming_detector.counts();
                 if gaming > 0 || batch > 0 || ai > 0 {
                     debug!(
@@ -887,29 +2303,127 @@ impl<'a> Scheduler<'a> {
         }
     }
 
+    /// Write the registry's resolved PID classes to gaming_pids and
+    /// container_pids
+    fn sync_workload_maps(&mut self) {
+        for update in self.workload_registry.sync() {
+            let (map, feature) = match update {
+                registry::MapUpdate::Set { map, .. } | registry::MapUpdate::Delete { map, .. } => {
+                    match map {
+                        registry::WorkloadMap::GamingPids => {
+                            (&self.skel.maps.gaming_pids, MapFeature::GamingPids)
+                        }
+                        registry::WorkloadMap::ContainerPids => {
+                            (&self.skel.maps.container_pids, MapFeature::ContainerPids)
+                        }
+                    }
+                }
+            };
+            if !self.map_caps.has(feature) {
+                continue;
+            }
+            let key = update.pid().to_ne_bytes();
+            match update {
+                registry::MapUpdate::Set { pid, class, .. } => {
+                    if let Err(e) = map.update(&key, &class.to_ne_bytes(), libbpf_rs::MapFlags::ANY)
+                    {
+                        debug!(
+                            "Failed to set {} for PID {}: {}",
+                            feature.map_name(),
+                            pid,
+                            e
+                        );
+                    }
+                }
+                registry::MapUpdate::Delete { .. } => {
+                    let _ = map.delete(&key);
+                }
+            }
+        }
+    }
+
+    /// Switch to --fsync-burst-threshold when the first fsync game starts
+    /// (profile tunables stay in charge while a profiled game runs)
+    fn apply_fsync_burst_threshold(&mut self, pid: u32) {
+        let Some(ns) = self.args.fsync_burst_threshold else {
+            return;
+        };
+        if !self.fsync_games.insert(pid) || self.fsync_games.len() > 1 {
+            return;
+        }
+        if !self.active_profiles.is_empty() {
+            return;
+        }
+        match self.update_runtime_tunables(Some(ns), None, None, None) {
+            Ok(()) => info!("fsync game running: burst threshold {} ns", ns),
+            Err(e) => warn!("Failed to apply fsync burst threshold: {}", e),
+        }
+    }
+
+    /// Keep only the focused game in the gaming tier (--focus-aware)
+    fn update_focus_tiers(&mut self) {
+        let Some(tracker) = self.focus_tracker.as_mut() else {
+            return;
+        };
+        let gaming: Vec<u32> = self.gaming_detector.gaming_pids().collect();
+
+        // The display session is found through a game's own environment
+        let env = gaming.iter().find_map(|&pid| {
+            let env = focus::session_env(&procfs::read_pid_file(pid, "environ")?);
+            focus::focus_source(&env).is_some().then_some(env)
+        });
+        let focused = env.and_then(|env| focus::focused_pid(&env));
+
+        for (pid, class) in tracker.update(focused, &gaming, focus::parent_pid) {
+            debug!(
+                "PID {} {} focus: {}",
+                pid,
+                if class == gaming::WORKLOAD_GAMING {
+                    "has"
+                } else {
+                    "lost"
+                },
+                classify::workload_class_name(class)
+            );
+            self.workload_registry
+                .claim(registry::Source::Process, pid, class);
+        }
+        self.sync_workload_maps();
+    }
+
     /// Update VM vCPU PIDs in BPF map
     fn update_vm_pids(&mut self) {
+        if !self.map_caps.has(MapFeature::VmVcpuPids) {
+            return;
+        }
+
         match self.vm_monitor.rescan() {
             Ok((new_vms, removed_pids)) => {
                 // Log new VMs
                 for vm in &new_vms {
                     info!(
-                        "New VM detected: {} ({}) with {} vCPUs",
+                        "New VM detected: {} ({}) with {} vCPUs, {} iothreads{}",
                         vm.name,
                         vm.workload_type,
-                        vm.vcpu_pids.len()
+                        vm.vcpu_pids.len(),
+                        vm.iothread_pids.len(),
+                        if vm.vcpus_pinned { ", pinned" } else { "" }
                     );
                 }
 
                 // Update BPF map with all vCPU workloads
                 let workloads = self.vm_monitor.get_vcpu_workloads();
+                let vcpus: HashSet<u32> = workloads.keys().copied().collect();
                 for (pid, workload_type) in workloads {
+                    if !procfs::pid_alive(pid) {
+                        continue;
+                    }
                     let key = pid.to_ne_bytes();
                     let class = match workload_type {
-                        vm::VmWorkloadType::Gaming => 6u32,  // WORKLOAD_VM_GAMING
-                        vm::VmWorkloadType::Dev => 5u32,     // WORKLOAD_VM_DEV
-                        vm::VmWorkloadType::Ai => 4u32,      // WORKLOAD_AI
-                        vm::VmWorkloadType::Unknown => 5u32, // Default to dev
+                        vm::VmWorkloadType::Gaming => WORKLOAD_VM_GAMING,
+                        vm::VmWorkloadType::Dev => WORKLOAD_VM_DEV,
+                        vm::VmWorkloadType::Ai => WORKLOAD_AI,
+                        vm::VmWorkloadType::Unknown => WORKLOAD_VM_DEV, // Default to dev
                     };
                     let value = class.to_ne_bytes();
                     let _ =
@@ -919,11 +2433,54 @@ impl<'a> Scheduler<'a> {
                             .update(&key, &value, libbpf_rs::MapFlags::ANY);
                 }
 
-                // Remove old PIDs
+                // Remove old PIDs, and vCPUs of VMs that exited or paused
                 for pid in removed_pids {
                     let key = pid.to_ne_bytes();
                     let _ = self.skel.maps.vm_vcpu_pids.delete(&key);
                 }
+                for &tid in self.vm_vcpu_tids.difference(&vcpus) {
+                    let key = tid.to_ne_bytes();
+                    let _ = self.skel.maps.vm_vcpu_pids.delete(&key);
+                }
+                self.vm_vcpu_tids = vcpus;
+
+                // IO threads get interactive priority so VM disk/network stays responsive
+                let iothreads = self.vm_monitor.iothread_pids();
+                for &tid in iothreads.difference(&self.vm_iothread_pids) {
+                    let key = tid.to_ne_bytes();
+                    let value = WORKLOAD_INTERACTIVE.to_ne_bytes();
+                    let _ =
+                        self.skel
+                            .maps
+                            .vm_vcpu_pids
+                            .update(&key, &value, libbpf_rs::MapFlags::ANY);
+                }
+                for &tid in self.vm_iothread_pids.difference(&iothreads) {
+                    let key = tid.to_ne_bytes();
+                    let _ = self.skel.maps.vm_vcpu_pids.delete(&key);
+                }
+                self.vm_iothread_pids = iothreads;
+                self.update_ai_memory_numa();
+                self.update_ai_gpu_numa();
+
+                // Active Looking Glass sessions: treat the whole QEMU process
+                // (vCPUs plus the threads servicing the frame buffer) as gaming
+                if !self.map_caps.has(MapFeature::GamingPids) {
+                    return;
+                }
+                let boosted = self.vm_monitor.boosted_vm_pids();
+                for &pid in boosted.difference(&self.boosted_vm_pids) {
+                    info!("Looking Glass session active: boosting QEMU PID {}", pid);
+                }
+                self.workload_registry.replace(
+                    registry::Source::LookingGlass,
+                    boosted
+                        .iter()
+                        .map(|&pid| (pid, gaming::WORKLOAD_GAMING))
+                        .collect(),
+                );
+                self.sync_workload_maps();
+                self.boosted_vm_pids = boosted;
             }
             Err(e) => {
                 debug!("VM scan failed: {}", e);
@@ -933,50 +2490,366 @@ impl<'a> Scheduler<'a> {
 
     /// Update container PIDs in BPF map
     fn update_container_pids(&mut self) {
+        if !self.map_caps.has(MapFeature::ContainerPids) {
+            return;
+        }
+
         match self.container_monitor.rescan() {
             Ok((new_containers, removed_ids)) => {
                 // Log new containers
                 for container in &new_containers {
                     info!(
-                        "New container detected: {} ({}) with {} PIDs, GPU: {}",
+                        "New container detected: {} ({}) with {} PIDs, GPUs: {}",
                         container.id,
                         container.workload_type,
                         container.pids.len(),
-                        container.has_gpu
+                        container.gpu_count
                     );
                 }
 
-                // Update BPF map with all container PIDs
-                let pids = self.container_monitor.all_pids();
-                for (pid, workload_type) in pids {
-                    let key = pid.to_ne_bytes();
-                    let class = match workload_type {
-                        container::ContainerWorkloadType::Ai => 4u32, // WORKLOAD_AI
-                        container::ContainerWorkloadType::Gaming => 1u32, // WORKLOAD_GAMING
-                        container::ContainerWorkloadType::Compute => 3u32, // WORKLOAD_BATCH
-                        container::ContainerWorkloadType::General => 7u32, // WORKLOAD_CONTAINER
-                    };
-                    let value = class.to_ne_bytes();
-                    let _ = self.skel.maps.container_pids.update(
-                        &key,
-                        &value,
+                // Claim all container PIDs; exited containers drop out
+                self.workload_registry.replace(
+                    registry::Source::Container,
+                    self.container_monitor
+                        .all_pids()
+                        .into_iter()
+                        .map(|(pid, workload_type)| (pid, workload_type.as_bpf_class()))
+                        .collect(),
+                );
+
+                // Log removed containers
+                for id in &removed_ids {
+                    debug!("Container removed: {}", id);
+                }
+
+                self.update_ollama_pids();
+                self.sync_workload_maps();
+                self.update_container_gpu_numa();
+                self.update_ai_memory_numa();
+                self.update_ai_gpu_numa();
+            }
+            Err(e) => {
+                debug!("Container scan failed: {}", e);
+            }
+        }
+    }
+
+    /// Warn when sysfs reports a different (or no) sched_ext scheduler
+    fn check_attached_scheduler(&mut self) {
+        let ops = doctor::attached_scheduler();
+        if ops == self.attached_ops {
+            return;
+        }
+
+        match ops.as_deref() {
+            Some(name) if doctor::is_ghostbrew_ops(name) => {
+                info!("GhostBrew is the attached sched_ext scheduler again");
+            }
+            Some(name) => warn!(
+                "Another sched_ext scheduler is active: {} (expected {})",
+                name, SCHEDULER_NAME
+            ),
+            None => warn!("No sched_ext scheduler attached, GhostBrew may have been ejected"),
+        }
+        self.attached_ops = ops;
+    }
+
+    /// Keep threads of GPU-using AI containers on their GPUs' NUMA node
+    fn update_container_gpu_numa(&mut self) {
+        let gpu_nodes = self.gpu_monitor.numa_nodes();
+
+        let mut threads = std::collections::HashMap::new();
+        for (pids, gpu_count) in self.container_monitor.gpu_ai_containers() {
+            let Some(node) = container::container_gpu_numa(gpu_count, &gpu_nodes) else {
+                continue;
+            };
+            for &pid in pids {
+                // Ollama threads are managed by update_ollama_pids
+                if self.ollama_pids.contains(&pid) {
+                    continue;
+                }
+                threads.extend(
+                    container::thread_ids(pid)
+                        .into_iter()
+                        .map(|tid| (tid, node)),
+                );
+            }
+        }
+        // [ai_gpus] matches keep their configured node (update_ai_gpu_numa)
+        threads.retain(|tid, _| !self.ai_gpu_threads.contains_key(tid));
+
+        for (&tid, &node) in &threads {
+            if self.container_gpu_threads.get(&tid) != Some(&node) {
+                let _ = self.skel.maps.numa_hints.update(
+                    &tid.to_ne_bytes(),
+                    &node.to_ne_bytes(),
+                    libbpf_rs::MapFlags::ANY,
+                );
+            }
+        }
+        for tid in self.container_gpu_threads.keys() {
+            if !threads.contains_key(tid) && !self.ai_gpu_threads.contains_key(tid) {
+                let _ = self.skel.maps.numa_hints.delete(&tid.to_ne_bytes());
+            }
+        }
+
+        self.container_gpu_threads = threads;
+    }
+
+    /// Keep CPU-bound AI containers and AI VM vCPUs on the NUMA node with the
+    /// most available memory; inference is memory-bandwidth bound
+    fn update_ai_memory_numa(&mut self) {
+        let nodes = topology::node_memory_pressure();
+        let node = topology::least_pressured_node(&nodes, self.ai_memory_node);
+        if node != self.ai_memory_node
+            && let Some(node) = node
+        {
+            info!(
+                "AI workloads: preferring NUMA node {} (most available memory)",
+                node
+            );
+        }
+
+        let mut threads = HashSet::new();
+        if node.is_some() {
+            // Ollama threads follow the GPU's node (update_ollama_pids)
+            for pids in self.container_monitor.cpu_ai_containers() {
+                for &pid in pids.iter().filter(|pid| !self.ollama_pids.contains(pid)) {
+                    threads.extend(container::thread_ids(pid));
+                }
+            }
+            threads.extend(
+                self.vm_monitor
+                    .get_vcpu_workloads()
+                    .into_iter()
+                    .filter(|&(_, workload)| workload == vm::VmWorkloadType::Ai)
+                    .map(|(tid, _)| tid),
+            );
+        }
+        threads.retain(|tid| !self.ai_gpu_threads.contains_key(tid));
+
+        if let Some(node) = node {
+            for &tid in &threads {
+                if Some(node) != self.ai_memory_node || !self.ai_memory_threads.contains(&tid) {
+                    let _ = self.skel.maps.numa_hints.update(
+                        &tid.to_ne_bytes(),
+                        &node.to_ne_bytes(),
                         libbpf_rs::MapFlags::ANY,
                     );
                 }
+            }
+        }
+        for tid in self
+            .ai_memory_threads
+            .difference(&threads)
+            .filter(|tid| !self.ai_gpu_threads.contains_key(tid))
+        {
+            let _ = self.skel.maps.numa_hints.delete(&tid.to_ne_bytes());
+        }
+
+        self.ai_memory_threads = threads;
+        self.ai_memory_node = node;
+    }
+
+    /// Keep threads of [ai_gpus] matches on their configured GPU's NUMA node
+    ///
+    /// The automatic NUMA hints skip these threads, so the configured node
+    /// wins over them; a released thread goes back to them on their next pass.
+    fn update_ai_gpu_numa(&mut self) {
+        if self.ai_gpu_map.is_empty() && self.ai_gpu_threads.is_empty() {
+            return;
+        }
+
+        let mut pids: std::collections::HashMap<u32, u32> = std::collections::HashMap::new();
+        let host_pids: HashSet<u32> = self
+            .gaming_detector
+            .ai_pids()
+            .chain(self.ollama_pids.iter().copied())
+            .collect();
+        for pid in host_pids {
+            let comm = procfs::read_pid_file(pid, "comm").unwrap_or_default();
+            let exe = gaming::get_exe_name(pid).unwrap_or_default();
+            if let Some(node) = self.ai_gpu_map.node_for([comm.trim(), exe.as_str()]) {
+                pids.insert(pid, node);
+            }
+        }
+        for container in self.container_monitor.ai_containers() {
+            let names = [container.name.as_str(), container.image.as_str()];
+            if let Some(node) = self.ai_gpu_map.node_for(names) {
+                pids.extend(container.pids.iter().map(|&pid| (pid, node)));
+            }
+        }
+
+        let threads: std::collections::HashMap<u32, u32> = pids
+            .into_iter()
+            .flat_map(|(pid, node)| {
+                container::thread_ids(pid)
+                    .into_iter()
+                    .map(move |tid| (tid, node))
+            })
+            .collect();
+
+        for (&tid, &node) in &threads {
+            if self.ai_gpu_threads.get(&tid) != Some(&node) {
+                let _ = self.skel.maps.numa_hints.update(
+                    &tid.to_ne_bytes(),
+                    &node.to_ne_bytes(),
+                    libbpf_rs::MapFlags::ANY,
+                );
+            }
+        }
+        for tid in self.ai_gpu_threads.keys() {
+            if threads.contains_key(tid) {
+                continue;
+            }
+            let _ = self.skel.maps.numa_hints.delete(&tid.to_ne_bytes());
+            // Forget the thread in the automatic hints so their next pass
+            // writes their own node back
+            self.ollama_threads.remove(tid);
+            self.container_gpu_threads.remove(tid);
+            self.ai_memory_threads.remove(tid);
+        }
+
+        self.ai_gpu_threads = threads;
+    }
+
+    /// Push shader compile threads of gaming PIDs so BPF keeps them off V-Cache
+    fn update_shader_threads(&mut self) {
+        if !self.map_caps.has(MapFeature::ShaderThreads) {
+            return;
+        }
 
-                // Log removed containers
-                for id in &removed_ids {
-                    debug!("Container removed: {}", id);
-                }
+        let threads: HashSet<u32> = self
+            .gaming_detector
+            .gaming_pids()
+            .flat_map(|pid| {
+                container::thread_ids(pid).into_iter().filter(move |tid| {
+                    std::fs::read_to_string(format!("/proc/{}/task/{}/comm", pid, tid))
+                        .is_ok_and(|comm| gpu::is_shader_thread_name(comm.trim()))
+                })
+            })
+            .collect();
+
+        for &tid in threads.difference(&self.shader_threads) {
+            debug!("Shader compile thread {}: frequency die", tid);
+            let _ = self.skel.maps.shader_threads.update(
+                &tid.to_ne_bytes(),
+                &WORKLOAD_SHADER.to_ne_bytes(),
+                libbpf_rs::MapFlags::ANY,
+            );
+        }
+        for &tid in self.shader_threads.difference(&threads) {
+            let _ = self.skel.maps.shader_threads.delete(&tid.to_ne_bytes());
+        }
+
+        self.shader_threads = threads;
+    }
+
+    /// Push OBS capture threads as interactive and encoder threads as batch,
+    /// so encoding stays on the frequency die while capture keeps up
+    fn update_obs_threads(&mut self) {
+        if !self.map_caps.has(MapFeature::ThreadClasses) {
+            return;
+        }
+
+        let threads: std::collections::HashMap<u32, u32> = self
+            .gaming_detector
+            .obs_pids()
+            .flat_map(|pid| {
+                container::thread_ids(pid)
+                    .into_iter()
+                    .filter_map(move |tid| {
+                        let comm =
+                            std::fs::read_to_string(format!("/proc/{}/task/{}/comm", pid, tid))
+                                .ok()?;
+                        Some((tid, gaming::classify_obs_thread(comm.trim())?))
+                    })
+            })
+            .collect();
+
+        for (&tid, &class) in &threads {
+            if self.obs_threads.get(&tid) == Some(&class) {
+                continue;
             }
-            Err(e) => {
-                debug!("Container scan failed: {}", e);
+            debug!(
+                "OBS thread {}: {}",
+                tid,
+                classify::workload_class_name(class)
+            );
+            let _ = self.skel.maps.thread_classes.update(
+                &tid.to_ne_bytes(),
+                &class.to_ne_bytes(),
+                libbpf_rs::MapFlags::ANY,
+            );
+        }
+        for tid in self.obs_threads.keys() {
+            if !threads.contains_key(tid) {
+                let _ = self.skel.maps.thread_classes.delete(&tid.to_ne_bytes());
+            }
+        }
+
+        self.obs_threads = threads;
+    }
+
+    /// Push Ollama PIDs as AI workloads and keep their threads on the GPU's NUMA node
+    fn update_ollama_pids(&mut self) {
+        let gpu_node = self
+            .gpu_monitor
+            .primary_gpu()
+            .map_or(-1, |gpu| gpu.numa_node);
+        let (workload, numa_node) = container::ollama_workload(gpu_node);
+        let class = workload.as_bpf_class();
+
+        let pids: HashSet<u32> = self.container_monitor.ollama_pids().into_iter().collect();
+        for &pid in pids.difference(&self.ollama_pids) {
+            info!(
+                "Ollama PID {}: AI priority, NUMA node {}",
+                pid,
+                numa_node.map_or_else(|| "any".to_string(), |n| n.to_string())
+            );
+        }
+        self.workload_registry.replace(
+            registry::Source::Ollama,
+            pids.iter().map(|&pid| (pid, class)).collect(),
+        );
+
+        // Threads come and go with model loads, so refresh every scan
+        let mut threads: HashSet<u32> = match numa_node {
+            Some(_) => pids
+                .iter()
+                .flat_map(|&pid| container::thread_ids(pid))
+                .collect(),
+            None => HashSet::new(),
+        };
+        // [ai_gpus] matches keep their configured node (update_ai_gpu_numa)
+        threads.retain(|tid| !self.ai_gpu_threads.contains_key(tid));
+        if let Some(node) = numa_node {
+            for &tid in threads.difference(&self.ollama_threads) {
+                let _ = self.skel.maps.numa_hints.update(
+                    &tid.to_ne_bytes(),
+                    &node.to_ne_bytes(),
+                    libbpf_rs::MapFlags::ANY,
+                );
             }
         }
+        for tid in self
+            .ollama_threads
+            .difference(&threads)
+            .filter(|tid| !self.ai_gpu_threads.contains_key(tid))
+        {
+            let _ = self.skel.maps.numa_hints.delete(&tid.to_ne_bytes());
+        }
+
+        self.ollama_pids = pids;
+        self.ollama_threads = threads;
     }
 
     /// Update cgroup classifications in BPF map
     fn update_cgroup_classes(&mut self) {
+        if !self.map_caps.has(MapFeature::CgroupClasses) {
+            return;
+        }
+
         match self.cgroup_monitor.rescan() {
             Ok((new_cgroups, removed_ids)) => {
                 // Log new gaming cgroups
@@ -1011,32 +2884,125 @@ impl<'a> Scheduler<'a> {
         }
     }
 
+    /// Detect pacman transactions and pause transient reclassification
+    fn check_package_transaction(&mut self) {
+        let active =
+            gaming::package_transaction_active(std::path::Path::new(gaming::PACMAN_DB_LOCK));
+        if active == self.package_transaction {
+            return;
+        }
+
+        self.package_transaction = active;
+        self.gaming_detector.set_freeze_transient(active);
+        if active {
+            info!("pacman transaction in progress - pausing batch/AI reclassification");
+        } else {
+            info!("pacman transaction finished - resuming normal scanning");
+        }
+    }
+
+    /// Update the approximate idle-CPU count per CCD in the BPF map
+    fn update_ccd_idle(&mut self) {
+        let idle = topology::idle_mask_per_ccd(
+            &mut self.idle_jiffies,
+            &self.topology.cpu_to_ccd,
+            self.topology.nr_ccds,
+        );
+
+        for (ccd, count) in idle.iter().enumerate() {
+            let key = (ccd as u32).to_ne_bytes();
+            let value = count.to_ne_bytes();
+            let _ = self
+                .skel
+                .maps
+                .ccd_idle_cpus
+                .update(&key, &value, libbpf_rs::MapFlags::ANY);
+        }
+        debug!("CCD idle CPUs: {:?}", idle);
+    }
+
+    /// Push per-CCD temperatures to BPF and log threshold crossings
+    fn update_ccd_temps(&mut self) {
+        if self.topology.nr_ccds < 2 || self.topology.is_virtualized {
+            return;
+        }
+
+        let threshold = self.args.ccd_temp_threshold;
+        for (ccd, millideg) in topology::ccd_temperatures() {
+            if ccd >= self.topology.nr_ccds {
+                continue;
+            }
+            let celsius = millideg.max(0) as u32 / 1000;
+            let _ = self.skel.maps.ccd_temps.update(
+                &ccd.to_ne_bytes(),
+                &celsius.to_ne_bytes(),
+                libbpf_rs::MapFlags::ANY,
+            );
+
+            if threshold == 0 {
+                continue;
+            }
+            if celsius >= threshold {
+                if self.hot_ccds.insert(ccd) {
+                    warn!(
+                        "CCD {} at {}°C, over the {}°C threshold",
+                        ccd, celsius, threshold
+                    );
+                }
+            } else if self.hot_ccds.remove(&ccd) {
+                info!("CCD {} cooled to {}°C", ccd, celsius);
+            }
+        }
+    }
+
     /// Update EPP hints based on active workloads
     fn update_epp_hints(&mut self) {
-        let (gaming_count, batch_count, _ai_count) = self.gaming_detector.counts();
-        let gpu_active = self.gpu_monitor.any_gpu_active();
-
-        // When gaming is active and GPU is in D0, boost preferred cores
-        if gaming_count > 0 && gpu_active {
-            // Set performance EPP on preferred cores (highest prefcore ranking)
-            for &cpu in &self.prefcore.preferred_cpus {
-                if let Err(e) = self.epp_manager.set_epp(cpu, "performance") {
-                    debug!("Failed to set EPP for CPU {}: {}", cpu, e);
-                }
+        // VM guest or no EPP-capable cpufreq driver
+        if !self.epp_hints {
+            return;
+        }
+
+        // gamemoded sets EPP itself; hand the CPUs back rather than fight it
+        if !self.gamemode.epp_allowed() {
+            if self.epp_manager.release() {
+                info!("Restored original EPP values for GameMode");
+                self.trace_marker.mark(&marker::Decision::Epp {
+                    epp: "original",
+                    cpus: self.prefcore.preferred_cpus.len(),
+                });
             }
+            return;
         }
 
+        let (gaming_count, batch_count, _ai_count) = self.gaming_detector.counts();
+        let gpu_active = self.gpu_monitor.is_active_debounced();
+
+        // When gaming is active and GPU has settled in D0, boost preferred cores
+        // (highest prefcore ranking) with performance EPP (balance_performance
+        // on handhelds); a host whose GPUs
+        // are all passed through boosts for its gaming VMs instead
+        let gaming_boost = pbo::gaming_boost(
+            gaming_count,
+            gpu_active,
+            self.vm_monitor.all_gpus_passed_through(),
+            self.vm_monitor.gaming_vcpu_count(),
+        );
+
         // On dual-CCD Zen 5 X3D systems like the 9950X3D, frequency mode should
         // nudge prefcores toward boost-friendly behavior for build/dev workloads.
-        if self.topology.asymmetric_ccd_boost
+        let freq_boost = self.topology.asymmetric_ccd_boost
             && self.vcache_controller.current_mode() == vcache::VCacheMode::Frequency
-            && batch_count > 0
-        {
-            for &cpu in &self.prefcore.preferred_cpus {
-                if let Err(e) = self.epp_manager.set_epp(cpu, "performance") {
-                    debug!("Failed to set freq-mode EPP for CPU {}: {}", cpu, e);
-                }
-            }
+            && batch_count > 0;
+
+        // Only writes when the decision changed and a CPU isn't there yet
+        let writes = self
+            .epp_manager
+            .update(gaming_boost || freq_boost, &self.prefcore.preferred_cpus);
+        if writes > 0 {
+            self.trace_marker.mark(&marker::Decision::Epp {
+                epp: self.epp_manager.boost_epp(),
+                cpus: writes,
+            });
         }
 
         // Note: EPP is automatically restored on shutdown via EppManager::drop
@@ -1052,8 +3018,15 @@ impl<'a> Scheduler<'a> {
         if let Some(new_mode) = self.vcache_controller.poll_changes() {
             let (gaming_mode, work_mode) = new_mode.to_scheduler_modes();
             info!(
-                "V-Cache mode changed to {} (gaming_mode={}, work_mode={})",
-                new_mode, gaming_mode, work_mode
+                "V-Cache mode changed to {} by {} (gaming_mode={}, work_mode={})",
+                new_mode,
+                if self.vcache_controller.last_change_was_external() {
+                    "ghost-vcache/manual"
+                } else {
+                    "GhostBrew"
+                },
+                gaming_mode,
+                work_mode
             );
 
             // Keep GhostBrew's Zen 5 X3D fast paths aligned with ghost-vcache mode.
@@ -1065,7 +3038,7 @@ impl<'a> Scheduler<'a> {
         }
 
         // For automatic switching strategy, evaluate based on workload metrics
-        if self.config.is_vcache_auto_switching() {
+        if self.vcache_controller.is_automatic() {
             let Some(bss) = self.skel.maps.bss_data.as_ref() else {
                 return;
             };
@@ -1082,44 +3055,265 @@ impl<'a> Scheduler<'a> {
 
     /// Poll control interface for runtime tuning commands
     fn poll_control_interface(&mut self) {
-        let commands = self.control_interface.poll_commands();
-
-        for cmd in commands {
-            match cmd {
-                control::ControlCommand::SetBurstThreshold(ns) => {
-                    info!("Control: Setting burst_threshold_ns={}", ns);
-                    if let Err(e) = self.update_runtime_tunables(Some(ns), None, None, None) {
-                        warn!("Failed to apply burst threshold: {}", e);
-                    }
+        for cmd in self.control_interface.poll_commands() {
+            self.apply_control_command("Control", cmd);
+        }
+    }
+
+    /// Apply one runtime command from the control file or the dashboard
+    fn apply_control_command(&mut self, source: &str, cmd: control::ControlCommand) {
+        match cmd {
+            control::ControlCommand::SetBurstThreshold(ns) => {
+                info!("{}: Setting burst_threshold_ns={}", source, ns);
+                if let Err(e) = self.update_runtime_tunables(Some(ns), None, None, None) {
+                    warn!("Failed to apply burst threshold: {}", e);
                 }
-                control::ControlCommand::SetSlice(ns) => {
-                    info!("Control: Setting slice_ns={}", ns);
-                    if let Err(e) = self.update_runtime_tunables(None, Some(ns), None, None) {
-                        warn!("Failed to apply slice: {}", e);
-                    }
+            }
+            control::ControlCommand::SetSlice(ns) => {
+                info!("{}: Setting slice_ns={}", source, ns);
+                if let Err(e) = self.update_runtime_tunables(None, Some(ns), None, None) {
+                    warn!("Failed to apply slice: {}", e);
                 }
-                control::ControlCommand::GamingMode(enabled) => {
-                    info!("Control: Setting gaming_mode={}", enabled);
-                    if let Err(e) = self.update_runtime_tunables(None, None, Some(enabled), None) {
-                        warn!("Failed to apply gaming mode: {}", e);
-                    }
+            }
+            control::ControlCommand::GamingMode(enabled) => {
+                info!("{}: Setting gaming_mode={}", source, enabled);
+                if let Err(e) = self.update_runtime_tunables(None, None, Some(enabled), None) {
+                    warn!("Failed to apply gaming mode: {}", e);
                 }
-                control::ControlCommand::WorkMode(enabled) => {
-                    info!("Control: Setting work_mode={}", enabled);
-                    if let Err(e) = self.update_runtime_tunables(None, None, None, Some(enabled)) {
-                        warn!("Failed to apply work mode: {}", e);
-                    }
+            }
+            control::ControlCommand::WorkMode(enabled) => {
+                info!("{}: Setting work_mode={}", source, enabled);
+                if let Err(e) = self.update_runtime_tunables(None, None, None, Some(enabled)) {
+                    warn!("Failed to apply work mode: {}", e);
                 }
             }
+            control::ControlCommand::Rescan => {
+                info!("{}: Rescan requested, reloading profiles", source);
+                self.reload_config(&reload::SystemSource);
+                self.rescan_requested = true;
+            }
+        }
+    }
+
+    fn stats_snapshot(&self) -> Option<StatsSnapshot> {
+        let bss = self.skel.maps.bss_data.as_ref()?;
+        let latency_known = bss.latency_count > 0;
+        Some(StatsSnapshot {
+            enqueued: bss.nr_enqueued,
+            dispatched: bss.nr_dispatched,
+            direct_dispatched: bss.nr_direct_dispatched,
+            gaming_tasks: bss.nr_gaming_tasks,
+            interactive_tasks: bss.nr_interactive_tasks,
+            vcache_migrations: bss.nr_vcache_migrations,
+            vcache_placement_pct: self.vcache_placement().and_then(|p| p.rate_pct()),
+            observed_slice_us: self.slice_observation.map(|o| o.median_ns / 1000),
+            slice_ratio: self.slice_ratio(),
+            ccd_local: bss.nr_ccd_local,
+            ccx_local: bss.nr_ccx_local,
+            ccd_cross: bss.nr_ccd_cross,
+            preempt_kicks: bss.nr_preempt_kicks,
+            preempt_kicks_throttled: bss.nr_preempt_kicks_throttled,
+            events_dropped: bss.nr_events_dropped,
+            avg_latency_us: bss
+                .latency_sum_ns
+                .checked_div(bss.latency_count)
+                .map(|ns| ns / 1000),
+            min_latency_us: latency_known.then_some(bss.latency_min_ns / 1000),
+            max_latency_us: latency_known.then_some(bss.latency_max_ns / 1000),
+            gaming_avg_latency_us: bss
+                .gaming_latency_sum_ns
+                .checked_div(bss.gaming_latency_count)
+                .map(|ns| ns / 1000),
+            gaming_late_frames: bss.gaming_late_frames,
+            class_dispatches: self.class_dispatches().into_iter().collect(),
+            gaming_vcpus: self.vm_monitor.gaming_vcpu_count(),
+            containers: self.container_monitor.container_count(),
+            numa_memory: self.numa_memory(),
+            boost_spread: self.boost_spread.clone(),
+            ccd_dsq_depths: self.ccd_dsq_depths(),
+            split_lock_detect: self.vm_monitor.split_lock_mode().to_string(),
+            attached_scheduler: self.attached_ops.clone(),
+        })
+    }
+
+    /// Last CPU of the tracked gaming threads against the V-Cache CCD,
+    /// None on CPUs without one
+    fn vcache_placement(&self) -> Option<vcache::Placement> {
+        let vcache_ccd = self.topology.vcache_ccd?;
+        let vcache_cpus: Vec<bool> = self
+            .topology
+            .cpu_to_ccd
+            .iter()
+            .map(|&ccd| ccd == vcache_ccd)
+            .collect();
+        Some(vcache::sample_placement(
+            self.gaming_detector.gaming_pids(),
+            &vcache_cpus,
+        ))
+    }
+
+    /// Sample the effective slice of the tracked CPU-bound tasks, which
+    /// are the ones that should run their slice out
+    fn sample_slices(&mut self) {
+        let pids: Vec<u32> = self
+            .gaming_detector
+            .batch_pids()
+            .chain(self.gaming_detector.ai_pids())
+            .collect();
+        self.slice_observation = self.slice_sampler.sample(pids);
+    }
+
+    /// Observed median slice against the slice_ns in effect
+    fn slice_ratio(&self) -> Option<f64> {
+        let slice_ns = self
+            .runtime_tunables()
+            .map_or(self.args.slice_ns, |t| t.slice_ns);
+        self.slice_observation?.ratio(slice_ns)
+    }
+
+    /// Per-node memory, empty on single-node systems
+    fn numa_memory(&self) -> Vec<topology::NodeMemory> {
+        let nodes = topology::node_memory_pressure();
+        if nodes.len() < 2 {
+            return Vec::new();
+        }
+        nodes
+    }
+
+    fn print_stats_json(&self) {
+        let Some(snapshot) = self.stats_snapshot() else {
+            println!("{{}}");
+            return;
+        };
+        match serde_json::to_string(&snapshot) {
+            Ok(json) => println!("{}", json),
+            Err(e) => warn!("Failed to serialize stats: {}", e),
+        }
+    }
+
+    /// Publish the current detector state to the API and the --export-pids
+    /// file (built before the shared snapshot is locked)
+    fn export_pids(&mut self) {
+        if self.pid_exporter.is_none() && self.api_server.is_none() {
+            return;
+        }
+        let pids = export::PidExport {
+            gaming: self.gaming_detector.gaming_pids().collect(),
+            batch: self.gaming_detector.batch_pids().collect(),
+            ai: self.gaming_detector.ai_pids().collect(),
+            vm_vcpu: self.vm_monitor.get_vcpu_workloads().into_keys().collect(),
+            container: self
+                .container_monitor
+                .all_pids()
+                .into_iter()
+                .map(|(pid, _)| pid)
+                .collect(),
+        };
+        self.shared_pids.publish(pids.clone());
+        if let Some(exporter) = self.pid_exporter.as_mut() {
+            exporter.write(pids);
+        }
+    }
+
+    /// Refresh the --api-addr /state document
+    fn publish_api_state(&self) {
+        let Some(api) = self.api_server.as_ref() else {
+            return;
+        };
+        let Some(stats) = self.stats_snapshot() else {
+            return;
+        };
+        let (gaming_mode, work_mode) = self.runtime_modes();
+        let doc = api::StateDoc {
+            version: env!("CARGO_PKG_VERSION"),
+            gaming_mode,
+            work_mode,
+            vcache_mode: self
+                .vcache_controller
+                .is_available()
+                .then(|| self.vcache_controller.current_mode().to_string()),
+            stats: &stats,
+        };
+        match api::state_json(&doc) {
+            Ok(json) => api.publish(json),
+            Err(e) => debug!("API: {:#}", e),
+        }
+    }
+
+    /// Userspace hint for a task, in the order BPF consults the maps
+    fn assigned_class(&self, pid: u32, tgid: u32) -> Option<u32> {
+        let lookup = |map: &libbpf_rs::Map, key: u32| {
+            map.lookup(&key.to_ne_bytes(), libbpf_rs::MapFlags::ANY)
+                .ok()
+                .flatten()
+                .and_then(|v| {
+                    v.get(..4)
+                        .map(|b| u32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+                })
+                .filter(|&class| class != 0)
+        };
+        let maps = &self.skel.maps;
+        lookup(&maps.gaming_pids, tgid)
+            .or_else(|| lookup(&maps.vm_vcpu_pids, pid))
+            .or_else(|| lookup(&maps.container_pids, tgid))
+    }
+
+    /// One --trace-pid line: measured bursts vs the assigned class
+    fn print_pid_trace(&mut self) {
+        let Some(tracer) = self.pid_tracer.as_mut() else {
+            return;
+        };
+        let pid = tracer.pid;
+        if !procfs::pid_alive(pid) {
+            println!("trace: PID {} exited", pid);
+            self.pid_tracer = None;
+            return;
         }
+        let Some(window) = tracer.sample() else {
+            debug!("trace: PID {} did not run this interval", pid);
+            return;
+        };
+
+        let (reference, ratio) = self.args.interactive_reference();
+        let measured = classify::classify_burst(window.avg_burst_ns, reference, ratio);
+        let tgid = trace::read_tgid(pid).unwrap_or(pid);
+        let assigned = self.assigned_class(pid, tgid);
+        let comm = procfs::read_pid_file(pid, "comm").unwrap_or_default();
+        let wait = window
+            .avg_wait_ns
+            .map(|ns| format!("{:.3}ms", ns as f64 / 1e6))
+            .unwrap_or_else(|| "n/a".to_string());
+
+        println!(
+            "trace: PID {} ({}) {} switches, avg burst {:.3}ms, avg wait {} -> {} (cutoff {:.3}ms); assigned {}: {}",
+            pid,
+            comm.trim(),
+            window.switches,
+            window.avg_burst_ns as f64 / 1e6,
+            wait,
+            measured,
+            classify::interactive_cutoff(reference, ratio) as f64 / 1e6,
+            assigned.map_or("none (burst-based)", classify::workload_class_name),
+            trace::verdict(measured, assigned.and_then(trace::expected_burst_class)),
+        );
     }
 
-    fn print_stats(&self) {
+    fn print_stats(&mut self) {
+        if self.args.stats_json {
+            self.print_stats_json();
+            return;
+        }
+
         let Some(bss) = self.skel.maps.bss_data.as_ref() else {
             println!("--- GhostBrew Stats (unavailable) ---");
             return;
         };
         println!("--- GhostBrew Stats ---");
+        match self.attached_ops.as_deref() {
+            Some(ops) if doctor::is_ghostbrew_ops(ops) => println!("  Scheduler: {}", ops),
+            Some(ops) => println!("  Scheduler: {} (WARNING: not GhostBrew)", ops),
+            None => println!("  Scheduler: none attached (WARNING)"),
+        }
         println!("  Enqueued: {}", bss.nr_enqueued);
         println!(
             "  Dispatched: {} (direct: {})",
@@ -1149,13 +3343,59 @@ impl<'a> Scheduler<'a> {
         println!("  Gaming tasks: {}", bss.nr_gaming_tasks);
         println!("  Interactive tasks: {}", bss.nr_interactive_tasks);
         println!("  V-Cache migrations: {}", bss.nr_vcache_migrations);
+        if let Some(placement) = self.vcache_placement()
+            && let Some(pct) = placement.rate_pct()
+        {
+            println!(
+                "  V-Cache placement: {:.0}% of {} gaming threads{}",
+                pct,
+                placement.threads,
+                if pct < vcache::LOW_PLACEMENT_PCT {
+                    " (LOW: check gaming detection and V-Cache mode)"
+                } else {
+                    ""
+                }
+            );
+        }
         println!(
-            "  CCD local: {} | cross: {}",
-            bss.nr_ccd_local, bss.nr_ccd_cross
+            "  CCD local: {} | cross: {} (idle-hint: {})",
+            bss.nr_ccd_local, bss.nr_ccd_cross, bss.nr_idle_hint_picks
         );
+        if self.args.ccx_locality {
+            println!("  CCX local: {}", bss.nr_ccx_local);
+        }
+        if let Some(observed) = self.slice_observation
+            && let Some(ratio) = self.slice_ratio()
+        {
+            println!(
+                "  Effective slice: {:.2}ms median over {} batch/AI tasks ({:.2}x slice_ns)",
+                observed.median_ns as f64 / 1_000_000.0,
+                observed.tasks,
+                ratio
+            );
+        }
         println!("  SMT idle picks: {}", bss.nr_smt_idle_picks);
         println!("  Compaction overflows: {}", bss.nr_compaction_overflows);
-        println!("  Preempt kicks: {}", bss.nr_preempt_kicks);
+        let kick_rate = tunables::preempt_kick_rate(
+            bss.nr_preempt_kicks.saturating_sub(self.last_preempt_kicks),
+            self.args.stats_interval as f64,
+            self.topology.nr_cpus,
+        );
+        self.last_preempt_kicks = bss.nr_preempt_kicks;
+        if self.args.max_preempt_rate > 0 {
+            println!(
+                "  Preempt kicks: {} ({:.1}/CPU/s, limit {}/CPU/s, throttled {})",
+                bss.nr_preempt_kicks,
+                kick_rate,
+                self.args.max_preempt_rate,
+                bss.nr_preempt_kicks_throttled
+            );
+        } else {
+            println!(
+                "  Preempt kicks: {} ({:.1}/CPU/s, unlimited)",
+                bss.nr_preempt_kicks, kick_rate
+            );
+        }
         // Scheduling latency stats
         let avg_latency_us = bss
             .latency_sum_ns
@@ -1213,6 +3453,26 @@ impl<'a> Scheduler<'a> {
             bss.nr_userspace_hint_detects
         );
         println!("  Prefcore placements: {}", bss.nr_prefcore_placements);
+        if self.boost_spread.max_spread_mhz() > 0 {
+            let ccds: Vec<String> = self
+                .boost_spread
+                .ccds
+                .iter()
+                .map(|c| format!("CCD{} {}-{} MHz", c.ccd, c.min_mhz, c.max_mhz))
+                .collect();
+            println!(
+                "  Boost spread: {} (outliers: {:?})",
+                ccds.join(", "),
+                self.boost_spread.outliers
+            );
+        }
+        let class_dispatches = self.class_dispatches();
+        if !class_dispatches.is_empty() {
+            println!("  Dispatches by class:");
+            for (name, count) in class_dispatches {
+                println!("    {:<12} {}", name, count);
+            }
+        }
         if self.topology.is_x3d {
             println!("  V-Cache CCD: {}", self.topology.vcache_ccd.unwrap_or(0));
             if self.vcache_controller.is_available() {
@@ -1225,6 +3485,12 @@ impl<'a> Scheduler<'a> {
             // Per-CCD load display
             self.print_ccd_loads();
         }
+        if self.topology.nr_ccds > 1 {
+            println!(
+                "  DSQ depth: {}",
+                topology::format_dsq_depths(&self.ccd_dsq_depths())
+            );
+        }
         // Game profiles loaded
         if self.profile_manager.count() > 0 {
             println!("  Game profiles: {}", self.profile_manager.count());
@@ -1236,6 +3502,12 @@ impl<'a> Scheduler<'a> {
         }
         // Phase 4b stats - GPU
         println!("  GPU feeder tasks: {}", bss.nr_gpu_feeder_tasks);
+        if bss.nr_shader_tasks > 0 {
+            println!(
+                "  Shader threads: {} ({} placed off V-Cache)",
+                bss.nr_shader_tasks, bss.nr_shader_placements
+            );
+        }
         if self.gpu_monitor.gpu_count() > 0 {
             println!(
                 "  GPU: {} ({})",
@@ -1250,8 +3522,11 @@ impl<'a> Scheduler<'a> {
         // Phase 4c stats - VM/Container
         if bss.nr_vm_vcpu_tasks > 0 || self.vm_monitor.vm_count() > 0 {
             println!(
-                "  VM vCPU tasks: {} (gaming: {}, dev: {})",
-                bss.nr_vm_vcpu_tasks, bss.nr_gaming_vm_vcpus, bss.nr_dev_vm_vcpus
+                "  VM vCPU tasks: {} (gaming: {}, dev: {}) | iothreads: {}",
+                bss.nr_vm_vcpu_tasks,
+                bss.nr_gaming_vm_vcpus,
+                bss.nr_dev_vm_vcpus,
+                bss.nr_vm_iothread_tasks
             );
         }
         if bss.nr_container_tasks > 0 || self.container_monitor.container_count() > 0 {
@@ -1260,33 +3535,265 @@ impl<'a> Scheduler<'a> {
                 bss.nr_container_tasks, bss.nr_ai_container_tasks
             );
         }
+        if self.args.ai_cpu_reserve.is_some() || self.args.isolate_ai {
+            println!("  AI/batch reserved placements: {}", bss.nr_ai_reserved);
+        }
+        if self.topology.cpu_isolated.contains(&true) {
+            println!("  Isolated CPU dispatches: {}", bss.nr_isolated_dispatches);
+        }
         if self.container_monitor.ollama_count() > 0 {
             println!(
                 "  Ollama processes: {}",
                 self.container_monitor.ollama_count()
             );
         }
-        // Phase 4d stats - Cgroup classification
-        if bss.nr_cgroup_classifications > 0 || self.cgroup_monitor.classified_count() > 0 {
-            println!(
-                "  Cgroup classifications: {} (gaming: {})",
-                bss.nr_cgroup_classifications, bss.nr_cgroup_gaming
-            );
+        // Phase 4d stats - Cgroup classification
+        if bss.nr_cgroup_classifications > 0 || self.cgroup_monitor.classified_count() > 0 {
+            println!(
+                "  Cgroup classifications: {} (gaming: {})",
+                bss.nr_cgroup_classifications, bss.nr_cgroup_gaming
+            );
+            println!(
+                "  Cgroups monitored: {} ({} gaming)",
+                self.cgroup_monitor.classified_count(),
+                self.cgroup_monitor.gaming_count()
+            );
+        }
+        let numa_memory = self.numa_memory();
+        if !numa_memory.is_empty() {
+            let nodes: Vec<String> = numa_memory
+                .iter()
+                .map(|n| {
+                    format!(
+                        "node{} {:.0}% available ({} MiB)",
+                        n.node,
+                        n.available_share() * 100.0,
+                        n.available_kb / 1024
+                    )
+                })
+                .collect();
+            println!("  NUMA memory: {}", nodes.join(", "));
+        }
+        if self.args.per_pid_stats {
+            let games = self.game_stats();
+            if !games.is_empty() {
+                println!("  Per-game:");
+                for game in &games {
+                    println!("    {}", game.summary());
+                }
+            }
+        }
+        // Event streaming stats
+        println!("  {}", self.event_handler.counters.summary());
+        println!("---");
+    }
+
+    /// pid_stats entries joined with the detected games (--per-pid-stats)
+    fn game_stats(&self) -> Vec<pidstats::GameStats> {
+        let map = &self.skel.maps.pid_stats;
+        let entries = map.keys().filter_map(|key| {
+            let pid = u32::from_ne_bytes(key.as_slice().try_into().ok()?);
+            let value = map.lookup(&key, libbpf_rs::MapFlags::ANY).ok()??;
+            Some((pid, pidstats::PidStats::from_bytes(&value)))
+        });
+        pidstats::join(entries, &self.gaming_detector)
+    }
+
+    /// Pull the BPF ringbuf drop count into the event counters, warning when
+    /// the event stream lost many events since the last interval
+    fn sync_event_drops(&self) {
+        let Some(bss) = self.skel.maps.bss_data.as_ref() else {
+            return;
+        };
+        let counters = &self.event_handler.counters;
+        let dropped = bss
+            .nr_events_dropped
+            .saturating_sub(counters.dropped.load(std::sync::atomic::Ordering::Relaxed));
+        if dropped == 0 {
+            return;
+        }
+        counters.record_dropped(dropped);
+        if dropped >= events::DROP_WARN_THRESHOLD {
+            warn!(
+                "Event ringbuf full: {} events dropped in the last {}s, the event stream is lossy",
+                dropped, self.args.stats_interval
+            );
+        }
+    }
+
+    /// Take the --adaptive-latency action when HighLatency events come
+    /// faster than the configured rate
+    fn adapt_to_latency(&mut self) {
+        let now = Instant::now();
+        let total = self
+            .event_handler
+            .counters
+            .high_latency
+            .load(std::sync::atomic::Ordering::Relaxed);
+        let Some(adaptive) = self.adaptive_latency.as_mut() else {
+            return;
+        };
+        let Some(rate) = adaptive.observe(total, now) else {
+            if adaptive.recovery_due(now) {
+                self.recover_from_latency(now);
+            }
+            return;
+        };
+        let action = adaptive.action;
+
+        let ctx = adaptive::LatencyContext {
+            slice_ns: self
+                .runtime_tunables()
+                .map_or(self.args.slice_ns, |t| t.slice_ns),
+            top_game: self
+                .event_handler
+                .top_high_latency_pid()
+                .and_then(trace::read_tgid)
+                .map(|tgid| (tgid, self.priority_boost(tgid))),
+            vcache_mode: (self.vcache_controller.supports_switching()
+                && self.vcache_controller.allows_mode_changes())
+            .then(|| self.vcache_controller.current_mode()),
+        };
+        let Some(correction) = adaptive::correction(action, &ctx) else {
+            debug!(
+                "Adaptive latency: {:.1} HighLatency events/s, {:?} has nothing left to adjust",
+                rate, action
+            );
+            return;
+        };
+
+        let applied = match correction {
+            adaptive::Correction::Slice(slice_ns) => self
+                .update_runtime_tunables(None, Some(slice_ns), None, None)
+                .map(|()| format!("slice_ns {} -> {}", ctx.slice_ns, slice_ns)),
+            adaptive::Correction::Boost { pid, boost } => {
+                if self.map_caps.has(MapFeature::PriorityBoosts) {
+                    self.skel
+                        .maps
+                        .priority_boosts
+                        .update(
+                            &pid.to_ne_bytes(),
+                            &boost.to_ne_bytes(),
+                            libbpf_rs::MapFlags::ANY,
+                        )
+                        .map(|()| format!("priority boost {} for PID {}", boost, pid))
+                        .context("Failed to update priority_boosts map")
+                } else {
+                    Err(anyhow::anyhow!("priority_boosts map unavailable"))
+                }
+            }
+            adaptive::Correction::VcacheCache => self
+                .vcache_controller
+                .request_mode(vcache::VCacheMode::Cache)
+                .map(|()| "V-Cache mode cache".to_string()),
+        };
+        match applied {
+            Ok(what) => {
+                info!(
+                    "Adaptive latency: {:.1} HighLatency events/s, {}",
+                    rate, what
+                );
+                if let Some(adaptive) = self.adaptive_latency.as_mut() {
+                    adaptive.corrected(now);
+                }
+            }
+            Err(e) => warn!("Adaptive latency: {:#}", e),
+        }
+    }
+
+    /// Step a shortened slice back toward --slice-ns after a quiet cooldown
+    ///
+    /// Active profiles own the slice, so nothing is recovered while one is.
+    fn recover_from_latency(&mut self, now: Instant) {
+        let Some(action) = self.adaptive_latency.as_ref().map(|a| a.action) else {
+            return;
+        };
+        if !self.active_profiles.is_empty() {
+            return;
+        }
+        let slice_ns = self
+            .runtime_tunables()
+            .map_or(self.args.slice_ns, |t| t.slice_ns);
+        let Some(adaptive::Correction::Slice(next)) =
+            adaptive::recovery(action, slice_ns, self.args.slice_ns)
+        else {
+            return;
+        };
+
+        match self.update_runtime_tunables(None, Some(next), None, None) {
+            Ok(()) => {
+                info!("Adaptive latency: quiet, slice_ns {} -> {}", slice_ns, next);
+                if let Some(adaptive) = self.adaptive_latency.as_mut() {
+                    adaptive.corrected(now);
+                }
+            }
+            Err(e) => warn!("Adaptive latency: {:#}", e),
+        }
+    }
+
+    /// Current priority_boosts entry of a TGID (0 without one)
+    fn priority_boost(&self, tgid: u32) -> u32 {
+        match self
+            .skel
+            .maps
+            .priority_boosts
+            .lookup(&tgid.to_ne_bytes(), libbpf_rs::MapFlags::ANY)
+        {
+            Ok(Some(value)) if value.len() >= 4 => {
+                u32::from_ne_bytes([value[0], value[1], value[2], value[3]])
+            }
+            _ => 0,
+        }
+    }
+
+    /// Print per-CCD load statistics
+    fn print_ccd_loads(&self) {
+        for (ccd, label, nr_tasks, nr_gaming) in self.ccd_loads() {
             println!(
-                "  Cgroups monitored: {} ({} gaming)",
-                self.cgroup_monitor.classified_count(),
-                self.cgroup_monitor.gaming_count()
+                "  {} CCD{}: {} tasks ({} gaming)",
+                label, ccd, nr_tasks, nr_gaming
             );
         }
-        // Event streaming stats
-        println!("  {}", self.event_handler.counters.summary());
-        println!("---");
     }
 
-    /// Print per-CCD load statistics
-    fn print_ccd_loads(&self) {
+    /// Tasks started running per workload class, summed over CPUs
+    fn class_dispatches(&self) -> Vec<(&'static str, u64)> {
+        (0..classify::NR_WORKLOAD_CLASSES as u32)
+            .filter_map(|class| {
+                let per_cpu = self
+                    .skel
+                    .maps
+                    .class_dispatches
+                    .lookup_percpu(&class.to_ne_bytes(), libbpf_rs::MapFlags::ANY)
+                    .ok()??;
+                let total = per_cpu
+                    .iter()
+                    .filter_map(|value| value.get(..8)?.try_into().ok())
+                    .map(u64::from_ne_bytes)
+                    .sum();
+                Some((classify::workload_class_name(class), total))
+            })
+            .collect()
+    }
+
+    /// Tasks queued on each CCD's DSQ, from the ccd_dsq_depths map
+    fn ccd_dsq_depths(&self) -> Vec<u64> {
+        topology::ccd_dsq_depths(self.topology.nr_ccds, |ccd| {
+            let value = self
+                .skel
+                .maps
+                .ccd_dsq_depths
+                .lookup(&ccd.to_ne_bytes(), libbpf_rs::MapFlags::ANY)
+                .ok()??;
+            Some(u64::from_ne_bytes(value.get(..8)?.try_into().ok()?))
+        })
+    }
+
+    /// CCD, label, running tasks and gaming tasks from the ccd_loads map
+    fn ccd_loads(&self) -> Vec<(u32, &'static str, u64, u64)> {
         let vcache_ccd = self.topology.vcache_ccd.unwrap_or(0);
         let freq_ccd = self.topology.freq_ccd;
+        let mut loads = Vec::new();
 
         for ccd in 0..self.topology.nr_ccds {
             let key = ccd.to_ne_bytes();
@@ -1307,13 +3814,177 @@ impl<'a> Scheduler<'a> {
                 } else {
                     "CCD"
                 };
+                loads.push((ccd, label, nr_tasks, nr_gaming));
+            }
+        }
 
-                println!(
-                    "  {} CCD{}: {} tasks ({} gaming)",
-                    label, ccd, nr_tasks, nr_gaming
-                );
+        loads
+    }
+
+    /// Sample everything the --tui dashboard shows
+    #[cfg(feature = "tui")]
+    fn dashboard_snapshot(&self) -> tui::DashboardSnapshot {
+        let mut counters = self.benchmark_counters();
+        if let Some(bss) = self.skel.maps.bss_data.as_ref() {
+            counters.insert("enqueued", bss.nr_enqueued);
+            counters.insert("dispatched", bss.nr_dispatched);
+        }
+        let (gaming_pids, batch_pids, ai_pids) = self.gaming_detector.counts();
+        let (gaming_mode, work_mode) = self.runtime_modes();
+
+        tui::DashboardSnapshot {
+            ccds: self
+                .ccd_loads()
+                .into_iter()
+                .map(|(ccd, label, nr_tasks, nr_gaming)| tui::CcdLoad {
+                    ccd,
+                    label,
+                    nr_tasks,
+                    nr_gaming,
+                })
+                .collect(),
+            counters,
+            gaming_pids,
+            batch_pids,
+            ai_pids,
+            gaming_vcpus: self.vm_monitor.gaming_vcpu_count(),
+            containers: self.container_monitor.container_count(),
+            gpu: (self.gpu_monitor.gpu_count() > 0).then(|| self.gpu_monitor.summary()),
+            gpu_active: self.gpu_monitor.is_active_debounced(),
+            vcache_mode: self
+                .vcache_controller
+                .is_available()
+                .then(|| self.vcache_controller.current_mode().to_string()),
+            gaming_mode,
+            work_mode,
+            recent_events: self.event_handler.recent(tui::EVENT_LINES),
+        }
+    }
+
+    /// Current contents of the runtime_tunables map
+    fn runtime_tunables(&self) -> Result<tunables::RuntimeTunables> {
+        let key = 0u32.to_ne_bytes();
+        let tunables = self
+            .skel
+            .maps
+            .runtime_tunables
+            .lookup(&key, libbpf_rs::MapFlags::ANY)?
+            .ok_or_else(|| anyhow::anyhow!("runtime_tunables map empty"))?;
+        Ok(tunables::RuntimeTunables::from_bytes(&tunables))
+    }
+
+    /// Current tunables, V-Cache mode, EPP boost and detector settings
+    fn runtime_state(&self) -> Result<state::RuntimeState> {
+        let vcache_mode = self.vcache_controller.current_mode();
+        Ok(state::RuntimeState {
+            version: state::STATE_VERSION,
+            tunables: self.runtime_tunables()?,
+            vcache_mode: (self.vcache_controller.is_available()
+                && vcache_mode != vcache::VCacheMode::Unknown)
+                .then(|| vcache_mode.to_sysfs_str().to_string()),
+            epp: state::EppState {
+                hints: self.epp_hints,
+                boost_epp: self.epp_manager.boost_epp().to_string(),
+            },
+            gaming: self.config.gaming.clone(),
+            max_scan_procs: self.args.max_scan_procs,
+        })
+    }
+
+    /// Write the --save-state snapshot, if requested
+    fn save_state(&self) {
+        let Some(path) = self.args.save_state.as_deref() else {
+            return;
+        };
+        match self.runtime_state().and_then(|s| s.save(path)) {
+            Ok(()) => info!("Saved runtime state to {}", path.display()),
+            Err(e) => warn!("Failed to save runtime state: {:#}", e),
+        }
+    }
+
+    /// Apply a --load-state snapshot; the detector settings, the default
+    /// tunables and EPP hints were applied before the scheduler was built
+    fn apply_state(&mut self, saved: &state::RuntimeState) {
+        let key = 0u32.to_ne_bytes();
+        if let Err(e) = self.skel.maps.runtime_tunables.update(
+            &key,
+            &saved.tunables.to_bytes(),
+            libbpf_rs::MapFlags::ANY,
+        ) {
+            warn!("Failed to restore runtime tunables: {}", e);
+        }
+
+        if let Some(mode) = saved.vcache_mode.as_deref() {
+            let mode = vcache::VCacheMode::from_str(mode);
+            if !self.vcache_controller.is_available() {
+                warn!("Saved V-Cache mode {} ignored: no V-Cache driver", mode);
+            } else if mode != vcache::VCacheMode::Unknown
+                && mode != self.vcache_controller.current_mode()
+                && let Err(e) = self.vcache_controller.request_mode(mode)
+            {
+                warn!("Failed to restore V-Cache mode {}: {}", mode, e);
             }
         }
+
+        match pbo::boost_epp_named(&saved.epp.boost_epp) {
+            Some(epp) => self.epp_manager.set_boost_epp(epp),
+            None => warn!(
+                "Saved boost EPP '{}' is not one GhostBrew writes, ignored",
+                saved.epp.boost_epp
+            ),
+        }
+        info!(
+            "Applied runtime state: burst={}ns, slice={}ns, gaming={}, work={}, V-Cache {:?}, boost EPP {}",
+            saved.tunables.burst_threshold_ns,
+            saved.tunables.slice_ns,
+            saved.tunables.gaming_mode,
+            saved.tunables.work_mode,
+            saved.vcache_mode,
+            self.epp_manager.boost_epp()
+        );
+    }
+
+    /// Current gaming_mode and work_mode from the runtime_tunables map
+    fn runtime_modes(&self) -> (bool, bool) {
+        let key = 0u32.to_ne_bytes();
+        match self
+            .skel
+            .maps
+            .runtime_tunables
+            .lookup(&key, libbpf_rs::MapFlags::ANY)
+        {
+            Ok(Some(value)) if value.len() >= 18 => (value[16] != 0, value[17] != 0),
+            _ => (false, false),
+        }
+    }
+
+    /// Apply a dashboard key action; returns true to quit
+    #[cfg(feature = "tui")]
+    fn handle_dashboard_action(&mut self, action: tui::DashboardAction) -> bool {
+        let (gaming_mode, work_mode) = self.runtime_modes();
+        let command = match action {
+            tui::DashboardAction::Quit => return true,
+            tui::DashboardAction::ToggleGaming => control::ControlCommand::GamingMode(!gaming_mode),
+            tui::DashboardAction::ToggleWork => control::ControlCommand::WorkMode(!work_mode),
+        };
+        self.apply_control_command("Dashboard", command);
+        false
+    }
+
+    /// Cumulative event counters reported by --benchmark COMMAND
+    fn benchmark_counters(&self) -> benchmark::Counters {
+        let Some(bss) = self.skel.maps.bss_data.as_ref() else {
+            return benchmark::Counters::new();
+        };
+
+        benchmark::Counters::from([
+            ("vcache_migrations", bss.nr_vcache_migrations),
+            ("ccd_cross", bss.nr_ccd_cross),
+            ("preempt_kicks", bss.nr_preempt_kicks),
+            ("gaming_preempted", bss.gaming_preempted),
+            ("late_frames", bss.gaming_late_frames),
+            ("thermal_steers", bss.nr_thermal_steers),
+        ])
     }
 
     /// Export stats to MangoHud-compatible CSV
@@ -1407,6 +4078,207 @@ impl<'a> Scheduler<'a> {
     }
 }
 
+/// Load the skeleton and round-trip known values through the userspace-fed
+/// maps, using the same update calls as the scheduler
+fn self_test() -> Result<()> {
+    attach::capture_libbpf_log();
+
+    let mut open_object = MaybeUninit::uninit();
+    let open_skel = GhostbrewSkelBuilder::default()
+        .open(&mut open_object)
+        .context("Failed to open BPF skeleton")?;
+    let skel = match open_skel.load() {
+        Ok(skel) => skel,
+        Err(e) => {
+            let report = attach::failure_report(
+                "load",
+                &e.to_string(),
+                &attach::KernelInfo::capture(),
+                &attach::libbpf_log(),
+            );
+            return Err(anyhow::Error::new(e).context(report));
+        }
+    };
+
+    let check = |name: &'static str, map: &libbpf_rs::Map, entries: &[(Vec<u8>, Vec<u8>)]| {
+        selftest::check_map(
+            name,
+            entries,
+            |key, value| {
+                map.update(key, value, libbpf_rs::MapFlags::ANY)
+                    .with_context(|| format!("Failed to update {}", name))
+            },
+            |key| {
+                map.lookup(key, libbpf_rs::MapFlags::ANY)
+                    .with_context(|| format!("Failed to look up {}", name))
+            },
+        )
+    };
+
+    let maps = &skel.maps;
+    let checks = [
+        check(
+            "gaming_pids",
+            &maps.gaming_pids,
+            &selftest::u32_entries(&[
+                (1, gaming::WORKLOAD_GAMING),
+                (2, gaming::WORKLOAD_INTERACTIVE),
+            ]),
+        ),
+        check(
+            "vm_vcpu_pids",
+            &maps.vm_vcpu_pids,
+            &selftest::u32_entries(&[(1, WORKLOAD_VM_GAMING), (2, WORKLOAD_VM_DEV)]),
+        ),
+        check(
+            "container_pids",
+            &maps.container_pids,
+            &selftest::u32_entries(&[(1, cgroup::WORKLOAD_CONTAINER)]),
+        ),
+        check(
+            "cgroup_classes",
+            &maps.cgroup_classes,
+            &selftest::u64_entries(&[(1, cgroup::WORKLOAD_GAMING), (u64::MAX, 0)]),
+        ),
+        check(
+            "prefcore_rankings",
+            &maps.prefcore_rankings,
+            &selftest::u32_entries(&[(0, 231), (1, 166)]),
+        ),
+    ];
+
+    selftest::print_report(&checks);
+    let failed = checks.iter().filter(|c| !c.passed()).count();
+    if failed > 0 {
+        bail!("{} of {} map round-trips failed", failed, checks.len());
+    }
+    println!();
+    println!("All {} maps passed", checks.len());
+    Ok(())
+}
+
+/// Run topology and process detection against a captured snapshot
+///
+/// Only the /proc and /sys scans are replayed; VM, container and cgroup
+/// detection need live state the snapshot doesn't carry.
+fn simulate(path: &std::path::Path, config: &config::GhostBrewConfig) -> Result<()> {
+    let snapshot = snapshot::Snapshot::open(path)?;
+    let paths = procfs::Paths::new(&snapshot.root);
+    let (topology, scan) = procfs::with_paths(&paths, || {
+        let topology = topology::detect_topology();
+        let scan = gaming::scan_gaming_pids(
+            &gaming::ClassifyOverrides::new(&config.gaming.allow, &config.gaming.deny),
+            &gaming::BrowserFilter::new(&config.gaming.browsers),
+            &gaming::CompositorFilter::new(&config.gaming.compositors),
+        );
+        let scan = scan.map(|scan| {
+            let mut classified: Vec<(u32, u32, String)> = scan
+                .classified
+                .iter()
+                .map(|&(pid, class)| {
+                    let comm = procfs::read_pid_file(pid, "comm").unwrap_or_default();
+                    (pid, class, comm.trim().to_string())
+                })
+                .collect();
+            classified.sort();
+            (classified, scan.realtime)
+        });
+        (topology, scan)
+    });
+    let topology = topology?;
+    let (classified, realtime) = scan?;
+
+    println!("GhostBrew simulation ({})", path.display());
+    println!();
+    println!("  CPU:        {}", topology.model_name);
+    println!(
+        "  CPUs:       {} ({} CCD(s), {} socket(s))",
+        topology.nr_cpus, topology.nr_ccds, topology.nr_sockets
+    );
+    if let Some(ccd) = topology.vcache_ccd {
+        println!("  V-Cache:    CCD {}", ccd);
+    }
+    if topology.is_intel_hybrid {
+        println!(
+            "  Hybrid:     {} P-core / {} E-core CPUs",
+            topology.pcore_cpus.len(),
+            topology.ecore_cpus.len()
+        );
+    }
+    println!();
+
+    println!("  Detected processes: {}", classified.len());
+    for (pid, class, comm) in &classified {
+        println!(
+            "    {:>7}  {:<12} {}",
+            pid,
+            classify::workload_class_name(*class),
+            comm
+        );
+    }
+    for (pid, policy) in &realtime {
+        println!(
+            "    {:>7}  skipped ({})",
+            pid,
+            procfs::rt_policy_name(*policy)
+        );
+    }
+    Ok(())
+}
+
+/// Validate a config file and print its unknown keys
+fn check_config(path: Option<std::path::PathBuf>) -> Result<()> {
+    let Some(path) = path.or_else(config::GhostBrewConfig::find_path) else {
+        bail!("No config file found (looked in /etc/ghostbrew and ~/.config/ghostbrew)");
+    };
+
+    let problems = config::GhostBrewConfig::check_path(&path)?;
+    if problems.is_empty() {
+        println!("OK    {}", path.display());
+        return Ok(());
+    }
+
+    println!("FAIL  {}", path.display());
+    for problem in &problems {
+        println!("        {}", problem);
+    }
+    bail!("{} problem(s) in {}", problems.len(), path.display());
+}
+
+/// Validate game profiles and print a per-file report
+fn validate_profiles(dir: &std::path::Path) -> Result<()> {
+    let results = profiles::ProfileManager::new().validate_directory(dir)?;
+
+    if results.is_empty() {
+        bail!("No profile files (*.toml) found in {}", dir.display());
+    }
+
+    let mut invalid = 0;
+    for result in &results {
+        let file = result.path.file_name().map_or_else(
+            || result.path.display().to_string(),
+            |f| f.to_string_lossy().into_owned(),
+        );
+        if result.is_valid() {
+            println!("OK    {} ({})", file, result.name.as_deref().unwrap_or("?"));
+        } else {
+            invalid += 1;
+            println!("FAIL  {}", file);
+            for problem in &result.problems {
+                println!("        {}", problem);
+            }
+        }
+    }
+
+    println!();
+    println!("{} profiles, {} invalid", results.len(), invalid);
+
+    if invalid > 0 {
+        bail!("{} invalid profile(s)", invalid);
+    }
+    Ok(())
+}
+
 /// Analyze MangoHud frame time log and print statistics
 fn analyze_frametime_log(path: Option<std::path::PathBuf>) -> Result<()> {
     use mangohud::MangoHudLogReader;
@@ -1450,7 +4322,9 @@ fn analyze_frametime_log(path: Option<std::path::PathBuf>) -> Result<()> {
     println!();
 
     // Quality assessment
-    let quality = if stats.std_dev_ms < 1.0 {
+    let quality = if stats.frame_capped {
+        "Capped (consistency set by the limiter)"
+    } else if stats.std_dev_ms < 1.0 {
         "Excellent (very consistent)"
     } else if stats.std_dev_ms < 2.0 {
         "Good (minor variance)"
@@ -1460,13 +4334,135 @@ fn analyze_frametime_log(path: Option<std::path::PathBuf>) -> Result<()> {
         "Poor (significant stuttering)"
     };
     println!("  Frame Pacing:     {}", quality);
+    if let Some(note) = stats.capped_note() {
+        println!("  Note:             {}", note);
+    }
+    println!();
+
+    Ok(())
+}
+
+/// Compare two MangoHud logs and print a per-metric diff
+fn compare_frametime_logs(a: &std::path::Path, b: &std::path::Path, json: bool) -> Result<()> {
+    use mangohud::MangoHudLogReader;
+
+    let reader = MangoHudLogReader::new();
+    let analyze = |path: &std::path::Path| -> Result<mangohud::FrameTimeStats> {
+        let frame_times = reader
+            .read_frame_times(&path.to_path_buf())
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        if frame_times.is_empty() {
+            bail!("No frame times found in {}", path.display());
+        }
+        Ok(MangoHudLogReader::analyze_frame_times(&frame_times))
+    };
+    let (stats_a, stats_b) = (analyze(a)?, analyze(b)?);
+    let diffs = mangohud::compare_frame_stats(&stats_a, &stats_b);
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string(&diffs).context("Failed to serialize comparison")?
+        );
+        return Ok(());
+    }
+
+    println!("A: {}", a.display());
+    println!("B: {}", b.display());
+    println!();
+    println!(
+        "  {:<18} {:>10} {:>10} {:>9}  B is",
+        "Metric", "A", "B", "Change"
+    );
+    for diff in &diffs {
+        println!(
+            "  {:<18} {:>10.2} {:>10.2} {:>+8.1}%  {}",
+            diff.metric, diff.a, diff.b, diff.delta_pct, diff.verdict
+        );
+    }
+    for (run, stats) in [("A", &stats_a), ("B", &stats_b)] {
+        if let Some(note) = stats.capped_note() {
+            println!("  Note: {} is {}", run, note);
+        }
+    }
+    println!();
+
+    Ok(())
+}
+
+/// Analyze a JSON Lines event log and print aggregate statistics
+fn analyze_event_log(path: &std::path::Path) -> Result<()> {
+    use std::sync::atomic::Ordering::Relaxed;
+
+    let file = std::fs::File::open(path)
+        .with_context(|| format!("Failed to open event log {:?}", path))?;
+    let analysis = events::analyze_event_log(std::io::BufReader::new(file))?;
+
+    if analysis.total() == 0 {
+        bail!("No events found in log file");
+    }
+
+    println!("Analyzing: {}", path.display());
+    println!();
+    println!("╔══════════════════════════════════════════╗");
+    println!("║         Scheduler Event Analysis         ║");
+    println!("╚══════════════════════════════════════════╝");
+    println!();
+    println!("  Events analyzed:  {}", analysis.total());
+    if analysis.malformed > 0 {
+        println!("  Malformed lines:  {}", analysis.malformed);
+    }
     println!();
 
+    let c = &analysis.counters;
+    println!("  Events by type:");
+    println!("    GamingDetected:   {}", c.gaming_detected.load(Relaxed));
+    println!(
+        "    VCacheMigration:  {}",
+        c.vcache_migrations.load(Relaxed)
+    );
+    println!("    PreemptKick:      {}", c.preempt_kicks.load(Relaxed));
+    println!("    HighLatency:      {}", c.high_latency.load(Relaxed));
+    println!("    CCDImbalance:     {}", c.ccd_imbalance.load(Relaxed));
+    println!("    ProfileMatch:     {}", c.profile_matches.load(Relaxed));
+    if analysis.unknown > 0 {
+        println!("    Unknown:          {}", analysis.unknown);
+    }
+    println!();
+
+    if c.high_latency.load(Relaxed) > 0 {
+        let (p50, p95, p99) = mangohud::calculate_latency_percentiles(&analysis.latency_hist);
+        println!("  High Latency (us, bucket estimates):");
+        println!("    P50:            {}", p50);
+        println!("    P95:            {}", p95);
+        println!("    P99:            {}", p99);
+        println!();
+        println!("  Top Offenders:");
+        for (comm, count, max_us) in analysis.top_latency_comms(5) {
+            println!("    {:<16} {:>6} events, max {}us", comm, count, max_us);
+        }
+        println!();
+    }
+
+    if !analysis.ccd_migrations.is_empty() {
+        println!("  Migrations (CCD -> CCD):");
+        for ((from, to), count) in &analysis.ccd_migrations {
+            println!("    {} -> {}:         {}", from, to, count);
+        }
+        println!();
+    }
+
     Ok(())
 }
 
 fn main() -> Result<()> {
-    let args = Args::parse();
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+
+    if args.version_verbose {
+        println!("{}", buildinfo::verbose());
+        return Ok(());
+    }
 
     // Handle shell completions
     if let Some(shell) = args.completions {
@@ -1480,6 +4476,101 @@ fn main() -> Result<()> {
         return analyze_frametime_log(path_option);
     }
 
+    // Handle A/B log comparison (doesn't require root or scheduler)
+    if let Some(logs) = &args.compare_logs {
+        return compare_frametime_logs(&logs[0], &logs[1], args.json);
+    }
+
+    // Handle event log analysis (doesn't require root or scheduler)
+    if let Some(path) = args.analyze_events {
+        return analyze_event_log(&path);
+    }
+
+    // Handle prerequisite checks (doesn't require root or scheduler)
+    if args.doctor {
+        let results = doctor::run_checks();
+        doctor::print_report(&results);
+        if doctor::has_failures(&results) {
+            bail!("required prerequisites are missing");
+        }
+        return Ok(());
+    }
+
+    // Handle config validation (doesn't require root or scheduler)
+    if let Some(path) = args.check_config {
+        return check_config(path);
+    }
+
+    // Handle profile validation (doesn't require root or scheduler)
+    if let Some(dir) = args.validate_profiles {
+        return validate_profiles(&dir);
+    }
+
+    // Handle snapshot capture (run as root to read every process)
+    if let Some(out) = args.capture_snapshot {
+        let processes = snapshot::capture(&out)?;
+        println!("Captured {} process(es) to {}", processes, out.display());
+        return Ok(());
+    }
+
+    // Handle Steam profile import (runs as the user who owns the library)
+    if args.import_steam_profiles {
+        let steamapps =
+            profiles::default_steamapps_dir().context("Cannot determine home directory")?;
+        let config_dir = config::GhostBrewConfig::load()
+            .ok()
+            .and_then(|config| config.profiles_dir);
+        let profile_dir = match args.profiles_dir.clone().or(config_dir.clone()) {
+            Some(dir) => dir,
+            None => profiles::user_profiles_dir().context("Cannot determine config directory")?,
+        };
+        let count = profiles::import_from_steam(&steamapps, &profile_dir)?;
+        println!(
+            "Imported {} Steam profile(s) to {}",
+            count,
+            profile_dir.display()
+        );
+        // The scheduler runs as root: it loads /etc/ghostbrew/profiles, root's
+        // own ~/.config and profiles_dir, not this user's ~/.config
+        if config_dir.as_ref() != Some(&profile_dir)
+            && profile_dir != std::path::Path::new(profiles::SYSTEM_PROFILES_DIR)
+        {
+            println!(
+                "The scheduler does not load this directory; set profiles_dir = {:?} \
+                 in /etc/ghostbrew/config.toml or copy the files to {}",
+                profile_dir,
+                profiles::SYSTEM_PROFILES_DIR
+            );
+        }
+        return Ok(());
+    }
+
+    // Handle pacman hook management (needs root for /etc, not the scheduler)
+    let hook_path = std::path::Path::new(hook::HOOK_PATH);
+    if args.install_pacman_hook {
+        hook::install(hook_path)?;
+        println!("Installed {}", hook_path.display());
+        return Ok(());
+    }
+    if args.remove_pacman_hook {
+        if hook::remove(hook_path)? {
+            println!("Removed {}", hook_path.display());
+        } else {
+            println!("{} is not installed", hook_path.display());
+        }
+        return Ok(());
+    }
+
+    // The dashboard takes over stdout; a pipe or log file would only get
+    // escape sequences
+    #[cfg(feature = "tui")]
+    {
+        use std::io::IsTerminal;
+        if args.tui && !std::io::stdout().is_terminal() {
+            bail!("--tui needs a terminal on stdout");
+        }
+    }
+
     // Initialize logging
     let log_level = if args.debug {
         "debug"
@@ -1488,8 +4579,39 @@ fn main() -> Result<()> {
     } else {
         "warn"
     };
+    // Log lines would tear through the dashboard
+    #[cfg(feature = "tui")]
+    let log_level = if args.tui { "off" } else { log_level };
+
+    logging::init(log_level, args.log_format);
+
+    // Config file values fill in whatever the CLI left at its defaults
+    let mut config = config::GhostBrewConfig::load().unwrap_or_else(|e| {
+        warn!("Failed to load config: {}, using defaults", e);
+        config::GhostBrewConfig::default()
+    });
+    let cli_settings = effective::CliSettings::from_matches(&matches);
+    effective::apply_config(&mut args, cli_settings, &config);
+
+    // Handle snapshot replay (doesn't require root or scheduler)
+    if let Some(path) = args.simulate {
+        return simulate(&path, &config);
+    }
 
-    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(log_level)).init();
+    // Handle the hardware report (doesn't require root or scheduler)
+    if args.hw_report {
+        println!("{}", report::render(&report::collect()?)?);
+        return Ok(());
+    }
+
+    // Handle effective config printing (doesn't require root or scheduler)
+    if let Some(format) = args.print_config {
+        let topology = topology::detect_topology()?;
+        let resolved = effective::resolve(&args, &config, config::GhostBrewConfig::find_path())
+            .with_topology(&args, &config, &topology)?;
+        print!("{}", effective::render(&resolved, format)?);
+        return Ok(());
+    }
 
     info!("scx_{} v{}", SCHEDULER_NAME, env!("CARGO_PKG_VERSION"));
 
@@ -1498,9 +4620,77 @@ fn main() -> Result<()> {
         bail!("scx_ghostbrew must be run as root");
     }
 
-    // Check for sched-ext support
-    if !std::path::Path::new("/sys/kernel/sched_ext").exists() {
-        bail!("sched-ext not supported - ensure CONFIG_SCHED_CLASS_EXT=y in kernel");
+    // Handle map self-test (loads the BPF program but never attaches it)
+    if args.self_test {
+        return self_test();
+    }
+
+    // scx_loader/scx.service may load their own scheduler at any time
+    let loader_unit = loader::active_loader();
+    if let Some(unit) = loader_unit {
+        if args.take_over {
+            loader::take_over(unit)?;
+            attach::wait_for_state(Duration::from_secs(args.attach_timeout), |now| {
+                *now != doctor::SchedExtState::Attached
+            });
+        } else {
+            warn!(
+                "{} is active and may load its own sched_ext scheduler, racing GhostBrew - \
+                 stop it (systemctl stop {}) or pass --take-over",
+                unit, unit
+            );
+        }
+    }
+
+    // Check for sched-ext support and that it can take a scheduler now
+    match doctor::sched_ext_state() {
+        doctor::SchedExtState::Idle => {}
+        doctor::SchedExtState::Unsupported => {
+            bail!("sched-ext not supported - ensure CONFIG_SCHED_CLASS_EXT=y in kernel");
+        }
+        doctor::SchedExtState::Disabled => {
+            bail!(
+                "sched_ext reports no state (/sys/kernel/sched_ext/state is missing or empty) - \
+                 a BPF scheduler may have failed to load its struct_ops or been ejected; \
+                 check `dmesg | grep sched_ext`, see --doctor"
+            );
+        }
+        doctor::SchedExtState::Attached => {
+            let source = loader_unit
+                .map(|unit| format!(", loaded by {}", unit))
+                .unwrap_or_default();
+            bail!(
+                "another sched_ext scheduler is attached ({}{}) - stop it first \
+                 (e.g. systemctl stop scx_loader) or pass --take-over",
+                doctor::attached_scheduler().unwrap_or_else(|| "unknown".to_string()),
+                source
+            );
+        }
+        doctor::SchedExtState::Transitioning(state) => {
+            info!(
+                "sched_ext is {}, waiting up to {}s for it to settle",
+                state, args.attach_timeout
+            );
+            if !attach::wait_for_state(Duration::from_secs(args.attach_timeout), |now| {
+                *now == doctor::SchedExtState::Idle
+            }) {
+                bail!(attach::failure_report(
+                    "attach",
+                    &format!(
+                        "sched_ext still {} after {}s (--attach-timeout)",
+                        state, args.attach_timeout
+                    ),
+                    &attach::KernelInfo::capture(),
+                    &[],
+                ));
+            }
+        }
+        doctor::SchedExtState::Unknown(state) => {
+            warn!(
+                "Unrecognized sched_ext state '{}', attempting to attach",
+                state
+            );
+        }
     }
 
     // Log kernel info
@@ -1528,11 +4718,59 @@ fn main() -> Result<()> {
     ctrlc::set_handler(move || {
         info!("Received shutdown signal");
         shutdown_clone.store(true, Ordering::Relaxed);
+
+        // Restore EPP right away from the persisted snapshot (the handler runs
+        // on its own thread) in case the main loop is stuck and we get SIGKILLed
+        // before EppManager::restore_original runs. The file stays until
+        // restore_original has put every CPU back.
+        match pbo::restore_epp_state(std::path::Path::new(pbo::EPP_STATE_PATH)) {
+            Ok(n) if n > 0 => info!("Restored EPP on {} CPUs", n),
+            Ok(_) => {}
+            Err(e) => warn!("Failed to restore EPP on signal: {}", e),
+        }
     })
     .context("Failed to set signal handler")?;
 
+    // SIGHUP reloads config and profiles instead of shutting down (installed
+    // after ctrlc, which claims SIGHUP with its termination feature)
+    // SAFETY: the handler only stores to an atomic
+    unsafe {
+        nix::sys::signal::signal(
+            nix::sys::signal::Signal::SIGHUP,
+            nix::sys::signal::SigHandler::Handler(handle_sighup),
+        )
+    }
+    .context("Failed to set SIGHUP handler")?;
+
+    // Keep libbpf output (verifier log) for load/attach failure reports
+    attach::capture_libbpf_log();
+
+    // --load-state: detector settings go in before the detector is built,
+    // and the tunables become the defaults profiles revert to
+    let saved_state = args
+        .load_state
+        .as_deref()
+        .map(state::RuntimeState::load)
+        .transpose()?;
+    if let Some(saved) = &saved_state {
+        config.gaming = saved.gaming.clone();
+        args.max_scan_procs = saved.max_scan_procs;
+        args.burst_threshold = saved.tunables.burst_threshold_ns;
+        args.slice_ns = saved.tunables.slice_ns;
+        args.max_preempt_rate = saved.tunables.max_preempt_rate;
+    }
+
     // Initialize and run scheduler
     let mut open_object = MaybeUninit::uninit();
-    let mut scheduler = Scheduler::init(args, &mut open_object)?;
+    let mut scheduler = Scheduler::init(
+        args,
+        cli_settings,
+        config,
+        saved_state.as_ref(),
+        &mut open_object,
+    )?;
+    if let Some(saved) = &saved_state {
+        scheduler.apply_state(saved);
+    }
     scheduler.run(shutdown)
 }
@@ -6,11 +6,13 @@
 // - Detects MangoHud processes
 // - Exports scheduler stats to MangoHud-compatible CSV
 // - Reads MangoHud frame time logs for analysis
+// - Compares two logs (A/B scheduler runs)
 //
 // Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
 
 use anyhow::Result;
 use log::{debug, info};
+use serde::Serialize;
 use std::fs::{self, File};
 use std::io::{BufWriter, Write};
 use std::path::PathBuf;
@@ -145,6 +147,19 @@ pub struct SchedulerStats {
 /// Number of histogram buckets (must match BPF HIST_BUCKETS)
 pub const HIST_BUCKETS: usize = 16;
 
+/// Histogram bucket for a latency in microseconds (mirror of BPF latency_to_bucket)
+pub fn latency_to_bucket(lat_us: u64) -> usize {
+    const UPPER_BOUNDS_US: [u64; HIST_BUCKETS - 1] = [
+        100, 200, 400, 800, 1600, 3200, 6400, 10000, 15000, 20000, 30000, 50000, 75000, 100000,
+        150000,
+    ];
+
+    UPPER_BOUNDS_US
+        .iter()
+        .position(|&bound| lat_us < bound)
+        .unwrap_or(HIST_BUCKETS - 1)
+}
+
 /// Calculate latency percentiles from histogram buckets
 ///
 /// The histogram uses buckets matching the BPF latency_to_bucket():
@@ -249,6 +264,11 @@ impl MangoHudLogReader {
         Self { log_dir }
     }
 
+    /// Create a log reader for a specific output folder
+    pub fn with_dir(log_dir: PathBuf) -> Self {
+        Self { log_dir }
+    }
+
     /// Find the most recent MangoHud log file
     pub fn find_latest_log(&self) -> Option<PathBuf> {
         if !self.log_dir.exists() {
@@ -260,7 +280,9 @@ impl MangoHudLogReader {
         if let Ok(entries) = fs::read_dir(&self.log_dir) {
             for entry in entries.flatten() {
                 let path = entry.path();
+                // Skip the per-run summary MangoHud writes next to the log
                 if path.extension().is_some_and(|e| e == "csv")
+                    && !path.to_string_lossy().ends_with("_summary.csv")
                     && let Ok(metadata) = path.metadata()
                     && let Ok(modified) = metadata.modified()
                 {
@@ -342,6 +364,18 @@ impl MangoHudLogReader {
         let fps_1_low = 1000.0 / sorted[p99_idx];
         let fps_01_low = 1000.0 / sorted[p999_idx];
 
+        // A frame limiter or vsync puts a floor under frame times: nearly
+        // every frame sits at the cap and almost none come in faster
+        let median = sorted[sorted.len() / 2];
+        let tolerance = median * CAP_TOLERANCE;
+        let at_cap = frame_times
+            .iter()
+            .filter(|&&ft| (ft - median).abs() <= tolerance)
+            .count();
+        let fastest = sorted[(count * 0.01) as usize];
+        let frame_capped =
+            median > 0.0 && fastest >= median - tolerance && at_cap as f64 >= count * CAP_SHARE;
+
         FrameTimeStats {
             count: frame_times.len(),
             avg_ms: avg,
@@ -351,6 +385,8 @@ impl MangoHudLogReader {
             fps_avg,
             fps_1_low,
             fps_01_low,
+            frame_capped,
+            cap_fps: frame_capped.then(|| 1000.0 / median),
         }
     }
 }
@@ -372,6 +408,96 @@ pub struct FrameTimeStats {
     pub fps_avg: f64,
     pub fps_1_low: f64,
     pub fps_01_low: f64,
+    /// Frame times cluster at a limiter or vsync cap, so jitter and FPS
+    /// say little about scheduling
+    pub frame_capped: bool,
+    /// Detected cap in FPS
+    pub cap_fps: Option<f64>,
+}
+
+impl FrameTimeStats {
+    /// Caveat to print next to a capped run's numbers
+    pub fn capped_note(&self) -> Option<String> {
+        self.cap_fps.map(|fps| {
+            format!(
+                "frame-capped at {:.1} FPS (limiter or vsync): low jitter reflects the cap, not scheduling",
+                fps
+            )
+        })
+    }
+}
+
+/// Frame times within this fraction of the median count as at the cap
+const CAP_TOLERANCE: f64 = 0.03;
+
+/// Share of frames at the cap for a run to count as capped
+const CAP_SHARE: f64 = 0.9;
+
+/// Relative change below which a metric counts as unchanged (percent)
+const SAME_THRESHOLD_PCT: f64 = 1.0;
+
+/// How run B compares with run A on one metric
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Verdict {
+    Better,
+    Worse,
+    Same,
+}
+
+impl std::fmt::Display for Verdict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Verdict::Better => write!(f, "better"),
+            Verdict::Worse => write!(f, "worse"),
+            Verdict::Same => write!(f, "same"),
+        }
+    }
+}
+
+/// One row of a log comparison
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MetricDiff {
+    pub metric: &'static str,
+    pub a: f64,
+    pub b: f64,
+    /// Change from A to B in percent
+    pub delta_pct: f64,
+    pub verdict: Verdict,
+}
+
+/// Compare run B against run A: avg FPS, 1% and 0.1% lows, jitter and the
+/// worst frame time
+pub fn compare_frame_stats(a: &FrameTimeStats, b: &FrameTimeStats) -> Vec<MetricDiff> {
+    // (name, A, B, higher is better)
+    let metrics = [
+        ("avg_fps", a.fps_avg, b.fps_avg, true),
+        ("1%_low_fps", a.fps_1_low, b.fps_1_low, true),
+        ("0.1%_low_fps", a.fps_01_low, b.fps_01_low, true),
+        ("jitter_ms", a.std_dev_ms, b.std_dev_ms, false),
+        ("max_frame_time_ms", a.max_ms, b.max_ms, false),
+    ];
+
+    metrics
+        .into_iter()
+        .map(|(metric, a, b, higher_is_better)| {
+            let delta_pct = if a != 0.0 { (b - a) / a * 100.0 } else { 0.0 };
+            let verdict = if delta_pct.abs() < SAME_THRESHOLD_PCT {
+                Verdict::Same
+            } else if (delta_pct > 0.0) == higher_is_better {
+                Verdict::Better
+            } else {
+                Verdict::Worse
+            };
+            MetricDiff {
+                metric,
+                a,
+                b,
+                delta_pct,
+                verdict,
+            }
+        })
+        .collect()
 }
 
 #[cfg(test)]
@@ -394,4 +520,67 @@ mod tests {
         assert!(stats.fps_avg > 59.0 && stats.fps_avg < 61.0); // ~60 FPS
         assert!(stats.std_dev_ms < 1.0); // Low jitter
     }
+
+    #[test]
+    fn test_compare_frame_stats() {
+        // A: steady 10ms with one 40ms hitch; B: steady 8ms with one 20ms hitch
+        let mut a = vec![10.0; 999];
+        a.push(40.0);
+        let mut b = vec![8.0; 999];
+        b.push(20.0);
+        let a = MangoHudLogReader::analyze_frame_times(&a);
+        let b = MangoHudLogReader::analyze_frame_times(&b);
+
+        let diffs = compare_frame_stats(&a, &b);
+        let row = |name: &str| diffs.iter().find(|d| d.metric == name).unwrap();
+
+        let fps = row("avg_fps");
+        assert!((fps.delta_pct - 25.2).abs() < 0.1, "{}", fps.delta_pct);
+        assert_eq!(fps.verdict, Verdict::Better);
+        // 1% lows are both the steady frame time
+        assert!((row("1%_low_fps").delta_pct - 25.0).abs() < 1e-9);
+        // 0.1% low is the hitch: 25 -> 50 FPS
+        assert!((row("0.1%_low_fps").delta_pct - 100.0).abs() < 1e-9);
+        assert_eq!(row("max_frame_time_ms").delta_pct, -50.0);
+        assert_eq!(row("max_frame_time_ms").verdict, Verdict::Better);
+        assert_eq!(row("jitter_ms").verdict, Verdict::Better);
+
+        // B against itself changes nothing
+        assert!(
+            compare_frame_stats(&b, &b)
+                .iter()
+                .all(|d| d.verdict == Verdict::Same)
+        );
+        // Swapped, every metric is worse
+        assert!(
+            compare_frame_stats(&b, &a)
+                .iter()
+                .all(|d| d.verdict == Verdict::Worse)
+        );
+    }
+
+    #[test]
+    fn test_frame_cap_detection() {
+        // 60 FPS limiter: frames at 16.67ms +/- a few microseconds, a couple
+        // of hitches above the cap but none below it
+        let mut capped: Vec<f64> = (0..1000)
+            .map(|i| 16.67 + [0.0, 0.02, -0.03, 0.05, -0.01][i % 5])
+            .collect();
+        capped[400] = 33.3;
+        capped[800] = 25.0;
+        let stats = MangoHudLogReader::analyze_frame_times(&capped);
+        assert!(stats.frame_capped);
+        let cap = stats.cap_fps.unwrap();
+        assert!((cap - 60.0).abs() < 0.1, "{}", cap);
+        assert!(stats.capped_note().unwrap().contains("60.0 FPS"));
+
+        // Uncapped: frame times spread between 7 and 14ms
+        let noisy: Vec<f64> = (0..1000)
+            .map(|i| 7.0 + ((i * 37) % 71) as f64 / 10.0)
+            .collect();
+        let stats = MangoHudLogReader::analyze_frame_times(&noisy);
+        assert!(!stats.frame_capped);
+        assert_eq!(stats.cap_fps, None);
+        assert_eq!(stats.capped_note(), None);
+    }
 }
@@ -0,0 +1,111 @@
+// SPDX-License-Identifier: GPL-2.0
+//
+// GhostBrew - BPF Map Feature Gates
+//
+// Map-fed features the running kernel can't support. The BPF object is built
+// from the same tree as this binary, so every map is always there; what
+// differs between machines is the kernel. Startup disables a feature when its
+// kernel check fails (the events ringbuf can't be set up, no unified cgroup
+// hierarchy for cgroup classification), and its userspace updates stop. The
+// BPF side then sees an empty map and keeps its defaults.
+//
+// Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
+
+use log::warn;
+use std::collections::HashSet;
+
+/// BPF maps fed from userspace, one feature each
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MapFeature {
+    GamingPids,
+    VmVcpuPids,
+    ContainerPids,
+    CgroupClasses,
+    PrefcoreRankings,
+    ShaderThreads,
+    ThreadClasses,
+    SmtPrefs,
+    PriorityBoosts,
+    PidStats,
+    ClassCpumasks,
+    Events,
+}
+
+impl MapFeature {
+    /// Map name in the BPF object
+    pub fn map_name(self) -> &'static str {
+        match self {
+            MapFeature::GamingPids => "gaming_pids",
+            MapFeature::VmVcpuPids => "vm_vcpu_pids",
+            MapFeature::ContainerPids => "container_pids",
+            MapFeature::CgroupClasses => "cgroup_classes",
+            MapFeature::PrefcoreRankings => "prefcore_rankings",
+            MapFeature::ShaderThreads => "shader_threads",
+            MapFeature::ThreadClasses => "thread_classes",
+            MapFeature::SmtPrefs => "smt_prefs",
+            MapFeature::PriorityBoosts => "priority_boosts",
+            MapFeature::PidStats => "pid_stats",
+            MapFeature::ClassCpumasks => "class_cpumasks",
+            MapFeature::Events => "events",
+        }
+    }
+
+    /// Feature the map feeds
+    pub fn description(self) -> &'static str {
+        match self {
+            MapFeature::GamingPids => "gaming/batch/AI process hints",
+            MapFeature::VmVcpuPids => "VM vCPU classification",
+            MapFeature::ContainerPids => "container classification",
+            MapFeature::CgroupClasses => "cgroup classification",
+            MapFeature::PrefcoreRankings => "prefcore rankings",
+            MapFeature::ShaderThreads => "shader compile thread placement",
+            MapFeature::ThreadClasses => "OBS encoder thread placement",
+            MapFeature::SmtPrefs => "per-game SMT preference",
+            MapFeature::PriorityBoosts => "per-game priority boost",
+            MapFeature::PidStats => "per-game scheduler stats",
+            MapFeature::ClassCpumasks => "AI CPU reservation",
+            MapFeature::Events => "event streaming",
+        }
+    }
+}
+
+/// Map-fed features the running kernel supports
+#[derive(Debug, Default)]
+pub struct MapCaps {
+    disabled: HashSet<MapFeature>,
+}
+
+impl MapCaps {
+    /// Check whether a feature is enabled
+    pub fn has(&self, feature: MapFeature) -> bool {
+        !self.disabled.contains(&feature)
+    }
+
+    /// Turn off a feature the running kernel can't support
+    pub fn disable(&mut self, feature: MapFeature, reason: &str) {
+        if self.disabled.insert(feature) {
+            warn!("{} disabled: {}", feature.description(), reason);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map_caps_gating() {
+        let mut caps = MapCaps::default();
+        assert!(caps.has(MapFeature::CgroupClasses));
+        assert!(caps.has(MapFeature::Events));
+
+        caps.disable(
+            MapFeature::CgroupClasses,
+            "no unified (v2) cgroup hierarchy mounted",
+        );
+        caps.disable(MapFeature::CgroupClasses, "again");
+        assert!(!caps.has(MapFeature::CgroupClasses));
+        assert!(caps.has(MapFeature::GamingPids));
+        assert_eq!(caps.disabled.len(), 1);
+    }
+}
@@ -0,0 +1,131 @@
+// SPDX-License-Identifier: GPL-2.0
+//
+// GhostBrew - ftrace Markers (--trace-markers)
+//
+// Writes userspace decisions (classifications, mode switches, EPP changes)
+// to the tracefs trace_marker so they land on the same timeline as the
+// kernel's scheduler events in trace-cmd/perfetto captures.
+//
+// Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
+
+use crate::procfs;
+use log::{info, warn};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+
+/// trace_marker locations (tracefs, then the legacy debugfs mount)
+const TRACE_MARKER_PATHS: &[&str] = &[
+    "/sys/kernel/tracing/trace_marker",
+    "/sys/kernel/debug/tracing/trace_marker",
+];
+
+/// A userspace decision worth a marker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision<'a> {
+    /// A process was given a workload class
+    Classify {
+        pid: u32,
+        comm: &'a str,
+        class: &'a str,
+    },
+    /// Gaming/work mode flags written to the BPF side
+    Mode { gaming: bool, work: bool },
+    /// EPP written on `cpus` CPUs
+    Epp { epp: &'a str, cpus: usize },
+}
+
+/// One trace_marker line for a decision
+pub fn format_marker(decision: &Decision) -> String {
+    match decision {
+        Decision::Classify { pid, comm, class } => format!(
+            "ghostbrew: classify pid={} comm={} class={}\n",
+            pid,
+            comm.trim(),
+            class
+        ),
+        Decision::Mode { gaming, work } => format!(
+            "ghostbrew: mode gaming={} work={}\n",
+            u8::from(*gaming),
+            u8::from(*work)
+        ),
+        Decision::Epp { epp, cpus } => format!("ghostbrew: epp value={} cpus={}\n", epp, cpus),
+    }
+}
+
+/// Writer for the trace_marker file (a no-op when disabled or unwritable)
+pub struct TraceMarker {
+    file: Option<File>,
+}
+
+impl TraceMarker {
+    pub fn new(enabled: bool) -> Self {
+        if !enabled {
+            return Self { file: None };
+        }
+        let file = TRACE_MARKER_PATHS.iter().find_map(|path| {
+            let file = OpenOptions::new()
+                .write(true)
+                .open(procfs::rooted(path))
+                .ok()?;
+            info!("Trace markers: writing decisions to {}", path);
+            Some(file)
+        });
+        if file.is_none() {
+            warn!("Trace markers: no writable trace_marker (is tracefs mounted?), disabled");
+        }
+        Self { file }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.file.is_some()
+    }
+
+    /// Record a decision; the first failed write disables further markers
+    pub fn mark(&mut self, decision: &Decision) {
+        let Some(file) = &mut self.file else {
+            return;
+        };
+        // One write per line so it stays a single trace entry
+        if let Err(e) = file.write(format_marker(decision).as_bytes()) {
+            warn!("Trace markers: write failed: {}, disabled", e);
+            self.file = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_marker() {
+        assert_eq!(
+            format_marker(&Decision::Classify {
+                pid: 4242,
+                comm: "Cyberpunk2077.e\n",
+                class: "gaming",
+            }),
+            "ghostbrew: classify pid=4242 comm=Cyberpunk2077.e class=gaming\n"
+        );
+        assert_eq!(
+            format_marker(&Decision::Mode {
+                gaming: true,
+                work: false
+            }),
+            "ghostbrew: mode gaming=1 work=0\n"
+        );
+        assert_eq!(
+            format_marker(&Decision::Epp {
+                epp: "performance",
+                cpus: 2
+            }),
+            "ghostbrew: epp value=performance cpus=2\n"
+        );
+
+        // Disabled markers never touch tracefs
+        TraceMarker::new(false).mark(&Decision::Mode {
+            gaming: false,
+            work: true,
+        });
+    }
+}
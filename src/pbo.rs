@@ -4,10 +4,13 @@
 //
 // Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
 
-use anyhow::{Context, Result};
+use crate::procfs;
+use anyhow::{Context, Result, bail};
 use log::{debug, info, warn};
+use serde::Serialize;
+use std::collections::HashSet;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 /// AMD pstate prefcore information
 pub struct PrefcoreInfo {
@@ -30,6 +33,91 @@ impl PrefcoreInfo {
             preferred_cpus: Vec::new(),
         }
     }
+
+    /// Recompute max_ranking and preferred_cpus from the rankings
+    fn rank(&mut self) {
+        self.max_ranking = self.rankings.iter().copied().max().unwrap_or(0);
+        self.preferred_cpus = (0..self.rankings.len() as u32)
+            .filter(|&cpu| self.max_ranking > 0 && self.rankings[cpu as usize] == self.max_ranking)
+            .collect();
+    }
+
+    /// Drop CPUs to the lowest ranking so BPF places latency-critical tasks
+    /// elsewhere first, and keep them out of the EPP-boosted set
+    pub fn avoid(&mut self, cpus: &[u32]) {
+        for &cpu in cpus {
+            if let Some(ranking) = self.rankings.get_mut(cpu as usize) {
+                *ranking = 0;
+            }
+        }
+        self.rank();
+    }
+}
+
+/// A core boosting this far below the best core of its CCD is an outlier,
+/// e.g. from an aggressive negative Curve Optimizer offset
+const BOOST_OUTLIER_PCT: u64 = 5;
+
+/// Max boost frequency range within one CCD
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CcdBoost {
+    pub ccd: u32,
+    pub min_mhz: u32,
+    pub max_mhz: u32,
+}
+
+/// Per-core max boost spread within each CCD
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct BoostSpread {
+    pub ccds: Vec<CcdBoost>,
+    /// CPUs boosting more than BOOST_OUTLIER_PCT below their CCD's best
+    pub outliers: Vec<u32>,
+}
+
+impl BoostSpread {
+    /// Widest min-to-max spread of any CCD in MHz
+    pub fn max_spread_mhz(&self) -> u32 {
+        self.ccds
+            .iter()
+            .map(|c| c.max_mhz - c.min_mhz)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// Compare cpuinfo_max_freq (kHz) of the cores within each CCD
+///
+/// GhostBrew can't read Curve Optimizer offsets, but a core whose achievable
+/// boost is well below its CCD siblings is a poor home for a game's render
+/// thread. CPUs without a max frequency are skipped.
+pub fn per_core_boost_spread(max_freqs: &[u32], cpu_to_ccd: &[u32]) -> BoostSpread {
+    let mut by_ccd: std::collections::BTreeMap<u32, Vec<(u32, u32)>> = Default::default();
+    for (cpu, &freq) in max_freqs.iter().enumerate() {
+        if let Some(&ccd) = cpu_to_ccd.get(cpu)
+            && freq > 0
+        {
+            by_ccd.entry(ccd).or_default().push((cpu as u32, freq));
+        }
+    }
+
+    let mut spread = BoostSpread::default();
+    for (ccd, cores) in by_ccd {
+        let best = cores.iter().map(|&(_, f)| f).max().unwrap_or(0);
+        let worst = cores.iter().map(|&(_, f)| f).min().unwrap_or(0);
+        spread.ccds.push(CcdBoost {
+            ccd,
+            min_mhz: worst / 1000,
+            max_mhz: best / 1000,
+        });
+        spread.outliers.extend(
+            cores
+                .iter()
+                .filter(|&&(_, f)| (f as u64) * 100 < best as u64 * (100 - BOOST_OUTLIER_PCT))
+                .map(|&(cpu, _)| cpu),
+        );
+    }
+    spread.outliers.sort_unstable();
+    spread
 }
 
 /// Detect AMD pstate prefcore rankings
@@ -38,8 +126,8 @@ pub fn detect_prefcore(nr_cpus: u32) -> Result<PrefcoreInfo> {
 
     // Check if prefcore is enabled
     let prefcore_path = "/sys/devices/system/cpu/amd_pstate/prefcore";
-    if Path::new(prefcore_path).exists()
-        && let Ok(content) = fs::read_to_string(prefcore_path)
+    if procfs::rooted(prefcore_path).exists()
+        && let Ok(content) = procfs::read_rooted(prefcore_path)
     {
         info.enabled = content.trim() == "enabled";
     }
@@ -58,22 +146,15 @@ pub fn detect_prefcore(nr_cpus: u32) -> Result<PrefcoreInfo> {
             cpu
         );
 
-        if let Ok(content) = fs::read_to_string(&ranking_path)
+        if let Ok(content) = procfs::read_rooted(&ranking_path)
             && let Ok(ranking) = content.trim().parse::<u32>()
         {
             info.rankings[cpu as usize] = ranking;
-            if ranking > info.max_ranking {
-                info.max_ranking = ranking;
-            }
         }
     }
 
     // Find CPUs with the highest ranking
-    for (cpu, &ranking) in info.rankings.iter().enumerate() {
-        if ranking == info.max_ranking && info.max_ranking > 0 {
-            info.preferred_cpus.push(cpu as u32);
-        }
-    }
+    info.rank();
 
     // Log summary
     if !info.preferred_cpus.is_empty() {
@@ -100,7 +181,7 @@ pub fn get_cpu_epp(cpu: u32) -> Result<String> {
         "/sys/devices/system/cpu/cpufreq/policy{}/energy_performance_preference",
         cpu
     );
-    fs::read_to_string(&path)
+    procfs::read_rooted(&path)
         .map(|s| s.trim().to_string())
         .with_context(|| format!("Failed to read EPP for CPU {}", cpu))
 }
@@ -111,17 +192,17 @@ pub fn set_cpu_epp(cpu: u32, epp: &str) -> Result<()> {
         "/sys/devices/system/cpu/cpufreq/policy{}/energy_performance_preference",
         cpu
     );
-    fs::write(&path, epp).with_context(|| format!("Failed to set EPP {} for CPU {}", epp, cpu))
+    fs::write(procfs::rooted(&path), epp)
+        .with_context(|| format!("Failed to set EPP {} for CPU {}", epp, cpu))
 }
 
 /// Get available EPP values for a CPU
-#[allow(dead_code)]
 pub fn get_available_epps(cpu: u32) -> Result<Vec<String>> {
     let path = format!(
         "/sys/devices/system/cpu/cpufreq/policy{}/energy_performance_available_preferences",
         cpu
     );
-    let content = fs::read_to_string(&path)
+    let content = procfs::read_rooted(&path)
         .with_context(|| format!("Failed to read available EPPs for CPU {}", cpu))?;
     Ok(content.split_whitespace().map(String::from).collect())
 }
@@ -137,11 +218,10 @@ pub enum PstateMode {
 }
 
 /// Detect the current amd_pstate driver mode
-#[allow(dead_code)]
 pub fn detect_pstate_mode() -> PstateMode {
     let status_path = "/sys/devices/system/cpu/amd_pstate/status";
 
-    if let Ok(content) = fs::read_to_string(status_path) {
+    if let Ok(content) = procfs::read_rooted(status_path) {
         match content.trim() {
             "active" => PstateMode::Active,
             "passive" => PstateMode::Passive,
@@ -151,7 +231,7 @@ pub fn detect_pstate_mode() -> PstateMode {
     } else {
         // Check if amd_pstate is even loaded
         let driver_path = "/sys/devices/system/cpu/cpufreq/policy0/scaling_driver";
-        if let Ok(driver) = fs::read_to_string(driver_path)
+        if let Ok(driver) = procfs::read_rooted(driver_path)
             && driver.trim().starts_with("amd")
         {
             return PstateMode::Unknown;
@@ -160,14 +240,176 @@ pub fn detect_pstate_mode() -> PstateMode {
     }
 }
 
+/// cpufreq scaling driver and governor (policy of CPU 0)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CpufreqInfo {
+    pub driver: String,
+    pub governor: String,
+}
+
+impl CpufreqInfo {
+    pub fn new(driver: &str, governor: &str) -> Self {
+        Self {
+            driver: driver.trim().to_string(),
+            governor: governor.trim().to_string(),
+        }
+    }
+
+    /// Why EPP hints cannot be applied, None if they can
+    pub fn epp_unavailable_reason(&self) -> Option<String> {
+        match self.driver.as_str() {
+            "amd-pstate-epp" | "intel_pstate" if self.governor == "performance" => Some(format!(
+                "{} with the performance governor pins EPP to performance",
+                self.driver
+            )),
+            "amd-pstate-epp" | "intel_pstate" => None,
+            "amd-pstate" => Some("amd-pstate in passive/guided mode has no EPP".to_string()),
+            "intel_cpufreq" => Some("intel_pstate in passive mode has no EPP".to_string()),
+            driver => Some(format!("{} has no EPP", driver)),
+        }
+    }
+}
+
+/// Read the cpufreq driver and governor of CPU 0, None without cpufreq
+pub fn detect_cpufreq() -> Option<CpufreqInfo> {
+    let base = "/sys/devices/system/cpu/cpu0/cpufreq";
+    let driver = procfs::read_rooted(format!("{}/scaling_driver", base)).ok()?;
+    let governor = procfs::read_rooted(format!("{}/scaling_governor", base)).unwrap_or_default();
+    Some(CpufreqInfo::new(&driver, &governor))
+}
+
+/// EPP written to preferred cores while gaming
+pub const BOOST_EPP: &str = "performance";
+
+/// Gaming EPP on handheld APUs: the CPU and GPU share a tight TDP, so
+/// "performance" on the cores would take power the iGPU needs
+pub const HANDHELD_BOOST_EPP: &str = "balance_performance";
+
+/// Boost EPP constant named `name`, for restoring a saved setting
+pub fn boost_epp_named(name: &str) -> Option<&'static str> {
+    [BOOST_EPP, HANDHELD_BOOST_EPP]
+        .into_iter()
+        .find(|&epp| epp == name)
+}
+
+/// Standard EPP strings, most performance-oriented first
+const EPP_ORDER: &[&str] = &[
+    "performance",
+    "balance_performance",
+    "default",
+    "balance_power",
+    "power",
+];
+
+/// Available EPP closest to `wanted` on the performance/power scale, ties
+/// going to the more performance-oriented one
+///
+/// None if nothing in `available` is a standard EPP string.
+pub fn closest_epp<'a>(wanted: &str, available: &'a [String]) -> Option<&'a str> {
+    if let Some(epp) = available.iter().find(|epp| *epp == wanted) {
+        return Some(epp);
+    }
+    let target = EPP_ORDER.iter().position(|&epp| epp == wanted).unwrap_or(0);
+    available
+        .iter()
+        .filter_map(|epp| {
+            let rank = EPP_ORDER.iter().position(|standard| standard == epp)?;
+            Some((rank.abs_diff(target), rank, epp.as_str()))
+        })
+        .min()
+        .map(|(_, _, epp)| epp)
+}
+
+/// Original EPP snapshot, kept so an unclean exit (SIGKILL) can be undone on next start
+pub const EPP_STATE_PATH: &str = "/run/ghostbrew/epp.state";
+
+/// Serialize per-CPU EPP values as `cpu=epp` lines
+fn format_epp_state(epp: &[Option<String>]) -> String {
+    epp.iter()
+        .enumerate()
+        .filter_map(|(cpu, value)| value.as_ref().map(|v| format!("{}={}\n", cpu, v)))
+        .collect()
+}
+
+/// Parse `cpu=epp` lines written by format_epp_state
+fn parse_epp_state(content: &str) -> Vec<(u32, String)> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let (cpu, epp) = line.trim().split_once('=')?;
+            let epp = epp.trim();
+            if epp.is_empty() {
+                return None;
+            }
+            Some((cpu.trim().parse().ok()?, epp.to_string()))
+        })
+        .collect()
+}
+
+/// Restore EPP from a state file left behind by an unclean exit
+///
+/// Returns the number of CPUs restored. The file is left in place; only
+/// EppManager::restore_original removes it, once EPP is back.
+pub fn restore_epp_state(path: &Path) -> Result<usize> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => {
+            return Err(e).with_context(|| format!("Failed to read EPP state {:?}", path));
+        }
+    };
+
+    let mut restored = 0;
+    for (cpu, epp) in parse_epp_state(&content) {
+        match set_cpu_epp(cpu, &epp) {
+            Ok(()) => restored += 1,
+            Err(e) => warn!("Failed to restore EPP for CPU {}: {}", cpu, e),
+        }
+    }
+    Ok(restored)
+}
+
+/// Whether gaming should boost preferred cores with performance EPP
+///
+/// A game must be running with the GPU awake. When every GPU is passed
+/// through to VMs the host never sees GPU activity, so running gaming VM
+/// vCPUs decide instead.
+pub fn gaming_boost(
+    gaming_pids: usize,
+    gpu_active: bool,
+    gpus_passed_through: bool,
+    gaming_vcpus: usize,
+) -> bool {
+    if gpus_passed_through {
+        gaming_vcpus > 0
+    } else {
+        gaming_pids > 0 && gpu_active
+    }
+}
+
 /// EPP state manager for tracking and restoring EPP values
 pub struct EppManager {
     /// Original EPP values per CPU
     original_epp: Vec<Option<String>>,
     /// Current EPP values per CPU
     current_epp: Vec<Option<String>>,
+    /// energy_performance_available_preferences per CPU (None: unreadable,
+    /// writes are not checked)
+    available_epp: Vec<Option<Vec<String>>>,
+    /// Unavailable EPPs already warned about
+    warned_epp: HashSet<String>,
     /// Whether we've modified EPP
     modified: bool,
+    /// Persisted copy of original_epp (removed on clean restore)
+    state_path: Option<PathBuf>,
+    /// Boost decision of the last update pass (None before the first)
+    last_boost: Option<bool>,
+    /// Sysfs EPP writes made by update passes
+    writes: u64,
+    /// EPP the boost writes
+    boost_epp: &'static str,
+    /// --epp-dry-run: log changes instead of writing them
+    dry_run: bool,
 }
 
 impl EppManager {
@@ -175,8 +417,42 @@ impl EppManager {
         Self {
             original_epp: vec![None; nr_cpus as usize],
             current_epp: vec![None; nr_cpus as usize],
+            available_epp: (0..nr_cpus)
+                .map(|cpu| get_available_epps(cpu).ok())
+                .collect(),
+            warned_epp: HashSet::new(),
             modified: false,
+            state_path: None,
+            last_boost: None,
+            writes: 0,
+            boost_epp: BOOST_EPP,
+            dry_run: false,
+        }
+    }
+
+    /// Log intended EPP changes without writing sysfs
+    pub fn set_dry_run(&mut self, dry_run: bool) {
+        self.dry_run = dry_run;
+    }
+
+    /// Change the EPP the boost writes (HANDHELD_BOOST_EPP on handhelds)
+    pub fn set_boost_epp(&mut self, epp: &'static str) {
+        self.boost_epp = epp;
+    }
+
+    pub fn boost_epp(&self) -> &'static str {
+        self.boost_epp
+    }
+
+    /// Write the original EPP snapshot to a state file
+    pub fn persist_state(&mut self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("Failed to create {:?}", parent))?;
         }
+        fs::write(path, format_epp_state(&self.original_epp))
+            .with_context(|| format!("Failed to write EPP state {:?}", path))?;
+        self.state_path = Some(path.to_path_buf());
+        Ok(())
     }
 
     /// Save original EPP values for all CPUs
@@ -189,9 +465,42 @@ impl EppManager {
         }
     }
 
+    /// EPP to write for `wanted` on `cpu`: itself if the CPU offers it,
+    /// otherwise the closest available value (warning once per EPP)
+    fn resolve_epp(&mut self, cpu: u32, wanted: &str) -> Result<String> {
+        let Some(available) = self
+            .available_epp
+            .get(cpu as usize)
+            .and_then(Option::as_ref)
+        else {
+            return Ok(wanted.to_string());
+        };
+        let Some(epp) = closest_epp(wanted, available) else {
+            bail!(
+                "EPP {} unavailable on CPU {} (available: {})",
+                wanted,
+                cpu,
+                available.join(" ")
+            );
+        };
+        if epp != wanted && self.warned_epp.insert(wanted.to_string()) {
+            warn!(
+                "EPP {} is not available on this system (available: {}), using {}",
+                wanted,
+                available.join(" "),
+                epp
+            );
+        }
+        Ok(epp.to_string())
+    }
+
     /// Set EPP for a CPU (tracks changes)
+    ///
+    /// An EPP the CPU doesn't offer is replaced by the closest one it does.
     pub fn set_epp(&mut self, cpu: u32, epp: &str) -> Result<()> {
         let cpu_idx = cpu as usize;
+        let epp = self.resolve_epp(cpu, epp)?;
+        let epp = epp.as_str();
 
         // Check if already set
         if let Some(current) = &self.current_epp[cpu_idx]
@@ -200,6 +509,17 @@ impl EppManager {
             return Ok(());
         }
 
+        if self.dry_run {
+            info!(
+                "EPP dry run: CPU {}: {} -> {}",
+                cpu,
+                self.current_epp[cpu_idx].as_deref().unwrap_or("unknown"),
+                epp
+            );
+            self.current_epp[cpu_idx] = Some(epp.to_string());
+            return Ok(());
+        }
+
         set_cpu_epp(cpu, epp)?;
         self.current_epp[cpu_idx] = Some(epp.to_string());
         self.modified = true;
@@ -208,21 +528,86 @@ impl EppManager {
         Ok(())
     }
 
+    /// Apply the boost decision to `cpus`, returning the sysfs writes made
+    ///
+    /// The pass is skipped while the decision is unchanged, and only CPUs not
+    /// already at the target EPP are written.
+    pub fn update(&mut self, boost: bool, cpus: &[u32]) -> usize {
+        if self.last_boost == Some(boost) {
+            return 0;
+        }
+        self.last_boost = Some(boost);
+        if !boost {
+            return 0;
+        }
+
+        let mut writes = 0;
+        for &cpu in cpus {
+            if self.current_epp[cpu as usize].as_deref() == Some(self.boost_epp) {
+                continue;
+            }
+            match self.set_epp(cpu, self.boost_epp) {
+                Ok(()) => writes += 1,
+                Err(e) => debug!("Failed to set EPP for CPU {}: {}", cpu, e),
+            }
+        }
+        self.writes += writes as u64;
+        debug!(
+            "EPP boost applied: {} write(s), {} total",
+            writes, self.writes
+        );
+        writes
+    }
+
     /// Restore original EPP values
     pub fn restore_original(&mut self) {
+        let failures = self.put_back();
+        match failures {
+            Some(0) => info!("Restored original EPP values"),
+            Some(n) => warn!(
+                "Restored original EPP values, {} CPU(s) failed; keeping the EPP state for the next start",
+                n
+            ),
+            None => {}
+        }
+
+        // Clean shutdown: the on-disk snapshot is no longer needed once
+        // every CPU is back
+        if failures.unwrap_or(0) == 0
+            && let Some(path) = self.state_path.take()
+        {
+            fs::remove_file(&path).ok();
+        }
+    }
+
+    /// Put back the original EPP values while keeping the state file, so a
+    /// later update starts from scratch; false if nothing had been changed
+    pub fn release(&mut self) -> bool {
+        self.put_back().is_some()
+    }
+
+    /// Write the original EPP values back; the number of failed writes, or
+    /// None if nothing had been changed
+    fn put_back(&mut self) -> Option<usize> {
+        self.last_boost = None;
+        // Dry runs only ever changed current_epp
         if !self.modified {
-            return;
+            self.current_epp.clone_from(&self.original_epp);
+            return None;
         }
 
+        let mut failures = 0;
         for (cpu, original) in self.original_epp.iter().enumerate() {
             if let Some(epp) = original
                 && let Err(e) = set_cpu_epp(cpu as u32, epp)
             {
                 warn!("Failed to restore EPP for CPU {}: {}", cpu, e);
+                failures += 1;
             }
         }
-
-        info!("Restored original EPP values");
+        self.current_epp.clone_from(&self.original_epp);
+        self.modified = false;
+        Some(failures)
     }
 }
 
@@ -242,10 +627,296 @@ mod tests {
         let _mode = detect_pstate_mode();
     }
 
+    #[test]
+    fn test_cpufreq_epp_capability() {
+        let epp = |driver, governor| CpufreqInfo::new(driver, governor).epp_unavailable_reason();
+
+        assert_eq!(epp("amd-pstate-epp\n", "powersave\n"), None);
+        assert_eq!(epp("intel_pstate", "powersave"), None);
+
+        // Performance governor rejects EPP writes on EPP drivers
+        assert!(epp("amd-pstate-epp", "performance").is_some());
+
+        // Passive/guided pstate and legacy drivers expose no EPP at all
+        assert!(epp("amd-pstate", "schedutil").is_some());
+        assert!(epp("intel_cpufreq", "schedutil").is_some());
+        assert_eq!(
+            epp("acpi-cpufreq", "ondemand").as_deref(),
+            Some("acpi-cpufreq has no EPP")
+        );
+    }
+
+    #[test]
+    fn test_per_core_boost_spread() {
+        // CCD0: CPU 2 boosts 300 MHz short of its siblings; CCD1 is lower
+        // clocked overall (V-Cache) but even; CPU 7 has no cpufreq
+        let max_freqs = [
+            5_700_000, 5_650_000, 5_400_000, 5_700_000, 5_250_000, 5_200_000, 5_250_000, 0,
+        ];
+        let cpu_to_ccd = [0, 0, 0, 0, 1, 1, 1, 1];
+        let spread = per_core_boost_spread(&max_freqs, &cpu_to_ccd);
+
+        assert_eq!(spread.outliers, vec![2]);
+        assert_eq!(
+            spread.ccds,
+            vec![
+                CcdBoost {
+                    ccd: 0,
+                    min_mhz: 5400,
+                    max_mhz: 5700
+                },
+                CcdBoost {
+                    ccd: 1,
+                    min_mhz: 5200,
+                    max_mhz: 5250
+                },
+            ]
+        );
+        assert_eq!(spread.max_spread_mhz(), 300);
+
+        // The outlier loses its prefcore ranking and leaves the boosted set
+        let mut prefcore = PrefcoreInfo::new(8);
+        prefcore.rankings = vec![231, 226, 236, 221, 196, 191, 196, 186];
+        prefcore.rank();
+        assert_eq!(prefcore.preferred_cpus, vec![2]);
+        prefcore.avoid(&spread.outliers);
+        assert_eq!(prefcore.rankings[2], 0);
+        assert_eq!(prefcore.max_ranking, 231);
+        assert_eq!(prefcore.preferred_cpus, vec![0]);
+    }
+
+    #[test]
+    fn test_headless_host_boosts_for_gaming_vm() {
+        // The only GPU (and its audio function) bound to vfio-pci
+        let fixture = procfs::Fixture::new("pbo-headless");
+        for (device, class) in [("0000:01:00.0", "0x030000"), ("0000:01:00.1", "0x040300")] {
+            fixture
+                .file(&format!("/sys/bus/pci/devices/{}/class", device), class)
+                .symlink(
+                    &format!("/sys/bus/pci/devices/{}/driver", device),
+                    "../../../bus/pci/drivers/vfio-pci",
+                );
+        }
+        let groups = [crate::vm::IommuGroup {
+            id: 14,
+            devices: vec!["0000:01:00.0".to_string(), "0000:01:00.1".to_string()],
+            has_gpu: true,
+            is_isolated: true,
+        }];
+        let headless = fixture.run(|| crate::vm::all_gpus_passed_through(&groups));
+        assert!(headless);
+
+        // No host GPU activity, but a gaming VM is running
+        assert!(gaming_boost(0, false, headless, 8));
+        assert!(!gaming_boost(0, false, headless, 0));
+
+        // With a host GPU, host games and GPU activity decide as before
+        assert!(gaming_boost(1, true, false, 0));
+        assert!(!gaming_boost(1, false, false, 8));
+        assert!(!fixture.run(|| crate::vm::all_gpus_passed_through(&[])));
+    }
+
+    #[test]
+    fn test_unchanged_update_writes_nothing() {
+        let fixture = procfs::Fixture::new("epp");
+        let epp_path = |cpu: u32| {
+            format!(
+                "/sys/devices/system/cpu/cpufreq/policy{}/energy_performance_preference",
+                cpu
+            )
+        };
+        for cpu in 0..3 {
+            fixture.file(&epp_path(cpu), "balance_performance\n");
+        }
+        // CPU 2 is already where the boost wants it
+        fixture.file(&epp_path(2), "performance\n");
+
+        fixture.run(|| {
+            let mut manager = EppManager::new(3);
+            manager.save_original(3);
+
+            assert_eq!(manager.update(true, &[0, 1, 2]), 2);
+            assert_eq!(manager.update(true, &[0, 1, 2]), 0);
+            assert_eq!(get_cpu_epp(0).unwrap(), "performance");
+
+            // Dropping the boost and raising it again finds nothing to write
+            assert_eq!(manager.update(false, &[0, 1, 2]), 0);
+            assert_eq!(manager.update(true, &[0, 1, 2]), 0);
+            assert_eq!(manager.writes, 2);
+
+            manager.restore_original();
+            assert_eq!(get_cpu_epp(1).unwrap(), "balance_performance");
+        });
+    }
+
+    #[test]
+    fn test_unavailable_epp_falls_back() {
+        let available =
+            |epps: &[&str]| -> Vec<String> { epps.iter().map(|epp| epp.to_string()).collect() };
+        // No "performance": the next most performance-oriented value
+        let limited = available(&["default", "balance_performance", "power"]);
+        assert_eq!(
+            closest_epp("performance", &limited),
+            Some("balance_performance")
+        );
+        assert_eq!(closest_epp("power", &limited), Some("power"));
+        // balance_power sits between default and power; the tie goes to default
+        assert_eq!(closest_epp("balance_power", &limited), Some("default"));
+        assert_eq!(closest_epp("performance", &available(&["turbo"])), None);
+
+        let fixture = procfs::Fixture::new("epp-available");
+        let policy = "/sys/devices/system/cpu/cpufreq/policy0";
+        fixture
+            .file(
+                &format!("{}/energy_performance_preference", policy),
+                "default\n",
+            )
+            .file(
+                &format!("{}/energy_performance_available_preferences", policy),
+                "default balance_performance balance_power power \n",
+            );
+        fixture.run(|| {
+            let mut manager = EppManager::new(1);
+            manager.save_original(1);
+            assert_eq!(manager.update(true, &[0]), 1);
+            assert_eq!(get_cpu_epp(0).unwrap(), "balance_performance");
+            assert!(manager.warned_epp.contains("performance"));
+            assert!(manager.set_epp(0, "turbo").is_ok());
+            assert_eq!(get_cpu_epp(0).unwrap(), "balance_performance");
+            manager.restore_original();
+        });
+    }
+
+    #[test]
+    fn test_epp_state_kept_until_restore() {
+        let fixture = procfs::Fixture::new("epp-state-kept");
+        fixture.file(
+            "/sys/devices/system/cpu/cpufreq/policy0/energy_performance_preference",
+            "balance_performance\n",
+        );
+        let state = fixture.paths.join("epp.state");
+
+        fixture.run(|| {
+            let mut manager = EppManager::new(1);
+            manager.save_original(1);
+            manager.persist_state(&state).unwrap();
+            assert_eq!(manager.update(true, &[0]), 1);
+
+            // Restoring from the file (next start after a SIGKILL) keeps it
+            assert_eq!(restore_epp_state(&state).unwrap(), 1);
+            assert_eq!(get_cpu_epp(0).unwrap(), "balance_performance");
+            assert!(state.exists());
+
+            manager.restore_original();
+            assert!(!state.exists());
+        });
+    }
+
+    #[test]
+    fn test_epp_state_kept_on_failed_restore() {
+        let fixture = procfs::Fixture::new("epp-state-failed-restore");
+        fixture.file(
+            "/sys/devices/system/cpu/cpufreq/policy0/energy_performance_preference",
+            "balance_performance\n",
+        );
+        let state = fixture.paths.join("epp.state");
+
+        fixture.run(|| {
+            let mut manager = EppManager::new(1);
+            manager.save_original(1);
+            manager.persist_state(&state).unwrap();
+            assert_eq!(manager.update(true, &[0]), 1);
+
+            // CPU went away: the write fails, so the snapshot must survive
+            fs::remove_dir_all(fixture.paths.join("sys/devices/system/cpu/cpufreq/policy0"))
+                .unwrap();
+            manager.restore_original();
+            assert!(state.exists());
+        });
+    }
+
+    #[test]
+    fn test_epp_dry_run() {
+        let fixture = procfs::Fixture::new("epp-dry-run");
+        let path = "/sys/devices/system/cpu/cpufreq/policy0/energy_performance_preference";
+        fixture.file(path, "balance_performance\n");
+
+        fixture.run(|| {
+            let mut manager = EppManager::new(1);
+            manager.set_dry_run(true);
+            manager.save_original(1);
+
+            // The intended value is tracked so the boost logic proceeds...
+            assert_eq!(manager.update(true, &[0]), 1);
+            assert_eq!(manager.current_epp[0].as_deref(), Some("performance"));
+            assert_eq!(manager.update(true, &[0]), 0);
+            // ...but nothing reached sysfs and there is nothing to restore
+            assert!(!manager.modified);
+            assert!(!manager.release());
+            assert_eq!(
+                manager.current_epp[0].as_deref(),
+                Some("balance_performance")
+            );
+        });
+        assert_eq!(
+            fs::read_to_string(fixture.paths.join(path)).unwrap(),
+            "balance_performance\n"
+        );
+    }
+
+    #[test]
+    fn test_epp_state_roundtrip() {
+        let original = vec![
+            Some("balance_performance".to_string()),
+            None,
+            Some("power".to_string()),
+            Some("performance".to_string()),
+        ];
+
+        let path = std::env::temp_dir().join(format!("ghostbrew-epp-{}.state", std::process::id()));
+        fs::write(&path, format_epp_state(&original)).unwrap();
+        let parsed = parse_epp_state(&fs::read_to_string(&path).unwrap());
+        fs::remove_file(&path).ok();
+
+        assert_eq!(
+            parsed,
+            vec![
+                (0, "balance_performance".to_string()),
+                (2, "power".to_string()),
+                (3, "performance".to_string()),
+            ]
+        );
+        assert!(parse_epp_state("garbage\n5=\n").is_empty());
+    }
+
     #[test]
     fn test_detect_prefcore() {
         // This just tests basic functionality
         let result = detect_prefcore(32);
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_detect_prefcore_from_fixture() {
+        let fixture = procfs::Fixture::new("pbo");
+        fixture
+            .file("/sys/devices/system/cpu/amd_pstate/prefcore", "enabled\n")
+            .file("/sys/devices/system/cpu/amd_pstate/status", "active\n");
+        for (cpu, ranking) in [166, 236, 236, 196].iter().enumerate() {
+            fixture.file(
+                &format!(
+                    "/sys/devices/system/cpu/cpufreq/policy{}/amd_pstate_prefcore_ranking",
+                    cpu
+                ),
+                &format!("{}\n", ranking),
+            );
+        }
+
+        let info = fixture.run(|| detect_prefcore(4)).unwrap();
+        assert!(info.enabled);
+        assert_eq!(info.rankings, vec![166, 236, 236, 196]);
+        assert_eq!(info.max_ranking, 236);
+        assert_eq!(info.preferred_cpus, vec![1, 2]);
+        assert_eq!(fixture.run(detect_pstate_mode), PstateMode::Active);
+    }
 }
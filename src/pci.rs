@@ -0,0 +1,64 @@
+// SPDX-License-Identifier: GPL-2.0
+//
+// GhostBrew - PCI Addresses
+//
+// sysfs, /proc/driver/nvidia and QEMU's vfio-pci host= option spell the same
+// device differently (with or without the domain, in either case). Every PCI
+// address GhostBrew stores goes through normalize() so GPUs found on the host
+// and devices passed through to VMs compare equal.
+//
+// Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
+
+/// Canonical `DDDD:BB:DD.F` form of a PCI address, None if it is not one
+///
+/// The domain defaults to 0000 when omitted.
+pub fn parse(addr: &str) -> Option<String> {
+    let addr = addr.trim();
+    let (rest, function) = addr.rsplit_once('.')?;
+    let mut parts: Vec<&str> = rest.split(':').collect();
+    if parts.len() == 2 {
+        parts.insert(0, "0");
+    }
+    let [domain, bus, device] = parts[..] else {
+        return None;
+    };
+
+    let hex = |field: &str, max_len: usize| {
+        (!field.is_empty() && field.len() <= max_len)
+            .then(|| u32::from_str_radix(field, 16).ok())
+            .flatten()
+    };
+    let domain = hex(domain, 4)?;
+    let bus = hex(bus, 2)?;
+    let device = hex(device, 2).filter(|&d| d < 32)?;
+    let function = hex(function, 1).filter(|&f| f < 8)?;
+    Some(format!(
+        "{:04x}:{:02x}:{:02x}.{:x}",
+        domain, bus, device, function
+    ))
+}
+
+/// Canonical form of a PCI address; anything unparsable is only trimmed and
+/// lowercased
+pub fn normalize(addr: &str) -> String {
+    parse(addr).unwrap_or_else(|| addr.trim().to_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("01:00.0"), "0000:01:00.0");
+        assert_eq!(normalize("0000:01:00.0"), "0000:01:00.0");
+        assert_eq!(normalize("0000:01:00.0\n"), "0000:01:00.0");
+        assert_eq!(normalize("0000:0A:00.1"), "0000:0a:00.1");
+        assert_eq!(normalize("1:0.0"), "0000:01:00.0");
+
+        assert_eq!(parse("0000:01:20.0"), None);
+        assert_eq!(parse("0000:01:00.8"), None);
+        assert_eq!(parse("card0"), None);
+        assert_eq!(normalize(" Card0 "), "card0");
+    }
+}
@@ -0,0 +1,155 @@
+// SPDX-License-Identifier: GPL-2.0
+//
+// GhostBrew - Per-Game Scheduler Stats (--per-pid-stats)
+//
+// The BPF side sums runtime, migrations and preemptions over all threads of
+// each detected game in the pid_stats map. This decodes those entries and
+// joins them with the comm the gaming detector saw when it classified the PID.
+//
+// Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
+
+use crate::gaming::GamingDetector;
+use std::collections::HashSet;
+
+/// Size of `struct pid_stats` in the BPF program
+pub const PID_STATS_SIZE: usize = 24;
+
+/// Userspace view of the BPF pid_stats map value
+///
+/// Layout: u64 runtime_ns, u64 migrations, u64 preemptions
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PidStats {
+    pub runtime_ns: u64,
+    pub migrations: u64,
+    pub preemptions: u64,
+}
+
+impl PidStats {
+    /// Decode a map value; short values leave the missing fields at 0
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let mut value = [0u8; PID_STATS_SIZE];
+        let len = bytes.len().min(PID_STATS_SIZE);
+        value[..len].copy_from_slice(&bytes[..len]);
+
+        Self {
+            runtime_ns: u64::from_ne_bytes(value[0..8].try_into().unwrap_or([0; 8])),
+            migrations: u64::from_ne_bytes(value[8..16].try_into().unwrap_or([0; 8])),
+            preemptions: u64::from_ne_bytes(value[16..24].try_into().unwrap_or([0; 8])),
+        }
+    }
+}
+
+/// Stats of one detected game
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameStats {
+    pub pid: u32,
+    pub comm: String,
+    pub stats: PidStats,
+}
+
+impl GameStats {
+    /// One line of --stats output
+    pub fn summary(&self) -> String {
+        format!(
+            "{} (PID {}): run {:.1}s, migrations {}, preemptions {}",
+            self.comm,
+            self.pid,
+            self.stats.runtime_ns as f64 / 1e9,
+            self.stats.migrations,
+            self.stats.preemptions
+        )
+    }
+}
+
+/// Join pid_stats entries with the detected games, busiest first
+///
+/// Entries for PIDs the detector no longer tracks (the game exited since the
+/// last scan) are dropped.
+pub fn join(
+    entries: impl IntoIterator<Item = (u32, PidStats)>,
+    detector: &GamingDetector,
+) -> Vec<GameStats> {
+    let gaming: HashSet<u32> = detector.gaming_pids().collect();
+    let mut games: Vec<GameStats> = entries
+        .into_iter()
+        .filter(|(pid, _)| gaming.contains(pid))
+        .map(|(pid, stats)| GameStats {
+            pid,
+            comm: detector.comm(pid).unwrap_or("?").to_string(),
+            stats,
+        })
+        .collect();
+    games.sort_by(|a, b| {
+        b.stats
+            .runtime_ns
+            .cmp(&a.stats.runtime_ns)
+            .then(a.pid.cmp(&b.pid))
+    });
+    games
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::procfs;
+
+    #[test]
+    fn test_join_pid_stats() {
+        let fixture = procfs::Fixture::new("pidstats");
+        for (pid, comm, exe) in [
+            (101, "Game.exe", "/games/steamapps/common/Game/Game.exe"),
+            (
+                102,
+                "eldenring.exe",
+                "/games/steamapps/common/ELDEN RING/eldenring.exe",
+            ),
+            (103, "bash", "/usr/bin/bash"),
+        ] {
+            fixture
+                .file(&format!("/proc/{}/comm", pid), &format!("{}\n", comm))
+                .file(&format!("/proc/{}/environ", pid), "HOME=\0")
+                .file(
+                    &format!("/proc/{}/stat", pid),
+                    &format!(
+                        "{} ({}) S 1 1 1 0 -1 4194560 0 0 0 0 0 0 0 0 20 0 1 0 {} 0",
+                        pid, comm, pid
+                    ),
+                )
+                .symlink(&format!("/proc/{}/exe", pid), exe);
+        }
+        let mut detector = GamingDetector::new();
+        fixture.run(|| detector.scan_changes()).unwrap();
+
+        let stats = |runtime_ns, migrations, preemptions| PidStats {
+            runtime_ns,
+            migrations,
+            preemptions,
+        };
+        let entries = [
+            (101, stats(1_500_000_000, 12, 40)),
+            (102, stats(9_000_000_000, 3, 7)),
+            // Stale entry for a game that already exited
+            (999, stats(5_000_000_000, 1, 1)),
+        ];
+
+        let games = join(entries, &detector);
+        assert_eq!(
+            games
+                .iter()
+                .map(|g| (g.pid, g.comm.as_str()))
+                .collect::<Vec<_>>(),
+            [(102, "eldenring.exe"), (101, "Game.exe")]
+        );
+        assert_eq!(
+            games[1].summary(),
+            "Game.exe (PID 101): run 1.5s, migrations 12, preemptions 40"
+        );
+
+        let mut bytes = Vec::new();
+        for field in [7u64, 8, 9] {
+            bytes.extend_from_slice(&field.to_ne_bytes());
+        }
+        assert_eq!(PidStats::from_bytes(&bytes), stats(7, 8, 9));
+        assert_eq!(PidStats::from_bytes(&bytes[..8]), stats(7, 0, 0));
+    }
+}
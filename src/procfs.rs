@@ -0,0 +1,336 @@
+// SPDX-License-Identifier: GPL-2.0
+//
+// GhostBrew - /proc Read Helpers
+//
+// Processes routinely exit between a /proc scan and the reads that follow.
+// These helpers report that as a quiet "vanished" outcome, separate from
+// permission and IO errors that are worth logging.
+//
+// Detection reads /proc and /sys through `rooted`, which resolves them under
+// the thread's `Paths` root: "/" normally, a captured snapshot under
+// --simulate, a fixture tree in tests.
+//
+// Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
+
+use log::debug;
+use std::cell::RefCell;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Root directory absolute /proc and /sys paths resolve under
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Paths {
+    root: PathBuf,
+}
+
+impl Default for Paths {
+    /// The live system
+    fn default() -> Self {
+        Self::new("/")
+    }
+}
+
+impl Paths {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// An absolute path under this root
+    pub fn join(&self, path: impl AsRef<Path>) -> PathBuf {
+        let path = path.as_ref();
+        self.root.join(path.strip_prefix("/").unwrap_or(path))
+    }
+}
+
+thread_local! {
+    /// Root for detection reads on this thread
+    static PATHS: RefCell<Paths> = RefCell::new(Paths::default());
+}
+
+/// Run `f` with this thread's detection reads resolved under `paths`
+pub fn with_paths<T>(paths: &Paths, f: impl FnOnce() -> T) -> T {
+    let previous = PATHS.replace(paths.clone());
+    let result = f();
+    PATHS.set(previous);
+    result
+}
+
+/// An absolute /proc or /sys path under the current root
+pub fn rooted(path: impl AsRef<Path>) -> PathBuf {
+    PATHS.with_borrow(|paths| paths.join(path))
+}
+
+/// fs::read_to_string under the current root
+pub fn read_rooted(path: impl AsRef<Path>) -> io::Result<String> {
+    fs::read_to_string(rooted(path))
+}
+
+/// Temporary /proc and /sys tree for tests, removed on drop
+#[cfg(test)]
+pub struct Fixture {
+    root: PathBuf,
+    pub paths: Paths,
+}
+
+#[cfg(test)]
+impl Fixture {
+    /// Empty tree; `name` keeps concurrent tests apart
+    pub fn new(name: &str) -> Self {
+        let root =
+            std::env::temp_dir().join(format!("ghostbrew-fixture-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        Self {
+            paths: Paths::new(&root),
+            root,
+        }
+    }
+
+    /// Write `contents` at an absolute path inside the tree
+    pub fn file(&self, path: &str, contents: &str) -> &Self {
+        let path = self.paths.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+        self
+    }
+
+    /// Create a symlink at an absolute path inside the tree
+    pub fn symlink(&self, path: &str, target: &str) -> &Self {
+        let path = self.paths.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::os::unix::fs::symlink(target, path).unwrap();
+        self
+    }
+
+    /// Run `f` with detection reads resolved inside the tree
+    pub fn run<T>(&self, f: impl FnOnce() -> T) -> T {
+        with_paths(&self.paths, f)
+    }
+}
+
+#[cfg(test)]
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+/// Outcome of reading a per-process /proc file
+#[derive(Debug)]
+pub enum ProcReadOutcome<T> {
+    /// File read successfully
+    Read(T),
+    /// Process exited (or the file went away) before the read
+    Vanished,
+    /// Genuine failure (permission, IO)
+    Failed(io::Error),
+}
+
+impl<T> ProcReadOutcome<T> {
+    /// Classify an io::Result, treating ENOENT and ESRCH as a vanished process
+    pub fn from_result(result: io::Result<T>) -> Self {
+        match result {
+            Ok(value) => Self::Read(value),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Self::Vanished,
+            Err(e) if e.raw_os_error() == Some(libc::ESRCH) => Self::Vanished,
+            Err(e) => Self::Failed(e),
+        }
+    }
+
+    /// The value if read; vanishes are silent, failures are logged at debug
+    pub fn ok(self, path: &Path) -> Option<T> {
+        match self {
+            Self::Read(value) => Some(value),
+            Self::Vanished => None,
+            Self::Failed(e) => {
+                debug!("Failed to read {:?}: {}", path, e);
+                None
+            }
+        }
+    }
+}
+
+/// Read a /proc file as a string
+pub fn read_to_string(path: impl AsRef<Path>) -> ProcReadOutcome<String> {
+    ProcReadOutcome::from_result(fs::read_to_string(path))
+}
+
+/// Read a /proc symlink such as /proc/[pid]/exe
+pub fn read_link(path: impl AsRef<Path>) -> ProcReadOutcome<PathBuf> {
+    ProcReadOutcome::from_result(fs::read_link(path))
+}
+
+/// Read /proc/[pid]/<file> as a string, None if the process is gone
+pub fn read_pid_file(pid: u32, file: &str) -> Option<String> {
+    let path = rooted(format!("/proc/{}/{}", pid, file));
+    read_to_string(&path).ok(&path)
+}
+
+/// Resolve /proc/[pid]/exe, None if the process is gone
+pub fn read_pid_exe(pid: u32) -> Option<PathBuf> {
+    let path = rooted(format!("/proc/{}/exe", pid));
+    read_link(&path).ok(&path)
+}
+
+/// Longest name /proc/[pid]/comm holds (TASK_COMM_LEN - 1)
+pub const COMM_MAX_LEN: usize = 15;
+
+/// Untruncated process name from comm and the raw cmdline
+///
+/// A comm of exactly COMM_MAX_LEN bytes was probably cut off; the basename
+/// of argv[0] replaces it when it extends the comm, so a renamed process
+/// (prctl PR_SET_NAME) keeps its chosen name.
+pub fn full_comm(comm: &str, cmdline: Option<&str>) -> String {
+    let comm = comm.trim();
+    if comm.len() != COMM_MAX_LEN {
+        return comm.to_string();
+    }
+    cmdline
+        .and_then(|cmdline| cmdline.split('\0').next())
+        .map(|argv0| argv0.rsplit(['/', '\\']).next().unwrap_or(argv0))
+        .filter(|name| name.len() > comm.len() && name.starts_with(comm))
+        .unwrap_or(comm)
+        .to_string()
+}
+
+/// Process name with truncation undone, None if the process is gone
+pub fn read_full_comm(pid: u32) -> Option<String> {
+    let comm = read_pid_file(pid, "comm")?;
+    if comm.trim().len() != COMM_MAX_LEN {
+        return Some(comm.trim().to_string());
+    }
+    Some(full_comm(&comm, read_pid_file(pid, "cmdline").as_deref()))
+}
+
+/// Whether a process or thread still exists
+pub fn pid_alive(pid: u32) -> bool {
+    rooted(format!("/proc/{}", pid)).exists()
+}
+
+/// Field `n` (1-based, as in proc(5)) of a /proc/[pid]/stat line
+///
+/// The comm field can contain spaces and parentheses, so fields are counted
+/// from the last ')'.
+fn stat_field(stat: &str, n: usize) -> Option<&str> {
+    let (_, rest) = stat.rsplit_once(')')?;
+    // rest starts at field 3 (state)
+    rest.split_whitespace().nth(n.checked_sub(3)?)
+}
+
+/// Parent PID (field 4) from a /proc/[pid]/stat line
+pub fn parse_stat_ppid(stat: &str) -> Option<u32> {
+    stat_field(stat, 4)?.parse().ok()
+}
+
+/// Scheduling policy (field 41) from a /proc/[pid]/stat line
+pub fn parse_stat_policy(stat: &str) -> Option<u32> {
+    stat_field(stat, 41)?.parse().ok()
+}
+
+/// CPU the task last ran on (field 39) from a /proc/[pid]/stat line
+pub fn parse_stat_processor(stat: &str) -> Option<u32> {
+    stat_field(stat, 39)?.parse().ok()
+}
+
+/// Start time (field 22, clock ticks since boot) from a /proc/[pid]/stat line
+///
+/// Together with the PID it identifies a process: a reused PID gets a new
+/// start time.
+pub fn parse_stat_starttime(stat: &str) -> Option<u64> {
+    stat_field(stat, 22)?.parse().ok()
+}
+
+/// System uptime from /proc/uptime
+pub fn read_uptime() -> Option<Duration> {
+    let uptime = read_rooted("/proc/uptime").ok()?;
+    let secs: f64 = uptime.split_whitespace().next()?.parse().ok()?;
+    Duration::try_from_secs_f64(secs).ok()
+}
+
+/// Clock ticks per second that stat start times are counted in
+pub fn clock_ticks() -> u64 {
+    // SAFETY: sysconf only reads a configuration value
+    let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    if ticks > 0 { ticks as u64 } else { 100 }
+}
+
+/// How long a process has existed, from its stat start time and the uptime
+pub fn process_age(starttime: u64, uptime: Duration, ticks: u64) -> Duration {
+    let started = Duration::from_secs_f64(starttime as f64 / ticks.max(1) as f64);
+    uptime.saturating_sub(started)
+}
+
+/// Whether a policy is real-time (SCHED_FIFO/SCHED_RR); such tasks run in
+/// the kernel's RT class and never reach sched_ext
+pub fn is_rt_policy(policy: u32) -> bool {
+    policy == libc::SCHED_FIFO as u32 || policy == libc::SCHED_RR as u32
+}
+
+/// Name of a real-time policy for log messages
+pub fn rt_policy_name(policy: u32) -> &'static str {
+    if policy == libc::SCHED_FIFO as u32 {
+        "SCHED_FIFO"
+    } else {
+        "SCHED_RR"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vanished_is_not_an_error() {
+        let gone = io::Error::from(io::ErrorKind::NotFound);
+        assert!(matches!(
+            ProcReadOutcome::<String>::from_result(Err(gone)),
+            ProcReadOutcome::Vanished
+        ));
+
+        let esrch = io::Error::from_raw_os_error(libc::ESRCH);
+        assert!(matches!(
+            ProcReadOutcome::<String>::from_result(Err(esrch)),
+            ProcReadOutcome::Vanished
+        ));
+
+        let denied = io::Error::from(io::ErrorKind::PermissionDenied);
+        assert!(matches!(
+            ProcReadOutcome::<String>::from_result(Err(denied)),
+            ProcReadOutcome::Failed(_)
+        ));
+
+        // A missing PID reads as vanished on a real /proc
+        assert!(matches!(
+            read_to_string("/proc/4294967295/comm"),
+            ProcReadOutcome::Vanished
+        ));
+    }
+
+    #[test]
+    fn test_parse_stat_policy() {
+        // PipeWire data thread: rt_priority 88, SCHED_FIFO
+        let stat = "2345 (data-loop.0) S 1 2345 2345 0 -1 1077936448 2816 0 0 0 \
+                    120 345 0 0 -89 0 4 0 1234 123456789 2048 18446744073709551615 \
+                    1 1 0 0 0 0 0 4096 0 0 0 0 17 3 88 1 0 0 0 0 0 0 0 0 0 0 0";
+        let policy = parse_stat_policy(stat).unwrap();
+        assert_eq!(policy, libc::SCHED_FIFO as u32);
+        assert_eq!(parse_stat_starttime(stat), Some(1234));
+        assert!(is_rt_policy(policy));
+        assert_eq!(rt_policy_name(policy), "SCHED_FIFO");
+
+        // A comm with spaces and ')' doesn't shift the fields; SCHED_OTHER
+        let stat = stat
+            .replace("(data-loop.0)", "(Game (Main) 1)")
+            .replace(" 88 1 ", " 0 0 ");
+        let policy = parse_stat_policy(&stat).unwrap();
+        assert_eq!(policy, 0);
+        assert!(!is_rt_policy(policy));
+        assert!(is_rt_policy(libc::SCHED_RR as u32));
+        // SCHED_BATCH/SCHED_IDLE/SCHED_EXT are ours to schedule
+        assert!(!is_rt_policy(3) && !is_rt_policy(5) && !is_rt_policy(7));
+
+        assert!(parse_stat_policy("2345 (truncated) S 1").is_none());
+    }
+}
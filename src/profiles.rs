@@ -6,12 +6,12 @@
 //
 // Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use log::{debug, info, warn};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// Game profile configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,7 +27,7 @@ pub struct GameProfile {
     #[serde(default)]
     pub steam_appid: Option<u32>,
 
-    /// Comm name pattern (regex-like matching)
+    /// Substring of the process comm (plain text, not a regex or glob)
     #[serde(default)]
     pub comm_pattern: Option<String>,
 
@@ -53,6 +53,68 @@ pub struct GameProfile {
     pub cpu_affinity: Option<Vec<u32>>,
 }
 
+impl GameProfile {
+    /// Check semantic constraints that TOML parsing alone does not catch
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if self.name.trim().is_empty() {
+            problems.push("name is empty".to_string());
+        }
+
+        if self.exe_name.is_none() && self.steam_appid.is_none() && self.comm_pattern.is_none() {
+            problems.push("no matcher (need exe_name, steam_appid or comm_pattern)".to_string());
+        }
+
+        if self.exe_name.as_ref().is_some_and(|e| e.trim().is_empty()) {
+            problems.push("exe_name is empty".to_string());
+        }
+
+        if let Some(ref pattern) = self.comm_pattern
+            && let Err(e) = check_pattern(pattern)
+        {
+            problems.push(format!("invalid comm_pattern '{}': {}", pattern, e));
+        }
+
+        if self.tunables.burst_threshold_ns == Some(0) {
+            problems.push("tunables.burst_threshold_ns must be > 0".to_string());
+        }
+        if self.tunables.slice_ns == Some(0) {
+            problems.push("tunables.slice_ns must be > 0".to_string());
+        }
+
+        problems
+    }
+}
+
+/// Check a comm_pattern: a plain substring that fits in a task comm
+fn check_pattern(pattern: &str) -> std::result::Result<(), String> {
+    if pattern.is_empty() {
+        return Err("empty pattern".to_string());
+    }
+    // comm is at most TASK_COMM_LEN - 1 bytes
+    if pattern.len() > 15 {
+        return Err("longer than a process comm (15 bytes)".to_string());
+    }
+    Ok(())
+}
+
+/// Validation result for one profile file
+#[derive(Debug)]
+pub struct ProfileValidation {
+    pub path: PathBuf,
+    /// Profile name (if the file parsed)
+    pub name: Option<String>,
+    /// Parse error or semantic problems (empty = valid)
+    pub problems: Vec<String>,
+}
+
+impl ProfileValidation {
+    pub fn is_valid(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
 /// Per-profile scheduling tunables
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ProfileTunables {
@@ -64,7 +126,8 @@ pub struct ProfileTunables {
     #[serde(default)]
     pub slice_ns: Option<u64>,
 
-    /// Priority boost for main game thread
+    /// Priority boost for the game's threads (0-4, clamped); each step
+    /// halves their vtime outside the gaming tier
     #[serde(default)]
     pub priority_boost: Option<i32>,
 
@@ -77,6 +140,18 @@ pub struct ProfileTunables {
     pub work_mode: Option<bool>,
 }
 
+/// Largest priority_boost the BPF side applies
+pub const PRIORITY_BOOST_MAX: u32 = 4;
+
+impl ProfileTunables {
+    /// priority_boost clamped to 0..=PRIORITY_BOOST_MAX, the value for the
+    /// BPF priority_boosts map (0 = no entry)
+    pub fn bpf_priority_boost(&self) -> u32 {
+        self.priority_boost
+            .map_or(0, |boost| boost.clamp(0, PRIORITY_BOOST_MAX as i32) as u32)
+    }
+}
+
 /// V-Cache preference for AMD X3D processors
 #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
 #[serde(rename_all = "lowercase")]
@@ -103,6 +178,17 @@ pub enum SmtPreference {
     AllowShared,
 }
 
+impl SmtPreference {
+    /// SMT_PREF_* value in the BPF smt_prefs map
+    pub fn bpf_value(&self) -> u32 {
+        match self {
+            SmtPreference::Auto => 0,
+            SmtPreference::PreferIdle => 1,
+            SmtPreference::AllowShared => 2,
+        }
+    }
+}
+
 /// NUMA node preference for multi-socket/multi-CCD systems
 #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
 #[serde(rename_all = "snake_case")]
@@ -120,6 +206,13 @@ pub enum NumaPreference {
     Spread,
 }
 
+/// Merged profile file: one `[[profile]]` table per game
+#[derive(Debug, Deserialize)]
+struct ProfileDocument {
+    #[serde(default)]
+    profile: Vec<GameProfile>,
+}
+
 /// Profile manager handles loading and matching game profiles
 pub struct ProfileManager {
     profiles: HashMap<String, GameProfile>,
@@ -153,11 +246,11 @@ impl ProfileManager {
             let path = entry.path();
 
             if path.extension().is_some_and(|e| e == "toml") {
-                match self.load_profile_file(&path) {
-                    Ok(profile) => {
-                        self.add_profile(profile);
-                        count += 1;
-                    }
+                match self
+                    .load_profile_file(&path)
+                    .and_then(|profile| self.add_unique_profile(profile))
+                {
+                    Ok(()) => count += 1,
                     Err(e) => {
                         warn!("Failed to load profile {:?}: {}", path, e);
                     }
@@ -169,27 +262,77 @@ impl ProfileManager {
         Ok(count)
     }
 
+    /// Load every `[[profile]]` entry from a single merged TOML file
+    ///
+    /// Entries whose name is already loaded (from this file or a profiles
+    /// directory) are skipped with a warning.
+    pub fn load_from_file(&mut self, path: &Path) -> Result<usize> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read profiles file: {:?}", path))?;
+        let profiles = parse_profile_document(&content)
+            .with_context(|| format!("Failed to parse profiles file: {:?}", path))?;
+
+        let mut count = 0;
+        for profile in profiles {
+            match self.add_unique_profile(profile) {
+                Ok(()) => count += 1,
+                Err(e) => warn!("Skipping profile in {:?}: {}", path, e),
+            }
+        }
+
+        info!("Loaded {} game profiles from {:?}", count, path);
+        Ok(count)
+    }
+
     /// Load profiles from standard paths
     pub fn load_standard_paths(&mut self) -> Result<usize> {
-        let paths = vec![
-            PathBuf::from("/etc/ghostbrew/profiles"),
-            dirs::config_dir()
-                .map(|p| p.join("ghostbrew/profiles"))
-                .unwrap_or_default(),
-        ];
+        let config_dir = dirs::config_dir().map(|p| p.join("ghostbrew"));
+        let bases = [Some(PathBuf::from("/etc/ghostbrew")), config_dir];
 
         let mut total = 0;
-        for path in paths {
-            if path.exists() {
-                total += self.load_from_directory(&path)?;
+        for base in bases.into_iter().flatten() {
+            let dir = base.join("profiles");
+            if dir.exists() {
+                total += self.load_from_directory(&dir)?;
+            }
+            let file = base.join("profiles.toml");
+            if file.exists() {
+                total += self.load_from_file(&file)?;
             }
         }
 
         Ok(total)
     }
 
+    /// Validate every profile in a directory without loading it
+    pub fn validate_directory(&self, dir: &Path) -> Result<Vec<ProfileValidation>> {
+        let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+            .with_context(|| format!("Failed to read {:?}", dir))?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|e| e == "toml"))
+            .collect();
+        paths.sort();
+
+        Ok(paths
+            .into_iter()
+            .map(|path| match self.load_profile_file(&path) {
+                Ok(profile) => ProfileValidation {
+                    problems: profile.validate(),
+                    name: Some(profile.name),
+                    path,
+                },
+                Err(e) => ProfileValidation {
+                    problems: vec![format!("{:#}", e)],
+                    name: None,
+                    path,
+                },
+            })
+            .collect())
+    }
+
     /// Load a single profile from a TOML file
-    fn load_profile_file(&self, path: &PathBuf) -> Result<GameProfile> {
+    fn load_profile_file(&self, path: &Path) -> Result<GameProfile> {
         let content = fs::read_to_string(path)
             .with_context(|| format!("Failed to read profile: {:?}", path))?;
 
@@ -200,8 +343,17 @@ impl ProfileManager {
         Ok(profile)
     }
 
+    /// Add a profile, rejecting a name that is already loaded
+    fn add_unique_profile(&mut self, profile: GameProfile) -> Result<()> {
+        if self.profiles.contains_key(&profile.name) {
+            bail!("duplicate profile name '{}'", profile.name);
+        }
+        self.add_profile(profile);
+        Ok(())
+    }
+
     /// Add a profile to the manager
-    fn add_profile(&mut self, profile: GameProfile) {
+    pub fn add_profile(&mut self, profile: GameProfile) {
         let name = profile.name.clone();
 
         // Index by exe name (lowercase for case-insensitive matching)
@@ -259,6 +411,127 @@ impl Default for ProfileManager {
     }
 }
 
+/// Parse a merged profile document into its `[[profile]]` entries
+fn parse_profile_document(content: &str) -> Result<Vec<GameProfile>> {
+    let document: ProfileDocument = toml::from_str(content)?;
+    Ok(document.profile)
+}
+
+/// Steam library installed with the native client
+pub fn default_steamapps_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".steam/steam/steamapps"))
+}
+
+/// System-wide profile directory, always loaded by the scheduler
+pub const SYSTEM_PROFILES_DIR: &str = "/etc/ghostbrew/profiles";
+
+/// Per-user profile directory
+pub fn user_profiles_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|p| p.join("ghostbrew/profiles"))
+}
+
+/// Steam tools that ship an appmanifest but are not games
+const STEAM_TOOL_PREFIXES: &[&str] = &[
+    "Proton",
+    "Steam Linux Runtime",
+    "Steamworks Common Redistributables",
+];
+
+/// Extract the App ID and name from an `appmanifest_*.acf` file
+///
+/// ACF is Valve's KeyValues format: quoted key/value pairs, one per line,
+/// nested in braces. Only the first `appid` and `name` are used.
+pub fn parse_appmanifest(content: &str) -> Option<(u32, String)> {
+    let mut appid = None;
+    let mut name = None;
+
+    for line in content.lines() {
+        let fields: Vec<&str> = line.split('"').skip(1).step_by(2).collect();
+        let [key, value] = fields[..] else {
+            continue;
+        };
+        if key.eq_ignore_ascii_case("appid") && appid.is_none() {
+            appid = value.parse().ok();
+        } else if key.eq_ignore_ascii_case("name") && name.is_none() && !value.trim().is_empty() {
+            name = Some(value.to_string());
+        }
+    }
+
+    Some((appid?, name?))
+}
+
+/// Write a default profile for every installed Steam game
+///
+/// Each game gets `steam_<appid>.toml` in `profile_dir` with its name, App ID
+/// and a V-Cache preference of `cache`. Games that already have a profile
+/// there (by App ID, name or file) are skipped. Returns the number written.
+pub fn import_from_steam(steamapps: &Path, profile_dir: &Path) -> Result<usize> {
+    let mut manifests: Vec<PathBuf> = fs::read_dir(steamapps)
+        .with_context(|| format!("Failed to read {:?}", steamapps))?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|f| f.to_str())
+                .is_some_and(|f| f.starts_with("appmanifest_") && f.ends_with(".acf"))
+        })
+        .collect();
+    manifests.sort();
+
+    let mut existing = ProfileManager::new();
+    existing.load_from_directory(&profile_dir.to_path_buf())?;
+
+    fs::create_dir_all(profile_dir)
+        .with_context(|| format!("Failed to create {:?}", profile_dir))?;
+
+    let mut count = 0;
+    for manifest in manifests {
+        let content = match fs::read_to_string(&manifest) {
+            Ok(content) => content,
+            Err(e) => {
+                warn!("Failed to read {:?}: {}", manifest, e);
+                continue;
+            }
+        };
+        let Some((appid, name)) = parse_appmanifest(&content) else {
+            warn!("No appid/name in {:?}", manifest);
+            continue;
+        };
+        if STEAM_TOOL_PREFIXES.iter().any(|p| name.starts_with(p)) {
+            debug!("Skipping Steam tool {} ({})", name, appid);
+            continue;
+        }
+
+        let path = profile_dir.join(format!("steam_{}.toml", appid));
+        if path.exists()
+            || existing.by_appid.contains_key(&appid)
+            || existing.profiles.contains_key(&name)
+        {
+            debug!("Profile for {} ({}) already exists", name, appid);
+            continue;
+        }
+
+        let profile = GameProfile {
+            name,
+            exe_name: None,
+            steam_appid: Some(appid),
+            comm_pattern: None,
+            tunables: ProfileTunables::default(),
+            vcache_preference: VCachePreference::Cache,
+            smt_preference: SmtPreference::default(),
+            numa_preference: NumaPreference::default(),
+            cpu_affinity: None,
+        };
+        fs::write(&path, toml::to_string_pretty(&profile)?)
+            .with_context(|| format!("Failed to write {:?}", path))?;
+        info!("Imported {} ({}) to {:?}", profile.name, appid, path);
+        existing.add_profile(profile);
+        count += 1;
+    }
+
+    Ok(count)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -286,6 +559,84 @@ work_mode = true
         assert_eq!(profile.smt_preference, SmtPreference::PreferIdle);
     }
 
+    #[test]
+    fn test_smt_preference_bpf_value() {
+        let parse = |pref: &str| {
+            toml::from_str::<GameProfile>(&format!(
+                "name = \"Test\"\nsmt_preference = \"{}\"\n",
+                pref
+            ))
+            .unwrap()
+            .smt_preference
+            .bpf_value()
+        };
+        assert_eq!(parse("auto"), 0);
+        assert_eq!(parse("prefer_idle"), 1);
+        assert_eq!(parse("allow_shared"), 2);
+
+        // Profiles without the key keep the built-in behaviour
+        let profile: GameProfile = toml::from_str("name = \"Test\"\n").unwrap();
+        assert_eq!(profile.smt_preference.bpf_value(), 0);
+    }
+
+    #[test]
+    fn test_priority_boost_clamped() {
+        let boost = |value: &str| {
+            toml::from_str::<GameProfile>(&format!(
+                "name = \"Test\"\n[tunables]\npriority_boost = {}\n",
+                value
+            ))
+            .unwrap()
+            .tunables
+            .bpf_priority_boost()
+        };
+        assert_eq!(boost("2"), 2);
+        assert_eq!(boost("4"), 4);
+        assert_eq!(boost("10"), PRIORITY_BOOST_MAX);
+        // Negative boosts never deprioritize a game
+        assert_eq!(boost("-3"), 0);
+
+        let profile: GameProfile = toml::from_str("name = \"Test\"\n").unwrap();
+        assert_eq!(profile.tunables.bpf_priority_boost(), 0);
+    }
+
+    #[test]
+    fn test_parse_appmanifest() {
+        let acf = r#""AppState"
+{
+	"appid"		"1091500"
+	"universe"		"1"
+	"name"		"Cyberpunk 2077"
+	"StateFlags"		"4"
+	"UserConfig"
+	{
+		"name"		"Localized Name"
+	}
+}
+"#;
+        assert_eq!(
+            parse_appmanifest(acf),
+            Some((1091500, "Cyberpunk 2077".to_string()))
+        );
+        assert_eq!(parse_appmanifest(r#""AppState" { "name" "No ID" }"#), None);
+    }
+
+    #[test]
+    fn test_profile_validate() {
+        let profile: GameProfile = toml::from_str(r#"name = "No Matchers""#).unwrap();
+        let problems = profile.validate();
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].contains("no matcher"));
+
+        let profile: GameProfile =
+            toml::from_str("name = \"Comm\"\ncomm_pattern = \"GameThreadWorker-1\"").unwrap();
+        assert!(profile.validate()[0].contains("longer than a process comm"));
+
+        let profile: GameProfile =
+            toml::from_str("name = \"Ok\"\ncomm_pattern = \"exefile\"").unwrap();
+        assert!(profile.validate().is_empty());
+    }
+
     #[test]
     fn test_profile_numa_preference() {
         let toml_str = r#"
@@ -299,4 +650,36 @@ cpu_affinity = [0, 1, 2, 3, 4, 5, 6, 7]
         assert_eq!(profile.numa_preference, NumaPreference::GpuLocal);
         assert_eq!(profile.cpu_affinity, Some(vec![0, 1, 2, 3, 4, 5, 6, 7]));
     }
+
+    #[test]
+    fn test_merged_profile_document() {
+        let toml_str = r#"
+[[profile]]
+name = "Cyberpunk 2077"
+exe_name = "Cyberpunk2077.exe"
+vcache_preference = "cache"
+
+[[profile]]
+name = "Counter-Strike 2"
+exe_name = "cs2"
+steam_appid = 730
+
+[profile.tunables]
+burst_threshold_ns = 500000
+"#;
+        let mut manager = ProfileManager::new();
+        for profile in parse_profile_document(toml_str).unwrap() {
+            manager.add_unique_profile(profile).unwrap();
+        }
+        assert_eq!(manager.count(), 2);
+
+        let cyberpunk = manager.match_process("cyberpunk2077.exe", None).unwrap();
+        assert_eq!(cyberpunk.vcache_preference, VCachePreference::Cache);
+        let cs2 = manager.match_process("cs2", None).unwrap();
+        assert_eq!(cs2.tunables.burst_threshold_ns, Some(500000));
+
+        // A name already loaded from a profiles directory is rejected
+        let duplicate: GameProfile = toml::from_str("name = \"Counter-Strike 2\"").unwrap();
+        assert!(manager.add_unique_profile(duplicate).is_err());
+    }
 }
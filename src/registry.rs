@@ -0,0 +1,243 @@
+// SPDX-License-Identifier: GPL-2.0
+//
+// GhostBrew - Workload Registry
+//
+// The /proc scan, Looking Glass boosts, the container scan and the Ollama
+// scan each claim PIDs with a workload class. A PID can be claimed by more
+// than one of them (an AI runtime inside a container, a game launched from a
+// toolbox), and writing each claim to its own map would leave BPF to pick
+// whichever map it checks first. The registry resolves every PID to one
+// class and one map, then emits a single set of gaming_pids/container_pids
+// updates against what it wrote last.
+//
+// Priority: gaming > interactive > AI > container > batch. Claims of the same
+// class go to the earliest source in `Source` order. vm_vcpu_pids is keyed by
+// thread ID, not TGID, and stays with the VM scan.
+//
+// Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
+
+use crate::cgroup::WORKLOAD_CONTAINER;
+use crate::gaming::{WORKLOAD_AI, WORKLOAD_BATCH, WORKLOAD_GAMING, WORKLOAD_INTERACTIVE};
+use std::collections::HashMap;
+
+/// Detector that claimed a PID, in tie-break order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Source {
+    /// /proc scan (gaming, browsers, AI runtimes, dev tools), with focus tiers
+    Process,
+    /// QEMU processes with an active Looking Glass session
+    LookingGlass,
+    /// Container scan
+    Container,
+    /// Host Ollama servers
+    Ollama,
+}
+
+impl Source {
+    const ALL: [Source; 4] = [
+        Source::Process,
+        Source::LookingGlass,
+        Source::Container,
+        Source::Ollama,
+    ];
+
+    /// Map this source's PIDs are written to
+    pub fn map(self) -> WorkloadMap {
+        match self {
+            Source::Process | Source::LookingGlass => WorkloadMap::GamingPids,
+            Source::Container | Source::Ollama => WorkloadMap::ContainerPids,
+        }
+    }
+}
+
+/// TGID-keyed BPF map a PID's class is written to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum WorkloadMap {
+    GamingPids,
+    ContainerPids,
+}
+
+/// One BPF map operation from `WorkloadRegistry::sync`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapUpdate {
+    Set {
+        map: WorkloadMap,
+        pid: u32,
+        class: u32,
+    },
+    Delete {
+        map: WorkloadMap,
+        pid: u32,
+    },
+}
+
+impl MapUpdate {
+    pub fn pid(&self) -> u32 {
+        match *self {
+            MapUpdate::Set { pid, .. } | MapUpdate::Delete { pid, .. } => pid,
+        }
+    }
+}
+
+/// Rank of a workload class; higher wins a conflict
+pub fn class_priority(class: u32) -> u8 {
+    match class {
+        WORKLOAD_GAMING => 5,
+        WORKLOAD_INTERACTIVE => 4,
+        WORKLOAD_AI => 3,
+        WORKLOAD_CONTAINER => 2,
+        WORKLOAD_BATCH => 1,
+        _ => 0,
+    }
+}
+
+/// Claims of every detector and the resolved state last written to BPF
+#[derive(Debug, Default)]
+pub struct WorkloadRegistry {
+    claims: HashMap<Source, HashMap<u32, u32>>,
+    written: HashMap<u32, (WorkloadMap, u32)>,
+}
+
+impl WorkloadRegistry {
+    /// Claim `pid` for `source`, replacing its earlier claim
+    pub fn claim(&mut self, source: Source, pid: u32, class: u32) {
+        self.claims.entry(source).or_default().insert(pid, class);
+    }
+
+    /// Drop the claim `source` has on `pid`
+    pub fn release(&mut self, source: Source, pid: u32) {
+        if let Some(claims) = self.claims.get_mut(&source) {
+            claims.remove(&pid);
+        }
+    }
+
+    /// Replace every claim of `source` with a complete scan result
+    pub fn replace(&mut self, source: Source, pids: HashMap<u32, u32>) {
+        self.claims.insert(source, pids);
+    }
+
+    /// Forget a PID whose map entries were deleted behind the registry's back
+    /// (the process exited and was pruned)
+    pub fn forget(&mut self, pid: u32) {
+        for claims in self.claims.values_mut() {
+            claims.remove(&pid);
+        }
+        self.written.remove(&pid);
+    }
+
+    /// Winning map and class of every claimed PID
+    pub fn resolve(&self) -> HashMap<u32, (WorkloadMap, u32)> {
+        let mut resolved: HashMap<u32, (WorkloadMap, u32)> = HashMap::new();
+        for source in Source::ALL {
+            let Some(claims) = self.claims.get(&source) else {
+                continue;
+            };
+            for (&pid, &class) in claims {
+                // Only a strictly higher class displaces an earlier source
+                let wins = resolved
+                    .get(&pid)
+                    .is_none_or(|&(_, winner)| class_priority(class) > class_priority(winner));
+                if wins {
+                    resolved.insert(pid, (source.map(), class));
+                }
+            }
+        }
+        resolved
+    }
+
+    /// Map operations that bring BPF from the last sync to the current claims
+    ///
+    /// Deletes come first, so a PID moving between maps is never in both.
+    pub fn sync(&mut self) -> Vec<MapUpdate> {
+        let resolved = self.resolve();
+
+        let mut deletes: Vec<MapUpdate> = self
+            .written
+            .iter()
+            .filter(|&(pid, &(map, _))| resolved.get(pid).is_none_or(|&(new, _)| new != map))
+            .map(|(&pid, &(map, _))| MapUpdate::Delete { map, pid })
+            .collect();
+        let mut sets: Vec<MapUpdate> = resolved
+            .iter()
+            .filter(|&(pid, entry)| self.written.get(pid) != Some(entry))
+            .map(|(&pid, &(map, class))| MapUpdate::Set { map, pid, class })
+            .collect();
+        deletes.sort_by_key(MapUpdate::pid);
+        sets.sort_by_key(MapUpdate::pid);
+
+        self.written = resolved;
+        deletes.extend(sets);
+        deletes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gaming_wins_over_ai() {
+        let mut registry = WorkloadRegistry::default();
+        // A game the /proc scan found inside an AI-classified container
+        registry.claim(Source::Process, 100, WORKLOAD_GAMING);
+        registry.claim(Source::Container, 100, WORKLOAD_AI);
+        // An AI runtime the /proc scan and the Ollama scan both found
+        registry.claim(Source::Process, 200, WORKLOAD_AI);
+        registry.claim(Source::Ollama, 200, WORKLOAD_AI);
+        // A plain container process
+        registry.claim(Source::Container, 300, WORKLOAD_CONTAINER);
+
+        assert_eq!(
+            registry.sync(),
+            [
+                MapUpdate::Set {
+                    map: WorkloadMap::GamingPids,
+                    pid: 100,
+                    class: WORKLOAD_GAMING,
+                },
+                MapUpdate::Set {
+                    map: WorkloadMap::GamingPids,
+                    pid: 200,
+                    class: WORKLOAD_AI,
+                },
+                MapUpdate::Set {
+                    map: WorkloadMap::ContainerPids,
+                    pid: 300,
+                    class: WORKLOAD_CONTAINER,
+                },
+            ]
+        );
+        // Nothing changed, nothing written
+        assert!(registry.sync().is_empty());
+
+        // The game exits: the container's AI claim takes over, in its map
+        registry.release(Source::Process, 100);
+        assert_eq!(
+            registry.sync(),
+            [
+                MapUpdate::Delete {
+                    map: WorkloadMap::GamingPids,
+                    pid: 100,
+                },
+                MapUpdate::Set {
+                    map: WorkloadMap::ContainerPids,
+                    pid: 100,
+                    class: WORKLOAD_AI,
+                },
+            ]
+        );
+
+        // A container scan without PID 300 removes it
+        registry.replace(Source::Container, HashMap::from([(100, WORKLOAD_AI)]));
+        assert_eq!(
+            registry.sync(),
+            [MapUpdate::Delete {
+                map: WorkloadMap::ContainerPids,
+                pid: 300,
+            }]
+        );
+
+        assert!(class_priority(WORKLOAD_INTERACTIVE) > class_priority(WORKLOAD_AI));
+        assert!(class_priority(WORKLOAD_CONTAINER) > class_priority(WORKLOAD_BATCH));
+    }
+}
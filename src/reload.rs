@@ -0,0 +1,275 @@
+// SPDX-License-Identifier: GPL-2.0
+//
+// GhostBrew - Configuration Reload (SIGHUP)
+//
+// Re-reads the config file and game profiles while the scheduler keeps
+// running. The signal handler only sets a flag; the run loop calls reload()
+// and applies the result, so nothing here touches BPF maps directly.
+//
+// Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
+
+use crate::config::GhostBrewConfig;
+use crate::profiles::ProfileManager;
+use anyhow::Result;
+use log::warn;
+use std::collections::BTreeSet;
+
+/// Where a reload reads its configuration from
+pub trait ConfigSource {
+    /// Load the main config file
+    fn load_config(&self) -> Result<GhostBrewConfig>;
+
+    /// Load game profiles for the given config
+    fn load_profiles(&self, config: &GhostBrewConfig) -> Result<ProfileManager>;
+}
+
+/// Standard config and profile paths (same as startup)
+pub struct SystemSource;
+
+impl ConfigSource for SystemSource {
+    fn load_config(&self) -> Result<GhostBrewConfig> {
+        GhostBrewConfig::load()
+    }
+
+    fn load_profiles(&self, config: &GhostBrewConfig) -> Result<ProfileManager> {
+        let mut profiles = ProfileManager::new();
+        if let Some(ref profiles_dir) = config.profiles_dir {
+            profiles.load_from_directory(profiles_dir)?;
+        }
+        profiles.load_standard_paths()?;
+        Ok(profiles)
+    }
+}
+
+/// Freshly loaded configuration and what changed against the running one
+pub struct ReloadOutcome {
+    pub config: GhostBrewConfig,
+    /// New profiles, None if loading failed and the current set is kept
+    pub profiles: Option<ProfileManager>,
+    /// Human-readable change descriptions for the log
+    pub changes: Vec<String>,
+}
+
+impl ReloadOutcome {
+    /// Check whether the [gaming] allow/deny/browser/compositor lists changed
+    pub fn overrides_changed(&self, current: &GhostBrewConfig) -> bool {
+        self.config.gaming.allow != current.gaming.allow
+            || self.config.gaming.deny != current.gaming.deny
+            || self.config.gaming.browsers != current.gaming.browsers
+            || self.config.gaming.compositors != current.gaming.compositors
+    }
+}
+
+/// Reload config and profiles, diffing them against the running state
+///
+/// A config that fails to load or parse aborts the reload so a typo never
+/// replaces a working config with defaults. A profile load failure only keeps
+/// the current profiles.
+pub fn reload(
+    current_config: &GhostBrewConfig,
+    current_profiles: &ProfileManager,
+    source: &impl ConfigSource,
+) -> Result<ReloadOutcome> {
+    let config = source.load_config()?;
+    let mut changes = diff_config(current_config, &config);
+
+    let profiles = match source.load_profiles(&config) {
+        Ok(profiles) => {
+            changes.extend(diff_profiles(current_profiles, &profiles));
+            Some(profiles)
+        }
+        Err(e) => {
+            warn!("Failed to reload game profiles, keeping current set: {}", e);
+            None
+        }
+    };
+
+    Ok(ReloadOutcome {
+        config,
+        profiles,
+        changes,
+    })
+}
+
+fn diff_config(old: &GhostBrewConfig, new: &GhostBrewConfig) -> Vec<String> {
+    let mut changes = Vec::new();
+    let mut field = |name: &str, old: String, new: String| {
+        if old != new {
+            changes.push(format!("{}: {} -> {}", name, old, new));
+        }
+    };
+
+    field(
+        "defaults.burst_threshold_ns",
+        old.defaults.burst_threshold_ns.to_string(),
+        new.defaults.burst_threshold_ns.to_string(),
+    );
+    field(
+        "defaults.slice_ns",
+        old.defaults.slice_ns.to_string(),
+        new.defaults.slice_ns.to_string(),
+    );
+    field(
+        "defaults.stats_interval",
+        old.defaults.stats_interval.to_string(),
+        new.defaults.stats_interval.to_string(),
+    );
+    field(
+        "gaming.allow",
+        format!("{:?}", old.gaming.allow),
+        format!("{:?}", new.gaming.allow),
+    );
+    field(
+        "gaming.deny",
+        format!("{:?}", old.gaming.deny),
+        format!("{:?}", new.gaming.deny),
+    );
+    field(
+        "gaming.browsers",
+        format!("{:?}", old.gaming.browsers),
+        format!("{:?}", new.gaming.browsers),
+    );
+    field(
+        "gaming.compositors",
+        format!("{:?}", old.gaming.compositors),
+        format!("{:?}", new.gaming.compositors),
+    );
+    field(
+        "gaming.min_age_ms",
+        old.gaming.min_age_ms.to_string(),
+        new.gaming.min_age_ms.to_string(),
+    );
+    field(
+        "containers",
+        format!("{:?}", old.containers),
+        format!("{:?}", new.containers),
+    );
+    field(
+        "placement (applies on restart)",
+        format!("{:?}", old.placement),
+        format!("{:?}", new.placement),
+    );
+    field(
+        "profiles_dir",
+        format!("{:?}", old.profiles_dir),
+        format!("{:?}", new.profiles_dir),
+    );
+
+    changes
+}
+
+fn diff_profiles(old: &ProfileManager, new: &ProfileManager) -> Vec<String> {
+    let names = |profiles: &ProfileManager| -> BTreeSet<String> {
+        profiles.all_profiles().map(|p| p.name.clone()).collect()
+    };
+    let (old_names, new_names) = (names(old), names(new));
+
+    let mut changes = Vec::new();
+    for name in new_names.difference(&old_names) {
+        changes.push(format!("profile added: {}", name));
+    }
+    for name in old_names.difference(&new_names) {
+        changes.push(format!("profile removed: {}", name));
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profiles::GameProfile;
+    use anyhow::bail;
+
+    struct FakeSource {
+        config: Option<&'static str>,
+        profiles: Option<Vec<&'static str>>,
+    }
+
+    impl ConfigSource for FakeSource {
+        fn load_config(&self) -> Result<GhostBrewConfig> {
+            match self.config {
+                Some(toml_str) => Ok(toml::from_str(toml_str)?),
+                None => bail!("config unreadable"),
+            }
+        }
+
+        fn load_profiles(&self, _config: &GhostBrewConfig) -> Result<ProfileManager> {
+            let Some(ref names) = self.profiles else {
+                bail!("profiles unreadable");
+            };
+            Ok(profiles(names))
+        }
+    }
+
+    fn profiles(names: &[&str]) -> ProfileManager {
+        let mut manager = ProfileManager::new();
+        for name in names {
+            let profile: GameProfile = toml::from_str(&format!("name = \"{}\"", name)).unwrap();
+            manager.add_profile(profile);
+        }
+        manager
+    }
+
+    #[test]
+    fn test_reload_reports_changes() {
+        let current = GhostBrewConfig::default();
+        let current_profiles = profiles(&["Cyberpunk 2077", "Elden Ring"]);
+
+        let source = FakeSource {
+            config: Some(
+                r#"
+[defaults]
+slice_ns = 2000000
+
+[gaming]
+deny = ["winword.exe"]
+"#,
+            ),
+            profiles: Some(vec!["Cyberpunk 2077", "Helldivers 2"]),
+        };
+        let outcome = reload(&current, &current_profiles, &source).unwrap();
+
+        assert_eq!(
+            outcome.changes,
+            vec![
+                "defaults.slice_ns: 3000000 -> 2000000",
+                "gaming.deny: [] -> [\"winword.exe\"]",
+                "profile added: Helldivers 2",
+                "profile removed: Elden Ring",
+            ]
+        );
+        assert!(outcome.overrides_changed(&current));
+        assert_eq!(outcome.profiles.map(|p| p.count()), Some(2));
+
+        // Unchanged sources produce no changes
+        let source = FakeSource {
+            config: Some(""),
+            profiles: Some(vec!["Cyberpunk 2077", "Elden Ring"]),
+        };
+        let outcome = reload(&current, &current_profiles, &source).unwrap();
+        assert!(outcome.changes.is_empty());
+        assert!(!outcome.overrides_changed(&current));
+    }
+
+    #[test]
+    fn test_reload_failures() {
+        let current = GhostBrewConfig::default();
+        let current_profiles = profiles(&["Elden Ring"]);
+
+        // Broken config aborts the whole reload
+        let source = FakeSource {
+            config: None,
+            profiles: Some(vec![]),
+        };
+        assert!(reload(&current, &current_profiles, &source).is_err());
+
+        // Broken profiles keep the current set
+        let source = FakeSource {
+            config: Some(""),
+            profiles: None,
+        };
+        let outcome = reload(&current, &current_profiles, &source).unwrap();
+        assert!(outcome.profiles.is_none());
+        assert!(outcome.changes.is_empty());
+    }
+}
@@ -0,0 +1,236 @@
+// SPDX-License-Identifier: GPL-2.0
+//
+// GhostBrew - Hardware Report (--hw-report)
+//
+// Gathers what GhostBrew detects about the machine (CPU topology, cpufreq and
+// prefcore, NVIDIA GPUs, kernel sched_ext support) into one JSON document
+// for bug reports. It only describes hardware and kernel features: no
+// hostnames, PIDs, usernames or GPU UUIDs. Everything it reads is world
+// readable, so it runs without root.
+//
+// Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
+
+use crate::doctor::{self, SchedExtState};
+use crate::gpu::{self, GpuInfo};
+use crate::kernel;
+use crate::pbo::{self, BoostSpread};
+use crate::topology::{self, CpuTopology};
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+/// Kernel and sched_ext support
+#[derive(Debug, Serialize)]
+pub struct KernelReport {
+    pub version: Option<String>,
+    /// unsupported, disabled, idle, attached, or the raw sysfs state
+    pub sched_ext: String,
+    pub attached_scheduler: Option<String>,
+    pub dsq_insert: bool,
+    pub cgroup_v2: bool,
+}
+
+/// Frequency scaling and AMD prefcore
+#[derive(Debug, Serialize)]
+pub struct CpufreqReport {
+    pub driver: Option<String>,
+    pub governor: Option<String>,
+    pub amd_pstate: String,
+    pub prefcore: bool,
+    pub prefcore_rankings: Vec<u32>,
+    pub boost_spread: BoostSpread,
+}
+
+/// One GPU, without its UUID
+#[derive(Debug, Serialize)]
+pub struct GpuReport {
+    pub model: String,
+    pub kind: String,
+    pub pci_address: String,
+    pub numa_node: i32,
+    pub power_state: String,
+    pub pcie_link: String,
+    pub link_degraded: bool,
+    pub rebar: bool,
+    pub bar1_mib: u64,
+}
+
+impl From<&GpuInfo> for GpuReport {
+    fn from(gpu: &GpuInfo) -> Self {
+        Self {
+            model: gpu.model.clone(),
+            kind: gpu.kind.to_string(),
+            pci_address: gpu.pci_address.clone(),
+            numa_node: gpu.numa_node,
+            power_state: gpu.power_state.to_string(),
+            pcie_link: gpu.link_summary(),
+            link_degraded: gpu.link_degraded,
+            rebar: gpu.rebar_enabled,
+            bar1_mib: gpu.bar1_size >> 20,
+        }
+    }
+}
+
+/// Everything --hw-report prints
+#[derive(Serialize)]
+pub struct HwReport {
+    pub ghostbrew: &'static str,
+    pub kernel: KernelReport,
+    pub topology: CpuTopology,
+    pub cpufreq: CpufreqReport,
+    pub gpus: Vec<GpuReport>,
+}
+
+fn sched_ext_name(state: SchedExtState) -> String {
+    match state {
+        SchedExtState::Unsupported => "unsupported".to_string(),
+        SchedExtState::Disabled => "disabled".to_string(),
+        SchedExtState::Idle => "idle".to_string(),
+        SchedExtState::Attached => "attached".to_string(),
+        SchedExtState::Transitioning(s) | SchedExtState::Unknown(s) => s,
+    }
+}
+
+/// Run hardware and kernel detection
+pub fn collect() -> Result<HwReport> {
+    let topology = topology::detect_topology().context("Failed to detect CPU topology")?;
+
+    let features = kernel::sched_ext_features();
+    let kernel = KernelReport {
+        version: features.kernel.map(|v| v.to_string()),
+        sched_ext: sched_ext_name(doctor::sched_ext_state()),
+        attached_scheduler: doctor::attached_scheduler(),
+        dsq_insert: features.dsq_insert,
+        cgroup_v2: features.cgroup_v2,
+    };
+
+    // Guest rankings and frequencies don't reflect host cores
+    let (prefcore, boost_spread) = if topology.is_virtualized {
+        (
+            pbo::PrefcoreInfo::new(topology.nr_cpus),
+            BoostSpread::default(),
+        )
+    } else {
+        (
+            pbo::detect_prefcore(topology.nr_cpus)?,
+            pbo::per_core_boost_spread(
+                &topology::cpu_max_freqs(topology.nr_cpus),
+                &topology.cpu_to_ccd,
+            ),
+        )
+    };
+    let cpufreq = pbo::detect_cpufreq();
+    let cpufreq = CpufreqReport {
+        driver: cpufreq.as_ref().map(|c| c.driver.clone()),
+        governor: cpufreq.map(|c| c.governor),
+        amd_pstate: format!("{:?}", pbo::detect_pstate_mode()).to_lowercase(),
+        prefcore: prefcore.enabled,
+        prefcore_rankings: if prefcore.enabled {
+            prefcore.rankings
+        } else {
+            Vec::new()
+        },
+        boost_spread,
+    };
+
+    let gpus = gpu::detect_gpus()
+        .unwrap_or_default()
+        .iter()
+        .map(GpuReport::from)
+        .collect();
+
+    Ok(HwReport {
+        ghostbrew: env!("CARGO_PKG_VERSION"),
+        kernel,
+        topology,
+        cpufreq,
+        gpus,
+    })
+}
+
+/// Pretty JSON for pasting into a bug report
+pub fn render(report: &HwReport) -> Result<String> {
+    Ok(serde_json::to_string_pretty(report)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::procfs;
+
+    /// Every object key in a JSON value
+    fn keys(value: &serde_json::Value, out: &mut Vec<String>) {
+        match value {
+            serde_json::Value::Object(map) => {
+                for (key, value) in map {
+                    out.push(key.clone());
+                    keys(value, out);
+                }
+            }
+            serde_json::Value::Array(items) => items.iter().for_each(|v| keys(v, out)),
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn test_hw_report_sections() {
+        let fixture = procfs::Fixture::new("hw-report");
+        fixture
+            .file(
+                "/proc/cpuinfo",
+                "processor\t: 0\nvendor_id\t: AuthenticAMD\ncpu family\t: 25\n\
+                 model name\t: AMD Ryzen 7 7800X3D 8-Core Processor\nflags\t\t: fpu sse\n",
+            )
+            .file("/sys/devices/system/cpu/online", "0-1\n")
+            .file(
+                "/sys/devices/system/cpu/cpu0/cpufreq/scaling_driver",
+                "amd-pstate-epp\n",
+            )
+            .file("/sys/devices/system/cpu/amd_pstate/status", "active\n")
+            .file("/proc/driver/nvidia/params", "EnableResizableBar: 1\n")
+            .file(
+                "/proc/driver/nvidia/gpus/0000:01:00.0/information",
+                "Model: \t\t NVIDIA GeForce RTX 4080\nGPU UUID: \t GPU-5d1f\n",
+            )
+            .file("/sys/bus/pci/devices/0000:01:00.0/power_state", "D0\n");
+        for cpu in 0..2 {
+            let base = format!("/sys/devices/system/cpu/cpu{}", cpu);
+            fixture
+                .file(&format!("{}/topology/physical_package_id", base), "0\n")
+                .file(&format!("{}/topology/core_id", base), &format!("{}\n", cpu))
+                .file(
+                    &format!("{}/topology/thread_siblings_list", base),
+                    &format!("{}\n", cpu),
+                );
+        }
+
+        let report = fixture.run(collect).unwrap();
+        let json = render(&report).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        let sections: Vec<&str> = value
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        for section in ["ghostbrew", "kernel", "topology", "cpufreq", "gpus"] {
+            assert!(sections.contains(&section), "missing {}", section);
+        }
+        assert_eq!(value["topology"]["nr_cpus"], 2);
+        assert_eq!(value["cpufreq"]["amd_pstate"], "active");
+        assert_eq!(value["gpus"][0]["model"], "NVIDIA GeForce RTX 4080");
+
+        // Nothing that identifies the machine, its users or its processes
+        let mut all_keys = Vec::new();
+        keys(&value, &mut all_keys);
+        let banned = ["pid", "pids", "user", "username", "hostname", "uuid"];
+        for key in &all_keys {
+            assert!(
+                !key.split('_').any(|word| banned.contains(&word)),
+                "report has a '{}' field",
+                key
+            );
+        }
+        assert!(!json.contains("GPU-5d1f"));
+    }
+}
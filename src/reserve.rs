@@ -0,0 +1,254 @@
+// SPDX-License-Identifier: GPL-2.0
+//
+// GhostBrew - AI CPU Reservation (--ai-cpu-reserve)
+//
+// Confines AI workloads (inference runtimes, AI containers and AI VMs) to a
+// set of CPUs so they can't crowd interactive apps off the rest. The set is
+// pushed into the BPF class_cpumasks map under the AI workload class.
+// --isolate-ai instead confines AI and batch work to the efficiency CPUs
+// (E-cores on Intel hybrid, the frequency CCD on AMD).
+//
+// Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
+
+use crate::topology::{self, CpuTopology};
+use anyhow::{Context, Result, bail};
+use std::fs;
+
+/// CPUs covered by a mask (must match BPF MAX_CPUS)
+pub const MAX_CPUS: u32 = 256;
+
+/// CPU bitmask matching the BPF struct class_cpumask
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ClassCpumask {
+    pub bits: [u64; MAX_CPUS as usize / 64],
+}
+
+impl ClassCpumask {
+    /// Build a mask from CPU numbers (all below MAX_CPUS)
+    pub fn from_cpus(cpus: &[u32]) -> Self {
+        let mut mask = Self::default();
+        for &cpu in cpus {
+            mask.bits[cpu as usize / 64] |= 1 << (cpu % 64);
+        }
+        mask
+    }
+
+    /// Number of CPUs in the mask
+    pub fn weight(&self) -> u32 {
+        self.bits.iter().map(|word| word.count_ones()).sum()
+    }
+
+    /// CPU numbers in the mask, ascending
+    pub fn cpus(&self) -> Vec<u32> {
+        (0..MAX_CPUS)
+            .filter(|&cpu| self.bits[cpu as usize / 64] & (1 << (cpu % 64)) != 0)
+            .collect()
+    }
+
+    /// Raw bytes for the BPF map update
+    pub fn as_bytes(&self) -> &[u8] {
+        // SAFETY: repr(C) struct of one u64 array, so no padding and every
+        // byte is initialized; the slice borrows self for its lifetime
+        unsafe {
+            std::slice::from_raw_parts(
+                self as *const ClassCpumask as *const u8,
+                std::mem::size_of::<ClassCpumask>(),
+            )
+        }
+    }
+}
+
+/// Parse a cpulist like "8-15,24-31", rejecting anything malformed
+///
+/// Unlike topology::parse_cpu_list (for trusted sysfs input), a typo here
+/// must fail instead of silently shrinking the reservation.
+pub fn parse_cpulist(list: &str) -> Result<Vec<u32>> {
+    let mut cpus = Vec::new();
+
+    for part in list.trim().split(',') {
+        let part = part.trim();
+        let parse = |s: &str| {
+            s.trim()
+                .parse::<u32>()
+                .with_context(|| format!("invalid CPU '{}' in cpulist '{}'", s, list))
+        };
+
+        match part.split_once('-') {
+            Some((start, end)) => {
+                let (start, end) = (parse(start)?, parse(end)?);
+                if start > end {
+                    bail!("invalid CPU range '{}' in cpulist '{}'", part, list);
+                }
+                cpus.extend(start..=end);
+            }
+            None => cpus.push(parse(part)?),
+        }
+    }
+
+    cpus.sort_unstable();
+    cpus.dedup();
+    Ok(cpus)
+}
+
+/// Convert a reserve cpulist into the mask pushed to BPF
+///
+/// Every CPU must be online.
+pub fn reserve_mask(list: &str, online: &[u32]) -> Result<ClassCpumask> {
+    let cpus = parse_cpulist(list)?;
+
+    let offline: Vec<u32> = cpus
+        .iter()
+        .copied()
+        .filter(|cpu| !online.contains(cpu))
+        .collect();
+    if !offline.is_empty() {
+        bail!("CPUs {:?} in '{}' are not online", offline, list);
+    }
+    if let Some(&cpu) = cpus.iter().find(|&&cpu| cpu >= MAX_CPUS) {
+        bail!(
+            "CPU {} exceeds the scheduler limit of {} CPUs",
+            cpu,
+            MAX_CPUS
+        );
+    }
+
+    Ok(ClassCpumask::from_cpus(&cpus))
+}
+
+/// Default reservation: the frequency CCD, the non-V-Cache CCD on X3D
+/// parts without asymmetric boost, or the first compact-core CCD
+pub fn default_ccd(topology: &CpuTopology) -> Option<u32> {
+    if topology.nr_ccds < 2 {
+        return None;
+    }
+    topology
+        .freq_ccd
+        .or_else(|| {
+            let vcache_ccd = topology.vcache_ccd?;
+            (0..topology.nr_ccds).find(|&ccd| ccd != vcache_ccd)
+        })
+        .or_else(|| topology.compact_ccds.first().copied())
+}
+
+/// Efficiency CPUs: the E-cores when there are any, else the CPUs of
+/// `efficiency_ccd`
+pub fn efficiency_cpus(
+    ecore_cpus: &[u32],
+    cpu_to_ccd: &[u32],
+    efficiency_ccd: Option<u32>,
+) -> Option<Vec<u32>> {
+    if !ecore_cpus.is_empty() {
+        let mut cpus = ecore_cpus.to_vec();
+        cpus.sort_unstable();
+        return Some(cpus);
+    }
+    let ccd = efficiency_ccd?;
+    Some(
+        (0..cpu_to_ccd.len() as u32)
+            .filter(|&cpu| cpu_to_ccd[cpu as usize] == ccd)
+            .collect(),
+    )
+}
+
+fn online_cpus() -> Result<Vec<u32>> {
+    fs::read_to_string("/sys/devices/system/cpu/online")
+        .map(|list| topology::parse_cpu_list(&list))
+        .context("Failed to read online CPUs")
+}
+
+/// Resolve --ai-cpu-reserve [CPULIST] against the running system
+pub fn resolve(list: Option<&str>, topology: &CpuTopology) -> Result<ClassCpumask> {
+    let online = online_cpus()?;
+
+    if let Some(list) = list {
+        return reserve_mask(list, &online);
+    }
+
+    let Some(ccd) = default_ccd(topology) else {
+        bail!("no frequency CCD to reserve for AI workloads; pass --ai-cpu-reserve=<cpulist>");
+    };
+    let cpus: Vec<u32> = online
+        .into_iter()
+        .filter(|&cpu| topology.cpu_to_ccd.get(cpu as usize) == Some(&ccd))
+        .collect();
+    Ok(ClassCpumask::from_cpus(&cpus))
+}
+
+/// Resolve the --isolate-ai mask against the running system
+pub fn resolve_efficiency(topology: &CpuTopology) -> Result<ClassCpumask> {
+    let online = online_cpus()?;
+    let Some(cpus) = efficiency_cpus(
+        &topology.efficiency_core_cpus(),
+        &topology.cpu_to_ccd,
+        default_ccd(topology),
+    ) else {
+        bail!("no E-cores, compact cores or frequency CCD to isolate AI and batch work on");
+    };
+    let cpus: Vec<u32> = cpus
+        .into_iter()
+        .filter(|cpu| online.contains(cpu) && *cpu < MAX_CPUS)
+        .collect();
+    if cpus.is_empty() {
+        bail!("none of the efficiency CPUs are online");
+    }
+    Ok(ClassCpumask::from_cpus(&cpus))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reserve_mask() {
+        let online: Vec<u32> = (0..32).collect();
+
+        // Frequency CCD of a 9950X3D with SMT siblings
+        let mask = reserve_mask("8-15,24-31", &online).unwrap();
+        assert_eq!(mask.bits, [0xff00_ff00, 0, 0, 0]);
+        assert_eq!(mask.weight(), 16);
+        assert_eq!(mask.as_bytes().len(), 32);
+        assert_eq!(&mask.as_bytes()[..8], &0xff00_ff00u64.to_ne_bytes());
+
+        // Duplicates collapse, single CPUs work
+        assert_eq!(reserve_mask("3,3,1-3", &online).unwrap().bits[0], 0b1110);
+
+        // Offline, reversed and malformed lists are rejected
+        assert!(reserve_mask("30-33", &online).is_err());
+        assert!(reserve_mask("15-8", &online).is_err());
+        assert!(reserve_mask("8-15,", &online).is_err());
+        assert!(reserve_mask("cpu8", &online).is_err());
+
+        // CPUs above 64 land in later words
+        let online: Vec<u32> = (0..192).collect();
+        assert_eq!(
+            reserve_mask("64,191", &online).unwrap().bits,
+            [0, 1, 1 << 63, 0]
+        );
+    }
+
+    #[test]
+    fn test_efficiency_cpus() {
+        // i9-13900K: 8 P-cores with SMT (0-15), 16 E-cores (16-31)
+        let intel = crate::intel::IntelHybridInfo {
+            is_hybrid: true,
+            generation: 13,
+            pcore_cpus: (0..16).collect(),
+            ecore_cpus: (16..32).collect(),
+            turbo_rankings: Vec::new(),
+        };
+        let cpu_to_ccd = vec![0; 32];
+        let cpus = efficiency_cpus(&intel.ecore_cpus, &cpu_to_ccd, None).unwrap();
+        assert_eq!(ClassCpumask::from_cpus(&cpus).bits, [0xffff_0000, 0, 0, 0]);
+
+        // 9950X3D: CCD 1 (CPUs 8-15 and SMT siblings 24-31) is the frequency CCD
+        let cpu_to_ccd: Vec<u32> = (0..32).map(|cpu| (cpu / 8) % 2).collect();
+        let cpus = efficiency_cpus(&[], &cpu_to_ccd, Some(1)).unwrap();
+        let mask = ClassCpumask::from_cpus(&cpus);
+        assert_eq!(mask.bits, [0xff00_ff00, 0, 0, 0]);
+        assert_eq!(mask.cpus(), cpus);
+
+        // Single-CCD AMD without E-cores has nothing to isolate on
+        assert!(efficiency_cpus(&[], &[0; 16], None).is_none());
+    }
+}
@@ -0,0 +1,148 @@
+// SPDX-License-Identifier: GPL-2.0
+//
+// GhostBrew - Per-Monitor Scan Cadence
+//
+// Gaming PIDs churn quickly while VMs, containers and cgroups rarely change,
+// so each monitor rescans on its own interval (--gaming-scan-secs,
+// --vm-scan-secs, --container-scan-secs, --cgroup-scan-secs). Every
+// RECONCILE_INTERVALS stats intervals the PID maps are also checked against
+// /proc, so entries a failed scan never removed don't pile up.
+//
+// Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
+
+use std::time::{Duration, Instant};
+
+/// Default gaming scan interval in seconds
+pub const DEFAULT_GAMING_SCAN_SECS: u64 = 1;
+
+/// Default VM, container and cgroup scan interval in seconds
+pub const DEFAULT_SLOW_SCAN_SECS: u64 = 5;
+
+/// Stats intervals between reconciliations of the PID maps with /proc
+pub const RECONCILE_INTERVALS: u64 = 30;
+
+/// Monitors with their own scan interval
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Monitor {
+    Gaming,
+    Vm,
+    Container,
+    Cgroup,
+}
+
+impl Monitor {
+    pub const ALL: [Monitor; 4] = [
+        Monitor::Gaming,
+        Monitor::Vm,
+        Monitor::Container,
+        Monitor::Cgroup,
+    ];
+}
+
+/// Whether a monitor last scanned at `last` is due at `now`
+pub fn is_due(last: Instant, interval: Duration, now: Instant) -> bool {
+    now.saturating_duration_since(last) >= interval
+}
+
+/// Last-scan times and intervals for each monitor
+#[derive(Debug, Clone)]
+pub struct ScanCadence {
+    intervals: [Duration; 4],
+    last: [Instant; 4],
+}
+
+impl ScanCadence {
+    /// Intervals in Monitor::ALL order; the first scans fall due one
+    /// interval after `start`
+    pub fn new(intervals: [Duration; 4], start: Instant) -> Self {
+        Self {
+            intervals,
+            last: [start; 4],
+        }
+    }
+
+    /// Monitors due at `now`, marking them scanned
+    ///
+    /// `factor` stretches every interval (package transactions); `force`
+    /// makes all of them due (control-file rescan).
+    pub fn take_due(&mut self, now: Instant, factor: u32, force: bool) -> Vec<Monitor> {
+        let mut due = Vec::new();
+        for (i, monitor) in Monitor::ALL.into_iter().enumerate() {
+            if force || is_due(self.last[i], self.intervals[i] * factor, now) {
+                self.last[i] = now;
+                due.push(monitor);
+            }
+        }
+        due
+    }
+}
+
+/// PIDs among a map's keys (native-endian u32) that are no longer alive
+pub fn stale_keys(
+    keys: impl IntoIterator<Item = Vec<u8>>,
+    alive: impl Fn(u32) -> bool,
+) -> Vec<u32> {
+    keys.into_iter()
+        .filter_map(|key| Some(u32::from_ne_bytes(key.as_slice().try_into().ok()?)))
+        .filter(|&pid| !alive(pid))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_due() {
+        let start = Instant::now();
+        let secs = Duration::from_secs;
+        let mut cadence = ScanCadence::new([secs(1), secs(5), secs(5), secs(10)], start);
+
+        assert!(cadence.take_due(start, 1, false).is_empty());
+        assert_eq!(
+            cadence.take_due(start + secs(1), 1, false),
+            [Monitor::Gaming]
+        );
+        // Gaming was just scanned, nothing else is due yet
+        assert!(cadence.take_due(start + secs(1), 1, false).is_empty());
+        assert_eq!(
+            cadence.take_due(start + secs(5), 1, false),
+            [Monitor::Gaming, Monitor::Vm, Monitor::Container]
+        );
+        assert_eq!(
+            cadence.take_due(start + secs(10), 1, false),
+            [
+                Monitor::Gaming,
+                Monitor::Vm,
+                Monitor::Container,
+                Monitor::Cgroup
+            ]
+        );
+
+        // A stretched interval delays the scan
+        assert!(cadence.take_due(start + secs(13), 4, false).is_empty());
+        assert_eq!(
+            cadence.take_due(start + secs(14), 4, false),
+            [Monitor::Gaming]
+        );
+
+        // A forced rescan covers every monitor
+        assert_eq!(cadence.take_due(start + secs(14), 1, true), Monitor::ALL);
+
+        // A clock that reads earlier than the last scan is never due
+        assert!(!is_due(start + secs(1), secs(1), start));
+    }
+
+    #[test]
+    fn test_stale_keys() {
+        let live = std::collections::HashSet::from([100u32, 101, 4242]);
+        let keys = [100u32, 200, 4242, 300]
+            .iter()
+            .map(|pid| pid.to_ne_bytes().to_vec())
+            // A malformed key is skipped rather than deleted
+            .chain([vec![1, 2]]);
+
+        assert_eq!(stale_keys(keys, |pid| live.contains(&pid)), [200, 300]);
+        assert!(stale_keys(Vec::new(), |_| false).is_empty());
+    }
+}
@@ -0,0 +1,135 @@
+// SPDX-License-Identifier: GPL-2.0
+//
+// GhostBrew - BPF Map Self-Test (--self-test)
+//
+// Writes known values into the userspace-fed BPF maps of a loaded (but not
+// attached) skeleton and reads them back, so users can confirm the map
+// plumbing works on their kernel before running the scheduler.
+//
+// Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
+
+use anyhow::{Result, bail};
+
+/// Outcome of one map's round-trip
+#[derive(Debug, Clone)]
+pub struct MapCheck {
+    pub map: &'static str,
+    /// None on PASS, otherwise what went wrong
+    pub error: Option<String>,
+}
+
+impl MapCheck {
+    pub fn passed(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Test entries for a u32 -> u32 map
+pub fn u32_entries(pairs: &[(u32, u32)]) -> Vec<(Vec<u8>, Vec<u8>)> {
+    pairs
+        .iter()
+        .map(|(k, v)| (k.to_ne_bytes().to_vec(), v.to_ne_bytes().to_vec()))
+        .collect()
+}
+
+/// Test entries for a u64 -> u32 map
+pub fn u64_entries(pairs: &[(u64, u32)]) -> Vec<(Vec<u8>, Vec<u8>)> {
+    pairs
+        .iter()
+        .map(|(k, v)| (k.to_ne_bytes().to_vec(), v.to_ne_bytes().to_vec()))
+        .collect()
+}
+
+/// Write every entry, then read each back and compare
+///
+/// `update` and `lookup` wrap the map calls so the check runs against the
+/// loaded skeleton or a stand-in.
+pub fn round_trip(
+    entries: &[(Vec<u8>, Vec<u8>)],
+    update: impl Fn(&[u8], &[u8]) -> Result<()>,
+    lookup: impl Fn(&[u8]) -> Result<Option<Vec<u8>>>,
+) -> Result<()> {
+    for (key, value) in entries {
+        update(key, value)?;
+    }
+    for (key, value) in entries {
+        match lookup(key)? {
+            Some(read) if read == *value => {}
+            Some(read) => bail!("key {:02x?}: wrote {:02x?}, read {:02x?}", key, value, read),
+            None => bail!("key {:02x?}: missing after update", key),
+        }
+    }
+    Ok(())
+}
+
+/// Run a round-trip and record the result under the map's name
+pub fn check_map(
+    map: &'static str,
+    entries: &[(Vec<u8>, Vec<u8>)],
+    update: impl Fn(&[u8], &[u8]) -> Result<()>,
+    lookup: impl Fn(&[u8]) -> Result<Option<Vec<u8>>>,
+) -> MapCheck {
+    MapCheck {
+        map,
+        error: round_trip(entries, update, lookup)
+            .err()
+            .map(|e| format!("{:#}", e)),
+    }
+}
+
+/// Print PASS/FAIL per map
+pub fn print_report(checks: &[MapCheck]) {
+    println!("GhostBrew self-test (BPF map round-trips)");
+    println!();
+    for check in checks {
+        match &check.error {
+            None => println!("  [PASS] {}", check.map),
+            Some(e) => println!("  [FAIL] {:<18} {}", check.map, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_round_trip_verification() {
+        let map: RefCell<HashMap<Vec<u8>, Vec<u8>>> = RefCell::new(HashMap::new());
+        let update = |k: &[u8], v: &[u8]| {
+            map.borrow_mut().insert(k.to_vec(), v.to_vec());
+            Ok(())
+        };
+        let lookup = |k: &[u8]| Ok(map.borrow().get(k).cloned());
+
+        let entries = u32_entries(&[(1000, 1), (1001, 4)]);
+        let check = check_map("gaming_pids", &entries, update, lookup);
+        assert!(check.passed());
+
+        // A map that drops the write is caught
+        let check = check_map("vm_vcpu_pids", &entries, |_, _| Ok(()), |_| Ok(None));
+        assert!(!check.passed());
+        assert!(check.error.unwrap().contains("missing after update"));
+
+        // So is one that returns different bytes
+        let entries = u64_entries(&[(7, 6)]);
+        let check = check_map(
+            "cgroup_classes",
+            &entries,
+            |_, _| Ok(()),
+            |_| Ok(Some(0u32.to_ne_bytes().to_vec())),
+        );
+        assert!(check.error.unwrap().contains("wrote"));
+
+        // Update errors are reported as the failure
+        let check = check_map(
+            "container_pids",
+            &u32_entries(&[(1, 7)]),
+            |_, _| bail!("E2BIG"),
+            |_| Ok(None),
+        );
+        assert_eq!(check.error.as_deref(), Some("E2BIG"));
+    }
+}
@@ -0,0 +1,98 @@
+// SPDX-License-Identifier: GPL-2.0
+//
+// GhostBrew - Shared Detector State
+//
+// GamingDetector, VmMonitor and ContainerMonitor stay owned by the scheduler
+// loop, their only writer. Other threads (the HTTP API today, a scan thread
+// or the TUI later) read a published snapshot of the detected PIDs instead.
+//
+// Locking discipline:
+// - Build the new PidExport before locking; /proc scans and BPF map updates
+//   never run with the lock held.
+// - The write lock is held only to swap in the new Arc, so every snapshot is
+//   one complete scan result, never a half-updated PID set.
+// - Readers hold the read lock only to clone the Arc, then work on that copy.
+//
+// Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
+
+use crate::export::PidExport;
+use std::sync::{Arc, RwLock};
+
+/// Latest detected PIDs, readable from any thread
+#[derive(Debug, Default)]
+pub struct SharedPids {
+    current: RwLock<Arc<PidExport>>,
+}
+
+impl SharedPids {
+    /// Replace the snapshot with a complete scan result
+    pub fn publish(&self, pids: PidExport) {
+        let pids = Arc::new(pids.normalized());
+        let mut current = self.current.write().unwrap_or_else(|e| e.into_inner());
+        *current = pids;
+    }
+
+    /// The last published snapshot
+    pub fn snapshot(&self) -> Arc<PidExport> {
+        self.current
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread;
+
+    #[test]
+    fn test_readers_see_whole_snapshots() {
+        // Every published snapshot has 64 gaming PIDs and the same PIDs + 1
+        // as batch; a torn read would break that
+        let scan = |round: u32| {
+            let gaming: Vec<u32> = (round * 64..round * 64 + 64).collect();
+            PidExport {
+                batch: gaming.iter().map(|pid| pid + 1).collect(),
+                gaming,
+                ..Default::default()
+            }
+        };
+        let shared = SharedPids::default();
+        shared.publish(scan(0));
+        let done = AtomicBool::new(false);
+
+        thread::scope(|s| {
+            let readers: Vec<_> = (0..4)
+                .map(|_| {
+                    s.spawn(|| {
+                        let mut reads = 0;
+                        while !done.load(Ordering::Relaxed) || reads == 0 {
+                            let pids = shared.snapshot();
+                            assert_eq!(pids.gaming.len(), 64);
+                            assert_eq!(pids.gaming[0] % 64, 0);
+                            assert!(
+                                pids.gaming
+                                    .iter()
+                                    .zip(&pids.batch)
+                                    .all(|(g, b)| g + 1 == *b)
+                            );
+                            reads += 1;
+                        }
+                    })
+                })
+                .collect();
+
+            for round in 1..2000 {
+                shared.publish(scan(round));
+            }
+            done.store(true, Ordering::Relaxed);
+            for reader in readers {
+                reader.join().unwrap();
+            }
+        });
+
+        assert_eq!(shared.snapshot().gaming[0], 1999 * 64);
+    }
+}
@@ -0,0 +1,236 @@
+// SPDX-License-Identifier: GPL-2.0
+//
+// GhostBrew - Detection Snapshots (--capture-snapshot / --simulate)
+//
+// Captures the /proc and /sys files process detection and topology
+// discovery read into a tarball, and opens one again so --simulate can
+// replay detection against it through procfs::with_paths. Environment values
+// are redacted except for the few detection depends on.
+//
+// Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
+
+use anyhow::{Context, Result, bail};
+use log::debug;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Files copied from each /proc/<pid>
+const PID_FILES: &[&str] = &["comm", "cmdline", "stat", "status", "root/.flatpak-info"];
+
+/// Environment variables whose values are kept; the rest keep only the name
+const KEPT_ENV_VALUES: &[&str] = &[
+    "WINEARCH",
+    "WINEPREFIX",
+    "STEAM_COMPAT_DATA_PATH",
+    "FLATPAK_ID",
+];
+
+/// System-wide files topology and VM detection read
+const SYSTEM_FILES: &[&str] = &[
+    "/proc/cpuinfo",
+    "/proc/sys/kernel/osrelease",
+    "/sys/devices/system/cpu/online",
+    "/sys/devices/system/cpu/isolated",
+    "/sys/devices/system/cpu/nohz_full",
+    "/sys/class/dmi/id/product_name",
+    "/sys/class/dmi/id/sys_vendor",
+];
+
+/// Per-CPU sysfs directories whose files are copied
+const CPU_DIRS: &[&str] = &["topology", "cpufreq"];
+
+/// Redact a NUL-separated environ block down to names (and kept values)
+pub fn redact_environ(environ: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(environ.len());
+    for var in environ.split(|&b| b == 0).filter(|v| !v.is_empty()) {
+        let name = var.split(|&b| b == b'=').next().unwrap_or(var);
+        let kept = KEPT_ENV_VALUES.iter().any(|k| k.as_bytes() == name);
+        out.extend_from_slice(if kept { var } else { name });
+        if !kept {
+            out.push(b'=');
+        }
+        out.push(0);
+    }
+    out
+}
+
+/// Copy `src` to the same absolute path under `root`; false if unreadable
+fn stage_file(root: &Path, src: &Path, contents: Option<Vec<u8>>) -> bool {
+    let Some(contents) = contents.or_else(|| fs::read(src).ok()) else {
+        return false;
+    };
+    let dst = root.join(src.strip_prefix("/").unwrap_or(src));
+    if let Some(parent) = dst.parent()
+        && fs::create_dir_all(parent).is_err()
+    {
+        return false;
+    }
+    fs::write(&dst, contents).is_ok()
+}
+
+/// Copy the regular files of a sysfs directory
+fn stage_dir_files(root: &Path, dir: &Path) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        if entry.file_type().is_ok_and(|t| t.is_file()) {
+            stage_file(root, &entry.path(), None);
+        }
+    }
+}
+
+/// Copy one process's detection inputs; false if it exited first
+fn stage_pid(root: &Path, pid: u32) -> bool {
+    let proc_dir = PathBuf::from(format!("/proc/{}", pid));
+    if !stage_file(root, &proc_dir.join("comm"), None) {
+        return false;
+    }
+    for name in &PID_FILES[1..] {
+        stage_file(root, &proc_dir.join(name), None);
+    }
+    if let Ok(environ) = fs::read(proc_dir.join("environ")) {
+        stage_file(
+            root,
+            &proc_dir.join("environ"),
+            Some(redact_environ(&environ)),
+        );
+    }
+    if let Ok(exe) = fs::read_link(proc_dir.join("exe")) {
+        let link = root.join(format!("proc/{}/exe", pid));
+        if let Err(e) = std::os::unix::fs::symlink(&exe, &link) {
+            debug!("Snapshot: {:?}: {}", link, e);
+        }
+    }
+    true
+}
+
+/// Copy everything detection reads into `root`; returns the process count
+fn stage(root: &Path) -> Result<usize> {
+    let mut processes = 0;
+    for entry in fs::read_dir("/proc")
+        .context("Failed to read /proc")?
+        .flatten()
+    {
+        if let Some(pid) = entry.file_name().to_str().and_then(|s| s.parse().ok())
+            && stage_pid(root, pid)
+        {
+            processes += 1;
+        }
+    }
+
+    for path in SYSTEM_FILES {
+        stage_file(root, Path::new(path), None);
+    }
+
+    let cpus = fs::read_dir("/sys/devices/system/cpu").context("Failed to read CPU sysfs")?;
+    for entry in cpus.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !name
+            .strip_prefix("cpu")
+            .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+        {
+            continue;
+        }
+        let cpu_dir = entry.path();
+        for dir in CPU_DIRS {
+            stage_dir_files(root, &cpu_dir.join(dir));
+        }
+        stage_file(root, &cpu_dir.join("cpu_capacity"), None);
+        if cpu_dir.join("node0").exists() {
+            let _ = fs::create_dir_all(root.join(format!("sys/devices/system/cpu/{}/node0", name)));
+        }
+    }
+
+    if let Ok(nodes) = fs::read_dir("/sys/devices/system/node") {
+        for entry in nodes.flatten() {
+            if entry.file_name().to_string_lossy().starts_with("node") {
+                stage_file(root, &entry.path().join("cpulist"), None);
+            }
+        }
+    }
+
+    Ok(processes)
+}
+
+/// Scratch directory for staging or extracting a snapshot
+fn scratch_dir(kind: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("ghostbrew-{}-{}", kind, std::process::id()))
+}
+
+fn run_tar(args: &[&std::ffi::OsStr]) -> Result<()> {
+    let status = Command::new("tar")
+        .args(args)
+        .status()
+        .context("Failed to run tar")?;
+    if !status.success() {
+        bail!("tar exited with {}", status);
+    }
+    Ok(())
+}
+
+/// Write a snapshot tarball to `out`; returns the process count
+pub fn capture(out: &Path) -> Result<usize> {
+    let staging = scratch_dir("snapshot");
+    let _ = fs::remove_dir_all(&staging);
+    fs::create_dir_all(&staging)
+        .with_context(|| format!("Failed to create {}", staging.display()))?;
+
+    let result = stage(&staging).and_then(|processes| {
+        run_tar(&[
+            "-czf".as_ref(),
+            out.as_os_str(),
+            "-C".as_ref(),
+            staging.as_os_str(),
+            ".".as_ref(),
+        ])
+        .with_context(|| format!("Failed to write {}", out.display()))?;
+        Ok(processes)
+    });
+    let _ = fs::remove_dir_all(&staging);
+    result
+}
+
+/// A snapshot ready to read; an extracted tarball is removed on drop
+pub struct Snapshot {
+    pub root: PathBuf,
+    extracted: bool,
+}
+
+impl Snapshot {
+    /// Open a snapshot directory, or extract a tarball from --capture-snapshot
+    pub fn open(path: &Path) -> Result<Self> {
+        if path.is_dir() {
+            return Ok(Self {
+                root: path.to_path_buf(),
+                extracted: false,
+            });
+        }
+
+        let root = scratch_dir("simulate");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root)
+            .with_context(|| format!("Failed to create {}", root.display()))?;
+        let snapshot = Self {
+            root,
+            extracted: true,
+        };
+        run_tar(&[
+            "-xzf".as_ref(),
+            path.as_os_str(),
+            "-C".as_ref(),
+            snapshot.root.as_os_str(),
+        ])
+        .with_context(|| format!("Failed to extract {}", path.display()))?;
+        Ok(snapshot)
+    }
+}
+
+impl Drop for Snapshot {
+    fn drop(&mut self) {
+        if self.extracted {
+            let _ = fs::remove_dir_all(&self.root);
+        }
+    }
+}
@@ -0,0 +1,123 @@
+// SPDX-License-Identifier: GPL-2.0
+//
+// GhostBrew - Runtime State Snapshot (--save-state / --load-state)
+//
+// Captures the runtime tunables, V-Cache mode, EPP boost and detector
+// settings as JSON so a benchmark can be rerun under exactly the same
+// scheduler state. --save-state writes the snapshot at shutdown;
+// --load-state applies one at startup, before the main loop.
+//
+// Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
+
+use crate::config::GamingConfig;
+use crate::export;
+use crate::tunables::RuntimeTunables;
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// Snapshot format version, bumped on incompatible changes
+pub const STATE_VERSION: u32 = 1;
+
+/// EPP settings of a snapshot
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EppState {
+    /// Whether GhostBrew was writing EPP hints
+    pub hints: bool,
+    /// EPP written to preferred cores while gaming
+    pub boost_epp: String,
+}
+
+/// Scheduler state at one moment
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuntimeState {
+    pub version: u32,
+    /// runtime_tunables map value
+    pub tunables: RuntimeTunables,
+    /// "cache" or "frequency", None without a V-Cache driver
+    pub vcache_mode: Option<String>,
+    pub epp: EppState,
+    /// [gaming] detector settings
+    pub gaming: GamingConfig,
+    /// --max-scan-procs
+    pub max_scan_procs: usize,
+}
+
+impl RuntimeState {
+    /// Write the snapshot as pretty-printed JSON
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).with_context(|| format!("Failed to create {:?}", parent))?;
+        }
+        let json = serde_json::to_string_pretty(self).context("Failed to serialize state")?;
+        export::write_atomic(path, &json)
+    }
+
+    /// Read a snapshot written by `save`
+    pub fn load(path: &Path) -> Result<Self> {
+        let json =
+            fs::read_to_string(path).with_context(|| format!("Failed to read state {:?}", path))?;
+        let state: Self = serde_json::from_str(&json)
+            .with_context(|| format!("Failed to parse state {:?}", path))?;
+        if state.version != STATE_VERSION {
+            bail!(
+                "state {:?} has version {}, expected {}",
+                path,
+                state.version,
+                STATE_VERSION
+            );
+        }
+        Ok(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_state_round_trip() {
+        let dir = std::env::temp_dir().join(format!("ghostbrew-state-{}", std::process::id()));
+        let path = dir.join("bench/state.json");
+        let state = RuntimeState {
+            version: STATE_VERSION,
+            tunables: RuntimeTunables {
+                burst_threshold_ns: 1_500_000,
+                slice_ns: 3_000_000,
+                gaming_mode: true,
+                work_mode: false,
+                power_save_mode: 1,
+                tickless_enabled: true,
+                gpu_bound_mode: 2,
+                interactive_pct: 40,
+                max_preempt_rate: 500,
+            },
+            vcache_mode: Some("cache".to_string()),
+            epp: EppState {
+                hints: true,
+                boost_epp: "balance_performance".to_string(),
+            },
+            gaming: GamingConfig {
+                allow: vec!["factorio".to_string()],
+                deny: vec!["steamwebhelper".to_string()],
+                min_age_ms: 500,
+                ..GamingConfig::default()
+            },
+            max_scan_procs: 4096,
+        };
+
+        state.save(&path).unwrap();
+        assert_eq!(RuntimeState::load(&path).unwrap(), state);
+
+        // A snapshot from an incompatible version is refused
+        let future = RuntimeState {
+            version: STATE_VERSION + 1,
+            ..state
+        };
+        future.save(&path).unwrap();
+        assert!(RuntimeState::load(&path).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
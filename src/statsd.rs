@@ -0,0 +1,169 @@
+// SPDX-License-Identifier: GPL-2.0
+//
+// GhostBrew - StatsD Export (--statsd-addr)
+//
+// Sends the numeric fields of the stats snapshot as StatsD gauges over UDP
+// once per stats interval, for existing StatsD/telegraf pipelines.
+//
+// Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
+
+use anyhow::{Context, Result};
+use log::{debug, info, warn};
+use serde::Serialize;
+use serde_json::Value;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+
+/// Metric name prefix
+pub const PREFIX: &str = "ghostbrew";
+
+/// Largest datagram to send (fits a 1500-byte MTU with IP/UDP headers)
+const MAX_PACKET_BYTES: usize = 1400;
+
+/// StatsD gauge lines for every numeric field of a snapshot
+///
+/// Nested maps become dotted names (`ghostbrew.class_dispatches.gaming`);
+/// strings, booleans and unset values are skipped.
+pub fn gauge_lines(snapshot: &impl Serialize) -> Result<Vec<String>> {
+    let value = serde_json::to_value(snapshot).context("Failed to serialize stats")?;
+    let mut lines = Vec::new();
+    collect_gauges(PREFIX, &value, &mut lines);
+    Ok(lines)
+}
+
+fn collect_gauges(name: &str, value: &Value, lines: &mut Vec<String>) {
+    match value {
+        Value::Number(n) => lines.push(format!("{}:{}|g", name, n)),
+        Value::Object(fields) => {
+            for (key, field) in fields {
+                // StatsD separators can't appear in a metric name
+                let key = key.replace([':', '|', '@', ' '], "_");
+                collect_gauges(&format!("{}.{}", name, key), field, lines);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Pack lines into newline-separated datagrams of at most MAX_PACKET_BYTES
+fn packets(lines: &[String]) -> Vec<String> {
+    let mut packets = Vec::new();
+    let mut packet = String::new();
+    for line in lines {
+        if !packet.is_empty() && packet.len() + 1 + line.len() > MAX_PACKET_BYTES {
+            packets.push(std::mem::take(&mut packet));
+        }
+        if !packet.is_empty() {
+            packet.push('\n');
+        }
+        packet.push_str(line);
+    }
+    if !packet.is_empty() {
+        packets.push(packet);
+    }
+    packets
+}
+
+/// UDP sender for StatsD gauges
+pub struct StatsdExporter {
+    socket: UdpSocket,
+    addr: SocketAddr,
+    /// A send failed and was logged; quiet until a send succeeds again
+    failing: bool,
+}
+
+impl StatsdExporter {
+    /// Resolve `host:port` and bind a local UDP socket
+    pub fn new(target: &str) -> Result<Self> {
+        let addr = target
+            .to_socket_addrs()
+            .with_context(|| format!("Failed to resolve StatsD address {}", target))?
+            .next()
+            .with_context(|| format!("No address for StatsD target {}", target))?;
+        let bind: SocketAddr = if addr.is_ipv4() {
+            ([0, 0, 0, 0], 0).into()
+        } else {
+            ([0u16; 8], 0).into()
+        };
+        let socket = UdpSocket::bind(bind).context("Failed to bind StatsD socket")?;
+
+        info!("StatsD export to {}", addr);
+        Ok(Self {
+            socket,
+            addr,
+            failing: false,
+        })
+    }
+
+    /// Send one snapshot; failures are logged once and otherwise ignored
+    pub fn send(&mut self, snapshot: &impl Serialize) {
+        let lines = match gauge_lines(snapshot) {
+            Ok(lines) => lines,
+            Err(e) => {
+                debug!("StatsD: {:#}", e);
+                return;
+            }
+        };
+
+        for packet in packets(&lines) {
+            if let Err(e) = self.socket.send_to(packet.as_bytes(), self.addr) {
+                if !self.failing {
+                    warn!(
+                        "StatsD send to {} failed: {} (will keep trying)",
+                        self.addr, e
+                    );
+                    self.failing = true;
+                }
+                return;
+            }
+        }
+        if self.failing {
+            info!("StatsD sends to {} recovered", self.addr);
+            self.failing = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize)]
+    struct Snapshot {
+        gaming_tasks: u64,
+        avg_latency_us: Option<u64>,
+        max_latency_us: Option<u64>,
+        class_dispatches: BTreeMap<&'static str, u64>,
+        split_lock_detect: String,
+    }
+
+    #[test]
+    fn test_gauge_lines() {
+        let snapshot = Snapshot {
+            gaming_tasks: 42,
+            avg_latency_us: Some(120),
+            max_latency_us: None,
+            class_dispatches: BTreeMap::from([("gaming", 7), ("vm-dev", 1)]),
+            split_lock_detect: "warn".to_string(),
+        };
+
+        let lines = gauge_lines(&snapshot).unwrap();
+        assert!(lines.contains(&"ghostbrew.gaming_tasks:42|g".to_string()));
+        assert!(lines.contains(&"ghostbrew.avg_latency_us:120|g".to_string()));
+        assert!(lines.contains(&"ghostbrew.class_dispatches.gaming:7|g".to_string()));
+        assert!(lines.contains(&"ghostbrew.class_dispatches.vm-dev:1|g".to_string()));
+        // Unset and non-numeric fields are not sent
+        assert_eq!(lines.len(), 4);
+
+        let many: Vec<String> = (0..200)
+            .map(|i| format!("ghostbrew.m{}:{}|g", i, i))
+            .collect();
+        let packets = packets(&many);
+        assert!(packets.len() > 1);
+        assert!(packets.iter().all(|p| p.len() <= MAX_PACKET_BYTES));
+        assert_eq!(
+            packets.iter().map(|p| p.lines().count()).sum::<usize>(),
+            200
+        );
+    }
+}
@@ -5,14 +5,16 @@
 // Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
 
 use crate::intel::{self, IntelHybridInfo};
+use crate::procfs;
 use anyhow::{Context, Result};
 use log::debug;
+use serde::Serialize;
 use std::collections::BTreeMap;
 use std::fs;
 use std::path::Path;
 
 /// CPU architecture type
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub enum CpuArch {
     /// AMD Zen architecture (optionally with X3D V-Cache)
     AmdZen {
@@ -26,8 +28,40 @@ pub enum CpuArch {
     Generic,
 }
 
+/// How far the detected CCD layout can be trusted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum TopologyConfidence {
+    /// Layout read from sysfs topology files
+    High,
+    /// No die or L3 topology was readable; all CPUs form a single domain,
+    /// or the CCDs are guessed from core IDs
+    Low,
+}
+
+/// Handheld gaming PC built on an AMD APU
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Handheld {
+    SteamDeck,
+    RogAlly,
+    LegionGo,
+}
+
+impl std::fmt::Display for Handheld {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Handheld::SteamDeck => "Steam Deck",
+            Handheld::RogAlly => "ROG Ally",
+            Handheld::LegionGo => "Legion Go",
+        })
+    }
+}
+
+/// Per-CPU CCD, CCX and NUMA node, plus the confidence in that layout
+type CpuLayout = (Vec<u32>, Vec<u32>, Vec<u32>, TopologyConfidence);
+
 /// CPU topology information
 #[allow(dead_code)]
+#[derive(Serialize)]
 pub struct CpuTopology {
     pub nr_cpus: u32,
     pub nr_ccds: u32,
@@ -35,6 +69,9 @@ pub struct CpuTopology {
     pub cpu_to_ccd: Vec<u32>,
     pub cpu_to_ccx: Vec<u32>,
     pub cpu_to_node: Vec<u32>,
+    /// Physical package (socket), renumbered densely from 0
+    pub cpu_to_socket: Vec<u32>,
+    pub nr_sockets: u32,
     pub cpu_to_sibling: Vec<i32>, // SMT sibling CPU (-1 if none)
     pub smt_enabled: bool,
     pub is_x3d: bool,
@@ -45,6 +82,8 @@ pub struct CpuTopology {
     pub pcore_cpus: Vec<u32>,
     pub ecore_cpus: Vec<u32>,
     pub turbo_rankings: Vec<u32>,
+    /// Relative CPU capacity (max 1024), weighted by max frequency and V-Cache
+    pub cpu_capacity: Vec<u32>,
     // Zen 5 specific
     /// AMD Zen generation (4 = Zen 4, 5 = Zen 5), None for non-AMD
     pub zen_generation: Option<u32>,
@@ -54,8 +93,60 @@ pub struct CpuTopology {
     pub vcache_l3_mb: Option<u32>,
     /// Whether this CPU has asymmetric CCD boost (Zen 5 X3D)
     pub asymmetric_ccd_boost: bool,
+    /// Running inside a VM guest (physical topology features disabled)
+    pub is_virtualized: bool,
+    /// Low when detection fell back to a single domain (placement degraded)
+    pub topology_confidence: TopologyConfidence,
+    /// CPUs isolated with isolcpus or nohz_full, kept out of the CCD DSQ pools
+    pub cpu_isolated: Vec<bool>,
+    /// CPUs listed in /sys/devices/system/cpu/online
+    pub cpu_online: Vec<bool>,
+    /// HandheldApu mode: single-CCD APU under a tight TDP, so V-Cache logic
+    /// is off and the gaming EPP boost is toned down
+    pub handheld_apu: Option<Handheld>,
+    /// AMD CCDs of compact (Zen 4c/5c) cores, treated like E-cores for
+    /// batch/AI placement
+    pub compact_ccds: Vec<u32>,
 }
 
+/// DMI vendor prefix and product substring of handheld APUs
+///
+/// Lenovo keeps the machine type in product_name and the marketing name in
+/// product_version, so both are matched.
+const HANDHELD_DMI_PATTERNS: &[(&str, &str, Handheld)] = &[
+    ("Valve", "Jupiter", Handheld::SteamDeck), // LCD
+    ("Valve", "Galileo", Handheld::SteamDeck), // OLED
+    ("ASUSTeK", "ROG Ally", Handheld::RogAlly),
+    ("ASUSTeK", "RC71L", Handheld::RogAlly),
+    ("ASUSTeK", "RC72LA", Handheld::RogAlly), // Ally X
+    ("LENOVO", "Legion Go", Handheld::LegionGo),
+    ("LENOVO", "83E1", Handheld::LegionGo),
+];
+
+/// DMI product/vendor strings reported by common hypervisors
+const VIRTUAL_DMI_PATTERNS: &[&str] = &[
+    "KVM",
+    "QEMU",
+    "VirtualBox",
+    "VMware",
+    "Virtual Machine", // Hyper-V
+    "Xen",
+    "Bochs",
+    "Parallels",
+    "Cloud Hypervisor",
+];
+
+/// Capacity scale, matching the kernel's SCHED_CAPACITY_SCALE
+pub const CAPACITY_SCALE: u32 = 1024;
+
+/// Extra weight for the V-Cache die: it clocks lower but its 96MB L3 makes
+/// up for most of that on cache-bound work
+const VCACHE_CAPACITY_BONUS_PCT: u64 = 10;
+
+/// A CCD boosting to at most this share of the fastest CCD's clock is a
+/// compact-core candidate (percent)
+const COMPACT_FREQ_PCT: u64 = 85;
+
 /// Known X3D processor models
 const X3D_MODELS: &[&str] = &[
     "7800X3D", "7900X3D", "7950X3D", "9800X3D", "9900X3D", "9950X3D",
@@ -65,14 +156,25 @@ const X3D_MODELS: &[&str] = &[
 pub fn detect_topology() -> Result<CpuTopology> {
     let nr_cpus = detect_nr_cpus()?;
     let model_name = detect_model_name()?;
-    let is_x3d = is_x3d_processor(&model_name);
+    let is_virtualized = is_running_in_vm();
+    let handheld_apu = if is_virtualized {
+        None
+    } else {
+        detect_handheld()
+    };
+    // Guests see the host model name but none of its cache/CCD layout
+    let is_x3d = !is_virtualized && handheld_apu.is_none() && is_x3d_processor(&model_name);
     let cpu_family = detect_cpu_family();
 
     debug!("Detected CPU: {}", model_name);
     debug!("Is X3D: {}, CPU family: {}", is_x3d, cpu_family);
 
     // Detect Intel hybrid architecture
-    let intel_info = intel::detect_intel_hybrid(nr_cpus, &model_name)?;
+    let intel_info = if is_virtualized {
+        IntelHybridInfo::default()
+    } else {
+        intel::detect_intel_hybrid(nr_cpus, &model_name)?
+    };
     let is_intel_hybrid = intel_info.is_hybrid;
 
     // Determine Zen generation from CPU family
@@ -113,15 +215,35 @@ pub fn detect_topology() -> Result<CpuTopology> {
 
     // Detect CCD/CCX mapping from sysfs topology
     // For Intel hybrid, we use cluster_id to group P-cores and E-cores
-    let (cpu_to_ccd, cpu_to_ccx, cpu_to_node) = if is_intel_hybrid {
+    let (mut cpu_to_ccd, mut cpu_to_ccx, cpu_to_node, topology_confidence) = if is_intel_hybrid {
         detect_intel_topology(nr_cpus, &intel_info)?
     } else {
         detect_cpu_topology(nr_cpus)?
     };
 
+    // vCPU die IDs don't map to real CCDs, schedule as a single domain
+    if is_virtualized {
+        cpu_to_ccd = vec![0; nr_cpus as usize];
+    }
+
+    // CCX IDs can repeat across CCDs (Intel cluster IDs); make each
+    // (CCD, CCX) pair unique
+    cpu_to_ccx = dense_ccx_ids(&cpu_to_ccd, &cpu_to_ccx);
+
     // Count unique CCDs (or clusters for Intel)
     let nr_ccds = cpu_to_ccd.iter().max().map(|&m| m + 1).unwrap_or(1);
 
+    // Sockets (dual-EPYC and friends); vCPU packages are as fictional as die IDs
+    let cpu_to_socket = if is_virtualized {
+        vec![0; nr_cpus as usize]
+    } else {
+        detect_cpu_sockets(nr_cpus)
+    };
+    let nr_sockets = cpu_to_socket.iter().max().map(|&m| m + 1).unwrap_or(1);
+    if nr_sockets > 1 {
+        debug!("Multi-socket system: {} sockets", nr_sockets);
+    }
+
     // Determine V-Cache CCD for X3D processors
     let vcache_ccd = if is_x3d {
         detect_vcache_ccd(&model_name, nr_ccds)
@@ -151,6 +273,28 @@ pub fn detect_topology() -> Result<CpuTopology> {
     // - Multi-CCD (7900X3D, 7950X3D, 9900X3D, 9950X3D): 96MB on V-Cache CCD, 32MB on regular CCD
     let vcache_l3_mb = if is_x3d { Some(96) } else { None };
 
+    // Relative capacity; guests don't see the host's frequency asymmetry
+    let max_freqs = cpu_max_freqs(nr_cpus);
+    let cpu_capacity = if is_virtualized {
+        vec![CAPACITY_SCALE; nr_cpus as usize]
+    } else {
+        normalize_capacity(&max_freqs, &cpu_to_ccd, vcache_ccd)
+    };
+    debug!("CPU capacities: {:?}", cpu_capacity);
+
+    // Mixed standard/compact AMD parts (Zen 4c/5c CCDs next to full cores)
+    let compact_ccds = if matches!(arch, CpuArch::AmdZen { .. }) && !is_virtualized {
+        compact_ccds(&ccd_profiles(&cpu_to_ccd, &max_freqs, nr_ccds))
+    } else {
+        Vec::new()
+    };
+    if !compact_ccds.is_empty() {
+        debug!("Compact-core CCDs: {:?}", compact_ccds);
+    }
+
+    let cpu_isolated = detect_isolated_cpus(nr_cpus);
+    let cpu_online = detect_online_cpus(nr_cpus);
+
     if asymmetric_ccd_boost {
         debug!(
             "Zen 5 X3D asymmetric boost: V-Cache CCD {:?}, Freq CCD {:?}, L3 {:?}MB",
@@ -170,6 +314,8 @@ pub fn detect_topology() -> Result<CpuTopology> {
         cpu_to_ccd,
         cpu_to_ccx,
         cpu_to_node,
+        cpu_to_socket,
+        nr_sockets,
         cpu_to_sibling,
         smt_enabled,
         is_x3d,
@@ -179,17 +325,84 @@ pub fn detect_topology() -> Result<CpuTopology> {
         pcore_cpus: intel_info.pcore_cpus,
         ecore_cpus: intel_info.ecore_cpus,
         turbo_rankings: intel_info.turbo_rankings,
+        cpu_capacity,
         zen_generation,
         freq_ccd,
         vcache_l3_mb,
         asymmetric_ccd_boost,
+        is_virtualized,
+        topology_confidence,
+        cpu_isolated,
+        cpu_online,
+        handheld_apu,
+        compact_ccds,
     })
 }
 
+/// Handheld named by DMI vendor and product strings
+pub fn handheld_from_dmi(vendor: &str, product: &str) -> Option<Handheld> {
+    HANDHELD_DMI_PATTERNS
+        .iter()
+        .find(|(v, p, _)| vendor.trim().starts_with(v) && product.contains(p))
+        .map(|&(_, _, handheld)| handheld)
+}
+
+/// Detect a handheld APU from /sys/class/dmi/id
+fn detect_handheld() -> Option<Handheld> {
+    let vendor = procfs::read_rooted("/sys/class/dmi/id/sys_vendor").ok()?;
+    ["product_name", "product_version"].iter().find_map(|name| {
+        let product = procfs::read_rooted(format!("/sys/class/dmi/id/{}", name)).ok()?;
+        handheld_from_dmi(&vendor, product.trim())
+    })
+}
+
+/// Check whether we are running inside a VM guest
+///
+/// Uses the CPUID hypervisor bit (exposed as the `hypervisor` cpuinfo flag)
+/// and falls back to DMI product/vendor strings, similar to systemd-detect-virt.
+pub fn is_running_in_vm() -> bool {
+    if let Ok(cpuinfo) = procfs::read_rooted("/proc/cpuinfo")
+        && cpuinfo_has_hypervisor_flag(&cpuinfo)
+    {
+        debug!("Hypervisor flag present in /proc/cpuinfo");
+        return true;
+    }
+
+    for path in [
+        "/sys/class/dmi/id/product_name",
+        "/sys/class/dmi/id/sys_vendor",
+    ] {
+        if let Ok(value) = procfs::read_rooted(path)
+            && is_virtual_dmi_string(value.trim())
+        {
+            debug!("Virtual machine DMI string in {}: {}", path, value.trim());
+            return true;
+        }
+    }
+
+    false
+}
+
+/// Check the first `flags` line of /proc/cpuinfo for the hypervisor bit
+fn cpuinfo_has_hypervisor_flag(cpuinfo: &str) -> bool {
+    cpuinfo
+        .lines()
+        .find(|line| line.starts_with("flags"))
+        .and_then(|line| line.split_once(':'))
+        .is_some_and(|(_, flags)| flags.split_whitespace().any(|f| f == "hypervisor"))
+}
+
+/// Check whether a DMI product/vendor string names a hypervisor
+fn is_virtual_dmi_string(value: &str) -> bool {
+    VIRTUAL_DMI_PATTERNS
+        .iter()
+        .any(|pattern| value.contains(pattern))
+}
+
 /// Detect CPU family from /proc/cpuinfo
 /// Family 25 = Zen 3/4, Family 26 = Zen 5
 fn detect_cpu_family() -> u32 {
-    let Ok(cpuinfo) = fs::read_to_string("/proc/cpuinfo") else {
+    let Ok(cpuinfo) = procfs::read_rooted("/proc/cpuinfo") else {
         return 0;
     };
     for line in cpuinfo.lines() {
@@ -205,7 +418,7 @@ fn detect_cpu_family() -> u32 {
 
 /// Get number of online CPUs
 fn detect_nr_cpus() -> Result<u32> {
-    let online = fs::read_to_string("/sys/devices/system/cpu/online")
+    let online = procfs::read_rooted("/sys/devices/system/cpu/online")
         .context("Failed to read online CPUs")?;
 
     // Parse CPU range like "0-31"
@@ -221,7 +434,7 @@ fn detect_nr_cpus() -> Result<u32> {
 
 /// Get CPU model name
 fn detect_model_name() -> Result<String> {
-    let cpuinfo = fs::read_to_string("/proc/cpuinfo").context("Failed to read /proc/cpuinfo")?;
+    let cpuinfo = procfs::read_rooted("/proc/cpuinfo").context("Failed to read /proc/cpuinfo")?;
 
     for line in cpuinfo.lines() {
         if line.starts_with("model name")
@@ -260,13 +473,11 @@ fn detect_vcache_ccd(model_name: &str, nr_ccds: u32) -> Option<u32> {
 }
 
 /// Detect Intel hybrid topology (cluster mapping for P-core/E-core grouping)
-fn detect_intel_topology(
-    nr_cpus: u32,
-    intel_info: &IntelHybridInfo,
-) -> Result<(Vec<u32>, Vec<u32>, Vec<u32>)> {
+fn detect_intel_topology(nr_cpus: u32, intel_info: &IntelHybridInfo) -> Result<CpuLayout> {
     let mut cpu_to_ccd = vec![0u32; nr_cpus as usize];
     let mut cpu_to_ccx = vec![0u32; nr_cpus as usize];
     let mut cpu_to_node = vec![0u32; nr_cpus as usize];
+    let mut confidence = TopologyConfidence::Low;
 
     // For Intel hybrid, use cluster_id to group CPUs
     // P-cores and E-cores are typically in different clusters
@@ -274,7 +485,13 @@ fn detect_intel_topology(
         let base = format!("/sys/devices/system/cpu/cpu{}/topology", cpu);
 
         // Read cluster ID (groups of cores)
-        let cluster_id = read_topology_file(&format!("{}/cluster_id", base)).unwrap_or(0);
+        let cluster_id = match read_topology_file(&format!("{}/cluster_id", base)) {
+            Ok(id) => {
+                confidence = TopologyConfidence::High;
+                id
+            }
+            Err(_) => 0,
+        };
 
         // Use cluster as CCD equivalent
         cpu_to_ccd[cpu as usize] = cluster_id;
@@ -296,52 +513,81 @@ fn detect_intel_topology(
         );
     }
 
-    Ok((cpu_to_ccd, cpu_to_ccx, cpu_to_node))
+    Ok((cpu_to_ccd, cpu_to_ccx, cpu_to_node, confidence))
 }
 
 /// Detect per-CPU topology (CCD, CCX, NUMA node)
-fn detect_cpu_topology(nr_cpus: u32) -> Result<(Vec<u32>, Vec<u32>, Vec<u32>)> {
-    let amd_layout = detect_amd_ccd_ccx_layout(nr_cpus);
-    let mut cpu_to_ccd = vec![0u32; nr_cpus as usize];
-    let mut cpu_to_ccx = vec![0u32; nr_cpus as usize];
+fn detect_cpu_topology(nr_cpus: u32) -> Result<CpuLayout> {
+    let samples = read_amd_topology_samples(nr_cpus);
+    let core_ids: Vec<Option<u32>> = (0..nr_cpus)
+        .map(|cpu| {
+            read_topology_file(&format!(
+                "/sys/devices/system/cpu/cpu{}/topology/core_id",
+                cpu
+            ))
+            .ok()
+        })
+        .collect();
+    let (cpu_to_ccd, cpu_to_ccx, confidence) = cpu_layout(&samples, &core_ids);
     let mut cpu_to_node = vec![0u32; nr_cpus as usize];
 
     for cpu in 0..nr_cpus {
-        let base = format!("/sys/devices/system/cpu/cpu{}/topology", cpu);
-
-        let die_id = read_topology_file(&format!("{}/die_id", base)).ok();
-        let cluster_id = read_topology_file(&format!("{}/cluster_id", base)).ok();
-        let core_id = read_topology_file(&format!("{}/core_id", base)).unwrap_or(cpu);
-
-        let (ccd, ccx) = amd_layout
-            .as_ref()
-            .map(|layout| {
-                (
-                    layout.cpu_to_ccd[cpu as usize],
-                    layout.cpu_to_ccx[cpu as usize],
-                )
-            })
-            .unwrap_or_else(|| {
-                // Final fallback heuristic: cores 0-7 = CCD0, 8-15 = CCD1, etc.
-                let ccd = core_id / 8;
-                let ccx = cluster_id.unwrap_or(ccd);
-                (ccd, ccx)
-            });
-
-        cpu_to_ccd[cpu as usize] = ccd;
-        cpu_to_ccx[cpu as usize] = ccx;
-
         // NUMA node
         let node = detect_cpu_node(cpu).unwrap_or(0);
         cpu_to_node[cpu as usize] = node;
 
+        let sample = &samples[cpu as usize];
         debug!(
-            "CPU {}: CCD={}, CCX={}, Node={} (die_id={:?}, cluster_id={:?})",
-            cpu, ccd, ccx, node, die_id, cluster_id
+            "CPU {}: CCD={}, CCX={}, Node={} (die_id={:?}, L3={:?})",
+            cpu, cpu_to_ccd[cpu as usize], cpu_to_ccx[cpu as usize], node, sample.die_id, sample.l3
         );
     }
 
-    Ok((cpu_to_ccd, cpu_to_ccx, cpu_to_node))
+    Ok((cpu_to_ccd, cpu_to_ccx, cpu_to_node, confidence))
+}
+
+/// CCD/CCX per CPU from sysfs samples, falling back to a core_id heuristic
+///
+/// With no topology file readable at all, the heuristic would only be
+/// guessing from CPU numbers, so every CPU goes into one domain instead and
+/// the layout is reported as low confidence.
+fn cpu_layout(
+    samples: &[AmdCpuTopologySample],
+    core_ids: &[Option<u32>],
+) -> (Vec<u32>, Vec<u32>, TopologyConfidence) {
+    if let Some(layout) = layout_from_samples(samples) {
+        return (
+            layout.cpu_to_ccd,
+            layout.cpu_to_ccx,
+            TopologyConfidence::High,
+        );
+    }
+
+    let readable = core_ids.iter().any(Option::is_some)
+        || samples.iter().any(|s| {
+            s.die_cpus_list.is_some()
+                || s.package_id.is_some()
+                || s.die_id.is_some()
+                || s.l3.is_some()
+        });
+    if !readable {
+        return (
+            vec![0; samples.len()],
+            vec![0; samples.len()],
+            TopologyConfidence::Low,
+        );
+    }
+
+    // Final fallback heuristic: cores 0-7 = CCD0, 8-15 = CCD1, etc.
+    let cpu_to_ccd: Vec<u32> = core_ids
+        .iter()
+        .enumerate()
+        .map(|(cpu, core_id)| core_id.unwrap_or(cpu as u32) / 8)
+        .collect();
+    let cpu_to_ccx = l3_groups(samples).unwrap_or_else(|| cpu_to_ccd.clone());
+
+    // A guess, not a reading
+    (cpu_to_ccd, cpu_to_ccx, TopologyConfidence::Low)
 }
 
 struct AmdTopologyLayout {
@@ -352,56 +598,73 @@ struct AmdTopologyLayout {
 #[derive(Clone, Debug)]
 struct AmdCpuTopologySample {
     die_cpus_list: Option<String>,
+    package_id: Option<u32>,
     die_id: Option<u32>,
-    cluster_id: Option<u32>,
+    /// CPUs sharing this CPU's L3 (cache/index3/shared_cpu_list), or the L3
+    /// cache id when the list is missing
+    l3: Option<String>,
 }
 
-fn detect_amd_ccd_ccx_layout(nr_cpus: u32) -> Option<AmdTopologyLayout> {
-    let samples = read_amd_topology_samples(nr_cpus)?;
-    layout_from_samples(&samples)
-}
-
-fn read_amd_topology_samples(nr_cpus: u32) -> Option<Vec<AmdCpuTopologySample>> {
+fn read_amd_topology_samples(nr_cpus: u32) -> Vec<AmdCpuTopologySample> {
     let mut samples = Vec::with_capacity(nr_cpus as usize);
 
     for cpu in 0..nr_cpus {
         let base = format!("/sys/devices/system/cpu/cpu{}/topology", cpu);
+        let l3 = format!("/sys/devices/system/cpu/cpu{}/cache/index3", cpu);
         samples.push(AmdCpuTopologySample {
-            die_cpus_list: fs::read_to_string(format!("{}/die_cpus_list", base)).ok(),
+            die_cpus_list: procfs::read_rooted(format!("{}/die_cpus_list", base)).ok(),
+            package_id: read_topology_file(&format!("{}/physical_package_id", base)).ok(),
             die_id: read_topology_file(&format!("{}/die_id", base)).ok(),
-            cluster_id: read_topology_file(&format!("{}/cluster_id", base)).ok(),
+            l3: procfs::read_rooted(format!("{}/shared_cpu_list", l3))
+                .ok()
+                .map(|list| normalize_cpu_list(&list))
+                .filter(|list| !list.is_empty())
+                .or_else(|| {
+                    read_topology_file(&format!("{}/id", l3))
+                        .ok()
+                        .map(|id| format!("id {}", id))
+                }),
         });
     }
 
-    Some(samples)
+    samples
 }
 
 fn layout_from_samples(samples: &[AmdCpuTopologySample]) -> Option<AmdTopologyLayout> {
     let nr_cpus = u32::try_from(samples.len()).ok()?;
     let mut cpu_to_ccd = vec![0u32; nr_cpus as usize];
-    let mut cpu_to_ccx = vec![0u32; nr_cpus as usize];
 
-    if populate_layout_from_die_cpu_lists(samples, &mut cpu_to_ccd, &mut cpu_to_ccx) {
-        return Some(AmdTopologyLayout {
-            cpu_to_ccd,
-            cpu_to_ccx,
-        });
+    if !populate_layout_from_die_cpu_lists(samples, &mut cpu_to_ccd)
+        && !populate_layout_from_die_ids(samples, &mut cpu_to_ccd)
+    {
+        return None;
     }
 
-    if populate_layout_from_die_ids(samples, &mut cpu_to_ccd, &mut cpu_to_ccx) {
-        return Some(AmdTopologyLayout {
-            cpu_to_ccd,
-            cpu_to_ccx,
-        });
-    }
+    let cpu_to_ccx = l3_groups(samples).unwrap_or_else(|| cpu_to_ccd.clone());
+    Some(AmdTopologyLayout {
+        cpu_to_ccd,
+        cpu_to_ccx,
+    })
+}
 
-    None
+/// CCX of each CPU: one per distinct L3, None unless every CPU reports one
+///
+/// A CCX is the set of cores sharing an L3, so this is what splits a Zen 2
+/// CCD in two. (The kernel's cluster_id groups L2 on AMD, not L3.)
+fn l3_groups(samples: &[AmdCpuTopologySample]) -> Option<Vec<u32>> {
+    let mut groups: BTreeMap<&str, u32> = BTreeMap::new();
+    samples
+        .iter()
+        .map(|sample| {
+            let next_id = groups.len() as u32;
+            Some(*groups.entry(sample.l3.as_deref()?).or_insert(next_id))
+        })
+        .collect()
 }
 
 fn populate_layout_from_die_cpu_lists(
     samples: &[AmdCpuTopologySample],
     cpu_to_ccd: &mut [u32],
-    cpu_to_ccx: &mut [u32],
 ) -> bool {
     let mut die_groups: BTreeMap<String, u32> = BTreeMap::new();
     let mut saw_group = false;
@@ -417,21 +680,16 @@ fn populate_layout_from_die_cpu_lists(
         }
 
         let next_id = die_groups.len() as u32;
-        let ccd = *die_groups.entry(normalized).or_insert(next_id);
-        cpu_to_ccd[cpu] = ccd;
-        cpu_to_ccx[cpu] = sanitize_cluster_id(sample.cluster_id).unwrap_or(ccd);
+        cpu_to_ccd[cpu] = *die_groups.entry(normalized).or_insert(next_id);
         saw_group = true;
     }
 
     saw_group && die_groups.len() > 1
 }
 
-fn populate_layout_from_die_ids(
-    samples: &[AmdCpuTopologySample],
-    cpu_to_ccd: &mut [u32],
-    cpu_to_ccx: &mut [u32],
-) -> bool {
-    let mut die_groups: BTreeMap<u32, u32> = BTreeMap::new();
+fn populate_layout_from_die_ids(samples: &[AmdCpuTopologySample], cpu_to_ccd: &mut [u32]) -> bool {
+    // die_id restarts at 0 on every package
+    let mut die_groups: BTreeMap<(u32, u32), u32> = BTreeMap::new();
 
     for (cpu, sample) in samples.iter().enumerate() {
         let Some(die_id) = sample.die_id else {
@@ -439,16 +697,274 @@ fn populate_layout_from_die_ids(
         };
 
         let next_id = die_groups.len() as u32;
-        let ccd = *die_groups.entry(die_id).or_insert(next_id);
-        cpu_to_ccd[cpu] = ccd;
-        cpu_to_ccx[cpu] = sanitize_cluster_id(sample.cluster_id).unwrap_or(ccd);
+        let package = sample.package_id.unwrap_or(0);
+        cpu_to_ccd[cpu] = *die_groups.entry((package, die_id)).or_insert(next_id);
     }
 
     die_groups.len() > 1
 }
 
-fn sanitize_cluster_id(cluster_id: Option<u32>) -> Option<u32> {
-    cluster_id.and_then(|id| (id != u32::MAX).then_some(id))
+/// Renumber CCX IDs densely from 0 so no two CCDs share one
+fn dense_ccx_ids(cpu_to_ccd: &[u32], cpu_to_ccx: &[u32]) -> Vec<u32> {
+    let mut ids: BTreeMap<(u32, u32), u32> = BTreeMap::new();
+    for (&ccd, &ccx) in cpu_to_ccd.iter().zip(cpu_to_ccx) {
+        ids.entry((ccd, ccx)).or_insert(0);
+    }
+    for (next, id) in ids.values_mut().enumerate() {
+        *id = next as u32;
+    }
+    cpu_to_ccd
+        .iter()
+        .zip(cpu_to_ccx)
+        .map(|(&ccd, &ccx)| ids[&(ccd, ccx)])
+        .collect()
+}
+
+/// Number of distinct CCX IDs within each CCD
+fn count_ccx_per_ccd(cpu_to_ccd: &[u32], cpu_to_ccx: &[u32], nr_ccds: u32) -> Vec<usize> {
+    let mut ccxs: Vec<Vec<u32>> = vec![Vec::new(); nr_ccds as usize];
+    for (&ccd, &ccx) in cpu_to_ccd.iter().zip(cpu_to_ccx) {
+        if let Some(list) = ccxs.get_mut(ccd as usize)
+            && !list.contains(&ccx)
+        {
+            list.push(ccx);
+        }
+    }
+    ccxs.iter().map(Vec::len).collect()
+}
+
+/// Read cpuinfo_max_freq (kHz) for each CPU, 0 where cpufreq is unavailable
+pub fn cpu_max_freqs(nr_cpus: u32) -> Vec<u32> {
+    (0..nr_cpus)
+        .map(|cpu| {
+            read_topology_file(&format!(
+                "/sys/devices/system/cpu/cpu{}/cpufreq/cpuinfo_max_freq",
+                cpu
+            ))
+            .unwrap_or(0)
+        })
+        .collect()
+}
+
+/// Max boost and L3 size of one CCD, 0 where unknown
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CcdProfile {
+    pub max_freq_khz: u32,
+    pub l3_kb: u32,
+}
+
+/// L3 size from a cache/index3/size file ("32768K")
+fn parse_cache_size_kb(size: &str) -> Option<u32> {
+    let size = size.trim();
+    if let Some(mb) = size.strip_suffix('M') {
+        return mb.parse::<u32>().ok()?.checked_mul(1024);
+    }
+    size.strip_suffix('K').unwrap_or(size).parse().ok()
+}
+
+/// Fastest max boost and L3 size of each CCD, from its CPUs
+fn ccd_profiles(cpu_to_ccd: &[u32], max_freqs: &[u32], nr_ccds: u32) -> Vec<CcdProfile> {
+    let mut profiles = vec![CcdProfile::default(); nr_ccds as usize];
+    for (cpu, &ccd) in cpu_to_ccd.iter().enumerate() {
+        let Some(profile) = profiles.get_mut(ccd as usize) else {
+            continue;
+        };
+        let freq = max_freqs.get(cpu).copied().unwrap_or(0);
+        profile.max_freq_khz = profile.max_freq_khz.max(freq);
+        if profile.l3_kb == 0 {
+            profile.l3_kb = procfs::read_rooted(format!(
+                "/sys/devices/system/cpu/cpu{}/cache/index3/size",
+                cpu
+            ))
+            .ok()
+            .and_then(|size| parse_cache_size_kb(&size))
+            .unwrap_or(0);
+        }
+    }
+    profiles
+}
+
+/// CCDs of compact ("c") cores: clocked well below the fastest CCD
+/// (COMPACT_FREQ_PCT) with no more L3 than it
+///
+/// The L3 check keeps an X3D V-Cache CCD, which clocks lower but has more
+/// cache, from counting as compact. Unknown frequencies never match.
+pub fn compact_ccds(profiles: &[CcdProfile]) -> Vec<u32> {
+    let Some(fastest) = profiles.iter().max_by_key(|p| p.max_freq_khz) else {
+        return Vec::new();
+    };
+    if fastest.max_freq_khz == 0 {
+        return Vec::new();
+    }
+    (0..profiles.len() as u32)
+        .filter(|&ccd| {
+            let p = &profiles[ccd as usize];
+            p.max_freq_khz > 0
+                && p.max_freq_khz as u64 * 100 <= fastest.max_freq_khz as u64 * COMPACT_FREQ_PCT
+                && (p.l3_kb == 0 || fastest.l3_kb == 0 || p.l3_kb <= fastest.l3_kb)
+        })
+        .collect()
+}
+
+/// Normalize per-CPU max frequencies into capacities (fastest CPU = 1024)
+///
+/// CPUs on the V-Cache CCD get VCACHE_CAPACITY_BONUS_PCT on top of their
+/// frequency weight. CPUs with an unknown frequency count as the fastest.
+pub fn normalize_capacity(
+    max_freqs: &[u32],
+    cpu_to_ccd: &[u32],
+    vcache_ccd: Option<u32>,
+) -> Vec<u32> {
+    let top_freq = max_freqs.iter().copied().max().unwrap_or(0);
+    if top_freq == 0 {
+        return vec![CAPACITY_SCALE; max_freqs.len()];
+    }
+
+    let raw: Vec<u64> = max_freqs
+        .iter()
+        .enumerate()
+        .map(|(cpu, &freq)| {
+            let freq = if freq == 0 { top_freq } else { freq } as u64;
+            let mut weight = freq * CAPACITY_SCALE as u64 / top_freq as u64;
+            if vcache_ccd.is_some() && cpu_to_ccd.get(cpu).copied() == vcache_ccd {
+                weight = weight * (100 + VCACHE_CAPACITY_BONUS_PCT) / 100;
+            }
+            weight
+        })
+        .collect();
+
+    let top = raw.iter().copied().max().unwrap_or(1).max(1);
+    raw.iter()
+        .map(|&w| (w * CAPACITY_SCALE as u64 / top) as u32)
+        .collect()
+}
+
+/// Map each CPU's physical_package_id to a socket index
+fn detect_cpu_sockets(nr_cpus: u32) -> Vec<u32> {
+    let package_ids: Vec<Option<u32>> = (0..nr_cpus)
+        .map(|cpu| {
+            read_topology_file(&format!(
+                "/sys/devices/system/cpu/cpu{}/topology/physical_package_id",
+                cpu
+            ))
+            .ok()
+        })
+        .collect();
+
+    sockets_from_package_ids(&package_ids)
+}
+
+/// Renumber package IDs densely in order of first appearance
+///
+/// Package IDs are not guaranteed to be contiguous; unreadable ones are
+/// treated as package 0.
+fn sockets_from_package_ids(package_ids: &[Option<u32>]) -> Vec<u32> {
+    let mut sockets: BTreeMap<u32, u32> = BTreeMap::new();
+
+    package_ids
+        .iter()
+        .map(|id| {
+            let next_id = sockets.len() as u32;
+            *sockets.entry(id.unwrap_or(0)).or_insert(next_id)
+        })
+        .collect()
+}
+
+/// Read the isolcpus and nohz_full cpulists into a per-CPU isolation mask
+fn detect_isolated_cpus(nr_cpus: u32) -> Vec<bool> {
+    let read = |name: &str| {
+        procfs::read_rooted(format!("/sys/devices/system/cpu/{}", name)).unwrap_or_default()
+    };
+    isolated_mask(&read("isolated"), &read("nohz_full"), nr_cpus)
+}
+
+/// Per-CPU online mask; all online when the list can't be read
+fn detect_online_cpus(nr_cpus: u32) -> Vec<bool> {
+    let Ok(online) = procfs::read_rooted("/sys/devices/system/cpu/online") else {
+        return vec![true; nr_cpus as usize];
+    };
+    let mut mask = vec![false; nr_cpus as usize];
+    for cpu in parse_cpu_list(&online) {
+        if let Some(slot) = mask.get_mut(cpu as usize) {
+            *slot = true;
+        }
+    }
+    mask
+}
+
+/// Per-CPU isolation mask from the isolated and nohz_full cpulists
+///
+/// nohz_full reads "(null)" when unset, which parses as an empty list.
+fn isolated_mask(isolated: &str, nohz_full: &str, nr_cpus: u32) -> Vec<bool> {
+    let mut mask = vec![false; nr_cpus as usize];
+    for cpu in parse_cpu_list(isolated)
+        .into_iter()
+        .chain(parse_cpu_list(nohz_full))
+    {
+        if let Some(slot) = mask.get_mut(cpu as usize) {
+            *slot = true;
+        }
+    }
+    mask
+}
+
+impl CpuTopology {
+    /// Efficiency cores for batch/AI placement: Intel E-cores, else the
+    /// CPUs of AMD compact-core CCDs
+    pub fn efficiency_core_cpus(&self) -> Vec<u32> {
+        if !self.ecore_cpus.is_empty() {
+            return self.ecore_cpus.clone();
+        }
+        (0..self.nr_cpus)
+            .filter(|&cpu| {
+                self.cpu_to_ccd
+                    .get(cpu as usize)
+                    .is_some_and(|ccd| self.compact_ccds.contains(ccd))
+            })
+            .collect()
+    }
+
+    /// CPUs isolated with isolcpus or nohz_full
+    pub fn isolated_cpus(&self) -> Vec<u32> {
+        (0..self.nr_cpus)
+            .filter(|&cpu| self.cpu_isolated.get(cpu as usize) == Some(&true))
+            .collect()
+    }
+
+    /// Physical cores with SMT enabled and cores running a single thread
+    pub fn smt_core_counts(&self) -> (usize, usize) {
+        count_smt_cores(&self.cpu_to_sibling, &self.cpu_online)
+    }
+
+    /// Number of distinct CCXs (separate L3 domains) in each CCD
+    pub fn ccx_per_ccd(&self) -> Vec<usize> {
+        count_ccx_per_ccd(&self.cpu_to_ccd, &self.cpu_to_ccx, self.nr_ccds)
+    }
+
+    /// Whether any CCD is split into more than one CCX (L3 domain), as on
+    /// Zen 2
+    pub fn has_split_ccx(&self) -> bool {
+        self.ccx_per_ccd().iter().any(|&n| n > 1)
+    }
+
+    /// NUMA node and socket of each CCD, taken from its first CPU
+    pub fn ccd_domains(&self) -> Vec<(u32, u32)> {
+        let mut domains = vec![(0, 0); self.nr_ccds as usize];
+        let mut seen = vec![false; self.nr_ccds as usize];
+
+        for (cpu, &ccd) in self.cpu_to_ccd.iter().enumerate() {
+            let ccd = ccd as usize;
+            if ccd < domains.len() && !seen[ccd] {
+                seen[ccd] = true;
+                domains[ccd] = (
+                    self.cpu_to_node.get(cpu).copied().unwrap_or(0),
+                    self.cpu_to_socket.get(cpu).copied().unwrap_or(0),
+                );
+            }
+        }
+
+        domains
+    }
 }
 
 fn normalize_cpu_list(list: &str) -> String {
@@ -457,48 +973,336 @@ fn normalize_cpu_list(list: &str) -> String {
 
 /// Read a topology file and parse as u32
 fn read_topology_file(path: &str) -> Result<u32> {
-    let content = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path))?;
+    let content = procfs::read_rooted(path).with_context(|| format!("Failed to read {}", path))?;
     content
         .trim()
         .parse()
         .with_context(|| format!("Failed to parse {}", path))
 }
 
-/// Detect SMT siblings for all CPUs
-fn detect_smt_siblings(nr_cpus: u32) -> Result<(Vec<i32>, bool)> {
-    let mut cpu_to_sibling = vec![-1i32; nr_cpus as usize];
-    let mut smt_enabled = false;
+/// Per-CPU idle and total jiffies from /proc/stat
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CpuJiffies {
+    /// idle + iowait
+    pub idle: u64,
+    /// Sum of all accounted states
+    pub total: u64,
+}
 
-    for cpu in 0..nr_cpus {
-        let path = format!(
-            "/sys/devices/system/cpu/cpu{}/topology/thread_siblings_list",
-            cpu
-        );
+/// Parse per-CPU `cpuN` lines from /proc/stat
+fn parse_proc_stat(content: &str, nr_cpus: u32) -> Vec<CpuJiffies> {
+    let mut jiffies = vec![CpuJiffies::default(); nr_cpus as usize];
+
+    for line in content.lines() {
+        let mut fields = line.split_whitespace();
+        let Some(cpu) = fields
+            .next()
+            .and_then(|name| name.strip_prefix("cpu"))
+            .and_then(|id| id.parse::<usize>().ok())
+        else {
+            continue;
+        };
+        if cpu >= jiffies.len() {
+            continue;
+        }
 
-        if let Ok(siblings_str) = fs::read_to_string(&path) {
-            let siblings: Vec<u32> = parse_cpu_list(&siblings_str);
+        let values: Vec<u64> = fields.filter_map(|v| v.parse().ok()).collect();
+        // user nice system idle iowait irq softirq steal ...
+        let idle = values.get(3).copied().unwrap_or(0) + values.get(4).copied().unwrap_or(0);
+        jiffies[cpu] = CpuJiffies {
+            idle,
+            total: values.iter().sum(),
+        };
+    }
 
-            // Find the sibling that isn't this CPU
-            for &sibling in &siblings {
-                if sibling != cpu && sibling < nr_cpus {
-                    cpu_to_sibling[cpu as usize] = sibling as i32;
-                    smt_enabled = true;
-                    break;
-                }
-            }
+    jiffies
+}
+
+/// Aggregate per-CPU idle deltas into an approximate idle-CPU count per CCD
+///
+/// Each CPU contributes its idle fraction over the interval, so two
+/// half-idle CPUs count as one idle CPU.
+pub fn aggregate_idle_per_ccd(deltas: &[CpuJiffies], cpu_to_ccd: &[u32], nr_ccds: u32) -> Vec<u32> {
+    // Idle fraction in per-mille to stay in integer math
+    let mut idle_permille = vec![0u64; nr_ccds as usize];
+
+    for (cpu, delta) in deltas.iter().enumerate() {
+        let Some(&ccd) = cpu_to_ccd.get(cpu) else {
+            continue;
+        };
+        if ccd >= nr_ccds || delta.total == 0 {
+            continue;
         }
+        idle_permille[ccd as usize] += delta.idle.min(delta.total) * 1000 / delta.total;
+    }
 
-        debug!(
-            "CPU {}: SMT sibling = {}",
-            cpu, cpu_to_sibling[cpu as usize]
-        );
+    idle_permille
+        .iter()
+        .map(|&permille| ((permille + 500) / 1000) as u32)
+        .collect()
+}
+
+/// Queued tasks per CCD DSQ, one entry per CCD (missing readings are 0)
+pub fn ccd_dsq_depths(nr_ccds: u32, depth: impl Fn(u32) -> Option<u64>) -> Vec<u64> {
+    (0..nr_ccds).map(|ccd| depth(ccd).unwrap_or(0)).collect()
+}
+
+/// Stats line for per-CCD DSQ depths, e.g. `CCD0 12 | CCD1 3`
+pub fn format_dsq_depths(depths: &[u64]) -> String {
+    depths
+        .iter()
+        .enumerate()
+        .map(|(ccd, depth)| format!("CCD{} {}", ccd, depth))
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
+/// Approximate idle CPUs per CCD since the previous call
+///
+/// `prev` holds the last /proc/stat sample and is updated in place. The first
+/// call only primes the sample and reports zero idle CPUs everywhere.
+pub fn idle_mask_per_ccd(prev: &mut Vec<CpuJiffies>, cpu_to_ccd: &[u32], nr_ccds: u32) -> Vec<u32> {
+    let nr_cpus = cpu_to_ccd.len() as u32;
+    let Ok(content) = procfs::read_rooted("/proc/stat") else {
+        return vec![0; nr_ccds as usize];
+    };
+    let current = parse_proc_stat(&content, nr_cpus);
+
+    let result = if prev.len() == current.len() {
+        let deltas: Vec<CpuJiffies> = current
+            .iter()
+            .zip(prev.iter())
+            .map(|(cur, old)| CpuJiffies {
+                idle: cur.idle.saturating_sub(old.idle),
+                total: cur.total.saturating_sub(old.total),
+            })
+            .collect();
+        aggregate_idle_per_ccd(&deltas, cpu_to_ccd, nr_ccds)
+    } else {
+        vec![0; nr_ccds as usize]
+    };
+
+    *prev = current;
+    result
+}
+
+/// Map k10temp `Tccd*` label/input pairs to per-CCD temperatures
+///
+/// Labels are 1-based (`Tccd1` is CCD 0); inputs are millidegrees Celsius.
+/// Non-CCD sensors (`Tctl`, `Tdie`) and unparsable inputs are skipped.
+pub fn parse_ccd_temps<'a>(
+    pairs: impl IntoIterator<Item = (&'a str, &'a str)>,
+) -> BTreeMap<u32, i32> {
+    pairs
+        .into_iter()
+        .filter_map(|(label, input)| {
+            let ccd = label.trim().strip_prefix("Tccd")?.parse::<u32>().ok()?;
+            let millideg = input.trim().parse::<i32>().ok()?;
+            Some((ccd.checked_sub(1)?, millideg))
+        })
+        .collect()
+}
+
+/// Read per-CCD temperatures (millidegrees Celsius) from k10temp hwmon
+///
+/// Empty if k10temp is not loaded or the CPU does not report Tccd sensors.
+pub fn ccd_temperatures() -> BTreeMap<u32, i32> {
+    let Ok(paths) = glob::glob(&procfs::rooted("/sys/class/hwmon/hwmon*").to_string_lossy()) else {
+        return BTreeMap::new();
+    };
+
+    for hwmon in paths.flatten() {
+        let is_k10temp =
+            fs::read_to_string(hwmon.join("name")).is_ok_and(|name| name.trim() == "k10temp");
+        if !is_k10temp {
+            continue;
+        }
+
+        let Ok(labels) = glob::glob(&format!("{}/temp*_label", hwmon.display())) else {
+            continue;
+        };
+        let readings: Vec<(String, String)> = labels
+            .flatten()
+            .filter_map(|label_path| {
+                let label = fs::read_to_string(&label_path).ok()?;
+                let input_path = label_path.to_string_lossy().replace("_label", "_input");
+                let input = fs::read_to_string(input_path).ok()?;
+                Some((label, input))
+            })
+            .collect();
+
+        return parse_ccd_temps(readings.iter().map(|(l, i)| (l.as_str(), i.as_str())));
     }
 
+    BTreeMap::new()
+}
+
+/// Available share by which another node must beat the current AI node to move
+const NODE_SWITCH_MARGIN: f64 = 0.10;
+
+/// Memory of one NUMA node, from its sysfs meminfo
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct NodeMemory {
+    pub node: u32,
+    pub total_kb: u64,
+    /// MemAvailable-style estimate: free plus reclaimable cache
+    pub available_kb: u64,
+}
+
+impl NodeMemory {
+    /// Share of the node's memory that is available (0.0 when unknown)
+    pub fn available_share(&self) -> f64 {
+        if self.total_kb == 0 {
+            return 0.0;
+        }
+        self.available_kb as f64 / self.total_kb as f64
+    }
+}
+
+/// MemTotal and available memory (kB) from a node meminfo
+///
+/// Lines look like `Node 0 MemFree:  1234 kB`. Node meminfo has no
+/// MemAvailable, so it is estimated the way the kernel does for the whole
+/// system: MemFree plus half of the file cache and half of reclaimable slab
+/// (without the watermark reserve). A node full of page cache is not short
+/// of memory.
+pub fn parse_node_meminfo(content: &str) -> Option<(u64, u64)> {
+    let field = |name: &str| {
+        content.lines().find_map(|line| {
+            let mut parts = line.split_whitespace().skip(2);
+            (parts.next()? == name).then(|| parts.next()?.parse::<u64>().ok())?
+        })
+    };
+    let cache = field("Active(file):").unwrap_or(0) + field("Inactive(file):").unwrap_or(0);
+    let slab = field("SReclaimable:").unwrap_or(0);
+    let available = field("MemFree:")? + cache / 2 + slab / 2;
+    let total = field("MemTotal:")?;
+    Some((total, available.min(total)))
+}
+
+/// Memory of each NUMA node, ordered by node
+pub fn node_memory_pressure() -> Vec<NodeMemory> {
+    let pattern = procfs::rooted("/sys/devices/system/node/node[0-9]*");
+    let Ok(paths) = glob::glob(&pattern.to_string_lossy()) else {
+        return Vec::new();
+    };
+
+    let mut nodes: Vec<NodeMemory> = paths
+        .flatten()
+        .filter_map(|dir| {
+            let node = dir
+                .file_name()?
+                .to_str()?
+                .strip_prefix("node")?
+                .parse()
+                .ok()?;
+            let meminfo = fs::read_to_string(dir.join("meminfo")).ok()?;
+            let (total_kb, available_kb) = parse_node_meminfo(&meminfo)?;
+            Some(NodeMemory {
+                node,
+                total_kb,
+                available_kb,
+            })
+        })
+        .collect();
+    nodes.sort_by_key(|n| n.node);
+    nodes
+}
+
+/// Node AI workloads should use: the one with the largest available share
+///
+/// `current` is kept unless another node has clearly more available memory, so
+/// hints don't bounce between similar nodes. None on single-node systems.
+pub fn least_pressured_node(nodes: &[NodeMemory], current: Option<u32>) -> Option<u32> {
+    if nodes.len() < 2 {
+        return None;
+    }
+    let best = nodes
+        .iter()
+        .max_by(|a, b| a.available_share().total_cmp(&b.available_share()))?;
+    match current.and_then(|node| nodes.iter().find(|n| n.node == node)) {
+        Some(current)
+            if best.available_share() - current.available_share() < NODE_SWITCH_MARGIN =>
+        {
+            Some(current.node)
+        }
+        _ => Some(best.node),
+    }
+}
+
+/// Detect SMT siblings for all CPUs
+///
+/// SMT can be off for individual cores (an offlined sibling), so the result
+/// is per CPU; the flag reports whether any core still has two threads.
+fn detect_smt_siblings(nr_cpus: u32) -> Result<(Vec<i32>, bool)> {
+    let lists: Vec<Option<String>> = (0..nr_cpus)
+        .map(|cpu| {
+            procfs::read_rooted(format!(
+                "/sys/devices/system/cpu/cpu{}/topology/thread_siblings_list",
+                cpu
+            ))
+            .ok()
+        })
+        .collect();
+
+    let cpu_to_sibling = pair_smt_siblings(&lists);
+    for (cpu, sibling) in cpu_to_sibling.iter().enumerate() {
+        debug!("CPU {}: SMT sibling = {}", cpu, sibling);
+    }
+
+    let smt_enabled = cpu_to_sibling.iter().any(|&sibling| sibling >= 0);
     Ok((cpu_to_sibling, smt_enabled))
 }
 
+/// Pair each CPU with its SMT sibling from thread_siblings_list contents
+///
+/// A sibling only counts if it lists the CPU back: an offline sibling has no
+/// readable list, so its core is treated as single-threaded.
+fn pair_smt_siblings(lists: &[Option<String>]) -> Vec<i32> {
+    let siblings: Vec<Vec<u32>> = lists
+        .iter()
+        .map(|list| list.as_deref().map(parse_cpu_list).unwrap_or_default())
+        .collect();
+
+    (0..siblings.len())
+        .map(|cpu| {
+            siblings[cpu]
+                .iter()
+                .copied()
+                .find(|&sibling| {
+                    sibling as usize != cpu
+                        && siblings
+                            .get(sibling as usize)
+                            .is_some_and(|back| back.contains(&(cpu as u32)))
+                })
+                .map_or(-1, |sibling| sibling as i32)
+        })
+        .collect()
+}
+
+/// Count SMT and single-threaded cores among the online CPUs
+///
+/// An offline CPU is no core of its own; the core it belongs to counts as
+/// single-threaded through its online sibling.
+fn count_smt_cores(cpu_to_sibling: &[i32], cpu_online: &[bool]) -> (usize, usize) {
+    let mut smt_cores = 0;
+    let mut single_cores = 0;
+    for (cpu, &sibling) in cpu_to_sibling.iter().enumerate() {
+        if cpu_online.get(cpu) != Some(&true) {
+            continue;
+        }
+        if sibling < 0 {
+            single_cores += 1;
+        } else if (cpu as i32) < sibling {
+            // Count each pair once, from its lower CPU
+            smt_cores += 1;
+        }
+    }
+    (smt_cores, single_cores)
+}
+
 /// Parse a CPU list string like "0,16" or "0-3,16-19" into a Vec of CPU numbers
-fn parse_cpu_list(list: &str) -> Vec<u32> {
+pub fn parse_cpu_list(list: &str) -> Vec<u32> {
     let mut cpus = Vec::new();
 
     for part in list.trim().split(',') {
@@ -519,14 +1323,14 @@ fn parse_cpu_list(list: &str) -> Vec<u32> {
 /// Detect NUMA node for a CPU
 fn detect_cpu_node(cpu: u32) -> Result<u32> {
     let node_path = format!("/sys/devices/system/cpu/cpu{}/node0", cpu);
-    if Path::new(&node_path).exists() {
+    if procfs::rooted(&node_path).exists() {
         return Ok(0);
     }
 
     // Check other nodes
     for node in 0..8 {
         let path = format!("/sys/devices/system/node/node{}/cpulist", node);
-        if let Ok(cpulist) = fs::read_to_string(&path)
+        if let Ok(cpulist) = procfs::read_rooted(&path)
             && cpu_in_list(cpu, &cpulist)
         {
             return Ok(node);
@@ -594,7 +1398,7 @@ impl std::fmt::Display for KernelVersion {
 
 /// Get the current kernel version
 pub fn get_kernel_version() -> Option<KernelVersion> {
-    let release = fs::read_to_string("/proc/sys/kernel/osrelease").ok()?;
+    let release = procfs::read_rooted("/proc/sys/kernel/osrelease").ok()?;
     KernelVersion::parse(release.trim())
 }
 
@@ -649,6 +1453,116 @@ mod tests {
         assert!(!is_x3d_processor("Intel Core i9-14900K"));
     }
 
+    #[test]
+    fn test_cpuinfo_hypervisor_flag() {
+        let guest = "processor\t: 0\nflags\t\t: fpu vme de pse tsc msr hypervisor lahf_lm\n";
+        let host = "processor\t: 0\nflags\t\t: fpu vme de pse tsc msr lahf_lm\n";
+        // "hypervisor" must match a whole flag, not a substring
+        let lookalike = "flags\t\t: fpu not_hypervisor_bit\n";
+
+        assert!(cpuinfo_has_hypervisor_flag(guest));
+        assert!(!cpuinfo_has_hypervisor_flag(host));
+        assert!(!cpuinfo_has_hypervisor_flag(lookalike));
+        assert!(!cpuinfo_has_hypervisor_flag(""));
+
+        assert!(is_virtual_dmi_string("QEMU"));
+        assert!(is_virtual_dmi_string("VMware Virtual Platform"));
+        assert!(!is_virtual_dmi_string("X670E AORUS MASTER"));
+    }
+
+    #[test]
+    fn test_handheld_from_dmi() {
+        assert_eq!(
+            handheld_from_dmi("Valve\n", "Jupiter"),
+            Some(Handheld::SteamDeck)
+        );
+        assert_eq!(
+            handheld_from_dmi("Valve", "Galileo"),
+            Some(Handheld::SteamDeck)
+        );
+        assert_eq!(
+            handheld_from_dmi("ASUSTeK COMPUTER INC.", "ROG Ally RC71L_RC71L"),
+            Some(Handheld::RogAlly)
+        );
+        assert_eq!(
+            handheld_from_dmi("ASUSTeK COMPUTER INC.", "ROG Ally X RC72LA_RC72LA"),
+            Some(Handheld::RogAlly)
+        );
+        assert_eq!(
+            handheld_from_dmi("LENOVO", "83E1"),
+            Some(Handheld::LegionGo)
+        );
+        assert_eq!(
+            handheld_from_dmi("LENOVO", "Legion Go 8APU1"),
+            Some(Handheld::LegionGo)
+        );
+
+        // Desktop boards and other vendors' products are not handhelds
+        assert_eq!(
+            handheld_from_dmi("ASUSTeK COMPUTER INC.", "ROG CROSSHAIR X670E HERO"),
+            None
+        );
+        assert_eq!(handheld_from_dmi("LENOVO", "ThinkPad T14 Gen 4"), None);
+        assert_eq!(
+            handheld_from_dmi("Micro-Star International", "Jupiter"),
+            None
+        );
+        assert_eq!(Handheld::RogAlly.to_string(), "ROG Ally");
+    }
+
+    #[test]
+    fn test_ccd_dsq_depths() {
+        // Entries past nr_ccds are ignored, unreadable ones count as empty
+        let map = [12u64, 3, 99];
+        let depths = ccd_dsq_depths(2, |ccd| map.get(ccd as usize).copied());
+        assert_eq!(depths, vec![12, 3]);
+        assert_eq!(format_dsq_depths(&depths), "CCD0 12 | CCD1 3");
+
+        let depths = ccd_dsq_depths(3, |ccd| (ccd != 1).then_some(5));
+        assert_eq!(depths.len(), 3);
+        assert_eq!(format_dsq_depths(&depths), "CCD0 5 | CCD1 0 | CCD2 5");
+        assert_eq!(format_dsq_depths(&ccd_dsq_depths(1, |_| None)), "CCD0 0");
+    }
+
+    #[test]
+    fn test_aggregate_idle_per_ccd() {
+        // 2 CCDs x 4 CPUs, 100 jiffies per interval
+        let cpu_to_ccd = [0, 0, 0, 0, 1, 1, 1, 1];
+        let deltas: Vec<CpuJiffies> = [100, 100, 0, 0, 50, 50, 25, 0]
+            .iter()
+            .map(|&idle| CpuJiffies { idle, total: 100 })
+            .collect();
+
+        assert_eq!(aggregate_idle_per_ccd(&deltas, &cpu_to_ccd, 2), vec![2, 1]);
+
+        // Offline CPUs (no jiffies) contribute nothing
+        let offline = vec![CpuJiffies::default(); 8];
+        assert_eq!(aggregate_idle_per_ccd(&offline, &cpu_to_ccd, 2), vec![0, 0]);
+    }
+
+    #[test]
+    fn test_parse_proc_stat() {
+        let stat = "cpu  10 0 10 180 0 0 0 0 0 0\n\
+                    cpu0 5 0 5 80 10 0 0 0 0 0\n\
+                    cpu1 5 0 5 90 0 0 0 0 0 0\n\
+                    intr 12345\n";
+        let jiffies = parse_proc_stat(stat, 2);
+        assert_eq!(
+            jiffies[0],
+            CpuJiffies {
+                idle: 90,
+                total: 100
+            }
+        );
+        assert_eq!(
+            jiffies[1],
+            CpuJiffies {
+                idle: 90,
+                total: 100
+            }
+        );
+    }
+
     #[test]
     fn test_cpu_in_list() {
         assert!(cpu_in_list(5, "0-7"));
@@ -670,23 +1584,27 @@ mod tests {
         let samples = vec![
             AmdCpuTopologySample {
                 die_cpus_list: Some("0-7,16-23\n".to_string()),
+                package_id: Some(0),
                 die_id: Some(0),
-                cluster_id: Some(u32::MAX),
+                l3: Some("0-7,16-23".to_string()),
             },
             AmdCpuTopologySample {
                 die_cpus_list: Some("0-7,16-23\n".to_string()),
+                package_id: Some(0),
                 die_id: Some(0),
-                cluster_id: Some(u32::MAX),
+                l3: Some("0-7,16-23".to_string()),
             },
             AmdCpuTopologySample {
                 die_cpus_list: Some("8-15,24-31\n".to_string()),
+                package_id: Some(0),
                 die_id: Some(1),
-                cluster_id: Some(u32::MAX),
+                l3: Some("8-15,24-31".to_string()),
             },
             AmdCpuTopologySample {
                 die_cpus_list: Some("8-15,24-31\n".to_string()),
+                package_id: Some(0),
                 die_id: Some(1),
-                cluster_id: Some(u32::MAX),
+                l3: Some("8-15,24-31".to_string()),
             },
         ];
 
@@ -700,36 +1618,236 @@ mod tests {
         let samples = vec![
             AmdCpuTopologySample {
                 die_cpus_list: None,
+                package_id: Some(0),
                 die_id: Some(0),
-                cluster_id: Some(3),
+                l3: Some("id 3".to_string()),
             },
             AmdCpuTopologySample {
                 die_cpus_list: None,
+                package_id: Some(0),
                 die_id: Some(0),
-                cluster_id: Some(3),
+                l3: Some("id 3".to_string()),
             },
             AmdCpuTopologySample {
                 die_cpus_list: None,
+                package_id: Some(0),
                 die_id: Some(2),
-                cluster_id: Some(7),
+                l3: Some("id 7".to_string()),
             },
             AmdCpuTopologySample {
                 die_cpus_list: None,
+                package_id: Some(0),
                 die_id: Some(2),
-                cluster_id: Some(7),
+                l3: Some("id 7".to_string()),
             },
         ];
 
         let layout = layout_from_samples(&samples).expect("die-id layout should parse");
         assert_eq!(layout.cpu_to_ccd, vec![0, 0, 1, 1]);
-        assert_eq!(layout.cpu_to_ccx, vec![3, 3, 7, 7]);
+        assert_eq!(layout.cpu_to_ccx, vec![0, 0, 1, 1]);
+    }
+
+    #[test]
+    fn test_dual_socket_fixtures() {
+        // Dual-socket: die_id restarts on the second package
+        let samples: Vec<AmdCpuTopologySample> = [(0, 0), (0, 1), (1, 0), (1, 1)]
+            .into_iter()
+            .map(|(package, die)| AmdCpuTopologySample {
+                die_cpus_list: None,
+                package_id: Some(package),
+                die_id: Some(die),
+                l3: None,
+            })
+            .collect();
+
+        let layout = layout_from_samples(&samples).expect("dual-socket layout should parse");
+        assert_eq!(layout.cpu_to_ccd, vec![0, 1, 2, 3]);
+
+        // Two packages produce distinct sockets, sparse IDs are renumbered
+        assert_eq!(
+            sockets_from_package_ids(&[Some(0), Some(0), Some(1), Some(1)]),
+            vec![0, 0, 1, 1]
+        );
+        assert_eq!(
+            sockets_from_package_ids(&[Some(3), Some(7), None, Some(3)]),
+            vec![0, 1, 2, 0]
+        );
+    }
+
+    #[test]
+    fn test_isolated_mask() {
+        // isolcpus=2-3,6 with nohz_full unset
+        let mask = isolated_mask("2-3,6\n", "(null)\n", 8);
+        assert_eq!(
+            mask,
+            vec![false, false, true, true, false, false, true, false]
+        );
+
+        // nohz_full adds to the set, CPUs beyond nr_cpus are ignored
+        let mask = isolated_mask("", "1,7-9\n", 8);
+        assert_eq!(mask.iter().filter(|&&iso| iso).count(), 2);
+        assert!(mask[1] && mask[7]);
+    }
+
+    #[test]
+    fn test_two_ccx_per_ccd() {
+        // Zen 2 style: 2 CCDs, each with two 2-CPU CCXs, told apart by the
+        // CPUs sharing each L3
+        let samples: Vec<AmdCpuTopologySample> =
+            ["0-1", "0-1", "2-3", "2-3", "4-5", "4-5", "6-7", "6-7"]
+                .iter()
+                .enumerate()
+                .map(|(cpu, l3)| AmdCpuTopologySample {
+                    die_cpus_list: Some(if cpu < 4 { "0-3" } else { "4-7" }.to_string()),
+                    package_id: Some(0),
+                    die_id: Some(cpu as u32 / 4),
+                    l3: Some(l3.to_string()),
+                })
+                .collect();
+        let layout = layout_from_samples(&samples).unwrap();
+        let cpu_to_ccd = layout.cpu_to_ccd;
+        assert_eq!(cpu_to_ccd, vec![0, 0, 0, 0, 1, 1, 1, 1]);
+        let cpu_to_ccx = dense_ccx_ids(&cpu_to_ccd, &layout.cpu_to_ccx);
+        assert_eq!(cpu_to_ccx, vec![0, 0, 1, 1, 2, 2, 3, 3]);
+        // Distinct CCX IDs within each CCD, none shared across CCDs
+        assert_ne!(cpu_to_ccx[0], cpu_to_ccx[2]);
+        assert_ne!(cpu_to_ccx[0], cpu_to_ccx[4]);
+        assert_eq!(count_ccx_per_ccd(&cpu_to_ccd, &cpu_to_ccx, 2), vec![2, 2]);
+
+        // Zen 3+: one CCX per CCD
+        let cpu_to_ccx = dense_ccx_ids(&cpu_to_ccd, &cpu_to_ccd);
+        assert_eq!(count_ccx_per_ccd(&cpu_to_ccd, &cpu_to_ccx, 2), vec![1, 1]);
+    }
+
+    #[test]
+    fn test_mixed_smt_siblings() {
+        // Cores 0/4 and 1/5 have SMT; CPU 6 (sibling of 2) is offline; CPU 3
+        // runs alone
+        let lists: Vec<Option<String>> = [
+            Some("0,4"),
+            Some("1,5"),
+            Some("2,6"),
+            Some("3"),
+            Some("0,4"),
+            Some("1,5"),
+            None,
+        ]
+        .iter()
+        .map(|list| list.map(str::to_string))
+        .collect();
+
+        let siblings = pair_smt_siblings(&lists);
+        assert_eq!(siblings, vec![4, 5, -1, -1, 0, 1, -1]);
+        assert!(siblings.iter().any(|&s| s >= 0));
+
+        // The offline CPU 6 is not a core of its own: 2 SMT cores, and
+        // CPUs 2 and 3 running alone
+        let online = [true, true, true, true, true, true, false];
+        assert_eq!(count_smt_cores(&siblings, &online), (2, 2));
+    }
+
+    #[test]
+    fn test_missing_sysfs_low_confidence() {
+        // Nothing readable: one domain instead of a guessed 2-CCD layout
+        let samples = vec![
+            AmdCpuTopologySample {
+                die_cpus_list: None,
+                package_id: None,
+                die_id: None,
+                l3: None,
+            };
+            16
+        ];
+        let (ccd, ccx, confidence) = cpu_layout(&samples, &[None; 16]);
+        assert_eq!(confidence, TopologyConfidence::Low);
+        assert!(ccd.iter().chain(&ccx).all(|&id| id == 0));
+
+        // core_id alone still drives the heuristic, but only as a guess
+        let core_ids: Vec<Option<u32>> = (0..16).map(Some).collect();
+        let (ccd, _, confidence) = cpu_layout(&samples, &core_ids);
+        assert_eq!(confidence, TopologyConfidence::Low);
+        assert_eq!((ccd[7], ccd[8]), (0, 1));
+    }
+
+    #[test]
+    fn test_normalize_capacity() {
+        // 9950X3D-like: V-Cache CCD 0 tops out at 5.25 GHz, freq CCD 1 at 5.7 GHz
+        let freqs = [5_250_000, 5_250_000, 5_700_000, 5_700_000];
+        let ccds = [0, 0, 1, 1];
+
+        // Frequency only: the freq CCD is the reference
+        assert_eq!(
+            normalize_capacity(&freqs, &ccds, None),
+            vec![943, 943, 1024, 1024]
+        );
+
+        // Cache-weighted: 943 * 1.10 = 1037 now leads, freq CCD scales down
+        assert_eq!(
+            normalize_capacity(&freqs, &ccds, Some(0)),
+            vec![1024, 1024, 1011, 1011]
+        );
+
+        // Intel hybrid-like E-cores, unknown frequency counts as fastest
+        assert_eq!(
+            normalize_capacity(&[6_000_000, 4_400_000, 0], &[0, 1, 1], None),
+            vec![1024, 750, 1024]
+        );
+
+        // No cpufreq at all
+        assert_eq!(
+            normalize_capacity(&[0, 0], &[0, 0], Some(0)),
+            vec![1024, 1024]
+        );
+    }
+
+    #[test]
+    fn test_parse_ccd_temps() {
+        // k10temp on a dual-CCD Zen 4: Tctl plus one Tccd per die
+        let pairs = [
+            ("Tctl\n", "71250\n"),
+            ("Tccd1\n", "68500\n"),
+            ("Tccd2\n", "91000\n"),
+            ("Tccd3\n", "garbage\n"),
+            ("Tccd0\n", "50000\n"),
+        ];
+
+        let temps = parse_ccd_temps(pairs);
+        assert_eq!(temps.get(&0), Some(&68500));
+        assert_eq!(temps.get(&1), Some(&91000));
+        assert_eq!(temps.len(), 2);
+
+        // Single-CCD parts without Tccd labels
+        assert!(parse_ccd_temps([("Tctl", "45000")]).is_empty());
     }
 
     #[test]
-    fn test_sanitize_cluster_id_rejects_invalid_value() {
-        assert_eq!(sanitize_cluster_id(Some(u32::MAX)), None);
-        assert_eq!(sanitize_cluster_id(Some(5)), Some(5));
-        assert_eq!(sanitize_cluster_id(None), None);
+    fn test_parse_node_meminfo() {
+        let meminfo = "Node 1 MemTotal:       65536000 kB\n\
+                       Node 1 MemFree:        16384000 kB\n\
+                       Node 1 MemUsed:        49152000 kB\n";
+        assert_eq!(parse_node_meminfo(meminfo), Some((65536000, 16384000)));
+        assert_eq!(parse_node_meminfo("Node 0 MemTotal: 100 kB\n"), None);
+
+        // Page cache and reclaimable slab count half toward available
+        let cached = "Node 0 MemTotal:       65536000 kB\n\
+                      Node 0 MemFree:         1024000 kB\n\
+                      Node 0 Active(file):   20000000 kB\n\
+                      Node 0 Inactive(file): 30000000 kB\n\
+                      Node 0 SReclaimable:    2000000 kB\n";
+        assert_eq!(parse_node_meminfo(cached), Some((65536000, 27024000)));
+
+        let node = |node, available_kb| NodeMemory {
+            node,
+            total_kb: 1000,
+            available_kb,
+        };
+        assert_eq!(node(0, 250).available_share(), 0.25);
+        let nodes = [node(0, 300), node(1, 650)];
+        assert_eq!(least_pressured_node(&nodes, None), Some(1));
+        // A small lead doesn't move workloads off their node
+        let nodes = [node(0, 600), node(1, 650)];
+        assert_eq!(least_pressured_node(&nodes, Some(0)), Some(0));
+        assert_eq!(least_pressured_node(&nodes[..1], None), None);
     }
 
     #[test]
@@ -801,4 +1919,80 @@ mod tests {
                 .any(|m| "AMD Ryzen 9 7950X3D".contains(m))
         );
     }
+
+    #[test]
+    fn test_detect_topology_from_fixture() {
+        let fixture = procfs::Fixture::new("topology");
+        fixture
+            .file(
+                "/proc/cpuinfo",
+                "processor\t: 0\nvendor_id\t: AuthenticAMD\ncpu family\t: 25\n\
+                 model name\t: AMD Ryzen 9 7950X3D 16-Core Processor\nflags\t\t: fpu sse\n",
+            )
+            .file("/sys/devices/system/cpu/online", "0-3\n");
+        for cpu in 0..4 {
+            let base = format!("/sys/devices/system/cpu/cpu{}", cpu);
+            fixture
+                .file(&format!("{}/topology/physical_package_id", base), "0\n")
+                .file(
+                    &format!("{}/topology/die_id", base),
+                    &format!("{}\n", cpu / 2),
+                )
+                .file(&format!("{}/topology/core_id", base), &format!("{}\n", cpu))
+                .file(
+                    &format!("{}/topology/thread_siblings_list", base),
+                    &format!("{}\n", cpu),
+                )
+                .file(
+                    &format!("{}/cpufreq/cpuinfo_max_freq", base),
+                    if cpu < 2 { "5250000\n" } else { "5750000\n" },
+                );
+        }
+
+        let topo = fixture.run(detect_topology).unwrap();
+        assert_eq!(topo.model_name, "AMD Ryzen 9 7950X3D 16-Core Processor");
+        assert_eq!(topo.nr_cpus, 4);
+        assert_eq!(topo.nr_ccds, 2);
+        assert_eq!(topo.cpu_to_ccd, vec![0, 0, 1, 1]);
+        assert_eq!(topo.vcache_ccd, Some(0));
+        assert!(!topo.smt_enabled);
+        assert!(!topo.is_virtualized);
+    }
+
+    #[test]
+    fn test_compact_ccd_detection() {
+        // Zen 5 (5.1GHz, 16MB L3) next to Zen 5c (3.3GHz, 8MB L3)
+        let hybrid = [
+            CcdProfile {
+                max_freq_khz: 5_100_000,
+                l3_kb: 16384,
+            },
+            CcdProfile {
+                max_freq_khz: 3_300_000,
+                l3_kb: 8192,
+            },
+        ];
+        assert_eq!(compact_ccds(&hybrid), vec![1]);
+
+        // 7950X3D: the V-Cache CCD clocks lower but has more L3
+        let x3d = [
+            CcdProfile {
+                max_freq_khz: 5_250_000,
+                l3_kb: 98304,
+            },
+            CcdProfile {
+                max_freq_khz: 5_750_000,
+                l3_kb: 32768,
+            },
+        ];
+        assert!(compact_ccds(&x3d).is_empty());
+
+        // Matching CCDs, or no cpufreq
+        assert!(compact_ccds(&[hybrid[0], hybrid[0]]).is_empty());
+        assert!(compact_ccds(&[CcdProfile::default(); 2]).is_empty());
+
+        assert_eq!(parse_cache_size_kb("16384K\n"), Some(16384));
+        assert_eq!(parse_cache_size_kb("96M"), Some(98304));
+        assert_eq!(parse_cache_size_kb("big"), None);
+    }
 }
@@ -0,0 +1,341 @@
+// SPDX-License-Identifier: GPL-2.0
+//
+// GhostBrew - Task Burst Trace (--trace-pid)
+//
+// Samples a task's CFS/EXT accounting from /proc/<pid>/sched (falling back
+// to /proc/<pid>/schedstat) and compares its measured burst length with the
+// class GhostBrew assigned, to help tune the burst threshold. The slice
+// check applies the same accounting to a few CPU-bound tasks to see how
+// much of slice_ns they actually get.
+//
+// Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
+
+use crate::classify::{self, WorkloadClass};
+use crate::procfs;
+use std::collections::HashMap;
+
+/// Tasks the slice check samples each interval
+pub const SLICE_SAMPLE_TASKS: usize = 8;
+
+/// Cumulative scheduler accounting for one task
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SchedSample {
+    /// Total time on CPU
+    pub runtime_ns: u64,
+    /// Context switches (bursts)
+    pub switches: u64,
+    /// Total time spent runnable but waiting for a CPU
+    pub wait_ns: Option<u64>,
+    /// Number of waits `wait_ns` covers
+    pub waits: Option<u64>,
+}
+
+/// Averages over the time between two samples
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BurstWindow {
+    pub switches: u64,
+    pub avg_burst_ns: u64,
+    /// Average wakeup-to-run latency (None without schedstats)
+    pub avg_wait_ns: Option<u64>,
+}
+
+/// Parse a /proc/sched time field ("5678.123456", in ms) to nanoseconds
+fn parse_ms(value: &str) -> Option<u64> {
+    let (ms, frac) = value.split_once('.').unwrap_or((value, ""));
+    let ms: u64 = ms.parse().ok()?;
+    let frac = format!("{:0<6}", frac);
+    let ns: u64 = frac.get(..6)?.parse().ok()?;
+    Some(ms * 1_000_000 + ns)
+}
+
+/// Parse the fields we need from /proc/<pid>/sched
+///
+/// Wait statistics are only present with schedstats enabled; their prefix is
+/// `se.statistics.` on older kernels and `stats.` on newer ones.
+pub fn parse_proc_sched(text: &str) -> Option<SchedSample> {
+    let mut runtime_ns = None;
+    let mut switches = None;
+    let mut sample = SchedSample::default();
+
+    for line in text.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim() {
+            "se.sum_exec_runtime" => runtime_ns = parse_ms(value),
+            "nr_switches" => switches = value.parse().ok(),
+            key if key.ends_with(".wait_sum") => sample.wait_ns = parse_ms(value),
+            key if key.ends_with(".wait_count") => sample.waits = value.parse().ok(),
+            _ => {}
+        }
+    }
+
+    sample.runtime_ns = runtime_ns?;
+    sample.switches = switches?;
+    Some(sample)
+}
+
+/// Parse /proc/<pid>/schedstat ("runtime_ns run_delay_ns timeslices")
+pub fn parse_schedstat(text: &str) -> Option<SchedSample> {
+    let mut fields = text.split_whitespace().map(|f| f.parse::<u64>().ok());
+    let runtime_ns = fields.next()??;
+    let wait_ns = fields.next()??;
+    let slices = fields.next()??;
+    Some(SchedSample {
+        runtime_ns,
+        switches: slices,
+        wait_ns: Some(wait_ns),
+        waits: Some(slices),
+    })
+}
+
+/// Read a task's accounting, None once it has exited
+pub fn read_sample(pid: u32) -> Option<SchedSample> {
+    let schedstat = || procfs::read_pid_file(pid, "schedstat").and_then(|s| parse_schedstat(&s));
+
+    let Some(mut sample) = procfs::read_pid_file(pid, "sched").and_then(|s| parse_proc_sched(&s))
+    else {
+        // No CONFIG_SCHED_DEBUG
+        return schedstat();
+    };
+    if sample.wait_ns.is_none()
+        && let Some(stat) = schedstat()
+    {
+        sample.wait_ns = stat.wait_ns;
+        sample.waits = stat.waits;
+    }
+    Some(sample)
+}
+
+/// Thread group (process) a thread belongs to
+pub fn read_tgid(pid: u32) -> Option<u32> {
+    procfs::read_pid_file(pid, "status")?
+        .lines()
+        .find_map(|line| line.strip_prefix("Tgid:"))?
+        .trim()
+        .parse()
+        .ok()
+}
+
+impl SchedSample {
+    /// Averages since an earlier sample, None if the task never ran
+    pub fn since(&self, earlier: &SchedSample) -> Option<BurstWindow> {
+        let switches = self.switches.checked_sub(earlier.switches)?;
+        if switches == 0 {
+            return None;
+        }
+        let runtime = self.runtime_ns.saturating_sub(earlier.runtime_ns);
+        let avg_wait_ns = match (self.wait_ns, self.waits, earlier.wait_ns, earlier.waits) {
+            (Some(wait), Some(waits), Some(prev_wait), Some(prev_waits)) => wait
+                .saturating_sub(prev_wait)
+                .checked_div(waits.saturating_sub(prev_waits)),
+            _ => None,
+        };
+        Some(BurstWindow {
+            switches,
+            avg_burst_ns: runtime / switches,
+            avg_wait_ns,
+        })
+    }
+}
+
+/// Burst class a workload class should show (None: nothing to compare)
+pub fn expected_burst_class(class: u32) -> Option<WorkloadClass> {
+    match classify::workload_class_name(class) {
+        "gaming" | "interactive" | "vm-gaming" => Some(WorkloadClass::Interactive),
+        "batch" | "ai" | "vm-dev" | "shader" => Some(WorkloadClass::Batch),
+        _ => None,
+    }
+}
+
+/// Whether the measured burst class fits the assigned one
+pub fn verdict(measured: WorkloadClass, expected: Option<WorkloadClass>) -> &'static str {
+    match expected {
+        None => "burst-classified",
+        Some(expected) if expected == measured => "matches",
+        Some(_) if measured == WorkloadClass::Normal => "borderline",
+        Some(_) => "MISMATCH",
+    }
+}
+
+/// Periodic sampler for one task
+pub struct PidTracer {
+    pub pid: u32,
+    last: Option<SchedSample>,
+}
+
+impl PidTracer {
+    pub fn new(pid: u32) -> Self {
+        Self {
+            pid,
+            last: read_sample(pid),
+        }
+    }
+
+    /// Sample again; the window covers the time since the previous call
+    pub fn sample(&mut self) -> Option<BurstWindow> {
+        let current = read_sample(self.pid)?;
+        let window = self.last.and_then(|last| current.since(&last));
+        self.last = Some(current);
+        window
+    }
+}
+
+/// Median on-CPU time per switch of the sampled tasks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SliceObservation {
+    pub median_ns: u64,
+    /// Tasks that ran during the window
+    pub tasks: usize,
+}
+
+impl SliceObservation {
+    /// Observed median over the configured slice
+    pub fn ratio(&self, slice_ns: u64) -> Option<f64> {
+        slice_ratio(self.median_ns, slice_ns)
+    }
+}
+
+/// Observed slice as a fraction of the configured one, None without a slice
+///
+/// Well under 1.0 means the tasks are preempted or yield long before their
+/// slice runs out, so raising slice_ns changes little.
+pub fn slice_ratio(observed_ns: u64, slice_ns: u64) -> Option<f64> {
+    (slice_ns > 0).then(|| observed_ns as f64 / slice_ns as f64)
+}
+
+/// Median of `values`, None if empty
+fn median(values: &mut [u64]) -> Option<u64> {
+    values.sort_unstable();
+    let mid = values.len() / 2;
+    match values.len() {
+        0 => None,
+        n if n % 2 == 0 => Some((values[mid - 1] + values[mid]) / 2),
+        _ => Some(values[mid]),
+    }
+}
+
+/// Periodic sampler for the slice check
+#[derive(Debug, Default)]
+pub struct SliceSampler {
+    last: HashMap<u32, SchedSample>,
+}
+
+impl SliceSampler {
+    /// Sample the lowest `SLICE_SAMPLE_TASKS` of `pids`; the observation
+    /// covers the time since the previous call
+    pub fn sample(&mut self, pids: impl IntoIterator<Item = u32>) -> Option<SliceObservation> {
+        let mut pids: Vec<u32> = pids.into_iter().collect();
+        pids.sort_unstable();
+        pids.dedup();
+
+        let mut current = HashMap::new();
+        let mut slices = Vec::new();
+        for pid in pids.into_iter().take(SLICE_SAMPLE_TASKS) {
+            let Some(sample) = read_sample(pid) else {
+                continue;
+            };
+            if let Some(window) = self.last.get(&pid).and_then(|last| sample.since(last)) {
+                slices.push(window.avg_burst_ns);
+            }
+            current.insert(pid, sample);
+        }
+        self.last = current;
+
+        let tasks = slices.len();
+        median(&mut slices).map(|median_ns| SliceObservation { median_ns, tasks })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROC_SCHED: &str = "\
+kwin_wayland (1234, #threads: 12)
+-------------------------------------------------------------------
+se.exec_start                                :      98765432.123456
+se.vruntime                                  :         12345.678901
+se.sum_exec_runtime                          :          5678.123456
+se.nr_migrations                             :                  321
+stats.wait_start                             :             0.000000
+stats.wait_sum                               :           250.500000
+stats.wait_count                             :                 5000
+nr_switches                                  :                10000
+nr_voluntary_switches                        :                 9500
+nr_involuntary_switches                      :                  500
+se.load.weight                               :              1048576
+policy                                       :                    7
+prio                                         :                  120
+";
+
+    #[test]
+    fn test_parse_proc_sched() {
+        let sample = parse_proc_sched(PROC_SCHED).unwrap();
+        assert_eq!(sample.runtime_ns, 5_678_123_456);
+        assert_eq!(sample.switches, 10_000);
+        assert_eq!(sample.wait_ns, Some(250_500_000));
+        assert_eq!(sample.waits, Some(5000));
+
+        // Without schedstats the wait fields are missing
+        let plain: String = PROC_SCHED
+            .lines()
+            .filter(|l| !l.starts_with("stats."))
+            .map(|l| format!("{}\n", l))
+            .collect();
+        let sample = parse_proc_sched(&plain).unwrap();
+        assert_eq!(sample.wait_ns, None);
+        assert!(parse_proc_sched("kwin_wayland (1234, #threads: 12)\n").is_none());
+
+        assert_eq!(
+            parse_schedstat("5678123456 250500000 10000\n"),
+            Some(SchedSample {
+                runtime_ns: 5_678_123_456,
+                switches: 10_000,
+                wait_ns: Some(250_500_000),
+                waits: Some(10_000),
+            })
+        );
+
+        // 1000 bursts totalling 500ms: 0.5ms each
+        let later = SchedSample {
+            runtime_ns: 5_678_123_456 + 500_000_000,
+            switches: 11_000,
+            wait_ns: Some(250_500_000 + 10_000_000),
+            waits: Some(5500),
+        };
+        let earlier = parse_proc_sched(PROC_SCHED).unwrap();
+        let window = later.since(&earlier).unwrap();
+        assert_eq!(window.avg_burst_ns, 500_000);
+        assert_eq!(window.avg_wait_ns, Some(20_000));
+        assert!(earlier.since(&earlier).is_none());
+
+        let measured = classify::classify_burst(window.avg_burst_ns, 2_000_000, 1.0);
+        assert_eq!(measured, WorkloadClass::Interactive);
+        let interactive = expected_burst_class(crate::gaming::WORKLOAD_INTERACTIVE);
+        assert_eq!(verdict(measured, interactive), "matches");
+        let batch = expected_burst_class(crate::gaming::WORKLOAD_BATCH);
+        assert_eq!(verdict(measured, batch), "MISMATCH");
+        assert_eq!(verdict(measured, None), "burst-classified");
+    }
+
+    #[test]
+    fn test_slice_ratio() {
+        // Tasks averaging 1.5ms, 2.5ms and 3ms per switch under a 3ms slice
+        let mut slices = [3_000_000, 1_500_000, 2_500_000];
+        let observed = SliceObservation {
+            median_ns: median(&mut slices).unwrap(),
+            tasks: slices.len(),
+        };
+        assert_eq!(observed.median_ns, 2_500_000);
+        let ratio = observed.ratio(3_000_000).unwrap();
+        assert!((ratio - 0.8333).abs() < 0.001);
+
+        assert_eq!(slice_ratio(1_000_000, 2_000_000), Some(0.5));
+        assert_eq!(slice_ratio(3_000_000, 3_000_000), Some(1.0));
+        assert_eq!(slice_ratio(1_000_000, 0), None);
+        assert_eq!(median(&mut [4, 1, 3, 2]), Some(2));
+        assert_eq!(median(&mut []), None);
+    }
+}
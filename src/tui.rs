@@ -0,0 +1,316 @@
+// SPDX-License-Identifier: GPL-2.0
+//
+// GhostBrew - Live Dashboard (--tui)
+//
+// Terminal dashboard for the running scheduler: per-CCD load, event rates,
+// detected workloads, GPU state and the V-Cache mode. Mode toggles are
+// applied as the same commands the control file accepts. Built only with
+// the `tui` feature.
+//
+// Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
+
+use crate::benchmark::Counters;
+use anyhow::Result;
+use ratatui::DefaultTerminal;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph, Row, Table};
+use std::time::Duration;
+
+/// Recent events shown in the events pane
+pub const EVENT_LINES: usize = 6;
+
+/// Load of one CCD as read from the ccd_loads map
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CcdLoad {
+    pub ccd: u32,
+    /// "V-Cache", "Freq" or "CCD"
+    pub label: &'static str,
+    pub nr_tasks: u64,
+    pub nr_gaming: u64,
+}
+
+/// Scheduler state sampled once per stats interval
+#[derive(Debug, Clone, Default)]
+pub struct DashboardSnapshot {
+    pub ccds: Vec<CcdLoad>,
+    /// Cumulative BPF event counters
+    pub counters: Counters,
+    pub gaming_pids: usize,
+    pub batch_pids: usize,
+    pub ai_pids: usize,
+    pub gaming_vcpus: usize,
+    pub containers: usize,
+    pub gpu: Option<String>,
+    pub gpu_active: bool,
+    pub vcache_mode: Option<String>,
+    pub gaming_mode: bool,
+    pub work_mode: bool,
+    /// Latest scheduler events, newest first
+    pub recent_events: Vec<String>,
+}
+
+/// What the widgets render, derived from two consecutive snapshots
+#[derive(Debug, Clone, PartialEq)]
+pub struct DashboardData {
+    pub header: String,
+    /// CCD name, running tasks, gaming tasks
+    pub ccd_rows: Vec<(String, u64, u64)>,
+    /// Counter name and events per second since the previous snapshot
+    pub rates: Vec<(&'static str, f64)>,
+    pub workloads: Vec<(&'static str, usize)>,
+    pub gpu: String,
+    pub events: Vec<String>,
+}
+
+/// Turn snapshots into widget data; rates are 0 until a previous sample exists
+pub fn dashboard_data(
+    prev: Option<&DashboardSnapshot>,
+    cur: &DashboardSnapshot,
+    interval: Duration,
+) -> DashboardData {
+    let on_off = |on: bool| if on { "on" } else { "off" };
+    let header = format!(
+        "Gaming mode: {} | Work mode: {} | V-Cache: {}",
+        on_off(cur.gaming_mode),
+        on_off(cur.work_mode),
+        cur.vcache_mode.as_deref().unwrap_or("n/a")
+    );
+
+    let ccd_rows = cur
+        .ccds
+        .iter()
+        .map(|load| {
+            (
+                format!("{} CCD{}", load.label, load.ccd),
+                load.nr_tasks,
+                load.nr_gaming,
+            )
+        })
+        .collect();
+
+    let secs = interval.as_secs_f64();
+    let rates = cur
+        .counters
+        .iter()
+        .map(|(&name, &value)| {
+            let rate = match prev.and_then(|prev| prev.counters.get(name)) {
+                Some(&before) if secs > 0.0 => value.saturating_sub(before) as f64 / secs,
+                _ => 0.0,
+            };
+            (name, rate)
+        })
+        .collect();
+
+    let workloads = vec![
+        ("Gaming", cur.gaming_pids),
+        ("Dev/batch", cur.batch_pids),
+        ("AI", cur.ai_pids),
+        ("VM gaming vCPUs", cur.gaming_vcpus),
+        ("Containers", cur.containers),
+    ];
+
+    let gpu = match &cur.gpu {
+        Some(summary) => format!(
+            "{} ({})",
+            summary,
+            if cur.gpu_active { "active" } else { "idle" }
+        ),
+        None => "No GPU detected".to_string(),
+    };
+
+    DashboardData {
+        header,
+        ccd_rows,
+        rates,
+        workloads,
+        gpu,
+        events: cur.recent_events.clone(),
+    }
+}
+
+/// Key actions on the dashboard
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DashboardAction {
+    Quit,
+    ToggleGaming,
+    ToggleWork,
+}
+
+/// Terminal session; the terminal is restored on drop
+pub struct Dashboard {
+    terminal: DefaultTerminal,
+}
+
+impl Dashboard {
+    /// Switch the terminal to the alternate screen in raw mode
+    pub fn start() -> Self {
+        Self {
+            terminal: ratatui::init(),
+        }
+    }
+
+    /// Next pending key action, without blocking
+    pub fn poll_action(&self) -> Option<DashboardAction> {
+        while event::poll(Duration::ZERO).ok()? {
+            let Ok(Event::Key(key)) = event::read() else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Some(DashboardAction::Quit),
+                // Raw mode swallows SIGINT
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    return Some(DashboardAction::Quit);
+                }
+                KeyCode::Char('g') => return Some(DashboardAction::ToggleGaming),
+                KeyCode::Char('w') => return Some(DashboardAction::ToggleWork),
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// Render one frame
+    pub fn draw(&mut self, data: &DashboardData) -> Result<()> {
+        self.terminal.draw(|frame| {
+            let [header, ccds, middle, gpu, events, footer] = Layout::vertical([
+                Constraint::Length(3),
+                Constraint::Length(data.ccd_rows.len() as u16 + 3),
+                Constraint::Min(8),
+                Constraint::Length(3),
+                Constraint::Length(EVENT_LINES as u16 + 2),
+                Constraint::Length(1),
+            ])
+            .areas(frame.area());
+            let [rates, workloads] =
+                Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)])
+                    .areas(middle);
+            let bold = Style::default().add_modifier(Modifier::BOLD);
+
+            frame.render_widget(
+                Paragraph::new(data.header.as_str()).block(Block::bordered().title(" GhostBrew ")),
+                header,
+            );
+
+            let rows = data.ccd_rows.iter().map(|(name, tasks, gaming)| {
+                Row::new([name.clone(), tasks.to_string(), gaming.to_string()])
+            });
+            frame.render_widget(
+                Table::new(
+                    rows,
+                    [
+                        Constraint::Fill(2),
+                        Constraint::Fill(1),
+                        Constraint::Fill(1),
+                    ],
+                )
+                .header(Row::new(["CCD", "Tasks", "Gaming"]).style(bold))
+                .block(Block::bordered().title(" Per-CCD load ")),
+                ccds,
+            );
+
+            let rows = data
+                .rates
+                .iter()
+                .map(|(name, rate)| Row::new([name.to_string(), format!("{:.1}/s", rate)]));
+            frame.render_widget(
+                Table::new(rows, [Constraint::Fill(2), Constraint::Fill(1)])
+                    .block(Block::bordered().title(" Event rates ")),
+                rates,
+            );
+
+            let rows = data
+                .workloads
+                .iter()
+                .map(|(name, count)| Row::new([name.to_string(), count.to_string()]));
+            frame.render_widget(
+                Table::new(rows, [Constraint::Fill(2), Constraint::Fill(1)])
+                    .block(Block::bordered().title(" Workloads ")),
+                workloads,
+            );
+
+            frame.render_widget(
+                Paragraph::new(data.gpu.as_str()).block(Block::bordered().title(" GPU ")),
+                gpu,
+            );
+            let lines: Vec<Line> = data.events.iter().map(|e| Line::from(e.as_str())).collect();
+            frame.render_widget(
+                Paragraph::new(lines).block(Block::bordered().title(" Recent events ")),
+                events,
+            );
+            frame.render_widget(
+                Line::from(" q quit | g toggle gaming mode | w toggle work mode"),
+                footer,
+            );
+        })?;
+        Ok(())
+    }
+}
+
+impl Drop for Dashboard {
+    fn drop(&mut self) {
+        ratatui::restore();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dashboard_data() {
+        let mut prev = DashboardSnapshot::default();
+        prev.counters.insert("preempt_kicks", 100);
+        prev.counters.insert("ccd_cross", 50);
+
+        let mut cur = DashboardSnapshot {
+            ccds: vec![
+                CcdLoad {
+                    ccd: 0,
+                    label: "V-Cache",
+                    nr_tasks: 12,
+                    nr_gaming: 4,
+                },
+                CcdLoad {
+                    ccd: 1,
+                    label: "Freq",
+                    nr_tasks: 7,
+                    nr_gaming: 0,
+                },
+            ],
+            gaming_pids: 2,
+            gpu: Some("RTX 4090".to_string()),
+            gpu_active: true,
+            vcache_mode: Some("cache".to_string()),
+            gaming_mode: true,
+            ..Default::default()
+        };
+        cur.counters.insert("preempt_kicks", 160);
+        cur.counters.insert("ccd_cross", 50);
+
+        let data = dashboard_data(Some(&prev), &cur, Duration::from_secs(2));
+        assert_eq!(
+            data.header,
+            "Gaming mode: on | Work mode: off | V-Cache: cache"
+        );
+        assert_eq!(data.ccd_rows[0], ("V-Cache CCD0".to_string(), 12, 4));
+        assert_eq!(data.ccd_rows[1], ("Freq CCD1".to_string(), 7, 0));
+        // Counters are ordered by name (BTreeMap)
+        assert_eq!(
+            data.rates,
+            vec![("ccd_cross", 0.0), ("preempt_kicks", 30.0)]
+        );
+        assert_eq!(data.workloads[0], ("Gaming", 2));
+        assert_eq!(data.gpu, "RTX 4090 (active)");
+        assert!(data.events.is_empty());
+
+        // First frame has nothing to diff against
+        let data = dashboard_data(None, &cur, Duration::from_secs(2));
+        assert!(data.rates.iter().all(|&(_, rate)| rate == 0.0));
+    }
+}
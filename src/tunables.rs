@@ -0,0 +1,113 @@
+// SPDX-License-Identifier: GPL-2.0
+//
+// GhostBrew - Runtime Tunables Map Layout
+//
+// Encodes and decodes the BPF runtime_tunables value so the init and live
+// update paths share one definition of the struct layout.
+//
+// Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
+
+use serde::{Deserialize, Serialize};
+
+/// Size of `struct runtime_tunables` in the BPF program
+pub const RUNTIME_TUNABLES_SIZE: usize = 24;
+
+/// Default --max-preempt-rate: 0 leaves preempt kicks unthrottled
+pub const DEFAULT_MAX_PREEMPT_RATE: u16 = 0;
+
+/// Userspace view of the BPF runtime_tunables map value
+///
+/// Layout: u64 burst_threshold_ns, u64 slice_ns, u8 gaming_mode,
+/// u8 work_mode, u8 power_save_mode, u8 tickless_enabled,
+/// u8 gpu_bound_mode, u8 interactive_pct, u16 max_preempt_rate
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuntimeTunables {
+    pub burst_threshold_ns: u64,
+    pub slice_ns: u64,
+    pub gaming_mode: bool,
+    pub work_mode: bool,
+    pub power_save_mode: u8,
+    pub tickless_enabled: bool,
+    pub gpu_bound_mode: u8,
+    pub interactive_pct: u8,
+    /// Preempt kicks allowed per CPU per second (0 = unlimited)
+    pub max_preempt_rate: u16,
+}
+
+impl RuntimeTunables {
+    /// Decode a map value; short values leave the missing fields at 0
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let mut value = [0u8; RUNTIME_TUNABLES_SIZE];
+        let len = bytes.len().min(RUNTIME_TUNABLES_SIZE);
+        value[..len].copy_from_slice(&bytes[..len]);
+
+        Self {
+            burst_threshold_ns: u64::from_ne_bytes(value[0..8].try_into().unwrap_or([0; 8])),
+            slice_ns: u64::from_ne_bytes(value[8..16].try_into().unwrap_or([0; 8])),
+            gaming_mode: value[16] != 0,
+            work_mode: value[17] != 0,
+            power_save_mode: value[18],
+            tickless_enabled: value[19] != 0,
+            gpu_bound_mode: value[20],
+            interactive_pct: value[21],
+            max_preempt_rate: u16::from_ne_bytes([value[22], value[23]]),
+        }
+    }
+
+    /// Encode as a map value
+    pub fn to_bytes(self) -> [u8; RUNTIME_TUNABLES_SIZE] {
+        let mut value = [0u8; RUNTIME_TUNABLES_SIZE];
+        value[0..8].copy_from_slice(&self.burst_threshold_ns.to_ne_bytes());
+        value[8..16].copy_from_slice(&self.slice_ns.to_ne_bytes());
+        value[16] = self.gaming_mode as u8;
+        value[17] = self.work_mode as u8;
+        value[18] = self.power_save_mode;
+        value[19] = self.tickless_enabled as u8;
+        value[20] = self.gpu_bound_mode;
+        value[21] = self.interactive_pct;
+        value[22..24].copy_from_slice(&self.max_preempt_rate.to_ne_bytes());
+        value
+    }
+}
+
+/// Preempt kicks per CPU per second over a stats interval
+pub fn preempt_kick_rate(kicks: u64, interval_secs: f64, nr_cpus: u32) -> f64 {
+    if interval_secs <= 0.0 || nr_cpus == 0 {
+        return 0.0;
+    }
+    kicks as f64 / interval_secs / nr_cpus as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_max_preempt_rate_plumbing() {
+        // Unlimited unless asked for
+        assert_eq!(RuntimeTunables::default().max_preempt_rate, 0);
+        assert_eq!(DEFAULT_MAX_PREEMPT_RATE, 0);
+
+        let tunables = RuntimeTunables {
+            burst_threshold_ns: 2_000_000,
+            slice_ns: 3_000_000,
+            gaming_mode: true,
+            interactive_pct: 40,
+            max_preempt_rate: 500,
+            ..Default::default()
+        };
+        let bytes = tunables.to_bytes();
+        // Last two bytes of the struct (the old padding)
+        assert_eq!(&bytes[22..24], &500u16.to_ne_bytes());
+        assert_eq!(RuntimeTunables::from_bytes(&bytes), tunables);
+
+        // A value written by an older BPF object keeps the limit off
+        assert_eq!(
+            RuntimeTunables::from_bytes(&bytes[..22]).max_preempt_rate,
+            0
+        );
+
+        assert_eq!(preempt_kick_rate(1600, 2.0, 8), 100.0);
+        assert_eq!(preempt_kick_rate(1600, 2.0, 0), 0.0);
+    }
+}
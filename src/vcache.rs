@@ -10,12 +10,30 @@
 //
 // Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
 
-use anyhow::{Context, Result};
-use log::{debug, info};
+use crate::container;
+use crate::procfs;
+use anyhow::{Context, Result, bail};
+use log::{debug, info, warn};
 use std::fs;
-use std::path::PathBuf;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 
+/// Default --vcache-gaming-threshold: gaming tasks that select cache mode
+pub const DEFAULT_GAMING_THRESHOLD: u32 = 1;
+
+/// Default --vcache-batch-threshold: batch tasks that select frequency mode
+pub const DEFAULT_BATCH_THRESHOLD: u32 = 4;
+
+/// Default --vcache-hysteresis-secs
+pub const DEFAULT_HYSTERESIS_SECS: u64 = 5;
+
+/// Default minimum interval between sysfs reads
+const DEFAULT_CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Placement rate below which stats flag a likely misconfiguration
+pub const LOW_PLACEMENT_PCT: f64 = 50.0;
+
 /// V-Cache operating mode
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum VCacheMode {
@@ -59,6 +77,19 @@ impl VCacheMode {
     }
 }
 
+/// Hardware mode requested by the mode flags (--gaming / --work)
+///
+/// Auto mode (neither flag) leaves the hardware mode alone.
+pub fn forced_mode(gaming: bool, work: bool) -> Option<VCacheMode> {
+    if gaming {
+        Some(VCacheMode::Cache)
+    } else if work {
+        Some(VCacheMode::Frequency)
+    } else {
+        None
+    }
+}
+
 impl std::fmt::Display for VCacheMode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -84,10 +115,52 @@ pub enum SwitchingStrategy {
     FollowGhostVcache,
 }
 
+/// Names accepted by SwitchingStrategy::from_settings
+const STRATEGY_NAMES: [&str; 3] = ["manual", "automatic", "follow_ghost_vcache"];
+
+/// Strategy name as matched: trimmed, lowercase, '-' read as '_'
+fn strategy_key(name: &str) -> String {
+    name.trim().to_lowercase().replace('-', "_")
+}
+
+impl SwitchingStrategy {
+    /// Whether `name` names a strategy
+    pub fn is_known(name: &str) -> bool {
+        STRATEGY_NAMES.contains(&strategy_key(name).as_str())
+    }
+
+    /// Build a strategy from its name ("manual", "automatic",
+    /// "follow_ghost_vcache") and the automatic-mode thresholds
+    pub fn from_settings(name: &str, gaming_threshold: u32, batch_threshold: u32) -> Result<Self> {
+        match strategy_key(name).as_str() {
+            "manual" => Ok(Self::Manual),
+            "follow_ghost_vcache" => Ok(Self::FollowGhostVcache),
+            "automatic" => {
+                if gaming_threshold == 0 {
+                    bail!("--vcache-gaming-threshold must be at least 1");
+                }
+                if batch_threshold == 0 {
+                    bail!("--vcache-batch-threshold must be at least 1");
+                }
+                Ok(Self::Automatic {
+                    gaming_threshold,
+                    batch_threshold,
+                })
+            }
+            other => bail!(
+                "unknown V-Cache strategy '{}' (expected manual, automatic or follow_ghost_vcache)",
+                other
+            ),
+        }
+    }
+}
+
 /// V-Cache controller for ghost-vcache integration
 pub struct VCacheController {
     /// Path to the amd_x3d_mode sysfs file
     sysfs_path: Option<PathBuf>,
+    /// Whether the driver accepts mode writes (false on read-only firmware)
+    switchable: bool,
     /// Current mode (cached)
     current_mode: VCacheMode,
     /// Switching strategy
@@ -102,6 +175,12 @@ pub struct VCacheController {
     stable_since: Option<Instant>,
     /// Target mode for hysteresis
     pending_mode: Option<VCacheMode>,
+    /// Mode found before GhostBrew first changed it (restored on shutdown)
+    original_mode: Option<VCacheMode>,
+    /// Last mode GhostBrew itself wrote, until something else changes it
+    requested_mode: Option<VCacheMode>,
+    /// Whether the last observed change came from outside GhostBrew
+    last_change_external: bool,
 }
 
 impl VCacheController {
@@ -115,32 +194,69 @@ impl VCacheController {
             VCacheMode::Unknown
         };
 
+        let switchable = sysfs_path
+            .as_deref()
+            .is_some_and(|path| probe_switching(path, current_mode));
+
         if sysfs_path.is_some() {
             info!(
                 "V-Cache controller initialized, current mode: {}",
                 current_mode
             );
+            if !switchable {
+                warn!(
+                    "V-Cache: amd_x3d_mode is read-only on this firmware; \
+                     following the mode but not switching it"
+                );
+            }
         } else {
             debug!("V-Cache sysfs interface not found (not an X3D processor?)");
         }
 
         Ok(Self {
             sysfs_path,
+            switchable,
             current_mode,
             strategy: SwitchingStrategy::default(),
             last_check: Instant::now(),
-            check_interval: Duration::from_millis(500),
-            hysteresis_duration: Duration::from_secs(5),
+            check_interval: DEFAULT_CHECK_INTERVAL,
+            hysteresis_duration: Duration::from_secs(DEFAULT_HYSTERESIS_SECS),
             stable_since: None,
             pending_mode: None,
+            original_mode: None,
+            requested_mode: None,
+            last_change_external: false,
         })
     }
 
+    /// Create a controller with V-Cache switching disabled
+    pub fn unavailable() -> Self {
+        Self {
+            sysfs_path: None,
+            switchable: false,
+            current_mode: VCacheMode::Unknown,
+            strategy: SwitchingStrategy::default(),
+            last_check: Instant::now(),
+            check_interval: DEFAULT_CHECK_INTERVAL,
+            hysteresis_duration: Duration::from_secs(DEFAULT_HYSTERESIS_SECS),
+            stable_since: None,
+            pending_mode: None,
+            original_mode: None,
+            requested_mode: None,
+            last_change_external: false,
+        }
+    }
+
     /// Check if V-Cache switching is available
     pub fn is_available(&self) -> bool {
         self.sysfs_path.is_some()
     }
 
+    /// Whether the hardware mode can be changed at runtime
+    pub fn supports_switching(&self) -> bool {
+        self.switchable
+    }
+
     /// Get current V-Cache mode
     pub fn current_mode(&self) -> VCacheMode {
         self.current_mode
@@ -151,9 +267,39 @@ impl VCacheController {
         self.strategy = strategy;
     }
 
+    /// Whether the automatic strategy is installed
+    pub fn is_automatic(&self) -> bool {
+        matches!(self.strategy, SwitchingStrategy::Automatic { .. })
+    }
+
+    /// Set the minimum interval between sysfs reads
+    #[cfg(test)]
+    pub fn set_check_interval(&mut self, interval: Duration) {
+        self.check_interval = interval;
+    }
+
+    /// Set how long the automatic target must hold before switching
+    pub fn set_hysteresis_duration(&mut self, duration: Duration) {
+        self.hysteresis_duration = duration;
+    }
+
+    /// Whether GhostBrew may write the hardware mode (not under manual control)
+    pub fn allows_mode_changes(&self) -> bool {
+        self.strategy != SwitchingStrategy::Manual
+    }
+
+    /// Whether the last mode change was made outside GhostBrew (ghost-vcache
+    /// or a manual write to amd_x3d_mode)
+    pub fn last_change_was_external(&self) -> bool {
+        self.last_change_external
+    }
+
     /// Poll for mode changes from ghost-vcache
     ///
-    /// Returns Some(new_mode) if the mode changed, None otherwise.
+    /// Returns Some(new_mode) if the mode changed, None otherwise. A change to
+    /// anything but the mode GhostBrew last requested is external; it also
+    /// restarts the automatic hysteresis window, so automatic switching
+    /// doesn't immediately undo it.
     pub fn poll_changes(&mut self) -> Option<VCacheMode> {
         if !self.is_available() {
             return None;
@@ -171,7 +317,21 @@ impl VCacheController {
         if new_mode != self.current_mode {
             let old_mode = self.current_mode;
             self.current_mode = new_mode;
-            info!("V-Cache mode changed: {} -> {}", old_mode, new_mode);
+            self.last_change_external = self.requested_mode != Some(new_mode);
+            if self.last_change_external {
+                info!(
+                    "V-Cache mode changed externally (ghost-vcache or manual): {} -> {}",
+                    old_mode, new_mode
+                );
+                self.requested_mode = None;
+                self.pending_mode = None;
+                self.stable_since = None;
+            } else {
+                info!(
+                    "V-Cache mode changed: {} -> {} (requested by GhostBrew)",
+                    old_mode, new_mode
+                );
+            }
             return Some(new_mode);
         }
 
@@ -183,18 +343,41 @@ impl VCacheController {
     /// Note: This requires appropriate permissions (typically root).
     pub fn request_mode(&mut self, mode: VCacheMode) -> Result<()> {
         let path = self.sysfs_path.as_ref().context("V-Cache not available")?;
+        if !self.switchable {
+            bail!("V-Cache mode is read-only on this firmware");
+        }
 
         let mode_str = mode.to_sysfs_str();
         fs::write(path, mode_str).with_context(|| {
             format!("Failed to write V-Cache mode '{}' to {:?}", mode_str, path)
         })?;
 
+        if self.original_mode.is_none() && self.current_mode != VCacheMode::Unknown {
+            self.original_mode = Some(self.current_mode);
+        }
         self.current_mode = mode;
+        self.requested_mode = Some(mode);
+        self.last_change_external = false;
         info!("V-Cache mode set to: {}", mode);
 
         Ok(())
     }
 
+    /// Put back the mode found before GhostBrew first changed it
+    pub fn restore_original(&mut self) {
+        let Some(original) = self.original_mode.take() else {
+            return;
+        };
+        if original == self.current_mode {
+            return;
+        }
+        match self.request_mode(original) {
+            Ok(()) => info!("V-Cache mode restored to: {}", original),
+            Err(e) => warn!("Failed to restore V-Cache mode: {:#}", e),
+        }
+        self.original_mode = None;
+    }
+
     /// Evaluate whether a mode switch is needed based on workload metrics
     ///
     /// For automatic strategy, decides based on gaming task count.
@@ -253,22 +436,13 @@ impl VCacheController {
 
 impl Default for VCacheController {
     fn default() -> Self {
-        Self::new().unwrap_or_else(|_| Self {
-            sysfs_path: None,
-            current_mode: VCacheMode::Unknown,
-            strategy: SwitchingStrategy::default(),
-            last_check: Instant::now(),
-            check_interval: Duration::from_millis(500),
-            hysteresis_duration: Duration::from_secs(5),
-            stable_since: None,
-            pending_mode: None,
-        })
+        Self::new().unwrap_or_else(|_| Self::unavailable())
     }
 }
 
 /// Find the sysfs path for amd_x3d_vcache mode file
 fn find_vcache_sysfs() -> Option<PathBuf> {
-    let base = PathBuf::from("/sys/bus/platform/drivers/amd_x3d_vcache");
+    let base = procfs::rooted("/sys/bus/platform/drivers/amd_x3d_vcache");
 
     if !base.exists() {
         return None;
@@ -304,6 +478,70 @@ fn find_vcache_sysfs() -> Option<PathBuf> {
     None
 }
 
+/// Whether the driver accepts writes to amd_x3d_mode
+///
+/// Read-only firmware exposes the file as 0444, which root could still open,
+/// so the permission bits are checked before writing the current mode back
+/// as a no-op.
+fn probe_switching(path: &Path, current: VCacheMode) -> bool {
+    let writable = fs::metadata(path).is_ok_and(|m| m.permissions().mode() & 0o222 != 0);
+    if !writable {
+        return false;
+    }
+    if current == VCacheMode::Unknown {
+        return true;
+    }
+    fs::write(path, current.to_sysfs_str())
+        .inspect_err(|e| debug!("V-Cache: no-op write to {:?} failed: {}", path, e))
+        .is_ok()
+}
+
+/// Where gaming threads last ran, relative to the V-Cache CCD
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Placement {
+    /// Threads with a readable last CPU
+    pub threads: usize,
+    /// Threads whose last CPU is on the V-Cache CCD
+    pub on_vcache: usize,
+}
+
+impl Placement {
+    /// Count last-run CPUs against the V-Cache CPU set (indexed by CPU)
+    pub fn from_last_cpus(last_cpus: impl IntoIterator<Item = u32>, vcache_cpus: &[bool]) -> Self {
+        let mut placement = Self::default();
+        for cpu in last_cpus {
+            placement.threads += 1;
+            if vcache_cpus.get(cpu as usize).copied().unwrap_or(false) {
+                placement.on_vcache += 1;
+            }
+        }
+        placement
+    }
+
+    /// Percentage of threads on the V-Cache CCD, None without threads
+    pub fn rate_pct(&self) -> Option<f64> {
+        (self.threads > 0).then(|| self.on_vcache as f64 * 100.0 / self.threads as f64)
+    }
+}
+
+/// Sample the last CPU (/proc/<pid>/task/<tid>/stat field 39) of every
+/// thread of the given processes
+///
+/// A snapshot, not a time average: a thread that is asleep reports the CPU
+/// it last ran on.
+pub fn sample_placement(pids: impl IntoIterator<Item = u32>, vcache_cpus: &[bool]) -> Placement {
+    let last_cpus = pids.into_iter().flat_map(|pid| {
+        container::thread_ids(pid)
+            .into_iter()
+            .filter_map(move |tid| {
+                procfs::read_rooted(format!("/proc/{}/task/{}/stat", pid, tid))
+                    .ok()
+                    .and_then(|stat| procfs::parse_stat_processor(&stat))
+            })
+    });
+    Placement::from_last_cpus(last_cpus, vcache_cpus)
+}
+
 /// Read current V-Cache mode from sysfs
 fn read_vcache_mode(path: &PathBuf) -> Result<VCacheMode> {
     let content = fs::read_to_string(path)
@@ -333,9 +571,132 @@ mod tests {
         assert_eq!(VCacheMode::Unknown.to_scheduler_modes(), (true, false));
     }
 
+    #[test]
+    fn test_forced_mode_from_flags() {
+        assert_eq!(forced_mode(true, false), Some(VCacheMode::Cache));
+        // --work / --productivity
+        assert_eq!(forced_mode(false, true), Some(VCacheMode::Frequency));
+        // Auto leaves the hardware mode alone
+        assert_eq!(forced_mode(false, false), None);
+    }
+
+    #[test]
+    fn test_automatic_strategy_from_flags() {
+        assert_eq!(
+            SwitchingStrategy::from_settings("automatic", 2, 8).unwrap(),
+            SwitchingStrategy::Automatic {
+                gaming_threshold: 2,
+                batch_threshold: 8,
+            }
+        );
+        // Thresholds only matter for automatic
+        assert_eq!(
+            SwitchingStrategy::from_settings("follow-ghost-vcache", 0, 0).unwrap(),
+            SwitchingStrategy::FollowGhostVcache
+        );
+        assert_eq!(
+            SwitchingStrategy::from_settings("Manual", 1, 4).unwrap(),
+            SwitchingStrategy::Manual
+        );
+
+        assert!(SwitchingStrategy::from_settings("automatic", 0, 4).is_err());
+        assert!(SwitchingStrategy::from_settings("automatic", 1, 0).is_err());
+        assert!(SwitchingStrategy::from_settings("sometimes", 1, 4).is_err());
+
+        let mut controller = VCacheController::unavailable();
+        controller.set_strategy(SwitchingStrategy::from_settings("automatic", 1, 4).unwrap());
+        controller.set_hysteresis_duration(Duration::from_secs(10));
+        controller.set_check_interval(Duration::from_millis(100));
+        assert!(controller.is_automatic());
+        assert_eq!(controller.hysteresis_duration, Duration::from_secs(10));
+        assert_eq!(controller.check_interval, Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_read_only_mode_disables_switching() {
+        let mode_file = "/sys/bus/platform/drivers/amd_x3d_vcache/AMDI0101:00/amd_x3d_mode";
+        let fixture = procfs::Fixture::new("vcache-probe");
+        fixture.file(mode_file, "cache\n");
+
+        let mut controller = fixture.run(VCacheController::new).unwrap();
+        assert!(controller.is_available());
+        assert!(controller.supports_switching());
+        assert!(controller.request_mode(VCacheMode::Frequency).is_ok());
+
+        let path = fixture.paths.join(mode_file);
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o444)).unwrap();
+        let mut controller = fixture.run(VCacheController::new).unwrap();
+        // X3D present, but the mode cannot be switched
+        assert!(controller.is_available());
+        assert!(!controller.supports_switching());
+        assert_eq!(controller.current_mode(), VCacheMode::Frequency);
+        assert!(controller.request_mode(VCacheMode::Cache).is_err());
+
+        // No X3D at all
+        assert!(!VCacheController::unavailable().supports_switching());
+    }
+
+    #[test]
+    fn test_external_mode_change() {
+        let mode_file = "/sys/bus/platform/drivers/amd_x3d_vcache/AMDI0101:00/amd_x3d_mode";
+        let fixture = procfs::Fixture::new("vcache-source");
+        fixture.file(mode_file, "cache\n");
+        let path = fixture.paths.join(mode_file);
+
+        let mut controller = fixture.run(VCacheController::new).unwrap();
+        controller.set_check_interval(Duration::ZERO);
+
+        // GhostBrew's own switch is not reported back as a change
+        controller.request_mode(VCacheMode::Frequency).unwrap();
+        assert!(!controller.last_change_was_external());
+        assert_eq!(controller.poll_changes(), None);
+
+        // The driver settling on the requested mode late is still internal
+        controller.current_mode = VCacheMode::Cache;
+        assert_eq!(controller.poll_changes(), Some(VCacheMode::Frequency));
+        assert!(!controller.last_change_was_external());
+
+        // `ghost-vcache cache` or `echo cache > amd_x3d_mode`
+        fs::write(&path, "cache\n").unwrap();
+        assert_eq!(controller.poll_changes(), Some(VCacheMode::Cache));
+        assert!(controller.last_change_was_external());
+
+        // Going back to GhostBrew's earlier request is external too
+        fs::write(&path, "frequency\n").unwrap();
+        assert_eq!(controller.poll_changes(), Some(VCacheMode::Frequency));
+        assert!(controller.last_change_was_external());
+    }
+
     #[test]
     fn test_switching_strategy_default() {
         let strategy = SwitchingStrategy::default();
         assert_eq!(strategy, SwitchingStrategy::FollowGhostVcache);
     }
+
+    #[test]
+    fn test_vcache_placement_rate() {
+        // 16 CPUs, CCD 0 (CPUs 0-7) has the V-Cache
+        let vcache_cpus: Vec<bool> = (0..16).map(|cpu| cpu < 8).collect();
+
+        let placement = Placement::from_last_cpus([0, 3, 7, 7, 2, 12, 9, 5], &vcache_cpus);
+        assert_eq!(
+            placement,
+            Placement {
+                threads: 8,
+                on_vcache: 6
+            }
+        );
+        assert_eq!(placement.rate_pct(), Some(75.0));
+
+        // A CPU past the set (hotplugged, unknown) doesn't count as V-Cache
+        let low = Placement::from_last_cpus([8, 15, 40, 1], &vcache_cpus);
+        assert_eq!(low.rate_pct(), Some(25.0));
+        assert!(low.rate_pct().unwrap() < LOW_PLACEMENT_PCT);
+        assert_eq!(Placement::from_last_cpus([], &vcache_cpus).rate_pct(), None);
+
+        // Field 39 of a thread's stat line, comm with spaces and parens
+        let fields: Vec<&str> = (4..=52).map(|n| if n == 39 { "11" } else { "0" }).collect();
+        let stat = format!("4242 (Main (Render)) S {}", fields.join(" "));
+        assert_eq!(procfs::parse_stat_processor(&stat), Some(11));
+    }
 }
@@ -4,8 +4,11 @@
 //
 // Copyright (C) 2025-2026 ghostkellz <ckelley@ghostkellz.sh>
 
-use anyhow::Result;
-use log::{debug, info};
+use crate::pci;
+use crate::procfs;
+use crate::topology;
+use anyhow::{Context, Result};
+use log::{debug, info, warn};
 use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::Path;
@@ -31,8 +34,98 @@ impl std::fmt::Display for VmWorkloadType {
     }
 }
 
+/// Looking Glass buffers above this size indicate an active low-latency session
+const LOOKING_GLASS_STRONG_BYTES: u64 = 32 * 1024 * 1024;
+
+/// IVSHMEM shared-memory device (Looking Glass frame buffer)
+#[derive(Debug, Clone, PartialEq)]
+pub struct IvshmemInfo {
+    /// Shared memory name or backing path (e.g. "looking-glass", "/dev/shm/looking-glass")
+    pub shm_name: String,
+    /// Buffer size in bytes (0 if not specified)
+    pub size_bytes: u64,
+}
+
+impl IvshmemInfo {
+    /// Large buffers (>32MB) are only allocated for high-resolution LG sessions
+    pub fn is_strong_gaming_signal(&self) -> bool {
+        self.size_bytes > LOOKING_GLASS_STRONG_BYTES
+    }
+}
+
+/// Kernel split-lock detection state
+pub const SPLIT_LOCK_DETECT_PATH: &str = "/sys/devices/system/cpu/split_lock_detect";
+
+/// Split-lock detection mode (split_lock_detect= boot parameter)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitLockMode {
+    Off,
+    Warn,
+    Fatal,
+    /// Bus locks are rate limited to N per second
+    Ratelimit(u32),
+    /// Interface not present or unrecognized value
+    Unknown,
+}
+
+impl SplitLockMode {
+    /// Parse the split_lock_detect state file value
+    pub fn parse(s: &str) -> Self {
+        let s = s.trim().to_lowercase();
+        if let Some(rate) = s.strip_prefix("ratelimit:") {
+            return rate
+                .parse()
+                .map(SplitLockMode::Ratelimit)
+                .unwrap_or(SplitLockMode::Unknown);
+        }
+        match s.as_str() {
+            "off" => SplitLockMode::Off,
+            "warn" => SplitLockMode::Warn,
+            "fatal" => SplitLockMode::Fatal,
+            _ => SplitLockMode::Unknown,
+        }
+    }
+
+    /// Whether guests doing split locks get throttled or killed (can cause stutter)
+    pub fn penalizes_guests(self) -> bool {
+        matches!(
+            self,
+            SplitLockMode::Warn | SplitLockMode::Fatal | SplitLockMode::Ratelimit(_)
+        )
+    }
+}
+
+impl std::fmt::Display for SplitLockMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SplitLockMode::Off => write!(f, "off"),
+            SplitLockMode::Warn => write!(f, "warn"),
+            SplitLockMode::Fatal => write!(f, "fatal"),
+            SplitLockMode::Ratelimit(n) => write!(f, "ratelimit:{}", n),
+            SplitLockMode::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+/// Read the host split-lock detection mode
+pub fn read_split_lock_mode() -> SplitLockMode {
+    procfs::read_rooted(SPLIT_LOCK_DETECT_PATH)
+        .map(|s| SplitLockMode::parse(&s))
+        .unwrap_or(SplitLockMode::Unknown)
+}
+
+/// Warn that split-lock detection can stutter a gaming VM (advisory only)
+fn warn_split_lock(mode: SplitLockMode, vm: &VmInfo) {
+    if mode.penalizes_guests() && vm.workload_type == VmWorkloadType::Gaming {
+        warn!(
+            "Gaming VM {} running with split_lock_detect={} - guest split locks may cause stutter (consider split_lock_detect=off)",
+            vm.name, mode
+        );
+    }
+}
+
 /// Information about a detected VM
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[allow(dead_code)]
 pub struct VmInfo {
     /// QEMU process PID
@@ -43,12 +136,113 @@ pub struct VmInfo {
     pub workload_type: VmWorkloadType,
     /// vCPU thread PIDs
     pub vcpu_pids: Vec<u32>,
+    /// IO thread PIDs (`-object iothread`), scheduled as interactive
+    pub iothread_pids: Vec<u32>,
     /// Whether VM has GPU passthrough
     pub has_gpu_passthrough: bool,
     /// PCI addresses of passed-through GPUs
     pub passthrough_gpus: Vec<String>,
     /// Whether vCPUs are pinned (don't override)
     pub vcpus_pinned: bool,
+    /// Looking Glass / IVSHMEM shared-memory device, if any
+    pub ivshmem: Option<IvshmemInfo>,
+    /// Guest paused (QEMU stopped, or libvirt reports the domain paused)
+    pub paused: bool,
+    /// L1 hypervisor: the guest runs (or is set up to run) its own L2 guests
+    pub nested: bool,
+}
+
+impl VmInfo {
+    /// Whether the VM should be promoted to native gaming priority
+    pub fn wants_priority_boost(&self) -> bool {
+        !self.paused
+            && !self.nested
+            && self
+                .ivshmem
+                .as_ref()
+                .is_some_and(IvshmemInfo::is_strong_gaming_signal)
+    }
+}
+
+/// libvirt status files for running QEMU domains
+const LIBVIRT_QEMU_STATE_DIR: &str = "/run/libvirt/qemu";
+
+/// Whether a /proc/[pid]/stat line shows a stopped or traced process
+fn stat_stopped(stat: &str) -> bool {
+    // comm may contain spaces and parens; the state follows the last ')'
+    stat.rsplit_once(')')
+        .and_then(|(_, rest)| rest.trim_start().chars().next())
+        .is_some_and(|state| state == 'T' || state == 't')
+}
+
+/// Domain state from a libvirt status file (`<domstatus state='paused' ...>`)
+fn libvirt_domain_state(status_xml: &str) -> Option<&str> {
+    let start = status_xml.find("<domstatus")?;
+    let tag = &status_xml[start..];
+    let tag = &tag[..tag.find('>')?];
+    let value = &tag[tag.find("state=")? + "state=".len()..];
+    let quote = value.chars().next().filter(|&c| c == '\'' || c == '"')?;
+    value[1..].split(quote).next()
+}
+
+/// Whether a QEMU guest is paused
+///
+/// A SIGSTOPped QEMU shows up in /proc; a guest paused through libvirt
+/// (`virsh suspend`) keeps a sleeping process, so its status file is checked.
+fn check_vm_paused(pid: u32, name: &str) -> bool {
+    if procfs::read_pid_file(pid, "stat").is_some_and(|stat| stat_stopped(&stat)) {
+        return true;
+    }
+    let status = Path::new(LIBVIRT_QEMU_STATE_DIR).join(format!("{}.xml", name));
+    fs::read_to_string(status)
+        .ok()
+        .is_some_and(|xml| libvirt_domain_state(&xml) == Some("paused"))
+}
+
+/// KVM module parameters enabling nested virtualization
+const KVM_NESTED_PARAMS: &[&str] = &[
+    "/sys/module/kvm_amd/parameters/nested",
+    "/sys/module/kvm_intel/parameters/nested",
+];
+
+/// Per-VM KVM statistics (`<qemu pid>-<vm fd>` directories)
+const KVM_DEBUGFS_DIR: &str = "/sys/kernel/debug/kvm";
+
+/// Parse a KVM `nested` parameter: kvm_amd reports 1/0, kvm_intel Y/N
+pub fn parse_nested_param(value: &str) -> bool {
+    matches!(value.trim(), "1" | "Y" | "y")
+}
+
+/// Whether the host KVM module allows nested virtualization
+pub fn nested_virt_enabled() -> bool {
+    KVM_NESTED_PARAMS.iter().any(|path| {
+        procfs::read_rooted(path)
+            .ok()
+            .is_some_and(|value| parse_nested_param(&value))
+    })
+}
+
+/// Whether a QEMU command line hands SVM/VMX to the guest explicitly
+fn exposes_virt_extensions(args: &[&str]) -> bool {
+    args.windows(2)
+        .filter(|pair| pair[0] == "-cpu")
+        .flat_map(|pair| pair[1].split(','))
+        .any(|flag| matches!(flag, "+svm" | "+vmx" | "svm=on" | "vmx=on"))
+}
+
+/// Whether KVM has run L2 guests for this QEMU (needs debugfs)
+fn runs_nested_guest(pid: u32) -> bool {
+    let Ok(entries) = fs::read_dir(procfs::rooted(KVM_DEBUGFS_DIR)) else {
+        return false;
+    };
+    let prefix = format!("{}-", pid);
+    entries.flatten().any(|entry| {
+        entry.file_name().to_string_lossy().starts_with(&prefix)
+            && fs::read_to_string(entry.path().join("nested_run"))
+                .ok()
+                .and_then(|count| count.trim().parse::<u64>().ok())
+                .is_some_and(|count| count > 0)
+    })
 }
 
 /// IOMMU group information
@@ -63,14 +257,14 @@ pub struct IommuGroup {
 /// Detect all IOMMU groups on the system
 pub fn detect_iommu_groups() -> Result<Vec<IommuGroup>> {
     let mut groups = Vec::new();
-    let iommu_path = Path::new("/sys/kernel/iommu_groups");
+    let iommu_path = procfs::rooted("/sys/kernel/iommu_groups");
 
     if !iommu_path.exists() {
         debug!("IOMMU not available");
         return Ok(groups);
     }
 
-    for entry in fs::read_dir(iommu_path)? {
+    for entry in fs::read_dir(&iommu_path)? {
         let entry = entry?;
         let group_name = entry.file_name().to_string_lossy().to_string();
 
@@ -82,7 +276,7 @@ pub fn detect_iommu_groups() -> Result<Vec<IommuGroup>> {
             if devices_path.exists() {
                 for dev_entry in fs::read_dir(&devices_path)? {
                     let dev_entry = dev_entry?;
-                    let pci_addr = dev_entry.file_name().to_string_lossy().to_string();
+                    let pci_addr = pci::normalize(&dev_entry.file_name().to_string_lossy());
 
                     // Check if this is a GPU (class 0x03xxxx)
                     let class_path = dev_entry.path().join("class");
@@ -135,7 +329,7 @@ pub fn detect_iommu_groups() -> Result<Vec<IommuGroup>> {
 pub fn is_vfio_bound(pci_addr: &str) -> bool {
     let driver_path = format!("/sys/bus/pci/devices/{}/driver", pci_addr);
 
-    if let Ok(driver_link) = fs::read_link(&driver_path)
+    if let Ok(driver_link) = fs::read_link(procfs::rooted(&driver_path))
         && let Some(driver_name) = driver_link.file_name()
     {
         return driver_name.to_string_lossy().contains("vfio");
@@ -144,33 +338,41 @@ pub fn is_vfio_bound(pci_addr: &str) -> bool {
     false
 }
 
+/// Whether a PCI device is a display controller (class 0x03xxxx)
+fn is_gpu_device(pci_addr: &str) -> bool {
+    procfs::read_rooted(format!("/sys/bus/pci/devices/{}/class", pci_addr))
+        .is_ok_and(|class| class.trim().starts_with("0x03"))
+}
+
+/// GPUs in the IOMMU groups (audio functions and bridges excluded)
+fn gpu_devices(iommu_groups: &[IommuGroup]) -> impl Iterator<Item = &String> {
+    iommu_groups
+        .iter()
+        .filter(|group| group.has_gpu)
+        .flat_map(|group| &group.devices)
+        .filter(|device| is_gpu_device(device))
+}
+
 /// Get all GPUs bound to vfio-pci (passed through to VMs)
 pub fn get_passthrough_gpus(iommu_groups: &[IommuGroup]) -> Vec<String> {
-    let mut passthrough = Vec::new();
-
-    for group in iommu_groups {
-        if group.has_gpu {
-            for device in &group.devices {
-                if is_vfio_bound(device) {
-                    // Check if it's actually a GPU
-                    let class_path = format!("/sys/bus/pci/devices/{}/class", device);
-                    if let Ok(class) = fs::read_to_string(&class_path)
-                        && class.trim().starts_with("0x03")
-                    {
-                        passthrough.push(device.clone());
-                    }
-                }
-            }
-        }
-    }
+    gpu_devices(iommu_groups)
+        .filter(|device| is_vfio_bound(device))
+        .cloned()
+        .collect()
+}
 
-    passthrough
+/// Whether the host has GPUs and every one is bound to vfio-pci, leaving
+/// the host headless
+pub fn all_gpus_passed_through(iommu_groups: &[IommuGroup]) -> bool {
+    let mut gpus = gpu_devices(iommu_groups).peekable();
+    gpus.peek().is_some() && gpus.all(|device| is_vfio_bound(device))
 }
 
 /// Scan /proc for QEMU/KVM processes
 pub fn scan_vms() -> Result<Vec<VmInfo>> {
     let mut vms = Vec::new();
-    let proc_dir = fs::read_dir("/proc")?;
+    let proc_dir = fs::read_dir(procfs::rooted("/proc"))?;
+    let nested_enabled = nested_virt_enabled();
 
     for entry in proc_dir.flatten() {
         let file_name = entry.file_name();
@@ -182,9 +384,11 @@ pub fn scan_vms() -> Result<Vec<VmInfo>> {
             Err(_) => continue,
         };
 
-        // Check if this is a QEMU process
-        if let Some(vm_info) = check_qemu_process(pid) {
-            vms.push(vm_info);
+        // Check if this is a QEMU process; one unreadable VM doesn't hide the rest
+        match check_qemu_process(pid, nested_enabled) {
+            Ok(Some(vm_info)) => vms.push(vm_info),
+            Ok(None) => {}
+            Err(e) => debug!("Skipping QEMU PID {}: {:#}", pid, e),
         }
     }
 
@@ -192,26 +396,29 @@ pub fn scan_vms() -> Result<Vec<VmInfo>> {
 }
 
 /// Check if a PID is a QEMU process and extract VM info
-fn check_qemu_process(pid: u32) -> Option<VmInfo> {
-    let comm_path = format!("/proc/{}/comm", pid);
-    let comm = fs::read_to_string(&comm_path).ok()?;
+///
+/// None for anything that isn't QEMU; an error for a QEMU process whose
+/// details can't be read.
+fn check_qemu_process(pid: u32, nested_enabled: bool) -> Result<Option<VmInfo>> {
+    let Some(comm) = procfs::read_pid_file(pid, "comm") else {
+        return Ok(None);
+    };
     let comm = comm.trim();
 
     // Check for QEMU process names
     if !comm.contains("qemu") && !comm.contains("kvm") {
-        return None;
+        return Ok(None);
     }
 
     // Read command line for VM details
-    let cmdline_path = format!("/proc/{}/cmdline", pid);
-    let cmdline = fs::read_to_string(&cmdline_path).ok()?;
+    let cmdline = procfs::read_pid_file(pid, "cmdline").context("unreadable cmdline")?;
     let args: Vec<&str> = cmdline.split('\0').collect();
 
     // Extract VM name
     let name = extract_vm_name(&args);
 
-    // Find vCPU threads
-    let vcpu_pids = find_vcpu_threads(pid);
+    // Find vCPU and IO threads
+    let (vcpu_pids, iothread_pids) = find_vcpu_threads(pid);
 
     // Check for vCPU pinning
     let vcpus_pinned = check_vcpu_pinning(pid, &vcpu_pids);
@@ -220,27 +427,52 @@ fn check_qemu_process(pid: u32) -> Option<VmInfo> {
     let passthrough_gpus = detect_vm_passthrough_gpus(&args);
     let has_gpu_passthrough = !passthrough_gpus.is_empty();
 
+    // Detect Looking Glass shared memory
+    let ivshmem = detect_ivshmem(&args);
+
+    let paused = check_vm_paused(pid, &name);
+
     // Classify workload type
-    let workload_type = classify_vm_workload(&args, &name, has_gpu_passthrough);
+    let mut workload_type = classify_vm_workload(&args, &name, has_gpu_passthrough);
+    if ivshmem
+        .as_ref()
+        .is_some_and(IvshmemInfo::is_strong_gaming_signal)
+    {
+        workload_type = VmWorkloadType::Gaming;
+    }
+
+    // vCPUs of an L1 hypervisor mostly run L2 guests: latency guarantees
+    // don't carry through, so they get batch rather than gaming priority
+    let nested = nested_enabled && (exposes_virt_extensions(&args) || runs_nested_guest(pid));
+    if nested && workload_type != VmWorkloadType::Ai {
+        workload_type = VmWorkloadType::Dev;
+    }
 
     debug!(
-        "Detected VM: {} (PID {}) - {} vCPUs, type: {}, GPU: {}",
+        "Detected VM: {} (PID {}) - {} vCPUs, {} iothreads, type: {}, GPU: {}{}{}",
         name,
         pid,
         vcpu_pids.len(),
+        iothread_pids.len(),
         workload_type,
-        has_gpu_passthrough
+        has_gpu_passthrough,
+        if nested { ", nested" } else { "" },
+        if paused { ", paused" } else { "" }
     );
 
-    Some(VmInfo {
+    Ok(Some(VmInfo {
         qemu_pid: pid,
         name,
         workload_type,
         vcpu_pids,
+        iothread_pids,
         has_gpu_passthrough,
         passthrough_gpus,
         vcpus_pinned,
-    })
+        ivshmem,
+        paused,
+        nested,
+    }))
 }
 
 /// Extract VM name from QEMU command line
@@ -261,12 +493,34 @@ fn extract_vm_name(args: &[&str]) -> String {
     "unknown-vm".to_string()
 }
 
-/// Find vCPU thread PIDs for a QEMU process
-fn find_vcpu_threads(qemu_pid: u32) -> Vec<u32> {
+/// Kind of QEMU thread, by comm
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QemuThread {
+    /// vCPU thread ("CPU 0/KVM")
+    Vcpu,
+    /// IO thread for disk/network ("IO iothread1", "IO mon_iothread")
+    IoThread,
+}
+
+/// Classify a QEMU thread by its comm
+pub fn classify_qemu_thread(comm: &str) -> Option<QemuThread> {
+    let comm = comm.trim();
+    if comm.contains("CPU") && comm.contains("KVM") {
+        Some(QemuThread::Vcpu)
+    } else if comm.to_lowercase().contains("iothread") {
+        Some(QemuThread::IoThread)
+    } else {
+        None
+    }
+}
+
+/// Find vCPU and IO thread PIDs for a QEMU process
+fn find_vcpu_threads(qemu_pid: u32) -> (Vec<u32>, Vec<u32>) {
     let mut vcpus = Vec::new();
+    let mut iothreads = Vec::new();
     let task_path = format!("/proc/{}/task", qemu_pid);
 
-    if let Ok(tasks) = fs::read_dir(&task_path) {
+    if let Ok(tasks) = fs::read_dir(procfs::rooted(&task_path)) {
         for task in tasks.flatten() {
             let tid: u32 = match task.file_name().to_string_lossy().parse() {
                 Ok(t) => t,
@@ -275,34 +529,34 @@ fn find_vcpu_threads(qemu_pid: u32) -> Vec<u32> {
 
             // Read thread comm
             let comm_path = format!("/proc/{}/task/{}/comm", qemu_pid, tid);
-            if let Ok(comm) = fs::read_to_string(&comm_path) {
-                let comm = comm.trim();
-                // vCPU threads are named "CPU N/KVM" or similar
-                if comm.contains("CPU") && comm.contains("KVM") {
-                    vcpus.push(tid);
+            if let Ok(comm) = procfs::read_rooted(&comm_path) {
+                match classify_qemu_thread(&comm) {
+                    Some(QemuThread::Vcpu) => vcpus.push(tid),
+                    Some(QemuThread::IoThread) => iothreads.push(tid),
+                    None => {}
                 }
             }
         }
     }
 
-    vcpus
+    (vcpus, iothreads)
 }
 
 /// Check if vCPUs are pinned (via cgroups or taskset)
 fn check_vcpu_pinning(qemu_pid: u32, vcpu_pids: &[u32]) -> bool {
+    let online = procfs::read_rooted("/sys/devices/system/cpu/online")
+        .map(|list| topology::parse_cpu_list(&list))
+        .unwrap_or_default();
+
     // Check if any vCPU has restricted CPU affinity
     for &vcpu_pid in vcpu_pids {
         let status_path = format!("/proc/{}/status", vcpu_pid);
-        if let Ok(status) = fs::read_to_string(&status_path) {
+        if let Ok(status) = procfs::read_rooted(&status_path) {
             for line in status.lines() {
-                if line.starts_with("Cpus_allowed:") {
-                    let hex = line.split(':').nth(1).map(|s| s.trim());
-                    if let Some(hex) = hex {
-                        // If not all Fs, it's pinned
-                        if !hex.chars().all(|c| c == 'f' || c == 'F' || c == ',') {
-                            return true;
-                        }
-                    }
+                if let Some(hex) = line.strip_prefix("Cpus_allowed:")
+                    && is_pinned_mask(hex, &online)
+                {
+                    return true;
                 }
             }
         }
@@ -310,7 +564,7 @@ fn check_vcpu_pinning(qemu_pid: u32, vcpu_pids: &[u32]) -> bool {
 
     // Also check libvirt cgroup for pinning
     let cgroup_path = format!("/proc/{}/cgroup", qemu_pid);
-    if let Ok(cgroup) = fs::read_to_string(&cgroup_path)
+    if let Ok(cgroup) = procfs::read_rooted(&cgroup_path)
         && cgroup.contains("vcpu")
         && cgroup.contains("emulator")
     {
@@ -321,6 +575,31 @@ fn check_vcpu_pinning(qemu_pid: u32, vcpu_pids: &[u32]) -> bool {
     false
 }
 
+/// Parse a Cpus_allowed hex mask ("ff,ffffffff", most significant group first)
+fn parse_cpu_mask(hex: &str) -> Option<HashSet<u32>> {
+    let mut cpus = HashSet::new();
+
+    for (group, word) in hex.trim().split(',').rev().enumerate() {
+        let bits = u32::from_str_radix(word, 16).ok()?;
+        for bit in 0..32 {
+            if bits & (1 << bit) != 0 {
+                cpus.insert(group as u32 * 32 + bit);
+            }
+        }
+    }
+
+    Some(cpus)
+}
+
+/// Whether an affinity mask allows only a proper subset of the online CPUs
+fn is_pinned_mask(hex: &str, online: &[u32]) -> bool {
+    let Some(allowed) = parse_cpu_mask(hex) else {
+        return false;
+    };
+    let allowed_online = online.iter().filter(|cpu| allowed.contains(cpu)).count();
+    allowed_online < online.len()
+}
+
 /// Detect GPU passthrough from QEMU command line
 fn detect_vm_passthrough_gpus(args: &[&str]) -> Vec<String> {
     let mut gpus = Vec::new();
@@ -332,16 +611,13 @@ fn detect_vm_passthrough_gpus(args: &[&str]) -> Vec<String> {
             && device_arg.contains("vfio-pci")
         {
             // Extract host= PCI address
-            for part in device_arg.split(',') {
-                if part.starts_with("host=") {
-                    let addr = part.strip_prefix("host=").unwrap_or("");
-                    // Normalize to full PCI address format
-                    let addr = if addr.contains(':') && !addr.starts_with("0000:") {
-                        format!("0000:{}", addr)
-                    } else {
-                        addr.to_string()
-                    };
-                    gpus.push(addr);
+            for addr in device_arg
+                .split(',')
+                .filter_map(|p| p.strip_prefix("host="))
+            {
+                match pci::parse(addr) {
+                    Some(addr) => gpus.push(addr),
+                    None => debug!("VM: ignoring vfio-pci host={}", addr),
                 }
             }
         }
@@ -350,6 +626,83 @@ fn detect_vm_passthrough_gpus(args: &[&str]) -> Vec<String> {
     gpus
 }
 
+/// Parse a QEMU size value ("32M", "1G", "33554432") into bytes
+fn parse_qemu_size(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let value = value
+        .strip_suffix('B')
+        .or_else(|| value.strip_suffix('b'))
+        .unwrap_or(value);
+    let (digits, multiplier) = match value.chars().last()? {
+        'k' | 'K' => (&value[..value.len() - 1], 1u64 << 10),
+        'm' | 'M' => (&value[..value.len() - 1], 1u64 << 20),
+        'g' | 'G' => (&value[..value.len() - 1], 1u64 << 30),
+        't' | 'T' => (&value[..value.len() - 1], 1u64 << 40),
+        _ => (value, 1),
+    };
+    digits.parse::<u64>().ok()?.checked_mul(multiplier)
+}
+
+/// Parse an `ivshmem`/`ivshmem-plain` device string
+///
+/// Returns (shm name, size in bytes, memdev id) for whichever keys are present.
+fn parse_ivshmem_device(device_arg: &str) -> Option<(Option<String>, Option<u64>, Option<String>)> {
+    let mut parts = device_arg.split(',');
+    if !parts.next()?.starts_with("ivshmem") {
+        return None;
+    }
+
+    let (mut shm, mut size, mut memdev) = (None, None, None);
+    for part in parts {
+        match part.split_once('=') {
+            Some(("shm", v)) => shm = Some(v.to_string()),
+            Some(("size", v)) => size = parse_qemu_size(v),
+            Some(("memdev", v)) => memdev = Some(v.to_string()),
+            _ => {}
+        }
+    }
+    Some((shm, size, memdev))
+}
+
+/// Detect a Looking Glass IVSHMEM device from QEMU command line
+///
+/// `ivshmem-plain` usually references a `-object memory-backend-file` via
+/// `memdev=`, which carries the mem-path and size.
+fn detect_ivshmem(args: &[&str]) -> Option<IvshmemInfo> {
+    for (i, arg) in args.iter().enumerate() {
+        if *arg != "-device" {
+            continue;
+        }
+        let Some((shm, size, memdev)) = args.get(i + 1).and_then(|d| parse_ivshmem_device(d))
+        else {
+            continue;
+        };
+
+        let (mut shm_name, mut size_bytes) = (shm, size);
+        if let Some(memdev) = memdev {
+            let id = format!("id={}", memdev);
+            let backend = args
+                .windows(2)
+                .find(|w| w[0] == "-object" && w[1].split(',').any(|p| p == id))
+                .map(|w| w[1]);
+            for part in backend.into_iter().flat_map(|b| b.split(',')) {
+                match part.split_once('=') {
+                    Some(("mem-path", v)) if shm_name.is_none() => shm_name = Some(v.to_string()),
+                    Some(("size", v)) if size_bytes.is_none() => size_bytes = parse_qemu_size(v),
+                    _ => {}
+                }
+            }
+        }
+
+        return Some(IvshmemInfo {
+            shm_name: shm_name.unwrap_or_else(|| "unknown".to_string()),
+            size_bytes: size_bytes.unwrap_or(0),
+        });
+    }
+
+    None
+}
+
 /// Classify VM workload type based on command line and GPU
 fn classify_vm_workload(args: &[&str], name: &str, has_gpu: bool) -> VmWorkloadType {
     let name_lower = name.to_lowercase();
@@ -406,13 +759,20 @@ pub struct VmMonitor {
     vms: Vec<VmInfo>,
     iommu_groups: Vec<IommuGroup>,
     passthrough_gpus: Vec<String>,
+    /// Every host GPU is passed through to VMs
+    headless_host: bool,
+    split_lock: SplitLockMode,
+    /// Leave explicitly pinned vCPUs to their affinity mask
+    respect_pinning: bool,
 }
 
 impl VmMonitor {
     pub fn new() -> Result<Self> {
         let iommu_groups = detect_iommu_groups()?;
         let passthrough_gpus = get_passthrough_gpus(&iommu_groups);
+        let headless_host = all_gpus_passed_through(&iommu_groups);
         let vms = scan_vms()?;
+        let split_lock = read_split_lock_mode();
 
         if !vms.is_empty() {
             info!("VMs: {} detected", vms.len());
@@ -424,50 +784,103 @@ impl VmMonitor {
                     vm.vcpu_pids.len(),
                     vm.has_gpu_passthrough
                 );
+                if let Some(ref shm) = vm.ivshmem {
+                    info!(
+                        "    Looking Glass: {} ({}MB)",
+                        shm.shm_name,
+                        shm.size_bytes / (1024 * 1024)
+                    );
+                }
+                warn_split_lock(split_lock, vm);
+                if vm.nested {
+                    info!("    Nested virtualization - vCPUs run at batch priority");
+                }
+                if vm.vcpus_pinned {
+                    info!("    vCPUs pinned - leaving placement to the affinity mask");
+                }
             }
         }
 
         if !passthrough_gpus.is_empty() {
             info!("GPU passthrough: {:?}", passthrough_gpus);
         }
+        if headless_host {
+            info!("All host GPUs are passed through; gaming VMs drive EPP boosting");
+        }
 
         Ok(Self {
             vms,
             iommu_groups,
             passthrough_gpus,
+            headless_host,
+            split_lock,
+            respect_pinning: true,
         })
     }
 
     /// Rescan for VMs (call periodically)
+    ///
+    /// /proc is walked once; new, removed and paused VMs are all diffed
+    /// against that one snapshot.
     pub fn rescan(&mut self) -> Result<(Vec<VmInfo>, Vec<u32>)> {
-        let current_vms = scan_vms()?;
+        let vms = scan_vms()?;
 
-        let current_pids: HashSet<u32> = current_vms.iter().map(|v| v.qemu_pid).collect();
+        let current_pids: HashSet<u32> = vms.iter().map(|v| v.qemu_pid).collect();
         let old_pids: HashSet<u32> = self.vms.iter().map(|v| v.qemu_pid).collect();
 
         // Find new VMs
-        let new_vms: Vec<VmInfo> = current_vms
-            .into_iter()
+        let new_vms: Vec<VmInfo> = vms
+            .iter()
             .filter(|v| !old_pids.contains(&v.qemu_pid))
+            .cloned()
             .collect();
 
+        for vm in &new_vms {
+            warn_split_lock(self.split_lock, vm);
+        }
+
         // Find removed VMs
         let removed_pids: Vec<u32> = old_pids.difference(&current_pids).copied().collect();
 
         // Update passthrough GPUs
         self.passthrough_gpus = get_passthrough_gpus(&self.iommu_groups);
+        self.headless_host = all_gpus_passed_through(&self.iommu_groups);
 
         // Update VM list
-        self.vms = scan_vms()?;
+        for vm in &vms {
+            let was_paused = self
+                .vms
+                .iter()
+                .find(|old| old.qemu_pid == vm.qemu_pid)
+                .map(|old| old.paused);
+            match (was_paused, vm.paused) {
+                (Some(false), true) => info!("VM {} paused: releasing its vCPUs", vm.name),
+                (Some(true), false) => info!("VM {} resumed", vm.name),
+                _ => {}
+            }
+        }
+        self.vms = vms;
 
         Ok((new_vms, removed_pids))
     }
 
+    /// Skip (true, default) or classify (false) vCPUs of VMs with explicit pinning
+    pub fn set_respect_pinning(&mut self, respect: bool) {
+        self.respect_pinning = respect;
+    }
+
     /// Get all vCPU PIDs with their workload type
+    ///
+    /// VMs with pinned vCPUs are left out unless pinning is overridden, so
+    /// the scheduler doesn't fight an admin's explicit placement. Paused VMs
+    /// are left out until they resume.
     pub fn get_vcpu_workloads(&self) -> HashMap<u32, VmWorkloadType> {
         let mut workloads = HashMap::new();
 
         for vm in &self.vms {
+            if vm.paused || (vm.vcpus_pinned && self.respect_pinning) {
+                continue;
+            }
             for &vcpu_pid in &vm.vcpu_pids {
                 workloads.insert(vcpu_pid, vm.workload_type);
             }
@@ -476,6 +889,26 @@ impl VmMonitor {
         workloads
     }
 
+    /// IO thread PIDs of all VMs
+    ///
+    /// Unlike vCPUs these are pushed even for pinned VMs: an interactive
+    /// class only changes priority, and placement still honours affinity.
+    pub fn iothread_pids(&self) -> HashSet<u32> {
+        self.vms
+            .iter()
+            .flat_map(|v| v.iothread_pids.iter().copied())
+            .collect()
+    }
+
+    /// QEMU PIDs of VMs with an active Looking Glass session
+    pub fn boosted_vm_pids(&self) -> HashSet<u32> {
+        self.vms
+            .iter()
+            .filter(|v| v.wants_priority_boost())
+            .map(|v| v.qemu_pid)
+            .collect()
+    }
+
     /// Get gaming VM vCPU count
     pub fn gaming_vcpu_count(&self) -> usize {
         self.vms
@@ -505,6 +938,11 @@ impl VmMonitor {
         self.passthrough_gpus.len()
     }
 
+    /// Whether every host GPU is passed through to VMs
+    pub fn all_gpus_passed_through(&self) -> bool {
+        self.headless_host
+    }
+
     /// Get IOMMU summary
     pub fn iommu_summary(&self) -> String {
         let gpu_groups: Vec<_> = self.iommu_groups.iter().filter(|g| g.has_gpu).collect();
@@ -517,6 +955,11 @@ impl VmMonitor {
         format!("{} GPU groups ({} isolated)", gpu_groups.len(), isolated)
     }
 
+    /// Host split-lock detection mode
+    pub fn split_lock_mode(&self) -> SplitLockMode {
+        self.split_lock
+    }
+
     /// Check if IOMMU is available
     pub fn has_iommu(&self) -> bool {
         !self.iommu_groups.is_empty()
@@ -529,6 +972,9 @@ impl Default for VmMonitor {
             vms: Vec::new(),
             iommu_groups: Vec::new(),
             passthrough_gpus: Vec::new(),
+            headless_host: false,
+            split_lock: read_split_lock_mode(),
+            respect_pinning: true,
         })
     }
 }
@@ -542,9 +988,249 @@ mod tests {
         let _groups = detect_iommu_groups();
     }
 
+    #[test]
+    fn test_parse_ivshmem_plain() {
+        let (shm, size, memdev) =
+            parse_ivshmem_device("ivshmem-plain,id=shmem0,shm=looking-glass,size=64M").unwrap();
+        assert_eq!(shm.as_deref(), Some("looking-glass"));
+        assert_eq!(size, Some(64 * 1024 * 1024));
+        assert_eq!(memdev, None);
+        assert!(parse_ivshmem_device("vfio-pci,host=01:00.0").is_none());
+
+        // libvirt style: size and path live on the memory backend
+        let args = [
+            "qemu-system-x86_64",
+            "-object",
+            "memory-backend-file,id=ivshmem_lg,share=on,mem-path=/dev/shm/looking-glass,size=128M",
+            "-device",
+            "ivshmem-plain,memdev=ivshmem_lg,bus=pcie.0",
+        ];
+        let info = detect_ivshmem(&args).unwrap();
+        assert_eq!(info.shm_name, "/dev/shm/looking-glass");
+        assert_eq!(info.size_bytes, 128 * 1024 * 1024);
+        assert!(info.is_strong_gaming_signal());
+
+        assert_eq!(parse_qemu_size("32M"), Some(32 * 1024 * 1024));
+        assert_eq!(parse_qemu_size("1G"), Some(1 << 30));
+        assert_eq!(parse_qemu_size("4096"), Some(4096));
+    }
+
+    #[test]
+    fn test_split_lock_mode_parse() {
+        assert_eq!(SplitLockMode::parse("off\n"), SplitLockMode::Off);
+        assert_eq!(SplitLockMode::parse("warn"), SplitLockMode::Warn);
+        assert_eq!(SplitLockMode::parse("fatal"), SplitLockMode::Fatal);
+        assert_eq!(
+            SplitLockMode::parse("ratelimit:10"),
+            SplitLockMode::Ratelimit(10)
+        );
+        assert_eq!(SplitLockMode::parse("ratelimit:x"), SplitLockMode::Unknown);
+        assert_eq!(SplitLockMode::parse(""), SplitLockMode::Unknown);
+
+        assert!(SplitLockMode::Warn.penalizes_guests());
+        assert!(SplitLockMode::Ratelimit(1).penalizes_guests());
+        assert!(!SplitLockMode::Off.penalizes_guests());
+        assert!(!SplitLockMode::Unknown.penalizes_guests());
+        assert_eq!(SplitLockMode::Ratelimit(10).to_string(), "ratelimit:10");
+    }
+
+    #[test]
+    fn test_nested_virt_detection() {
+        // kvm_amd takes an int, kvm_intel a bool
+        assert!(parse_nested_param("1\n"));
+        assert!(parse_nested_param("Y\n"));
+        assert!(!parse_nested_param("0\n"));
+        assert!(!parse_nested_param("N\n"));
+        assert!(!parse_nested_param(""));
+
+        let l1 = [
+            "qemu-system-x86_64",
+            "-cpu",
+            "EPYC-v4,+svm,+npt",
+            "-m",
+            "16G",
+        ];
+        assert!(exposes_virt_extensions(&l1));
+        let intel = ["qemu-system-x86_64", "-cpu", "host,vmx=on"];
+        assert!(exposes_virt_extensions(&intel));
+        // Host passthrough alone is what gaming VMs use; not a nested signal
+        let gaming = ["qemu-system-x86_64", "-cpu", "host,topoext=on,-svm"];
+        assert!(!exposes_virt_extensions(&gaming));
+        assert!(!exposes_virt_extensions(&["qemu-system-x86_64", "-cpu"]));
+    }
+
+    #[test]
+    fn test_pinned_vcpus_respected() {
+        let vm = |qemu_pid: u32, vcpu_pids: Vec<u32>, vcpus_pinned: bool| VmInfo {
+            qemu_pid,
+            name: format!("vm{}", qemu_pid),
+            workload_type: VmWorkloadType::Gaming,
+            vcpu_pids,
+            iothread_pids: Vec::new(),
+            has_gpu_passthrough: true,
+            passthrough_gpus: Vec::new(),
+            vcpus_pinned,
+            ivshmem: None,
+            paused: false,
+            nested: false,
+        };
+        let mut monitor = VmMonitor {
+            vms: vec![vm(100, vec![101, 102], true), vm(200, vec![201], false)],
+            iommu_groups: Vec::new(),
+            passthrough_gpus: Vec::new(),
+            headless_host: false,
+            split_lock: SplitLockMode::Unknown,
+            respect_pinning: true,
+        };
+
+        let workloads = monitor.get_vcpu_workloads();
+        assert_eq!(workloads.len(), 1);
+        assert!(workloads.contains_key(&201));
+        assert!(!workloads.contains_key(&101));
+
+        // --override-pinned
+        monitor.set_respect_pinning(false);
+        assert_eq!(monitor.get_vcpu_workloads().len(), 3);
+    }
+
+    #[test]
+    fn test_paused_vm_excluded() {
+        let vm = |qemu_pid: u32, stat: &str| VmInfo {
+            qemu_pid,
+            name: format!("vm{}", qemu_pid),
+            workload_type: VmWorkloadType::Gaming,
+            vcpu_pids: vec![qemu_pid + 1],
+            iothread_pids: Vec::new(),
+            has_gpu_passthrough: true,
+            passthrough_gpus: Vec::new(),
+            vcpus_pinned: false,
+            ivshmem: None,
+            paused: stat_stopped(stat),
+            nested: false,
+        };
+        let monitor = VmMonitor {
+            vms: vec![
+                vm(100, "100 (qemu-system-x86) T 1 100 100 0 -1"),
+                vm(200, "200 (qemu-system-x86) S 1 200 200 0 -1"),
+            ],
+            iommu_groups: Vec::new(),
+            passthrough_gpus: Vec::new(),
+            headless_host: false,
+            split_lock: SplitLockMode::Unknown,
+            respect_pinning: true,
+        };
+
+        let workloads = monitor.get_vcpu_workloads();
+        assert!(!workloads.contains_key(&101));
+        assert!(workloads.contains_key(&201));
+
+        // Odd comms don't confuse the state field
+        assert!(stat_stopped("300 (qemu) x) t 1"));
+        assert_eq!(
+            libvirt_domain_state("<domstatus state='paused' reason='user' pid='100'>"),
+            Some("paused")
+        );
+        assert_eq!(
+            libvirt_domain_state("<domstatus state=\"running\" pid='100'>"),
+            Some("running")
+        );
+    }
+
+    #[test]
+    fn test_qemu_thread_comm() {
+        assert_eq!(classify_qemu_thread("CPU 0/KVM\n"), Some(QemuThread::Vcpu));
+        assert_eq!(classify_qemu_thread("CPU 15/KVM"), Some(QemuThread::Vcpu));
+        assert_eq!(
+            classify_qemu_thread("IO iothread1\n"),
+            Some(QemuThread::IoThread)
+        );
+        assert_eq!(
+            classify_qemu_thread("IO mon_iothread"),
+            Some(QemuThread::IoThread)
+        );
+        assert_eq!(classify_qemu_thread("qemu-system-x86"), None);
+        assert_eq!(classify_qemu_thread("worker"), None);
+    }
+
+    #[test]
+    fn test_vcpu_pinning_mask() {
+        let online: Vec<u32> = (0..32).collect();
+
+        // Full masks, including wider-than-needed kernel masks with zero groups
+        assert!(!is_pinned_mask("ffffffff", &online));
+        assert!(!is_pinned_mask("00000000,ffffffff", &online));
+        assert!(!is_pinned_mask("ff,ffffffff", &online));
+
+        // 4-CPU subset
+        assert!(is_pinned_mask("0000000f", &online));
+        assert!(is_pinned_mask("00000000,000f0000", &online));
+
+        // Unparseable masks are never reported as pinned
+        assert!(!is_pinned_mask("zz", &online));
+        assert_eq!(parse_cpu_mask("1,00000001").unwrap().len(), 2);
+    }
+
     #[test]
     fn test_scan_vms() {
         let result = scan_vms();
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_rescan_skips_unreadable_vm() {
+        // Two QEMU processes; 200's cmdline can't be read
+        let fixture = procfs::Fixture::new("vm-rescan");
+        fixture
+            .file("/proc/100/comm", "qemu-system-x86\n")
+            .file(
+                "/proc/100/cmdline",
+                "qemu-system-x86_64\0-name\0guest=win11\0",
+            )
+            .file("/proc/100/task/101/comm", "CPU 0/KVM\n")
+            .file("/proc/200/comm", "qemu-system-x86\n")
+            .file("/proc/300/comm", "bash\n");
+
+        let mut monitor = VmMonitor {
+            vms: Vec::new(),
+            iommu_groups: Vec::new(),
+            passthrough_gpus: Vec::new(),
+            headless_host: false,
+            split_lock: SplitLockMode::Unknown,
+            respect_pinning: true,
+        };
+        let (new_vms, removed) = fixture.run(|| monitor.rescan()).unwrap();
+        assert_eq!(new_vms.len(), 1);
+        assert_eq!(new_vms[0].qemu_pid, 100);
+        assert_eq!(new_vms[0].name, "win11");
+        assert!(removed.is_empty());
+        assert_eq!(monitor.vm_count(), 1);
+        assert!(monitor.get_vcpu_workloads().contains_key(&101));
+    }
+
+    #[test]
+    fn test_host_state_from_fixture() {
+        let fixture = procfs::Fixture::new("vm");
+        fixture
+            .file("/sys/devices/system/cpu/split_lock_detect", "warn\n")
+            .file("/sys/module/kvm_amd/parameters/nested", "1\n")
+            .file("/sys/kernel/debug/kvm/4242-12/nested_run", "7\n")
+            .file("/sys/kernel/debug/kvm/4243-13/nested_run", "0\n")
+            .symlink(
+                "/sys/bus/pci/devices/0000:01:00.0/driver",
+                "../../../bus/pci/drivers/vfio-pci",
+            )
+            .symlink(
+                "/sys/bus/pci/devices/0000:02:00.0/driver",
+                "../../../bus/pci/drivers/nvidia",
+            );
+
+        fixture.run(|| {
+            assert_eq!(read_split_lock_mode(), SplitLockMode::Warn);
+            assert!(nested_virt_enabled());
+            assert!(runs_nested_guest(4242));
+            assert!(!runs_nested_guest(4243));
+            assert!(is_vfio_bound("0000:01:00.0"));
+            assert!(!is_vfio_bound("0000:02:00.0"));
+        });
+    }
 }
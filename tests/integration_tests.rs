@@ -136,6 +136,96 @@ fn test_completions_generation() {
     }
 }
 
+/// Test --stats-once: attach, print one snapshot, detach and exit 0
+///
+/// Requires root and an idle sched-ext slot; skipped otherwise.
+#[test]
+fn test_stats_once_snapshot() {
+    use std::process::Command;
+
+    let binary = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("target/debug/scx_ghostbrew");
+
+    if !binary.exists() {
+        eprintln!("Skipping: binary not built");
+        return;
+    }
+    if !nix::unistd::Uid::effective().is_root() {
+        eprintln!("Skipping: --stats-once requires root");
+        return;
+    }
+    let state = std::fs::read_to_string("/sys/kernel/sched_ext/state").unwrap_or_default();
+    if state.trim() != "disabled" {
+        eprintln!("Skipping: sched-ext unavailable or another scheduler is attached");
+        return;
+    }
+
+    let output = Command::new(&binary)
+        .args(["--stats-once", "--stats-interval", "1", "--stats-json"])
+        .output()
+        .expect("Failed to run binary");
+
+    assert!(output.status.success(), "--stats-once should exit 0");
+
+    // Exactly one JSON snapshot on stdout
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().filter(|l| l.starts_with('{')).collect();
+    assert_eq!(lines.len(), 1, "Expected a single stats snapshot");
+    let snapshot: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+    assert!(snapshot.get("enqueued").is_some());
+
+    // Scheduler must be detached again
+    let state = std::fs::read_to_string("/sys/kernel/sched_ext/state").unwrap_or_default();
+    assert_eq!(state.trim(), "disabled", "scheduler should detach on exit");
+}
+
+/// Test --export-topology: maps are pinned while running and removed on exit
+///
+/// Requires root and an idle sched-ext slot; skipped otherwise.
+#[test]
+fn test_export_topology_pins() {
+    use std::process::Command;
+    use std::time::{Duration, Instant};
+
+    let binary = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("target/debug/scx_ghostbrew");
+
+    if !binary.exists() {
+        eprintln!("Skipping: binary not built");
+        return;
+    }
+    if !nix::unistd::Uid::effective().is_root() {
+        eprintln!("Skipping: --export-topology requires root");
+        return;
+    }
+    let state = std::fs::read_to_string("/sys/kernel/sched_ext/state").unwrap_or_default();
+    if state.trim() != "disabled" {
+        eprintln!("Skipping: sched-ext unavailable or another scheduler is attached");
+        return;
+    }
+
+    let pin_dir = std::path::Path::new("/sys/fs/bpf/ghostbrew");
+    let mut child = Command::new(&binary)
+        .arg("--export-topology")
+        .spawn()
+        .expect("Failed to run binary");
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while !pin_dir.join("cpu_ctxs").exists() && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    let pinned = pin_dir.join("cpu_ctxs").exists() && pin_dir.join("topology_summary").exists();
+
+    nix::sys::signal::kill(
+        nix::unistd::Pid::from_raw(child.id() as i32),
+        nix::sys::signal::Signal::SIGINT,
+    )
+    .expect("Failed to signal scheduler");
+    let status = child.wait().expect("Failed to wait for scheduler");
+
+    assert!(pinned, "topology maps should be pinned under {:?}", pin_dir);
+    assert!(status.success(), "scheduler should exit cleanly on SIGINT");
+    assert!(!pin_dir.exists(), "pins should be removed on shutdown");
+}
+
 /// Test sched-ext kernel support detection
 #[test]
 fn test_schedext_detection() {